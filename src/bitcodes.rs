@@ -2,36 +2,452 @@
 //!
 //! See chapter 2 of the ODS for details on the structure of the datatypes that can be read
 //!
-//! This module currently is fairly unoptimized; however, given the bitwise nature of DWGs,
-//! the API should stay the same and can't really be made any faster
-use std::mem::size_of;
+//! Given the bitwise nature of DWGs most reads can't be made much faster, however multi-byte
+//! reads from memory backed sources (see [`BitSliceReader`]) are done a word at a time
+use std::{
+    io::{Read, Seek, SeekFrom},
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
 
-use crate::version::DWGVersion;
+use arrayvec::ArrayVec;
 
-/// A structure that wraps a `Iterator<&u8>` that enables reading DWG datatypes from a byte stream
+use crate::{
+    crc::crc8,
+    error::{DwgError, Result},
+    types::{
+        CmColor, CodePage, Color, EntityColor, HandleRef, JulianDate, Point2, Point3, RawBits,
+        RefType, Transparency,
+    },
+    version::{DWGVersion, LegacyVersion},
+};
+
+/// A source of bytes for a `BitReader`
+///
+/// This is implemented for every `Iterator<Item = &u8>`, which can only be read forwards, for
+/// [`SliceSource`] and for [`IoSource`], which can also seek
+pub trait ByteSource {
+    /// Returns the next byte, or None at the end of the stream
+    fn next_byte(&mut self) -> Result<Option<u8>>;
+
+    /// Moves the source so that the next byte returned is the byte at `pos`
+    ///
+    /// Fails if `pos` is past the end of the source or the source cannot seek
+    fn seek_byte(&mut self, _pos: u64) -> Result<()> {
+        Err(DwgError::SeekUnsupported)
+    }
+
+    /// Returns the unread bytes if the source is backed by memory
+    ///
+    /// This lets `BitReader` read whole words at a time instead of a byte at a time
+    fn remaining(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Skips `n` bytes, only called after `remaining` returned at least `n` bytes
+    fn advance(&mut self, _n: usize) {}
+}
+
+impl<'a, I: Iterator<Item = &'a u8>> ByteSource for I {
+    fn next_byte(&mut self) -> Result<Option<u8>> {
+        Ok(self.next().copied())
+    }
+}
+
+/// A seekable `ByteSource` over a byte slice
+pub struct SliceSource<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> SliceSource<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+}
+
+impl ByteSource for SliceSource<'_> {
+    fn next_byte(&mut self) -> Result<Option<u8>> {
+        let byte = self.data.get(self.pos).copied();
+        if byte.is_some() {
+            self.pos += 1;
+        }
+        Ok(byte)
+    }
+
+    fn seek_byte(&mut self, pos: u64) -> Result<()> {
+        if pos > self.data.len() as u64 {
+            return Err(DwgError::SeekOutOfRange { offset: pos * 8 });
+        }
+        self.pos = pos as usize;
+        Ok(())
+    }
+
+    fn remaining(&self) -> Option<&[u8]> {
+        Some(&self.data[self.pos..])
+    }
+
+    fn advance(&mut self, n: usize) {
+        self.pos += n;
+    }
+}
+
+/// A seekable `ByteSource` over any `Read + Seek`
+///
+/// Reads from the underlying reader are buffered, so readers such as `File` can be used
+/// directly. Buffered bytes are exposed through `remaining`, letting `BitReader` read whole
+/// words from the buffer as it does for slices
+pub struct IoSource<R: Read + Seek> {
+    inner: R,
+    buf: Box<[u8]>,
+    /// Position of the next byte in `buf`
+    pos: usize,
+    /// Number of valid bytes in `buf`
+    len: usize,
+    /// Offset in `inner` of the start of `buf`
+    buf_start: u64,
+}
+
+impl<R: Read + Seek> IoSource<R> {
+    /// Default size of the read buffer in bytes
+    pub const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+    pub fn new(inner: R) -> Self {
+        Self::with_capacity(Self::DEFAULT_CAPACITY, inner)
+    }
+
+    /// Creates a source that reads `capacity` bytes at a time from `inner`
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        Self {
+            inner,
+            buf: vec![0; capacity.max(1)].into_boxed_slice(),
+            pos: 0,
+            len: 0,
+            buf_start: 0,
+        }
+    }
+
+    /// Returns the underlying reader, its position may be past the last byte read because of
+    /// buffering
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read + Seek> ByteSource for IoSource<R> {
+    fn next_byte(&mut self) -> Result<Option<u8>> {
+        if self.pos == self.len {
+            self.buf_start += self.len as u64;
+            self.pos = 0;
+            self.len = 0;
+            self.len = loop {
+                match self.inner.read(&mut self.buf) {
+                    Ok(len) => break len,
+                    Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(err) => return Err(err.into()),
+                }
+            };
+            if self.len == 0 {
+                return Ok(None);
+            }
+        }
+        self.pos += 1;
+        Ok(Some(self.buf[self.pos - 1]))
+    }
+
+    fn seek_byte(&mut self, pos: u64) -> Result<()> {
+        // Seeks within the buffer do not need to touch the reader
+        if pos >= self.buf_start && pos <= self.buf_start + self.len as u64 {
+            self.pos = (pos - self.buf_start) as usize;
+            return Ok(());
+        }
+        self.inner.seek(SeekFrom::Start(pos))?;
+        self.buf_start = pos;
+        self.pos = 0;
+        self.len = 0;
+        Ok(())
+    }
+
+    fn remaining(&self) -> Option<&[u8]> {
+        Some(&self.buf[self.pos..self.len])
+    }
+
+    fn advance(&mut self, n: usize) {
+        self.pos += n;
+    }
+}
+
+/// A structure that wraps a `ByteSource` that enables reading DWG datatypes from a byte stream
 ///
 /// This struct does not allow for modification or writing of the DWG and instead will be
 /// performed by a future struct instead
 ///
-/// This struct does no buffering and this functionality needs to be implemented from the iterator
-pub struct BitReader<'a, I: Iterator<Item = &'a u8>> {
+/// Any buffering is done by the source, see [`IoSource`]
+pub struct BitReader<S: ByteSource> {
     cur_byte: u8,
     cur_bit: u32,
-    iter: I,
+    /// Number of bytes taken from the source, including `cur_byte`
+    byte_pos: u64,
+    /// Bytes taken from the source by a peek that have not been read yet
+    lookahead: ArrayVec<u8, 8>,
+    /// Running CRC8 of the bytes read since `start_crc`
+    crc: Option<u16>,
+    /// Bit position reads may not go past, set by `take_bits`
+    end_bit: Option<u64>,
+    src: S,
     version: DWGVersion,
+    codepage: CodePage,
+}
+
+/// A zero-copy `BitReader` over a byte slice
+///
+/// Multi-byte values are read a word at a time rather than a byte at a time
+pub type BitSliceReader<'a> = BitReader<SliceSource<'a>>;
+
+impl<'a> BitSliceReader<'a> {
+    /// Creates a new seekable `BitReader` over a byte slice
+    pub fn from_slice(data: &'a [u8]) -> Self {
+        Self::new(SliceSource::new(data))
+    }
+
+    /// Returns the whole slice being read, regardless of the current position
+    pub fn data(&self) -> &'a [u8] {
+        self.src.data
+    }
+
+    /// Creates a new reader over `len` bytes of this reader's slice starting at byte `start`
+    ///
+    /// The new reader shares the version and codepage of this reader, but has its own
+    /// position starting at 0
+    pub fn sub_reader(&self, start: usize, len: usize) -> Result<BitSliceReader<'a>> {
+        let data = self.src.data;
+        let sub = start
+            .checked_add(len)
+            .and_then(|end| data.get(start..end))
+            .ok_or(DwgError::SeekOutOfRange {
+                offset: (start as u64 + len as u64) * 8,
+            })?;
+        let mut reader = BitReader::from_slice(sub);
+        reader.version = self.version;
+        reader.codepage = self.codepage;
+        Ok(reader)
+    }
+}
+
+/// A position in a `BitReader` from [`BitReader::mark`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mark {
+    bit: u64,
+    crc: Option<u16>,
+}
+
+/// A `BitReader` limited to a number of bits, see [`BitReader::take_bits`]
+pub struct BoundedReader<'r, S: ByteSource> {
+    reader: &'r mut BitReader<S>,
+    prev_end: Option<u64>,
+}
+
+impl<S: ByteSource> BoundedReader<'_, S> {
+    /// Returns the number of bits left before the bound
+    pub fn remaining_bits(&self) -> u64 {
+        let end_bit = self.reader.end_bit.unwrap_or(u64::MAX);
+        end_bit.saturating_sub(self.reader.bit_position())
+    }
+}
+
+impl<S: ByteSource> Deref for BoundedReader<'_, S> {
+    type Target = BitReader<S>;
+
+    fn deref(&self) -> &Self::Target {
+        self.reader
+    }
+}
+
+impl<S: ByteSource> DerefMut for BoundedReader<'_, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.reader
+    }
 }
 
-impl<'a, I: Iterator<Item = &'a u8>> BitReader<'a, I> {
-    /// Creates a new `BitReader` by wrapping an `Iterator<&u8>`
+impl<S: ByteSource> Drop for BoundedReader<'_, S> {
+    fn drop(&mut self) {
+        self.reader.end_bit = self.prev_end;
+    }
+}
+
+impl<R: Read + Seek> BitReader<IoSource<R>> {
+    /// Creates a new seekable `BitReader` over a `Read + Seek` source, so a drawing does not
+    /// need to be loaded into memory to be read
+    pub fn from_reader(reader: R) -> Self {
+        Self::new(IoSource::new(reader))
+    }
+
+    /// Creates a new `BitReader` over a `Read + Seek` source that reads `capacity` bytes at a
+    /// time
+    pub fn from_reader_with_capacity(capacity: usize, reader: R) -> Self {
+        Self::new(IoSource::with_capacity(capacity, reader))
+    }
+}
+
+impl<S: ByteSource> BitReader<S> {
+    /// Creates a new `BitReader` by wrapping a `ByteSource` such as an `Iterator<&u8>`
     ///
-    /// Assumes a Version of AC1015 (R2000) initially  
-    pub fn new(iter: I) -> Self {
+    /// Assumes a Version of AC1015 (R2000) and a codepage of ANSI 1252 initially
+    pub fn new(src: S) -> Self {
         Self {
-            iter,
+            src,
             cur_byte: 0,
             cur_bit: 8,
+            byte_pos: 0,
+            lookahead: ArrayVec::new(),
+            crc: None,
+            end_bit: None,
             version: DWGVersion::AC1015,
+            codepage: CodePage::ANSI1252,
+        }
+    }
+
+    /// Returns the number of bits read so far, relative to the start of the source
+    pub fn bit_position(&self) -> u64 {
+        self.byte_pos * 8 - (8 - self.cur_bit as u64)
+    }
+
+    /// Returns the byte containing the next bit to be read, relative to the start of the
+    /// source
+    pub fn byte_position(&self) -> u64 {
+        self.bit_position() / 8
+    }
+
+    /// Moves the reader so that the next bit read is the bit at `offset`, relative to the
+    /// start of the source
+    ///
+    /// Fails if the source cannot seek or `offset` is past the end of the source
+    pub fn seek_to_bit(&mut self, offset: u64) -> Result<()> {
+        self.src.seek_byte(offset / 8)?;
+        self.lookahead.clear();
+        self.byte_pos = offset / 8;
+        self.cur_bit = 8;
+        let bit = (offset % 8) as u32;
+        if bit != 0 {
+            self.cur_byte = self.next_byte().map_err(|err| match err {
+                DwgError::UnexpectedEof { .. } => DwgError::SeekOutOfRange { offset },
+                err => err,
+            })?;
+            self.cur_bit = bit;
         }
+        Ok(())
+    }
+
+    /// Returns the current position so that it can be returned to with `rewind`
+    pub fn mark(&self) -> Mark {
+        Mark {
+            bit: self.bit_position(),
+            crc: self.crc,
+        }
+    }
+
+    /// Returns to a position from `mark`, so a different layout can be tried after a failed
+    /// speculative parse
+    ///
+    /// The running CRC is restored along with the position. Fails with `SeekUnsupported` if
+    /// the source cannot seek
+    pub fn rewind(&mut self, mark: Mark) -> Result<()> {
+        self.crc = None;
+        self.seek_to_bit(mark.bit)?;
+        self.crc = mark.crc;
+        Ok(())
+    }
+
+    /// Returns a reader bounded to the next `n` bits of this reader
+    ///
+    /// Reads through the bounded reader that would go past those bits fail with
+    /// `BoundExceeded` instead of reading into whatever follows, such as the next object. The
+    /// bound is lifted when the bounded reader is dropped, leaving this reader wherever the
+    /// bounded reader stopped
+    pub fn take_bits(&mut self, n: u64) -> BoundedReader<'_, S> {
+        let prev_end = self.end_bit;
+        let end = self.bit_position().saturating_add(n);
+        self.end_bit = Some(prev_end.map_or(end, |prev| prev.min(end)));
+        BoundedReader {
+            reader: self,
+            prev_end,
+        }
+    }
+
+    /// Fails with `BoundExceeded` if reading `n` more bits would pass the bound set by
+    /// `take_bits`
+    fn check_bound(&self, n: u64) -> Result<()> {
+        match self.end_bit {
+            Some(end_bit) if self.bit_position() + n > end_bit => Err(DwgError::BoundExceeded {
+                at_bit: self.bit_position(),
+                end_bit,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Takes the next byte from the lookahead or the source
+    fn next_byte(&mut self) -> Result<u8> {
+        let byte = if self.lookahead.is_empty() {
+            self.src.next_byte()?
+        } else {
+            Some(self.lookahead.remove(0))
+        };
+        let byte = byte.ok_or(DwgError::UnexpectedEof {
+            at_bit: self.byte_pos * 8,
+        })?;
+        self.byte_pos += 1;
+        if let Some(crc) = self.crc.as_mut() {
+            *crc = crc8(*crc, &[byte]);
+        }
+        Ok(byte)
+    }
+
+    /// Starts accumulating a CRC8 with the initial value `seed` over every byte read from now
+    /// on, see [`crate::crc::crc8`]
+    ///
+    /// If the reader is part way through a byte, that byte is included
+    pub fn start_crc(&mut self, seed: u16) {
+        self.crc = Some(if self.cur_bit == 8 {
+            seed
+        } else {
+            crc8(seed, &[self.cur_byte])
+        });
+    }
+
+    /// Stops accumulating and returns the CRC8 of every byte read since `start_crc`,
+    /// including a partially read final byte
+    ///
+    /// Returns None if `start_crc` was not called
+    pub fn finish_crc(&mut self) -> Option<u16> {
+        self.crc.take()
+    }
+
+    /// Returns the next N bits as [`BitReader::read_bits`] would without consuming them
+    pub fn peek_bits<const N: u32>(&mut self) -> Result<u32> {
+        // Make sure every byte the read needs is buffered so it can be read again
+        let needed = N.saturating_sub(8 - self.cur_bit).div_ceil(8) as usize;
+        while self.lookahead.len() < needed {
+            match self.src.next_byte()? {
+                Some(byte) => self.lookahead.push(byte),
+                None => break,
+            }
+        }
+        let (cur_byte, cur_bit, byte_pos) = (self.cur_byte, self.cur_bit, self.byte_pos);
+        let (lookahead, crc) = (self.lookahead.clone(), self.crc);
+        let res = self.read_bits::<N>();
+        self.cur_byte = cur_byte;
+        self.cur_bit = cur_bit;
+        self.byte_pos = byte_pos;
+        self.lookahead = lookahead;
+        // The peeked bytes are added to the CRC when they are read
+        self.crc = crc;
+        res
+    }
+
+    /// Returns the next bit without consuming it
+    pub fn peek_bit(&mut self) -> Result<u8> {
+        self.peek_bits::<1>().map(|x| x as u8)
     }
 
     pub fn get_version(&self) -> DWGVersion {
@@ -42,59 +458,210 @@ impl<'a, I: Iterator<Item = &'a u8>> BitReader<'a, I> {
         self.version = version
     }
 
+    pub fn get_codepage(&self) -> CodePage {
+        self.codepage
+    }
+
+    /// Sets the codepage used to decode text, this should be set from the DWGCODEPAGE header
+    /// variable
+    pub fn set_codepage(&mut self, codepage: CodePage) {
+        self.codepage = codepage
+    }
+
     /// Read 6 byte magic number and return the DWG version
     ///
-    /// This will not update the version of the reader automatically
-    pub fn read_version(&mut self) -> Option<DWGVersion> {
+    /// This will not update the version of the reader automatically. Fails with
+    /// `UnsupportedVersion` naming the magic number, and the release for pre-R13 files
+    pub fn read_version(&mut self) -> Result<DWGVersion> {
         let mut bytes = [0u8; 6];
-        for byte in bytes.iter_mut() {
+        self.read_bytes(&mut bytes)?;
+        DWGVersion::from_magic(&bytes).ok_or_else(|| {
+            let magic = String::from_utf8_lossy(&bytes);
+            DwgError::UnsupportedVersion {
+                found: match LegacyVersion::from_magic(&bytes) {
+                    Some(legacy) => format!("{magic} ({})", legacy.release()),
+                    None => magic.into_owned(),
+                },
+            }
+        })
+    }
+
+    /// Fills `buf` with the next bytes of the stream
+    ///
+    /// Byte aligned reads from memory backed sources are a single copy
+    pub fn read_bytes(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.check_bound(buf.len() as u64 * 8)?;
+        if self.cur_bit == 8 && self.lookahead.is_empty() {
+            if let Some(rest) = self.src.remaining() {
+                if rest.len() >= buf.len() {
+                    buf.copy_from_slice(&rest[..buf.len()]);
+                    if let Some(crc) = self.crc.as_mut() {
+                        *crc = crc8(*crc, buf);
+                    }
+                    self.src.advance(buf.len());
+                    self.byte_pos += buf.len() as u64;
+                    return Ok(());
+                }
+            }
+        }
+        for byte in buf.iter_mut() {
             *byte = self.read_bits::<8>()? as u8;
         }
-        DWGVersion::from_magic(&bytes)
+        Ok(())
+    }
+
+    /// Reads the next `len` bytes of the stream into a new `Vec`
+    ///
+    /// Memory is allocated as bytes are read, so a corrupt length fails with `UnexpectedEof`
+    /// rather than attempting a huge allocation
+    pub fn read_vec(&mut self, len: usize) -> Result<Vec<u8>> {
+        const CHUNK_SIZE: usize = 1 << 16;
+        let mut bytes = Vec::new();
+        while bytes.len() < len {
+            let start = bytes.len();
+            let chunk = (len - start).min(CHUNK_SIZE);
+            bytes.resize(start + chunk, 0);
+            self.read_bytes(&mut bytes[start..])?;
+        }
+        Ok(bytes)
+    }
+
+    /// Reads the next `n` bits verbatim, so they can be kept and written back unchanged
+    ///
+    /// Like `read_vec`, memory is allocated as bits are read
+    pub fn read_raw_bits(&mut self, n: u64) -> Result<RawBits> {
+        self.check_bound(n)?;
+        let len = usize::try_from(n / 8).map_err(|_| DwgError::Overflow {
+            at_bit: self.bit_position(),
+        })?;
+        let mut bytes = self.read_vec(len)?;
+        let rem = (n % 8) as u32;
+        if rem != 0 {
+            bytes.push((self.read_bits_msb(rem)? << (8 - rem)) as u8);
+        }
+        Ok(RawBits::new(bytes, n))
+    }
+
+    /// Reads N bits to a u32 and returns the results
+    ///
+    /// Bits are read starting from the most significant bit of each byte. Reads of more than
+    /// 8 bits must be a whole number of bytes and are assembled in little endian order, as all
+    /// multi-byte raw values in a DWG are stored that way.
+    ///
+    /// This will fail with `UnexpectedEof` if there are less than N bits in the stream
+    fn read_bits<const N: u32>(&mut self) -> Result<u32> {
+        assert!(N > 0 && N <= u32::BITS);
+        assert!(N <= 8 || N.is_multiple_of(8));
+
+        self.read_bits_dyn(N).map(|x| x as u32)
+    }
+
+    /// Reads `n` bits, where `n` is only known at runtime, to a u64 and returns the result
+    ///
+    /// Reads of up to 8 bits are read most significant bit first. Longer reads are assembled
+    /// from whole bytes in little endian order, with any remaining `n % 8` bits read last
+    /// as the most significant bits, matching [`BitReader::read_bits`] for whole bytes.
+    ///
+    /// Fails with `Overflow` if `n` is greater than 64
+    pub fn read_bits_dyn(&mut self, n: u32) -> Result<u64> {
+        if n > u64::BITS {
+            return Err(DwgError::Overflow {
+                at_bit: self.bit_position(),
+            });
+        }
+        self.check_bound(n as u64)?;
+        if n <= 8 {
+            return self.read_bits_msb(n).map(u64::from);
+        }
+        let n_bytes = n / 8;
+        let mut res = 0u64;
+        let mut i = 0;
+        while i < n_bytes {
+            let chunk = (n_bytes - i).min(4);
+            let value = match self.read_le_fast(chunk) {
+                Some(value) => value,
+                None => {
+                    let mut value = 0u64;
+                    for j in 0..chunk {
+                        value |= (self.read_bits_msb(8)? as u64) << (8 * j);
+                    }
+                    value
+                }
+            };
+            res |= value << (8 * i);
+            i += chunk;
+        }
+        let rem = n % 8;
+        if rem != 0 {
+            res |= (self.read_bits_msb(rem)? as u64) << (8 * n_bytes);
+        }
+        Ok(res)
     }
 
-    /// Reads N bits to a usize and returns the results
+    /// Fast path to read `n_bytes` whole bytes in little endian order from memory backed
+    /// sources
     ///
-    /// This will return None if there are less than N bits in the stream
-    fn read_bits<const N: u32>(&mut self) -> Option<u32> {
-        if cfg!(target_endian = "big") {
-            panic!("read_bits not supported for big endian architectures")
+    /// Returns None if the source is not memory backed or is near its end, in which case the
+    /// byte at a time path must be used
+    fn read_le_fast(&mut self, n_bytes: u32) -> Option<u64> {
+        debug_assert!(n_bytes > 0 && n_bytes < 8);
+        if !self.lookahead.is_empty() {
+            return None;
         }
+        let rest = self.src.remaining()?;
+        if rest.len() < 8 {
+            return None;
+        }
+        let word = u64::from_be_bytes(rest[..8].try_into().unwrap());
+        let bits = 8 * n_bytes;
+        // Upcoming bits of the stream starting at the most significant bit
+        let stream = if self.cur_bit == 8 {
+            word
+        } else {
+            let unaligned = ((self.cur_byte as u64) << 56 | word >> 8) << self.cur_bit;
+            // The last byte read is only partially consumed and becomes the current byte
+            self.cur_byte = rest[n_bytes as usize - 1];
+            unaligned
+        };
+        if let Some(crc) = self.crc.as_mut() {
+            *crc = crc8(*crc, &rest[..n_bytes as usize]);
+        }
+        self.src.advance(n_bytes as usize);
+        self.byte_pos += n_bytes as u64;
+        // Bytes appear in stream order, so swap them to little endian
+        Some((stream >> (64 - bits)).swap_bytes() >> (64 - bits))
+    }
+
+    /// Reads up to 8 bits with the first bit read as the most significant bit of the result
+    fn read_bits_msb(&mut self, n: u32) -> Result<u32> {
         // kind of redundant since bytes are 8 bits by default in rust
         const BITS_PER_BYTE: u32 = 8;
-
-        assert!(size_of::<u32>() * BITS_PER_BYTE as usize >= N as usize);
-        assert!(N > 0);
+        debug_assert!(n <= BITS_PER_BYTE);
 
         let mut res: u32 = 0;
-        let mut n = N;
+        let mut n = n;
         while n > 0 {
-            let mut rem_bits = BITS_PER_BYTE - self.cur_bit;
-            if rem_bits == 0 {
-                if let Some(byte) = self.iter.next() {
-                    self.cur_byte = *byte;
-                    self.cur_bit = 0;
-                    rem_bits = BITS_PER_BYTE - self.cur_bit;
-                } else {
-                    return None;
-                }
+            if self.cur_bit == BITS_PER_BYTE {
+                self.cur_byte = self.next_byte()?;
+                self.cur_bit = 0;
             }
+            let rem_bits = BITS_PER_BYTE - self.cur_bit;
 
             let bits_read = if n > rem_bits { rem_bits } else { n };
             let mask = (1 << bits_read) - 1;
-            res |= (mask & (self.cur_byte >> self.cur_bit) as u32) << (N - n);
+            res = res << bits_read | (mask & (self.cur_byte >> (rem_bits - bits_read)) as u32);
             n -= bits_read;
             self.cur_bit += bits_read;
         }
 
-        Some(res)
+        Ok(res)
     }
 
-    pub fn read_bit(&mut self) -> Option<u8> {
+    pub fn read_bit(&mut self) -> Result<u8> {
         self.read_bits::<1>().map(|x| x as u8)
     }
 
-    pub fn read_bit_triplet(&mut self) -> Option<u8> {
+    pub fn read_bit_triplet(&mut self) -> Result<u8> {
         let mut byte = 0;
         for _ in 0..3 {
             let bit = self.read_bit()?;
@@ -103,184 +670,540 @@ impl<'a, I: Iterator<Item = &'a u8>> BitReader<'a, I> {
                 break;
             }
         }
-        Some(byte)
+        Ok(byte)
     }
 
-    pub fn read_bitshort(&mut self) -> Option<i16> {
+    pub fn read_bitshort(&mut self) -> Result<i16> {
         let flag = self.read_bits::<2>()?;
         match flag {
             0x0 => self.read_raw_short(),
             0x1 => self.read_bits::<8>().map(|x| x as i16),
-            0x2 => Some(0),
-            0x3 => Some(256),
-            _ => unreachable!(),
+            0x2 => Ok(0),
+            0x3 => Ok(256),
+            _ => unreachable!("flag is 2 bits"),
         }
     }
 
-    pub fn read_bitlong(&mut self) -> Option<i32> {
+    pub fn read_bitlong(&mut self) -> Result<i32> {
         let flag = self.read_bits::<2>()?;
         match flag {
             0x0 => self.read_raw_long(),
             0x1 => self.read_bits::<8>().map(|x| x as i32),
-            0x2 => Some(0),
-            0x3 => Some(256),
-            _ => unreachable!(),
+            0x2 => Ok(0),
+            0x3 => Ok(256),
+            _ => unreachable!("flag is 2 bits"),
         }
     }
 
-    pub fn read_bitlonglong(&mut self) -> Option<i64> {
-        let flag = self.read_bits::<2>()?;
-        match flag {
-            0x0 => {
-                let x1 = self.read_raw_long()? as u64;
-                let x2 = self.read_raw_long()? as u64;
-                Some((x2 << 32 | x1) as i64)
-            }
-            0x1 => self.read_bits::<8>().map(|x| x as i64),
-            0x2 => Some(0),
-            0x3 => Some(256),
-            _ => unreachable!(),
+    /// Reads a bitlonglong (BLL)
+    ///
+    /// A 3 bit count of bytes followed by that many bytes in little endian order
+    pub fn read_bitlonglong(&mut self) -> Result<i64> {
+        let len = self.read_bits::<3>()?;
+        let mut res = 0u64;
+        for i in 0..len {
+            res |= (self.read_bits::<8>()? as u64) << (8 * i);
         }
+        Ok(res as i64)
     }
 
-    pub fn read_bitdouble(&mut self) -> Option<f64> {
+    pub fn read_bitdouble(&mut self) -> Result<f64> {
         let flag = self.read_bits::<2>()?;
         match flag {
             0x0 => self.read_raw_double(),
-            0x1 => Some(1.0),
-            0x2 => Some(0.0),
-            _ => unreachable!(),
+            0x1 => Ok(1.0),
+            0x2 => Ok(0.0),
+            _ => Err(DwgError::InvalidFlag {
+                at_bit: self.bit_position() - 2,
+                value: flag as u64,
+            }),
         }
     }
 
-    pub fn read_modular_char(&mut self) -> Option<i32> {
-        let mut res = 0i32;
-        let mut i = 0;
-        loop {
+    /// Reads a signed modular char (MC)
+    ///
+    /// Each byte holds 7 bits of the value with the high bit set if another byte follows. In
+    /// the final byte, bit 0x40 is the sign bit and only 6 bits hold the value. Fails with
+    /// `Overflow` if the value does not fit in an i32, without reading more than the 5 bytes
+    /// an i32 can need
+    pub fn read_modular_char(&mut self) -> Result<i32> {
+        let at_bit = self.bit_position();
+        let res = self.read_signed_modular_char(i32::BITS)?;
+        i32::try_from(res).map_err(|_| DwgError::Overflow { at_bit })
+    }
+
+    /// Reads a signed modular char (MC) as an offset between handles or file positions
+    ///
+    /// The object map and second header store deltas that may be negative and wider than
+    /// 32 bits. Fails with `Overflow` if the value does not fit in an i64
+    pub fn read_handle_offset(&mut self) -> Result<i64> {
+        let at_bit = self.bit_position();
+        let res = self.read_signed_modular_char(i64::BITS)?;
+        i64::try_from(res).map_err(|_| DwgError::Overflow { at_bit })
+    }
+
+    /// Reads a signed MC of at most as many bytes as a value of `bits` bits can need
+    fn read_signed_modular_char(&mut self, bits: u32) -> Result<i128> {
+        let mut res = 0i128;
+        let at_bit = self.bit_position();
+        for i in 0..bits.div_ceil(7) {
             let byte = self.read_bits::<8>()? as u8;
-            res |= ((byte & !(1 << 7)) as i32) << (i * 7);
             if byte & (1 << 7) == 0 {
-                break;
+                res |= ((byte & 0x3F) as i128) << (i * 7);
+                if byte & (1 << 6) != 0 {
+                    res = -res;
+                }
+                return Ok(res);
             }
-            i += 1;
+            res |= ((byte & !(1 << 7)) as i128) << (i * 7);
         }
-        Some(res)
+        Err(DwgError::Overflow { at_bit })
     }
 
-    pub fn read_modular_short(&mut self) -> Option<i32> {
-        let mut res = 0i32;
-        let mut i = 0;
-        loop {
-            let byte = self.read_bits::<16>()? as u16;
-            res |= ((byte & !(1 << 15)) as i32) << (i * 15);
-            if byte & (1 << 15) == 0 {
-                break;
+    /// Reads an unsigned modular char (UMC)
+    ///
+    /// Identical to the MC encoding except that the final byte has no sign bit, used for
+    /// handles and sizes. Fails with `Overflow` if the value does not fit in a u32
+    pub fn read_unsigned_modular_char(&mut self) -> Result<u32> {
+        let at_bit = self.bit_position();
+        u32::try_from(self.read_modular_char_u64()?).map_err(|_| DwgError::Overflow { at_bit })
+    }
+
+    /// Reads an unsigned modular char (UMC) that may be wider than 32 bits
+    ///
+    /// Fails with `Overflow` if the value does not fit in a u64, without reading more than the
+    /// 10 bytes a u64 can need
+    pub fn read_modular_char_u64(&mut self) -> Result<u64> {
+        let at_bit = self.bit_position();
+        let mut res = 0u64;
+        for i in 0..u64::BITS.div_ceil(7) {
+            let byte = self.read_bits::<8>()? as u8;
+            let value = (byte & !(1 << 7)) as u64;
+            let shift = i * 7;
+            if (value << shift) >> shift != value {
+                return Err(DwgError::Overflow { at_bit });
+            }
+            res |= value << shift;
+            if byte & (1 << 7) == 0 {
+                return Ok(res);
+            }
+        }
+        Err(DwgError::Overflow { at_bit })
+    }
+
+    /// Reads a signed modular short (MS)
+    ///
+    /// Each little endian short holds 15 bits of the value with the high bit set if another
+    /// short follows. In the final short, bit 0x4000 is the sign bit and only 14 bits hold the
+    /// value, as in MC. Fails with `Overflow` if the value does not fit in an i32
+    pub fn read_modular_short(&mut self) -> Result<i32> {
+        let at_bit = self.bit_position();
+        let mut res = 0i64;
+        for i in 0..i32::BITS.div_ceil(15) {
+            let word = self.read_bits::<16>()? as u16;
+            if word & (1 << 15) == 0 {
+                res |= ((word & 0x3FFF) as i64) << (i * 15);
+                if word & (1 << 14) != 0 {
+                    res = -res;
+                }
+                return i32::try_from(res).map_err(|_| DwgError::Overflow { at_bit });
+            }
+            res |= ((word & !(1 << 15)) as i64) << (i * 15);
+        }
+        Err(DwgError::Overflow { at_bit })
+    }
+
+    /// Reads an unsigned modular short (MS), such as the size of an object, that may be wider
+    /// than 32 bits
+    ///
+    /// The final short has no sign bit, so 0x4000-0x7FFF are positive. Fails with `Overflow`
+    /// if the value does not fit in a u64, without reading more than the 5 shorts a u64 can
+    /// need
+    pub fn read_modular_short_u64(&mut self) -> Result<u64> {
+        let at_bit = self.bit_position();
+        let mut res = 0u64;
+        for i in 0..u64::BITS.div_ceil(15) {
+            let word = self.read_bits::<16>()? as u16;
+            let value = (word & !(1 << 15)) as u64;
+            let shift = i * 15;
+            if (value << shift) >> shift != value {
+                return Err(DwgError::Overflow { at_bit });
+            }
+            res |= value << shift;
+            if word & (1 << 15) == 0 {
+                return Ok(res);
             }
-            i += 1;
         }
-        Some(res)
+        Err(DwgError::Overflow { at_bit })
     }
 
-    pub fn read_raw_char(&mut self) -> Option<i8> {
+    pub fn read_raw_char(&mut self) -> Result<i8> {
         self.read_bits::<8>().map(|x| x as i8)
     }
 
-    pub fn read_raw_short(&mut self) -> Option<i16> {
+    pub fn read_raw_short(&mut self) -> Result<i16> {
         self.read_bits::<16>().map(|x| x as i16)
     }
 
-    pub fn read_raw_long(&mut self) -> Option<i32> {
+    pub fn read_raw_long(&mut self) -> Result<i32> {
         self.read_bits::<32>().map(|x| x as i32)
     }
 
-    pub fn read_raw_longlong(&mut self) -> Option<i64> {
-        let x1 = self.read_bits::<32>()? as u64;
-        let x2 = self.read_bits::<32>()? as u64;
-        Some((x2 << 32 | x1) as i64)
+    pub fn read_raw_longlong(&mut self) -> Result<i64> {
+        self.read_bits_dyn(64).map(|x| x as i64)
+    }
+
+    pub fn read_raw_double(&mut self) -> Result<f64> {
+        self.read_bits_dyn(64).map(f64::from_bits)
+    }
+
+    /// Reads two raw doubles (2RD)
+    pub fn read_2rd(&mut self) -> Result<Point2> {
+        let x = self.read_raw_double()?;
+        let y = self.read_raw_double()?;
+        Ok(Point2 { x, y })
+    }
+
+    /// Reads three raw doubles (3RD)
+    pub fn read_3rd(&mut self) -> Result<Point3> {
+        let x = self.read_raw_double()?;
+        let y = self.read_raw_double()?;
+        let z = self.read_raw_double()?;
+        Ok(Point3 { x, y, z })
+    }
+
+    /// Reads two bitdoubles (2BD)
+    pub fn read_2bd(&mut self) -> Result<Point2> {
+        let x = self.read_bitdouble()?;
+        let y = self.read_bitdouble()?;
+        Ok(Point2 { x, y })
     }
 
-    pub fn read_raw_double(&mut self) -> Option<f64> {
-        let x1 = self.read_bits::<32>()? as u64;
-        let x2 = self.read_bits::<32>()? as u64;
-        Some(f64::from_bits(x2 << 32 | x1))
+    /// Reads three bitdoubles (3BD)
+    pub fn read_3bd(&mut self) -> Result<Point3> {
+        let x = self.read_bitdouble()?;
+        let y = self.read_bitdouble()?;
+        let z = self.read_bitdouble()?;
+        Ok(Point3 { x, y, z })
     }
 
-    pub fn read_bit_extrusion(&mut self) -> Option<(f64, f64, f64)> {
+    pub fn read_bit_extrusion(&mut self) -> Result<Point3> {
         if self.version >= DWGVersion::AC1015 {
             // NOTE: ODS does not specifically say that post R16 versions use this method,
             // only that R16 uses this method
             let bit = self.read_bit()?;
             if bit == 1 {
-                return Some((0.0, 0.0, 1.0));
+                return Ok(Point3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 1.0,
+                });
+            }
+        }
+        self.read_3bd()
+    }
+
+    #[deprecated(note = "this reads a BT, use `read_bit_thickness` or `read_dd` instead")]
+    pub fn read_bitdouble_with_default(&mut self) -> Result<f64> {
+        if self.version >= DWGVersion::AC1015 {
+            let bit = self.read_bit()?;
+            if bit == 1 {
+                return Ok(0.0);
+            }
+        }
+        self.read_bitdouble()
+    }
+
+    /// Reads a bitdouble with default (DD)
+    ///
+    /// The 2 bit flag selects between the default value (00), patching the low 4 bytes of the
+    /// default (01), patching bytes 5-6 then bytes 1-4 of the default (10), or a full raw
+    /// double (11)
+    pub fn read_dd(&mut self, default: f64) -> Result<f64> {
+        let flag = self.read_bits::<2>()?;
+        let mut bytes = default.to_le_bytes();
+        match flag {
+            0x0 => return Ok(default),
+            0x1 => {
+                for byte in bytes[0..4].iter_mut() {
+                    *byte = self.read_bits::<8>()? as u8;
+                }
+            }
+            0x2 => {
+                for byte in bytes[4..6].iter_mut() {
+                    *byte = self.read_bits::<8>()? as u8;
+                }
+                for byte in bytes[0..4].iter_mut() {
+                    *byte = self.read_bits::<8>()? as u8;
+                }
             }
+            0x3 => return self.read_raw_double(),
+            _ => unreachable!("flag is 2 bits"),
         }
-        let x1 = self.read_bitdouble()?;
-        let x2 = self.read_bitdouble()?;
-        let x3 = self.read_bitdouble()?;
-        Some((x1, x2, x3))
+        Ok(f64::from_le_bytes(bytes))
     }
 
-    pub fn read_bitdouble_with_default(&mut self) -> Option<f64> {
+    /// Reads a bit thickness (BT)
+    ///
+    /// For AC1015 (R2000) and later a leading bit of 1 means the thickness is 0.0, otherwise
+    /// a bitdouble follows. Earlier versions always store a bitdouble
+    pub fn read_bit_thickness(&mut self) -> Result<f64> {
         if self.version >= DWGVersion::AC1015 {
             let bit = self.read_bit()?;
             if bit == 1 {
-                return Some(0.0);
+                return Ok(0.0);
             }
         }
         self.read_bitdouble()
     }
 
-    pub fn read_cm_color_short(&mut self) -> Option<i16> {
+    pub fn read_cm_color_short(&mut self) -> Result<i16> {
         self.read_bitshort()
     }
 
-    pub fn read_object_type(&mut self) -> Option<i16> {
+    /// Reads a full color value (CMC)
+    ///
+    /// Before AC1018 (R2004) this is only a BS color index. Later versions store a BS index,
+    /// a BL RGB value, a RC of flags, then the color name and book name if flagged
+    pub fn read_cm_color(&mut self) -> Result<CmColor> {
+        let index = self.read_bitshort()?;
+        if self.version < DWGVersion::AC1018 {
+            return Ok(CmColor {
+                index,
+                ..Default::default()
+            });
+        }
+        let rgb = self.read_bitlong()? as u32;
+        let flags = self.read_bits::<8>()? as u8;
+        let name = if flags & 0x1 != 0 {
+            Some(self.read_text()?)
+        } else {
+            None
+        };
+        let book_name = if flags & 0x2 != 0 {
+            Some(self.read_text()?)
+        } else {
+            None
+        };
+        Ok(CmColor {
+            index,
+            rgb,
+            flags,
+            name,
+            book_name,
+        })
+    }
+
+    /// Reads an entity color (ENC)
+    ///
+    /// For AC1018 (R2004) and later, the BS holds flags in the high bits and the color index
+    /// in the low 9 bits. 0x8000 means a BL RGB value follows, 0x4000 means the color is a
+    /// reference to an AcDbColor object and 0x2000 means a BL transparency follows.
+    /// Earlier versions only store the color index
+    pub fn read_entity_color(&mut self) -> Result<EntityColor> {
+        let raw = self.read_bitshort()? as u16;
+        if self.version < DWGVersion::AC1018 {
+            return Ok(EntityColor {
+                color: Color::from_index(raw as i16),
+                transparency: None,
+                has_color_handle: false,
+            });
+        }
+        let index = (raw & 0x1FF) as i16;
+        let color = if raw & 0x8000 != 0 {
+            let rgb = self.read_bitlong()? as u32;
+            Color::TrueColor {
+                rgb: rgb & 0xFFFFFF,
+                aci: index as u8,
+            }
+        } else {
+            Color::from_index(index)
+        };
+        let transparency = if raw & 0x2000 != 0 {
+            Some(Transparency::from_raw(self.read_bitlong()? as u32))
+        } else {
+            None
+        };
+        Ok(EntityColor {
+            color,
+            transparency,
+            has_color_handle: raw & 0x4000 != 0,
+        })
+    }
+
+    pub fn read_object_type(&mut self) -> Result<i16> {
         if self.version <= DWGVersion::AC1021 {
             self.read_bitshort()
         } else {
             let flags = self.read_bits::<2>()?;
             match flags {
-                0x0 => self.read_raw_char().map(|x| x as i16),
-                0x1 => self.read_raw_char().map(|x| x as i16 + 0x1f0),
+                0x0 => self.read_raw_char().map(|x| x as u8 as i16),
+                0x1 => self.read_raw_char().map(|x| x as u8 as i16 + 0x1f0),
                 0x2 => self.read_raw_short(),
                 0x3 => self.read_raw_short(),
-                _ => unreachable!(),
+                _ => unreachable!("flag is 2 bits"),
             }
         }
     }
-}
 
-#[test]
-fn test_read_bits() {
-    let buf: [_; 4] = [0xFF, 0xDD, 0xCC, 0xBB];
-    let mut reader = BitReader::new(buf.iter());
-    assert_eq!(reader.read_bits::<8>(), Some(0xFF));
-    assert_eq!(reader.read_bits::<16>(), Some(0xCCDD));
-    assert_eq!(reader.read_bits::<5>(), Some(0x1B));
-    assert_eq!(reader.read_bits::<3>(), Some(0x5));
-    assert_eq!(reader.read_bits::<1>(), None);
-}
+    /// Reads a handle reference (H)
+    ///
+    /// The first byte contains the code in the high nibble and the number of handle bytes that
+    /// follow in the low nibble. Handle bytes are stored most significant byte first.
+    /// Fails with `InvalidFlag` if the code is not a valid `RefType`
+    pub fn read_handle(&mut self) -> Result<HandleRef> {
+        let at_bit = self.bit_position();
+        let byte = self.read_bits::<8>()? as u8;
+        let invalid = DwgError::InvalidFlag {
+            at_bit,
+            value: byte as u64,
+        };
+        let code = RefType::from_repr(byte >> 4).ok_or(invalid.clone())?;
+        let counter = byte & 0xF;
+        // Handles are at most 8 bytes
+        if counter > 8 {
+            return Err(invalid);
+        }
+        let mut handle = 0u64;
+        for _ in 0..counter {
+            handle = handle << 8 | self.read_bits::<8>()? as u64;
+        }
+        Ok(HandleRef { code, handle })
+    }
 
-#[test]
-fn test_read_raw_long() {
+    /// Reads a date as two BLs, the Julian day followed by milliseconds since midnight
+    ///
+    /// Used for TDCREATE and TDUPDATE
+    pub fn read_bl_date(&mut self) -> Result<JulianDate> {
+        let day = self.read_bitlong()?;
+        let ms = self.read_bitlong()?;
+        Ok(JulianDate { day, ms })
+    }
+
+    /// Reads an elapsed time (TIMEBLL) as two BLs, the number of days followed by milliseconds
+    ///
+    /// Used for TDINDWG and TDUSRTIMER. Fails with `Overflow` if either value is negative
+    pub fn read_timebll(&mut self) -> Result<Duration> {
+        let at_bit = self.bit_position();
+        let days = u64::try_from(self.read_bitlong()?);
+        let ms = u64::try_from(self.read_bitlong()?);
+        match (days, ms) {
+            (Ok(days), Ok(ms)) => {
+                Ok(Duration::from_secs(days * 86_400) + Duration::from_millis(ms))
+            }
+            _ => Err(DwgError::Overflow { at_bit }),
+        }
+    }
+
+    /// Reads 16 bytes and verifies they match the expected sentinel, see
+    /// [`crate::sentinels`] for the known values
+    ///
+    /// Fails with `BadSentinel` at the start of the sentinel if any byte differs
+    pub fn read_sentinel(&mut self, expected: &[u8; 16]) -> Result<()> {
+        let at_bit = self.bit_position();
+        let mut matches = true;
+        for byte in expected {
+            matches &= self.read_bits::<8>()? as u8 == *byte;
+        }
+        if !matches {
+            return Err(DwgError::BadSentinel { at_bit });
+        }
+        Ok(())
+    }
+
+    /// Reads variable text (TV), a BS length followed by that many raw chars
+    ///
+    /// The bytes are decoded using the reader's codepage. Some writers include the null
+    /// terminator in the length, so trailing nulls are stripped.
+    ///
+    /// For AC1021 (R2007) and later, strings are unicode (TU) and this defers to
+    /// [`BitReader::read_text_unicode`]
+    pub fn read_text(&mut self) -> Result<String> {
+        if self.version >= DWGVersion::AC1021 {
+            return self.read_text_unicode();
+        }
+        let len = self.read_bitshort()? as u16;
+        let mut bytes = self.read_vec(len as usize)?;
+        while bytes.last() == Some(&0) {
+            bytes.pop();
+        }
+        Ok(self.codepage.decode(&bytes))
+    }
+
+    /// Reads a plain string of `len` raw bytes, as used in the second header and SummaryInfo
+    ///
+    /// The string ends at the first null if there is one, and is decoded using `codepage`
+    /// rather than the reader's codepage
+    pub fn read_fixed_string(&mut self, len: usize, codepage: CodePage) -> Result<String> {
+        let bytes = self.read_vec(len)?;
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(len);
+        Ok(codepage.decode(&bytes[..end]))
+    }
+
+    /// Reads a null terminated string of raw bytes, decoded using `codepage`
+    ///
+    /// The terminator is consumed but not included in the result
+    pub fn read_cstring(&mut self, codepage: CodePage) -> Result<String> {
+        let mut bytes = Vec::new();
+        loop {
+            match self.read_bits::<8>()? as u8 {
+                0 => break,
+                byte => bytes.push(byte),
+            }
+        }
+        Ok(codepage.decode(&bytes))
+    }
+
+    /// Reads unicode text (TU), a BS length followed by that many UTF-16LE code units
+    ///
+    /// Unpaired surrogates are replaced and trailing nulls are stripped
+    pub fn read_text_unicode(&mut self) -> Result<String> {
+        let len = self.read_bitshort()? as u16;
+        let mut units = Vec::with_capacity(len as usize);
+        for _ in 0..len {
+            units.push(self.read_bits::<16>()? as u16);
+        }
+        while units.last() == Some(&0) {
+            units.pop();
+        }
+        Ok(String::from_utf16_lossy(&units))
+    }
+}
+
+#[test]
+fn test_read_bits() {
+    let buf: [_; 4] = [0xFF, 0xDD, 0xCC, 0xBB];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_bits::<8>(), Ok(0xFF));
+    assert_eq!(reader.read_bits::<16>(), Ok(0xCCDD));
+    assert_eq!(reader.read_bits::<5>(), Ok(0x17));
+    assert_eq!(reader.read_bits::<3>(), Ok(0x3));
+    assert_eq!(
+        reader.read_bits::<1>(),
+        Err(DwgError::UnexpectedEof { at_bit: 32 })
+    );
+}
+
+#[test]
+fn test_read_raw_long() {
     let buf: [_; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
     let mut reader = BitReader::new(buf.iter());
-    assert_eq!(reader.read_raw_long(), Some(-1));
+    assert_eq!(reader.read_raw_long(), Ok(-1));
 
     let buf: [_; 4] = [0x01, 0x00, 0x00, 0x00];
     let mut reader = BitReader::new(buf.iter());
-    assert_eq!(reader.read_raw_long(), Some(1));
+    assert_eq!(reader.read_raw_long(), Ok(1));
 }
 
 #[test]
 fn test_read_raw_longlong() {
     let buf: [_; 8] = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
     let mut reader = BitReader::new(buf.iter());
-    assert_eq!(reader.read_raw_longlong(), Some(-1));
+    assert_eq!(reader.read_raw_longlong(), Ok(-1));
 
     let buf: [_; 8] = [0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
     let mut reader = BitReader::new(buf.iter());
-    assert_eq!(reader.read_raw_longlong(), Some(1));
+    assert_eq!(reader.read_raw_longlong(), Ok(1));
 }
 
 #[test]
@@ -288,7 +1211,100 @@ fn test_read_modular_char() {
     // Opendesign specification example
     let buf: [_; 2] = [0b10000010, 0b00100100];
     let mut reader = BitReader::new(buf.iter());
-    assert_eq!(reader.read_modular_char(), Some(4610));
+    assert_eq!(reader.read_modular_char(), Ok(4610));
+
+    // Sign bit set in the final byte
+    let buf: [_; 2] = [0b10000010, 0b01100100];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_modular_char(), Ok(-4610));
+
+    let buf: [_; 1] = [0b01000001];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_modular_char(), Ok(-1));
+
+    // Endless continuation bytes stop once an i32 is exceeded
+    let buf = [0xFFu8; 64];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(
+        reader.read_modular_char(),
+        Err(DwgError::Overflow { at_bit: 0 })
+    );
+    assert_eq!(reader.bit_position(), 40);
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(
+        reader.read_modular_char_u64(),
+        Err(DwgError::Overflow { at_bit: 0 })
+    );
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(
+        reader.read_modular_short_u64(),
+        Err(DwgError::Overflow { at_bit: 0 })
+    );
+
+    // Zero continuation bytes past the width of the value are still rejected
+    let buf = [0x80u8; 64];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(
+        reader.read_modular_char_u64(),
+        Err(DwgError::Overflow { at_bit: 0 })
+    );
+    assert_eq!(reader.bit_position(), 80);
+
+    // A final byte that overflows an i32
+    let buf: [_; 5] = [0xFF, 0xFF, 0xFF, 0xFF, 0x3F];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(
+        reader.read_modular_char(),
+        Err(DwgError::Overflow { at_bit: 0 })
+    );
+}
+
+#[test]
+fn test_read_handle_offset() {
+    let buf: [_; 2] = [0b10000010, 0b01100100];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_handle_offset(), Ok(-4610));
+
+    // -2^35, too large for the i32 reader
+    let buf: [_; 6] = [0x80, 0x80, 0x80, 0x80, 0x80, 0x41];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_handle_offset(), Ok(-(1 << 35)));
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(
+        reader.read_modular_char(),
+        Err(DwgError::Overflow { at_bit: 0 })
+    );
+}
+
+#[test]
+fn test_read_unsigned_modular_char() {
+    // Bit 0x40 of the final byte is part of the value
+    let buf: [_; 2] = [0b10000010, 0b01100100];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_unsigned_modular_char(), Ok(12802));
+}
+
+#[test]
+fn test_read_modular_u64() {
+    // 2^35, too large for 32 bits
+    let buf: [_; 6] = [0x80, 0x80, 0x80, 0x80, 0x80, 0x01];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_modular_char_u64(), Ok(1 << 35));
+
+    // 2^45 in modular shorts, which does not fit in the i32 reader
+    let buf: [_; 8] = [0x00, 0x80, 0x00, 0x80, 0x00, 0x80, 0x01, 0x00];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_modular_short_u64(), Ok(1 << 45));
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(
+        reader.read_modular_short(),
+        Err(DwgError::Overflow { at_bit: 0 })
+    );
+
+    // A final short with 0x4000 set is not negative when unsigned
+    let buf: [_; 2] = [0x00, 0x40];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_modular_short_u64(), Ok(0x4000));
 }
 
 #[test]
@@ -297,5 +1313,794 @@ fn test_read_modular_short() {
     // NOTE: First byte of example in PDF is wrong
     let buf: [_; 4] = [0b00110001, 0b11110100, 0b10001101, 0b00000000];
     let mut reader = BitReader::new(buf.iter());
-    assert_eq!(reader.read_modular_short(), Some(4650033));
+    assert_eq!(reader.read_modular_short(), Ok(4650033));
+
+    // The sign bit of the final short, after one short of 15 bits
+    let buf: [_; 4] = [0x05, 0x80, 0x02, 0x40];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_modular_short(), Ok(-(2 << 15 | 5)));
+    let buf: [_; 2] = [0x00, 0x40];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_modular_short(), Ok(0));
+    let buf: [_; 2] = [0x01, 0x40];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_modular_short(), Ok(-1));
+
+    for value in [
+        0,
+        1,
+        -1,
+        0x3FFF,
+        -0x3FFF,
+        0x4000,
+        -0x4000,
+        i32::MAX,
+        i32::MIN + 1,
+    ] {
+        let mut writer = crate::bitcodes_write::BitWriter::new();
+        writer.write_signed_modular_short(value);
+        let buf = writer.into_bytes();
+        let mut reader = BitReader::new(buf.iter());
+        assert_eq!(reader.read_modular_short(), Ok(value));
+    }
+}
+
+#[test]
+fn test_read_handle() {
+    // Hard pointer to handle 0x1F
+    let buf: [_; 2] = [0x51, 0x1F];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(
+        reader.read_handle(),
+        Ok(HandleRef {
+            code: RefType::HardPointer,
+            handle: 0x1F
+        })
+    );
+
+    // Soft owned, two byte handle
+    let buf: [_; 3] = [0x22, 0x01, 0x02];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(
+        reader.read_handle(),
+        Ok(HandleRef {
+            code: RefType::SoftOwned,
+            handle: 0x0102
+        })
+    );
+
+    // Relative reference with no handle bytes
+    let buf: [_; 1] = [0x60];
+    let mut reader = BitReader::new(buf.iter());
+    let handle = reader.read_handle().unwrap();
+    assert_eq!(handle.code, RefType::PlusOne);
+    assert_eq!(handle.absolute(0x10), 0x11);
+}
+
+/// Packs `(value, n_bits)` fields into bytes in DWG bit order for tests
+#[cfg(test)]
+pub(crate) fn pack_bits(fields: &[(u64, u32)]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut n_bits = 0u32;
+    for &(value, width) in fields {
+        for i in (0..width).rev() {
+            if n_bits.is_multiple_of(8) {
+                bytes.push(0);
+            }
+            let bit = ((value >> i) & 1) as u8;
+            *bytes.last_mut().unwrap() |= bit << (7 - n_bits % 8);
+            n_bits += 1;
+        }
+    }
+    bytes
+}
+
+/// The fields of test vectors in the order they are stored, written as chapter 2 of the ODS
+/// describes each datatype, see [`pack_bits`]
+///
+/// Only the R2000 forms of the datatypes are written, which AC1018 (R2004) shares, apart from
+/// the unicode text of AC1021 (R2007) and later
+#[cfg(test)]
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Bits(Vec<(u64, u32)>);
+
+#[cfg(test)]
+impl Bits {
+    pub(crate) fn bits(mut self, value: u64, width: u32) -> Self {
+        self.0.push((value, width));
+        self
+    }
+
+    pub(crate) fn bytes(self, bytes: &[u8]) -> Self {
+        bytes
+            .iter()
+            .fold(self, |bits, &byte| bits.bits(byte as u64, 8))
+    }
+
+    pub(crate) fn then(mut self, other: Bits) -> Self {
+        self.0.extend(other.0);
+        self
+    }
+
+    pub(crate) fn len(&self) -> u32 {
+        self.0.iter().map(|&(_, width)| width).sum()
+    }
+
+    pub(crate) fn pack(&self) -> Vec<u8> {
+        pack_bits(&self.0)
+    }
+
+    pub(crate) fn b(self, value: bool) -> Self {
+        self.bits(value as u64, 1)
+    }
+
+    pub(crate) fn bb(self, value: u8) -> Self {
+        self.bits(value as u64, 2)
+    }
+
+    pub(crate) fn rc(self, value: u8) -> Self {
+        self.bits(value as u64, 8)
+    }
+
+    pub(crate) fn rs(self, value: i16) -> Self {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    pub(crate) fn rl(self, value: i32) -> Self {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    pub(crate) fn rd(self, value: f64) -> Self {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    /// 0 and 256 take 2 bits and 1 to 255 a byte more, anything else an RS
+    pub(crate) fn bs(self, value: i16) -> Self {
+        match value {
+            0 => self.bb(2),
+            256 => self.bb(3),
+            1..=255 => self.bb(1).rc(value as u8),
+            _ => self.bb(0).rs(value),
+        }
+    }
+
+    /// 0 takes 2 bits and 1 to 255 a byte more, anything else an RL
+    pub(crate) fn bl(self, value: i32) -> Self {
+        match value {
+            0 => self.bb(2),
+            1..=255 => self.bb(1).rc(value as u8),
+            _ => self.bb(0).rl(value),
+        }
+    }
+
+    /// 1.0 and 0.0 take 2 bits, anything else an RD more
+    pub(crate) fn bd(self, value: f64) -> Self {
+        match value {
+            1.0 => self.bb(1),
+            0.0 => self.bb(2),
+            _ => self.bb(0).rd(value),
+        }
+    }
+
+    /// Patches the low 4 or 6 bytes of `default` if the rest are the same
+    pub(crate) fn dd(self, value: f64, default: f64) -> Self {
+        let (bytes, default) = (value.to_le_bytes(), default.to_le_bytes());
+        if bytes == default {
+            self.bb(0)
+        } else if bytes[4..] == default[4..] {
+            self.bb(1).bytes(&bytes[..4])
+        } else if bytes[6..] == default[6..] {
+            self.bb(2).bytes(&bytes[4..6]).bytes(&bytes[..4])
+        } else {
+            self.bb(3).rd(value)
+        }
+    }
+
+    pub(crate) fn p2(self, (x, y): (f64, f64)) -> Self {
+        self.rd(x).rd(y)
+    }
+
+    pub(crate) fn p3(self, (x, y, z): (f64, f64, f64)) -> Self {
+        self.bd(x).bd(y).bd(z)
+    }
+
+    /// A thickness of 0 is a set bit
+    pub(crate) fn bt(self, value: f64) -> Self {
+        match value {
+            0.0 => self.b(true),
+            _ => self.b(false).bd(value),
+        }
+    }
+
+    /// An extrusion of (0, 0, 1) is a set bit
+    pub(crate) fn be(self, value: (f64, f64, f64)) -> Self {
+        match value {
+            (0.0, 0.0, 1.0) => self.b(true),
+            _ => self.b(false).p3(value),
+        }
+    }
+
+    /// A BS length, including the null terminator, followed by the text
+    pub(crate) fn tv(self, text: &str) -> Self {
+        match text.is_empty() {
+            true => self.bs(0),
+            false => self.bs(text.len() as i16 + 1).bytes(text.as_bytes()).rc(0),
+        }
+    }
+
+    /// A BS length in UTF-16 code units, including the null terminator, followed by the text
+    /// in UTF-16LE
+    pub(crate) fn tu(self, text: &str) -> Self {
+        let units: Vec<u16> = text.encode_utf16().collect();
+        match units.is_empty() {
+            true => self.bs(0),
+            false => units
+                .iter()
+                .fold(self.bs(units.len() as i16 + 1), |bits, unit| {
+                    bits.bytes(&unit.to_le_bytes())
+                })
+                .rs(0),
+        }
+    }
+
+    /// A 4 bit code and count of handle bytes followed by the handle, high byte first
+    pub(crate) fn h(self, code: u8, handle: u64) -> Self {
+        let bytes = handle.to_be_bytes();
+        let skip = bytes.iter().take_while(|&&byte| byte == 0).count();
+        self.bits(code as u64, 4)
+            .bits((8 - skip) as u64, 4)
+            .bytes(&bytes[skip..])
+    }
+
+    /// A CMC, which from AC1018 (R2004) is a BS index of 0, a BL color method and RGB and an
+    /// RC of flags
+    pub(crate) fn cmc(self, version: DWGVersion, index: i16, rgb: u32) -> Self {
+        match version < DWGVersion::AC1018 {
+            true => self.bs(index),
+            false => self.bs(0).bl(rgb as i32).rc(0),
+        }
+    }
+}
+
+#[test]
+fn test_read_bits_unaligned() {
+    // 2 bit flag followed by an unaligned raw short 0x1234 (low byte first)
+    let buf = pack_bits(&[(0b10, 2), (0x34, 8), (0x12, 8)]);
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_bits::<2>(), Ok(0b10));
+    assert_eq!(reader.read_raw_short(), Ok(0x1234));
+}
+
+#[test]
+fn test_read_text() {
+    // BS flag 01 (8 bit value) with length 3 followed by "abc"
+    let buf = pack_bits(&[
+        (0b01, 2),
+        (3, 8),
+        (b'a' as u64, 8),
+        (b'b' as u64, 8),
+        (b'c' as u64, 8),
+    ]);
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_text(), Ok(String::from("abc")));
+}
+
+#[test]
+fn test_read_raw_strings() {
+    let buf = b"AB\0\0C\xB0\0D";
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(
+        reader.read_fixed_string(4, CodePage::ANSI1252),
+        Ok(String::from("AB"))
+    );
+    assert_eq!(
+        reader.read_cstring(CodePage::ANSI1252),
+        Ok(String::from("C\u{B0}"))
+    );
+    assert_eq!(
+        reader.read_cstring(CodePage::ANSI1252),
+        Err(DwgError::UnexpectedEof { at_bit: 64 })
+    );
+}
+
+#[test]
+fn test_read_text_unicode() {
+    // "Aé€" with a null terminator, read through read_text for an R2007 reader
+    let buf = pack_bits(&[
+        (0b01, 2),
+        (4, 8),
+        (0x41, 8),
+        (0x00, 8),
+        (0xE9, 8),
+        (0x00, 8),
+        (0xAC, 8),
+        (0x20, 8),
+        (0x00, 8),
+        (0x00, 8),
+    ]);
+    let mut reader = BitReader::new(buf.iter());
+    reader.set_version(DWGVersion::AC1021);
+    assert_eq!(reader.read_text(), Ok(String::from("Aé€")));
+}
+
+#[test]
+fn test_read_cm_color() {
+    // R2000 reads only the index
+    let buf = pack_bits(&[(0b01, 2), (7, 8)]);
+    let mut reader = BitReader::new(buf.iter());
+    let color = reader.read_cm_color().unwrap();
+    assert_eq!(color.index, 7);
+    assert_eq!(color.name, None);
+
+    // R2004 true color with a name
+    let buf = pack_bits(&[
+        (0b10, 2),
+        (0b00, 2),
+        (0x56, 8),
+        (0x34, 8),
+        (0x12, 8),
+        (0xC2, 8),
+        (0x01, 8),
+        (0b01, 2),
+        (1, 8),
+        (b'X' as u64, 8),
+    ]);
+    let mut reader = BitReader::new(buf.iter());
+    reader.set_version(DWGVersion::AC1018);
+    let color = reader.read_cm_color().unwrap();
+    assert_eq!(color.index, 0);
+    assert_eq!(color.rgb, 0xC2123456);
+    assert_eq!(color.method(), 0xC2);
+    assert_eq!(color.name.as_deref(), Some("X"));
+    assert_eq!(color.book_name, None);
+}
+
+#[test]
+fn test_read_entity_color() {
+    // R2000 ByLayer
+    let buf = pack_bits(&[(0b00, 2), (0x00, 8), (0x01, 8)]);
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_entity_color().unwrap().color, Color::ByLayer);
+
+    // R2004 true color with transparency, index 5 as the fallback
+    let buf = pack_bits(&[
+        (0b00, 2),
+        (0x05, 8),
+        (0xA0, 8),
+        (0b00, 2),
+        (0x33, 8),
+        (0x22, 8),
+        (0x11, 8),
+        (0xC2, 8),
+        (0b00, 2),
+        (0x80, 8),
+        (0x00, 8),
+        (0x00, 8),
+        (0x03, 8),
+    ]);
+    let mut reader = BitReader::new(buf.iter());
+    reader.set_version(DWGVersion::AC1018);
+    let color = reader.read_entity_color().unwrap();
+    assert_eq!(
+        color.color,
+        Color::TrueColor {
+            rgb: 0x112233,
+            aci: 5
+        }
+    );
+    assert_eq!(color.transparency, Some(Transparency::Alpha(0x80)));
+    assert!(!color.has_color_handle);
+}
+
+#[test]
+fn test_read_bit_thickness() {
+    // Set bit means 0.0, otherwise a bitdouble of 1.0 (flag 01)
+    let buf = pack_bits(&[(1, 1), (0, 1), (0b01, 2)]);
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_bit_thickness(), Ok(0.0));
+    assert_eq!(reader.read_bit_thickness(), Ok(1.0));
+
+    // R14 has no leading bit
+    let buf = pack_bits(&[(0b01, 2)]);
+    let mut reader = BitReader::new(buf.iter());
+    reader.set_version(DWGVersion::AC1014);
+    assert_eq!(reader.read_bit_thickness(), Ok(1.0));
+}
+
+#[test]
+fn test_read_3rd() {
+    let mut buf = Vec::new();
+    for v in [1.5f64, -2.0, 1e10] {
+        buf.extend_from_slice(&v.to_le_bytes());
+    }
+    let mut reader = BitReader::new(buf.iter());
+    let point = reader.read_3rd().unwrap();
+    assert_eq!(<(f64, f64, f64)>::from(point), (1.5, -2.0, 1e10));
+
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_2rd(), Ok(Point2 { x: 1.5, y: -2.0 }));
+}
+
+#[test]
+fn test_read_3bd() {
+    // 1.0, 0.0 then a full double
+    let mut fields = vec![(0b01, 2), (0b10, 2), (0b00, 2)];
+    for byte in 3.25f64.to_le_bytes() {
+        fields.push((byte as u64, 8));
+    }
+    let buf = pack_bits(&fields);
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(
+        reader.read_3bd(),
+        Ok(Point3 {
+            x: 1.0,
+            y: 0.0,
+            z: 3.25
+        })
+    );
+}
+
+#[test]
+fn test_read_dd() {
+    let default = 1.0f64;
+    let target = 1.0000001f64;
+    let default_bytes = default.to_le_bytes();
+    let target_bytes = target.to_le_bytes();
+    assert_eq!(default_bytes[6..], target_bytes[6..]);
+
+    // Default
+    let buf = pack_bits(&[(0b00, 2)]);
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_dd(default), Ok(default));
+
+    // Patch bytes 5-6 then 1-4
+    let mut fields = vec![(0b10, 2)];
+    for byte in target_bytes[4..6].iter().chain(target_bytes[0..4].iter()) {
+        fields.push((*byte as u64, 8));
+    }
+    let buf = pack_bits(&fields);
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_dd(default), Ok(target));
+
+    // Patch only the low 4 bytes
+    let patched = f64::from_le_bytes([1, 2, 3, 4, 0, 0, 0xF0, 0x3F]);
+    let buf = pack_bits(&[(0b01, 2), (1, 8), (2, 8), (3, 8), (4, 8)]);
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_dd(default), Ok(patched));
+
+    // Full raw double
+    let mut fields = vec![(0b11, 2)];
+    for byte in (-5.5f64).to_le_bytes() {
+        fields.push((byte as u64, 8));
+    }
+    let buf = pack_bits(&fields);
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_dd(default), Ok(-5.5));
+}
+
+#[test]
+fn test_read_bitlonglong() {
+    let buf = pack_bits(&[(0, 3), (3, 3), (0x01, 8), (0x02, 8), (0x03, 8)]);
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_bitlonglong(), Ok(0));
+    assert_eq!(reader.read_bitlonglong(), Ok(0x030201));
+}
+
+#[test]
+fn test_bit_position() {
+    let buf: [_; 4] = [0xFF, 0xDD, 0xCC, 0xBB];
+    let mut reader = BitReader::from_slice(&buf);
+    assert_eq!(reader.bit_position(), 0);
+    reader.read_bits::<3>().unwrap();
+    assert_eq!(reader.bit_position(), 3);
+    assert_eq!(reader.byte_position(), 0);
+    reader.read_bits::<8>().unwrap();
+    assert_eq!(reader.bit_position(), 11);
+    assert_eq!(reader.byte_position(), 1);
+
+    reader.seek_to_bit(16).unwrap();
+    assert_eq!(reader.read_bits::<8>(), Ok(0xCC));
+    reader.seek_to_bit(4).unwrap();
+    assert_eq!(reader.bit_position(), 4);
+    assert_eq!(reader.read_bits::<8>(), Ok(0xFD));
+    assert_eq!(
+        reader.seek_to_bit(33),
+        Err(DwgError::SeekOutOfRange { offset: 33 })
+    );
+
+    // Iterators cannot seek
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.seek_to_bit(8), Err(DwgError::SeekUnsupported));
+}
+
+#[test]
+fn test_read_errors() {
+    // Bitdouble flag 11 is not valid
+    let buf = pack_bits(&[(0b1, 1), (0b11, 2)]);
+    let mut reader = BitReader::new(buf.iter());
+    reader.read_bit().unwrap();
+    assert_eq!(
+        reader.read_bitdouble(),
+        Err(DwgError::InvalidFlag {
+            at_bit: 1,
+            value: 0b11
+        })
+    );
+
+    // Handle code 1 is not valid
+    let buf: [_; 1] = [0x10];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(
+        reader.read_handle(),
+        Err(DwgError::InvalidFlag {
+            at_bit: 0,
+            value: 0x10
+        })
+    );
+}
+
+#[test]
+fn test_take_bits() {
+    let buf = pack_bits(&[(0b01, 2), (7, 8), (0xAB, 8), (0xCD, 8)]);
+    let mut reader = BitReader::new(buf.iter());
+    {
+        let mut object = reader.take_bits(18);
+        assert_eq!(object.read_bitshort(), Ok(7));
+        assert_eq!(object.remaining_bits(), 8);
+        // A nested bound cannot extend the outer one
+        let mut inner = object.take_bits(16);
+        assert_eq!(inner.remaining_bits(), 8);
+        assert_eq!(
+            inner.read_raw_short(),
+            Err(DwgError::BoundExceeded {
+                at_bit: 10,
+                end_bit: 18
+            })
+        );
+        drop(inner);
+        assert_eq!(object.read_raw_char(), Ok(0xABu8 as i8));
+        assert!(object.read_bit().is_err());
+    }
+    assert_eq!(reader.read_raw_char(), Ok(0xCDu8 as i8));
+}
+
+#[test]
+fn test_mark_rewind() {
+    let buf = pack_bits(&[(0b01, 2), (7, 8), (0xAB, 8), (0xCD, 8)]);
+    let mut reader = BitSliceReader::from_slice(&buf);
+    reader.read_bitshort().unwrap();
+    let mark = reader.mark();
+    reader.start_crc(0);
+    assert_eq!(reader.read_raw_short(), Ok(0xCDABu16 as i16));
+    reader.rewind(mark).unwrap();
+    assert_eq!(reader.finish_crc(), None);
+    assert_eq!(reader.bit_position(), 10);
+    assert_eq!(reader.read_raw_char(), Ok(0xABu8 as i8));
+
+    let mut reader = BitReader::new(buf.iter());
+    let mark = reader.mark();
+    assert_eq!(reader.rewind(mark), Err(DwgError::SeekUnsupported));
+}
+
+#[test]
+fn test_read_raw_bits() {
+    let buf = pack_bits(&[(0b101, 3), (0xABC, 12), (1, 1)]);
+    let mut reader = BitReader::new(buf.iter());
+    reader.read_bits::<3>().unwrap();
+    let bits = reader.read_raw_bits(12).unwrap();
+    assert_eq!(bits.as_bytes(), [0xAB, 0xC0]);
+    assert_eq!(bits.bit_len(), 12);
+    assert_eq!(reader.read_bit(), Ok(1));
+    assert!(reader.read_raw_bits(1).is_err());
+}
+
+#[test]
+fn test_from_reader() {
+    let buf = vec![0xFF, 0xDD, 0xCC, 0xBB];
+    let mut reader = BitReader::from_reader(std::io::Cursor::new(buf));
+    assert_eq!(reader.read_raw_short(), Ok(0xDDFFu16 as i16));
+    reader.seek_to_bit(20).unwrap();
+    assert_eq!(reader.read_bits::<4>(), Ok(0xC));
+    assert_eq!(reader.read_raw_char(), Ok(0xBBu8 as i8));
+    assert_eq!(
+        reader.read_bit(),
+        Err(DwgError::UnexpectedEof { at_bit: 32 })
+    );
+}
+
+#[test]
+fn test_from_reader_buffered() {
+    let buf: Vec<u8> = (0..32).collect();
+    // A small buffer so reads and seeks cross buffer boundaries
+    let mut reader = BitReader::from_reader_with_capacity(5, std::io::Cursor::new(&buf));
+    reader.read_bit().unwrap();
+    assert_eq!(reader.read_raw_long(), Ok(0x06040200));
+    reader.seek_to_bit(8 * 3).unwrap();
+    assert_eq!(reader.read_raw_longlong(), Ok(0x0A09080706050403));
+    reader.seek_to_bit(8 * 30).unwrap();
+    assert_eq!(reader.read_raw_short(), Ok(0x1F1E));
+    reader.seek_to_bit(8).unwrap();
+    let mut bytes = [0u8; 20];
+    reader.read_bytes(&mut bytes).unwrap();
+    assert_eq!(bytes[..], buf[1..21]);
+    assert_eq!(
+        reader.read_vec(12),
+        Err(DwgError::UnexpectedEof { at_bit: 256 })
+    );
+}
+
+#[test]
+fn test_slice_reader_word_reads() {
+    let buf: Vec<u8> = (0..32).map(|x| x * 7 + 3).collect();
+    // Compare the word at a time path against the byte at a time path at every bit offset
+    for offset in 0..64 {
+        let mut fast = BitSliceReader::from_slice(&buf);
+        let mut slow = BitReader::new(buf.iter());
+        fast.seek_to_bit(offset).unwrap();
+        for _ in 0..offset {
+            slow.read_bit().unwrap();
+        }
+        assert_eq!(fast.read_raw_long(), slow.read_raw_long());
+        assert_eq!(fast.read_raw_short(), slow.read_raw_short());
+        assert_eq!(fast.read_bits::<3>(), slow.read_bits::<3>());
+        assert_eq!(fast.read_raw_double(), slow.read_raw_double());
+        assert_eq!(fast.bit_position(), slow.bit_position());
+    }
+}
+
+#[test]
+fn test_sub_reader() {
+    let buf: [_; 4] = [0xFF, 0xDD, 0xCC, 0xBB];
+    let mut reader = BitSliceReader::from_slice(&buf);
+    reader.set_version(DWGVersion::AC1018);
+    let mut sub = reader.sub_reader(1, 2).unwrap();
+    assert_eq!(sub.get_version(), DWGVersion::AC1018);
+    assert_eq!(sub.read_raw_short(), Ok(0xCCDDu16 as i16));
+    assert!(sub.read_bit().is_err());
+    assert!(reader.sub_reader(3, 2).is_err());
+}
+
+#[test]
+fn test_read_bits_dyn() {
+    let buf: [_; 8] = [0xFF, 0xDD, 0xCC, 0xBB, 0x01, 0x02, 0x03, 0x04];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.read_bits_dyn(0), Ok(0));
+    assert_eq!(reader.read_bits_dyn(4), Ok(0xF));
+    assert_eq!(reader.read_bits_dyn(4), Ok(0xF));
+    // Two whole bytes then 4 bits as the most significant bits
+    assert_eq!(reader.read_bits_dyn(20), Ok(0xBCCDD));
+    assert_eq!(reader.read_bits_dyn(36), Ok(0x4302010B0));
+    assert_eq!(
+        reader.read_bits_dyn(65),
+        Err(DwgError::Overflow { at_bit: 64 })
+    );
+
+    let mut reader = BitSliceReader::from_slice(&buf);
+    assert_eq!(reader.read_bits_dyn(64), Ok(0x04030201BBCCDDFF));
+}
+
+#[test]
+fn test_peek_bits() {
+    let buf: [_; 4] = [0xFF, 0xDD, 0xCC, 0xBB];
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(reader.peek_bit(), Ok(1));
+    assert_eq!(reader.bit_position(), 0);
+    reader.read_bits::<4>().unwrap();
+    assert_eq!(reader.peek_bits::<16>(), Ok(0xDCFD));
+    assert_eq!(reader.bit_position(), 4);
+    assert_eq!(reader.read_bits::<16>(), Ok(0xDCFD));
+    assert_eq!(reader.peek_bits::<8>(), Ok(0xCB));
+    assert_eq!(reader.read_bits::<8>(), Ok(0xCB));
+    assert_eq!(
+        reader.peek_bits::<8>(),
+        Err(DwgError::UnexpectedEof { at_bit: 32 })
+    );
+    assert_eq!(reader.peek_bits::<4>(), Ok(0xB));
+
+    // Peeked bytes are still read by the word at a time path
+    let buf: Vec<u8> = (0..16).collect();
+    let mut reader = BitSliceReader::from_slice(&buf);
+    assert_eq!(reader.peek_bits::<32>(), Ok(0x03020100));
+    assert_eq!(reader.read_raw_long(), Ok(0x03020100));
+    assert_eq!(reader.read_raw_long(), Ok(0x07060504));
+}
+
+#[test]
+fn test_read_sentinel() {
+    use crate::sentinels;
+
+    let mut buf = vec![0u8];
+    buf.extend_from_slice(&sentinels::CLASSES_START);
+    buf.extend_from_slice(&sentinels::CLASSES_START);
+    let mut reader = BitReader::new(buf.iter());
+    reader.read_raw_char().unwrap();
+    assert_eq!(reader.read_sentinel(&sentinels::CLASSES_START), Ok(()));
+    assert_eq!(
+        reader.read_sentinel(&sentinels::CLASSES_END),
+        Err(DwgError::BadSentinel { at_bit: 136 })
+    );
+}
+
+#[test]
+fn test_running_crc() {
+    use crate::crc::CRC8_SEED;
+
+    let buf: Vec<u8> = (0..32).collect();
+    let mut reader = BitSliceReader::from_slice(&buf);
+    reader.read_raw_char().unwrap();
+    reader.start_crc(CRC8_SEED);
+    reader.read_raw_long().unwrap();
+    // Finishing part way through a byte includes that byte
+    reader.read_bits::<3>().unwrap();
+    assert_eq!(reader.finish_crc(), Some(crc8(CRC8_SEED, &buf[1..6])));
+    assert_eq!(reader.finish_crc(), None);
+
+    // Starting part way through a byte includes that byte, through both read paths
+    let mut reader = BitReader::new(buf.iter());
+    reader.read_bits::<3>().unwrap();
+    reader.start_crc(CRC8_SEED);
+    reader.read_raw_short().unwrap();
+    assert_eq!(reader.finish_crc(), Some(crc8(CRC8_SEED, &buf[0..3])));
+
+    // Peeking does not add the peeked bytes twice
+    let mut reader = BitReader::new(buf.iter());
+    reader.start_crc(CRC8_SEED);
+    assert_eq!(reader.peek_bits::<16>(), Ok(0x0100));
+    assert_eq!(reader.read_raw_short(), Ok(0x0100));
+    assert_eq!(reader.finish_crc(), Some(crc8(CRC8_SEED, &buf[0..2])));
+    let mut reader = BitSliceReader::from_slice(&buf);
+    reader.read_bits::<3>().unwrap();
+    reader.start_crc(CRC8_SEED);
+    reader.peek_bit().unwrap();
+    reader.read_bits::<5>().unwrap();
+    reader.read_raw_char().unwrap();
+    assert_eq!(reader.finish_crc(), Some(crc8(CRC8_SEED, &buf[0..2])));
+}
+
+#[test]
+fn test_read_bytes() {
+    let buf: Vec<u8> = (0..200).map(|x| x as u8).collect();
+    let mut reader = BitSliceReader::from_slice(&buf);
+    let mut bytes = [0u8; 4];
+    reader.read_bytes(&mut bytes).unwrap();
+    assert_eq!(bytes, [0, 1, 2, 3]);
+    assert_eq!(reader.read_vec(150).unwrap(), buf[4..154]);
+    assert_eq!(reader.bit_position(), 154 * 8);
+
+    // Unaligned reads through an iterator
+    let mut reader = BitReader::new(buf.iter());
+    reader.read_bits::<4>().unwrap();
+    assert_eq!(reader.read_vec(2).unwrap(), [0x00, 0x10]);
+    assert_eq!(
+        reader.read_vec(1 << 20),
+        Err(DwgError::UnexpectedEof { at_bit: 200 * 8 })
+    );
+}
+
+#[test]
+fn test_read_dates() {
+    // Julian day 2440588 (1970-01-01) at 1.5 seconds past midnight
+    let mut fields = vec![(0b00, 2)];
+    for byte in 2440588i32.to_le_bytes() {
+        fields.push((byte as u64, 8));
+    }
+    fields.extend([(0b00, 2), (0xDC, 8), (0x05, 8), (0x00, 8), (0x00, 8)]);
+    // One day and 256 milliseconds
+    fields.extend([(0b01, 2), (1, 8), (0b11, 2)]);
+    let buf = pack_bits(&fields);
+    let mut reader = BitReader::new(buf.iter());
+    let date = reader.read_bl_date().unwrap();
+    assert_eq!(
+        date,
+        JulianDate {
+            day: 2440588,
+            ms: 1500
+        }
+    );
+    #[cfg(feature = "chrono")]
+    assert_eq!(
+        date.to_datetime().unwrap().and_utc().timestamp_millis(),
+        1500
+    );
+    assert_eq!(reader.read_timebll(), Ok(Duration::from_millis(86_400_256)));
 }
@@ -0,0 +1,984 @@
+//! A struct to write DWG datatypes to a byte buffer
+//!
+//! [`BitWriter`] is the counterpart of [`crate::bitcodes::BitReader`], each `write_` method
+//! writes a value that the `read_` method of the same name reads back. Where a datatype has
+//! several encodings of a value the shortest is chosen
+
+use std::time::Duration;
+
+use crate::{
+    crc::{crc32, crc8},
+    error::{DwgError, Result},
+    types::{
+        CmColor, CodePage, Color, EntityColor, HandleRef, JulianDate, Point2, Point3, RawBits,
+        RefType,
+    },
+    version::DWGVersion,
+};
+
+/// Writes DWG datatypes to an in memory buffer
+///
+/// Values are packed most significant bit first, as `BitReader` reads them. The last byte is
+/// padded with zero bits
+pub struct BitWriter {
+    buf: Vec<u8>,
+    /// Number of bits written
+    bit_len: u64,
+    /// Byte the running CRC8 starts at and its seed, set by `start_crc`
+    crc: Option<(usize, u16)>,
+    /// Byte the running CRC32 starts at and its seed, set by `start_crc32`
+    crc32: Option<(usize, u32)>,
+    version: DWGVersion,
+}
+
+impl Default for BitWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BitWriter {
+    /// Creates an empty `BitWriter`
+    ///
+    /// Assumes a Version of AC1015 (R2000) initially
+    pub fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            bit_len: 0,
+            crc: None,
+            crc32: None,
+            version: DWGVersion::AC1015,
+        }
+    }
+
+    /// Returns the number of bits written so far
+    pub fn bit_position(&self) -> u64 {
+        self.bit_len
+    }
+
+    /// Returns the bytes written so far, including a partially written final byte
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Returns the bytes written, including a partially written final byte
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+
+    pub fn get_version(&self) -> DWGVersion {
+        self.version
+    }
+
+    pub fn set_version(&mut self, version: DWGVersion) {
+        self.version = version
+    }
+
+    /// Writes zero bits up to the next byte boundary
+    pub fn align(&mut self) {
+        self.bit_len = self.buf.len() as u64 * 8;
+    }
+
+    /// Returns the index of the byte the next bit is written to
+    fn current_byte(&self) -> usize {
+        (self.bit_len / 8) as usize
+    }
+
+    /// Starts a CRC8 with the initial value `seed` over every byte written from now on, see
+    /// [`crate::crc::crc8`]
+    ///
+    /// If the writer is part way through a byte, that byte is included
+    pub fn start_crc(&mut self, seed: u16) {
+        self.crc = Some((self.current_byte(), seed));
+    }
+
+    /// Pads to a byte boundary and writes the CRC8 of every byte since `start_crc` as a RS,
+    /// returning the CRC written
+    ///
+    /// Fails with `CrcNotStarted` if `start_crc` was not called
+    pub fn write_crc(&mut self) -> Result<u16> {
+        let (start, seed) = self.crc.take().ok_or(DwgError::CrcNotStarted)?;
+        self.align();
+        let crc = crc8(seed, &self.buf[start..]);
+        self.write_raw_short(crc as i16);
+        Ok(crc)
+    }
+
+    /// Starts a CRC32 with the initial value `seed` over every byte written from now on, see
+    /// [`crate::crc::crc32`]
+    ///
+    /// If the writer is part way through a byte, that byte is included
+    pub fn start_crc32(&mut self, seed: u32) {
+        self.crc32 = Some((self.current_byte(), seed));
+    }
+
+    /// Pads to a byte boundary and writes the CRC32 of every byte since `start_crc32` as a
+    /// RL, returning the CRC written
+    ///
+    /// Fails with `CrcNotStarted` if `start_crc32` was not called
+    pub fn write_crc32(&mut self) -> Result<u32> {
+        let (start, seed) = self.crc32.take().ok_or(DwgError::CrcNotStarted)?;
+        self.align();
+        let crc = crc32(seed, &self.buf[start..]);
+        self.write_raw_long(crc as i32);
+        Ok(crc)
+    }
+
+    /// Writes a 16 byte sentinel, see [`crate::sentinels`] for the known values
+    pub fn write_sentinel(&mut self, sentinel: &[u8; 16]) {
+        self.write_bytes(sentinel);
+    }
+
+    /// Writes raw bytes
+    ///
+    /// Byte aligned writes are a single copy
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        if self.bit_len.is_multiple_of(8) {
+            self.buf.extend_from_slice(bytes);
+            self.bit_len += bytes.len() as u64 * 8;
+        } else {
+            for &byte in bytes {
+                self.write_bits_msb(byte as u32, 8);
+            }
+        }
+    }
+
+    /// Writes a run of bits from [`crate::bitcodes::BitReader::read_raw_bits`] unchanged
+    pub fn write_raw_bits(&mut self, bits: &RawBits) {
+        let bytes = bits.as_bytes();
+        let whole = (bits.bit_len() / 8) as usize;
+        self.write_bytes(&bytes[..whole]);
+        let rem = (bits.bit_len() % 8) as u32;
+        if rem != 0 {
+            self.write_bits_msb((bytes[whole] >> (8 - rem)) as u32, rem);
+        }
+    }
+
+    /// Writes the low `n` bits of `value`, the counterpart of
+    /// [`crate::bitcodes::BitReader::read_bits_dyn`]
+    ///
+    /// Writes of up to 8 bits are written most significant bit first. Longer writes are
+    /// whole bytes in little endian order followed by the remaining `n % 8` most significant
+    /// bits. Fails with `ValueOutOfRange` if `n` is greater than 64
+    pub fn write_bits_dyn(&mut self, value: u64, n: u32) -> Result<()> {
+        if n > u64::BITS {
+            return Err(self.out_of_range());
+        }
+        self.write_bits_le(value, n);
+        Ok(())
+    }
+
+    /// Writes the low `n` bits of `value`, up to 64, as [`BitWriter::write_bits_dyn`]
+    fn write_bits_le(&mut self, value: u64, n: u32) {
+        debug_assert!(n <= u64::BITS);
+        if n <= 8 {
+            self.write_bits_msb(value as u32, n);
+            return;
+        }
+        let n_bytes = n / 8;
+        for i in 0..n_bytes {
+            self.write_bits_msb((value >> (8 * i)) as u32 & 0xFF, 8);
+        }
+        let rem = n % 8;
+        if rem != 0 {
+            self.write_bits_msb((value >> (8 * n_bytes)) as u32, rem);
+        }
+    }
+
+    /// Writes the low `n` bits of `value`, up to 8, most significant bit first
+    fn write_bits_msb(&mut self, value: u32, n: u32) {
+        debug_assert!(n <= 8);
+        let mut n = n;
+        while n > 0 {
+            let used = (self.bit_len % 8) as u32;
+            if used == 0 {
+                self.buf.push(0);
+            }
+            let free = 8 - used;
+            let count = n.min(free);
+            let bits = (value >> (n - count)) & ((1 << count) - 1);
+            *self.buf.last_mut().expect("a byte was pushed") |= (bits << (free - count)) as u8;
+            n -= count;
+            self.bit_len += count as u64;
+        }
+    }
+
+    /// Overwrites a raw long (RL) written earlier at bit `at_bit`, such as a size that is
+    /// only known once the data it covers has been written
+    ///
+    /// Fails with `SeekOutOfRange` if the RL would extend past the bits written so far
+    pub fn set_raw_long_at(&mut self, at_bit: u64, value: i32) -> Result<()> {
+        if at_bit.saturating_add(32) > self.bit_len {
+            return Err(DwgError::SeekOutOfRange { offset: at_bit });
+        }
+        for (i, byte) in value.to_le_bytes().into_iter().enumerate() {
+            for bit in 0..8 {
+                let pos = at_bit + i as u64 * 8 + bit;
+                let mask = 0x80 >> (pos % 8);
+                let target = &mut self.buf[(pos / 8) as usize];
+                if byte & (0x80 >> bit) != 0 {
+                    *target |= mask;
+                } else {
+                    *target &= !mask;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The error for a value that does not fit in the datatype being written
+    fn out_of_range(&self) -> DwgError {
+        DwgError::ValueOutOfRange {
+            at_bit: self.bit_len,
+        }
+    }
+
+    /// Writes the low bit of `bit`
+    pub fn write_bit(&mut self, bit: u8) {
+        self.write_bits_msb((bit & 1) as u32, 1);
+    }
+
+    /// Writes a bit triplet (3B)
+    ///
+    /// Fails with `ValueOutOfRange` if `value` is not one of the values a triplet can hold,
+    /// 0, 2, 6 or 7
+    pub fn write_bit_triplet(&mut self, value: u8) -> Result<()> {
+        match value {
+            0b0 => self.write_bits_msb(0b0, 1),
+            0b10 => self.write_bits_msb(0b10, 2),
+            0b110 => self.write_bits_msb(0b110, 3),
+            0b111 => self.write_bits_msb(0b111, 3),
+            _ => return Err(self.out_of_range()),
+        }
+        Ok(())
+    }
+
+    pub fn write_bitshort(&mut self, value: i16) {
+        match value {
+            0 => self.write_bits_msb(0x2, 2),
+            256 => self.write_bits_msb(0x3, 2),
+            1..=255 => {
+                self.write_bits_msb(0x1, 2);
+                self.write_bits_msb(value as u32, 8);
+            }
+            _ => {
+                self.write_bits_msb(0x0, 2);
+                self.write_raw_short(value);
+            }
+        }
+    }
+
+    pub fn write_bitlong(&mut self, value: i32) {
+        match value {
+            0 => self.write_bits_msb(0x2, 2),
+            1..=255 => {
+                self.write_bits_msb(0x1, 2);
+                self.write_bits_msb(value as u32, 8);
+            }
+            _ => {
+                self.write_bits_msb(0x0, 2);
+                self.write_raw_long(value);
+            }
+        }
+    }
+
+    /// Writes a bitlonglong (BLL), a 3 bit count of bytes followed by that many bytes
+    ///
+    /// Fails with `ValueOutOfRange` if `value` needs all 8 bytes, which the count cannot hold
+    pub fn write_bitlonglong(&mut self, value: i64) -> Result<()> {
+        let value = value as u64;
+        let len = (u64::BITS - value.leading_zeros()).div_ceil(8);
+        if len >= 8 {
+            return Err(self.out_of_range());
+        }
+        self.write_bits_msb(len, 3);
+        self.write_bits_le(value, len * 8);
+        Ok(())
+    }
+
+    pub fn write_bitdouble(&mut self, value: f64) {
+        // Compare bits so that -0.0 is not written as 0.0
+        if value.to_bits() == 1.0f64.to_bits() {
+            self.write_bits_msb(0x1, 2);
+        } else if value.to_bits() == 0 {
+            self.write_bits_msb(0x2, 2);
+        } else {
+            self.write_bits_msb(0x0, 2);
+            self.write_raw_double(value);
+        }
+    }
+
+    /// Writes a signed modular char (MC)
+    pub fn write_modular_char(&mut self, value: i32) {
+        self.write_signed_modular_char(value as i64);
+    }
+
+    /// Writes a signed modular char (MC) as an offset between handles or file positions,
+    /// the counterpart of [`crate::bitcodes::BitReader::read_handle_offset`]
+    pub fn write_handle_offset(&mut self, value: i64) {
+        self.write_signed_modular_char(value);
+    }
+
+    fn write_signed_modular_char(&mut self, value: i64) {
+        let mut magnitude = value.unsigned_abs();
+        // The final byte holds 6 bits and the sign bit
+        while magnitude > 0x3F {
+            self.write_bits_msb((magnitude & 0x7F) as u32 | 0x80, 8);
+            magnitude >>= 7;
+        }
+        let sign = if value < 0 { 0x40 } else { 0 };
+        self.write_bits_msb(magnitude as u32 | sign, 8);
+    }
+
+    /// Writes an unsigned modular char (UMC)
+    pub fn write_unsigned_modular_char(&mut self, value: u32) {
+        self.write_modular_char_u64(value as u64);
+    }
+
+    /// Writes an unsigned modular char (UMC) that may be wider than 32 bits
+    pub fn write_modular_char_u64(&mut self, value: u64) {
+        let mut value = value;
+        while value > 0x7F {
+            self.write_bits_msb((value & 0x7F) as u32 | 0x80, 8);
+            value >>= 7;
+        }
+        self.write_bits_msb(value as u32, 8);
+    }
+
+    /// Writes a signed modular short (MS), the counterpart of
+    /// [`crate::bitcodes::BitReader::read_modular_short`]
+    pub fn write_signed_modular_short(&mut self, value: i32) {
+        let mut magnitude = value.unsigned_abs();
+        // The final short holds 14 bits and the sign bit
+        while magnitude > 0x3FFF {
+            self.write_bits_le((magnitude & 0x7FFF | 0x8000) as u64, 16);
+            magnitude >>= 15;
+        }
+        let sign = if value < 0 { 0x4000 } else { 0 };
+        self.write_bits_le((magnitude | sign) as u64, 16);
+    }
+
+    /// Writes an unsigned modular short (MS), such as the size of an object
+    pub fn write_modular_short(&mut self, value: u32) {
+        self.write_modular_short_u64(value as u64);
+    }
+
+    /// Writes a modular short (MS) that may be wider than 32 bits
+    pub fn write_modular_short_u64(&mut self, value: u64) {
+        let mut value = value;
+        while value > 0x7FFF {
+            self.write_bits_le(value & 0x7FFF | 0x8000, 16);
+            value >>= 15;
+        }
+        self.write_bits_le(value, 16);
+    }
+
+    pub fn write_raw_char(&mut self, value: i8) {
+        self.write_bits_msb(value as u8 as u32, 8);
+    }
+
+    pub fn write_raw_short(&mut self, value: i16) {
+        self.write_bits_le(value as u16 as u64, 16);
+    }
+
+    pub fn write_raw_long(&mut self, value: i32) {
+        self.write_bits_le(value as u32 as u64, 32);
+    }
+
+    pub fn write_raw_longlong(&mut self, value: i64) {
+        self.write_bits_le(value as u64, 64);
+    }
+
+    pub fn write_raw_double(&mut self, value: f64) {
+        self.write_bits_le(value.to_bits(), 64);
+    }
+
+    /// Writes two raw doubles (2RD)
+    pub fn write_2rd(&mut self, point: Point2) {
+        self.write_raw_double(point.x);
+        self.write_raw_double(point.y);
+    }
+
+    /// Writes three raw doubles (3RD)
+    pub fn write_3rd(&mut self, point: Point3) {
+        self.write_raw_double(point.x);
+        self.write_raw_double(point.y);
+        self.write_raw_double(point.z);
+    }
+
+    /// Writes two bitdoubles (2BD)
+    pub fn write_2bd(&mut self, point: Point2) {
+        self.write_bitdouble(point.x);
+        self.write_bitdouble(point.y);
+    }
+
+    /// Writes three bitdoubles (3BD)
+    pub fn write_3bd(&mut self, point: Point3) {
+        self.write_bitdouble(point.x);
+        self.write_bitdouble(point.y);
+        self.write_bitdouble(point.z);
+    }
+
+    /// Writes a bit extrusion (BE)
+    ///
+    /// For AC1015 (R2000) and later an extrusion of (0, 0, 1) is written as a single bit
+    pub fn write_bit_extrusion(&mut self, extrusion: Point3) {
+        if self.version >= DWGVersion::AC1015 {
+            let Point3 { x, y, z } = extrusion;
+            let is_default = x == 0.0 && y == 0.0 && z == 1.0;
+            self.write_bit(is_default as u8);
+            if is_default {
+                return;
+            }
+        }
+        self.write_3bd(extrusion);
+    }
+
+    /// Writes a bitdouble with default (DD), patching only the bytes that differ from
+    /// `default`
+    pub fn write_dd(&mut self, value: f64, default: f64) {
+        let bytes = value.to_le_bytes();
+        let default_bytes = default.to_le_bytes();
+        if bytes == default_bytes {
+            self.write_bits_msb(0x0, 2);
+        } else if bytes[4..] == default_bytes[4..] {
+            self.write_bits_msb(0x1, 2);
+            self.write_bytes(&bytes[0..4]);
+        } else if bytes[6..] == default_bytes[6..] {
+            self.write_bits_msb(0x2, 2);
+            self.write_bytes(&bytes[4..6]);
+            self.write_bytes(&bytes[0..4]);
+        } else {
+            self.write_bits_msb(0x3, 2);
+            self.write_raw_double(value);
+        }
+    }
+
+    /// Writes a bit thickness (BT)
+    ///
+    /// For AC1015 (R2000) and later a thickness of 0.0 is written as a single bit
+    pub fn write_bit_thickness(&mut self, value: f64) {
+        if self.version >= DWGVersion::AC1015 {
+            let is_zero = value.to_bits() == 0;
+            self.write_bit(is_zero as u8);
+            if is_zero {
+                return;
+            }
+        }
+        self.write_bitdouble(value);
+    }
+
+    pub fn write_cm_color_short(&mut self, value: i16) {
+        self.write_bitshort(value);
+    }
+
+    /// Writes a full color value (CMC), see [`crate::bitcodes::BitReader::read_cm_color`]
+    ///
+    /// The color is converted if it was read from a version that stores it differently:
+    /// before AC1018 (R2004) only the index of the color is written, later versions write
+    /// the index as 0 followed by the color method and RGB value. Names are encoded with
+    /// `codepage` as in [`BitWriter::write_text`]
+    pub fn write_cm_color(&mut self, value: &CmColor, codepage: CodePage) -> Result<()> {
+        if self.version < DWGVersion::AC1018 {
+            self.write_bitshort(value.color().index());
+            return Ok(());
+        }
+        if value.method() == 0 {
+            return self.write_cm_color(&CmColor::from_color(value.color()), codepage);
+        }
+        self.write_bitshort(0);
+        self.write_bitlong(value.rgb as i32);
+        self.write_raw_char(value.flags as i8);
+        if let (true, Some(name)) = (value.flags & 0x1 != 0, &value.name) {
+            self.write_text(name, codepage)?;
+        }
+        if let (true, Some(book_name)) = (value.flags & 0x2 != 0, &value.book_name) {
+            self.write_text(book_name, codepage)?;
+        }
+        Ok(())
+    }
+
+    /// Writes an entity color (ENC), see [`crate::bitcodes::BitReader::read_entity_color`]
+    ///
+    /// Before AC1018 (R2004) only the color index is written, so true colors are replaced by
+    /// their closest index and the transparency and color handle flag are dropped
+    pub fn write_entity_color(&mut self, value: &EntityColor) {
+        if self.version < DWGVersion::AC1018 {
+            self.write_bitshort(value.color.index());
+            return;
+        }
+        let mut raw = value.color.index() as u16 & 0x1FF;
+        if matches!(value.color, Color::TrueColor { .. }) {
+            raw |= 0x8000;
+        }
+        if value.has_color_handle {
+            raw |= 0x4000;
+        }
+        if value.transparency.is_some() {
+            raw |= 0x2000;
+        }
+        self.write_bitshort(raw as i16);
+        if let Color::TrueColor { rgb, .. } = value.color {
+            self.write_bitlong((0xC2000000 | rgb & 0xFFFFFF) as i32);
+        }
+        if let Some(transparency) = value.transparency {
+            self.write_bitlong(transparency.to_raw() as i32);
+        }
+    }
+
+    /// Writes an object type (OT)
+    ///
+    /// Before AC1024 (R2010) this is a BS. Later versions use a 2 bit flag to store types
+    /// from 0 to 0xFF or from 0x1F0 to 0x2EF in a single byte
+    pub fn write_object_type(&mut self, value: i16) {
+        if self.version <= DWGVersion::AC1021 {
+            self.write_bitshort(value);
+            return;
+        }
+        match value {
+            0..=0xFF => {
+                self.write_bits_msb(0x0, 2);
+                self.write_raw_char(value as u8 as i8);
+            }
+            0x1F0..=0x2EF => {
+                self.write_bits_msb(0x1, 2);
+                self.write_raw_char((value - 0x1F0) as u8 as i8);
+            }
+            _ => {
+                self.write_bits_msb(0x2, 2);
+                self.write_raw_short(value);
+            }
+        }
+    }
+
+    /// Writes a handle reference (H)
+    ///
+    /// Only as many handle bytes as are needed are written, and none for the +1 and -1 codes
+    pub fn write_handle(&mut self, handle: HandleRef) {
+        let counter = match handle.code {
+            RefType::PlusOne | RefType::MinusOne => 0,
+            _ => (u64::BITS - handle.handle.leading_zeros()).div_ceil(8),
+        };
+        self.write_bits_msb((handle.code as u32) << 4 | counter, 8);
+        for i in (0..counter).rev() {
+            self.write_bits_msb((handle.handle >> (8 * i)) as u32 & 0xFF, 8);
+        }
+    }
+
+    /// Writes variable text (TV), a BS length followed by the text encoded in `codepage`
+    /// and a null terminator
+    ///
+    /// For AC1021 (R2007) and later, strings are unicode (TU) and this defers to
+    /// [`BitWriter::write_text_unicode`]. Fails with `ValueOutOfRange` if the encoded text is
+    /// longer than a BS length can hold
+    pub fn write_text(&mut self, text: &str, codepage: CodePage) -> Result<()> {
+        if self.version >= DWGVersion::AC1021 {
+            return self.write_text_unicode(text);
+        }
+        if text.is_empty() {
+            self.write_bitshort(0);
+            return Ok(());
+        }
+        let mut bytes = codepage.encode(text);
+        bytes.push(0);
+        let len = u16::try_from(bytes.len()).map_err(|_| self.out_of_range())?;
+        self.write_bitshort(len as i16);
+        self.write_bytes(&bytes);
+        Ok(())
+    }
+
+    /// Writes unicode text (TU), a BS length followed by UTF-16LE code units and a null
+    /// terminator
+    ///
+    /// Fails with `ValueOutOfRange` if the text is longer than a BS length can hold
+    pub fn write_text_unicode(&mut self, text: &str) -> Result<()> {
+        if text.is_empty() {
+            self.write_bitshort(0);
+            return Ok(());
+        }
+        let units: Vec<u16> = text.encode_utf16().chain([0]).collect();
+        let len = u16::try_from(units.len()).map_err(|_| self.out_of_range())?;
+        self.write_bitshort(len as i16);
+        for unit in units {
+            self.write_bits_le(unit as u64, 16);
+        }
+        Ok(())
+    }
+
+    /// Writes a date as two BLs, the Julian day followed by milliseconds since midnight
+    pub fn write_bl_date(&mut self, date: JulianDate) {
+        self.write_bitlong(date.day);
+        self.write_bitlong(date.ms);
+    }
+
+    /// Writes an elapsed time (TIMEBLL) as two BLs, the number of days followed by
+    /// milliseconds
+    ///
+    /// Fails with `ValueOutOfRange` if the number of days does not fit in a BL
+    pub fn write_timebll(&mut self, time: Duration) -> Result<()> {
+        let days = i32::try_from(time.as_secs() / 86_400).map_err(|_| self.out_of_range())?;
+        let ms = (time.as_millis() % 86_400_000) as i32;
+        self.write_bitlong(days);
+        self.write_bitlong(ms);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_write_bits() {
+    let mut writer = BitWriter::new();
+    writer.write_bit(1);
+    writer.write_bits_dyn(0b011, 3).unwrap();
+    writer.write_raw_short(0x1234);
+    assert_eq!(writer.bit_position(), 20);
+    assert_eq!(writer.as_bytes(), [0b1011_0011, 0b0100_0001, 0b0010_0000]);
+    writer.align();
+    writer.write_bytes(&[0xAB]);
+    assert_eq!(
+        writer.into_bytes(),
+        [0b1011_0011, 0b0100_0001, 0b0010_0000, 0xAB]
+    );
+}
+
+#[test]
+fn test_write_round_trip() {
+    use crate::bitcodes::BitSliceReader;
+
+    let shorts = [0, 1, 255, 256, 257, -1, i16::MIN];
+    let longs = [0, 1, 255, 256, -1, i32::MAX];
+    let longlongs = [0, 1, 256, 1 << 40, (1 << 56) - 1];
+    let doubles = [0.0, -0.0, 1.0, 2.5, f64::MAX];
+    let modular = [0, 1, 0x3F, 0x40, 4610, -4610, i32::MAX, i32::MIN];
+    let unsigned_modular = [0u64, 0x7F, 0x80, 1 << 35, u64::MAX];
+    let triplets = [0, 2, 6, 7];
+
+    for version in [DWGVersion::AC1014, DWGVersion::AC1015, DWGVersion::AC1024] {
+        let mut writer = BitWriter::new();
+        writer.set_version(version);
+        writer.write_bit(1);
+        shorts.iter().for_each(|&x| writer.write_bitshort(x));
+        longs.iter().for_each(|&x| writer.write_bitlong(x));
+        longlongs
+            .iter()
+            .for_each(|&x| writer.write_bitlonglong(x).unwrap());
+        doubles.iter().for_each(|&x| writer.write_bitdouble(x));
+        modular.iter().for_each(|&x| writer.write_modular_char(x));
+        writer.write_handle_offset(-(1 << 40));
+        unsigned_modular
+            .iter()
+            .for_each(|&x| writer.write_modular_char_u64(x));
+        unsigned_modular
+            .iter()
+            .for_each(|&x| writer.write_modular_short_u64(x));
+        triplets
+            .iter()
+            .for_each(|&x| writer.write_bit_triplet(x).unwrap());
+        doubles.iter().for_each(|&x| writer.write_dd(x, 2.0));
+        doubles.iter().for_each(|&x| writer.write_bit_thickness(x));
+        writer.write_bit_extrusion(Point3::from((0.0, 0.0, 1.0)));
+        writer.write_bit_extrusion(Point3::from((1.0, 0.5, 0.0)));
+        writer.write_3bd(Point3::from((1.0, 2.0, 3.0)));
+        writer.write_2rd(Point2::from((1.0, 2.0)));
+        [1, 0x1F5, 0x300]
+            .iter()
+            .for_each(|&x| writer.write_object_type(x));
+        writer.write_bl_date(JulianDate {
+            day: 2451545,
+            ms: 1000,
+        });
+        writer
+            .write_timebll(Duration::from_millis(86_400_001))
+            .unwrap();
+        let buf = writer.into_bytes();
+
+        let mut reader = BitSliceReader::from_slice(&buf);
+        reader.set_version(version);
+        assert_eq!(reader.read_bit(), Ok(1));
+        for &x in &shorts {
+            assert_eq!(reader.read_bitshort(), Ok(x));
+        }
+        for &x in &longs {
+            assert_eq!(reader.read_bitlong(), Ok(x));
+        }
+        for &x in &longlongs {
+            assert_eq!(reader.read_bitlonglong(), Ok(x));
+        }
+        for &x in &doubles {
+            assert_eq!(reader.read_bitdouble().map(f64::to_bits), Ok(x.to_bits()));
+        }
+        for &x in &modular {
+            assert_eq!(reader.read_modular_char(), Ok(x));
+        }
+        assert_eq!(reader.read_handle_offset(), Ok(-(1 << 40)));
+        for &x in &unsigned_modular {
+            assert_eq!(reader.read_modular_char_u64(), Ok(x));
+        }
+        for &x in &unsigned_modular {
+            assert_eq!(reader.read_modular_short_u64(), Ok(x));
+        }
+        for &x in &triplets {
+            assert_eq!(reader.read_bit_triplet(), Ok(x));
+        }
+        for &x in &doubles {
+            assert_eq!(reader.read_dd(2.0).map(f64::to_bits), Ok(x.to_bits()));
+        }
+        for &x in &doubles {
+            assert_eq!(reader.read_bit_thickness(), Ok(x));
+        }
+        assert_eq!(
+            reader.read_bit_extrusion(),
+            Ok(Point3::from((0.0, 0.0, 1.0)))
+        );
+        assert_eq!(
+            reader.read_bit_extrusion(),
+            Ok(Point3::from((1.0, 0.5, 0.0)))
+        );
+        assert_eq!(reader.read_3bd(), Ok(Point3::from((1.0, 2.0, 3.0))));
+        assert_eq!(reader.read_2rd(), Ok(Point2::from((1.0, 2.0))));
+        for x in [1, 0x1F5, 0x300] {
+            assert_eq!(reader.read_object_type(), Ok(x));
+        }
+        assert_eq!(
+            reader.read_bl_date(),
+            Ok(JulianDate {
+                day: 2451545,
+                ms: 1000
+            })
+        );
+        assert_eq!(reader.read_timebll(), Ok(Duration::from_millis(86_400_001)));
+    }
+}
+
+#[test]
+fn test_write_handle_and_text() {
+    use crate::bitcodes::BitSliceReader;
+
+    let handles = [
+        HandleRef {
+            code: RefType::HardPointer,
+            handle: 0x1F,
+        },
+        HandleRef {
+            code: RefType::Unspecified,
+            handle: 0,
+        },
+        HandleRef {
+            code: RefType::SoftOwned,
+            handle: 0x0102030405060708,
+        },
+        HandleRef {
+            code: RefType::PlusOne,
+            handle: 0,
+        },
+    ];
+    let texts = ["", "Layer \u{B0}", "\u{410}"];
+
+    for version in [DWGVersion::AC1015, DWGVersion::AC1021] {
+        let mut writer = BitWriter::new();
+        writer.set_version(version);
+        writer.write_bit(1);
+        handles.iter().for_each(|&x| writer.write_handle(x));
+        for text in texts {
+            writer.write_text(text, CodePage::ANSI1251).unwrap();
+        }
+        let buf = writer.into_bytes();
+
+        let mut reader = BitSliceReader::from_slice(&buf);
+        reader.set_version(version);
+        reader.set_codepage(CodePage::ANSI1251);
+        reader.read_bit().unwrap();
+        for &x in &handles {
+            assert_eq!(reader.read_handle(), Ok(x));
+        }
+        for x in texts {
+            assert_eq!(reader.read_text(), Ok(String::from(x)));
+        }
+    }
+
+    let mut writer = BitWriter::new();
+    writer.write_handle(handles[0]);
+    assert_eq!(writer.as_bytes(), [0x51, 0x1F]);
+}
+
+#[test]
+fn test_write_crc_and_sentinel() {
+    use crate::{bitcodes::BitSliceReader, crc::CRC8_SEED, sentinels};
+
+    let mut writer = BitWriter::new();
+    writer.write_sentinel(&sentinels::HEADER_VARIABLES_START);
+    writer.start_crc(CRC8_SEED);
+    writer.start_crc32(0);
+    writer.write_raw_long(5);
+    writer.write_bitshort(100);
+    let crc = writer.write_crc().unwrap();
+    writer.write_sentinel(&sentinels::HEADER_VARIABLES_END);
+    let crc32 = writer.write_crc32().unwrap();
+    let buf = writer.into_bytes();
+
+    let mut reader = BitSliceReader::from_slice(&buf);
+    reader
+        .read_sentinel(&sentinels::HEADER_VARIABLES_START)
+        .unwrap();
+    reader.start_crc(CRC8_SEED);
+    reader.read_raw_long().unwrap();
+    reader.read_bitshort().unwrap();
+    // The padding bits of the last byte are included in the CRC
+    reader.read_bits_dyn(6).unwrap();
+    assert_eq!(reader.finish_crc(), Some(crc));
+    assert_eq!(reader.read_raw_short(), Ok(crc as i16));
+    reader
+        .read_sentinel(&sentinels::HEADER_VARIABLES_END)
+        .unwrap();
+    let end = buf.len() - 4;
+    assert_eq!(crc32, crate::crc::crc32(0, &buf[16..end]));
+    assert_eq!(buf[end..], crc32.to_le_bytes());
+}
+
+#[test]
+fn test_write_text_too_long() {
+    // With its null terminator the longest text a BS length can hold is 65534 units
+    let longest = "a".repeat(0xFFFE);
+    let too_long = "a".repeat(0xFFFF);
+    for version in [DWGVersion::AC1015, DWGVersion::AC1021] {
+        let mut writer = BitWriter::new();
+        writer.set_version(version);
+        writer.write_text(&longest, CodePage::ANSI1252).unwrap();
+        let at_bit = writer.bit_position();
+        assert_eq!(
+            writer.write_text(&too_long, CodePage::ANSI1252),
+            Err(DwgError::ValueOutOfRange { at_bit })
+        );
+        assert_eq!(writer.bit_position(), at_bit);
+    }
+
+    let mut writer = BitWriter::new();
+    writer.set_version(DWGVersion::AC1018);
+    let color = CmColor {
+        flags: 0x1,
+        name: Some(too_long),
+        ..CmColor::from_color(Color::TrueColor {
+            rgb: 0x102030,
+            aci: 1,
+        })
+    };
+    assert!(matches!(
+        writer.write_cm_color(&color, CodePage::ANSI1252),
+        Err(DwgError::ValueOutOfRange { .. })
+    ));
+}
+
+#[test]
+fn test_set_raw_long_at() {
+    let mut writer = BitWriter::new();
+    writer.write_bit(1);
+    writer.write_raw_long(0);
+    writer.write_bit(1);
+    writer.set_raw_long_at(1, 0x12345678).unwrap();
+    let buf = writer.into_bytes();
+    let mut reader = crate::bitcodes::BitSliceReader::from_slice(&buf);
+    assert_eq!(reader.read_bit(), Ok(1));
+    assert_eq!(reader.read_raw_long(), Ok(0x12345678));
+    assert_eq!(reader.read_bit(), Ok(1));
+}
+
+#[test]
+fn test_write_out_of_range() {
+    let out_of_range = Err(DwgError::ValueOutOfRange { at_bit: 1 });
+    let mut writer = BitWriter::new();
+    writer.write_bit(1);
+    assert_eq!(writer.write_bit_triplet(0b1), out_of_range);
+    assert_eq!(writer.write_bitlonglong(1 << 56), out_of_range);
+    assert_eq!(writer.write_bitlonglong(-1), out_of_range);
+    let days = Duration::from_secs(86_400 * (i32::MAX as u64 + 1));
+    assert_eq!(writer.write_timebll(days), out_of_range);
+    assert_eq!(writer.write_bits_dyn(0, 65), out_of_range);
+    assert_eq!(
+        writer.set_raw_long_at(0, 0),
+        Err(DwgError::SeekOutOfRange { offset: 0 })
+    );
+    assert_eq!(writer.write_crc(), Err(DwgError::CrcNotStarted));
+    assert_eq!(writer.write_crc32(), Err(DwgError::CrcNotStarted));
+    // Nothing is written by a failed write
+    assert_eq!(writer.into_bytes(), [0x80]);
+}
+
+#[test]
+fn test_write_raw_bits() {
+    use crate::bitcodes::BitSliceReader;
+
+    let buf = [0xAB, 0xCD, 0xEF];
+    let mut reader = BitSliceReader::from_slice(&buf);
+    reader.read_bits_dyn(3).unwrap();
+    let bits = reader.read_raw_bits(13).unwrap();
+
+    let mut writer = BitWriter::new();
+    writer.write_bits_dyn(0b101, 3).unwrap();
+    writer.write_raw_bits(&bits);
+    assert_eq!(writer.as_bytes(), &buf[..2]);
+}
+
+#[test]
+fn test_write_colors() {
+    use crate::{bitcodes::BitSliceReader, types::Transparency};
+
+    let cm_colors = [
+        CmColor {
+            index: 3,
+            ..Default::default()
+        },
+        CmColor {
+            rgb: 0xC2123456,
+            flags: 0x3,
+            name: Some(String::from("name")),
+            book_name: Some(String::from("book")),
+            ..Default::default()
+        },
+    ];
+    let entity_colors = [
+        EntityColor {
+            color: Color::ByLayer,
+            transparency: None,
+            has_color_handle: false,
+        },
+        EntityColor {
+            color: Color::TrueColor {
+                rgb: 0x123456,
+                aci: 5,
+            },
+            transparency: Some(Transparency::Alpha(0x80)),
+            has_color_handle: true,
+        },
+    ];
+    for version in [DWGVersion::AC1015, DWGVersion::AC1018] {
+        let mut writer = BitWriter::new();
+        writer.set_version(version);
+        for color in &cm_colors {
+            writer.write_cm_color(color, CodePage::ANSI1252).unwrap();
+        }
+        for color in &entity_colors {
+            writer.write_entity_color(color);
+        }
+        let buf = writer.into_bytes();
+        let mut reader = BitSliceReader::from_slice(&buf);
+        reader.set_version(version);
+        let old = version < DWGVersion::AC1018;
+
+        let color = reader.read_cm_color().unwrap();
+        assert_eq!(color.color(), Color::Index(3));
+        let color = reader.read_cm_color().unwrap();
+        if old {
+            // True colors cannot be stored, so fall back to white
+            assert_eq!(color.index, 7);
+        } else {
+            assert_eq!(color, cm_colors[1]);
+        }
+
+        assert_eq!(reader.read_entity_color(), Ok(entity_colors[0]));
+        let color = reader.read_entity_color().unwrap();
+        if old {
+            assert_eq!(color.color, Color::Index(5));
+        } else {
+            assert_eq!(color, entity_colors[1]);
+        }
+    }
+}
@@ -0,0 +1,1029 @@
+//! Creating drawings from scratch and adding entities to them
+//!
+//! [`Dwg::new`] creates the smallest drawing AutoCAD considers complete: the symbol tables
+//! with their standard entries, the model and paper space blocks and the dictionaries every
+//! drawing is expected to have. Entities are then added to model space with methods such as
+//! [`Dwg::add_line`], which also work on drawings that were read from a file
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    bitcodes_write::BitWriter,
+    classes::{write_classes, DwgClass, OBJECT_CLASS_ID},
+    decode::DwgDecode,
+    dwg::Dwg,
+    dwg_write::is_writable,
+    entities::{Block, EndBlk, Line, Text},
+    error::{DwgError, Result},
+    objects::{
+        decode_object, encode_object, object_types, open_object, AppId, BlockHeader,
+        CommonEntityData, ControlObject, Dictionary, DwgEncode, Layer, Linetype, MlineStyle,
+        ObjectHeader, PartialObject, TableEntry, TextStyle,
+    },
+    types::{CmColor, CodePage, Color, HandleRef, JulianDate, Point2, Point3, RefType},
+    version::DWGVersion,
+};
+
+/// Handles of the objects created by [`Dwg::new`]
+mod handles {
+    pub const BLOCK_CONTROL: u64 = 0x1;
+    pub const LAYER_CONTROL: u64 = 0x2;
+    pub const STYLE_CONTROL: u64 = 0x3;
+    pub const LTYPE_CONTROL: u64 = 0x5;
+    pub const VIEW_CONTROL: u64 = 0x6;
+    pub const UCS_CONTROL: u64 = 0x7;
+    pub const VPORT_CONTROL: u64 = 0x8;
+    pub const APPID_CONTROL: u64 = 0x9;
+    pub const DIMSTYLE_CONTROL: u64 = 0xA;
+    pub const VP_ENT_HDR_CONTROL: u64 = 0xB;
+    pub const NAMED_OBJECTS: u64 = 0xC;
+    pub const GROUPS: u64 = 0xD;
+    pub const MLINESTYLES: u64 = 0xE;
+    pub const LAYER_0: u64 = 0xF;
+    pub const STYLE_STANDARD: u64 = 0x10;
+    pub const APPID_ACAD: u64 = 0x11;
+    pub const LTYPE_BYBLOCK: u64 = 0x12;
+    pub const LTYPE_BYLAYER: u64 = 0x13;
+    pub const LTYPE_CONTINUOUS: u64 = 0x14;
+    pub const MODEL_SPACE: u64 = 0x15;
+    pub const PAPER_SPACE: u64 = 0x16;
+    pub const MODEL_SPACE_BLOCK: u64 = 0x17;
+    pub const MODEL_SPACE_ENDBLK: u64 = 0x18;
+    pub const PAPER_SPACE_BLOCK: u64 = 0x19;
+    pub const PAPER_SPACE_ENDBLK: u64 = 0x1A;
+    pub const MLINESTYLE_STANDARD: u64 = 0x1B;
+    pub const LAYOUTS: u64 = 0x1C;
+    pub const PLOT_SETTINGS: u64 = 0x1D;
+    pub const PLOT_STYLES: u64 = 0x1E;
+    /// The first handle free for other objects
+    pub const FIRST_FREE: u64 = 0x1F;
+}
+
+/// Types of the control objects of the tables in [`Dwg::handle_records`], starting from
+/// record 1
+const TABLE_CONTROLS: [i16; 10] = [
+    object_types::BLOCK_CONTROL,
+    object_types::LAYER_CONTROL,
+    object_types::STYLE_CONTROL,
+    object_types::LTYPE_CONTROL,
+    object_types::VIEW_CONTROL,
+    object_types::UCS_CONTROL,
+    object_types::VPORT_CONTROL,
+    object_types::APPID_CONTROL,
+    object_types::DIMSTYLE_CONTROL,
+    object_types::VP_ENT_HDR_CONTROL,
+];
+
+const Z_AXIS: Point3 = Point3 {
+    x: 0.0,
+    y: 0.0,
+    z: 1.0,
+};
+
+impl Dwg {
+    /// Creates a drawing ready to have entities added to it
+    ///
+    /// The drawing holds layer "0", the "Standard" text style, the ByBlock, ByLayer and
+    /// Continuous linetypes, the ACAD application, the model and paper space blocks and the
+    /// named object dictionary with the group, layout, multiline style, plot settings and
+    /// plot style dictionaries, along with default header variables and the classes of a new
+    /// AutoCAD drawing. HANDSEED in the header variables is the first handle after these
+    /// objects. Only AC1015 (R2000) and AC1018 (R2004) drawings can be written, other
+    /// versions fail with `UnsupportedVersion`
+    pub fn new(version: DWGVersion) -> Result<Dwg> {
+        if !is_writable(version) {
+            return Err(DwgError::UnsupportedVersion {
+                found: format!("{version:?}"),
+            });
+        }
+        let mut dwg = Dwg::empty(version);
+        dwg.handle_records = [
+            handles::FIRST_FREE,
+            handles::BLOCK_CONTROL,
+            handles::LAYER_CONTROL,
+            handles::STYLE_CONTROL,
+            handles::LTYPE_CONTROL,
+            handles::VIEW_CONTROL,
+            handles::UCS_CONTROL,
+            handles::VPORT_CONTROL,
+            handles::APPID_CONTROL,
+            handles::DIMSTYLE_CONTROL,
+            handles::VP_ENT_HDR_CONTROL,
+            handles::NAMED_OBJECTS,
+            handles::MLINESTYLES,
+            handles::GROUPS,
+        ];
+        dwg.header_vars = write_header_vars(version, dwg.codepage, handles::FIRST_FREE)?;
+        dwg.classes = write_classes(&standard_classes(), version, dwg.codepage)?;
+        dwg.add_tables()?;
+        dwg.add_blocks()?;
+        dwg.add_dictionaries()?;
+        Ok(dwg)
+    }
+
+    /// Adds a line to model space on layer "0", returning its handle
+    ///
+    /// Fails with `MissingObject` if the block table, model space or the layer cannot be
+    /// found
+    pub fn add_line(&mut self, start: Point3, end: Point3) -> Result<u64> {
+        self.add_model_space_entity(object_types::LINE, |header| Line {
+            header,
+            start,
+            end,
+            thickness: 0.0,
+            extrusion: Z_AXIS,
+        })
+    }
+
+    /// Adds single line text to model space on layer "0" with the "Standard" style,
+    /// returning its handle
+    ///
+    /// Fails with `MissingObject` if the block table, model space, the layer or the style
+    /// cannot be found
+    pub fn add_text(&mut self, insertion: Point3, height: f64, value: &str) -> Result<u64> {
+        let style = self.table_entry(object_types::STYLE_CONTROL, "Standard")?;
+        self.add_model_space_entity(object_types::TEXT, |header| Text {
+            header,
+            insertion,
+            alignment: Point2::default(),
+            extrusion: Z_AXIS,
+            thickness: 0.0,
+            oblique_angle: 0.0,
+            rotation: 0.0,
+            height,
+            width_factor: 1.0,
+            value: String::from(value),
+            generation: 0,
+            horizontal_alignment: 0,
+            vertical_alignment: 0,
+            style: HandleRef::new(RefType::HardPointer, style),
+        })
+    }
+
+    /// Returns the handles of the entities of a block, in order
+    ///
+    /// Before AC1018 (R2004) the entities of a block are a linked list from the first and last
+    /// entities the block stores, which is followed from the first entity and ends at the last
+    /// or at the ENDBLK of the block, whichever comes first. Later versions list every entity in
+    /// the block. Fails with `MissingObject` if the block or an entity in the list cannot be
+    /// found
+    pub fn block_entities(&self, block: u64) -> Result<Vec<u64>> {
+        let header = self.block_header(block)?;
+        if self.version() >= DWGVersion::AC1018 {
+            let entities = header.entities.iter();
+            return Ok(entities.map(|entity| entity.absolute(block)).collect());
+        }
+        let Some((first, last)) = header.first_last else {
+            return Ok(Vec::new());
+        };
+        let endblk = header.endblk_entity.absolute(block);
+        self.linked_entities(first.absolute(block), last.absolute(block), endblk)
+    }
+
+    /// Returns the entities linked through [`CommonEntityData::links`] from `first`, ending at
+    /// `last` or before `end`, whichever comes first
+    pub(crate) fn linked_entities(&self, first: u64, last: u64, end: u64) -> Result<Vec<u64>> {
+        let mut entity = first;
+        let mut entities = Vec::new();
+        while entity != 0 && entity != end {
+            // Links that loop without reaching the last entity would never end
+            if entities.len() > self.objects.len() {
+                return Err(DwgError::MissingObject { handle: last });
+            }
+            entities.push(entity);
+            if entity == last {
+                break;
+            }
+            entity = self.entity_header(entity)?.resolved_links().1;
+        }
+        Ok(entities)
+    }
+
+    /// Adds entities that are already in the drawing to the end of a block
+    pub(crate) fn append_to_block(&mut self, block: u64, entities: &[u64]) -> Result<()> {
+        let mut header = self.block_header(block)?;
+        let (Some(&first_new), Some(&last_new)) = (entities.first(), entities.last()) else {
+            return Ok(());
+        };
+        if self.version() < DWGVersion::AC1018 {
+            let (first, last) = header.first_last.map_or((0, 0), |(first, last)| {
+                (first.absolute(block), last.absolute(block))
+            });
+            if last != 0 {
+                self.set_entity_links(last, None, first_new)?;
+            }
+            let mut prev = last;
+            for (i, &entity) in entities.iter().enumerate() {
+                let next = entities.get(i + 1).copied().unwrap_or(0);
+                self.set_entity_links(entity, Some(prev), next)?;
+                prev = entity;
+            }
+            let first = if last == 0 { first_new } else { first };
+            header.first_last = Some((
+                HandleRef::new(RefType::SoftPointer, first),
+                HandleRef::new(RefType::SoftPointer, last_new),
+            ));
+        } else {
+            let entities = entities.iter();
+            header
+                .entities
+                .extend(entities.map(|&entity| HandleRef::new(RefType::HardOwned, entity)));
+        }
+        self.insert_object(block, object_types::BLOCK_HEADER, &header)?;
+        Ok(())
+    }
+
+    /// Adds an entity to the end of model space with the next free handle
+    fn add_model_space_entity<T: DwgEncode>(
+        &mut self,
+        object_type: i16,
+        build: impl FnOnce(CommonEntityData) -> T,
+    ) -> Result<u64> {
+        let space = self.model_space()?;
+        let layer = self.table_entry(object_types::LAYER_CONTROL, "0")?;
+        let handle = self.next_handle();
+        // Entities in model space do not store their owner
+        let entity = build(CommonEntityData::new(handle, 2, None, layer));
+        self.insert_object(handle, object_type, &entity)?;
+        self.handle_records[0] = handle + 1;
+        self.append_to_block(space, &[handle])?;
+        Ok(handle)
+    }
+
+    /// Links an entity to the previous and next entities of its block, keeping its link to the
+    /// previous entity if `prev` is None
+    fn set_entity_links(&mut self, handle: u64, prev: Option<u64>, next: u64) -> Result<()> {
+        let version = self.version();
+        let mut entity = PartialObject::<CommonEntityData>::decode(
+            self.object(handle)?,
+            version,
+            self.codepage,
+        )?;
+        let prev = prev.unwrap_or_else(|| entity.header.resolved_links().0);
+        entity.header.links = Some((
+            HandleRef::new(RefType::SoftPointer, prev),
+            HandleRef::new(RefType::SoftPointer, next),
+        ));
+        self.insert_object(handle, entity.object_type, &entity)?;
+        Ok(())
+    }
+
+    /// Returns the handle of the model space block, the first of the blocks the block table
+    /// does not count
+    pub(crate) fn model_space(&self) -> Result<u64> {
+        let (control_handle, control) = self.control_object(object_types::BLOCK_CONTROL)?;
+        let space = control.extra.first();
+        space
+            .map(|space| space.absolute(control_handle))
+            .ok_or(DwgError::MissingObject {
+                handle: control_handle,
+            })
+    }
+
+    pub(crate) fn block_header(&self, handle: u64) -> Result<BlockHeader> {
+        let data = self.object(handle)?;
+        decode_object(
+            data,
+            object_types::BLOCK_HEADER,
+            self.version(),
+            self.codepage,
+        )
+        .map_err(|_| DwgError::MissingObject { handle })
+    }
+
+    fn entity_header(&self, handle: u64) -> Result<CommonEntityData> {
+        let data = self.object(handle)?;
+        let entity =
+            PartialObject::<CommonEntityData>::decode(data, self.version(), self.codepage)?;
+        Ok(entity.header)
+    }
+
+    /// Returns the handle of the entry named `name`, ignoring case, of the table with the
+    /// control object of type `control_type`, or None if the table has no such entry
+    ///
+    /// The entries the table does not count, such as *Model_Space, are included
+    pub(crate) fn find_table_entry(&self, control_type: i16, name: &str) -> Result<Option<u64>> {
+        let (control_handle, control) = self.control_object(control_type)?;
+        for entry in control.entries.iter().chain(&control.extra) {
+            let handle = entry.absolute(control_handle);
+            let Some(data) = self.get_object(handle) else {
+                continue;
+            };
+            let (_, mut reader) = open_object(data, self.version(), self.codepage)?;
+            if TableEntry::decode(&mut reader)?
+                .name
+                .eq_ignore_ascii_case(name)
+            {
+                return Ok(Some(handle));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the handle of a table entry, see [`Dwg::find_table_entry`], failing with
+    /// `MissingObject` if there is no such entry
+    pub(crate) fn table_entry(&self, control_type: i16, name: &str) -> Result<u64> {
+        let control = control_record(control_type).map_or(0, |record| self.handle_records[record]);
+        self.find_table_entry(control_type, name)?
+            .ok_or(DwgError::MissingObject { handle: control })
+    }
+
+    /// Adds an entry that is already in the drawing to the table with the control object of
+    /// type `control_type`
+    pub(crate) fn add_table_entry(&mut self, control_type: i16, entry: u64) -> Result<()> {
+        let (handle, mut control) = self.control_object(control_type)?;
+        control
+            .entries
+            .push(HandleRef::new(RefType::SoftOwned, entry));
+        self.insert_object(handle, control_type, &control)?;
+        Ok(())
+    }
+
+    /// Decodes the control object of type `control_type` listed in the second header
+    pub(crate) fn control_object(&self, control_type: i16) -> Result<(u64, ControlObject)> {
+        let Some(record) = control_record(control_type) else {
+            return Err(DwgError::MissingObject { handle: 0 });
+        };
+        let handle = self.handle_records[record];
+        let (found, mut reader) = open_object(self.object(handle)?, self.version(), self.codepage)?;
+        if found != control_type {
+            return Err(DwgError::MissingObject { handle });
+        }
+        Ok((handle, ControlObject::read(&mut reader, control_type)?))
+    }
+
+    pub(crate) fn object(&self, handle: u64) -> Result<&[u8]> {
+        self.get_object(handle)
+            .ok_or(DwgError::MissingObject { handle })
+    }
+
+    pub(crate) fn insert_object(
+        &mut self,
+        handle: u64,
+        object_type: i16,
+        object: &impl DwgEncode,
+    ) -> Result<()> {
+        let data = encode_object(object_type, object, self.version(), self.codepage)?;
+        self.objects.insert(handle, data);
+        Ok(())
+    }
+
+    fn add_tables(&mut self) -> Result<()> {
+        let control = |handle, entries: &[u64], extra: &[u64]| ControlObject {
+            header: ObjectHeader::new(handle, HandleRef::new(RefType::SoftPointer, 0)),
+            entries: entries
+                .iter()
+                .map(|&entry| HandleRef::new(RefType::SoftOwned, entry))
+                .collect(),
+            extra: extra
+                .iter()
+                .map(|&entry| HandleRef::new(RefType::HardOwned, entry))
+                .collect(),
+        };
+        let controls = [
+            (
+                object_types::BLOCK_CONTROL,
+                control(
+                    handles::BLOCK_CONTROL,
+                    &[],
+                    &[handles::MODEL_SPACE, handles::PAPER_SPACE],
+                ),
+            ),
+            (
+                object_types::LAYER_CONTROL,
+                control(handles::LAYER_CONTROL, &[handles::LAYER_0], &[]),
+            ),
+            (
+                object_types::STYLE_CONTROL,
+                control(handles::STYLE_CONTROL, &[handles::STYLE_STANDARD], &[]),
+            ),
+            (
+                object_types::LTYPE_CONTROL,
+                control(
+                    handles::LTYPE_CONTROL,
+                    &[handles::LTYPE_CONTINUOUS],
+                    &[handles::LTYPE_BYBLOCK, handles::LTYPE_BYLAYER],
+                ),
+            ),
+            (
+                object_types::VIEW_CONTROL,
+                control(handles::VIEW_CONTROL, &[], &[]),
+            ),
+            (
+                object_types::UCS_CONTROL,
+                control(handles::UCS_CONTROL, &[], &[]),
+            ),
+            (
+                object_types::VPORT_CONTROL,
+                control(handles::VPORT_CONTROL, &[], &[]),
+            ),
+            (
+                object_types::APPID_CONTROL,
+                control(handles::APPID_CONTROL, &[handles::APPID_ACAD], &[]),
+            ),
+            (
+                object_types::DIMSTYLE_CONTROL,
+                control(handles::DIMSTYLE_CONTROL, &[], &[]),
+            ),
+            (
+                object_types::VP_ENT_HDR_CONTROL,
+                control(handles::VP_ENT_HDR_CONTROL, &[], &[]),
+            ),
+        ];
+        for (object_type, control) in controls {
+            self.insert_object(control.header.handle, object_type, &control)?;
+        }
+
+        let layer = Layer {
+            entry: TableEntry::new(handles::LAYER_0, handles::LAYER_CONTROL, "0"),
+            // Plotted, with the default lineweight
+            flags: 0x3F0,
+            color: CmColor::from_color(Color::Index(7)),
+            plotstyle: HandleRef::new(RefType::HardPointer, 0),
+            linetype: HandleRef::new(RefType::HardPointer, handles::LTYPE_CONTINUOUS),
+        };
+        self.insert_object(handles::LAYER_0, object_types::LAYER, &layer)?;
+
+        let style = TextStyle {
+            entry: TableEntry::new(handles::STYLE_STANDARD, handles::STYLE_CONTROL, "Standard"),
+            is_shape: false,
+            is_vertical: false,
+            fixed_height: 0.0,
+            width_factor: 1.0,
+            oblique_angle: 0.0,
+            generation: 0,
+            last_height: 0.2,
+            font_name: String::from("txt"),
+            bigfont_name: String::new(),
+        };
+        self.insert_object(handles::STYLE_STANDARD, object_types::STYLE, &style)?;
+
+        let app = AppId {
+            entry: TableEntry::new(handles::APPID_ACAD, handles::APPID_CONTROL, "ACAD"),
+        };
+        self.insert_object(handles::APPID_ACAD, object_types::APPID, &app)?;
+
+        for (handle, name, description) in [
+            (handles::LTYPE_BYBLOCK, "ByBlock", ""),
+            (handles::LTYPE_BYLAYER, "ByLayer", ""),
+            (handles::LTYPE_CONTINUOUS, "Continuous", "Solid line"),
+        ] {
+            let linetype = Linetype {
+                entry: TableEntry::new(handle, handles::LTYPE_CONTROL, name),
+                description: String::from(description),
+            };
+            self.insert_object(handle, object_types::LTYPE, &linetype)?;
+        }
+        Ok(())
+    }
+
+    fn add_blocks(&mut self) -> Result<()> {
+        let null = HandleRef::new(RefType::SoftPointer, 0);
+        for (handle, name, block, endblk) in [
+            (
+                handles::MODEL_SPACE,
+                "*Model_Space",
+                handles::MODEL_SPACE_BLOCK,
+                handles::MODEL_SPACE_ENDBLK,
+            ),
+            (
+                handles::PAPER_SPACE,
+                "*Paper_Space",
+                handles::PAPER_SPACE_BLOCK,
+                handles::PAPER_SPACE_ENDBLK,
+            ),
+        ] {
+            let entry = TableEntry::new(handle, handles::BLOCK_CONTROL, name);
+            let mut header = BlockHeader::new(entry, block, endblk);
+            if self.version() < DWGVersion::AC1018 {
+                header.first_last = Some((null, null));
+            }
+            self.insert_object(handle, object_types::BLOCK_HEADER, &header)?;
+
+            // BLOCK and ENDBLK are owned by the block rather than being part of its entities
+            let owner = Some(HandleRef::new(RefType::SoftPointer, handle));
+            let entity_header = |entity| {
+                let mut header = CommonEntityData::new(entity, 0, owner, handles::LAYER_0);
+                header.links = Some((null, null));
+                header
+            };
+            let block_entity = Block {
+                header: entity_header(block),
+                name: String::from(name),
+            };
+            let endblk_entity = EndBlk {
+                header: entity_header(endblk),
+            };
+            self.insert_object(block, object_types::BLOCK, &block_entity)?;
+            self.insert_object(endblk, object_types::ENDBLK, &endblk_entity)?;
+        }
+        Ok(())
+    }
+
+    fn add_dictionaries(&mut self) -> Result<()> {
+        let named = Dictionary::new(
+            handles::NAMED_OBJECTS,
+            0,
+            &[
+                ("ACAD_GROUP", handles::GROUPS),
+                ("ACAD_LAYOUT", handles::LAYOUTS),
+                ("ACAD_MLINESTYLE", handles::MLINESTYLES),
+                ("ACAD_PLOTSETTINGS", handles::PLOT_SETTINGS),
+                ("ACAD_PLOTSTYLENAME", handles::PLOT_STYLES),
+            ],
+        );
+        let mlinestyles = Dictionary::new(
+            handles::MLINESTYLES,
+            handles::NAMED_OBJECTS,
+            &[("Standard", handles::MLINESTYLE_STANDARD)],
+        );
+        let mut dictionaries = vec![named, mlinestyles];
+        for handle in [
+            handles::GROUPS,
+            handles::LAYOUTS,
+            handles::PLOT_SETTINGS,
+            handles::PLOT_STYLES,
+        ] {
+            dictionaries.push(Dictionary::new(handle, handles::NAMED_OBJECTS, &[]));
+        }
+        for dictionary in dictionaries {
+            self.insert_object(
+                dictionary.header.handle,
+                object_types::DICTIONARY,
+                &dictionary,
+            )?;
+        }
+
+        let style = MlineStyle {
+            header: ObjectHeader::new(
+                handles::MLINESTYLE_STANDARD,
+                HandleRef::new(RefType::SoftPointer, handles::MLINESTYLES),
+            ),
+            name: String::from("Standard"),
+            description: String::new(),
+            offsets: [0.5, -0.5],
+        };
+        self.insert_object(
+            handles::MLINESTYLE_STANDARD,
+            object_types::MLINESTYLE,
+            &style,
+        )
+    }
+}
+
+/// Returns the record of [`Dwg::handle_records`] listing the control object of type
+/// `control_type`
+fn control_record(control_type: i16) -> Option<usize> {
+    let record = TABLE_CONTROLS
+        .iter()
+        .position(|&table| table == control_type)?;
+    Some(record + 1)
+}
+
+/// Returns the classes AutoCAD defines in a new drawing, numbered from 500
+///
+/// None of their objects are created by [`Dwg::new`], so their instance counts are 0
+fn standard_classes() -> Vec<DwgClass> {
+    [
+        ("ACDBDICTIONARYWDFLT", "AcDbDictionaryWithDefault", 0),
+        ("ACDBPLACEHOLDER", "AcDbPlaceHolder", 0),
+        ("LAYOUT", "AcDbLayout", 0),
+        ("DICTIONARYVAR", "AcDbDictionaryVar", 0),
+        ("TABLESTYLE", "AcDbTableStyle", 0xFFF),
+    ]
+    .into_iter()
+    .zip(500..)
+    .map(
+        |((dxf_name, cpp_class_name, proxy_flags), number)| DwgClass {
+            number,
+            proxy_flags,
+            app_name: String::from("ObjectDBX Classes"),
+            cpp_class_name: String::from(cpp_class_name),
+            dxf_name: String::from(dxf_name),
+            was_zombie: false,
+            item_class_id: OBJECT_CLASS_ID,
+            instance_count: 0,
+            dwg_version: 0,
+            maintenance_version: 0,
+        },
+    )
+    .collect()
+}
+
+/// Returns the current time as a Julian date
+fn now() -> JulianDate {
+    /// Julian day of the Unix epoch
+    const UNIX_EPOCH_DAY: i32 = 2440588;
+    let ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis());
+    JulianDate {
+        day: UNIX_EPOCH_DAY + (ms / 86_400_000) as i32,
+        ms: (ms % 86_400_000) as i32,
+    }
+}
+
+/// Writes the header variables of a drawing created by [`Dwg::new`], in the order of chapter
+/// 9 of the ODS
+///
+/// Variables hold the values of a new drawing in imperial units, referencing the objects
+/// created by [`Dwg::new`]. `handseed` is the next free handle
+pub(crate) fn write_header_vars(
+    version: DWGVersion,
+    codepage: CodePage,
+    handseed: u64,
+) -> Result<Vec<u8>> {
+    let mut writer = BitWriter::new();
+    writer.set_version(version);
+    let w = &mut writer;
+    let r2004 = version >= DWGVersion::AC1018;
+    let hard = |handle| HandleRef::new(RefType::HardPointer, handle);
+    let owner = |handle| HandleRef::new(RefType::HardOwned, handle);
+    let origin = Point3::default();
+    let x_axis = Point3 {
+        x: 1.0,
+        y: 0.0,
+        z: 0.0,
+    };
+    let y_axis = Point3 {
+        x: 0.0,
+        y: 1.0,
+        z: 0.0,
+    };
+    // Extents of a drawing with nothing in it
+    let extents_min = Point3 {
+        x: 1e20,
+        y: 1e20,
+        z: 1e20,
+    };
+    let extents_max = Point3 {
+        x: -1e20,
+        y: -1e20,
+        z: -1e20,
+    };
+    let limits_max = Point2 { x: 12.0, y: 9.0 };
+
+    // Unknown values
+    w.write_bitdouble(412148564080.0);
+    for _ in 0..3 {
+        w.write_bitdouble(1.0);
+    }
+    for _ in 0..4 {
+        w.write_text("", codepage)?;
+    }
+    w.write_bitlong(24);
+    w.write_bitlong(0);
+    if !r2004 {
+        // Current viewport entity header
+        w.write_handle(hard(0));
+    }
+
+    // DIMASO, DIMSHO, PLINEGEN, ORTHOMODE, REGENMODE, FILLMODE, QTEXTMODE, PSLTSCALE and
+    // LIMCHECK
+    for value in [1, 1, 0, 0, 1, 1, 0, 1, 0] {
+        w.write_bit(value);
+    }
+    if r2004 {
+        // Undocumented
+        w.write_bit(0);
+    }
+    // USRTIMER, SKPOLY, ANGDIR, SPLFRAME, MIRRTEXT, WORLDVIEW, TILEMODE, PLIMCHECK,
+    // VISRETAIN, DISPSILH and PELLIPSE
+    for value in [1, 0, 0, 0, 0, 1, 1, 0, 1, 0, 0] {
+        w.write_bit(value);
+    }
+    // PROXYGRAPHICS, TREEDEPTH, LUNITS, LUPREC, AUNITS, AUPREC, ATTMODE and PDMODE
+    for value in [1, 3020, 2, 4, 0, 0, 1, 0] {
+        w.write_bitshort(value);
+    }
+    if r2004 {
+        for _ in 0..3 {
+            w.write_bitlong(0);
+        }
+    }
+    // USERI1-5, SPLINESEGS, SURFU, SURFV, SURFTYPE, SURFTAB1, SURFTAB2, SPLINETYPE, SHADEDGE,
+    // SHADEDIF, UNITMODE, MAXACTVP, ISOLINES, CMLJUST and TEXTQLTY
+    for value in [0, 0, 0, 0, 0, 8, 6, 6, 6, 6, 6, 6, 3, 70, 0, 64, 4, 0, 50] {
+        w.write_bitshort(value);
+    }
+    // LTSCALE, TEXTSIZE, TRACEWID, SKETCHINC, FILLETRAD, THICKNESS, ANGBASE, PDSIZE,
+    // PLINEWID, USERR1-5, CHAMFERA-D, FACETRES, CMLSCALE and CELTSCALE
+    for value in [
+        1.0, 0.2, 0.05, 0.1, 0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.5, 0.5, 0.0, 0.0,
+        0.5, 1.0, 1.0,
+    ] {
+        w.write_bitdouble(value);
+    }
+    // MENUNAME
+    w.write_text("acad", codepage)?;
+    // TDCREATE and TDUPDATE
+    let created = now();
+    w.write_bl_date(created);
+    w.write_bl_date(created);
+    if r2004 {
+        for _ in 0..3 {
+            w.write_bitlong(0);
+        }
+    }
+    // TDINDWG and TDUSRTIMER
+    w.write_bl_date(JulianDate { day: 0, ms: 0 });
+    w.write_bl_date(JulianDate { day: 0, ms: 0 });
+    // CECOLOR
+    w.write_cm_color(&CmColor::from_color(Color::ByLayer), codepage)?;
+    w.write_handle(HandleRef::new(RefType::Unspecified, handseed));
+    // CLAYER, TEXTSTYLE, CELTYPE, DIMSTYLE and CMLSTYLE
+    for handle in [
+        handles::LAYER_0,
+        handles::STYLE_STANDARD,
+        handles::LTYPE_BYLAYER,
+        0,
+        handles::MLINESTYLE_STANDARD,
+    ] {
+        w.write_handle(hard(handle));
+    }
+    // PSVPSCALE
+    w.write_bitdouble(0.0);
+
+    // Paper space then model space UCS and limits
+    for paper_space in [true, false] {
+        // PINSBASE or INSBASE
+        w.write_3bd(origin);
+        if paper_space {
+            w.write_3bd(extents_max);
+            w.write_3bd(extents_min);
+            w.write_2rd(limits_max);
+            w.write_2rd(Point2::default());
+        } else {
+            w.write_3bd(extents_min);
+            w.write_3bd(extents_max);
+            w.write_2rd(Point2::default());
+            w.write_2rd(limits_max);
+        }
+        // Elevation, UCS origin and axes
+        w.write_bitdouble(0.0);
+        w.write_3bd(origin);
+        w.write_3bd(x_axis);
+        w.write_3bd(y_axis);
+        // UCS name, orthographic reference UCS and view, and base UCS
+        w.write_handle(hard(0));
+        w.write_handle(hard(0));
+        w.write_bitshort(0);
+        w.write_handle(hard(0));
+        // Origins of the top, bottom, left, right, front and back orthographic UCSs
+        for _ in 0..6 {
+            w.write_3bd(origin);
+        }
+    }
+
+    // DIMPOST and DIMAPOST
+    w.write_text("", codepage)?;
+    w.write_text("", codepage)?;
+    // DIMSCALE, DIMASZ, DIMEXO, DIMDLI, DIMEXE, DIMRND, DIMDLE, DIMTP and DIMTM
+    for value in [1.0, 0.18, 0.0625, 0.38, 0.18, 0.0, 0.0, 0.0, 0.0] {
+        w.write_bitdouble(value);
+    }
+    // DIMTOL, DIMLIM, DIMTIH, DIMTOH, DIMSE1 and DIMSE2
+    for value in [0, 0, 1, 1, 0, 0] {
+        w.write_bit(value);
+    }
+    // DIMTAD, DIMZIN and DIMAZIN
+    for value in [0, 0, 0] {
+        w.write_bitshort(value);
+    }
+    // DIMTXT, DIMCEN, DIMTSZ, DIMALTF, DIMLFAC, DIMTVP, DIMTFAC, DIMGAP and DIMALTRND
+    for value in [0.18, 0.09, 0.0, 25.4, 1.0, 0.0, 1.0, 0.09, 0.0] {
+        w.write_bitdouble(value);
+    }
+    // DIMALT
+    w.write_bit(0);
+    // DIMALTD
+    w.write_bitshort(2);
+    // DIMTOFL, DIMSAH, DIMTIX and DIMSOXD
+    for value in [0, 0, 0, 0] {
+        w.write_bit(value);
+    }
+    // DIMCLRD, DIMCLRE and DIMCLRT
+    for _ in 0..3 {
+        w.write_cm_color(&CmColor::from_color(Color::ByBlock), codepage)?;
+    }
+    // DIMADEC, DIMDEC, DIMTDEC, DIMALTU, DIMALTTD, DIMAUNIT, DIMFRAC, DIMLUNIT, DIMDSEP,
+    // DIMTMOVE and DIMJUST
+    for value in [0, 4, 4, 2, 2, 0, 0, 2, b'.' as i16, 0, 0] {
+        w.write_bitshort(value);
+    }
+    // DIMSD1 and DIMSD2
+    w.write_bit(0);
+    w.write_bit(0);
+    // DIMTOLJ, DIMTZIN, DIMALTZ and DIMALTTZ
+    for value in [1, 0, 0, 0] {
+        w.write_bitshort(value);
+    }
+    // DIMUPT
+    w.write_bit(0);
+    // DIMATFIT
+    w.write_bitshort(3);
+    // DIMTXSTY, DIMLDRBLK, DIMBLK, DIMBLK1 and DIMBLK2
+    for handle in [handles::STYLE_STANDARD, 0, 0, 0, 0] {
+        w.write_handle(hard(handle));
+    }
+    // DIMLWD and DIMLWE, ByBlock
+    w.write_bitshort(-2);
+    w.write_bitshort(-2);
+
+    for handle in [
+        handles::BLOCK_CONTROL,
+        handles::LAYER_CONTROL,
+        handles::STYLE_CONTROL,
+        handles::LTYPE_CONTROL,
+        handles::VIEW_CONTROL,
+        handles::UCS_CONTROL,
+        handles::VPORT_CONTROL,
+        handles::APPID_CONTROL,
+        handles::DIMSTYLE_CONTROL,
+    ] {
+        w.write_handle(owner(handle));
+    }
+    if !r2004 {
+        w.write_handle(owner(handles::VP_ENT_HDR_CONTROL));
+    }
+    w.write_handle(hard(handles::GROUPS));
+    w.write_handle(hard(handles::MLINESTYLES));
+    w.write_handle(owner(handles::NAMED_OBJECTS));
+    // TSTACKALIGN and TSTACKSIZE
+    w.write_bitshort(1);
+    w.write_bitshort(70);
+    // HYPERLINKBASE and STYLESHEET
+    w.write_text("", codepage)?;
+    w.write_text("", codepage)?;
+    for handle in [
+        handles::LAYOUTS,
+        handles::PLOT_SETTINGS,
+        handles::PLOT_STYLES,
+    ] {
+        w.write_handle(hard(handle));
+    }
+    if r2004 {
+        // Materials and colors dictionaries
+        w.write_handle(hard(0));
+        w.write_handle(hard(0));
+    }
+    // CELWEIGHT of ByLayer, with EXTNAMES and PSTYLEMODE set
+    w.write_bitlong(0x1D | 0x800 | 0x2000);
+    // INSUNITS and CEPSNTYPE
+    w.write_bitshort(0);
+    w.write_bitshort(0);
+    // FINGERPRINTGUID and VERSIONGUID
+    w.write_text("", codepage)?;
+    w.write_text("", codepage)?;
+    if r2004 {
+        // SORTENTS, INDEXCTL, HIDETEXT, XCLIPFRAME, DIMASSOC and HALOGAP
+        for value in [127, 0, 1, 0, 2, 0] {
+            w.write_raw_char(value);
+        }
+        // OBSCUREDCOLOR and INTERSECTIONCOLOR
+        w.write_bitshort(257);
+        w.write_bitshort(257);
+        // OBSCUREDLTYPE and INTERSECTIONDISPLAY
+        w.write_raw_char(0);
+        w.write_raw_char(0);
+        // PROJECTNAME
+        w.write_text("", codepage)?;
+    }
+    for handle in [
+        handles::PAPER_SPACE,
+        handles::MODEL_SPACE,
+        handles::LTYPE_BYLAYER,
+        handles::LTYPE_BYBLOCK,
+        handles::LTYPE_CONTINUOUS,
+    ] {
+        w.write_handle(hard(handle));
+    }
+    // Unknown values
+    for _ in 0..4 {
+        w.write_bitshort(0);
+    }
+    Ok(writer.into_bytes())
+}
+
+#[test]
+fn test_new() {
+    assert!(Dwg::new(DWGVersion::AC1021).is_err());
+    for version in [DWGVersion::AC1015, DWGVersion::AC1018] {
+        let dwg = Dwg::new(version).unwrap();
+        assert_eq!(dwg.next_handle(), handles::FIRST_FREE);
+        assert_eq!(
+            dwg.find_table_entry(object_types::LAYER_CONTROL, "0"),
+            Ok(Some(handles::LAYER_0))
+        );
+        assert_eq!(
+            dwg.find_table_entry(object_types::STYLE_CONTROL, "STANDARD"),
+            Ok(Some(handles::STYLE_STANDARD))
+        );
+        assert_eq!(
+            dwg.table_entry(object_types::LAYER_CONTROL, "1"),
+            Err(DwgError::MissingObject {
+                handle: handles::LAYER_CONTROL
+            })
+        );
+        assert!(!dwg.to_bytes().unwrap().is_empty());
+    }
+}
+
+#[test]
+fn test_new_classes() {
+    let writable = [
+        DWGVersion::AC1012,
+        DWGVersion::AC1014,
+        DWGVersion::AC1015,
+        DWGVersion::AC1018,
+        DWGVersion::AC1021,
+        DWGVersion::AC1024,
+        DWGVersion::AC1027,
+        DWGVersion::AC1032,
+    ]
+    .into_iter()
+    .filter(|&version| is_writable(version));
+    for version in writable {
+        let dwg = Dwg::new(version).unwrap();
+        let read = Dwg::from_bytes(&dwg.to_bytes().unwrap()).unwrap();
+        let classes = read.class_definitions().unwrap();
+        assert_eq!(classes, standard_classes(), "{version:?}");
+        assert_eq!(classes[0].dxf_name, "ACDBDICTIONARYWDFLT");
+    }
+}
+
+#[test]
+fn test_add_entities() {
+    for version in [DWGVersion::AC1015, DWGVersion::AC1018] {
+        let mut dwg = Dwg::new(version).unwrap();
+        let start = Point3::default();
+        let end = Point3 {
+            x: 10.0,
+            y: 5.0,
+            z: 0.0,
+        };
+        let first = dwg.add_line(start, end).unwrap();
+        let second = dwg.add_line(end, start).unwrap();
+        let text = dwg.add_text(end, 2.5, "Hello").unwrap();
+        assert_eq!([first, second, text], [0x1F, 0x20, 0x21]);
+        assert_eq!(dwg.next_handle(), 0x22);
+        assert_eq!(
+            dwg.block_entities(handles::MODEL_SPACE),
+            Ok(vec![first, second, text])
+        );
+        assert_eq!(dwg.block_entities(handles::PAPER_SPACE), Ok(vec![]));
+
+        let space: BlockHeader = decode_object(
+            dwg.get_object(handles::MODEL_SPACE).unwrap(),
+            object_types::BLOCK_HEADER,
+            version,
+            dwg.codepage,
+        )
+        .unwrap();
+        let links = |handle| {
+            PartialObject::<CommonEntityData>::decode(
+                dwg.get_object(handle).unwrap(),
+                version,
+                dwg.codepage,
+            )
+            .unwrap()
+            .header
+            .resolved_links()
+        };
+        if version < DWGVersion::AC1018 {
+            let (first_ref, last_ref) = space.first_last.unwrap();
+            assert_eq!((first_ref.handle, last_ref.handle), (first, text));
+            assert_eq!(links(first), (0, second));
+            assert_eq!(links(second), (first, text));
+            assert_eq!(links(text), (second, 0));
+
+            let read = Dwg::from_bytes(&dwg.to_bytes().unwrap()).unwrap();
+            assert_eq!(read.objects, dwg.objects);
+            assert_eq!(read.header_vars, dwg.header_vars);
+
+            // A chain running on to the ENDBLK ends there without reaching the last entity
+            let mut space = space;
+            space.first_last = Some((first_ref, HandleRef::new(RefType::SoftPointer, 0x99)));
+            dwg.insert_object(handles::MODEL_SPACE, object_types::BLOCK_HEADER, &space)
+                .unwrap();
+            let endblk = space.endblk_entity.absolute(handles::MODEL_SPACE);
+            dwg.set_entity_links(text, None, endblk).unwrap();
+            assert_eq!(
+                dwg.block_entities(handles::MODEL_SPACE),
+                Ok(vec![first, second, text])
+            );
+        } else {
+            let entities: Vec<_> = space.entities.iter().map(|entity| entity.handle).collect();
+            assert_eq!(entities, [first, second, text]);
+            assert!(!dwg.to_bytes().unwrap().is_empty());
+        }
+    }
+}
+
+#[test]
+fn test_add_entity_missing_table() {
+    let mut dwg = Dwg::empty(DWGVersion::AC1015);
+    assert_eq!(
+        dwg.add_line(Point3::default(), Point3::default()),
+        Err(DwgError::MissingObject { handle: 0 })
+    );
+}
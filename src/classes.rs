@@ -0,0 +1,260 @@
+//! The classes section, defining the object types from 500 a drawing uses
+//!
+//! Object types below 500 are fixed, see [`crate::objects::object_types`]. Types from 500 are
+//! numbered by the drawing, each defined by a class naming the application that defines it
+//! and its DXF name, which is what says how to decode its objects
+use std::collections::BTreeMap;
+
+use crate::{
+    bitcodes::BitSliceReader,
+    bitcodes_write::BitWriter,
+    decode::DwgDecode,
+    dwg::Dwg,
+    error::{DwgError, Result},
+    objects::open_object,
+    streams::{join_section_streams, DualStreamReader},
+    types::CodePage,
+    version::DWGVersion,
+};
+
+/// The item class id of classes whose objects are entities
+pub const ENTITY_CLASS_ID: u16 = 0x1F2;
+/// The item class id of classes whose objects are not entities
+pub const OBJECT_CLASS_ID: u16 = 0x1F3;
+
+/// The definition of an object type from 500
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DwgClass {
+    /// The object type the class defines
+    pub number: i16,
+    /// Flags of what can be done to objects of the class if its application is missing,
+    /// such as whether they can be erased or moved
+    pub proxy_flags: u16,
+    pub app_name: String,
+    pub cpp_class_name: String,
+    /// The name objects of the class have in DXF files, such as `LAYOUT`
+    pub dxf_name: String,
+    /// True if the class was loaded as a proxy when the drawing was saved
+    pub was_zombie: bool,
+    /// [`ENTITY_CLASS_ID`] or [`OBJECT_CLASS_ID`]
+    pub item_class_id: u16,
+    /// Number of objects of the class in the drawing, stored from AC1018 (R2004)
+    pub instance_count: u32,
+    /// Version of the drawing the class was defined in, stored from AC1018 (R2004)
+    pub dwg_version: u32,
+    /// Maintenance version of the drawing the class was defined in, stored from AC1018
+    /// (R2004)
+    pub maintenance_version: u32,
+}
+
+impl DwgClass {
+    /// Returns true if objects of the class are entities
+    pub fn is_entity(&self) -> bool {
+        self.item_class_id == ENTITY_CLASS_ID
+    }
+}
+
+/// Reads the classes section data of a drawing of `version`
+///
+/// AC1018 (R2004) and later sections start with the highest class number, which gives the
+/// number of classes. Earlier sections hold classes until the end of the data
+pub fn read_classes(data: &[u8], version: DWGVersion, codepage: CodePage) -> Result<Vec<DwgClass>> {
+    let mut data = BitSliceReader::from_slice(data);
+    data.set_version(version);
+    data.set_codepage(codepage);
+    let mut reader = DualStreamReader::section(data)?;
+    let count = if version >= DWGVersion::AC1018 {
+        let max_number = reader.read_bitshort()?;
+        reader.read_raw_char()?;
+        reader.read_raw_char()?;
+        reader.read_bit()?;
+        Some((max_number as u16).saturating_sub(499))
+    } else {
+        None
+    };
+
+    let mut classes = Vec::new();
+    // The last class may be followed by padding to the end of its byte
+    let bits = reader.data().len() as u64 * 8;
+    while match count {
+        Some(count) => classes.len() < count as usize,
+        None => bits.saturating_sub(reader.bit_position()) >= 8,
+    } {
+        let number = reader.read_bitshort()?;
+        let proxy_flags = reader.read_bitshort()? as u16;
+        let app_name = reader.read_text()?;
+        let cpp_class_name = reader.read_text()?;
+        let dxf_name = reader.read_text()?;
+        let was_zombie = reader.read_bit()? == 1;
+        let item_class_id = reader.read_bitshort()? as u16;
+        let mut class = DwgClass {
+            number,
+            proxy_flags,
+            app_name,
+            cpp_class_name,
+            dxf_name,
+            was_zombie,
+            item_class_id,
+            instance_count: 0,
+            dwg_version: 0,
+            maintenance_version: 0,
+        };
+        if version >= DWGVersion::AC1018 {
+            class.instance_count = reader.read_bitlong()? as u32;
+            class.dwg_version = reader.read_bitlong()? as u32;
+            class.maintenance_version = reader.read_bitlong()? as u32;
+            reader.read_bitlong()?;
+            reader.read_bitlong()?;
+        }
+        classes.push(class);
+    }
+    Ok(classes)
+}
+
+/// Writes the classes section data of a drawing of `version`, the inverse of
+/// [`read_classes`]
+pub fn write_classes(
+    classes: &[DwgClass],
+    version: DWGVersion,
+    codepage: CodePage,
+) -> Result<Vec<u8>> {
+    let new_writer = || {
+        let mut writer = BitWriter::new();
+        writer.set_version(version);
+        writer
+    };
+    let (mut data, mut strings) = (new_writer(), new_writer());
+    let split = version >= DWGVersion::AC1021;
+    if version >= DWGVersion::AC1018 {
+        let max_number = classes.iter().map(|class| class.number).max();
+        data.write_bitshort(max_number.unwrap_or(499));
+        data.write_raw_char(0);
+        data.write_raw_char(0);
+        data.write_bit(1);
+    }
+    for class in classes {
+        data.write_bitshort(class.number);
+        data.write_bitshort(class.proxy_flags as i16);
+        let text = if split { &mut strings } else { &mut data };
+        text.write_text(&class.app_name, codepage)?;
+        text.write_text(&class.cpp_class_name, codepage)?;
+        text.write_text(&class.dxf_name, codepage)?;
+        data.write_bit(class.was_zombie as u8);
+        data.write_bitshort(class.item_class_id as i16);
+        if version >= DWGVersion::AC1018 {
+            data.write_bitlong(class.instance_count as i32);
+            data.write_bitlong(class.dwg_version as i32);
+            data.write_bitlong(class.maintenance_version as i32);
+            data.write_bitlong(0);
+            data.write_bitlong(0);
+        }
+    }
+    if !split {
+        return Ok(data.into_bytes());
+    }
+    join_section_streams(data, strings, new_writer())
+}
+
+/// Returns the classes by the object type they define, the lookup needed to decode objects of
+/// types from 500
+///
+/// A number defined more than once is taken to be the last class defining it
+pub fn classes_by_type(classes: &[DwgClass]) -> BTreeMap<i16, &DwgClass> {
+    classes.iter().map(|class| (class.number, class)).collect()
+}
+
+impl Dwg {
+    /// Reads the classes of the drawing, see [`read_classes`]
+    pub fn class_definitions(&self) -> Result<Vec<DwgClass>> {
+        read_classes(&self.classes, self.version(), self.codepage)
+    }
+
+    /// Decodes the object with the handle `handle`, which must be of the type defined by the
+    /// class named `dxf_name`
+    ///
+    /// Fails with `MissingObject` if there is no such object, and with `InvalidFlag` if its
+    /// type is not defined by such a class
+    pub(crate) fn decode_class_object<T: DwgDecode>(
+        &self,
+        handle: u64,
+        dxf_name: &str,
+    ) -> Result<T> {
+        let data = self
+            .get_object(handle)
+            .ok_or(DwgError::MissingObject { handle })?;
+        let (object_type, mut reader) = open_object(data, self.version(), self.codepage)?;
+        let classes = self.class_definitions()?;
+        let class = classes_by_type(&classes).get(&object_type).copied();
+        if class.is_none_or(|class| class.dxf_name != dxf_name) {
+            return Err(DwgError::InvalidFlag {
+                at_bit: 0,
+                value: object_type as u16 as u64,
+            });
+        }
+        T::decode(&mut reader)
+    }
+}
+
+#[test]
+fn test_classes() {
+    let classes = vec![
+        DwgClass {
+            number: 500,
+            proxy_flags: 0,
+            app_name: "ObjectDBX Classes".into(),
+            cpp_class_name: "AcDbDictionaryWithDefault".into(),
+            dxf_name: "ACDBDICTIONARYWDFLT".into(),
+            was_zombie: false,
+            item_class_id: OBJECT_CLASS_ID,
+            instance_count: 1,
+            dwg_version: 0x1F,
+            maintenance_version: 0x0F,
+        },
+        DwgClass {
+            number: 501,
+            proxy_flags: 0x401,
+            app_name: "AutoCAD 2004".into(),
+            cpp_class_name: "AcDbWipeout".into(),
+            dxf_name: "WIPEOUT".into(),
+            was_zombie: true,
+            item_class_id: ENTITY_CLASS_ID,
+            instance_count: 2,
+            dwg_version: 0x19,
+            maintenance_version: 0,
+        },
+    ];
+    for version in [
+        DWGVersion::AC1012,
+        DWGVersion::AC1015,
+        DWGVersion::AC1018,
+        DWGVersion::AC1021,
+        DWGVersion::AC1032,
+    ] {
+        let mut expected = classes.clone();
+        if version < DWGVersion::AC1018 {
+            for class in &mut expected {
+                class.instance_count = 0;
+                class.dwg_version = 0;
+                class.maintenance_version = 0;
+            }
+        }
+        let data = write_classes(&classes, version, CodePage::ANSI1252).unwrap();
+        let read = read_classes(&data, version, CodePage::ANSI1252).unwrap();
+        assert_eq!(read, expected, "{version:?}");
+        assert_eq!(
+            read_classes(&[], version, CodePage::ANSI1252).is_err(),
+            version >= DWGVersion::AC1018
+        );
+    }
+
+    let by_type = classes_by_type(&classes);
+    assert!(by_type[&501].is_entity());
+    assert!(!by_type[&500].is_entity());
+    assert_eq!(by_type[&500].dxf_name, "ACDBDICTIONARYWDFLT");
+    assert!(!by_type.contains_key(&502));
+
+    let mut dwg = Dwg::empty(DWGVersion::AC1015);
+    dwg.classes = write_classes(&classes, DWGVersion::AC1015, dwg.codepage).unwrap();
+    let read = Dwg::from_bytes(&dwg.to_bytes().unwrap()).unwrap();
+    assert_eq!(read.class_definitions().unwrap().len(), 2);
+}
@@ -0,0 +1,708 @@
+//! The LZ77 variants used to compress section data from AC1018 (R2004) and AC1021 (R2007)
+//!
+//! R2004 compressed data is an initial run of literal bytes followed by opcodes. Each opcode copies
+//! a run of previously decompressed bytes, found at an offset back from the end of the
+//! output, and is followed by a further run of literals. Opcode 0x11 ends the data
+
+use crate::error::{DwgError, Result};
+
+/// Opcode that ends compressed data
+const END: u8 = 0x11;
+
+/// Fewest bytes a copy can repeat
+const MIN_MATCH: usize = 3;
+
+/// Largest offset that can be encoded without the extended offset opcodes
+const MAX_OFFSET: usize = 0x3FFF;
+
+/// Largest offset of the short single byte opcodes
+const MAX_SHORT_OFFSET: usize = 0x3FF;
+
+/// Longest copy of the short single byte opcodes
+const MAX_SHORT_LEN: usize = 14;
+
+/// Number of bits of the hash table used to find matches
+const HASH_BITS: u32 = 14;
+
+/// A copy of `len` bytes from `offset + 1` bytes back, followed by `literals` literal bytes
+struct Copy {
+    offset: usize,
+    len: usize,
+    literals: usize,
+}
+
+/// Compresses `data` for an AC1018 (R2004) or later section page
+///
+/// Data must be empty or at least 4 bytes long, as shorter runs of literals cannot start the
+/// compressed data. Section data shorter than this is stored uncompressed. Fails with
+/// `ValueOutOfRange` if `data` is 1 to 3 bytes long
+pub fn compress_r2004(data: &[u8]) -> Result<Vec<u8>> {
+    if (1..4).contains(&data.len()) {
+        return Err(DwgError::ValueOutOfRange { at_bit: 0 });
+    }
+    let mut table = vec![usize::MAX; 1 << HASH_BITS];
+    let hash = |pos: usize| {
+        let key = u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], 0]);
+        (key.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+    };
+
+    // The first 4 bytes are always literals, so that the initial run is long enough to encode
+    let mut initial_literals = data.len().min(4);
+    let mut copies: Vec<Copy> = Vec::new();
+    let mut pos = initial_literals;
+    for p in 0..pos.min(data.len().saturating_sub(MIN_MATCH - 1)) {
+        table[hash(p)] = p;
+    }
+    while pos + MIN_MATCH <= data.len() {
+        let h = hash(pos);
+        let candidate = table[h];
+        table[h] = pos;
+        let len = if candidate != usize::MAX && pos - candidate - 1 <= MAX_OFFSET {
+            data[pos..]
+                .iter()
+                .zip(&data[candidate..])
+                .take_while(|(a, b)| a == b)
+                .count()
+        } else {
+            0
+        };
+        if len < MIN_MATCH {
+            match copies.last_mut() {
+                Some(copy) => copy.literals += 1,
+                None => initial_literals += 1,
+            }
+            pos += 1;
+            continue;
+        }
+        copies.push(Copy {
+            offset: pos - candidate - 1,
+            len,
+            literals: 0,
+        });
+        for p in pos + 1..(pos + len).min(data.len() - (MIN_MATCH - 1)) {
+            table[hash(p)] = p;
+        }
+        pos += len;
+    }
+    // Remaining bytes too short to match
+    let rest = data.len() - pos;
+    match copies.last_mut() {
+        Some(copy) => copy.literals += rest,
+        None => initial_literals += rest,
+    }
+
+    let mut out = Vec::with_capacity(data.len() / 2 + 16);
+    let mut pos = 0;
+    if initial_literals > 0 {
+        write_literal_length(&mut out, initial_literals);
+        out.extend(&data[..initial_literals]);
+        pos = initial_literals;
+    }
+    for copy in &copies {
+        // One to three following literals are stored in the opcode, otherwise their count
+        // follows separately
+        let inline_literals = if copy.literals <= 3 {
+            copy.literals as u8
+        } else {
+            0
+        };
+        if copy.len <= MAX_SHORT_LEN && copy.offset <= MAX_SHORT_OFFSET {
+            out.push(
+                ((copy.len as u8 + 1) << 4) | ((copy.offset as u8 & 0x3) << 2) | inline_literals,
+            );
+            out.push((copy.offset >> 2) as u8);
+        } else {
+            if copy.len <= 0x21 {
+                out.push(copy.len as u8 + 0x1E);
+            } else {
+                out.push(0x20);
+                write_long_length(&mut out, copy.len - 0x21);
+            }
+            out.push(((copy.offset as u8 & 0x3F) << 2) | inline_literals);
+            out.push((copy.offset >> 6) as u8);
+        }
+        pos += copy.len;
+        if copy.literals > 3 {
+            write_literal_length(&mut out, copy.literals);
+        }
+        out.extend(&data[pos..pos + copy.literals]);
+        pos += copy.literals;
+    }
+    out.push(END);
+    Ok(out)
+}
+
+/// Decompresses data from an AC1018 (R2004) or later section page
+///
+/// Opcodes 0x40-0xFF hold the length and the low bits of the offset of a short copy, with
+/// the rest of the offset in the next byte. The others are followed by a two byte offset:
+/// 0x21-0x3F hold the length, 0x20 is followed by a longer length and 0x10 and 0x12-0x1F
+/// copy from over 0x4000 bytes back. The low 2 bits of the offset byte are the count of
+/// literals that follow, if 0 they are preceded by their count unless an opcode is next.
+/// Only 0x40-0xFF, 0x20-0x3F and the end opcode are written by [`compress_r2004`]
+///
+/// Positions in errors are in bits from the start of `data`. Fails with `InvalidFlag` for an
+/// unknown opcode or a copy from before the start of the output
+pub fn decompress_r2004(data: &[u8]) -> Result<Vec<u8>> {
+    let mut src = Source { data, pos: 0 };
+    let mut out = Vec::with_capacity(data.len() * 2);
+
+    let mut opcode = src.next()?;
+    if opcode < 0x10 {
+        let len = src.literal_length(opcode)?;
+        out.extend(src.take(len)?);
+        opcode = src.next()?;
+    }
+    loop {
+        let at_bit = src.pos as u64 * 8 - 8;
+        let (len, offset, literals) = match opcode {
+            END => break,
+            0x40..=0xFF => {
+                let next = src.next()?;
+                let offset = (next as usize) << 2 | ((opcode & 0x0C) >> 2) as usize;
+                ((opcode >> 4) as usize - 1, offset, (opcode & 3) as usize)
+            }
+            0x21..=0x3F => {
+                let (offset, literals) = src.two_byte_offset()?;
+                (opcode as usize - 0x1E, offset, literals)
+            }
+            0x20 => {
+                let len = src.long_length()? + 0x21;
+                let (offset, literals) = src.two_byte_offset()?;
+                (len, offset, literals)
+            }
+            0x10 | 0x12..=0x1F => {
+                let len = match opcode & 7 {
+                    0 => src.long_length()? + 9,
+                    len => len as usize + 2,
+                };
+                let (offset, literals) = src.two_byte_offset()?;
+                (
+                    len,
+                    offset + ((opcode as usize & 8) << 11) + 0x3FFF,
+                    literals,
+                )
+            }
+            _ => {
+                return Err(DwgError::InvalidFlag {
+                    at_bit,
+                    value: opcode as u64,
+                })
+            }
+        };
+        let Some(start) = out.len().checked_sub(offset + 1) else {
+            return Err(DwgError::InvalidFlag {
+                at_bit,
+                value: offset as u64,
+            });
+        };
+        // The copy may overlap the bytes it adds, so is made a byte at a time
+        for i in start..start + len {
+            out.push(out[i]);
+        }
+
+        if literals > 0 {
+            out.extend(src.take(literals)?);
+            opcode = src.next()?;
+        } else {
+            opcode = src.next()?;
+            if opcode < 0x10 {
+                let len = src.literal_length(opcode)?;
+                out.extend(src.take(len)?);
+                opcode = src.next()?;
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// The order literals are stored in by AC1021 (R2007) compressed data, for runs of up to 31
+///
+/// Each entry is an offset into the stored run and a count of bytes to copy from there. Runs
+/// of 1-3 bytes are copied in reverse, longer runs in order. Runs of 32 or more store each 32
+/// bytes as their second 16 bytes followed by their first 16
+const R2007_LITERAL_ORDER: [&[(usize, usize)]; 32] = [
+    &[],
+    &[(0, 1)],
+    &[(0, 2)],
+    &[(0, 3)],
+    &[(0, 4)],
+    &[(4, 1), (0, 4)],
+    &[(5, 1), (1, 4), (0, 1)],
+    &[(5, 2), (1, 4), (0, 1)],
+    &[(0, 8)],
+    &[(8, 1), (0, 8)],
+    &[(9, 1), (1, 8), (0, 1)],
+    &[(9, 2), (1, 8), (0, 1)],
+    &[(8, 4), (0, 8)],
+    &[(12, 1), (8, 4), (0, 8)],
+    &[(13, 1), (9, 4), (1, 8), (0, 1)],
+    &[(13, 2), (9, 4), (1, 8), (0, 1)],
+    &[(0, 16)],
+    &[(9, 8), (8, 1), (0, 8)],
+    &[(17, 1), (1, 16), (0, 1)],
+    &[(16, 3), (0, 16)],
+    &[(16, 4), (0, 16)],
+    &[(20, 1), (16, 4), (0, 16)],
+    &[(20, 2), (16, 4), (0, 16)],
+    &[(20, 3), (16, 4), (0, 16)],
+    &[(16, 8), (0, 16)],
+    &[(17, 8), (16, 1), (0, 16)],
+    &[(25, 1), (17, 8), (16, 1), (0, 16)],
+    &[(25, 2), (17, 8), (16, 1), (0, 16)],
+    &[(24, 4), (16, 8), (0, 16)],
+    &[(28, 1), (24, 4), (16, 8), (0, 16)],
+    &[(28, 2), (24, 4), (16, 8), (0, 16)],
+    &[(30, 1), (26, 4), (18, 8), (2, 16), (0, 2)],
+];
+
+/// Decompresses data from an AC1021 (R2007) page, which decompresses to `size` bytes
+///
+/// Compressed data is runs of literals alternating with copies of previous output. The
+/// high nibble of each opcode selects how the length and offset of a copy are stored, and
+/// the low 3 bits of the last byte read for a copy are the length of the literal run that
+/// follows. If they are 0, the next opcode either starts another copy or, if its high
+/// nibble is 0, holds the length of a literal run
+///
+/// Positions in errors are in bits from the start of `data`. Fails with `InvalidFlag` for a
+/// copy from before the start of the output and `InvalidStreamSize` if the output would be
+/// longer than `size`
+pub fn decompress_r2007(data: &[u8], size: usize) -> Result<Vec<u8>> {
+    let mut src = Source { data, pos: 0 };
+    let mut out = Vec::with_capacity(size);
+    let overflow = |src: &Source| DwgError::InvalidStreamSize {
+        at_bit: src.pos as u64 * 8,
+    };
+
+    let mut opcode = src.next()?;
+    let mut literals = 0;
+    if opcode & 0xF0 == 0x20 {
+        src.take(2)?;
+        literals = (src.next()? & 7) as usize;
+    }
+    while src.pos < data.len() {
+        if literals == 0 {
+            literals = src.r2007_literal_length(opcode)?;
+        }
+        if out.len() + literals > size {
+            return Err(overflow(&src));
+        }
+        let run = src.take(literals)?;
+        for chunk in run.chunks(32) {
+            if chunk.len() == 32 {
+                out.extend(&chunk[16..]);
+                out.extend(&chunk[..16]);
+                continue;
+            }
+            for &(offset, count) in R2007_LITERAL_ORDER[chunk.len()] {
+                let bytes = &chunk[offset..offset + count];
+                if count < 4 {
+                    out.extend(bytes.iter().rev());
+                } else {
+                    out.extend(bytes);
+                }
+            }
+        }
+        if src.pos >= data.len() {
+            break;
+        }
+
+        opcode = src.next()?;
+        loop {
+            let at_bit = src.pos as u64 * 8 - 8;
+            let (offset, len);
+            (offset, len, opcode) = src.r2007_copy(opcode)?;
+            let Some(start) = out.len().checked_sub(offset) else {
+                return Err(DwgError::InvalidFlag {
+                    at_bit,
+                    value: offset as u64,
+                });
+            };
+            if out.len() + len > size {
+                return Err(overflow(&src));
+            }
+            for i in start..start + len {
+                out.push(out[i]);
+            }
+
+            literals = (opcode & 7) as usize;
+            if literals != 0 || src.pos >= data.len() {
+                break;
+            }
+            opcode = src.next()?;
+            match opcode >> 4 {
+                0 => break,
+                0x0F => opcode &= 0x0F,
+                _ => {}
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Compressed data being read by [`decompress_r2004`] or [`decompress_r2007`]
+struct Source<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Source<'a> {
+    fn eof(&self) -> DwgError {
+        DwgError::UnexpectedEof {
+            at_bit: self.data.len() as u64 * 8,
+        }
+    }
+
+    fn next(&mut self) -> Result<u8> {
+        let byte = *self.data.get(self.pos).ok_or_else(|| self.eof())?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| self.eof())?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    /// Reads the rest of a literal length starting with `first`, the inverse of
+    /// [`write_literal_length`]
+    fn literal_length(&mut self, first: u8) -> Result<usize> {
+        Ok(match first {
+            0 => self.zero_extended()? + 0x12,
+            len => len as usize + 3,
+        })
+    }
+
+    /// Reads the inverse of [`write_long_length`]
+    fn long_length(&mut self) -> Result<usize> {
+        Ok(match self.next()? {
+            0 => self.zero_extended()? + 0xFF,
+            len => len as usize,
+        })
+    }
+
+    /// Reads the inverse of [`write_zero_extended`]
+    fn zero_extended(&mut self) -> Result<usize> {
+        let mut len = 0;
+        loop {
+            match self.next()? {
+                0 => len += 0xFF,
+                rem => return Ok(len + rem as usize),
+            }
+        }
+    }
+
+    /// Reads the length of an AC1021 (R2007) literal run from `opcode` and any following
+    /// bytes
+    ///
+    /// Opcodes 0x00-0x0E are lengths of 8 to 0x16. 0x0F is followed by a byte to add and, if
+    /// that is 0xFF, RSs to add until one is not 0xFFFF
+    fn r2007_literal_length(&mut self, opcode: u8) -> Result<usize> {
+        let mut len = opcode as usize + 8;
+        if len == 0x17 {
+            let n = self.next()?;
+            len += n as usize;
+            if n == 0xFF {
+                loop {
+                    let n = u16::from_le_bytes([self.next()?, self.next()?]);
+                    len += n as usize;
+                    if n != 0xFFFF {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(len)
+    }
+
+    /// Reads an AC1021 (R2007) copy starting with `opcode`, returning its offset back from
+    /// the end of the output, its length and the last byte read, which holds the length of
+    /// the following literal run
+    fn r2007_copy(&mut self, opcode: u8) -> Result<(usize, usize, u8)> {
+        let low = (opcode & 0x0F) as usize;
+        Ok(match opcode >> 4 {
+            0 => {
+                let offset = self.next()? as usize;
+                let next = self.next()?;
+                let len = low + 0x13 + ((next >> 3) & 0x10) as usize;
+                ((((next & 0x78) as usize) << 5) + 1 + offset, len, next)
+            }
+            1 => {
+                let offset = self.next()? as usize;
+                let next = self.next()?;
+                ((((next & 0xF8) as usize) << 5) + 1 + offset, low + 3, next)
+            }
+            2 => {
+                let offset = u16::from_le_bytes([self.next()?, self.next()?]) as usize;
+                let len = (opcode & 7) as usize;
+                if opcode & 8 == 0 {
+                    let next = self.next()?;
+                    (offset, len + (next & 0xF8) as usize, next)
+                } else {
+                    let len = len + ((self.next()? as usize) << 3);
+                    let next = self.next()?;
+                    let len = len + (((next & 0xF8) as usize) << 8) + 0x100;
+                    (offset + 1, len, next)
+                }
+            }
+            high => {
+                let next = self.next()?;
+                (
+                    (((next & 0xF8) as usize) << 1) + low + 1,
+                    high as usize,
+                    next,
+                )
+            }
+        })
+    }
+
+    /// Reads the offset and the count of following literals of a copy with a separate length
+    fn two_byte_offset(&mut self) -> Result<(usize, usize)> {
+        let first = self.next()?;
+        let second = self.next()?;
+        Ok((
+            (first >> 2) as usize | (second as usize) << 6,
+            (first & 3) as usize,
+        ))
+    }
+}
+
+/// Writes the length of a run of at least 4 literals
+///
+/// Lengths up to 0x12 are a single byte of the length - 3. Longer lengths are a 0 byte
+/// followed by the remainder in the same form as [`write_long_length`]
+fn write_literal_length(out: &mut Vec<u8>, len: usize) {
+    debug_assert!(len >= 4);
+    if len <= 0x12 {
+        out.push((len - 3) as u8);
+    } else {
+        out.push(0);
+        write_zero_extended(out, len - 0x12);
+    }
+}
+
+/// Writes the extra length of a long copy, which must not be 0
+///
+/// Lengths up to 0xFF are a single byte. Longer lengths are a 0 byte, followed by a 0 byte
+/// for each further 0xFF, followed by the remainder
+fn write_long_length(out: &mut Vec<u8>, len: usize) {
+    debug_assert!(len > 0);
+    if len <= 0xFF {
+        out.push(len as u8);
+    } else {
+        out.push(0);
+        write_zero_extended(out, len - 0xFF);
+    }
+}
+
+/// Writes a 0 byte for each 0xFF in `rem` then the nonzero remainder
+fn write_zero_extended(out: &mut Vec<u8>, mut rem: usize) {
+    while rem > 0xFF {
+        out.push(0);
+        rem -= 0xFF;
+    }
+    out.push(rem as u8);
+}
+
+#[test]
+fn test_compress_r2004() {
+    assert_eq!(compress_r2004(&[]), Ok(vec![END]));
+    // Too short to match, so a literal run
+    assert_eq!(compress_r2004(b"abcde").unwrap(), b"\x02abcde\x11");
+    // The 4 initial literals then a copy of 8 bytes from 3 back
+    assert_eq!(
+        compress_r2004(b"abcabcabcabc"),
+        Ok(vec![0x01, b'a', b'b', b'c', b'a', 0x98, 0x00, END])
+    );
+    let data = vec![7u8; 0x10000];
+    assert!(compress_r2004(&data).unwrap().len() < 0x200);
+}
+
+#[test]
+fn test_compress_r2004_short() {
+    let data = b"abcd";
+    for len in 0..=data.len() {
+        let compressed = compress_r2004(&data[..len]);
+        if (1..4).contains(&len) {
+            assert_eq!(compressed, Err(DwgError::ValueOutOfRange { at_bit: 0 }));
+        } else {
+            let compressed = compressed.unwrap();
+            assert_eq!(decompress_r2004(&compressed).unwrap(), &data[..len]);
+        }
+    }
+    assert_eq!(compress_r2004(data).unwrap(), b"\x01abcd\x11");
+}
+
+#[test]
+fn test_decompress_r2004() {
+    assert_eq!(decompress_r2004(&[END]), Ok(vec![]));
+    assert_eq!(decompress_r2004(b"\x02abcde\x11").unwrap(), b"abcde");
+    assert_eq!(
+        decompress_r2004(&[0x01, b'a', b'b', b'c', b'a', 0x98, 0x00, END]).unwrap(),
+        b"abcabcabcabc"
+    );
+
+    // Round trips of repetitive, random and sparse data
+    let mut seed = 12345u32;
+    let mut random = || {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        (seed >> 16) as u8
+    };
+    for len in [4, 20, 1000, 0x7400, 100000] {
+        let data: Vec<u8> = (0..len).map(|i| (i % 7) as u8).collect();
+        assert_eq!(
+            decompress_r2004(&compress_r2004(&data).unwrap()).unwrap(),
+            data
+        );
+        let data: Vec<u8> = (0..len).map(|_| random()).collect();
+        assert_eq!(
+            decompress_r2004(&compress_r2004(&data).unwrap()).unwrap(),
+            data
+        );
+        let data: Vec<u8> = (0..len)
+            .map(|_| if random() % 10 == 0 { random() } else { 0 })
+            .collect();
+        assert_eq!(
+            decompress_r2004(&compress_r2004(&data).unwrap()).unwrap(),
+            data
+        );
+    }
+
+    // Truncated, an unknown opcode and a copy from before the start
+    assert_eq!(
+        decompress_r2004(b"\x02abc"),
+        Err(DwgError::UnexpectedEof { at_bit: 32 })
+    );
+    assert_eq!(
+        decompress_r2004(&[0x01, b'a', b'b', b'c', b'a', 0x05]),
+        Err(DwgError::InvalidFlag {
+            at_bit: 40,
+            value: 5
+        })
+    );
+    assert_eq!(
+        decompress_r2004(&[0x01, b'a', b'b', b'c', b'a', 0x98, 0x01, END]),
+        Err(DwgError::InvalidFlag {
+            at_bit: 40,
+            value: 6
+        })
+    );
+}
+
+#[test]
+fn test_decompress_r2004_samples() {
+    // A run of 0x13 literals, with the length following a 0 byte
+    let mut data = vec![0x00, 0x01];
+    data.extend(b"abcdefghijklmnopqrs");
+    data.push(END);
+    assert_eq!(decompress_r2004(&data).unwrap(), b"abcdefghijklmnopqrs");
+
+    // A run of 0x215 literals, the 0 bytes of its length each adding 0xFF, as section data
+    // with few repeats starts
+    let literals: Vec<u8> = (0..0x215).map(|i| (i * 7 % 251) as u8).collect();
+    let mut data = vec![0x00, 0x00, 0x00, 0x05];
+    data.extend(&literals);
+    data.push(END);
+    assert_eq!(decompress_r2004(&data).unwrap(), literals);
+
+    // A copy of 4 bytes from 4 back with no literals in its offset byte, so followed by the
+    // length of a run of 5 literals
+    assert_eq!(
+        decompress_r2004(b"\x01abcd\x22\x0c\x00\x02vwxyz\x11").unwrap(),
+        b"abcdabcdvwxyz"
+    );
+    // The same copy directly followed by the end opcode
+    assert_eq!(
+        decompress_r2004(b"\x01abcd\x22\x0c\x00\x11").unwrap(),
+        b"abcdabcd"
+    );
+
+    // A copy of 4 bytes from 4 back followed by 2 literals in the offset byte
+    assert_eq!(
+        decompress_r2004(b"\x01abcd\x22\x0e\x00ef\x11").unwrap(),
+        b"abcdabcdef"
+    );
+
+    // A copy of 0x22 bytes, its length following opcode 0x20
+    let mut expected = b"abcd".repeat(10);
+    expected.truncate(0x26);
+    assert_eq!(
+        decompress_r2004(b"\x01abcd\x20\x01\x0c\x00\x11").unwrap(),
+        expected
+    );
+
+    // 0x4000 copies of "z" from 1 back, the length of 0x3FDF past 0x21 being 0x3EE0 past
+    // 0xFF, then a copy of 10 bytes from 0x4004 back, its length 1 past 9 following opcode
+    // 0x10 and its offset 4 past 0x3FFF
+    let mut data = b"\x01wxyz\x20\x00".to_vec();
+    data.extend([0; 63]);
+    data.extend([0x1F, 0x00, 0x00, 0x10, 0x01, 0x10, 0x00, END]);
+    let mut expected = b"wxyz".to_vec();
+    expected.extend([b'z'; 0x4000]);
+    expected.extend(b"wxyzzzzzzz");
+    assert_eq!(decompress_r2004(&data).unwrap(), expected);
+
+    // The same, copying 4 bytes with the length held in opcode 0x12
+    let len = data.len();
+    data[len - 5..].copy_from_slice(&[0x12, 0x10, 0x00, END, END]);
+    data.pop();
+    expected.truncate(expected.len() - 6);
+    assert_eq!(decompress_r2004(&data).unwrap(), expected);
+}
+
+#[test]
+fn test_decompress_r2007() {
+    // 8 literals in order, 4 copies of the last from 1 back then 2 literals in reverse
+    assert_eq!(
+        decompress_r2007(b"\x00abcdefgh\x40\x02xy", 14).unwrap(),
+        b"abcdefghhhhhyx"
+    );
+    assert_eq!(
+        decompress_r2007(b"\x00abcdefgh\x40\x02xy", 13),
+        Err(DwgError::InvalidStreamSize { at_bit: 11 * 8 })
+    );
+    // Offset 0x21 is before the start
+    assert_eq!(
+        decompress_r2007(b"\x00abcdefgh\x40\x10xy", 14),
+        Err(DwgError::InvalidFlag {
+            at_bit: 9 * 8,
+            value: 0x21
+        })
+    );
+
+    // 26 literals, stored in the order of R2007_LITERAL_ORDER
+    let mut data = vec![0x0F, 3];
+    data.extend(0..26);
+    let mut expected = vec![25];
+    expected.extend(17..25);
+    expected.push(16);
+    expected.extend(0..16);
+    assert_eq!(decompress_r2007(&data, 26).unwrap(), expected);
+
+    // 40 literals, the first 32 swapping halves, then the remaining 8 in order
+    let mut data = vec![0x0F, 17];
+    data.extend(0..40);
+    let mut expected: Vec<u8> = (16..32).chain(0..16).collect();
+    expected.extend(32..40);
+    assert_eq!(decompress_r2007(&data, 40).unwrap(), expected);
+
+    // Copies of each form: 5 bytes from 4 back, 9 from 2 back with the length in a
+    // following byte, then 0x14 from 1 back with opcode 0xF1 standing for 0x01
+    let mut data = vec![0x00];
+    data.extend(1..=8);
+    data.extend([0x12, 0x03, 0x00, 0x21, 0x02, 0x00, 0x08, 0xF1, 0x00, 0x00]);
+    let mut expected: Vec<u8> = (1..=8).collect();
+    for (offset, len) in [(4, 5), (2, 9), (1, 0x14)] {
+        for _ in 0..len {
+            expected.push(expected[expected.len() - offset]);
+        }
+    }
+    assert_eq!(decompress_r2007(&data, expected.len()).unwrap(), expected);
+
+    // Opcode 0x2X first skips 2 bytes and holds the length of the first literal run
+    assert_eq!(decompress_r2007(b"\x20\0\0\x03abc", 3).unwrap(), b"cba");
+}
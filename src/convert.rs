@@ -0,0 +1,401 @@
+//! Converting drawings between versions, see [`Dwg::save_as`]
+//!
+//! The header variables, classes and objects of a drawing are encoded for its version. They
+//! are converted by decoding them as that version stores them and encoding them again as the
+//! new version does, so colors, text, the types of objects and the entities owned by blocks,
+//! inserts and polylines are converted in one place. Before AC1018 (R2004) owned entities are
+//! a list linked through [`CommonEntityData::links`] from the first and last the owner stores,
+//! later versions list every owned entity in the owner
+use std::collections::BTreeMap;
+
+use crate::{
+    builder::write_header_vars,
+    classes::{read_classes, write_classes},
+    decode::DwgDecode,
+    dwg::Dwg,
+    dwg_write::is_writable,
+    entities::{Entity, MInsert, OwnedVertices},
+    error::{DwgError, Result},
+    header_vars::HeaderVariables,
+    objects::{
+        encode_object, is_entity_type, object_types, open_object, AppId, BlockHeader,
+        CommonEntityData, ControlObject, Dictionary, DwgEncode, Layer, Linetype, MlineStyle,
+        TextStyle,
+    },
+    streams::DualStreamWriter,
+    types::{HandleRef, RefType},
+    version::DWGVersion,
+};
+
+/// An object decoded by its type, to be encoded for another version
+enum TypedObject {
+    Entity(Entity),
+    Control(ControlObject),
+    BlockHeader(BlockHeader),
+    Layer(Layer),
+    TextStyle(TextStyle),
+    Linetype(Linetype),
+    AppId(AppId),
+    Dictionary(Dictionary),
+    MlineStyle(MlineStyle),
+}
+
+impl DwgEncode for TypedObject {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        match self {
+            Self::Entity(entity) => entity.encode(writer),
+            Self::Control(control) => control.encode(writer),
+            Self::BlockHeader(header) => header.encode(writer),
+            Self::Layer(layer) => layer.encode(writer),
+            Self::TextStyle(style) => style.encode(writer),
+            Self::Linetype(linetype) => linetype.encode(writer),
+            Self::AppId(app) => app.encode(writer),
+            Self::Dictionary(dictionary) => dictionary.encode(writer),
+            Self::MlineStyle(style) => style.encode(writer),
+        }
+    }
+}
+
+impl Dwg {
+    /// Converts the drawing so that it is written as `version`
+    ///
+    /// The header variables, classes and objects are decoded as the current version stores
+    /// them and encoded again for `version`, see [`crate::convert`]. Variables `version` does
+    /// not store are dropped and those it adds take the values of [`Dwg::new`]. The file
+    /// container is converted when the drawing is written.
+    ///
+    /// Only objects of the types this crate decodes can be converted: the entities of
+    /// [`Entity`] along with SEQEND, the symbol table controls, block headers, layers, text
+    /// styles, linetypes without dashes, registered applications, dictionaries and the
+    /// standard multiline style. Any other object fails with `ObjectConversionUnsupported`
+    /// and linetypes with dashes or other multiline styles with `InvalidFlag`, leaving the
+    /// drawing unchanged. Fails with `UnsupportedVersion` if `version` cannot be written
+    pub fn save_as(&mut self, version: DWGVersion) -> Result<()> {
+        if !is_writable(version) {
+            return Err(DwgError::UnsupportedVersion {
+                found: format!("{version:?}"),
+            });
+        }
+        if version == self.version() {
+            return Ok(());
+        }
+        let objects = self.convert_objects(version)?;
+        let header_vars = match self.header_vars.is_empty() {
+            true => Vec::new(),
+            false => {
+                let defaults = write_header_vars(version, self.codepage, self.next_handle())?;
+                let defaults = HeaderVariables::read(&defaults, version, self.codepage)?;
+                let vars = self.header_variables()?.convert(version, &defaults);
+                vars.write(version, self.codepage)?
+            }
+        };
+        let classes = self.convert_classes(version, &objects)?;
+        self.header_vars = header_vars;
+        self.classes = classes;
+        self.objects = objects;
+        self.set_version(version);
+        Ok(())
+    }
+
+    /// Converts the classes, counting the objects of each class from AC1018 (R2004), which
+    /// stores the count
+    fn convert_classes(
+        &self,
+        version: DWGVersion,
+        objects: &BTreeMap<u64, Vec<u8>>,
+    ) -> Result<Vec<u8>> {
+        if self.classes.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut classes = read_classes(&self.classes, self.version(), self.codepage)?;
+        if version >= DWGVersion::AC1018 {
+            let mut counts = BTreeMap::new();
+            for data in objects.values() {
+                let (object_type, _) = open_object(data, version, self.codepage)?;
+                *counts.entry(object_type).or_insert(0) += 1;
+            }
+            for class in &mut classes {
+                class.instance_count = counts.get(&class.number).copied().unwrap_or(0);
+            }
+        }
+        write_classes(&classes, version, self.codepage)
+    }
+
+    /// Converts every object, failing if one is not of a type decoded here
+    fn convert_objects(&self, version: DWGVersion) -> Result<BTreeMap<u64, Vec<u8>>> {
+        let (from, codepage) = (self.version(), self.codepage);
+        let mut typed = BTreeMap::new();
+        // The previous and next entity of each owned entity, for versions that link them
+        let mut links = BTreeMap::new();
+        for (&handle, data) in &self.objects {
+            let (object_type, mut reader) = open_object(data, from, codepage)?;
+            let unsupported = DwgError::ObjectConversionUnsupported {
+                handle,
+                object_type,
+            };
+            let object = match object_type {
+                object_types::BLOCK_CONTROL
+                | object_types::LAYER_CONTROL
+                | object_types::STYLE_CONTROL
+                | object_types::LTYPE_CONTROL
+                | object_types::VIEW_CONTROL
+                | object_types::UCS_CONTROL
+                | object_types::VPORT_CONTROL
+                | object_types::APPID_CONTROL
+                | object_types::DIMSTYLE_CONTROL
+                | object_types::VP_ENT_HDR_CONTROL => {
+                    TypedObject::Control(ControlObject::read(&mut reader, object_type)?)
+                }
+                object_types::BLOCK_HEADER => {
+                    let mut header = BlockHeader::decode(&mut reader)?;
+                    if header.has_entities() {
+                        let entities = self.block_entities(handle)?;
+                        (header.first_last, header.entities) =
+                            own(&entities, 0, version, &mut links);
+                    }
+                    TypedObject::BlockHeader(header)
+                }
+                object_types::LAYER => TypedObject::Layer(Layer::decode(&mut reader)?),
+                object_types::STYLE => TypedObject::TextStyle(TextStyle::decode(&mut reader)?),
+                object_types::LTYPE => TypedObject::Linetype(Linetype::decode(&mut reader)?),
+                object_types::APPID => TypedObject::AppId(AppId::decode(&mut reader)?),
+                object_types::DICTIONARY => {
+                    TypedObject::Dictionary(Dictionary::decode(&mut reader)?)
+                }
+                object_types::MLINESTYLE => {
+                    TypedObject::MlineStyle(MlineStyle::decode(&mut reader)?)
+                }
+                _ if is_entity_type(object_type) => {
+                    let mut entity = Entity::decode(data, from, codepage)?;
+                    self.convert_owned(&mut entity, version, &mut links)?;
+                    match &mut entity {
+                        // SEQEND has no data of its own
+                        Entity::Other(seqend) if object_type == object_types::SEQEND => {
+                            seqend.data = Default::default();
+                            seqend.handles = Default::default();
+                        }
+                        Entity::Other(_) => return Err(unsupported),
+                        _ => {}
+                    }
+                    TypedObject::Entity(entity)
+                }
+                _ => return Err(unsupported),
+            };
+            typed.insert(handle, (object_type, object));
+        }
+
+        let mut objects = BTreeMap::new();
+        for (handle, (object_type, mut object)) in typed {
+            if let TypedObject::Entity(entity) = &mut object {
+                set_links(entity.header_mut(), links.get(&handle).copied(), version);
+            }
+            let data = encode_object(object_type, &object, version, codepage)?;
+            objects.insert(handle, data);
+        }
+        Ok(objects)
+    }
+
+    /// Converts the ATTRIBs an INSERT owns or the vertices a polyline owns
+    fn convert_owned(
+        &self,
+        entity: &mut Entity,
+        version: DWGVersion,
+        links: &mut BTreeMap<u64, (u64, u64)>,
+    ) -> Result<()> {
+        let handle = entity.header().handle;
+        let owned = match entity {
+            Entity::Insert(insert) | Entity::MInsert(MInsert { insert, .. }) => {
+                let Some(seqend) = insert.seqend else {
+                    return Ok(());
+                };
+                let attribs = self.insert_attribs(insert)?;
+                let seqend = seqend.absolute(handle);
+                let (first_last, attribs) = own(&attribs, seqend, version, links);
+                insert.first_last_attribs = first_last;
+                insert.attribs = attribs;
+                return Ok(());
+            }
+            Entity::Polyline2d(polyline) => &mut polyline.owned,
+            Entity::Polyline3d(polyline) => &mut polyline.owned,
+            Entity::PolylinePface(polyline) => &mut polyline.owned,
+            Entity::PolylineMesh(polyline) => &mut polyline.owned,
+            _ => return Ok(()),
+        };
+        let vertices = self.polyline_vertices(handle, owned)?;
+        let OwnedVertices { seqend, .. } = *owned;
+        (owned.first_last, owned.vertices) =
+            own(&vertices, seqend.absolute(handle), version, links);
+        Ok(())
+    }
+}
+
+/// Returns the first and last of the `owned` entities as stored before AC1018 (R2004), with
+/// the links between them added to `links` ending at `end`, or every entity as stored from
+/// AC1018
+fn own(
+    owned: &[u64],
+    end: u64,
+    version: DWGVersion,
+    links: &mut BTreeMap<u64, (u64, u64)>,
+) -> (Option<(HandleRef, HandleRef)>, Vec<HandleRef>) {
+    if version >= DWGVersion::AC1018 {
+        let owned = owned.iter();
+        let owned = owned.map(|&entity| HandleRef::new(RefType::HardOwned, entity));
+        return (None, owned.collect());
+    }
+    for (i, &entity) in owned.iter().enumerate() {
+        let prev = i.checked_sub(1).map_or(0, |prev| owned[prev]);
+        let next = owned.get(i + 1).copied().unwrap_or(end);
+        links.insert(entity, (prev, next));
+    }
+    let soft = |entity: Option<&u64>| HandleRef::new(RefType::SoftPointer, *entity.unwrap_or(&0));
+    (Some((soft(owned.first()), soft(owned.last()))), Vec::new())
+}
+
+/// Sets the links of an entity to `links` before AC1018 (R2004), or to null links if it is not
+/// owned through them, and clears them from AC1018, which does not store them
+fn set_links(header: &mut CommonEntityData, links: Option<(u64, u64)>, version: DWGVersion) {
+    header.links = match version >= DWGVersion::AC1018 {
+        true => None,
+        false => {
+            let (prev, next) = links.unwrap_or((0, 0));
+            Some((
+                HandleRef::new(RefType::SoftPointer, prev),
+                HandleRef::new(RefType::SoftPointer, next),
+            ))
+        }
+    };
+}
+
+#[test]
+fn test_save_as() {
+    use crate::{
+        classes::DwgClass,
+        entities::{EndBlk, Polyline3d, Vertex3d},
+        objects::TableEntry,
+        types::Point3,
+    };
+
+    let mut dwg = Dwg::empty(DWGVersion::AC1015);
+    dwg.raw_sections.insert(4, vec![0, 0, 1, 0]);
+    dwg.save_as(DWGVersion::AC1018).unwrap();
+    assert_eq!(dwg.version(), DWGVersion::AC1018);
+    assert_eq!(&dwg.to_bytes().unwrap()[..6], b"AC1018");
+    assert_eq!(
+        dwg.save_as(DWGVersion::AC1021),
+        Err(DwgError::UnsupportedVersion {
+            found: String::from("AC1021")
+        })
+    );
+
+    // A drawing with entities in model space and a polyline owning its vertices
+    let mut dwg = Dwg::new(DWGVersion::AC1015).unwrap();
+    let line = dwg
+        .add_line(Point3::default(), Point3::from((1.0, 2.0, 0.0)))
+        .unwrap();
+    dwg.add_text(Point3::default(), 0.2, "text").unwrap();
+    let layer = dwg.table_entry(object_types::LAYER_CONTROL, "0").unwrap();
+    let [polyline, first, second, seqend] = std::array::from_fn(|i| 0x100 + i as u64);
+    let soft = |handle| HandleRef::new(RefType::SoftPointer, handle);
+    let null = soft(0);
+    let owned = |handle, links| {
+        let mut header = CommonEntityData::new(handle, 0, Some(soft(polyline)), layer);
+        header.links = Some(links);
+        header
+    };
+    for (vertex, links, x) in [
+        (first, (null, soft(second)), 1.0),
+        (second, (soft(first), soft(seqend)), 2.0),
+    ] {
+        let vertex_3d = Vertex3d {
+            header: owned(vertex, links),
+            flags: 0,
+            point: Point3::from((x, 0.0, 0.0)),
+        };
+        dwg.insert_object(vertex, object_types::VERTEX_3D, &vertex_3d)
+            .unwrap();
+    }
+    // SEQEND has only the common data, as ENDBLK
+    let end = EndBlk {
+        header: owned(seqend, (null, null)),
+    };
+    dwg.insert_object(seqend, object_types::SEQEND, &end)
+        .unwrap();
+    let polyline_3d = Polyline3d {
+        header: CommonEntityData::new(polyline, 2, None, layer),
+        spline_flags: 0,
+        closed_flags: 1,
+        owned: OwnedVertices {
+            first_last: Some((soft(first), soft(second))),
+            vertices: Vec::new(),
+            seqend: HandleRef::new(RefType::HardOwned, seqend),
+        },
+    };
+    dwg.insert_object(polyline, object_types::POLYLINE_3D, &polyline_3d)
+        .unwrap();
+    let space = dwg.model_space().unwrap();
+    dwg.append_to_block(space, &[polyline]).unwrap();
+
+    let copy = |dwg: &Dwg| Dwg::from_bytes(&dwg.to_bytes().unwrap()).unwrap();
+    let mut converted = copy(&dwg);
+    converted.save_as(DWGVersion::AC1018).unwrap();
+    let buf = converted.to_bytes().unwrap();
+    assert!(Dwg::verify(&buf).is_ok());
+    let read = Dwg::from_bytes(&buf).unwrap();
+    assert_eq!(read.version(), DWGVersion::AC1018);
+    assert_eq!(read.block_entities(space), dwg.block_entities(space));
+    assert_eq!(read.block_entities(space).unwrap()[0], line);
+    let vertices = read.polyline_3d(polyline).unwrap().vertices;
+    let points: Vec<_> = vertices.iter().map(|vertex| vertex.point.x).collect();
+    assert_eq!(points, [1.0, 2.0]);
+    assert!(vertices.iter().all(|vertex| vertex.header.links.is_none()));
+    let vars = read.header_variables().unwrap();
+    assert_eq!(vars.handseed(), dwg.header_variables().unwrap().handseed());
+    assert_eq!(vars.clayer(), Some(layer));
+    assert!(vars.get("DICTIONARY_COLORS").is_some());
+    assert!(vars.get("VIEWPORT_ENTITY_HEADER").is_none());
+    let classes = read.class_definitions().unwrap();
+    let without_counts = |classes: Vec<DwgClass>| -> Vec<_> {
+        classes
+            .into_iter()
+            .map(|class| DwgClass {
+                instance_count: 0,
+                ..class
+            })
+            .collect()
+    };
+    assert_eq!(without_counts(classes), dwg.class_definitions().unwrap());
+
+    // Converting back gives the drawing that was converted
+    converted.save_as(DWGVersion::AC1015).unwrap();
+    assert_eq!(converted.header_vars, dwg.header_vars);
+    assert_eq!(converted.classes, dwg.classes);
+    assert_eq!(converted.objects, dwg.objects);
+
+    // Objects of later versions are converted down, including their strings
+    let mut later = copy(&dwg);
+    later.objects = dwg.convert_objects(DWGVersion::AC1032).unwrap();
+    later.classes = dwg
+        .convert_classes(DWGVersion::AC1032, &later.objects)
+        .unwrap();
+    later.header_vars.clear();
+    later.set_version(DWGVersion::AC1032);
+    later.save_as(DWGVersion::AC1015).unwrap();
+    assert_eq!(later.classes, dwg.classes);
+    assert_eq!(later.objects, dwg.objects);
+
+    // Objects that are not typed cannot be converted, and leave the drawing unchanged
+    let view_control = dwg.handle_records[5];
+    let view = TableEntry::new(0x200, view_control, "view");
+    dwg.insert_object(0x200, object_types::VIEW, &view).unwrap();
+    let objects = dwg.objects.clone();
+    assert_eq!(
+        dwg.save_as(DWGVersion::AC1018),
+        Err(DwgError::ObjectConversionUnsupported {
+            handle: 0x200,
+            object_type: object_types::VIEW
+        })
+    );
+    assert_eq!(dwg.version(), DWGVersion::AC1015);
+    assert_eq!(dwg.objects, objects);
+}
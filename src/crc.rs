@@ -1,3 +1,5 @@
+use crate::error::{DwgError, Result};
+
 const CRC_TABLE8: [u16; 256] = [
     0x0000, 0xC0C1, 0xC181, 0x0140, 0xC301, 0x03C0, 0x0280, 0xC241, 0xC601, 0x06C0, 0x0780, 0xC741,
     0x0500, 0xC5C1, 0xC481, 0x0440, 0xCC01, 0x0CC0, 0x0D80, 0xCD41, 0x0F00, 0xCFC1, 0xCE81, 0x0E40,
@@ -23,13 +25,125 @@ const CRC_TABLE8: [u16; 256] = [
     0x4100, 0x81C1, 0x8081, 0x4040,
 ];
 
+/// Initial value used for the CRC of header variables, classes, objects and the object map
+pub const CRC8_SEED: u16 = 0xC0C1;
+
+/// The DWG "8 bit" CRC, a 16 bit CRC computed with an 8 bit lookup table
+///
+/// `dx` is the initial value, which allows the CRC to be computed incrementally by passing the
+/// result of the previous call
 pub fn crc8(dx: u16, data: &[u8]) -> u16 {
     let mut dx = dx;
     for data in data {
         let al = *data ^ ((dx & 0xFF) as u8);
         dx = (dx >> 8) & 0xFF;
-        // TODO: make sure bounds checking gets optimized out of this
-        dx = dx ^ CRC_TABLE8[(al & 0xFF) as usize];
+        dx ^= CRC_TABLE8[al as usize];
     }
     dx
 }
+
+/// Lookup table for the reflected CRC32 polynomial 0xEDB88320
+const CRC_TABLE32: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xEDB88320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// The CRC32 used by AC1018 (R2004) and later, the same CRC used by zlib
+///
+/// `seed` is the CRC of any preceding data, or 0 to start a new CRC
+pub fn crc32(seed: u32, data: &[u8]) -> u32 {
+    let mut crc = !seed;
+    for data in data {
+        crc = (crc >> 8) ^ CRC_TABLE32[((crc ^ *data as u32) & 0xFF) as usize];
+    }
+    !crc
+}
+
+/// The checksum of R2004 section pages, a variant of Adler-32 where the two sums are seeded
+/// from the high and low halves of `seed`
+///
+/// Data pages store one checksum of their compressed data with a seed of 0 and another of
+/// their header, seeded with the data checksum
+pub fn page_checksum(seed: u32, data: &[u8]) -> u32 {
+    // Largest number of bytes that can be summed before the sums must be reduced to avoid
+    // overflowing
+    const CHUNK_SIZE: usize = 0x15B0;
+    const MODULUS: u32 = 0xFFF1;
+    let mut sum1 = seed & 0xFFFF;
+    let mut sum2 = seed >> 16;
+    for chunk in data.chunks(CHUNK_SIZE) {
+        for &byte in chunk {
+            sum1 += byte as u32;
+            sum2 += sum1;
+        }
+        sum1 %= MODULUS;
+        sum2 %= MODULUS;
+    }
+    sum2 << 16 | sum1
+}
+
+/// Verifies the CRC32 of a section page
+///
+/// Fails with `SectionCrcMismatch` identifying `page` if the CRC of `data` is not `expected`
+pub fn verify_section_crc(page: u32, data: &[u8], expected: u32) -> Result<()> {
+    let found = crc32(0, data);
+    if found != expected {
+        return Err(DwgError::SectionCrcMismatch {
+            page,
+            expected,
+            found,
+        });
+    }
+    Ok(())
+}
+
+#[test]
+fn test_crc8() {
+    // Matches CRC-16/ARC
+    assert_eq!(crc8(0, b"123456789"), 0xBB3D);
+    let partial = crc8(CRC8_SEED, b"1234");
+    assert_eq!(crc8(partial, b"56789"), crc8(CRC8_SEED, b"123456789"));
+}
+
+#[test]
+fn test_page_checksum() {
+    // Matches Adler-32 when seeded with 1
+    assert_eq!(page_checksum(1, b"Wikipedia"), 0x11E60398);
+    let data = vec![0xFF; 0x10000];
+    let partial = page_checksum(0, &data[..0x15B0]);
+    assert_eq!(
+        page_checksum(partial, &data[0x15B0..]),
+        page_checksum(0, &data)
+    );
+}
+
+#[test]
+fn test_crc32() {
+    assert_eq!(crc32(0, b"123456789"), 0xCBF43926);
+    assert_eq!(crc32(crc32(0, b"1234"), b"56789"), 0xCBF43926);
+
+    assert_eq!(verify_section_crc(3, b"123456789", 0xCBF43926), Ok(()));
+    assert_eq!(
+        verify_section_crc(3, b"123456788", 0xCBF43926),
+        Err(DwgError::SectionCrcMismatch {
+            page: 3,
+            expected: 0xCBF43926,
+            found: crc32(0, b"123456788"),
+        })
+    );
+}
@@ -0,0 +1,356 @@
+//! Declarative decoding of object layouts
+//!
+//! Objects are declared with [`dwg_object!`](crate::dwg_object) as a list of fields and the
+//! bit codes they are stored as, which generates the struct and its [`DwgDecode`]
+//! implementation:
+//!
+//! ```
+//! use dwg_rs::{decode::codes::*, dwg_object};
+//!
+//! dwg_object! {
+//!     /// A circle entity
+//!     pub struct Circle {
+//!         pub center: BD3,
+//!         pub radius: BD,
+//!         pub thickness: BT,
+//!         pub extrusion: BE,
+//!         // Only present in AC1018 (R2004) and later, so this is an Option
+//!         pub color: CMC [AC1018..],
+//!     }
+//! }
+//! ```
+use crate::{
+    bitcodes::{BitReader, ByteSource, SliceSource},
+    error::Result,
+    streams::DualStreamReader,
+    types::HandleRef,
+};
+
+/// A type that can be decoded from the bit stream of an object
+pub trait DwgDecode: Sized {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self>;
+}
+
+/// A reader objects can be decoded from
+///
+/// Text and handles are read through this trait so that they can be routed to the string and
+/// handle streams of a [`DualStreamReader`]. Everything else is read from the data stream
+pub trait ObjectRead {
+    type Source: ByteSource;
+
+    /// Returns the data stream
+    fn data(&mut self) -> &mut BitReader<Self::Source>;
+
+    fn read_text(&mut self) -> Result<String> {
+        self.data().read_text()
+    }
+
+    fn read_text_unicode(&mut self) -> Result<String> {
+        self.data().read_text_unicode()
+    }
+
+    fn read_handle(&mut self) -> Result<HandleRef> {
+        self.data().read_handle()
+    }
+}
+
+impl<S: ByteSource> ObjectRead for BitReader<S> {
+    type Source = S;
+
+    fn data(&mut self) -> &mut BitReader<S> {
+        self
+    }
+}
+
+impl<'a> ObjectRead for DualStreamReader<'a> {
+    type Source = SliceSource<'a>;
+
+    fn data(&mut self) -> &mut BitReader<SliceSource<'a>> {
+        self
+    }
+
+    fn read_text(&mut self) -> Result<String> {
+        DualStreamReader::read_text(self)
+    }
+
+    fn read_text_unicode(&mut self) -> Result<String> {
+        DualStreamReader::read_text_unicode(self)
+    }
+
+    fn read_handle(&mut self) -> Result<HandleRef> {
+        DualStreamReader::read_handle(self)
+    }
+}
+
+/// A bit code that a field can be stored as, see [`codes`]
+pub trait Code {
+    /// The type the code is decoded to
+    type Value;
+
+    fn read<R: ObjectRead>(reader: &mut R) -> Result<Self::Value>;
+}
+
+/// Any `DwgDecode` type can be used as a code to nest objects
+impl<T: DwgDecode> Code for T {
+    type Value = T;
+
+    fn read<R: ObjectRead>(reader: &mut R) -> Result<T> {
+        T::decode(reader)
+    }
+}
+
+/// Marker types for the bit codes of the ODA specification
+///
+/// Codes with a leading dimension such as 3BD are written with it trailing, as in `BD3`
+pub mod codes {
+    use super::{Code, ObjectRead};
+    use crate::{
+        error::Result,
+        types::{CmColor, HandleRef, Point2, Point3},
+    };
+
+    macro_rules! data_codes {
+        ($($(#[$meta:meta])* $name:ident => $value:ty, $read:ident;)*) => {
+            $(
+                $(#[$meta])*
+                pub struct $name;
+
+                impl Code for $name {
+                    type Value = $value;
+
+                    fn read<R: ObjectRead>(reader: &mut R) -> Result<$value> {
+                        reader.data().$read()
+                    }
+                }
+            )*
+        };
+    }
+
+    data_codes! {
+        /// Bitshort
+        BS => i16, read_bitshort;
+        /// Bitlong
+        BL => i32, read_bitlong;
+        /// Bitlonglong
+        BLL => i64, read_bitlonglong;
+        /// Bitdouble
+        BD => f64, read_bitdouble;
+        /// Raw char
+        RC => i8, read_raw_char;
+        /// Raw short
+        RS => i16, read_raw_short;
+        /// Raw long
+        RL => i32, read_raw_long;
+        /// Raw double
+        RD => f64, read_raw_double;
+        /// Modular char
+        MC => i32, read_modular_char;
+        /// Unsigned modular char
+        UMC => u32, read_unsigned_modular_char;
+        /// Modular short
+        MS => i32, read_modular_short;
+        /// 2 raw doubles
+        RD2 => Point2, read_2rd;
+        /// 3 raw doubles
+        RD3 => Point3, read_3rd;
+        /// 2 bitdoubles
+        BD2 => Point2, read_2bd;
+        /// 3 bitdoubles
+        BD3 => Point3, read_3bd;
+        /// Bit thickness
+        BT => f64, read_bit_thickness;
+        /// Bit extrusion
+        BE => Point3, read_bit_extrusion;
+        /// CmColor
+        CMC => CmColor, read_cm_color;
+    }
+
+    /// Bit, decoded to a bool
+    pub struct B;
+
+    impl Code for B {
+        type Value = bool;
+
+        fn read<R: ObjectRead>(reader: &mut R) -> Result<bool> {
+            reader.data().read_bit().map(|bit| bit == 1)
+        }
+    }
+
+    /// Handle reference, read from the handle stream
+    pub struct H;
+
+    impl Code for H {
+        type Value = HandleRef;
+
+        fn read<R: ObjectRead>(reader: &mut R) -> Result<HandleRef> {
+            reader.read_handle()
+        }
+    }
+
+    /// Variable text, read from the string stream
+    pub struct TV;
+
+    impl Code for TV {
+        type Value = String;
+
+        fn read<R: ObjectRead>(reader: &mut R) -> Result<String> {
+            reader.read_text()
+        }
+    }
+
+    /// Unicode text, read from the string stream
+    pub struct TU;
+
+    impl Code for TU {
+        type Value = String;
+
+        fn read<R: ObjectRead>(reader: &mut R) -> Result<String> {
+            reader.read_text_unicode()
+        }
+    }
+}
+
+/// Declares a struct along with a [`DwgDecode`] implementation that reads its fields in
+/// order
+///
+/// Each field is given the [`Code`] it is stored as and has the type the code decodes to.
+/// A field followed by a range of versions such as `[AC1018..]`, `[..AC1021]` or
+/// `[AC1015..AC1021]` is only read for those versions, with the end exclusive, and is an
+/// `Option`. See the [module documentation](crate::decode) for an example
+#[macro_export]
+macro_rules! dwg_object {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_meta:meta])*
+                $field_vis:vis $field:ident : $code:path $([$($range:tt)*])?
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                $field_vis $field: $crate::dwg_object!(@type $code $([$($range)*])?),
+            )*
+        }
+
+        impl $crate::decode::DwgDecode for $name {
+            fn decode<R: $crate::decode::ObjectRead>(
+                reader: &mut R,
+            ) -> $crate::error::Result<Self> {
+                $(
+                    let $field = $crate::dwg_object!(@read reader $code $([$($range)*])?);
+                )*
+                Ok(Self { $($field),* })
+            }
+        }
+    };
+
+    (@type $code:path) => {
+        <$code as $crate::decode::Code>::Value
+    };
+    (@type $code:path [$($range:tt)*]) => {
+        Option<<$code as $crate::decode::Code>::Value>
+    };
+
+    (@read $reader:ident $code:path) => {
+        <$code as $crate::decode::Code>::read($reader)?
+    };
+    (@read $reader:ident $code:path [$($range:tt)*]) => {{
+        let version = $crate::decode::ObjectRead::data($reader).get_version();
+        if $crate::dwg_object!(@in_range version $($range)*) {
+            Some(<$code as $crate::decode::Code>::read($reader)?)
+        } else {
+            None
+        }
+    }};
+
+    (@in_range $version:ident $from:ident ..) => {
+        $version >= $crate::version::DWGVersion::$from
+    };
+    (@in_range $version:ident .. $to:ident) => {
+        $version < $crate::version::DWGVersion::$to
+    };
+    (@in_range $version:ident $from:ident .. $to:ident) => {
+        $version >= $crate::version::DWGVersion::$from
+            && $version < $crate::version::DWGVersion::$to
+    };
+}
+
+#[cfg(test)]
+dwg_object! {
+    #[derive(Debug, PartialEq)]
+    pub struct Inner {
+        pub flag: codes::B,
+        pub count: codes::BS,
+    }
+}
+
+#[cfg(test)]
+dwg_object! {
+    #[derive(Debug, PartialEq)]
+    pub struct Outer {
+        pub point: codes::BD3,
+        pub inner: Inner,
+        pub before_r2004: codes::BL [..AC1018],
+        pub since_r2004: codes::BS [AC1018..],
+        pub name: codes::TV,
+        pub owner: codes::H,
+    }
+}
+
+#[test]
+fn test_dwg_object() {
+    use crate::{
+        bitcodes::pack_bits,
+        types::{Point3, RefType},
+        version::DWGVersion,
+    };
+
+    let bits = [
+        // BD3 of (1.0, 0.0, 1.0)
+        (0b01, 2),
+        (0b10, 2),
+        (0b01, 2),
+        // Inner
+        (1, 1),
+        (0b10, 2),
+        // BL or BS of 0
+        (0b10, 2),
+        // TV "a"
+        (0b01, 2),
+        (1, 8),
+        (b'a' as u64, 8),
+        // H
+        (0x41, 8),
+        (0x1F, 8),
+    ];
+    let buf = pack_bits(&bits);
+    let expected = |before_r2004, since_r2004| Outer {
+        point: Point3 {
+            x: 1.0,
+            y: 0.0,
+            z: 1.0,
+        },
+        inner: Inner {
+            flag: true,
+            count: 0,
+        },
+        before_r2004,
+        since_r2004,
+        name: String::from("a"),
+        owner: HandleRef {
+            code: RefType::SoftPointer,
+            handle: 0x1F,
+        },
+    };
+
+    let mut reader = BitReader::new(buf.iter());
+    assert_eq!(Outer::decode(&mut reader), Ok(expected(Some(0), None)));
+
+    let mut reader = BitReader::new(buf.iter());
+    reader.set_version(DWGVersion::AC1018);
+    assert_eq!(Outer::decode(&mut reader), Ok(expected(None, Some(0))));
+}
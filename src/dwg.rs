@@ -1,103 +1,3079 @@
-use std::{fs::{self}, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self},
+};
 
-use crate::{bitcodes::BitReader, types::CodePage, version::DWGVersion};
+use crate::{
+    bitcodes::{BitReader, BitSliceReader, ByteSource},
+    bitcodes_write::BitWriter,
+    compression::{decompress_r2004, decompress_r2007},
+    crc::{crc32, crc8, page_checksum, CRC8_SEED},
+    dwg_file::DwgFile,
+    error::{DwgError, Result},
+    preview::{Preview, Thumbnail},
+    reed_solomon, sentinels,
+    summary_info::SummaryInfo,
+    types::{CodePage, JulianDate},
+    version::DWGVersion,
+};
 
+/// A drawing
+///
+/// Sections are currently kept as their raw data, without the sentinels, sizes and CRCs that
+/// surround them in the file, which are generated when the drawing is written
 pub struct Dwg {
     version: DWGVersion,
+    /// Maintenance release version, stored after the version magic number
+    pub maintenance_version: u8,
+    /// Codepage of text in the drawing, stored in the file header
+    pub codepage: CodePage,
+    /// Data of the header variables section
+    pub header_vars: Vec<u8>,
+    /// Data of the classes section
+    pub classes: Vec<u8>,
+    /// Data of each object by handle, without the MS size and CRC that surround it
+    ///
+    /// AC1024 (R2010) and later objects start with the MC size of their handle stream
+    pub objects: BTreeMap<u64, Vec<u8>>,
+    /// Data of sections that are not otherwise kept, by R2000 section locator record number
+    ///
+    /// These are written back unchanged. Records 0-3 are always generated from the rest of
+    /// the drawing, and record 4, the template, is generated if missing
+    pub raw_sections: BTreeMap<u8, Vec<u8>>,
+    /// Data of each section of AC1018 (R2004) and later files by name, as read, see
+    /// [`Dwg::section`]
+    ///
+    /// The header variables, classes, objects, object map, ObjFreeSpace and template are
+    /// generated from the rest of the drawing when written, other sections are written back
+    /// unchanged
+    pub sections: BTreeMap<String, Vec<u8>>,
+    /// Handles of the control objects and dictionaries listed in the second header, by record
+    /// number
+    ///
+    /// Record 0 is HANDSEED, the next free handle, which is raised past the last object when
+    /// written so handles are never reused, see [`Dwg::next_handle`]. Records 1-10
+    /// are the block, layer, style, linetype, view, UCS, viewport, appid, dimstyle and
+    /// viewport entity header controls, followed by the named object, MLINESTYLE and GROUP
+    /// dictionaries
+    pub handle_records: [u64; 14],
+    /// The preview image section, see [`Dwg::thumbnail`]
+    ///
+    /// This is written to R2000 files. Later files write back their "AcDb:Preview" section
+    /// from [`Dwg::sections`] unchanged instead
+    pub preview: Option<Preview>,
+    /// The ObjFreeSpace section, as read
+    ///
+    /// The object count and offset are regenerated when written, the date and handle values
+    /// are written back unchanged
+    pub obj_free_space: Option<ObjFreeSpace>,
 }
 
-fn read_obj_free_space<'a, I: Iterator<Item = &'a u8>>(
-    bit_reader: &mut BitReader<'a, I>,
-) -> Option<()> {
-    if bit_reader.get_version() <= DWGVersion::AC1021 {
-        let _x = bit_reader.read_raw_long()?;
-        let _approx_n_objects = bit_reader.read_raw_long()?;
-        let _y = bit_reader.read_raw_longlong()?;
-        let _object_section_offset = bit_reader.read_raw_long()?;
-        let _n_64b_vals = bit_reader.read_raw_char()?;
-        let _max32 = bit_reader.read_raw_longlong()?;
-        let _max64 = bit_reader.read_raw_longlong()?;
-        let _maxtbl = bit_reader.read_raw_longlong()?;
-        let _maxrl = bit_reader.read_raw_longlong()?;
+/// Units of a drawing, see [`Dwg::measurement`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Measurement {
+    English,
+    Metric,
+}
+
+/// A section locator record from the R13-R2000 file header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SectionLocator {
+    /// Record number, identifying the section
+    pub number: u8,
+    /// Offset of the section from the start of the file
+    pub seeker: u32,
+    /// Size of the section in bytes
+    pub size: u32,
+}
+
+/// A section of a drawing, whichever container the file uses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SectionKind {
+    Header,
+    Classes,
+    /// The object map, locating each object by handle
+    ObjectMap,
+    ObjFreeSpace,
+    Template,
+    AuxHeader,
+    /// The objects, which R13-R2000 files store loose between their sections
+    Objects,
+    Preview,
+    SummaryInfo,
+    AppInfo,
+    AppInfoHistory,
+    FileDepList,
+    RevHistory,
+    Security,
+    Signature,
+    VbaProject,
+    /// Data storage of AC1027 (R2013) and later files
+    AcDsPrototype,
+}
+
+impl SectionKind {
+    /// Every kind of section
+    pub const ALL: [SectionKind; 17] = [
+        Self::Header,
+        Self::Classes,
+        Self::ObjectMap,
+        Self::ObjFreeSpace,
+        Self::Template,
+        Self::AuxHeader,
+        Self::Objects,
+        Self::Preview,
+        Self::SummaryInfo,
+        Self::AppInfo,
+        Self::AppInfoHistory,
+        Self::FileDepList,
+        Self::RevHistory,
+        Self::Security,
+        Self::Signature,
+        Self::VbaProject,
+        Self::AcDsPrototype,
+    ];
+
+    /// Returns the name of the section in AC1018 (R2004) and later files
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Header => "AcDb:Header",
+            Self::Classes => "AcDb:Classes",
+            Self::ObjectMap => "AcDb:Handles",
+            Self::ObjFreeSpace => "AcDb:ObjFreeSpace",
+            Self::Template => "AcDb:Template",
+            Self::AuxHeader => "AcDb:AuxHeader",
+            Self::Objects => "AcDb:AcDbObjects",
+            Self::Preview => "AcDb:Preview",
+            Self::SummaryInfo => "AcDb:SummaryInfo",
+            Self::AppInfo => "AcDb:AppInfo",
+            Self::AppInfoHistory => "AcDb:AppInfoHistory",
+            Self::FileDepList => "AcDb:FileDepList",
+            Self::RevHistory => "AcDb:RevHistory",
+            Self::Security => "AcDb:Security",
+            Self::Signature => "AcDb:Signature",
+            Self::VbaProject => "AcDb:VBAProject",
+            Self::AcDsPrototype => "AcDb:AcDsPrototype_1b",
+        }
+    }
+
+    /// Returns the number of the section locator record of the section in R13-R2000 files,
+    /// if it has one
+    pub fn record_number(self) -> Option<u8> {
+        match self {
+            Self::Header => Some(0),
+            Self::Classes => Some(1),
+            Self::ObjectMap => Some(2),
+            Self::ObjFreeSpace => Some(3),
+            Self::Template => Some(4),
+            Self::AuxHeader => Some(5),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the data of a section, see [`SectionDirectory::get`]
+pub type SectionReader<'a> = BitSliceReader<'a>;
+
+/// Locates the sections of a drawing, hiding which container the file uses
+pub enum SectionDirectory<'a> {
+    /// The section locator records of an R13-R2000 file, locating sections in `bytes`, the
+    /// whole file, along with the address of the preview image from the file header
+    Locators {
+        bytes: &'a [u8],
+        records: &'a [SectionLocator],
+        preview_address: u64,
+    },
+    /// The decoded sections of an AC1018 (R2004) or later file by name
+    Named(&'a BTreeMap<String, Vec<u8>>),
+    /// The sections of an AC1018 (R2004) or later file, decoded when first asked for
+    Lazy(&'a DwgFile<'a>),
+}
+
+impl<'a> SectionDirectory<'a> {
+    /// Returns a reader over the data of a section, or None if the file does not have it
+    ///
+    /// The objects of R13-R2000 files are located by offsets from the start of the file, so
+    /// their reader spans the whole file, as does the reader of their preview image from its
+    /// start. Fails with `SeekOutOfRange` if a section locator record or the preview image
+    /// is past the end of the file
+    pub fn get(&self, kind: SectionKind) -> Result<Option<SectionReader<'a>>> {
+        let data = match *self {
+            Self::Locators { bytes, .. } if kind == SectionKind::Objects => bytes,
+            Self::Locators {
+                preview_address: 0, ..
+            } if kind == SectionKind::Preview => return Ok(None),
+            Self::Locators {
+                bytes,
+                preview_address,
+                ..
+            } if kind == SectionKind::Preview => {
+                bytes
+                    .get(preview_address as usize..)
+                    .ok_or(DwgError::SeekOutOfRange {
+                        offset: preview_address * 8,
+                    })?
+            }
+            Self::Locators { bytes, records, .. } => {
+                let Some(record) = records
+                    .iter()
+                    .find(|record| Some(record.number) == kind.record_number())
+                else {
+                    return Ok(None);
+                };
+                locator_data(bytes, record)?
+            }
+            Self::Named(sections) => match sections.get(kind.name()) {
+                Some(data) => data,
+                None => return Ok(None),
+            },
+            Self::Lazy(file) => match file.named_section(kind.name())? {
+                Some(data) => data,
+                None => return Ok(None),
+            },
+        };
+        Ok(Some(BitSliceReader::from_slice(data)))
+    }
+
+    /// Reads the data of the header variables section, with the high 32 bits of its size if
+    /// `size_high`, see [`has_size_high`]
+    pub(crate) fn header_vars(&self, size_high: bool) -> Result<Option<Vec<u8>>> {
+        self.get(SectionKind::Header)?
+            .map(|mut section| {
+                read_r2000_section(
+                    &mut section,
+                    0,
+                    &sentinels::HEADER_VARIABLES_START,
+                    &sentinels::HEADER_VARIABLES_END,
+                    size_high,
+                )
+            })
+            .transpose()
+    }
+
+    /// Reads the data of the classes section, see [`SectionDirectory::header_vars`]
+    pub(crate) fn classes(&self, size_high: bool) -> Result<Option<Vec<u8>>> {
+        self.get(SectionKind::Classes)?
+            .map(|mut section| {
+                read_r2000_section(
+                    &mut section,
+                    1,
+                    &sentinels::CLASSES_START,
+                    &sentinels::CLASSES_END,
+                    size_high,
+                )
+            })
+            .transpose()
+    }
+
+    /// Reads the data of every object of a drawing of `version` by handle, located by the
+    /// object map
+    pub(crate) fn objects(&self, version: DWGVersion) -> Result<Option<BTreeMap<u64, Vec<u8>>>> {
+        let (Some(map), Some(objects)) = (
+            self.get(SectionKind::ObjectMap)?,
+            self.get(SectionKind::Objects)?,
+        ) else {
+            return Ok(None);
+        };
+        read_objects(map.data(), objects.data(), version).map(Some)
+    }
+
+    /// Reads the object map, see [`read_object_map`]
+    pub(crate) fn object_map(&self) -> Result<Option<BTreeMap<u64, u64>>> {
+        self.get(SectionKind::ObjectMap)?
+            .map(|map| read_object_map(map.data()))
+            .transpose()
+    }
+
+    /// Reads the preview image section
+    pub(crate) fn preview(&self) -> Result<Option<Preview>> {
+        self.get(SectionKind::Preview)?
+            .map(|preview| Preview::read(preview.data()))
+            .transpose()
+    }
+}
+
+/// Returns the data of the section located by `record` in the R13-R2000 file `bytes`
+pub(crate) fn locator_data<'a>(bytes: &'a [u8], record: &SectionLocator) -> Result<&'a [u8]> {
+    let start = record.seeker as usize;
+    bytes
+        .get(start..start + record.size as usize)
+        .ok_or(DwgError::SeekOutOfRange {
+            offset: record.seeker as u64 * 8,
+        })
+}
+
+/// The R13-R2000 file header, see [`read_r2000_header`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct R2000FileHeader {
+    pub maintenance_version: u8,
+    pub codepage: CodePage,
+    /// Where each section is in the file, in the order they are listed
+    pub records: Vec<SectionLocator>,
+    /// Offset of the preview image from the start of the file, or 0 if there is none
+    pub preview_address: u64,
+    /// Size of the file header in bytes
+    pub size: u64,
+    /// Sanity checks that failed when the header was read leniently
+    pub warnings: Vec<ParseWarning>,
+}
+
+/// Options for reading a drawing, see [`Dwg::from_bytes_with`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Whether to fail on a [`ParseWarning`] rather than keep reading, which is the default
+    pub strict: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions { strict: true }
+    }
+}
+
+impl ParseOptions {
+    /// Fails with the error of `warning` in strict mode, otherwise keeps it in `warnings`
+    pub(crate) fn check(
+        self,
+        warnings: &mut Vec<ParseWarning>,
+        warning: ParseWarning,
+    ) -> Result<()> {
+        if self.strict {
+            return Err(warning.into());
+        }
+        warnings.push(warning);
+        Ok(())
+    }
+}
+
+/// Something unusual about a file that does not stop it being read, see [`ParseOptions`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseWarning {
+    /// A byte of unknown purpose that is always `expected` held another value
+    UnexpectedByte {
+        at_bit: u64,
+        expected: u8,
+        found: u8,
+    },
+    /// The CRC of the R13-R2000 file header does not match its data
+    FileHeaderCrcMismatch { expected: u16, found: u16 },
+    /// The R13-R2000 file header does not end with its sentinel, or a page of an AC1018
+    /// (R2004) file does not start with the type expected of it
+    BadSentinel { at_bit: u64 },
+    /// The checksum of a system page of an AC1018 (R2004) file does not match its data
+    SectionCrcMismatch {
+        page: u32,
+        expected: u32,
+        found: u32,
+    },
+    /// A checksum of a data page of an AC1018 (R2004) file does not match its data
+    PageChecksumMismatch {
+        section: String,
+        page: i32,
+        expected: u32,
+        found: u32,
+    },
+}
+
+impl From<ParseWarning> for DwgError {
+    fn from(warning: ParseWarning) -> DwgError {
+        match warning {
+            ParseWarning::UnexpectedByte { at_bit, found, .. } => DwgError::InvalidFlag {
+                at_bit,
+                value: found as u64,
+            },
+            ParseWarning::FileHeaderCrcMismatch { expected, found } => {
+                DwgError::FileHeaderCrcMismatch { expected, found }
+            }
+            ParseWarning::BadSentinel { at_bit } => DwgError::BadSentinel { at_bit },
+            ParseWarning::SectionCrcMismatch {
+                page,
+                expected,
+                found,
+            } => DwgError::SectionCrcMismatch {
+                page,
+                expected,
+                found,
+            },
+            ParseWarning::PageChecksumMismatch {
+                section,
+                page,
+                expected,
+                found,
+            } => DwgError::PageChecksumMismatch {
+                section,
+                page,
+                expected,
+                found,
+            },
+        }
+    }
+}
+
+impl From<SecondHeaderWarning> for DwgError {
+    fn from(warning: SecondHeaderWarning) -> DwgError {
+        DwgError::SecondHeaderMismatch(warning)
+    }
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedByte {
+                at_bit,
+                expected,
+                found,
+            } => write!(
+                f,
+                "byte at bit {at_bit} is {found:#04x} rather than {expected:#04x}"
+            ),
+            Self::FileHeaderCrcMismatch { expected, found } => write!(
+                f,
+                "file header CRC is {found:#06x} but {expected:#06x} is stored"
+            ),
+            Self::BadSentinel { at_bit } => write!(f, "bad sentinel at bit {at_bit}"),
+            Self::SectionCrcMismatch {
+                page,
+                expected,
+                found,
+            } => write!(
+                f,
+                "checksum of system page {page} is {found:#x} but {expected:#x} is stored"
+            ),
+            Self::PageChecksumMismatch {
+                section,
+                page,
+                expected,
+                found,
+            } => write!(
+                f,
+                "checksum of page {page} of section {section:?} is {found:#x} but {expected:#x} \
+                 is stored"
+            ),
+        }
+    }
+}
+
+/// Size of the encrypted AC1018 (R2004) file header
+pub const R2004_HEADER_SIZE: usize = 0x6C;
+
+/// Identifies the encrypted AC1018 (R2004) file header
+pub(crate) const R2004_FILE_ID: &[u8; 12] = b"AcFssFcAJMB\0";
+
+/// The fields of the AC1018 (R2004) file header, stored encrypted at 0x80
+///
+/// Addresses are relative to the end of the file header at 0x100, where the pages start
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct R2004Header {
+    pub root_tree_node_gap: i32,
+    pub lowermost_left_tree_node_gap: i32,
+    pub lowermost_right_tree_node_gap: i32,
+    /// Number of the last page in the file
+    pub last_section_page_id: u32,
+    /// Address of the end of the last page
+    pub last_section_page_end_address: u64,
+    /// Address of the copy of the file header at the end of the file, or 0 if there is none
+    pub second_header_address: u64,
+    /// Number of gaps between pages
+    pub gap_amount: u32,
+    /// Number of pages
+    pub section_page_amount: u32,
+    /// Number of the page holding the page map
+    pub section_page_map_id: u32,
+    /// Address of the page map
+    pub section_page_map_address: u64,
+    /// Number of the page holding the section map
+    pub section_map_id: u32,
+    pub section_page_array_size: u32,
+    pub gap_array_size: u32,
+}
+
+impl R2004Header {
+    /// Decrypts and decodes the header from the bytes stored at 0x80
+    ///
+    /// Fails with `BadSentinel` if the decrypted header does not start with the file ID
+    /// "AcFssFcAJMB" and with `SectionCrcMismatch` for page 0 if its CRC does not match
+    pub fn decrypt(encrypted: &[u8; R2004_HEADER_SIZE]) -> Result<Self> {
+        let mut data = *encrypted;
+        for (byte, mask) in data.iter_mut().zip(r2004_header_mask()) {
+            *byte ^= mask;
+        }
+        if &data[..R2004_FILE_ID.len()] != R2004_FILE_ID {
+            return Err(DwgError::BadSentinel { at_bit: 0 });
+        }
+        // The CRC is calculated with its own field set to 0
+        let (fields, crc) = data.split_at(R2004_HEADER_SIZE - 4);
+        let expected = u32::from_le_bytes(crc.try_into().unwrap());
+        let found = crc32(0, &[fields, &[0; 4]].concat());
+        if found != expected {
+            return Err(DwgError::SectionCrcMismatch {
+                page: 0,
+                expected,
+                found,
+            });
+        }
+
+        let mut reader = BitSliceReader::from_slice(&data);
+        reader.seek_to_bit(0x18 * 8)?;
+        let root_tree_node_gap = reader.read_raw_long()?;
+        let lowermost_left_tree_node_gap = reader.read_raw_long()?;
+        let lowermost_right_tree_node_gap = reader.read_raw_long()?;
+        // Unknown, always 1
+        reader.read_raw_long()?;
+        let last_section_page_id = reader.read_raw_long()? as u32;
+        let last_section_page_end_address = reader.read_raw_longlong()? as u64;
+        let second_header_address = reader.read_raw_longlong()? as u64;
+        let gap_amount = reader.read_raw_long()? as u32;
+        let section_page_amount = reader.read_raw_long()? as u32;
+        // Always 0x20, 0x80 and 0x40
+        for _ in 0..3 {
+            reader.read_raw_long()?;
+        }
+        Ok(Self {
+            root_tree_node_gap,
+            lowermost_left_tree_node_gap,
+            lowermost_right_tree_node_gap,
+            last_section_page_id,
+            last_section_page_end_address,
+            second_header_address,
+            gap_amount,
+            section_page_amount,
+            section_page_map_id: reader.read_raw_long()? as u32,
+            section_page_map_address: reader.read_raw_longlong()? as u64,
+            section_map_id: reader.read_raw_long()? as u32,
+            section_page_array_size: reader.read_raw_long()? as u32,
+            gap_array_size: reader.read_raw_long()? as u32,
+        })
+    }
+
+    /// Encodes and encrypts the header, the inverse of [`R2004Header::decrypt`]
+    pub fn encrypt(&self) -> [u8; R2004_HEADER_SIZE] {
+        let mut writer = BitWriter::new();
+        writer.write_bytes(R2004_FILE_ID);
+        writer.write_raw_long(0);
+        writer.write_raw_long(R2004_HEADER_SIZE as i32);
+        writer.write_raw_long(4);
+        writer.write_raw_long(self.root_tree_node_gap);
+        writer.write_raw_long(self.lowermost_left_tree_node_gap);
+        writer.write_raw_long(self.lowermost_right_tree_node_gap);
+        writer.write_raw_long(1);
+        writer.write_raw_long(self.last_section_page_id as i32);
+        writer.write_raw_longlong(self.last_section_page_end_address as i64);
+        writer.write_raw_longlong(self.second_header_address as i64);
+        writer.write_raw_long(self.gap_amount as i32);
+        writer.write_raw_long(self.section_page_amount as i32);
+        writer.write_raw_long(0x20);
+        writer.write_raw_long(0x80);
+        writer.write_raw_long(0x40);
+        writer.write_raw_long(self.section_page_map_id as i32);
+        writer.write_raw_longlong(self.section_page_map_address as i64);
+        writer.write_raw_long(self.section_map_id as i32);
+        writer.write_raw_long(self.section_page_array_size as i32);
+        writer.write_raw_long(self.gap_array_size as i32);
+        let crc = crc32(0, &[writer.as_bytes(), &[0; 4]].concat());
+        writer.write_raw_long(crc as i32);
+
+        let mut encrypted: [u8; R2004_HEADER_SIZE] = writer.as_bytes().try_into().unwrap();
+        for (byte, mask) in encrypted.iter_mut().zip(r2004_header_mask()) {
+            *byte ^= mask;
+        }
+        encrypted
+    }
+}
+
+/// The AC1018 (R2004) file header, the unencrypted fields at the start of the file followed
+/// by the encrypted [`R2004Header`]
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct R2004FileHeader {
+    pub maintenance_version: u8,
+    pub codepage: CodePage,
+    pub header: R2004Header,
+}
+
+/// Reads the AC1018 (R2004) file header from the start of the file
+pub(crate) fn read_r2004_file_header(bit_reader: &mut BitSliceReader) -> Result<R2004FileHeader> {
+    let (maintenance_version, codepage) = read_file_header_start(bit_reader)?;
+    bit_reader.seek_to_bit(0x80 * 8)?;
+    let mut encrypted = [0; R2004_HEADER_SIZE];
+    bit_reader.read_bytes(&mut encrypted)?;
+    Ok(R2004FileHeader {
+        maintenance_version,
+        codepage,
+        header: R2004Header::decrypt(&encrypted)?,
+    })
+}
+
+/// Security flag of AC1018 (R2004) and later files whose data is encrypted with a password
+pub const ENCRYPT_DATA: u32 = 0x1;
+
+/// Security flag of AC1018 (R2004) and later files whose properties, such as the
+/// SummaryInfo section, are encrypted with a password
+pub const ENCRYPT_PROPERTIES: u32 = 0x2;
+
+/// Reads the version, maintenance version and codepage at the start of the file header of
+/// AC1018 (R2004) and later files
+///
+/// Fails with `Encrypted` if the security flags say the file is protected by a password, as
+/// the key derivation of the encryption is not documented
+fn read_file_header_start(bit_reader: &mut BitSliceReader) -> Result<(u8, CodePage)> {
+    let version = bit_reader.read_version()?;
+    bit_reader.set_version(version);
+    let mut skipped = [0; 5];
+    bit_reader.read_bytes(&mut skipped)?;
+    let maintenance_version = bit_reader.read_raw_char()? as u8;
+    // Always 1, then the address of the preview image and the versions of the application
+    // that wrote the file
+    bit_reader.read_raw_char()?;
+    bit_reader.read_raw_long()?;
+    bit_reader.read_raw_short()?;
+    let at_bit = bit_reader.bit_position();
+    let codepage = bit_reader.read_raw_short()? as u16;
+    let codepage = CodePage::from_repr(codepage).ok_or(DwgError::InvalidFlag {
+        at_bit,
+        value: codepage as u64,
+    })?;
+    let mut skipped = [0; 3];
+    bit_reader.read_bytes(&mut skipped)?;
+    let flags = bit_reader.read_raw_long()? as u32;
+    if flags & (ENCRYPT_DATA | ENCRYPT_PROPERTIES) != 0 {
+        return Err(DwgError::Encrypted { flags });
+    }
+    Ok((maintenance_version, codepage))
+}
+
+/// Number of Reed-Solomon blocks holding the AC1021 (R2007) file header at 0x80
+pub(crate) const R2007_HEADER_BLOCKS: usize = 3;
+
+/// Size of the decoded fields of the AC1021 (R2007) file header
+const R2007_HEADER_SIZE: usize = 0x110;
+
+/// The fields of the AC1021 (R2007) file header, stored Reed-Solomon encoded at 0x80
+///
+/// Every field is an RLL. Offsets are from the end of the file header at 0x480. The CRCs
+/// are CRC64s of the maps, seeded with their CRC seed
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct R2007Header {
+    pub header_size: u64,
+    pub file_size: u64,
+    pub pages_map_crc_compressed: u64,
+    pub pages_map_correction_factor: u64,
+    pub pages_map_crc_seed: u64,
+    pub pages_map2_offset: u64,
+    pub pages_map2_id: u64,
+    pub pages_map_offset: u64,
+    pub pages_map_id: u64,
+    pub header2_offset: u64,
+    pub pages_map_size_compressed: u64,
+    pub pages_map_size_uncompressed: u64,
+    pub pages_amount: u64,
+    pub pages_max_id: u64,
+    pub pages_map_crc_uncompressed: u64,
+    pub sections_amount: u64,
+    pub sections_map_crc_uncompressed: u64,
+    pub sections_map_size_compressed: u64,
+    pub sections_map2_id: u64,
+    pub sections_map_id: u64,
+    pub sections_map_size_uncompressed: u64,
+    pub sections_map_crc_compressed: u64,
+    pub sections_map_correction_factor: u64,
+    pub sections_map_crc_seed: u64,
+    pub stream_version: u64,
+    pub crc_seed: u64,
+    pub crc_seed_encoded: u64,
+    pub random_seed: u64,
+    pub header_crc: u64,
+}
+
+impl R2007Header {
+    /// Decodes the header from the Reed-Solomon encoded bytes stored at 0x80, correcting any
+    /// errors the code allows
+    ///
+    /// The decoded data starts with an RLL CRC, RLL key, RLL CRC of the compressed data, RL
+    /// compressed size and another RL size, followed by the compressed fields. A negative
+    /// compressed size means the fields are stored uncompressed
+    pub fn decode(encoded: &[u8]) -> Result<Self> {
+        let data = reed_solomon::decode(encoded, R2007_HEADER_BLOCKS)?;
+        let mut reader = BitSliceReader::from_slice(&data);
+        let _crc = reader.read_raw_longlong()?;
+        let _key = reader.read_raw_longlong()?;
+        let _compressed_crc = reader.read_raw_longlong()?;
+        let at_bit = reader.bit_position();
+        let compressed_size = reader.read_raw_long()?;
+        let _size = reader.read_raw_long()?;
+        let stored = data
+            .get(32..32 + compressed_size.unsigned_abs() as usize)
+            .ok_or(DwgError::InvalidStreamSize { at_bit })?;
+        let fields = if compressed_size < 0 {
+            stored.to_vec()
+        } else {
+            decompress_r2007(stored, R2007_HEADER_SIZE)?
+        };
+        if fields.len() < R2007_HEADER_SIZE {
+            return Err(DwgError::InvalidStreamSize { at_bit });
+        }
+
+        let mut reader = BitSliceReader::from_slice(&fields);
+        let mut rll = || -> Result<u64> { Ok(reader.read_raw_longlong()? as u64) };
+        Ok(Self {
+            header_size: rll()?,
+            file_size: rll()?,
+            pages_map_crc_compressed: rll()?,
+            pages_map_correction_factor: rll()?,
+            pages_map_crc_seed: rll()?,
+            pages_map2_offset: rll()?,
+            pages_map2_id: rll()?,
+            pages_map_offset: rll()?,
+            pages_map_id: rll()?,
+            header2_offset: rll()?,
+            pages_map_size_compressed: rll()?,
+            pages_map_size_uncompressed: rll()?,
+            pages_amount: rll()?,
+            pages_max_id: rll()?,
+            // Follows two unknown RLLs, always 0x20 and 0x40
+            pages_map_crc_uncompressed: {
+                rll()?;
+                rll()?;
+                rll()?
+            },
+            // Follows three unknown RLLs, always 0xF800, 4 and 1
+            sections_amount: {
+                rll()?;
+                rll()?;
+                rll()?;
+                rll()?
+            },
+            sections_map_crc_uncompressed: rll()?,
+            sections_map_size_compressed: rll()?,
+            sections_map2_id: rll()?,
+            sections_map_id: rll()?,
+            sections_map_size_uncompressed: rll()?,
+            sections_map_crc_compressed: rll()?,
+            sections_map_correction_factor: rll()?,
+            sections_map_crc_seed: rll()?,
+            stream_version: rll()?,
+            crc_seed: rll()?,
+            crc_seed_encoded: rll()?,
+            random_seed: rll()?,
+            header_crc: rll()?,
+        })
+    }
+
+    /// Encodes the header uncompressed, the inverse of [`R2007Header::decode`]
+    #[cfg(test)]
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let words = [
+            self.header_size,
+            self.file_size,
+            self.pages_map_crc_compressed,
+            self.pages_map_correction_factor,
+            self.pages_map_crc_seed,
+            self.pages_map2_offset,
+            self.pages_map2_id,
+            self.pages_map_offset,
+            self.pages_map_id,
+            self.header2_offset,
+            self.pages_map_size_compressed,
+            self.pages_map_size_uncompressed,
+            self.pages_amount,
+            self.pages_max_id,
+            0x20,
+            0x40,
+            self.pages_map_crc_uncompressed,
+            0xF800,
+            4,
+            1,
+            self.sections_amount,
+            self.sections_map_crc_uncompressed,
+            self.sections_map_size_compressed,
+            self.sections_map2_id,
+            self.sections_map_id,
+            self.sections_map_size_uncompressed,
+            self.sections_map_crc_compressed,
+            self.sections_map_correction_factor,
+            self.sections_map_crc_seed,
+            self.stream_version,
+            self.crc_seed,
+            self.crc_seed_encoded,
+            self.random_seed,
+            self.header_crc,
+        ];
+        let mut writer = BitWriter::new();
+        writer.write_bytes(&[0; 24]);
+        writer.write_raw_long(-(R2007_HEADER_SIZE as i32));
+        writer.write_raw_long(R2007_HEADER_SIZE as i32);
+        for word in words {
+            writer.write_raw_longlong(word as i64);
+        }
+        reed_solomon::encode(writer.as_bytes(), R2007_HEADER_BLOCKS)
+    }
+}
+
+/// The AC1021 (R2007) file header, the fields at the start of the file followed by the
+/// encoded [`R2007Header`]
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct R2007FileHeader {
+    pub maintenance_version: u8,
+    pub codepage: CodePage,
+    pub header: R2007Header,
+}
+
+/// Reads the AC1021 (R2007) file header from the start of the file
+pub(crate) fn read_r2007_file_header(bit_reader: &mut BitSliceReader) -> Result<R2007FileHeader> {
+    let (maintenance_version, codepage) = read_file_header_start(bit_reader)?;
+    bit_reader.seek_to_bit(0x80 * 8)?;
+    let encoded = bit_reader.read_vec(R2007_HEADER_BLOCKS * reed_solomon::BLOCK_SIZE)?;
+    Ok(R2007FileHeader {
+        maintenance_version,
+        codepage,
+        header: R2007Header::decode(&encoded)?,
+    })
+}
+
+/// Offset of the first page of AC1018 (R2004) files, following the file header
+pub(crate) const R2004_PAGES_START: usize = 0x100;
+
+/// Largest amount of section data held in each AC1018 (R2004) data page
+pub(crate) const R2004_PAGE_DATA_SIZE: usize = 0x7400;
+
+/// Pages of AC1018 (R2004) files are padded to a multiple of this size
+pub(crate) const R2004_PAGE_ALIGNMENT: usize = 0x20;
+
+/// Type of AC1018 (R2004) pages holding section data
+pub(crate) const R2004_DATA_PAGE_TYPE: u32 = 0x4163043B;
+
+/// Type of the AC1018 (R2004) system page holding the section map
+pub(crate) const R2004_SECTION_MAP_TYPE: u32 = 0x4163003B;
+
+/// Type of the AC1018 (R2004) system page holding the page map
+pub(crate) const R2004_PAGE_MAP_TYPE: u32 = 0x41630E3B;
+
+/// Each RL of an AC1018 (R2004) data page header is XORed with this and the address of the
+/// page
+pub(crate) const R2004_DATA_PAGE_MASK: u32 = 0x4164536B;
+
+/// Returns the bytes the AC1018 (R2004) file header is XORed with
+///
+/// These come from a linear congruential generator seeded with 1, taking bits 16-23 of each
+/// value
+pub(crate) fn r2004_header_mask() -> [u8; R2004_HEADER_SIZE] {
+    let mut mask = [0u8; R2004_HEADER_SIZE];
+    let mut seed = 1u32;
+    for byte in mask.iter_mut() {
+        seed = seed.wrapping_mul(0x343FD).wrapping_add(0x269EC3);
+        *byte = (seed >> 16) as u8;
+    }
+    mask
+}
+
+/// A page or gap listed in the page map of an AC1018 (R2004) or later file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageMapEntry {
+    /// Number of the page, referred to by the file header and section map, or a negative
+    /// number for a gap, an unused page
+    pub number: i32,
+    /// Size of the page in the file, including its header and padding
+    pub size: u32,
+    /// Offset of the page from the start of the file
+    pub address: u64,
+}
+
+/// The page map of an AC1018 (R2004) or later file, locating every page in the file
+///
+/// Pages follow each other from the end of the file header, so each address is the sum of
+/// the sizes of the pages before it
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PageMap {
+    /// The pages and gaps, in the order they are stored in the file
+    pub pages: Vec<PageMapEntry>,
+}
+
+impl PageMap {
+    /// Parses the decompressed data of the page map system page
+    ///
+    /// Each entry is an RL page number and an RL size. Gaps are also followed by the parent,
+    /// left and right gaps of a tree of free pages and an unknown RL, which are not kept
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut reader = BitSliceReader::from_slice(data);
+        let mut address = R2004_PAGES_START as u64;
+        let mut pages = Vec::new();
+        while reader.bit_position() < data.len() as u64 * 8 {
+            let number = reader.read_raw_long()?;
+            let size = reader.read_raw_long()? as u32;
+            if number < 0 {
+                for _ in 0..4 {
+                    reader.read_raw_long()?;
+                }
+            }
+            pages.push(PageMapEntry {
+                number,
+                size,
+                address,
+            });
+            address += size as u64;
+        }
+        Ok(Self { pages })
+    }
+
+    /// Parses the decoded data of the AC1021 (R2007) pages map system page
+    ///
+    /// Each entry is an RLL size and an RLL page number, negative for a gap
+    pub fn parse_r2007(data: &[u8]) -> Result<Self> {
+        let mut reader = BitSliceReader::from_slice(data);
+        let mut address = R2007_PAGES_START;
+        let mut pages = Vec::new();
+        while reader.bit_position() < data.len() as u64 * 8 {
+            let size = reader.read_raw_longlong()? as u64;
+            let number = reader.read_raw_longlong()? as i32;
+            pages.push(PageMapEntry {
+                number,
+                size: size as u32,
+                address,
+            });
+            address += size;
+        }
+        Ok(Self { pages })
+    }
+
+    /// Returns the page numbered `number`, if listed
+    pub fn page(&self, number: i32) -> Option<&PageMapEntry> {
+        self.pages.iter().find(|page| page.number == number)
+    }
+
+    /// Returns the gaps between pages
+    pub fn gaps(&self) -> impl Iterator<Item = &PageMapEntry> {
+        self.pages.iter().filter(|page| page.number < 0)
+    }
+}
+
+/// Reads the system page `number` of type `page_type` at `address` in an AC1018 (R2004)
+/// file, returning its decompressed data
+///
+/// The page header is an RL each of the page type, decompressed size, compressed size,
+/// compression type and checksum. Fails with `BadSentinel` if the type does not match and
+/// `SectionCrcMismatch` if the checksum does not, unless `options` are lenient, when they are
+/// kept in `warnings` instead
+pub(crate) fn read_system_page(
+    bytes: &[u8],
+    address: u64,
+    page_type: u32,
+    number: i32,
+    options: ParseOptions,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<Vec<u8>> {
+    let mut reader = BitSliceReader::from_slice(bytes);
+    reader.seek_to_bit(address * 8)?;
+    let mut header = [0u32; 5];
+    for word in header.iter_mut() {
+        *word = reader.read_raw_long()? as u32;
+    }
+    let [found_type, size, compressed_size, compression, expected] = header;
+    if found_type != page_type {
+        let at_bit = address * 8;
+        options.check(warnings, ParseWarning::BadSentinel { at_bit })?;
+    }
+    let at_bit = reader.bit_position();
+    let page_data = reader.read_vec(compressed_size as usize)?;
+
+    // The checksum is of the header with its own field set to 0, seeded with the checksum of
+    // the data
+    header[4] = 0;
+    let header_bytes: Vec<u8> = header.iter().flat_map(|word| word.to_le_bytes()).collect();
+    let found = page_checksum(page_checksum(0, &page_data), &header_bytes);
+    if found != expected {
+        let page = number as u32;
+        options.check(
+            warnings,
+            ParseWarning::SectionCrcMismatch {
+                page,
+                expected,
+                found,
+            },
+        )?;
+    }
+
+    let data = match compression {
+        1 => page_data,
+        2 => decompress_r2004(&page_data)?,
+        value => {
+            return Err(DwgError::InvalidFlag {
+                at_bit: at_bit - 0x40,
+                value: value as u64,
+            })
+        }
+    };
+    if data.len() != size as usize {
+        return Err(DwgError::InvalidStreamSize {
+            at_bit: at_bit + compressed_size as u64 * 8,
+        });
+    }
+    Ok(data)
+}
+
+/// Reads the page map of an AC1018 (R2004) file located by its file header, see
+/// [`read_system_page`]
+pub(crate) fn read_r2004_page_map(
+    bytes: &[u8],
+    header: &R2004Header,
+    options: ParseOptions,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<PageMap> {
+    let address = header.section_page_map_address + R2004_PAGES_START as u64;
+    let data = read_system_page(
+        bytes,
+        address,
+        R2004_PAGE_MAP_TYPE,
+        header.section_page_map_id as i32,
+        options,
+        warnings,
+    )?;
+    PageMap::parse(&data)
+}
+
+/// A page of section data listed in the section map of an AC1018 (R2004) or later file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SectionPage {
+    /// Number of the page in the page map
+    pub number: i32,
+    /// Size of the possibly compressed data of the page
+    pub data_size: u32,
+    /// Size of the decompressed data of the page
+    pub size: u64,
+    /// Offset of the decompressed data of the page within the section
+    pub start: u64,
+}
+
+/// A section listed in the section map of an AC1018 (R2004) or later file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SectionDescriptor {
+    /// Name of the section, such as "AcDb:Header"
+    pub name: String,
+    /// Size of the decompressed data of the section
+    pub size: u64,
+    /// Largest decompressed size of each page, usually 0x7400
+    pub max_page_size: u32,
+    /// Compression type, 1 if the data is stored as is and 2 if compressed
+    ///
+    /// The pages of AC1021 (R2007) sections are each compressed only if that makes them
+    /// smaller, so this is always 2
+    pub compression: u32,
+    /// Number of the section, stored in the header of each of its pages
+    ///
+    /// AC1021 (R2007) pages have no header, so this is the hash of the section name instead
+    pub id: u32,
+    /// 1 if the data is encrypted, 2 if this is unknown and 0 otherwise
+    pub encrypted: u32,
+    /// The pages holding the section data, in order
+    pub pages: Vec<SectionPage>,
+}
+
+/// The section map of an AC1018 (R2004) or later file, listing the pages of each section
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SectionMap {
+    /// The sections, in the order they are listed
+    pub sections: Vec<SectionDescriptor>,
+}
+
+impl SectionMap {
+    /// Parses the decompressed data of the section map system page
+    ///
+    /// The map starts with the number of sections, compression type, largest page size,
+    /// encryption and the number of sections again. Each section is an RLL size, RL page
+    /// count, RL largest page size, an unknown RL, RL compression type, RL id, RL encryption
+    /// and a 64 byte name, followed by an RL number, RL data size and RLL start of each page
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut reader = BitSliceReader::from_slice(data);
+        let n_sections = reader.read_raw_long()?;
+        for _ in 0..4 {
+            reader.read_raw_long()?;
+        }
+        let mut sections = Vec::new();
+        for _ in 0..n_sections {
+            let size = reader.read_raw_longlong()? as u64;
+            let n_pages = reader.read_raw_long()?;
+            let max_page_size = reader.read_raw_long()? as u32;
+            reader.read_raw_long()?;
+            let compression = reader.read_raw_long()? as u32;
+            let id = reader.read_raw_long()? as u32;
+            let encrypted = reader.read_raw_long()? as u32;
+            let name = reader.read_vec(64)?;
+            let name_len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+            let mut pages = Vec::new();
+            for _ in 0..n_pages {
+                let number = reader.read_raw_long()?;
+                let data_size = reader.read_raw_long()? as u32;
+                let start = reader.read_raw_longlong()? as u64;
+                pages.push(SectionPage {
+                    number,
+                    data_size,
+                    size: size.saturating_sub(start).min(max_page_size as u64),
+                    start,
+                });
+            }
+            sections.push(SectionDescriptor {
+                name: String::from_utf8_lossy(&name[..name_len]).into_owned(),
+                size,
+                max_page_size,
+                compression,
+                id,
+                encrypted,
+                pages,
+            });
+        }
+        Ok(Self { sections })
+    }
+
+    /// Parses the decoded data of the AC1021 (R2007) sections map system page
+    ///
+    /// Each section is an RLL size, RLL largest page size, RLL encryption, RLL hash of the
+    /// name, RLL name size, an unknown RLL, RLL encoding and RLL page count, followed by the
+    /// UTF-16 name. Each page is an RLL start, RLL size in the file, RLL number, RLL
+    /// decompressed size, RLL data size, RLL checksum and RLL CRC
+    pub fn parse_r2007(data: &[u8]) -> Result<Self> {
+        let mut reader = BitSliceReader::from_slice(data);
+        let mut sections = Vec::new();
+        while reader.bit_position() < data.len() as u64 * 8 {
+            let size = reader.read_raw_longlong()? as u64;
+            let max_page_size = reader.read_raw_longlong()? as u32;
+            let encrypted = reader.read_raw_longlong()? as u32;
+            let id = reader.read_raw_longlong()? as u32;
+            let name_size = reader.read_raw_longlong()? as usize;
+            let _unknown = reader.read_raw_longlong()?;
+            let _encoding = reader.read_raw_longlong()?;
+            let n_pages = reader.read_raw_longlong()?;
+            let name: Vec<u16> = reader
+                .read_vec(name_size)?
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .take_while(|&c| c != 0)
+                .collect();
+            let mut pages = Vec::new();
+            for _ in 0..n_pages {
+                let start = reader.read_raw_longlong()? as u64;
+                let _page_size = reader.read_raw_longlong()?;
+                let number = reader.read_raw_longlong()? as i32;
+                let page_size = reader.read_raw_longlong()? as u64;
+                let data_size = reader.read_raw_longlong()? as u32;
+                let _checksum = reader.read_raw_longlong()?;
+                let _crc = reader.read_raw_longlong()?;
+                pages.push(SectionPage {
+                    number,
+                    data_size,
+                    size: page_size,
+                    start,
+                });
+            }
+            sections.push(SectionDescriptor {
+                name: String::from_utf16_lossy(&name),
+                size,
+                max_page_size,
+                compression: 2,
+                id,
+                encrypted,
+                pages,
+            });
+        }
+        Ok(Self { sections })
+    }
+
+    /// Returns the section named `name`, if listed
+    pub fn section(&self, name: &str) -> Option<&SectionDescriptor> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+}
+
+/// Reads the section map of an AC1018 (R2004) file located by its file header and page map,
+/// see [`read_system_page`]
+pub(crate) fn read_r2004_section_map(
+    bytes: &[u8],
+    header: &R2004Header,
+    page_map: &PageMap,
+    options: ParseOptions,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<SectionMap> {
+    let number = header.section_map_id as i32;
+    let page = page_map
+        .page(number)
+        .ok_or(DwgError::MissingSectionPage { number })?;
+    let data = read_system_page(
+        bytes,
+        page.address,
+        R2004_SECTION_MAP_TYPE,
+        number,
+        options,
+        warnings,
+    )?;
+    SectionMap::parse(&data)
+}
+
+/// Checks that the pages of `section` can hold its size, so that a corrupt section map cannot
+/// make reading it allocate any size it claims
+///
+/// Fails with `InvalidStreamSize` if the size is more than its number of pages times its
+/// largest page size
+fn check_section_size(section: &SectionDescriptor) -> Result<()> {
+    let capacity = section.pages.len() as u64 * section.max_page_size as u64;
+    if section.size > capacity {
+        return Err(DwgError::InvalidStreamSize { at_bit: 0 });
+    }
+    Ok(())
+}
+
+/// Copies the decompressed data of a page to `start` within the data of `section`, growing
+/// `data` to the end of the page
+///
+/// Fails with `InvalidStreamSize` at `at_bit` if the page ends past the size of the section
+fn copy_section_page(
+    data: &mut Vec<u8>,
+    section: &SectionDescriptor,
+    start: u64,
+    page_data: &[u8],
+    at_bit: u64,
+) -> Result<()> {
+    let end = start
+        .checked_add(page_data.len() as u64)
+        .filter(|&end| end <= section.size)
+        .ok_or(DwgError::InvalidStreamSize { at_bit })?;
+    let (start, end) = (start as usize, end as usize);
+    if data.len() < end {
+        data.resize(end, 0);
+    }
+    data[start..end].copy_from_slice(page_data);
+    Ok(())
+}
+
+/// Reads and decompresses the data of `section` from the data pages of an AC1018 (R2004)
+/// file
+///
+/// Each RL of a data page header is XORed with [`R2004_DATA_PAGE_MASK`] and the address of
+/// the page. The header is the page type, section id, data size, decompressed size, RLL
+/// start within the section and the checksums of the header and data. Fails with
+/// `BadSentinel` if the page type does not match and `PageChecksumMismatch` naming the
+/// section and page if either checksum does not, unless `options` are lenient, when they are
+/// kept in `warnings` instead. Fails with `InvalidStreamSize` if the section is larger than
+/// its pages can hold or a page lies outside it
+pub(crate) fn read_r2004_section(
+    bytes: &[u8],
+    page_map: &PageMap,
+    section: &SectionDescriptor,
+    options: ParseOptions,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<Vec<u8>> {
+    check_section_size(section)?;
+    let mut data = Vec::new();
+    let mut reader = BitSliceReader::from_slice(bytes);
+    for page in &section.pages {
+        let address = page_map
+            .page(page.number)
+            .ok_or(DwgError::MissingSectionPage {
+                number: page.number,
+            })?
+            .address;
+        reader.seek_to_bit(address * 8)?;
+        let mask = R2004_DATA_PAGE_MASK ^ address as u32;
+        let mut header = [0u32; 8];
+        for word in header.iter_mut() {
+            *word = reader.read_raw_long()? as u32 ^ mask;
+        }
+        if header[0] != R2004_DATA_PAGE_TYPE {
+            let at_bit = address * 8;
+            options.check(warnings, ParseWarning::BadSentinel { at_bit })?;
+        }
+        if header[1] != section.id {
+            return Err(DwgError::InvalidFlag {
+                at_bit: address * 8 + 32,
+                value: header[1] as u64,
+            });
+        }
+        let page_data = reader.read_vec(header[2] as usize)?;
+        if let Err(warning) = verify_data_page_checksums(section, page.number, header, &page_data) {
+            options.check(warnings, warning)?;
+        }
+        let page_data = match section.compression {
+            2 => decompress_r2004(&page_data)?,
+            _ => page_data,
+        };
+        let start = header[4] as u64 | (header[5] as u64) << 32;
+        let at_bit = reader.bit_position();
+        copy_section_page(&mut data, section, start, &page_data, at_bit)?;
+    }
+    data.resize(section.size as usize, 0);
+    Ok(data)
+}
+
+/// Verifies the checksums of data page `number` of `section`, one of the compressed data
+/// seeded with 0 and one of the decrypted header with its own field set to 0, seeded with
+/// the data checksum
+fn verify_data_page_checksums(
+    section: &SectionDescriptor,
+    number: i32,
+    mut header: [u32; 8],
+    data: &[u8],
+) -> std::result::Result<(), ParseWarning> {
+    let mismatch = |expected, found| ParseWarning::PageChecksumMismatch {
+        section: section.name.clone(),
+        page: number,
+        expected,
+        found,
+    };
+    let data_checksum = page_checksum(0, data);
+    if data_checksum != header[7] {
+        return Err(mismatch(header[7], data_checksum));
+    }
+    let expected = std::mem::take(&mut header[6]);
+    let header_bytes: Vec<u8> = header.iter().flat_map(|word| word.to_le_bytes()).collect();
+    let found = page_checksum(data_checksum, &header_bytes);
+    if found != expected {
+        return Err(mismatch(expected, found));
+    }
+    Ok(())
+}
+
+/// Reads the header variables, classes, objects, template and preview image of a drawing from
+/// its sections, which hold them the same way in every container
+pub(crate) fn read_sections(dwg: &mut Dwg, directory: &SectionDirectory) -> Result<()> {
+    let size_high = has_size_high(dwg.version, dwg.maintenance_version);
+    if let Some(header_vars) = directory.header_vars(size_high)? {
+        dwg.header_vars = header_vars;
+    }
+    if let Some(classes) = directory.classes(size_high)? {
+        dwg.classes = classes;
+    }
+    if let Some(objects) = directory.objects(dwg.version)? {
+        dwg.objects = objects;
+    }
+    if let Some(mut section) = directory.get(SectionKind::ObjFreeSpace)? {
+        dwg.obj_free_space = Some(read_obj_free_space(&mut section)?);
+    }
+    if let Some(template) = directory.get(SectionKind::Template)? {
+        dwg.raw_sections.insert(4, template.data().to_vec());
+    }
+    dwg.preview = directory.preview()?;
+    Ok(())
+}
+
+/// Offset of the first page of AC1021 (R2007) files, following the file header
+pub(crate) const R2007_PAGES_START: u64 = 0x480;
+
+/// Number of data bytes in each Reed-Solomon block of AC1021 (R2007) data pages, which have 4
+/// parity bytes rather than the 16 of the file header and system pages
+const R2007_DATA_BLOCK_SIZE: usize = 251;
+
+/// Returns the number of Reed-Solomon blocks of `block_data_size` data bytes holding `size`
+/// bytes padded to a multiple of 8 and repeated `repeat` times
+fn r2007_block_count(size: u64, repeat: u64, block_data_size: usize) -> usize {
+    (size.next_multiple_of(8) * repeat.max(1)).div_ceil(block_data_size as u64) as usize
+}
+
+/// Returns the bytes of an AC1021 (R2007) file from `address`
+fn r2007_page_bytes(bytes: &[u8], address: u64) -> Result<&[u8]> {
+    bytes
+        .get(address as usize..)
+        .ok_or(DwgError::SeekOutOfRange {
+            offset: address * 8,
+        })
+}
+
+/// Returns the `size` bytes of decoded AC1021 (R2007) page data, the first `data_size` of
+/// which are compressed if that is smaller
+fn r2007_page_data(data: &[u8], data_size: u64, size: u64) -> Result<Vec<u8>> {
+    let stored = data
+        .get(..data_size.min(size) as usize)
+        .ok_or(DwgError::InvalidStreamSize {
+            at_bit: data.len() as u64 * 8,
+        })?;
+    let data = if data_size < size {
+        decompress_r2007(stored, size as usize)?
     } else {
-        let _ = bit_reader.read_raw_longlong()?;
-        let _approx_n_objects = bit_reader.read_raw_longlong()?;
-        let _ = bit_reader.read_raw_longlong()?;
-        let _max32 = bit_reader.read_raw_longlong()?;
-        let _max32hi = bit_reader.read_raw_longlong()?;
-        let _max64 = bit_reader.read_raw_longlong()?;
-        let _max64hi = bit_reader.read_raw_longlong()?;
-        let _maxtbl = bit_reader.read_raw_longlong()?;
-        let _maxtblhi = bit_reader.read_raw_longlong()?;
-        let _maxrl = bit_reader.read_raw_longlong()?;
-        let _maxrlhi = bit_reader.read_raw_longlong()?;
-    }
-    Some(())
-}
-
-fn read_r2000_header<'a, I: Iterator<Item = &'a u8>>(
-    bit_reader: &mut BitReader<'a, I>,
-) -> Option<()> {
+        stored.to_vec()
+    };
+    if data.len() != size as usize {
+        return Err(DwgError::InvalidStreamSize {
+            at_bit: data_size * 8,
+        });
+    }
+    Ok(data)
+}
+
+/// Reads the AC1021 (R2007) system page at `address`, returning its decoded data
+///
+/// System pages are Reed-Solomon encoded, with their data repeated `repeat` times, the
+/// correction factor of the map in the file header. Only the first copy is used, after any
+/// errors in it are corrected
+pub(crate) fn read_r2007_system_page(
+    bytes: &[u8],
+    address: u64,
+    data_size: u64,
+    size: u64,
+    repeat: u64,
+) -> Result<Vec<u8>> {
+    let n_blocks = r2007_block_count(data_size, repeat, reed_solomon::DATA_SIZE);
+    let data = reed_solomon::decode(r2007_page_bytes(bytes, address)?, n_blocks)?;
+    r2007_page_data(&data, data_size, size)
+}
+
+/// Reads the pages map of an AC1021 (R2007) file located by its file header
+pub(crate) fn read_r2007_page_map(bytes: &[u8], header: &R2007Header) -> Result<PageMap> {
+    let data = read_r2007_system_page(
+        bytes,
+        R2007_PAGES_START + header.pages_map_offset,
+        header.pages_map_size_compressed,
+        header.pages_map_size_uncompressed,
+        header.pages_map_correction_factor,
+    )?;
+    PageMap::parse_r2007(&data)
+}
+
+/// Reads the sections map of an AC1021 (R2007) file located by its file header and pages
+/// map
+pub(crate) fn read_r2007_section_map(
+    bytes: &[u8],
+    header: &R2007Header,
+    page_map: &PageMap,
+) -> Result<SectionMap> {
+    let number = header.sections_map_id as i32;
+    let page = page_map
+        .page(number)
+        .ok_or(DwgError::MissingSectionPage { number })?;
+    let data = read_r2007_system_page(
+        bytes,
+        page.address,
+        header.sections_map_size_compressed,
+        header.sections_map_size_uncompressed,
+        header.sections_map_correction_factor,
+    )?;
+    SectionMap::parse_r2007(&data)
+}
+
+/// Reads and decompresses the data of `section` from the data pages of an AC1021 (R2007)
+/// file
+///
+/// Data pages have no header. Their data is interleaved in Reed-Solomon (255,251) blocks,
+/// the parity of which is not checked. As the section map has no checksum either, fails with
+/// `InvalidStreamSize` if the section is larger than its pages can hold or a page lies
+/// outside it
+pub(crate) fn read_r2007_section(
+    bytes: &[u8],
+    page_map: &PageMap,
+    section: &SectionDescriptor,
+) -> Result<Vec<u8>> {
+    check_section_size(section)?;
+    let mut data = Vec::new();
+    for page in &section.pages {
+        let address = page_map
+            .page(page.number)
+            .ok_or(DwgError::MissingSectionPage {
+                number: page.number,
+            })?
+            .address;
+        let n_blocks = r2007_block_count(page.data_size as u64, 1, R2007_DATA_BLOCK_SIZE);
+        let decoded = reed_solomon::deinterleave(
+            r2007_page_bytes(bytes, address)?,
+            n_blocks,
+            R2007_DATA_BLOCK_SIZE,
+        )?;
+        let page_data = r2007_page_data(&decoded, page.data_size as u64, page.size)?;
+        copy_section_page(&mut data, section, page.start, &page_data, address * 8)?;
+    }
+    data.resize(section.size as usize, 0);
+    Ok(data)
+}
+
+/// The ObjFreeSpace section, describing the objects section when it was last written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ObjFreeSpace {
+    /// Approximate number of objects, useful for preallocating before reading them
+    pub approx_n_objects: u32,
+    /// Date the section was last updated
+    pub updated: JulianDate,
+    /// Offset of the objects section from the start of the file, or 0 in AC1018 (R2004) and
+    /// later files
+    pub objects_offset: u32,
+    /// The largest handle values, called Max32, Max64, MaxTbl and MaxRl
+    pub max_handles: [u64; 4],
+}
+
+impl Default for ObjFreeSpace {
+    fn default() -> ObjFreeSpace {
+        ObjFreeSpace {
+            approx_n_objects: 0,
+            updated: JulianDate::default(),
+            objects_offset: 0,
+            max_handles: [0x32, 0x64, 0x200, 0xFFFF_FFFF],
+        }
+    }
+}
+
+/// Reads the ObjFreeSpace section, which is laid out the same way in every version
+///
+/// Only the four handle values the section always has are kept
+fn read_obj_free_space<S: ByteSource>(bit_reader: &mut BitReader<S>) -> Result<ObjFreeSpace> {
+    let _ = bit_reader.read_raw_long()?;
+    let approx_n_objects = bit_reader.read_raw_long()? as u32;
+    let updated = JulianDate {
+        day: bit_reader.read_raw_long()?,
+        ms: bit_reader.read_raw_long()?,
+    };
+    let objects_offset = bit_reader.read_raw_long()? as u32;
+    let _n_64b_vals = bit_reader.read_raw_char()?;
+    let mut max_handles = [0; 4];
+    for value in &mut max_handles {
+        *value = bit_reader.read_raw_longlong()? as u64;
+    }
+    Ok(ObjFreeSpace {
+        approx_n_objects,
+        updated,
+        objects_offset,
+        max_handles,
+    })
+}
+
+/// Returns the value the R2000 file header CRC is XORed with, which depends on the number of
+/// locator records, or `None` for a number of records no known file has
+pub(crate) fn r2000_header_crc_xor(n_records: usize) -> Option<u16> {
+    match n_records {
+        3 => Some(0xA598),
+        4 => Some(0x8101),
+        5 => Some(0x3CC4),
+        6 => Some(0x8461),
+        _ => None,
+    }
+}
+
+/// Reads the file header of an R13-R2000 file, whose section locator records say where the
+/// header variables, classes, object map and other sections are
+///
+/// The CRC8 of the file header is XORed with a value depending on the number of records, and
+/// is not checked if that value is unknown. In strict mode, fails with the error of the first
+/// [`ParseWarning`], otherwise they are kept in [`R2000FileHeader::warnings`]
+pub fn read_r2000_header<S: ByteSource>(
+    bit_reader: &mut BitReader<S>,
+    options: ParseOptions,
+) -> Result<R2000FileHeader> {
+    bit_reader.start_crc(0);
     let version = bit_reader.read_version()?;
     bit_reader.set_version(version);
+    let mut warnings = Vec::new();
+    let mut check = |warning| options.check(&mut warnings, warning);
+    let mut expect_byte = |bit_reader: &mut BitReader<S>, expected: u8| -> Result<()> {
+        let at_bit = bit_reader.bit_position();
+        let found = bit_reader.read_raw_char()? as u8;
+        if found != expected {
+            check(ParseWarning::UnexpectedByte {
+                at_bit,
+                expected,
+                found,
+            })?;
+        }
+        Ok(())
+    };
 
-    // Read 6 bytes, unknown purpose
+    // 5 bytes of unknown purpose, always 0
     for _ in 0..5 {
-        let res = bit_reader.read_raw_char()?;
-        // Sanity check, find dlls with nonzero elements in these positions
-        assert_eq!(res, 0);
+        expect_byte(bit_reader, 0)?;
     }
-    bit_reader.read_raw_char()?;
-    // Skip next byte, should be 1
-    assert_eq!(bit_reader.read_raw_char(), Some(1));
+    let maintenance_version = bit_reader.read_raw_char()? as u8;
+    // Always 1
+    expect_byte(bit_reader, 1)?;
 
     // Read image sentinel at 0x0D
-    let _image_sentinel_seeker = bit_reader.read_raw_long()?;
+    let preview_address = bit_reader.read_raw_long()? as u32 as u64;
 
     // Two unknown bytes
-    bit_reader.read_raw_char()?;
+    bit_reader.read_raw_short()?;
+    let at_bit = bit_reader.bit_position();
+    let codepage = bit_reader.read_raw_short()? as u16;
+    let codepage = CodePage::from_repr(codepage).ok_or(DwgError::InvalidFlag {
+        at_bit,
+        value: codepage as u64,
+    })?;
 
     // Read section-locator record starting at 0x15
     let n_records = bit_reader.read_raw_long()?;
+    let mut records = Vec::new();
     for _record in 0..n_records {
-        let _unused = bit_reader.read_raw_char()?;
-        let _seeker = bit_reader.read_raw_long()?;
-        let _size = bit_reader.read_raw_long()?;
-    }
-
-    // TODO: Verify CRC
-    let _crc = bit_reader.read_raw_short()?;
-    
-    // sentinel after crc
-    let sentinel = [
-        0x95, 0xA0, 0x4E, 0x28, 0x99, 0x82, 0x1A, 0xE5, 0x5E, 0x41, 0xE0, 0x5F, 0x9D, 0x3A, 0x4D,
-        0x00,
-    ];
+        let number = bit_reader.read_raw_char()? as u8;
+        let seeker = bit_reader.read_raw_long()? as u32;
+        let size = bit_reader.read_raw_long()? as u32;
+        records.push(SectionLocator {
+            number,
+            seeker,
+            size,
+        });
+    }
+
+    let found = bit_reader.finish_crc().unwrap_or_default();
+    let expected = bit_reader.read_raw_short()? as u16;
+    if let Some(xor) = r2000_header_crc_xor(records.len()) {
+        if found ^ xor != expected {
+            check(ParseWarning::FileHeaderCrcMismatch {
+                expected,
+                found: found ^ xor,
+            })?;
+        }
+    }
+
+    match bit_reader.read_sentinel(&sentinels::FILE_HEADER_END) {
+        Err(DwgError::BadSentinel { at_bit }) => check(ParseWarning::BadSentinel { at_bit })?,
+        result => result?,
+    }
+    Ok(R2000FileHeader {
+        maintenance_version,
+        codepage,
+        records,
+        preview_address,
+        size: bit_reader.byte_position(),
+        warnings,
+    })
+}
+
+/// Reads a section wrapped in sentinels, returning its data
+///
+/// The data is preceded by its RL size and followed by an RS CRC, which must match
+fn read_r2000_section(
+    bit_reader: &mut BitSliceReader,
+    number: u8,
+    start: &[u8; 16],
+    end: &[u8; 16],
+    size_high: bool,
+) -> Result<Vec<u8>> {
+    bit_reader.read_sentinel(start)?;
+    bit_reader.start_crc(CRC8_SEED);
+    let size = bit_reader.read_raw_long()? as u32;
+    if size_high {
+        // Sections are never large enough to need the high bits
+        bit_reader.read_raw_long()?;
+    }
+    let data = bit_reader.read_vec(size as usize)?;
+    let found = bit_reader.finish_crc().unwrap_or_default();
+    let expected = bit_reader.read_raw_short()? as u16;
+    if found != expected {
+        return Err(DwgError::SectionCrcMismatch {
+            page: number as u32,
+            expected: expected as u32,
+            found: found as u32,
+        });
+    }
+    bit_reader.read_sentinel(end)?;
+    Ok(data)
+}
+
+/// Returns true if the header variables and classes sections store the high 32 bits of their
+/// size after the RL size, as AC1032 (R2018) files and AC1024 (R2010) and AC1027 (R2013)
+/// files with a maintenance version above 3 do
+pub(crate) fn has_size_high(version: DWGVersion, maintenance_version: u8) -> bool {
+    version >= DWGVersion::AC1032 || (version >= DWGVersion::AC1024 && maintenance_version > 3)
+}
+
+/// Reads the object map section `data`, giving the location of each object in the objects
+/// section by handle
+///
+/// The map is a series of sections, each a big endian RS size followed by pairs of offsets
+/// from the previous handle and location, then a big endian RS CRC of the size and pairs. A
+/// section with no pairs ends the map. A handle listed again replaces the earlier entry, see
+/// `read_objects`
+///
+/// Fails with `SectionCrcMismatch` numbering the sections of the map from 0, and with
+/// `Overflow` if a location is negative
+pub fn read_object_map(data: &[u8]) -> Result<BTreeMap<u64, u64>> {
+    let mut locations = BTreeMap::new();
+    read_object_map_into(data, &mut locations)?;
+    Ok(locations)
+}
+
+/// Reads the object map into `locations`, see [`read_object_map`], keeping the locations
+/// read before a failure
+pub(crate) fn read_object_map_into(data: &[u8], locations: &mut BTreeMap<u64, u64>) -> Result<()> {
+    let mut reader = BitSliceReader::from_slice(data);
+    for part in 0.. {
+        let start = reader.byte_position();
+        let size = u16::from_be_bytes(reader.read_vec(2)?.try_into().unwrap());
+        let end = start + size as u64;
+        let content = data
+            .get(start as usize..end as usize)
+            .ok_or(DwgError::SeekOutOfRange { offset: end * 8 })?;
+        let found = crc8(CRC8_SEED, content);
+        let (mut handle, mut location) = (0u64, 0i64);
+        while reader.byte_position() < end {
+            handle = handle.wrapping_add(reader.read_modular_char_u64()?);
+            let at_bit = reader.bit_position();
+            location = location.wrapping_add(reader.read_handle_offset()?);
+            let address = u64::try_from(location).map_err(|_| DwgError::Overflow { at_bit })?;
+            locations.insert(handle, address);
+        }
+        reader.seek_to_bit(end * 8)?;
+        let expected = u16::from_be_bytes(reader.read_vec(2)?.try_into().unwrap());
+        if found != expected {
+            return Err(DwgError::SectionCrcMismatch {
+                page: part,
+                expected: expected as u32,
+                found: found as u32,
+            });
+        }
+        if size <= 2 {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Reads the objects located by the object map `map` in the objects section `data` of a
+/// drawing of `version`
+///
+/// AC1024 (R2010) and later objects follow their MS size with the MC size of their handle
+/// stream, which is not counted in the MS size but is kept as the start of their data
+///
+/// Incremental saves append changed objects rather than rewriting them, leaving the old
+/// copies and padding behind as gaps and objects out of handle order. Each object is read
+/// only from where the map locates it, so anything between objects is skipped, and a handle
+/// listed again in the map replaces the earlier entry
+fn read_objects(map: &[u8], data: &[u8], version: DWGVersion) -> Result<BTreeMap<u64, Vec<u8>>> {
+    let mut objects = BTreeMap::new();
+    let mut object_reader = BitSliceReader::from_slice(data);
+    for (handle, address) in read_object_map(map)? {
+        let object = read_object_at(&mut object_reader, address, version)?;
+        objects.insert(handle, object);
+    }
+    Ok(objects)
+}
 
-    // Verify that sentinel is equal to expected value
-    for byte in sentinel {
-        assert_eq!(byte, bit_reader.read_raw_char()? as u8);
+/// Reads the data of the object at `address` in the objects section, without the MS size
+/// before it and the CRC after it, see `read_objects`
+pub(crate) fn read_object_at(
+    reader: &mut BitSliceReader,
+    address: u64,
+    version: DWGVersion,
+) -> Result<Vec<u8>> {
+    reader.seek_to_bit(address.saturating_mul(8))?;
+    let size = reader.read_modular_short_u64()? as usize;
+    let start = reader.bit_position();
+    if version >= DWGVersion::AC1024 {
+        reader.read_modular_char_u64()?;
     }
-    Some(())
+    let handle_stream_size = (reader.bit_position() - start) as usize / 8;
+    reader.seek_to_bit(start)?;
+    reader.read_vec(handle_stream_size + size)
+}
+
+/// The second header of an R13-R2000 file, which follows its last section
+///
+/// It repeats the section locator records of the file header and lists the handles of the
+/// control objects and dictionaries
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SecondHeader {
+    /// Offset of the second header from the start of the file
+    pub address: u64,
+    /// Copies of the section locator records of the file header
+    pub records: Vec<SectionLocator>,
+    /// See [`Dwg::handle_records`]
+    pub handle_records: [u64; 14],
+}
+
+impl SecondHeader {
+    /// Compares the second header, found at `address`, with the section locator records of
+    /// the file header and the objects of the drawing, returning every difference
+    pub fn cross_check(
+        &self,
+        address: u64,
+        records: &[SectionLocator],
+        dwg: &Dwg,
+    ) -> Vec<SecondHeaderWarning> {
+        let last_handle = dwg.objects.keys().next_back().copied();
+        self.cross_check_handles(address, records, last_handle)
+    }
+
+    /// Compares the second header as [`SecondHeader::cross_check`] does, given the largest
+    /// handle of an object rather than the drawing
+    pub(crate) fn cross_check_handles(
+        &self,
+        address: u64,
+        records: &[SectionLocator],
+        last_handle: Option<u64>,
+    ) -> Vec<SecondHeaderWarning> {
+        let mut warnings = Vec::new();
+        if self.address != address {
+            warnings.push(SecondHeaderWarning::AddressMismatch {
+                expected: address,
+                found: self.address,
+            });
+        }
+        let mut numbers: Vec<u8> = records
+            .iter()
+            .chain(&self.records)
+            .map(|record| record.number)
+            .collect();
+        numbers.sort_unstable();
+        numbers.dedup();
+        for number in numbers {
+            let find = |records: &[SectionLocator]| {
+                records
+                    .iter()
+                    .find(|record| record.number == number)
+                    .copied()
+            };
+            let (file_header, second_header) = (find(records), find(&self.records));
+            if file_header != second_header {
+                warnings.push(SecondHeaderWarning::RecordMismatch {
+                    number,
+                    file_header,
+                    second_header,
+                });
+            }
+        }
+        if let Some(last_handle) = last_handle {
+            let handseed = self.handle_records[0];
+            if handseed <= last_handle {
+                warnings.push(SecondHeaderWarning::HandseedTooLow {
+                    handseed,
+                    last_handle,
+                });
+            }
+        }
+        warnings
+    }
+}
+
+/// A difference between the second header of an R13-R2000 file and the rest of the file,
+/// see [`SecondHeader::cross_check`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SecondHeaderWarning {
+    /// The CRC of the second header does not match its data
+    CrcMismatch { expected: u16, found: u16 },
+    /// The address the second header gives for itself is not where it was found
+    AddressMismatch { expected: u64, found: u64 },
+    /// A section locator record differs from the one in the file header or is missing from
+    /// one of the headers
+    RecordMismatch {
+        number: u8,
+        file_header: Option<SectionLocator>,
+        second_header: Option<SectionLocator>,
+    },
+    /// HANDSEED is not above the handle of every object, so adding objects would reuse
+    /// handles
+    HandseedTooLow { handseed: u64, last_handle: u64 },
+}
+
+impl fmt::Display for SecondHeaderWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CrcMismatch { expected, found } => write!(
+                f,
+                "second header CRC is {found:#06x} but {expected:#06x} is stored"
+            ),
+            Self::AddressMismatch { expected, found } => write!(
+                f,
+                "second header gives its address as {found:#x} but is at {expected:#x}"
+            ),
+            Self::RecordMismatch {
+                number,
+                file_header,
+                second_header,
+            } => write!(
+                f,
+                "section locator record {number} is {file_header:?} in the file header but \
+                 {second_header:?} in the second header"
+            ),
+            Self::HandseedTooLow {
+                handseed,
+                last_handle,
+            } => write!(
+                f,
+                "HANDSEED {handseed:#x} is not above the last object handle {last_handle:#x}"
+            ),
+        }
+    }
+}
+
+/// Reads the second header, along with a warning if its CRC does not match
+pub(crate) fn read_second_header(
+    bit_reader: &mut BitSliceReader,
+) -> Result<(SecondHeader, Option<SecondHeaderWarning>)> {
+    bit_reader.read_sentinel(&sentinels::SECOND_HEADER_START)?;
+    bit_reader.start_crc(CRC8_SEED);
+    let size = bit_reader.read_raw_long()? as u32;
+    let body = bit_reader.read_vec(size as usize)?;
+    let found = bit_reader.finish_crc().unwrap_or_default();
+    let expected = bit_reader.read_raw_short()? as u16;
+    let warning =
+        (found != expected).then_some(SecondHeaderWarning::CrcMismatch { expected, found });
+
+    let mut bit_reader = BitSliceReader::from_slice(&body);
+    let address = bit_reader.read_bitlong()? as u32 as u64;
+    // The version magic number and 6 unknown bytes, 4 unknown bits and 5 more unknown bytes
+    let mut skipped = [0; 12];
+    bit_reader.read_bytes(&mut skipped)?;
+    for _ in 0..4 {
+        bit_reader.read_bit()?;
+    }
+    let mut skipped = [0; 5];
+    bit_reader.read_bytes(&mut skipped)?;
+    let n_records = bit_reader.read_raw_char()? as u8;
+    let mut records = Vec::with_capacity(n_records as usize);
+    for _ in 0..n_records {
+        records.push(SectionLocator {
+            number: bit_reader.read_raw_char()? as u8,
+            seeker: bit_reader.read_bitlong()? as u32,
+            size: bit_reader.read_bitlong()? as u32,
+        });
+    }
+    let mut handle_records = [0; 14];
+    let n_handles = bit_reader.read_bitshort()?;
+    for _ in 0..n_handles {
+        let size = bit_reader.read_raw_char()? as u8;
+        let number = bit_reader.read_raw_char()? as u8 as usize;
+        let mut handle = 0u64;
+        for _ in 0..size {
+            handle = handle << 8 | bit_reader.read_raw_char()? as u8 as u64;
+        }
+        if let Some(slot) = handle_records.get_mut(number) {
+            *slot = handle;
+        }
+    }
+    let header = SecondHeader {
+        address,
+        records,
+        handle_records,
+    };
+    Ok((header, warning))
+}
+
+/// Returns the address of the second header of an R13-R2000 file, following the end of the
+/// file header or the last section
+pub(crate) fn second_header_address(header: &R2000FileHeader) -> u64 {
+    header
+        .records
+        .iter()
+        .map(|record| record.seeker as u64 + record.size as u64)
+        .fold(header.size, u64::max)
 }
 
 impl Dwg {
-    pub fn read_from_file(file_name: &str) -> Option<Dwg> {
-        let bytes = fs::read(file_name).unwrap();
-        let mut bit_reader = BitReader::new(bytes.iter());
+    /// Creates a drawing with no header variables, classes or objects
+    pub fn empty(version: DWGVersion) -> Dwg {
+        Dwg {
+            version,
+            maintenance_version: 0,
+            codepage: CodePage::ANSI1252,
+            header_vars: Vec::new(),
+            classes: Vec::new(),
+            objects: BTreeMap::new(),
+            raw_sections: BTreeMap::new(),
+            sections: BTreeMap::new(),
+            handle_records: [0; 14],
+            preview: None,
+            obj_free_space: None,
+        }
+    }
+
+    /// Returns the data of the section named `name`, such as "AcDb:Header", as read from an
+    /// AC1018 (R2004) or later file
+    ///
+    /// The data is decompressed and reassembled from the pages of the section
+    pub fn section(&self, name: &str) -> Option<&[u8]> {
+        self.sections.get(name).map(Vec::as_slice)
+    }
+
+    /// Returns the data of the embedded VBA project of an AC1018 (R2004) or later file, or
+    /// `None` if it has none
+    ///
+    /// The data is that of the "AcDb:VBAProject" section, which is written back unchanged
+    pub fn vba_project(&self) -> Option<&[u8]> {
+        self.section(SectionKind::VbaProject.name())
+    }
+
+    /// Returns whether the drawing uses English or metric units, the MEASUREMENT stored in
+    /// its template section, or `None` if it has no template
+    ///
+    /// The template starts with a description, an RS length then that many characters,
+    /// which are UTF-16LE code units from AC1021 (R2007). Fails with `UnexpectedEof` if the
+    /// template ends before the MEASUREMENT
+    pub fn measurement(&self) -> Result<Option<Measurement>> {
+        let Some(template) = self.raw_sections.get(&4) else {
+            return Ok(None);
+        };
+        let mut reader = BitSliceReader::from_slice(template);
+        let mut len = reader.read_raw_short()? as u16 as usize;
+        if self.version >= DWGVersion::AC1021 {
+            len *= 2;
+        }
+        reader.read_vec(len)?;
+        Ok(Some(match reader.read_raw_short()? {
+            0 => Measurement::English,
+            _ => Measurement::Metric,
+        }))
+    }
+
+    /// Returns the document properties from the SummaryInfo section of an AC1018 (R2004) or
+    /// later file, or `None` if it has none
+    ///
+    /// Strings before AC1021 (R2007) are decoded using the codepage of the drawing
+    pub fn summary_info(&self) -> Result<Option<SummaryInfo>> {
+        self.section(SectionKind::SummaryInfo.name())
+            .map(|data| SummaryInfo::read(data, self.version, self.codepage))
+            .transpose()
+    }
+
+    /// Returns the thumbnail image of the drawing, if it has a preview image with one
+    pub fn thumbnail(&self) -> Option<Thumbnail<'_>> {
+        self.preview.as_ref()?.thumbnail()
+    }
+
+    pub fn version(&self) -> DWGVersion {
+        self.version
+    }
+
+    pub(crate) fn set_version(&mut self, version: DWGVersion) {
+        self.version = version;
+    }
+
+    /// Returns the handle the next added object will get, the larger of HANDSEED and the
+    /// handle after the last object
+    pub fn next_handle(&self) -> u64 {
+        let after_last = self
+            .objects
+            .keys()
+            .next_back()
+            .map_or(1, |handle| handle + 1);
+        after_last.max(self.handle_records[0])
+    }
+
+    /// Returns the data of an object
+    pub fn get_object(&self, handle: u64) -> Option<&[u8]> {
+        self.objects.get(&handle).map(Vec::as_slice)
+    }
+
+    /// Returns the data of an object for modification
+    ///
+    /// The object map and the sizes of objects are rebuilt when the drawing is written, so
+    /// the data can be resized freely
+    pub fn get_object_mut(&mut self, handle: u64) -> Option<&mut Vec<u8>> {
+        self.objects.get_mut(&handle)
+    }
+
+    /// Adds an entity with the next free handle, returning the handle
+    ///
+    /// `write` writes the data of the entity, starting with its type, to a writer set to the
+    /// version of the drawing. It is given the handle, which the entity must store in its
+    /// data. The entity must also be added to its owning block for it to be drawn
+    pub fn add_entity(&mut self, write: impl FnOnce(&mut BitWriter, u64)) -> u64 {
+        let handle = self.next_handle();
+        let mut writer = BitWriter::new();
+        writer.set_version(self.version);
+        write(&mut writer, handle);
+        self.objects.insert(handle, writer.into_bytes());
+        self.handle_records[0] = handle + 1;
+        handle
+    }
+
+    /// Removes an object, returning its data if it existed
+    ///
+    /// References to the object from other objects are left as they are. Its handle is not
+    /// reused by [`Dwg::add_entity`]
+    pub fn delete_object(&mut self, handle: u64) -> Option<Vec<u8>> {
+        let data = self.objects.remove(&handle)?;
+        self.handle_records[0] = self.handle_records[0].max(handle + 1);
+        Some(data)
+    }
+
+    pub fn read_from_file(file_name: &str) -> Result<Dwg> {
+        Self::from_bytes(&fs::read(file_name)?)
+    }
+
+    /// Reads a drawing from a DWG file mapped into memory, rather than copied into memory
+    /// first as [`Dwg::read_from_file`] does
+    ///
+    /// Sections are still copied out of the file as they are read. The file must not be
+    /// changed by another process while it is read
+    #[cfg(feature = "memmap2")]
+    pub fn read_mmap(file_name: &str) -> Result<Dwg> {
+        let file = fs::File::open(file_name)?;
+        // SAFETY: the map is only read, and is dropped before returning
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Self::from_bytes(&map)
+    }
+
+    /// Reads a drawing from the contents of a DWG file
+    ///
+    /// AC1012 (R13) and later files can be read, earlier versions fail with
+    /// `UnsupportedVersion`. Password protected drawings are detected and rejected with
+    /// `Encrypted`, as there is no way to supply the password. R13 and R14 files share the
+    /// layout of R2000 files, with objects encoded as described in [`crate::objects`].
+    /// AC1024 (R2010) and later files use the same container as AC1018 (R2004) files, with
+    /// the larger section sizes and handle stream sizes those versions add. Files after R2000
+    /// have no second header, so their [`Dwg::handle_records`] are left as 0. Objects and
+    /// sections are kept as their raw data rather than decoded, so anything the crate does
+    /// not understand survives being written back with [`Dwg::to_bytes`], which reproduces
+    /// each object byte for byte. Only the object map, ObjFreeSpace and the headers are
+    /// regenerated, as they depend on where everything ends up in the file
+    pub fn from_bytes(bytes: &[u8]) -> Result<Dwg> {
+        Self::from_bytes_with(bytes, ParseOptions::default()).map(|(dwg, _)| dwg)
+    }
+
+    /// Reads a drawing from the contents of a DWG file as [`Dwg::from_bytes`] does, returning
+    /// the sanity checks that failed along the way if `options` is not strict
+    ///
+    /// These are the unknown bytes, CRC and sentinel of the R13-R2000 file header, and the
+    /// page types and checksums of AC1018 (R2004) files. AC1021 (R2007) pages have no
+    /// checksums, as their Reed-Solomon coding corrects errors instead
+    pub fn from_bytes_with(
+        bytes: &[u8],
+        options: ParseOptions,
+    ) -> Result<(Dwg, Vec<ParseWarning>)> {
+        let mut bit_reader = BitSliceReader::from_slice(bytes);
+        let magic = bytes.get(..6).and_then(|magic| magic.try_into().ok());
+        match magic.and_then(DWGVersion::from_magic) {
+            Some(version) if version >= DWGVersion::AC1018 => {
+                return DwgFile::open_with(bytes, options)?.into_dwg_with_warnings()
+            }
+            _ => {}
+        }
+        let header = read_r2000_header(&mut bit_reader, options)?;
+        let version = bit_reader.get_version();
+        if version > DWGVersion::AC1015 {
+            return Err(DwgError::UnsupportedVersion {
+                found: format!("{version:?}"),
+            });
+        }
+        let mut dwg = Dwg::empty(version);
+        dwg.maintenance_version = header.maintenance_version;
+        dwg.codepage = header.codepage;
+        bit_reader.set_codepage(header.codepage);
+
+        let directory = SectionDirectory::Locators {
+            bytes,
+            records: &header.records,
+            preview_address: header.preview_address,
+        };
+        read_sections(&mut dwg, &directory)?;
+        for record in &header.records {
+            // Sections 0-4 are read above, and 3 is regenerated from the objects when written
+            if record.number > 4 {
+                let section = locator_data(bytes, record)?;
+                dwg.raw_sections.insert(record.number, section.to_vec());
+            }
+        }
+
+        // The second header is optional, so is ignored if it cannot be read
+        let mut second_header = bit_reader.sub_reader(0, bytes.len())?;
+        second_header.seek_to_bit(second_header_address(&header) * 8)?;
+        if let Ok((second_header, _)) = read_second_header(&mut second_header) {
+            dwg.handle_records = second_header.handle_records;
+        }
+        Ok((dwg, header.warnings))
+    }
+
+    /// Reads the second header of an R13-R2000 file and compares it with the rest of the
+    /// file, see [`SecondHeader::cross_check`]
+    ///
+    /// Reading a file ignores its second header if it cannot be read, while this fails.
+    /// Later files, which have no second header, fail with `UnsupportedVersion`
+    pub fn check_second_header(bytes: &[u8]) -> Result<(SecondHeader, Vec<SecondHeaderWarning>)> {
+        let dwg = Dwg::from_bytes(bytes)?;
+        if dwg.version() > DWGVersion::AC1015 {
+            return Err(DwgError::UnsupportedVersion {
+                found: format!("{:?}", dwg.version()),
+            });
+        }
+        let header = read_r2000_header(
+            &mut BitSliceReader::from_slice(bytes),
+            ParseOptions::default(),
+        )?;
+        let address = second_header_address(&header);
+        let mut bit_reader = BitSliceReader::from_slice(bytes);
+        bit_reader.seek_to_bit(address * 8)?;
+        let (second_header, crc_warning) = read_second_header(&mut bit_reader)?;
+        let mut warnings: Vec<_> = crc_warning.into_iter().collect();
+        warnings.extend(second_header.cross_check(address, &header.records, &dwg));
+        Ok((second_header, warnings))
+    }
+}
+
+#[test]
+fn test_section_directory() {
+    let bytes = [0, 1, 2, 3, 4, 5];
+    let records = [
+        SectionLocator {
+            number: 4,
+            seeker: 2,
+            size: 3,
+        },
+        SectionLocator {
+            number: 5,
+            seeker: 4,
+            size: 3,
+        },
+    ];
+    let directory = SectionDirectory::Locators {
+        bytes: &bytes,
+        records: &records,
+        preview_address: 0,
+    };
+    let data = |kind| directory.get(kind).unwrap().map(|section| section.data());
+    assert_eq!(data(SectionKind::Template), Some(&bytes[2..5]));
+    assert_eq!(data(SectionKind::Objects), Some(&bytes[..]));
+    assert_eq!(data(SectionKind::Header), None);
+    assert!(matches!(
+        directory.get(SectionKind::AuxHeader),
+        Err(DwgError::SeekOutOfRange { offset: 32 })
+    ));
+
+    let sections = BTreeMap::from([(String::from("AcDb:Template"), vec![2, 3, 4])]);
+    let directory = SectionDirectory::Named(&sections);
+    let data = |kind| directory.get(kind).unwrap().map(|section| section.data());
+    assert_eq!(data(SectionKind::Template), Some(&bytes[2..5]));
+    assert_eq!(data(SectionKind::Objects), None);
+}
+
+#[test]
+fn test_r2004_header_mask() {
+    // The start of the encrypted header, which is the same in every file
+    let mask = r2004_header_mask();
+    assert_eq!(mask[..4], [0x29, 0x23, 0xBE, 0x84]);
+}
+
+#[test]
+fn test_r2004_header() {
+    let header = R2004Header {
+        root_tree_node_gap: -1,
+        last_section_page_id: 7,
+        last_section_page_end_address: 0x1_0000_0000,
+        section_page_amount: 7,
+        section_page_map_id: 7,
+        section_page_map_address: 0x1200,
+        section_map_id: 6,
+        ..Default::default()
+    };
+    let mut encrypted = header.encrypt();
+    assert_eq!(R2004Header::decrypt(&encrypted), Ok(header.clone()));
+
+    encrypted[0x50] ^= 1;
+    assert!(matches!(
+        R2004Header::decrypt(&encrypted),
+        Err(DwgError::SectionCrcMismatch { page: 0, .. })
+    ));
+    assert_eq!(
+        R2004Header::decrypt(&[0; R2004_HEADER_SIZE]),
+        Err(DwgError::BadSentinel { at_bit: 0 })
+    );
+
+    let mut dwg = Dwg::empty(DWGVersion::AC1018);
+    dwg.maintenance_version = 3;
+    dwg.codepage = CodePage::ANSI1251;
+    let buf = dwg.to_bytes().unwrap();
+    let file_header = read_r2004_file_header(&mut BitSliceReader::from_slice(&buf)).unwrap();
+    assert_eq!(file_header.maintenance_version, 3);
+    assert_eq!(file_header.codepage, CodePage::ANSI1251);
+    let header = file_header.header;
+    assert_eq!(
+        header.last_section_page_end_address as usize + R2004_PAGES_START,
+        buf.len()
+    );
+    assert_eq!(header.section_page_amount, header.last_section_page_id);
+}
+
+#[test]
+fn test_r2004_page_map() {
+    let mut data = Vec::new();
+    for word in [1, 0x7400, -2, 0x400, 0, 0, 0, 0, 3, 0x100] {
+        data.extend(i32::to_le_bytes(word));
+    }
+    let map = PageMap::parse(&data).unwrap();
+    assert_eq!(map.pages.len(), 3);
+    assert_eq!(
+        map.page(3),
+        Some(&PageMapEntry {
+            number: 3,
+            size: 0x100,
+            address: 0x7900
+        })
+    );
+    assert_eq!(
+        map.gaps().map(|gap| gap.address).collect::<Vec<_>>(),
+        [0x7500]
+    );
+    assert!(PageMap::parse(&data[..12]).is_err());
+
+    let mut dwg = Dwg::empty(DWGVersion::AC1018);
+    dwg.header_vars = vec![1, 2, 3];
+    let mut buf = dwg.to_bytes().unwrap();
+    let header = read_r2004_file_header(&mut BitSliceReader::from_slice(&buf))
+        .unwrap()
+        .header;
+    let map = read_r2004_page_map(&buf, &header, ParseOptions::default(), &mut Vec::new()).unwrap();
+    assert_eq!(map.pages.len() as u32, header.section_page_amount);
+    assert_eq!(map.gaps().count(), 0);
+    let last = map.pages.last().unwrap();
+    assert_eq!(last.address + last.size as u64, buf.len() as u64);
+    let page_map = map.page(header.section_page_map_id as i32).unwrap();
+    assert_eq!(
+        page_map.address,
+        header.section_page_map_address + R2004_PAGES_START as u64
+    );
+
+    // Corrupting the page map fails its checksum
+    buf[page_map.address as usize + 0x20] ^= 1;
+    assert!(matches!(
+        read_r2004_page_map(&buf, &header, ParseOptions::default(), &mut Vec::new()),
+        Err(DwgError::SectionCrcMismatch { .. })
+    ));
+    assert!(matches!(
+        Dwg::from_bytes(&buf),
+        Err(DwgError::SectionCrcMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_r2007_header() {
+    let header = R2007Header {
+        header_size: 0x70,
+        file_size: 0x12345,
+        pages_map_offset: 0x800,
+        pages_map_id: 9,
+        sections_map_id: 8,
+        sections_map_crc_seed: 0x1122_3344_5566_7788,
+        header_crc: u64::MAX,
+        ..Default::default()
+    };
+    let mut file = b"AC1021\0\0\0\0\0\x1d\x01".to_vec();
+    file.resize(0x13, 0);
+    file.extend((CodePage::ANSI1252 as u16).to_le_bytes());
+    file.resize(0x80, 0);
+    file.extend(header.encode());
+    let file_header = read_r2007_file_header(&mut BitSliceReader::from_slice(&file)).unwrap();
+    assert_eq!(file_header.maintenance_version, 0x1D);
+    assert_eq!(file_header.codepage, CodePage::ANSI1252);
+    assert_eq!(file_header.header, header);
+
+    // Corrupt bytes are corrected, up to 8 in each of the interleaved blocks
+    for byte in &mut file[0x90..0x90 + 24] {
+        *byte ^= 0x5A;
+    }
+    assert_eq!(
+        read_r2007_file_header(&mut BitSliceReader::from_slice(&file))
+            .unwrap()
+            .header,
+        header
+    );
+    // The pages map is past the end of the file
+    assert!(matches!(
+        Dwg::from_bytes(&file),
+        Err(DwgError::SeekOutOfRange { .. })
+    ));
+    for byte in &mut file[0x100..0x100 + 27] {
+        *byte ^= 0x5A;
+    }
+    assert!(matches!(
+        Dwg::from_bytes(&file),
+        Err(DwgError::UncorrectableBlock { .. })
+    ));
+}
+
+#[test]
+fn test_r2007_compressed() {
+    // 8 zero literals then a copy of 0x108 bytes from 1 back decompress to all-zero fields
+    let stream = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0x28, 0, 0, 1, 0];
+    let mut writer = BitWriter::new();
+    writer.write_bytes(&[0; 24]);
+    writer.write_raw_long(stream.len() as i32);
+    writer.write_raw_long(R2007_HEADER_SIZE as i32);
+    writer.write_bytes(&stream);
+    let mut file = b"AC1021\0\0\0\0\0\0\x01".to_vec();
+    file.resize(0x80, 0);
+    file.extend(reed_solomon::encode(writer.as_bytes(), R2007_HEADER_BLOCKS));
+    let file_header = read_r2007_file_header(&mut BitSliceReader::from_slice(&file)).unwrap();
+    assert_eq!(file_header.header, R2007Header::default());
+
+    // Page data is compressed when its stored size is smaller, padding after it is ignored
+    let page = b"\x00abcdefgh\x40\x02xy\0\0\0";
+    assert_eq!(r2007_page_data(page, 13, 14).unwrap(), b"abcdefghhhhhyx");
+    assert_eq!(r2007_page_data(page, 14, 14).unwrap(), &page[..14]);
+    assert!(matches!(
+        r2007_page_data(page, 13, 15),
+        Err(DwgError::InvalidStreamSize { .. })
+    ));
+}
+
+/// Writes an AC1021 (R2007) file holding `sections` uncompressed, split into pages of at
+/// most `max_page_size` bytes
+#[cfg(test)]
+fn write_r2007_test_file(sections: &[(&str, Vec<u8>)], max_page_size: usize) -> Vec<u8> {
+    let system_page = |data: &[u8]| {
+        let n_blocks = r2007_block_count(data.len() as u64, 1, reed_solomon::DATA_SIZE);
+        let mut page = reed_solomon::encode(data, n_blocks);
+        page.resize(page.len().next_multiple_of(8), 0);
+        page
+    };
+
+    let mut out = b"AC1021\0\0\0\0\0\0\x01".to_vec();
+    out.resize(0x13, 0);
+    out.extend((CodePage::ANSI1252 as u16).to_le_bytes());
+    out.resize(R2007_PAGES_START as usize, 0);
+    let mut page_sizes = Vec::new();
+    let mut section_map = BitWriter::new();
+    for (name, data) in sections {
+        let name: Vec<u8> = name
+            .encode_utf16()
+            .chain([0])
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let pages: Vec<_> = data.chunks(max_page_size).collect();
+        for word in [data.len(), max_page_size, 0, 0x1234, name.len(), 0, 4] {
+            section_map.write_raw_longlong(word as i64);
+        }
+        section_map.write_raw_longlong(pages.len() as i64);
+        section_map.write_bytes(&name);
+        for (i, data) in pages.into_iter().enumerate() {
+            let n_blocks = r2007_block_count(data.len() as u64, 1, R2007_DATA_BLOCK_SIZE);
+            let mut page = reed_solomon::interleave(data, n_blocks, R2007_DATA_BLOCK_SIZE);
+            page.resize(page.len().next_multiple_of(8), 0);
+            page_sizes.push(page.len());
+            let words = [i * max_page_size, page.len(), page_sizes.len()];
+            for word in words.into_iter().chain([data.len(), data.len(), 0, 0]) {
+                section_map.write_raw_longlong(word as i64);
+            }
+            out.extend(page);
+        }
+    }
+    let section_map = section_map.as_bytes().to_vec();
+    let page = system_page(&section_map);
+    page_sizes.push(page.len());
+    let sections_map_id = page_sizes.len() as u64;
+    out.extend(page);
+
+    // The pages map lists itself, and its size only depends on the number of pages
+    let pages_map_size = 16 * (page_sizes.len() + 1);
+    page_sizes.push(system_page(&vec![0; pages_map_size]).len());
+    let mut pages_map = BitWriter::new();
+    for (number, size) in (1..).zip(&page_sizes) {
+        pages_map.write_raw_longlong(*size as i64);
+        pages_map.write_raw_longlong(number);
+    }
+    let pages_map_offset = out.len() as u64 - R2007_PAGES_START;
+    out.extend(system_page(pages_map.as_bytes()));
+
+    let header = R2007Header {
+        header_size: 0x70,
+        file_size: out.len() as u64,
+        pages_map_correction_factor: 1,
+        pages_map_offset,
+        pages_map_id: page_sizes.len() as u64,
+        pages_map_size_compressed: pages_map_size as u64,
+        pages_map_size_uncompressed: pages_map_size as u64,
+        pages_amount: page_sizes.len() as u64,
+        pages_max_id: page_sizes.len() as u64,
+        sections_amount: sections.len() as u64,
+        sections_map_size_compressed: section_map.len() as u64,
+        sections_map_size_uncompressed: section_map.len() as u64,
+        sections_map_id,
+        sections_map_correction_factor: 1,
+        ..Default::default()
+    };
+    let encoded = header.encode();
+    out[0x80..0x80 + encoded.len()].copy_from_slice(&encoded);
+    out
+}
+
+#[test]
+fn test_r2007_sections() {
+    let mut header_vars = Vec::new();
+    crate::dwg_write::write_r2000_section(
+        &mut header_vars,
+        &sentinels::HEADER_VARIABLES_START,
+        &[1, 2, 3],
+        &sentinels::HEADER_VARIABLES_END,
+        false,
+    )
+    .unwrap();
+    let app_info: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+    let file = write_r2007_test_file(
+        &[
+            ("AcDb:Header", header_vars.clone()),
+            ("AcDb:AppInfo", app_info.clone()),
+        ],
+        0x100,
+    );
+
+    let header = read_r2007_file_header(&mut BitSliceReader::from_slice(&file))
+        .unwrap()
+        .header;
+    let page_map = read_r2007_page_map(&file, &header).unwrap();
+    assert_eq!(page_map.pages.len(), 7);
+    let last = page_map.pages.last().unwrap();
+    assert_eq!(last.address + last.size as u64, file.len() as u64);
+    let section_map = read_r2007_section_map(&file, &header, &page_map).unwrap();
+    let section = section_map.section("AcDb:AppInfo").unwrap();
+    assert_eq!(section.size, 1000);
+    assert_eq!(section.pages.len(), 4);
+    assert_eq!(section.pages[3].start, 0x300);
+    assert_eq!(section.pages[3].size, 1000 - 0x300);
+
+    let dwg = Dwg::from_bytes(&file).unwrap();
+    assert_eq!(dwg.version(), DWGVersion::AC1021);
+    assert_eq!(dwg.header_vars, [1, 2, 3]);
+    assert_eq!(dwg.section("AcDb:Header"), Some(&header_vars[..]));
+    assert_eq!(dwg.section("AcDb:AppInfo"), Some(&app_info[..]));
+
+    // The section map has no checksum, so sizes and starts it claims are checked
+    for corrupt in [
+        SectionDescriptor {
+            size: u64::MAX,
+            ..section.clone()
+        },
+        SectionDescriptor {
+            max_page_size: u32::MAX,
+            size: 1 << 40,
+            ..section.clone()
+        },
+    ] {
+        assert!(matches!(
+            read_r2007_section(&file, &page_map, &corrupt),
+            Err(DwgError::InvalidStreamSize { .. })
+        ));
+    }
+    let mut corrupt = section.clone();
+    corrupt.pages[1].start = u64::MAX;
+    assert!(matches!(
+        read_r2007_section(&file, &page_map, &corrupt),
+        Err(DwgError::InvalidStreamSize { .. })
+    ));
+}
+
+#[test]
+fn test_round_trip() {
+    let mut dwg = Dwg::empty(DWGVersion::AC1015);
+    dwg.maintenance_version = 6;
+    dwg.codepage = CodePage::ANSI1251;
+    dwg.header_vars = vec![1, 2, 3];
+    dwg.classes = vec![4, 5];
+    for handle in 1..3000u64 {
+        dwg.objects
+            .insert(handle * 2, vec![handle as u8; (handle % 5) as usize + 1]);
+    }
+    dwg.raw_sections.insert(4, vec![0, 0, 1, 0]);
+    dwg.raw_sections.insert(5, vec![6; 10]);
+    dwg.handle_records[1] = 0x10000;
+    dwg.preview = Some(Preview {
+        entries: vec![crate::preview::PreviewEntry {
+            code: crate::preview::BMP,
+            data: vec![7; 20],
+        }],
+    });
+    dwg.obj_free_space = Some(ObjFreeSpace {
+        updated: JulianDate {
+            day: 2_460_000,
+            ms: 5,
+        },
+        max_handles: [1, 2, 3, 4],
+        ..Default::default()
+    });
+    let buf = dwg.to_bytes().unwrap();
+
+    let read = Dwg::from_bytes(&buf).unwrap();
+    assert_eq!(read.version(), DWGVersion::AC1015);
+    assert_eq!(read.maintenance_version, dwg.maintenance_version);
+    assert_eq!(read.codepage, dwg.codepage);
+    assert_eq!(read.header_vars, dwg.header_vars);
+    assert_eq!(read.classes, dwg.classes);
+    assert_eq!(read.objects, dwg.objects);
+    assert_eq!(read.raw_sections, dwg.raw_sections);
+    assert_eq!(read.handle_records[1], 0x10000);
+    assert_eq!(read.thumbnail(), Some(Thumbnail::Bmp(&[7; 20])));
+    assert_eq!(read.measurement(), Ok(Some(Measurement::Metric)));
+    let space = read.obj_free_space.unwrap();
+    assert_eq!(space.approx_n_objects, 2999);
+    assert_eq!(space.updated, dwg.obj_free_space.unwrap().updated);
+    assert_eq!(space.max_handles, [1, 2, 3, 4]);
+    // The first object, an MS size of 2 and its data
+    assert_eq!(buf[space.objects_offset as usize..][..4], [2, 0, 1, 1]);
+    assert_eq!(read.to_bytes().unwrap(), buf);
+
+    // Corrupt the header variables
+    let mut corrupt = buf.clone();
+    let header =
+        read_r2000_header(&mut BitReader::new(buf.iter()), ParseOptions::default()).unwrap();
+    corrupt[header.records[0].seeker as usize + 20] ^= 1;
+    assert!(matches!(
+        Dwg::from_bytes(&corrupt),
+        Err(DwgError::SectionCrcMismatch { page: 0, .. })
+    ));
+
+    // Corrupt a locator record of the file header
+    let mut corrupt = buf.clone();
+    corrupt[0x1A] ^= 1;
+    assert!(matches!(
+        Dwg::from_bytes(&corrupt),
+        Err(DwgError::FileHeaderCrcMismatch { .. })
+    ));
+
+    // An unknown byte that is always 0, which is only a warning when read leniently
+    let mut unusual = buf.clone();
+    unusual[6] = 1;
+    assert!(matches!(
+        Dwg::from_bytes(&unusual),
+        Err(DwgError::InvalidFlag { at_bit: 48, .. })
+    ));
+    let (lenient, warnings) =
+        Dwg::from_bytes_with(&unusual, ParseOptions { strict: false }).unwrap();
+    assert_eq!(lenient.objects, dwg.objects);
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(
+        warnings[0],
+        ParseWarning::UnexpectedByte {
+            at_bit: 48,
+            expected: 0,
+            found: 1
+        }
+    );
+    assert!(matches!(
+        warnings[1],
+        ParseWarning::FileHeaderCrcMismatch { .. }
+    ));
+}
+
+#[test]
+fn test_unsupported_version() {
+    for (magic, found) in [(b"AC1009", "AC1009 (R11/R12)"), (b"AC1020", "AC1020")] {
+        let mut bytes = magic.to_vec();
+        bytes.resize(0x100, 0);
+        assert_eq!(
+            Dwg::from_bytes(&bytes).err(),
+            Some(DwgError::UnsupportedVersion {
+                found: found.into()
+            })
+        );
+    }
+}
+
+#[test]
+fn test_incremental_save_objects() {
+    // Objects out of handle order, with padding and an older copy of handle 1 between them
+    let data = [
+        0xFF, 0xFF, 1, 0, 0xAA, 0xBB, 0xBB, 0, 1, 0, 1, 0xCC, 0xCC, 2, 0, 3, 4, 0xDD, 0xDD,
+    ];
+    let map = |pairs: &[(u64, i64)]| {
+        let mut section = BitWriter::new();
+        for &(handle, loc) in pairs {
+            section.write_modular_char_u64(handle);
+            section.write_handle_offset(loc);
+        }
+        let section = section.into_bytes();
+        let mut map = ((section.len() + 2) as u16).to_be_bytes().to_vec();
+        map.extend(section);
+        map.extend(crc8(CRC8_SEED, &map).to_be_bytes());
+        map.extend([0, 2]);
+        map.extend(crc8(CRC8_SEED, &[0, 2]).to_be_bytes());
+        map
+    };
+    // Handle 1 at 8, then handle 2 at 2, 6 back
+    let map_data = map(&[(1, 8), (1, -6)]);
+    let objects = read_objects(&map_data, &data, DWGVersion::AC1015).unwrap();
+    assert_eq!(objects[&1], [1]);
+    assert_eq!(objects[&2], [0xAA]);
+
+    // A handle listed again replaces its earlier entry
+    let map_data = map(&[(1, 8), (0, 5)]);
+    assert_eq!(
+        read_objects(&map_data, &data, DWGVersion::AC1015).unwrap()[&1],
+        [3, 4]
+    );
+
+    let map_data = map(&[(1, -1)]);
+    assert!(matches!(
+        read_objects(&map_data, &data, DWGVersion::AC1015),
+        Err(DwgError::Overflow { .. })
+    ));
+}
+
+#[test]
+fn test_object_map() {
+    let mut dwg = Dwg::empty(DWGVersion::AC1015);
+    for handle in 1..=3000 {
+        dwg.objects.insert(handle, vec![4; 5]);
+    }
+    let buf = dwg.to_bytes().unwrap();
+    let file = DwgFile::open(&buf).unwrap();
+    let map_data = file.section(SectionKind::ObjectMap).unwrap().unwrap();
+    let map_data = map_data.data();
+    // The map is split into sections once it grows past 2032 bytes
+    let locations = read_object_map(map_data).unwrap();
+    assert!(map_data.len() > 2040);
+    assert_eq!(file.object_map(), Ok(Some(locations.clone())));
+    assert_eq!(
+        locations.keys().copied().collect::<Vec<_>>(),
+        (1..=3000).collect::<Vec<_>>()
+    );
+    // R13-R2000 objects are located by their offset in the file, starting with their MS size
+    assert_eq!(locations[&3] - locations[&2], locations[&2] - locations[&1]);
+    assert_eq!(buf[locations[&3000] as usize], 5);
+
+    // A corrupt section of the map fails its CRC, numbered from the first
+    let mut corrupt = map_data.to_vec();
+    let second = u16::from_be_bytes([corrupt[0], corrupt[1]]) as usize + 2;
+    corrupt[second + 2] ^= 1;
+    assert!(matches!(
+        read_object_map(&corrupt),
+        Err(DwgError::SectionCrcMismatch { page: 1, .. })
+    ));
+}
+
+#[test]
+fn test_measurement() {
+    let mut dwg = Dwg::empty(DWGVersion::AC1015);
+    assert_eq!(dwg.measurement(), Ok(None));
+    dwg.raw_sections.insert(4, vec![2, 0, b'i', b'n', 0, 0]);
+    assert_eq!(dwg.measurement(), Ok(Some(Measurement::English)));
+
+    // The description is counted in UTF-16 code units from AC1021
+    let mut dwg = Dwg::empty(DWGVersion::AC1021);
+    dwg.raw_sections.insert(4, vec![1, 0, b'm', 0, 1, 0]);
+    assert_eq!(dwg.measurement(), Ok(Some(Measurement::Metric)));
+    dwg.raw_sections.insert(4, vec![2, 0, b'm', 0, 1, 0]);
+    assert!(matches!(
+        dwg.measurement(),
+        Err(DwgError::UnexpectedEof { .. })
+    ));
+}
+
+#[cfg(feature = "memmap2")]
+#[test]
+fn test_read_mmap() {
+    let mut dwg = Dwg::empty(DWGVersion::AC1015);
+    dwg.objects.insert(1, vec![1, 2, 3]);
+    let path = std::env::temp_dir().join("dwg-rs-test-read-mmap.dwg");
+    let path = path.to_str().unwrap();
+    dwg.write_to_file(path).unwrap();
+    let read = Dwg::read_mmap(path);
+    fs::remove_file(path).unwrap();
+    assert_eq!(read.unwrap().objects, dwg.objects);
+    assert!(matches!(
+        Dwg::read_mmap("missing.dwg"),
+        Err(DwgError::Io(std::io::ErrorKind::NotFound))
+    ));
+}
+
+#[test]
+fn test_second_header() {
+    let mut dwg = Dwg::empty(DWGVersion::AC1015);
+    dwg.objects.insert(0x20, vec![1]);
+    dwg.handle_records[1] = 0x10;
+    let mut buf = dwg.to_bytes().unwrap();
+    let header = read_r2000_header(
+        &mut BitSliceReader::from_slice(&buf),
+        ParseOptions::default(),
+    )
+    .unwrap();
+    let (second_header, warnings) = Dwg::check_second_header(&buf).unwrap();
+    assert_eq!(warnings, []);
+    assert_eq!(second_header.address, second_header_address(&header));
+    assert_eq!(second_header.records, header.records);
+    assert_eq!(second_header.handle_records[..2], [0x21, 0x10]);
+
+    let mut moved = second_header.clone();
+    moved.address += 1;
+    moved.records[0].size += 1;
+    moved.records.pop();
+    moved.handle_records[0] = 0x20;
+    let last = *header.records.last().unwrap();
+    assert_eq!(
+        moved.cross_check(second_header.address, &header.records, &dwg),
+        [
+            SecondHeaderWarning::AddressMismatch {
+                expected: second_header.address,
+                found: second_header.address + 1,
+            },
+            SecondHeaderWarning::RecordMismatch {
+                number: 0,
+                file_header: Some(header.records[0]),
+                second_header: Some(moved.records[0]),
+            },
+            SecondHeaderWarning::RecordMismatch {
+                number: last.number,
+                file_header: Some(last),
+                second_header: None,
+            },
+            SecondHeaderWarning::HandseedTooLow {
+                handseed: 0x20,
+                last_handle: 0x20,
+            },
+        ]
+    );
+
+    // A corrupt second header is still used, but its CRC no longer matches
+    let crc_at = buf.len() - 16 - 8 - 2;
+    buf[crc_at] ^= 1;
+    assert_eq!(Dwg::from_bytes(&buf).unwrap().handle_records[1], 0x10);
+    let (_, warnings) = Dwg::check_second_header(&buf).unwrap();
+    assert!(matches!(
+        warnings[..],
+        [SecondHeaderWarning::CrcMismatch { .. }]
+    ));
+}
+
+#[test]
+fn test_r2004_round_trip() {
+    let mut dwg = Dwg::empty(DWGVersion::AC1018);
+    dwg.maintenance_version = 3;
+    dwg.header_vars = vec![1, 2, 3];
+    dwg.classes = vec![4, 5];
+    // Enough objects that the objects section needs several pages
+    for handle in 1..20000u64 {
+        dwg.objects
+            .insert(handle, vec![handle as u8; (handle % 5) as usize + 1]);
+    }
+    dwg.raw_sections.insert(4, vec![0, 0, 1, 0]);
+    dwg.sections.insert("AcDb:AppInfo".into(), vec![7; 3]);
+    dwg.sections
+        .insert(SectionKind::VbaProject.name().into(), vec![8; 5]);
+    let summary_info = SummaryInfo {
+        title: String::from("Plan"),
+        ..Default::default()
+    };
+    dwg.sections.insert(
+        SectionKind::SummaryInfo.name().into(),
+        summary_info.write(DWGVersion::AC1018, dwg.codepage),
+    );
+    assert_eq!(Dwg::empty(DWGVersion::AC1018).summary_info(), Ok(None));
+    let buf = dwg.to_bytes().unwrap();
+
+    let read = Dwg::from_bytes(&buf).unwrap();
+    assert_eq!(read.version(), DWGVersion::AC1018);
+    assert_eq!(read.maintenance_version, dwg.maintenance_version);
+    assert_eq!(read.header_vars, dwg.header_vars);
+    assert_eq!(read.classes, dwg.classes);
+    assert_eq!(read.objects, dwg.objects);
+    assert_eq!(read.raw_sections, dwg.raw_sections);
+    assert_eq!(read.section("AcDb:AppInfo"), Some(&[7u8; 3][..]));
+    assert_eq!(read.section("AcDb:Template"), Some(&[0u8, 0, 1, 0][..]));
+    assert!(read.section("AcDb:AcDbObjects").unwrap().len() > R2004_PAGE_DATA_SIZE);
+    assert_eq!(read.section("AcDb:Preview"), None);
+    assert_eq!(read.thumbnail(), None);
+    assert_eq!(read.summary_info(), Ok(Some(summary_info)));
+    assert_eq!(read.measurement(), Ok(Some(Measurement::Metric)));
+    assert_eq!(read.vba_project(), Some(&[8u8; 5][..]));
+    assert_eq!(Dwg::empty(DWGVersion::AC1018).vba_project(), None);
+    assert_eq!(read.to_bytes().unwrap(), buf);
+
+    let header = read_r2004_file_header(&mut BitSliceReader::from_slice(&buf))
+        .unwrap()
+        .header;
+    let page_map =
+        read_r2004_page_map(&buf, &header, ParseOptions::default(), &mut Vec::new()).unwrap();
+    let section_map = read_r2004_section_map(
+        &buf,
+        &header,
+        &page_map,
+        ParseOptions::default(),
+        &mut Vec::new(),
+    )
+    .unwrap();
+    let objects = section_map.section("AcDb:AcDbObjects").unwrap();
+    assert_eq!(objects.compression, 2);
+    assert_eq!(objects.pages[1].start, R2004_PAGE_DATA_SIZE as u64);
+    assert_eq!(
+        section_map.section("AcDb:Header").unwrap().size,
+        read.section("AcDb:Header").unwrap().len() as u64
+    );
+
+    // Corrupting the data or header of a page fails its checksums
+    let page = page_map.page(objects.pages[1].number).unwrap().address as usize;
+    for at in [page + 0x40, page + 0x0C] {
+        let mut corrupt = buf.clone();
+        corrupt[at] ^= 1;
+        assert!(matches!(
+            Dwg::from_bytes(&corrupt),
+            Err(DwgError::PageChecksumMismatch { section, page, .. })
+                if section == "AcDb:AcDbObjects" && page == objects.pages[1].number
+        ));
+    }
+
+    // Read leniently, a page with a corrupt header or type is read anyway, with warnings
+    let lenient = ParseOptions { strict: false };
+    let mut corrupt = buf.clone();
+    corrupt[page + 0x0C] ^= 1;
+    let (dwg, warnings) = Dwg::from_bytes_with(&corrupt, lenient).unwrap();
+    assert_eq!(dwg.objects, read.objects);
+    assert!(matches!(
+        &warnings[..],
+        [ParseWarning::PageChecksumMismatch { section, .. }] if section == "AcDb:AcDbObjects"
+    ));
+    corrupt[page] ^= 1;
+    let at_bit = page as u64 * 8;
+    assert_eq!(
+        Dwg::from_bytes(&corrupt).err(),
+        Some(DwgError::BadSentinel { at_bit })
+    );
+    let (dwg, warnings) = Dwg::from_bytes_with(&corrupt, lenient).unwrap();
+    assert_eq!(dwg.objects, read.objects);
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(warnings[0], ParseWarning::BadSentinel { at_bit });
+    // Sections are only checked once read
+    let file = DwgFile::open_with(&corrupt, lenient).unwrap();
+    assert_eq!(file.warnings(), []);
+    file.header_vars().unwrap();
+    assert_eq!(file.warnings(), []);
+    file.objects().unwrap();
+    assert_eq!(file.warnings(), warnings);
+
+    // A page whose start overflows when its size is added, read past its checksums
+    let mut corrupt = buf.clone();
+    let start = !(R2004_PAGE_DATA_SIZE as u32) as u64 | 0xFFFFFFFF << 32;
+    for (byte, mask) in corrupt[page + 0x10..page + 0x18]
+        .iter_mut()
+        .zip(start.to_le_bytes())
+    {
+        *byte ^= mask;
+    }
+    assert!(matches!(
+        Dwg::from_bytes_with(&corrupt, lenient),
+        Err(DwgError::InvalidStreamSize { .. })
+    ));
+
+    // A section larger than its pages can hold is not allocated
+    let huge = SectionDescriptor {
+        size: u64::MAX,
+        ..objects.clone()
+    };
+    assert_eq!(
+        read_r2004_section(
+            &buf,
+            &page_map,
+            &huge,
+            ParseOptions::default(),
+            &mut Vec::new()
+        ),
+        Err(DwgError::InvalidStreamSize { at_bit: 0 })
+    );
+
+    // A section page missing from the page map
+    let mut section_map = section_map;
+    section_map.sections[0].pages[0].number = 1000;
+    assert_eq!(
+        read_r2004_section(
+            &buf,
+            &page_map,
+            &section_map.sections[0],
+            ParseOptions::default(),
+            &mut Vec::new()
+        ),
+        Err(DwgError::MissingSectionPage { number: 1000 })
+    );
+}
+
+#[test]
+fn test_encrypted() {
+    let r2004 = Dwg::new(DWGVersion::AC1018).unwrap().to_bytes().unwrap();
+    let r2007 = write_r2007_test_file(&[("AcDb:Header", vec![0; 8])], 0x100);
+    for buf in [&r2004, &r2007] {
+        // The security flags are the RL at 0x18
+        assert_eq!(buf[0x18..0x1C], [0; 4]);
+        // Password protected files, but not those that are only signed
+        for (flags, encrypted) in [
+            (ENCRYPT_DATA, true),
+            (ENCRYPT_PROPERTIES, true),
+            (ENCRYPT_DATA | ENCRYPT_PROPERTIES | 0x20, true),
+            (0x10, false),
+        ] {
+            let mut protected = buf.clone();
+            protected[0x18..0x1C].copy_from_slice(&flags.to_le_bytes());
+            assert_eq!(
+                DwgFile::open(&protected).err(),
+                encrypted.then_some(DwgError::Encrypted { flags })
+            );
+        }
+    }
+
+    let mut protected = r2004;
+    protected[0x18] = ENCRYPT_DATA as u8;
+    assert_eq!(
+        Dwg::from_bytes(&protected).err(),
+        Some(DwgError::Encrypted {
+            flags: ENCRYPT_DATA
+        })
+    );
+}
+
+#[test]
+fn test_modify_objects() {
+    use crate::types::{HandleRef, RefType};
+
+    let mut dwg = Dwg::empty(DWGVersion::AC1015);
+    dwg.objects.insert(5, vec![1, 2]);
+    dwg.objects.insert(6, vec![3]);
+
+    dwg.get_object_mut(5).unwrap().extend([4; 300]);
+    assert_eq!(dwg.delete_object(6), Some(vec![3]));
+    assert_eq!(dwg.delete_object(6), None);
+    let handle = dwg.add_entity(|writer, handle| {
+        writer.write_object_type(0x13);
+        writer.write_raw_long(0);
+        writer.write_handle(HandleRef {
+            code: RefType::Unspecified,
+            handle,
+        });
+    });
+    // The deleted handle is not reused
+    assert_eq!(handle, 7);
+    assert_eq!(dwg.next_handle(), 8);
+
+    let read = Dwg::from_bytes(&dwg.to_bytes().unwrap()).unwrap();
+    assert_eq!(read.get_object(5).map(<[u8]>::len), Some(302));
+    assert_eq!(read.get_object(6), None);
+    assert_eq!(read.get_object(7), dwg.get_object(7));
+    assert_eq!(read.handle_records[0], 8);
+}
+
+#[test]
+#[ignore = "needs test_data/2004/Line.dwg"]
+fn test_r2004_sections_of_file() {
+    let mut d = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("test_data/2004/Line.dwg");
 
-        read_r2000_header(&mut bit_reader);
-        unimplemented!()
+    let bytes = fs::read(d).unwrap();
+    let header = read_r2004_file_header(&mut BitSliceReader::from_slice(&bytes)).unwrap();
+    let page_map = read_r2004_page_map(
+        &bytes,
+        &header.header,
+        ParseOptions::default(),
+        &mut Vec::new(),
+    )
+    .unwrap();
+    let section_map = read_r2004_section_map(
+        &bytes,
+        &header.header,
+        &page_map,
+        ParseOptions::default(),
+        &mut Vec::new(),
+    )
+    .unwrap();
+    // Every page decompresses into its place in the section, and the sections start as
+    // they do in R2000 files
+    for section in section_map.sections.iter().filter(|s| !s.name.is_empty()) {
+        let data = read_r2004_section(
+            &bytes,
+            &page_map,
+            section,
+            ParseOptions::default(),
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(data.len() as u64, section.size, "{}", section.name);
+        let start = match section.name.as_str() {
+            "AcDb:Header" => &sentinels::HEADER_VARIABLES_START[..],
+            "AcDb:Classes" => &sentinels::CLASSES_START[..],
+            "AcDb:AcDbObjects" => &[0xCA, 0x0D, 0x00, 0x00][..],
+            _ => continue,
+        };
+        assert!(data.starts_with(start), "{}", section.name);
     }
 }
 
 #[test]
+#[ignore = "needs test_data/Line.dwg"]
 fn test_r2000_header() {
-    let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let mut d = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     d.push("test_data/Line.dwg");
 
     let bytes = fs::read(d.as_path().to_str().unwrap()).unwrap();
     let mut bit_reader = BitReader::new(bytes.iter());
     // Currently just attempt to read the data
-    read_r2000_header(&mut bit_reader);
+    read_r2000_header(&mut bit_reader, ParseOptions::default()).unwrap();
 }
@@ -0,0 +1,508 @@
+//! Reading the sections of a DWG file only when they are asked for
+//!
+//! [`Dwg::from_bytes`] decodes every section of a file. A [`DwgFile`] instead locates the
+//! sections when it is opened and decompresses each the first time it is read, so reading
+//! the header variables or the thumbnail does not decompress the objects. Objects can be read
+//! one at a time in the same way with [`DwgFile::object`], and entities decoded one at a time
+//! with [`DwgFile::entity`]
+use std::{
+    cell::{OnceCell, RefCell},
+    collections::BTreeMap,
+};
+
+use crate::{
+    bitcodes::BitSliceReader,
+    classes::{classes_by_type, read_classes},
+    dwg::{
+        has_size_high, read_object_at, read_r2000_header, read_r2004_file_header,
+        read_r2004_page_map, read_r2004_section, read_r2004_section_map, read_r2007_file_header,
+        read_r2007_page_map, read_r2007_section, read_r2007_section_map, read_sections, Dwg,
+        PageMap, ParseOptions, ParseWarning, SectionDescriptor, SectionDirectory, SectionKind,
+        SectionLocator, SectionReader,
+    },
+    entities::Entity,
+    error::{DwgError, Result},
+    objects::{is_entity_type, open_object},
+    preview::Preview,
+    summary_info::SummaryInfo,
+    types::CodePage,
+    version::DWGVersion,
+};
+
+/// A DWG file whose sections are read on first access
+pub struct DwgFile<'a> {
+    bytes: &'a [u8],
+    version: DWGVersion,
+    maintenance_version: u8,
+    codepage: CodePage,
+    layout: Layout,
+    /// The objects, once the object map is read
+    objects: OnceCell<ObjectCache>,
+    options: ParseOptions,
+    /// Sanity checks that failed when reading leniently, see [`DwgFile::warnings`]
+    warnings: RefCell<Vec<ParseWarning>>,
+}
+
+/// The location of each object by handle from the object map, along with what has been read
+/// of it
+type ObjectCache = BTreeMap<u64, CachedObject>;
+
+/// An object listed in the object map, see [`ObjectCache`]
+struct CachedObject {
+    /// Location of the object in the objects section
+    address: u64,
+    /// The data of the object, once read
+    data: OnceCell<Vec<u8>>,
+    /// The object decoded as an entity, once decoded
+    entity: OnceCell<Entity>,
+}
+
+/// Where the sections of a file are
+enum Layout {
+    /// The section locator records of an R13-R2000 file, which need no decoding
+    Locators {
+        records: Vec<SectionLocator>,
+        preview_address: u64,
+    },
+    /// The pages of each section of an AC1018 (R2004) or later file by name, along with its
+    /// data once decoded
+    Pages {
+        page_map: PageMap,
+        sections: BTreeMap<String, (SectionDescriptor, OnceCell<Vec<u8>>)>,
+    },
+}
+
+impl<'a> DwgFile<'a> {
+    /// Reads the file header of the DWG file `bytes` and locates its sections, without
+    /// reading any of them
+    ///
+    /// Fails as [`Dwg::from_bytes`] does if the file headers or the page and section maps
+    /// cannot be read
+    pub fn open(bytes: &'a [u8]) -> Result<DwgFile<'a>> {
+        Self::open_with(bytes, ParseOptions::default())
+    }
+
+    /// Opens the DWG file `bytes` as [`DwgFile::open`] does, reading it and each section
+    /// later read from it as `options` say
+    ///
+    /// When reading leniently, the sanity checks that fail are kept in
+    /// [`DwgFile::warnings`] rather than failing the read
+    pub fn open_with(bytes: &'a [u8], options: ParseOptions) -> Result<DwgFile<'a>> {
+        let mut warnings = Vec::new();
+        let mut bit_reader = BitSliceReader::from_slice(bytes);
+        let magic = bytes.get(..6).and_then(|magic| magic.try_into().ok());
+        let (maintenance_version, codepage, page_map, section_map) = match magic
+            .and_then(DWGVersion::from_magic)
+        {
+            Some(DWGVersion::AC1021) => {
+                let file_header = read_r2007_file_header(&mut bit_reader)?;
+                let page_map = read_r2007_page_map(bytes, &file_header.header)?;
+                let section_map = read_r2007_section_map(bytes, &file_header.header, &page_map)?;
+                let (maintenance_version, codepage) =
+                    (file_header.maintenance_version, file_header.codepage);
+                (maintenance_version, codepage, page_map, section_map)
+            }
+            Some(version) if version >= DWGVersion::AC1018 => {
+                let file_header = read_r2004_file_header(&mut bit_reader)?;
+                let header = &file_header.header;
+                let page_map = read_r2004_page_map(bytes, header, options, &mut warnings)?;
+                let section_map =
+                    read_r2004_section_map(bytes, header, &page_map, options, &mut warnings)?;
+                let (maintenance_version, codepage) =
+                    (file_header.maintenance_version, file_header.codepage);
+                (maintenance_version, codepage, page_map, section_map)
+            }
+            _ => {
+                let header = read_r2000_header(&mut bit_reader, options)?;
+                return Ok(DwgFile {
+                    bytes,
+                    version: bit_reader.get_version(),
+                    maintenance_version: header.maintenance_version,
+                    codepage: header.codepage,
+                    layout: Layout::Locators {
+                        records: header.records,
+                        preview_address: header.preview_address,
+                    },
+                    objects: OnceCell::new(),
+                    options,
+                    warnings: RefCell::new(header.warnings),
+                });
+            }
+        };
+        let sections = section_map
+            .sections
+            .into_iter()
+            .filter(|section| !section.name.is_empty())
+            .map(|section| (section.name.clone(), (section, OnceCell::new())))
+            .collect();
+        Ok(DwgFile {
+            bytes,
+            version: bit_reader.get_version(),
+            maintenance_version,
+            codepage,
+            layout: Layout::Pages { page_map, sections },
+            objects: OnceCell::new(),
+            options,
+            warnings: RefCell::new(warnings),
+        })
+    }
+
+    pub fn version(&self) -> DWGVersion {
+        self.version
+    }
+
+    /// Maintenance release version, see [`Dwg::maintenance_version`]
+    pub fn maintenance_version(&self) -> u8 {
+        self.maintenance_version
+    }
+
+    /// Codepage of text in the drawing, see [`Dwg::codepage`]
+    pub fn codepage(&self) -> CodePage {
+        self.codepage
+    }
+
+    /// Returns the sanity checks that failed when reading the file leniently so far, see
+    /// [`DwgFile::open_with`]
+    ///
+    /// Sections are only checked when first read, so more may be added as they are
+    pub fn warnings(&self) -> Vec<ParseWarning> {
+        self.warnings.borrow().clone()
+    }
+
+    /// Returns the directory of the sections of the file
+    pub fn directory(&self) -> SectionDirectory<'_> {
+        match &self.layout {
+            Layout::Locators {
+                records,
+                preview_address,
+            } => SectionDirectory::Locators {
+                bytes: self.bytes,
+                records,
+                preview_address: *preview_address,
+            },
+            Layout::Pages { .. } => SectionDirectory::Lazy(self),
+        }
+    }
+
+    /// Returns a reader over the data of a section, see [`SectionDirectory::get`]
+    pub fn section(&self, kind: SectionKind) -> Result<Option<SectionReader<'_>>> {
+        self.directory().get(kind)
+    }
+
+    /// Returns the decoded data of the section named `name` of an AC1018 (R2004) or later
+    /// file, decoding it if this is the first time it is asked for
+    ///
+    /// Returns `None` if the file has no such section, and for every name in R13-R2000 files
+    pub fn named_section(&self, name: &str) -> Result<Option<&[u8]>> {
+        let Layout::Pages { page_map, sections } = &self.layout else {
+            return Ok(None);
+        };
+        let Some((section, data)) = sections.get(name) else {
+            return Ok(None);
+        };
+        if let Some(data) = data.get() {
+            return Ok(Some(data));
+        }
+        let decoded = match self.version {
+            DWGVersion::AC1021 => read_r2007_section(self.bytes, page_map, section)?,
+            _ => {
+                let warnings = &mut self.warnings.borrow_mut();
+                read_r2004_section(self.bytes, page_map, section, self.options, warnings)?
+            }
+        };
+        Ok(Some(data.get_or_init(|| decoded)))
+    }
+
+    /// Returns the names of the sections of an AC1018 (R2004) or later file, in order, or
+    /// nothing for R13-R2000 files
+    pub fn section_names(&self) -> impl Iterator<Item = &str> {
+        let sections = match &self.layout {
+            Layout::Pages { sections, .. } => Some(sections.keys().map(String::as_str)),
+            Layout::Locators { .. } => None,
+        };
+        sections.into_iter().flatten()
+    }
+
+    /// Reads the header variables, see [`Dwg::header_vars`]
+    pub fn header_vars(&self) -> Result<Option<Vec<u8>>> {
+        self.directory().header_vars(self.size_high())
+    }
+
+    /// Reads the classes, see [`Dwg::classes`]
+    pub fn classes(&self) -> Result<Option<Vec<u8>>> {
+        self.directory().classes(self.size_high())
+    }
+
+    /// Reads the objects, see [`Dwg::objects`]
+    pub fn objects(&self) -> Result<Option<BTreeMap<u64, Vec<u8>>>> {
+        self.directory().objects(self.version)
+    }
+
+    /// Reads the location of each object by handle, see [`crate::dwg::read_object_map`]
+    pub fn object_map(&self) -> Result<Option<BTreeMap<u64, u64>>> {
+        self.directory().object_map()
+    }
+
+    /// Returns the data of the object with `handle`, as [`Dwg::objects`] holds it, reading it
+    /// if this is the first time it is asked for
+    ///
+    /// Only the object map and the object are read, along with the objects section they are
+    /// in for AC1018 (R2004) and later files, so references can be followed without reading
+    /// every object. Returns `None` if the object map does not list the handle or the file has
+    /// no objects
+    pub fn object(&self, handle: u64) -> Result<Option<&[u8]>> {
+        let Some(cached) = self.cached_object(handle)? else {
+            return Ok(None);
+        };
+        if let Some(data) = cached.data.get() {
+            return Ok(Some(data));
+        }
+        let Some(objects) = self.section(SectionKind::Objects)? else {
+            return Ok(None);
+        };
+        let mut reader = BitSliceReader::from_slice(objects.data());
+        let object = read_object_at(&mut reader, cached.address, self.version)?;
+        Ok(Some(cached.data.get_or_init(|| object)))
+    }
+
+    /// Returns the entity with `handle` decoded, decoding it if this is the first time it is
+    /// asked for, see [`Entity::decode`]
+    ///
+    /// Only the object is read, as [`DwgFile::object`] does, so the handle references of an
+    /// entity can be followed to the entities they refer to without decoding any others. The
+    /// classes are read as well if the type of the object is defined by them. Returns `None`
+    /// as [`DwgFile::object`] does, and fails with `InvalidFlag` if the object is not an
+    /// entity
+    pub fn entity(&self, handle: u64) -> Result<Option<&Entity>> {
+        let Some(cached) = self.cached_object(handle)? else {
+            return Ok(None);
+        };
+        if let Some(entity) = cached.entity.get() {
+            return Ok(Some(entity));
+        }
+        let Some(data) = self.object(handle)? else {
+            return Ok(None);
+        };
+        let (object_type, _) = open_object(data, self.version, self.codepage)?;
+        if !is_entity_type(object_type) && !self.is_class_entity(object_type)? {
+            return Err(DwgError::InvalidFlag {
+                at_bit: 0,
+                value: object_type as u16 as u64,
+            });
+        }
+        let entity = Entity::decode(data, self.version, self.codepage)?;
+        Ok(Some(cached.entity.get_or_init(|| entity)))
+    }
+
+    /// Returns what is cached of the object with `handle`, reading the object map if this is
+    /// the first time an object is asked for
+    fn cached_object(&self, handle: u64) -> Result<Option<&CachedObject>> {
+        let locations = match self.objects.get() {
+            Some(locations) => locations,
+            None => {
+                let Some(map) = self.object_map()? else {
+                    return Ok(None);
+                };
+                let locations = map
+                    .into_iter()
+                    .map(|(handle, address)| {
+                        let cached = CachedObject {
+                            address,
+                            data: OnceCell::new(),
+                            entity: OnceCell::new(),
+                        };
+                        (handle, cached)
+                    })
+                    .collect();
+                self.objects.get_or_init(|| locations)
+            }
+        };
+        Ok(locations.get(&handle))
+    }
+
+    /// Returns true if `object_type` is defined by a class of entities
+    fn is_class_entity(&self, object_type: i16) -> Result<bool> {
+        let Some(classes) = self.classes()? else {
+            return Ok(false);
+        };
+        let classes = read_classes(&classes, self.version, self.codepage)?;
+        let class = classes_by_type(&classes).get(&object_type).copied();
+        Ok(class.is_some_and(|class| class.is_entity()))
+    }
+
+    /// Reads the preview image section, see [`Dwg::preview`]
+    pub fn preview(&self) -> Result<Option<Preview>> {
+        self.directory().preview()
+    }
+
+    /// Reads the document properties, see [`Dwg::summary_info`]
+    pub fn summary_info(&self) -> Result<Option<SummaryInfo>> {
+        self.named_section(SectionKind::SummaryInfo.name())?
+            .map(|data| SummaryInfo::read(data, self.version, self.codepage))
+            .transpose()
+    }
+
+    /// Reads every section of the file into a drawing, as [`Dwg::from_bytes`] does
+    pub fn into_dwg(self) -> Result<Dwg> {
+        self.into_dwg_with_warnings().map(|(dwg, _)| dwg)
+    }
+
+    /// Reads every section of the file into a drawing, as [`Dwg::from_bytes_with`] does,
+    /// along with the [`DwgFile::warnings`] of the whole file
+    pub(crate) fn into_dwg_with_warnings(self) -> Result<(Dwg, Vec<ParseWarning>)> {
+        let Layout::Pages { sections, .. } = &self.layout else {
+            return Dwg::from_bytes_with(self.bytes, self.options);
+        };
+        for name in sections.keys() {
+            self.named_section(name)?;
+        }
+        let mut dwg = Dwg::empty(self.version);
+        dwg.maintenance_version = self.maintenance_version;
+        dwg.codepage = self.codepage;
+        read_sections(&mut dwg, &self.directory())?;
+        if let Layout::Pages { sections, .. } = self.layout {
+            dwg.sections = sections
+                .into_iter()
+                .map(|(name, (_, data))| (name, data.into_inner().unwrap_or_default()))
+                .collect();
+        }
+        Ok((dwg, self.warnings.into_inner()))
+    }
+
+    fn size_high(&self) -> bool {
+        has_size_high(self.version, self.maintenance_version)
+    }
+}
+
+#[test]
+fn test_dwg_file() {
+    use crate::preview::{PreviewEntry, BMP};
+
+    let mut dwg = Dwg::empty(DWGVersion::AC1018);
+    dwg.header_vars = vec![1, 2, 3];
+    dwg.objects.insert(1, vec![4; 5]);
+    dwg.sections.insert(
+        SectionKind::Preview.name().into(),
+        Preview {
+            entries: vec![PreviewEntry {
+                code: BMP,
+                data: vec![6; 7],
+            }],
+        }
+        .write(0),
+    );
+    let buf = dwg.to_bytes().unwrap();
+
+    let file = DwgFile::open(&buf).unwrap();
+    assert_eq!(file.version(), DWGVersion::AC1018);
+    let Layout::Pages { sections, .. } = &file.layout else {
+        panic!("R2004 sections are paged");
+    };
+    let decoded = || sections.values().filter(|(_, data)| data.get().is_some());
+    assert_eq!(decoded().count(), 0);
+    assert_eq!(file.header_vars(), Ok(Some(dwg.header_vars.clone())));
+    assert!(file.preview().unwrap().unwrap().thumbnail().is_some());
+    assert_eq!(file.summary_info(), Ok(None));
+    // Only the sections asked for are decoded, and only once
+    let names: Vec<_> = decoded()
+        .map(|(section, _)| section.name.as_str())
+        .collect();
+    assert_eq!(names, ["AcDb:Header", "AcDb:Preview"]);
+    let header = file.named_section("AcDb:Header").unwrap().unwrap();
+    assert!(std::ptr::eq(
+        header,
+        file.named_section("AcDb:Header").unwrap().unwrap()
+    ));
+
+    // Objects are read one at a time, and only once
+    assert_eq!(file.object(1), Ok(Some(&[4; 5][..])));
+    assert!(std::ptr::eq(
+        file.object(1).unwrap().unwrap(),
+        file.object(1).unwrap().unwrap()
+    ));
+    assert_eq!(file.object(2), Ok(None));
+
+    let read = file.into_dwg().unwrap();
+    assert_eq!(read.objects, dwg.objects);
+    assert_eq!(read.to_bytes().unwrap(), buf);
+
+    // R13-R2000 sections need no decoding
+    let mut dwg = Dwg::empty(DWGVersion::AC1015);
+    dwg.classes = vec![8, 9];
+    let buf = dwg.to_bytes().unwrap();
+    let file = DwgFile::open(&buf).unwrap();
+    assert_eq!(file.classes(), Ok(Some(dwg.classes.clone())));
+    assert_eq!(file.object(1), Ok(None));
+    assert_eq!(file.named_section("AcDb:Classes"), Ok(None));
+    assert_eq!(file.into_dwg().unwrap().classes, dwg.classes);
+}
+
+#[test]
+fn test_dwg_file_object() {
+    let mut dwg = Dwg::empty(DWGVersion::AC1015);
+    for handle in 1..=10 {
+        dwg.objects
+            .insert(handle, vec![handle as u8; handle as usize]);
+    }
+    let buf = dwg.to_bytes().unwrap();
+    let file = DwgFile::open(&buf).unwrap();
+    assert_eq!(file.object(7), Ok(Some(&dwg.objects[&7][..])));
+    let read: Vec<_> = file
+        .objects
+        .get()
+        .unwrap()
+        .iter()
+        .filter(|(_, cached)| cached.data.get().is_some())
+        .map(|(handle, _)| *handle)
+        .collect();
+    assert_eq!(read, [7]);
+    assert_eq!(file.object(11), Ok(None));
+}
+
+#[test]
+fn test_dwg_file_entity() {
+    use crate::types::Point3;
+
+    let mut dwg = Dwg::new(DWGVersion::AC1015).unwrap();
+    let lines: Vec<_> = (0..4)
+        .map(|i| {
+            let start = Point3::from((i as f64, 0.0, 0.0));
+            dwg.add_line(start, Point3::default()).unwrap()
+        })
+        .collect();
+    let buf = dwg.to_bytes().unwrap();
+    let file = DwgFile::open(&buf).unwrap();
+
+    // Following the link from one line to the next decodes only those two
+    let Some(Entity::Line(first)) = file.entity(lines[1]).unwrap() else {
+        panic!("lines decode as lines");
+    };
+    assert_eq!(first.start, Point3::from((1.0, 0.0, 0.0)));
+    let next = first.header.resolved_links().1;
+    assert_eq!(next, lines[2]);
+    let Some(Entity::Line(second)) = file.entity(next).unwrap() else {
+        panic!("lines decode as lines");
+    };
+    assert_eq!(second.start, Point3::from((2.0, 0.0, 0.0)));
+    let decoded: Vec<_> = file
+        .objects
+        .get()
+        .unwrap()
+        .iter()
+        .filter(|(_, cached)| cached.data.get().is_some() || cached.entity.get().is_some())
+        .map(|(handle, _)| *handle)
+        .collect();
+    assert_eq!(decoded, [lines[1], lines[2]]);
+    assert!(std::ptr::eq(
+        file.entity(next).unwrap().unwrap(),
+        file.entity(next).unwrap().unwrap()
+    ));
+
+    // Objects that are not entities, such as the layer, are not decoded as them
+    let layer = second.header.layer.absolute(next);
+    assert!(matches!(
+        file.entity(layer),
+        Err(DwgError::InvalidFlag { .. })
+    ));
+    assert_eq!(file.entity(0x999), Ok(None));
+}
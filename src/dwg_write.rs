@@ -0,0 +1,803 @@
+//! Writing drawings to DWG files
+use std::fs;
+
+use crate::{
+    bitcodes::BitSliceReader,
+    bitcodes_write::BitWriter,
+    compression::compress_r2004,
+    crc::{crc8, page_checksum, CRC8_SEED},
+    dwg::{
+        has_size_high, r2000_header_crc_xor, Dwg, ObjFreeSpace, R2004Header, SecondHeader,
+        SectionKind, SectionLocator, R2004_DATA_PAGE_MASK, R2004_DATA_PAGE_TYPE, R2004_PAGES_START,
+        R2004_PAGE_ALIGNMENT, R2004_PAGE_DATA_SIZE, R2004_PAGE_MAP_TYPE, R2004_SECTION_MAP_TYPE,
+    },
+    error::{DwgError, Result},
+    sentinels,
+    version::DWGVersion,
+};
+
+/// Largest object map section in bytes, including its size but not its CRC
+const OBJECT_MAP_SECTION_SIZE: usize = 2032;
+
+/// Number of section locator records always written to R2000 file headers, for the header
+/// variables, classes, object map, ObjFreeSpace and template sections
+const R2000_N_RECORDS: usize = 5;
+
+impl Dwg {
+    /// Writes the drawing to a file, see [`Dwg::to_bytes`]
+    pub fn write_to_file(&self, file_name: &str) -> Result<()> {
+        fs::write(file_name, self.to_bytes()?)?;
+        Ok(())
+    }
+
+    /// Writes the drawing to a new buffer in DWG format
+    ///
+    /// Only AC1015 (R2000) and AC1018 (R2004) drawings can be written, other versions fail
+    /// with `UnsupportedVersion`
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        match self.version() {
+            DWGVersion::AC1015 => write_r2000(self),
+            DWGVersion::AC1018 => write_r2004(self),
+            version => Err(DwgError::UnsupportedVersion {
+                found: format!("{version:?}"),
+            }),
+        }
+    }
+}
+
+/// Returns true if drawings can be written as `version`
+pub(crate) fn is_writable(version: DWGVersion) -> bool {
+    matches!(version, DWGVersion::AC1015 | DWGVersion::AC1018)
+}
+
+/// Writes an R2000 drawing
+///
+/// The file header is followed by the preview image, header variables, classes, objects,
+/// object map, ObjFreeSpace, template, any other raw sections and finally the second header,
+/// which lists where the others are
+fn write_r2000(dwg: &Dwg) -> Result<Vec<u8>> {
+    let extra_sections = dwg.raw_sections.range(R2000_N_RECORDS as u8..);
+    let n_records = R2000_N_RECORDS + extra_sections.clone().count();
+    let header_size = 0x19 + 9 * n_records + 2 + 16;
+    // The file header is written last, once the locations of the sections are known
+    let mut out = vec![0u8; header_size];
+    let preview_address = match &dwg.preview {
+        Some(preview) => {
+            let address = out.len();
+            out.extend(preview.write(address as u64));
+            address
+        }
+        None => 0,
+    };
+    let mut records = Vec::with_capacity(n_records);
+    let mut add_record = |out: &Vec<u8>, number, start: usize| {
+        records.push(SectionLocator {
+            number,
+            seeker: start as u32,
+            size: (out.len() - start) as u32,
+        });
+    };
+
+    let start = out.len();
+    write_r2000_section(
+        &mut out,
+        &sentinels::HEADER_VARIABLES_START,
+        &dwg.header_vars,
+        &sentinels::HEADER_VARIABLES_END,
+        false,
+    )?;
+    add_record(&out, 0, start);
+
+    let start = out.len();
+    write_r2000_section(
+        &mut out,
+        &sentinels::CLASSES_START,
+        &dwg.classes,
+        &sentinels::CLASSES_END,
+        false,
+    )?;
+    add_record(&out, 1, start);
+
+    let objects_start = out.len();
+    let locations = write_objects(&mut out, dwg)?;
+
+    let start = out.len();
+    write_object_map(&mut out, &locations);
+    add_record(&out, 2, start);
+
+    let start = out.len();
+    write_obj_free_space(&mut out, dwg, objects_start as u32);
+    add_record(&out, 3, start);
+
+    let start = out.len();
+    match dwg.raw_sections.get(&4) {
+        Some(template) => out.extend(template),
+        None => write_template(&mut out),
+    }
+    add_record(&out, 4, start);
+
+    for (&number, data) in extra_sections {
+        let start = out.len();
+        out.extend(data);
+        add_record(&out, number, start);
+    }
+
+    let mut handle_records = dwg.handle_records;
+    handle_records[0] = dwg.next_handle();
+    let second_header = SecondHeader {
+        address: out.len() as u64,
+        records: records.clone(),
+        handle_records,
+    };
+    write_second_header(&mut out, dwg.version(), &second_header)?;
+
+    let header = write_r2000_file_header(dwg, &records, preview_address);
+    debug_assert_eq!(header.len(), header_size);
+    out[..header_size].copy_from_slice(&header);
+    Ok(out)
+}
+
+/// Writes the file header with the section locator records
+fn write_r2000_file_header(
+    dwg: &Dwg,
+    records: &[SectionLocator],
+    preview_address: usize,
+) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    writer.write_bytes(dwg.version().magic());
+    writer.write_bytes(&[0; 5]);
+    writer.write_raw_char(dwg.maintenance_version as i8);
+    writer.write_raw_char(1);
+    writer.write_raw_long(preview_address as i32);
+    writer.write_bytes(&[0; 2]);
+    writer.write_raw_short(dwg.codepage as u16 as i16);
+    writer.write_raw_long(records.len() as i32);
+    for record in records {
+        writer.write_raw_char(record.number as i8);
+        writer.write_raw_long(record.seeker as i32);
+        writer.write_raw_long(record.size as i32);
+    }
+    let crc = crc8(0, writer.as_bytes()) ^ r2000_header_crc_xor(records.len()).unwrap_or(0);
+    writer.write_raw_short(crc as i16);
+    writer.write_sentinel(&sentinels::FILE_HEADER_END);
+    writer.into_bytes()
+}
+
+/// Writes a section as its start sentinel, RL size, data, RS CRC of the size and data, then
+/// its end sentinel
+///
+/// `size_high` adds the RL high 32 bits of the size of later versions, see
+/// [`has_size_high`]
+pub(crate) fn write_r2000_section(
+    out: &mut Vec<u8>,
+    start: &[u8; 16],
+    data: &[u8],
+    end: &[u8; 16],
+    size_high: bool,
+) -> Result<()> {
+    let mut writer = BitWriter::new();
+    writer.write_sentinel(start);
+    writer.start_crc(CRC8_SEED);
+    writer.write_raw_long(data.len() as i32);
+    if size_high {
+        writer.write_raw_long(0);
+    }
+    writer.write_bytes(data);
+    writer.write_crc()?;
+    writer.write_sentinel(end);
+    out.extend(writer.into_bytes());
+    Ok(())
+}
+
+/// Writes each object as its MS size, data and RS CRC, returning `(handle, offset)` pairs
+/// locating them in `out`
+///
+/// The MC handle stream size starting AC1024 (R2010) and later objects is not counted in
+/// their size
+fn write_objects(out: &mut Vec<u8>, dwg: &Dwg) -> Result<Vec<(u64, u64)>> {
+    let mut locations = Vec::with_capacity(dwg.objects.len());
+    for (&handle, data) in &dwg.objects {
+        locations.push((handle, out.len() as u64));
+        let mut size = data.len();
+        if dwg.version() >= DWGVersion::AC1024 {
+            let mut reader = BitSliceReader::from_slice(data);
+            if reader.read_modular_char_u64().is_ok() {
+                size -= reader.byte_position() as usize;
+            }
+        }
+        let mut writer = BitWriter::new();
+        writer.start_crc(CRC8_SEED);
+        writer.write_modular_short(size as u32);
+        writer.write_bytes(data);
+        writer.write_crc()?;
+        out.extend(writer.into_bytes());
+    }
+    Ok(locations)
+}
+
+/// Writes the object map from `(handle, file offset)` pairs sorted by handle
+///
+/// The map is split into sections of at most [`OBJECT_MAP_SECTION_SIZE`] bytes. Each starts
+/// with its big endian RS size, then the pairs as offsets from the previous pair in the
+/// section, a UMC for the handle and an MC for the location, then a big endian RS CRC. An
+/// empty section ends the map
+fn write_object_map(out: &mut Vec<u8>, locations: &[(u64, u64)]) {
+    fn write_section(out: &mut Vec<u8>, data: &[u8]) {
+        let start = out.len();
+        out.extend(((data.len() + 2) as u16).to_be_bytes());
+        out.extend(data);
+        let crc = crc8(CRC8_SEED, &out[start..]);
+        out.extend(crc.to_be_bytes());
+    }
+
+    let mut section = Vec::new();
+    let (mut last_handle, mut last_loc) = (0, 0);
+    for &(handle, loc) in locations {
+        let mut pair = BitWriter::new();
+        pair.write_modular_char_u64(handle - last_handle);
+        pair.write_handle_offset(loc as i64 - last_loc as i64);
+        if 2 + section.len() + pair.as_bytes().len() > OBJECT_MAP_SECTION_SIZE {
+            write_section(out, &section);
+            section.clear();
+            // Offsets start again from 0 in each section
+            pair = BitWriter::new();
+            pair.write_modular_char_u64(handle);
+            pair.write_handle_offset(loc as i64);
+        }
+        section.extend(pair.as_bytes());
+        (last_handle, last_loc) = (handle, loc);
+    }
+    if !section.is_empty() {
+        write_section(out, &section);
+    }
+    write_section(out, &[]);
+}
+
+/// Writes the ObjFreeSpace section, see `read_obj_free_space`
+fn write_obj_free_space(out: &mut Vec<u8>, dwg: &Dwg, objects_start: u32) {
+    let space = ObjFreeSpace {
+        approx_n_objects: dwg.objects.len() as u32,
+        objects_offset: objects_start,
+        ..dwg.obj_free_space.unwrap_or_default()
+    };
+    let mut writer = BitWriter::new();
+    writer.write_raw_long(0);
+    writer.write_raw_long(space.approx_n_objects as i32);
+    writer.write_raw_long(space.updated.day);
+    writer.write_raw_long(space.updated.ms);
+    writer.write_raw_long(space.objects_offset as i32);
+    writer.write_raw_char(space.max_handles.len() as i8);
+    for value in space.max_handles {
+        writer.write_raw_longlong(value as i64);
+    }
+    out.extend(writer.into_bytes());
+}
+
+/// Writes the template section, an empty description and a MEASUREMENT of 0 (English)
+fn write_template(out: &mut Vec<u8>) {
+    let mut writer = BitWriter::new();
+    writer.write_raw_short(0);
+    writer.write_raw_short(0);
+    out.extend(writer.into_bytes());
+}
+
+/// Writes the second header, which repeats the section locator records and lists the
+/// handles of the control objects
+fn write_second_header(
+    out: &mut Vec<u8>,
+    version: DWGVersion,
+    header: &SecondHeader,
+) -> Result<()> {
+    let mut body = BitWriter::new();
+    body.write_bitlong(header.address as i32);
+    body.write_bytes(version.magic());
+    body.write_bytes(&[0; 6]);
+    for _ in 0..4 {
+        body.write_bit(0);
+    }
+    body.write_raw_char(0x10);
+    body.write_bytes(&[0x84, 0x74, 0x78, 0x01]);
+    body.write_raw_char(header.records.len() as i8);
+    for record in &header.records {
+        body.write_raw_char(record.number as i8);
+        body.write_bitlong(record.seeker as i32);
+        body.write_bitlong(record.size as i32);
+    }
+    let handles = &header.handle_records;
+    body.write_bitshort(handles.len() as i16);
+    for (number, handle) in handles.iter().enumerate() {
+        let bytes = handle.to_be_bytes();
+        let skip = (handle.leading_zeros() / 8).min(7) as usize;
+        body.write_raw_char((8 - skip) as i8);
+        body.write_raw_char(number as i8);
+        body.write_bytes(&bytes[skip..]);
+    }
+    body.align();
+
+    let mut writer = BitWriter::new();
+    writer.write_sentinel(&sentinels::SECOND_HEADER_START);
+    writer.start_crc(CRC8_SEED);
+    writer.write_raw_long(body.as_bytes().len() as i32);
+    writer.write_bytes(body.as_bytes());
+    writer.write_crc()?;
+    // Unused
+    writer.write_raw_longlong(0);
+    writer.write_sentinel(&sentinels::SECOND_HEADER_END);
+    out.extend(writer.into_bytes());
+    Ok(())
+}
+
+/// Writes an R2004 drawing
+///
+/// The data of each section is split into pages of at most [`R2004_PAGE_DATA_SIZE`] bytes,
+/// which are compressed and written from [`R2004_PAGES_START`]. They are followed by the
+/// section map, listing the pages of each section, then the page map, listing the size of
+/// every page. The file header, locating the maps, is encrypted
+fn write_r2004(dwg: &Dwg) -> Result<Vec<u8>> {
+    let sections = r2004_sections(dwg)?;
+    let mut out = vec![0u8; R2004_PAGES_START];
+    // Size of each page, by page number starting from 1
+    let mut page_sizes = Vec::new();
+
+    let mut section_map = BitWriter::new();
+    section_map.write_raw_long(sections.len() as i32);
+    section_map.write_raw_long(2);
+    section_map.write_raw_long(R2004_PAGE_DATA_SIZE as i32);
+    section_map.write_raw_long(0);
+    section_map.write_raw_long(sections.len() as i32);
+    for (id, (name, data)) in (1..).zip(&sections) {
+        let pages: Vec<_> = data.chunks(R2004_PAGE_DATA_SIZE).collect();
+        // Pages too short to compress are only possible at the end of a section, in which
+        // case the whole section is stored uncompressed
+        let compressed = pages.iter().all(|page| page.len() >= 4);
+        section_map.write_raw_longlong(data.len() as i64);
+        section_map.write_raw_long(pages.len() as i32);
+        section_map.write_raw_long(R2004_PAGE_DATA_SIZE as i32);
+        section_map.write_raw_long(1);
+        section_map.write_raw_long(if compressed { 2 } else { 1 });
+        section_map.write_raw_long(id);
+        // Not encrypted
+        section_map.write_raw_long(0);
+        let mut name_bytes = [0u8; 64];
+        name_bytes[..name.len()].copy_from_slice(name.as_bytes());
+        section_map.write_bytes(&name_bytes);
+
+        for (i, page) in pages.into_iter().enumerate() {
+            let start = i * R2004_PAGE_DATA_SIZE;
+            let (bytes, data_size) =
+                write_data_page(out.len(), id as u32, start, page, compressed)?;
+            section_map.write_raw_long(page_sizes.len() as i32 + 1);
+            section_map.write_raw_long(data_size as i32);
+            section_map.write_raw_longlong(start as i64);
+            page_sizes.push(bytes.len() as u32);
+            out.extend(bytes);
+        }
+    }
+
+    let section_map = write_system_page(R2004_SECTION_MAP_TYPE, section_map.as_bytes())?;
+    page_sizes.push(section_map.len() as u32);
+    let section_map_id = page_sizes.len() as u32;
+    out.extend(section_map);
+
+    // The page map lists itself, so it is rewritten until its size settles
+    let page_map_address = out.len();
+    let mut page_map_size = 0;
+    let page_map = loop {
+        let mut writer = BitWriter::new();
+        for (number, size) in (1..).zip(page_sizes.iter().chain([&page_map_size])) {
+            writer.write_raw_long(number);
+            writer.write_raw_long(*size as i32);
+        }
+        let page_map = write_system_page(R2004_PAGE_MAP_TYPE, writer.as_bytes())?;
+        if page_map.len() as u32 == page_map_size {
+            break page_map;
+        }
+        page_map_size = page_map.len() as u32;
+    };
+    page_sizes.push(page_map_size);
+    out.extend(page_map);
+
+    let n_pages = page_sizes.len() as u32;
+    let header = R2004Header {
+        last_section_page_id: n_pages,
+        last_section_page_end_address: (out.len() - R2004_PAGES_START) as u64,
+        section_page_amount: n_pages,
+        section_page_map_id: n_pages,
+        section_page_map_address: (page_map_address - R2004_PAGES_START) as u64,
+        section_map_id,
+        section_page_array_size: n_pages,
+        ..Default::default()
+    };
+    out[..R2004_PAGES_START].copy_from_slice(&write_r2004_file_header(dwg, &header));
+    Ok(out)
+}
+
+/// Returns the name and data of each section of an R2004 drawing
+///
+/// The header variables and classes keep the sentinels and CRCs of R2000. Offsets in the
+/// object map are from the start of the objects section, which begins with RL 0x0DCA. Any
+/// other sections read with the drawing follow the generated ones
+fn r2004_sections(dwg: &Dwg) -> Result<Vec<(&str, Vec<u8>)>> {
+    let size_high = has_size_high(dwg.version(), dwg.maintenance_version);
+    let mut header_vars = Vec::new();
+    write_r2000_section(
+        &mut header_vars,
+        &sentinels::HEADER_VARIABLES_START,
+        &dwg.header_vars,
+        &sentinels::HEADER_VARIABLES_END,
+        size_high,
+    )?;
+
+    let mut classes = Vec::new();
+    write_r2000_section(
+        &mut classes,
+        &sentinels::CLASSES_START,
+        &dwg.classes,
+        &sentinels::CLASSES_END,
+        size_high,
+    )?;
+
+    let mut objects = 0x0DCAu32.to_le_bytes().to_vec();
+    let locations = write_objects(&mut objects, dwg)?;
+
+    let mut handles = Vec::new();
+    write_object_map(&mut handles, &locations);
+
+    let mut obj_free_space = Vec::new();
+    write_obj_free_space(&mut obj_free_space, dwg, 0);
+
+    let template = dwg.raw_sections.get(&4).cloned().unwrap_or_else(|| {
+        let mut template = Vec::new();
+        write_template(&mut template);
+        template
+    });
+
+    let mut sections = vec![
+        (SectionKind::Header.name(), header_vars),
+        (SectionKind::Classes.name(), classes),
+        (SectionKind::Objects.name(), objects),
+        (SectionKind::ObjectMap.name(), handles),
+        (SectionKind::ObjFreeSpace.name(), obj_free_space),
+        (SectionKind::Template.name(), template),
+    ];
+    for (name, data) in &dwg.sections {
+        if !sections.iter().any(|(generated, _)| generated == name) {
+            sections.push((name, data.clone()));
+        }
+    }
+    Ok(sections)
+}
+
+/// Writes a data page at `address` holding `data` from offset `start` of section `id`,
+/// returning the page and the size of its possibly compressed data
+///
+/// The page header is encrypted and the page padded to [`R2004_PAGE_ALIGNMENT`]
+fn write_data_page(
+    address: usize,
+    id: u32,
+    start: usize,
+    data: &[u8],
+    compressed: bool,
+) -> Result<(Vec<u8>, usize)> {
+    let page_data = if compressed {
+        compress_r2004(data)?
+    } else {
+        data.to_vec()
+    };
+    let data_checksum = page_checksum(0, &page_data);
+    let mut header = [
+        R2004_DATA_PAGE_TYPE,
+        id,
+        page_data.len() as u32,
+        data.len() as u32,
+        start as u32,
+        (start as u64 >> 32) as u32,
+        0,
+        data_checksum,
+    ];
+    // The header checksum is calculated with its own field set to 0
+    header[6] = page_checksum(data_checksum, &words_to_bytes(&header));
+
+    let mask = R2004_DATA_PAGE_MASK ^ address as u32;
+    let mut page = words_to_bytes(&header.map(|word| word ^ mask));
+    page.extend(&page_data);
+    page.resize(page.len().next_multiple_of(R2004_PAGE_ALIGNMENT), 0);
+    Ok((page, page_data.len()))
+}
+
+/// Writes a system page holding the section or page map, compressing `data`
+///
+/// The header lists the type of the page, the decompressed and compressed sizes of the data,
+/// the compression type and a checksum of the compressed data and header
+fn write_system_page(page_type: u32, data: &[u8]) -> Result<Vec<u8>> {
+    let compressed = compress_r2004(data)?;
+    let mut header = [page_type, data.len() as u32, compressed.len() as u32, 2, 0];
+    header[4] = page_checksum(page_checksum(0, &compressed), &words_to_bytes(&header));
+    let mut page = words_to_bytes(&header);
+    page.extend(compressed);
+    page.resize(page.len().next_multiple_of(R2004_PAGE_ALIGNMENT), 0);
+    Ok(page)
+}
+
+/// Returns RLs as their little endian bytes
+fn words_to_bytes(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+/// Writes the R2004 file header, the unencrypted fields followed by `header` encrypted at
+/// 0x80, padded to [`R2004_PAGES_START`]
+fn write_r2004_file_header(dwg: &Dwg, header: &R2004Header) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    writer.write_bytes(dwg.version().magic());
+    writer.write_bytes(&[0; 5]);
+    writer.write_raw_char(dwg.maintenance_version as i8);
+    writer.write_raw_char(1);
+    // No preview image
+    writer.write_raw_long(0);
+    writer.write_bytes(&[0; 2]);
+    writer.write_raw_short(dwg.codepage as u16 as i16);
+    writer.write_bytes(&[0; 3]);
+    // Security flags
+    writer.write_raw_long(0);
+    writer.write_raw_long(0);
+    // No summary info or VBA project
+    writer.write_raw_long(0);
+    writer.write_raw_long(0);
+    writer.write_raw_long(0x80);
+    writer.write_bytes(&[0; 0x54]);
+
+    let mut out = writer.into_bytes();
+    out.extend(header.encrypt());
+    out.resize(R2004_PAGES_START, 0);
+    out
+}
+
+#[test]
+fn test_write_r2000() {
+    use crate::{
+        bitcodes::BitSliceReader,
+        crc::crc8,
+        dwg::{read_r2000_header, ParseOptions},
+    };
+
+    let mut dwg = Dwg::empty(DWGVersion::AC1015);
+    dwg.header_vars = vec![1, 2, 3];
+    dwg.classes = vec![4, 5];
+    // Enough objects that the object map needs several sections
+    for handle in 1..2000u64 {
+        dwg.objects
+            .insert(handle * 3, vec![handle as u8; (handle % 7) as usize + 1]);
+    }
+    let buf = dwg.to_bytes().unwrap();
+
+    let mut reader = BitSliceReader::from_slice(&buf);
+    let records = read_r2000_header(&mut reader, ParseOptions::default())
+        .unwrap()
+        .records;
+    assert_eq!(records.len(), R2000_N_RECORDS);
+    let crc_at = 0x19 + 9 * R2000_N_RECORDS;
+    assert_eq!(
+        crc8(0, &buf[..crc_at]) ^ r2000_header_crc_xor(R2000_N_RECORDS).unwrap(),
+        u16::from_le_bytes([buf[crc_at], buf[crc_at + 1]])
+    );
+
+    // Header variables and classes
+    for (record, data) in records.iter().zip([&dwg.header_vars, &dwg.classes]) {
+        let start = record.seeker as usize + 16;
+        let mut reader = BitSliceReader::from_slice(&buf);
+        reader.seek_to_bit(start as u64 * 8).unwrap();
+        assert_eq!(reader.read_raw_long(), Ok(data.len() as i32));
+        assert_eq!(&reader.read_vec(data.len()).unwrap(), data);
+        let end = start + 4 + data.len();
+        assert_eq!(
+            reader.read_raw_short(),
+            Ok(crc8(CRC8_SEED, &buf[start..end]) as i16)
+        );
+        assert_eq!(record.size as usize, data.len() + 38);
+    }
+
+    // Every object can be found through the object map
+    let mut reader = BitSliceReader::from_slice(&buf);
+    reader.seek_to_bit(records[2].seeker as u64 * 8).unwrap();
+    let mut n_sections = 0;
+    let mut found = Vec::new();
+    loop {
+        let start = reader.byte_position() as usize;
+        let size = u16::from_be_bytes([buf[start], buf[start + 1]]) as usize;
+        assert!(size <= OBJECT_MAP_SECTION_SIZE);
+        reader.seek_to_bit((start as u64 + 2) * 8).unwrap();
+        let (mut handle, mut loc) = (0u64, 0i64);
+        while (reader.byte_position() as usize) < start + size {
+            handle += reader.read_modular_char_u64().unwrap();
+            loc += reader.read_handle_offset().unwrap();
+            found.push((handle, loc as usize));
+        }
+        let crc = crc8(CRC8_SEED, &buf[start..start + size]);
+        assert_eq!(buf[start + size..start + size + 2], crc.to_be_bytes());
+        reader.seek_to_bit((start + size + 2) as u64 * 8).unwrap();
+        n_sections += 1;
+        if size == 2 {
+            break;
+        }
+    }
+    assert!(n_sections > 2);
+    assert_eq!(found.len(), dwg.objects.len());
+    for ((handle, loc), (expected_handle, data)) in found.into_iter().zip(&dwg.objects) {
+        assert_eq!(handle, *expected_handle);
+        let mut reader = BitSliceReader::from_slice(&buf);
+        reader.seek_to_bit(loc as u64 * 8).unwrap();
+        assert_eq!(reader.read_modular_short_u64(), Ok(data.len() as u64));
+        assert_eq!(&reader.read_vec(data.len()).unwrap(), data);
+        let end = reader.byte_position() as usize;
+        assert_eq!(
+            reader.read_raw_short(),
+            Ok(crc8(CRC8_SEED, &buf[loc..end]) as i16)
+        );
+    }
+
+    // The second header comes last
+    let start = buf.len() - 16 - 8 - 2;
+    assert_eq!(buf[buf.len() - 16..], sentinels::SECOND_HEADER_END);
+    let size_at = buf[..start]
+        .windows(16)
+        .rposition(|window| window == sentinels::SECOND_HEADER_START)
+        .unwrap()
+        + 16;
+    let size = u32::from_le_bytes(buf[size_at..size_at + 4].try_into().unwrap()) as usize;
+    assert_eq!(size_at + 4 + size, start);
+}
+
+#[test]
+fn test_write_r2004() {
+    use crate::{
+        crc::crc32,
+        dwg::{r2004_header_mask, R2004_FILE_ID, R2004_HEADER_SIZE},
+    };
+
+    let mut dwg = Dwg::empty(DWGVersion::AC1018);
+    dwg.header_vars = vec![1, 2, 3];
+    // Enough objects that the objects section needs several pages
+    for handle in 1..20000u64 {
+        dwg.objects.insert(handle, vec![handle as u8; 4]);
+    }
+    let buf = dwg.to_bytes().unwrap();
+    let rl = |at: usize| u32::from_le_bytes(buf[at..at + 4].try_into().unwrap());
+    assert_eq!(&buf[..6], b"AC1018");
+
+    let header: Vec<u8> = buf[0x80..0x80 + R2004_HEADER_SIZE]
+        .iter()
+        .zip(r2004_header_mask())
+        .map(|(byte, mask)| byte ^ mask)
+        .collect();
+    assert_eq!(&header[..12], R2004_FILE_ID);
+    let crc = u32::from_le_bytes(header[0x68..].try_into().unwrap());
+    assert_eq!(crc32(0, &[&header[..0x68], &[0; 4]].concat()), crc);
+    let header_rl = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap());
+    let n_pages = header_rl(0x40);
+    let page_map_address = header_rl(0x54) as usize + R2004_PAGES_START;
+    assert_eq!(header_rl(0x2C) as usize + R2004_PAGES_START, buf.len());
+
+    // Walk the pages, checking their checksums and which sections they hold
+    let mut address = R2004_PAGES_START;
+    let mut section_sizes = [0; 7];
+    let mut system_pages = Vec::new();
+    for _ in 0..n_pages {
+        let mask = R2004_DATA_PAGE_MASK ^ address as u32;
+        let (header_size, page_type) = if rl(address) ^ mask == R2004_DATA_PAGE_TYPE {
+            let mut page_header: Vec<u32> = (0..8).map(|i| rl(address + i * 4) ^ mask).collect();
+            let data = &buf[address + 0x20..][..page_header[2] as usize];
+            assert_eq!(page_header[7], page_checksum(0, data));
+            let header_checksum = std::mem::take(&mut page_header[6]);
+            assert_eq!(
+                header_checksum,
+                page_checksum(page_header[7], &words_to_bytes(&page_header))
+            );
+            section_sizes[page_header[1] as usize] += page_header[3];
+            (0x20 + page_header[2] as usize, R2004_DATA_PAGE_TYPE)
+        } else {
+            let mut page_header: Vec<u32> = (0..5).map(|i| rl(address + i * 4)).collect();
+            let data = &buf[address + 0x14..][..page_header[2] as usize];
+            let checksum = std::mem::take(&mut page_header[4]);
+            assert_eq!(
+                checksum,
+                page_checksum(page_checksum(0, data), &words_to_bytes(&page_header))
+            );
+            system_pages.push((address, page_header[0]));
+            (0x14 + page_header[2] as usize, page_header[0])
+        };
+        assert_ne!(page_type, 0);
+        address += header_size.next_multiple_of(R2004_PAGE_ALIGNMENT);
+    }
+    assert_eq!(address, buf.len());
+    assert_eq!(
+        system_pages,
+        [
+            (system_pages[0].0, R2004_SECTION_MAP_TYPE),
+            (page_map_address, R2004_PAGE_MAP_TYPE)
+        ]
+    );
+    assert_eq!(section_sizes[1], dwg.header_vars.len() as u32 + 38);
+    assert_eq!(section_sizes[3], 4 + 19999 * 8);
+}
+
+#[test]
+fn test_write_unsupported_version() {
+    let dwg = Dwg::empty(DWGVersion::AC1021);
+    assert_eq!(
+        dwg.to_bytes(),
+        Err(DwgError::UnsupportedVersion {
+            found: String::from("AC1021")
+        })
+    );
+}
+
+#[test]
+fn test_later_r2004_containers() {
+    for (version, maintenance_version) in [
+        (DWGVersion::AC1024, 3),
+        (DWGVersion::AC1024, 4),
+        (DWGVersion::AC1027, 0),
+        (DWGVersion::AC1032, 0),
+    ] {
+        let mut dwg = Dwg::empty(DWGVersion::AC1018);
+        dwg.set_version(version);
+        dwg.maintenance_version = maintenance_version;
+        dwg.header_vars = vec![1, 2, 3];
+        dwg.classes = vec![4, 5];
+        // Objects start with the MC size of their handle stream, which may be several bytes
+        dwg.objects.insert(1, vec![0x08, 1, 2, 3]);
+        dwg.objects.insert(2, vec![0x81, 0x01, 4, 5]);
+        dwg.sections
+            .insert("AcDb:AcDsPrototype_1b".into(), vec![6; 10]);
+        let buf = write_r2004(&dwg).unwrap();
+        assert_eq!(&buf[..6], version.magic());
+        // The size of the header variables has high bits from R2010 maintenance version 4
+        let size_high = has_size_high(version, maintenance_version);
+        assert_eq!(
+            size_high,
+            maintenance_version == 4 || version == DWGVersion::AC1032
+        );
+
+        let read = Dwg::from_bytes(&buf).unwrap();
+        assert_eq!(read.version(), version);
+        assert_eq!(read.header_vars, dwg.header_vars);
+        assert_eq!(read.classes, dwg.classes);
+        assert_eq!(read.objects, dwg.objects);
+        assert_eq!(read.section("AcDb:AcDsPrototype_1b"), Some(&[6u8; 10][..]));
+        let header_size = read.section("AcDb:Header").unwrap().len();
+        assert_eq!(header_size, 16 + 4 + 3 + 2 + 16 + 4 * size_high as usize);
+    }
+}
+
+#[test]
+fn test_r13_r14_containers() {
+    use crate::{
+        objects::{decode_object, encode_object, object_types, Dictionary},
+        types::CodePage,
+    };
+
+    for version in [DWGVersion::AC1012, DWGVersion::AC1014] {
+        let mut dwg = Dwg::empty(DWGVersion::AC1015);
+        dwg.set_version(version);
+        dwg.header_vars = vec![1, 2, 3];
+        let dictionary = Dictionary::new(0xC, 0, &[("ACAD_GROUP", 0xD)]);
+        let data = encode_object(
+            object_types::DICTIONARY,
+            &dictionary,
+            version,
+            CodePage::ANSI1252,
+        )
+        .unwrap();
+        dwg.objects.insert(0xC, data.clone());
+        let buf = write_r2000(&dwg).unwrap();
+        assert_eq!(&buf[..6], version.magic());
+
+        let read = Dwg::from_bytes(&buf).unwrap();
+        assert_eq!(read.version(), version);
+        assert_eq!(read.header_vars, dwg.header_vars);
+        assert_eq!(read.objects[&0xC], data);
+        let decoded = decode_object(&data, object_types::DICTIONARY, version, CodePage::ANSI1252);
+        assert_eq!(decoded, Ok(dictionary));
+        assert_eq!(Dwg::check_second_header(&buf).unwrap().1, vec![]);
+    }
+}
@@ -0,0 +1,5027 @@
+//! Typed entities
+//!
+//! Each entity starts with its [`CommonEntityData`], followed by the data of its type. Objects
+//! of the types typed here are decoded by [`Entity::decode`], and any other entity is kept as a
+//! [`PartialObject`] so its common data can still be read and edited
+#[cfg(test)]
+use crate::bitcodes::Bits;
+use crate::{
+    decode::{codes::*, DwgDecode, ObjectRead},
+    dwg::Dwg,
+    dwg_object,
+    error::{DwgError, Result},
+    objects::{
+        decode_object, object_types, open_object, CommonEntityData, DwgEncode, PartialObject,
+    },
+    streams::DualStreamWriter,
+    types::{CmColor, CodePage, Color, HandleRef, Point2, Point3, RefType},
+    version::DWGVersion,
+};
+
+/// An entity decoded by its type
+#[derive(Clone, Debug, PartialEq)]
+pub enum Entity {
+    Line(Line),
+    Point(Point),
+    Circle(Circle),
+    Arc(Arc),
+    Text(Text),
+    MText(MText),
+    Attrib(Attrib),
+    AttDef(AttDef),
+    Block(Block),
+    EndBlk(EndBlk),
+    Insert(Insert),
+    MInsert(MInsert),
+    Polyline2d(Polyline2d),
+    Vertex2d(Vertex2d),
+    Polyline3d(Polyline3d),
+    Vertex3d(Vertex3d),
+    PolylinePface(PolylinePface),
+    /// A VERTEX_PFACE, laid out as a VERTEX_3D
+    VertexPface(Vertex3d),
+    FaceRecord(FaceRecord),
+    PolylineMesh(PolylineMesh),
+    /// A VERTEX_MESH, laid out as a VERTEX_3D
+    VertexMesh(Vertex3d),
+    Face3d(Face3d),
+    Solid(Solid),
+    /// A TRACE, laid out as a SOLID
+    Trace(Solid),
+    Shape(Shape),
+    Ray(Ray),
+    /// An XLINE, laid out as a RAY
+    XLine(Ray),
+    Leader(Leader),
+    LwPolyline(LwPolyline),
+    Spline(Spline),
+    /// An entity of a type not typed here, with only its common data decoded
+    Other(PartialObject<CommonEntityData>),
+}
+
+impl Entity {
+    /// Decodes the entity object `data`, as [`crate::dwg::Dwg::objects`] holds it
+    ///
+    /// `data` must be an entity, which can be told from its type with
+    /// [`crate::objects::is_entity_type`] or the classes of the drawing
+    pub fn decode(data: &[u8], version: DWGVersion, codepage: CodePage) -> Result<Self> {
+        let (object_type, mut reader) = open_object(data, version, codepage)?;
+        match object_type {
+            object_types::LINE => Ok(Self::Line(Line::decode(&mut reader)?)),
+            object_types::POINT => Ok(Self::Point(Point::decode(&mut reader)?)),
+            object_types::CIRCLE => Ok(Self::Circle(Circle::decode(&mut reader)?)),
+            object_types::ARC => Ok(Self::Arc(Arc::decode(&mut reader)?)),
+            object_types::TEXT => Ok(Self::Text(Text::decode(&mut reader)?)),
+            object_types::MTEXT => Ok(Self::MText(MText::decode(&mut reader)?)),
+            object_types::ATTRIB => Ok(Self::Attrib(Attrib::decode(&mut reader)?)),
+            object_types::ATTDEF => Ok(Self::AttDef(AttDef::decode(&mut reader)?)),
+            object_types::BLOCK => Ok(Self::Block(Block::decode(&mut reader)?)),
+            object_types::ENDBLK => Ok(Self::EndBlk(EndBlk::decode(&mut reader)?)),
+            object_types::INSERT => Ok(Self::Insert(Insert::decode(&mut reader)?)),
+            object_types::MINSERT => Ok(Self::MInsert(MInsert::decode(&mut reader)?)),
+            object_types::POLYLINE_2D => Ok(Self::Polyline2d(Polyline2d::decode(&mut reader)?)),
+            object_types::VERTEX_2D => Ok(Self::Vertex2d(Vertex2d::decode(&mut reader)?)),
+            object_types::POLYLINE_3D => Ok(Self::Polyline3d(Polyline3d::decode(&mut reader)?)),
+            object_types::VERTEX_3D => Ok(Self::Vertex3d(Vertex3d::decode(&mut reader)?)),
+            object_types::POLYLINE_PFACE => {
+                Ok(Self::PolylinePface(PolylinePface::decode(&mut reader)?))
+            }
+            object_types::VERTEX_PFACE => Ok(Self::VertexPface(Vertex3d::decode(&mut reader)?)),
+            object_types::VERTEX_PFACE_FACE => {
+                Ok(Self::FaceRecord(FaceRecord::decode(&mut reader)?))
+            }
+            object_types::POLYLINE_MESH => {
+                Ok(Self::PolylineMesh(PolylineMesh::decode(&mut reader)?))
+            }
+            object_types::VERTEX_MESH => Ok(Self::VertexMesh(Vertex3d::decode(&mut reader)?)),
+            object_types::FACE_3D => Ok(Self::Face3d(Face3d::decode(&mut reader)?)),
+            object_types::SOLID => Ok(Self::Solid(Solid::decode(&mut reader)?)),
+            object_types::TRACE => Ok(Self::Trace(Solid::decode(&mut reader)?)),
+            object_types::SHAPE => Ok(Self::Shape(Shape::decode(&mut reader)?)),
+            object_types::RAY => Ok(Self::Ray(Ray::decode(&mut reader)?)),
+            object_types::XLINE => Ok(Self::XLine(Ray::decode(&mut reader)?)),
+            object_types::LEADER => Ok(Self::Leader(Leader::decode(&mut reader)?)),
+            object_types::LWPOLYLINE => Ok(Self::LwPolyline(LwPolyline::decode(&mut reader)?)),
+            object_types::SPLINE => Ok(Self::Spline(Spline::decode(&mut reader)?)),
+            _ => Ok(Self::Other(PartialObject::decode(data, version, codepage)?)),
+        }
+    }
+
+    pub fn object_type(&self) -> i16 {
+        match self {
+            Self::Line(_) => object_types::LINE,
+            Self::Point(_) => object_types::POINT,
+            Self::Circle(_) => object_types::CIRCLE,
+            Self::Arc(_) => object_types::ARC,
+            Self::Text(_) => object_types::TEXT,
+            Self::MText(_) => object_types::MTEXT,
+            Self::Attrib(_) => object_types::ATTRIB,
+            Self::AttDef(_) => object_types::ATTDEF,
+            Self::Block(_) => object_types::BLOCK,
+            Self::EndBlk(_) => object_types::ENDBLK,
+            Self::Insert(_) => object_types::INSERT,
+            Self::MInsert(_) => object_types::MINSERT,
+            Self::Polyline2d(_) => object_types::POLYLINE_2D,
+            Self::Vertex2d(_) => object_types::VERTEX_2D,
+            Self::Polyline3d(_) => object_types::POLYLINE_3D,
+            Self::Vertex3d(_) => object_types::VERTEX_3D,
+            Self::PolylinePface(_) => object_types::POLYLINE_PFACE,
+            Self::VertexPface(_) => object_types::VERTEX_PFACE,
+            Self::FaceRecord(_) => object_types::VERTEX_PFACE_FACE,
+            Self::PolylineMesh(_) => object_types::POLYLINE_MESH,
+            Self::VertexMesh(_) => object_types::VERTEX_MESH,
+            Self::Face3d(_) => object_types::FACE_3D,
+            Self::Solid(_) => object_types::SOLID,
+            Self::Trace(_) => object_types::TRACE,
+            Self::Shape(_) => object_types::SHAPE,
+            Self::Ray(_) => object_types::RAY,
+            Self::XLine(_) => object_types::XLINE,
+            Self::Leader(_) => object_types::LEADER,
+            Self::LwPolyline(_) => object_types::LWPOLYLINE,
+            Self::Spline(_) => object_types::SPLINE,
+            Self::Other(entity) => entity.object_type,
+        }
+    }
+
+    pub fn header(&self) -> &CommonEntityData {
+        match self {
+            Self::Line(line) => &line.header,
+            Self::Point(point) => &point.header,
+            Self::Circle(circle) => &circle.header,
+            Self::Arc(arc) => &arc.header,
+            Self::Text(text) => &text.header,
+            Self::MText(mtext) => &mtext.header,
+            Self::Attrib(attrib) => &attrib.text.header,
+            Self::AttDef(attdef) => &attdef.attrib.text.header,
+            Self::Block(block) => &block.header,
+            Self::EndBlk(endblk) => &endblk.header,
+            Self::Insert(insert) => &insert.header,
+            Self::MInsert(minsert) => &minsert.insert.header,
+            Self::Polyline2d(polyline) => &polyline.header,
+            Self::Vertex2d(vertex) => &vertex.header,
+            Self::Polyline3d(polyline) => &polyline.header,
+            Self::Vertex3d(vertex) | Self::VertexPface(vertex) | Self::VertexMesh(vertex) => {
+                &vertex.header
+            }
+            Self::PolylineMesh(polyline) => &polyline.header,
+            Self::Face3d(face) => &face.header,
+            Self::Solid(solid) | Self::Trace(solid) => &solid.header,
+            Self::Shape(shape) => &shape.header,
+            Self::Ray(ray) | Self::XLine(ray) => &ray.header,
+            Self::Leader(leader) => &leader.header,
+            Self::PolylinePface(polyline) => &polyline.header,
+            Self::FaceRecord(face) => &face.header,
+            Self::LwPolyline(polyline) => &polyline.header,
+            Self::Spline(spline) => &spline.header,
+            Self::Other(entity) => &entity.header,
+        }
+    }
+
+    /// Returns the common data of the entity to be changed
+    pub fn header_mut(&mut self) -> &mut CommonEntityData {
+        match self {
+            Self::Line(line) => &mut line.header,
+            Self::Point(point) => &mut point.header,
+            Self::Circle(circle) => &mut circle.header,
+            Self::Arc(arc) => &mut arc.header,
+            Self::Text(text) => &mut text.header,
+            Self::MText(mtext) => &mut mtext.header,
+            Self::Attrib(attrib) => &mut attrib.text.header,
+            Self::AttDef(attdef) => &mut attdef.attrib.text.header,
+            Self::Block(block) => &mut block.header,
+            Self::EndBlk(endblk) => &mut endblk.header,
+            Self::Insert(insert) => &mut insert.header,
+            Self::MInsert(minsert) => &mut minsert.insert.header,
+            Self::Polyline2d(polyline) => &mut polyline.header,
+            Self::Vertex2d(vertex) => &mut vertex.header,
+            Self::Polyline3d(polyline) => &mut polyline.header,
+            Self::Vertex3d(vertex) | Self::VertexPface(vertex) | Self::VertexMesh(vertex) => {
+                &mut vertex.header
+            }
+            Self::PolylineMesh(polyline) => &mut polyline.header,
+            Self::Face3d(face) => &mut face.header,
+            Self::Solid(solid) | Self::Trace(solid) => &mut solid.header,
+            Self::Shape(shape) => &mut shape.header,
+            Self::Ray(ray) | Self::XLine(ray) => &mut ray.header,
+            Self::Leader(leader) => &mut leader.header,
+            Self::PolylinePface(polyline) => &mut polyline.header,
+            Self::FaceRecord(face) => &mut face.header,
+            Self::LwPolyline(polyline) => &mut polyline.header,
+            Self::Spline(spline) => &mut spline.header,
+            Self::Other(entity) => &mut entity.header,
+        }
+    }
+}
+
+impl DwgEncode for Entity {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        match self {
+            Self::Line(line) => line.encode(writer),
+            Self::Point(point) => point.encode(writer),
+            Self::Circle(circle) => circle.encode(writer),
+            Self::Arc(arc) => arc.encode(writer),
+            Self::Text(text) => text.encode(writer),
+            Self::MText(mtext) => mtext.encode(writer),
+            Self::Attrib(attrib) => attrib.encode(writer),
+            Self::AttDef(attdef) => attdef.encode(writer),
+            Self::Block(block) => block.encode(writer),
+            Self::EndBlk(endblk) => endblk.encode(writer),
+            Self::Insert(insert) => insert.encode(writer),
+            Self::MInsert(minsert) => minsert.encode(writer),
+            Self::Polyline2d(polyline) => polyline.encode(writer),
+            Self::Vertex2d(vertex) => vertex.encode(writer),
+            Self::Polyline3d(polyline) => polyline.encode(writer),
+            Self::Vertex3d(vertex) | Self::VertexPface(vertex) | Self::VertexMesh(vertex) => {
+                vertex.encode(writer)
+            }
+            Self::PolylinePface(polyline) => polyline.encode(writer),
+            Self::FaceRecord(face) => face.encode(writer),
+            Self::PolylineMesh(polyline) => polyline.encode(writer),
+            Self::Face3d(face) => face.encode(writer),
+            Self::Solid(solid) | Self::Trace(solid) => solid.encode(writer),
+            Self::Shape(shape) => shape.encode(writer),
+            Self::Ray(ray) | Self::XLine(ray) => ray.encode(writer),
+            Self::Leader(leader) => leader.encode(writer),
+            Self::LwPolyline(polyline) => polyline.encode(writer),
+            Self::Spline(spline) => spline.encode(writer),
+            Self::Other(entity) => entity.encode(writer),
+        }
+    }
+}
+
+/// A line entity
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    pub header: CommonEntityData,
+    pub start: Point3,
+    pub end: Point3,
+    pub thickness: f64,
+    pub extrusion: Point3,
+}
+
+impl DwgDecode for Line {
+    /// AC1015 (R2000) and later store each coordinate of the end as a DD defaulting to that of
+    /// the start, and leave out both z coordinates if they are 0
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let header = CommonEntityData::decode(reader)?;
+        let data = reader.data();
+        let (start, end) = if data.get_version() < DWGVersion::AC1015 {
+            (data.read_3bd()?, data.read_3bd()?)
+        } else {
+            let z_is_zero = data.read_bit()? == 1;
+            let mut start = Point3::default();
+            let mut end = Point3::default();
+            start.x = data.read_raw_double()?;
+            end.x = data.read_dd(start.x)?;
+            start.y = data.read_raw_double()?;
+            end.y = data.read_dd(start.y)?;
+            if !z_is_zero {
+                start.z = data.read_raw_double()?;
+                end.z = data.read_dd(start.z)?;
+            }
+            (start, end)
+        };
+        let thickness = data.read_bit_thickness()?;
+        let extrusion = data.read_bit_extrusion()?;
+        Ok(Self {
+            header,
+            start,
+            end,
+            thickness,
+            extrusion,
+        })
+    }
+}
+
+impl DwgEncode for Line {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        if writer.get_version() < DWGVersion::AC1015 {
+            writer.write_3bd(self.start);
+            writer.write_3bd(self.end);
+            writer.write_bit_thickness(self.thickness);
+            writer.write_bit_extrusion(self.extrusion);
+            return Ok(());
+        }
+        let z_is_zero = self.start.z == 0.0 && self.end.z == 0.0;
+        writer.write_bit(z_is_zero as u8);
+        writer.write_raw_double(self.start.x);
+        writer.write_dd(self.end.x, self.start.x);
+        writer.write_raw_double(self.start.y);
+        writer.write_dd(self.end.y, self.start.y);
+        if !z_is_zero {
+            writer.write_raw_double(self.start.z);
+            writer.write_dd(self.end.z, self.start.z);
+        }
+        writer.write_bit_thickness(self.thickness);
+        writer.write_bit_extrusion(self.extrusion);
+        Ok(())
+    }
+}
+
+dwg_object! {
+    /// A point entity
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Point {
+        pub header: CommonEntityData,
+        pub position: BD3,
+        pub thickness: BT,
+        pub extrusion: BE,
+        /// Angle of the x axis of the UCS the point was drawn in, used to orient point
+        /// symbols
+        pub x_axis_angle: BD,
+    }
+}
+
+impl DwgEncode for Point {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        writer.write_3bd(self.position);
+        writer.write_bit_thickness(self.thickness);
+        writer.write_bit_extrusion(self.extrusion);
+        writer.write_bitdouble(self.x_axis_angle);
+        Ok(())
+    }
+}
+
+dwg_object! {
+    /// A circle entity, in the plane normal to its extrusion
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Circle {
+        pub header: CommonEntityData,
+        pub center: BD3,
+        pub radius: BD,
+        pub thickness: BT,
+        pub extrusion: BE,
+    }
+}
+
+impl DwgEncode for Circle {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        writer.write_3bd(self.center);
+        writer.write_bitdouble(self.radius);
+        writer.write_bit_thickness(self.thickness);
+        writer.write_bit_extrusion(self.extrusion);
+        Ok(())
+    }
+}
+
+/// An arc entity, drawn counterclockwise from its start angle to its end angle about the
+/// extrusion
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arc {
+    pub header: CommonEntityData,
+    pub center: Point3,
+    pub radius: f64,
+    pub thickness: f64,
+    pub extrusion: Point3,
+    /// In radians, from 0 up to a full turn
+    pub start_angle: f64,
+    /// In radians, from 0 up to a full turn
+    pub end_angle: f64,
+}
+
+impl DwgDecode for Arc {
+    /// The angles are normalized, as files may store them out of range, such as negative
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let header = CommonEntityData::decode(reader)?;
+        let data = reader.data();
+        let center = data.read_3bd()?;
+        let radius = data.read_bitdouble()?;
+        let thickness = data.read_bit_thickness()?;
+        let extrusion = data.read_bit_extrusion()?;
+        let start_angle = normalize_angle(data.read_bitdouble()?);
+        let end_angle = normalize_angle(data.read_bitdouble()?);
+        Ok(Self {
+            header,
+            center,
+            radius,
+            thickness,
+            extrusion,
+            start_angle,
+            end_angle,
+        })
+    }
+}
+
+impl DwgEncode for Arc {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        writer.write_3bd(self.center);
+        writer.write_bitdouble(self.radius);
+        writer.write_bit_thickness(self.thickness);
+        writer.write_bit_extrusion(self.extrusion);
+        writer.write_bitdouble(self.start_angle);
+        writer.write_bitdouble(self.end_angle);
+        Ok(())
+    }
+}
+
+/// Returns the angle `radians` in the range from 0 up to a full turn
+fn normalize_angle(radians: f64) -> f64 {
+    let angle = radians.rem_euclid(std::f64::consts::TAU);
+    // A tiny negative angle rounds to a full turn
+    if angle == std::f64::consts::TAU {
+        0.0
+    } else {
+        angle
+    }
+}
+
+/// A single line text entity
+#[derive(Clone, Debug, PartialEq)]
+pub struct Text {
+    pub header: CommonEntityData,
+    /// Insertion point, where z is the elevation
+    pub insertion: Point3,
+    /// Alignment point, used unless both alignments are 0
+    pub alignment: Point2,
+    pub extrusion: Point3,
+    pub thickness: f64,
+    pub oblique_angle: f64,
+    pub rotation: f64,
+    pub height: f64,
+    pub width_factor: f64,
+    pub value: String,
+    /// 0x2 if the text is mirrored in X and 0x4 if it is mirrored in Y
+    pub generation: i16,
+    pub horizontal_alignment: i16,
+    pub vertical_alignment: i16,
+    pub style: HandleRef,
+}
+
+impl DwgDecode for Text {
+    /// AC1015 (R2000) and later start with data flags, each set if a value is its default and
+    /// left out: the elevation, the alignment point, the oblique angle, the rotation, the width
+    /// factor, the generation and the horizontal and vertical alignments
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let header = CommonEntityData::decode(reader)?;
+        let data = reader.data();
+        if data.get_version() < DWGVersion::AC1015 {
+            let elevation = data.read_bitdouble()?;
+            let Point2 { x, y } = data.read_2rd()?;
+            let alignment = data.read_2rd()?;
+            let extrusion = data.read_bit_extrusion()?;
+            let thickness = data.read_bit_thickness()?;
+            let oblique_angle = data.read_bitdouble()?;
+            let rotation = data.read_bitdouble()?;
+            let height = data.read_bitdouble()?;
+            let width_factor = data.read_bitdouble()?;
+            let value = reader.read_text()?;
+            let data = reader.data();
+            let generation = data.read_bitshort()?;
+            let horizontal_alignment = data.read_bitshort()?;
+            let vertical_alignment = data.read_bitshort()?;
+            return Ok(Self {
+                header,
+                insertion: Point3 { x, y, z: elevation },
+                alignment,
+                extrusion,
+                thickness,
+                oblique_angle,
+                rotation,
+                height,
+                width_factor,
+                value,
+                generation,
+                horizontal_alignment,
+                vertical_alignment,
+                style: reader.read_handle()?,
+            });
+        }
+
+        let flags = data.read_raw_char()? as u8;
+        let stored = |bit: u8| flags & 1 << bit == 0;
+        let elevation = match stored(0) {
+            true => data.read_raw_double()?,
+            false => 0.0,
+        };
+        let Point2 { x, y } = data.read_2rd()?;
+        let alignment = match stored(1) {
+            true => Point2 {
+                x: data.read_dd(x)?,
+                y: data.read_dd(y)?,
+            },
+            false => Point2::default(),
+        };
+        let extrusion = data.read_bit_extrusion()?;
+        let thickness = data.read_bit_thickness()?;
+        let mut read_rd = |bit, default| match stored(bit) {
+            true => data.read_raw_double(),
+            false => Ok(default),
+        };
+        let oblique_angle = read_rd(2, 0.0)?;
+        let rotation = read_rd(3, 0.0)?;
+        let height = data.read_raw_double()?;
+        let width_factor = match stored(4) {
+            true => data.read_raw_double()?,
+            false => 1.0,
+        };
+        let value = reader.read_text()?;
+        let mut read_bs = |bit| match stored(bit) {
+            true => reader.data().read_bitshort(),
+            false => Ok(0),
+        };
+        let generation = read_bs(5)?;
+        let horizontal_alignment = read_bs(6)?;
+        let vertical_alignment = read_bs(7)?;
+        Ok(Self {
+            header,
+            insertion: Point3 { x, y, z: elevation },
+            alignment,
+            extrusion,
+            thickness,
+            oblique_angle,
+            rotation,
+            height,
+            width_factor,
+            value,
+            generation,
+            horizontal_alignment,
+            vertical_alignment,
+            style: reader.read_handle()?,
+        })
+    }
+}
+
+impl DwgEncode for Text {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        if writer.get_version() < DWGVersion::AC1015 {
+            // Every value is stored, as BDs rather than RDs
+            writer.write_bitdouble(self.insertion.z);
+            writer.write_2rd(Point2 {
+                x: self.insertion.x,
+                y: self.insertion.y,
+            });
+            writer.write_2rd(self.alignment);
+            writer.write_bit_extrusion(self.extrusion);
+            writer.write_bit_thickness(self.thickness);
+            for value in [
+                self.oblique_angle,
+                self.rotation,
+                self.height,
+                self.width_factor,
+            ] {
+                writer.write_bitdouble(value);
+            }
+            writer.write_text(&self.value)?;
+            writer.write_bitshort(self.generation);
+            writer.write_bitshort(self.horizontal_alignment);
+            writer.write_bitshort(self.vertical_alignment);
+            writer.write_handle(self.style);
+            return Ok(());
+        }
+        let has_alignment = self.horizontal_alignment != 0 || self.vertical_alignment != 0;
+        // Each bit of the data flags is set if the value is its default and not stored
+        let defaults = [
+            self.insertion.z == 0.0,
+            !has_alignment,
+            self.oblique_angle == 0.0,
+            self.rotation == 0.0,
+            self.width_factor == 1.0,
+            self.generation == 0,
+            self.horizontal_alignment == 0,
+            self.vertical_alignment == 0,
+        ];
+        let flags = (0..8).fold(0u8, |flags, bit| flags | (defaults[bit] as u8) << bit);
+        writer.write_raw_char(flags as i8);
+        if !defaults[0] {
+            writer.write_raw_double(self.insertion.z);
+        }
+        writer.write_2rd(Point2 {
+            x: self.insertion.x,
+            y: self.insertion.y,
+        });
+        if !defaults[1] {
+            writer.write_dd(self.alignment.x, self.insertion.x);
+            writer.write_dd(self.alignment.y, self.insertion.y);
+        }
+        writer.write_bit_extrusion(self.extrusion);
+        writer.write_bit_thickness(self.thickness);
+        if !defaults[2] {
+            writer.write_raw_double(self.oblique_angle);
+        }
+        if !defaults[3] {
+            writer.write_raw_double(self.rotation);
+        }
+        writer.write_raw_double(self.height);
+        if !defaults[4] {
+            writer.write_raw_double(self.width_factor);
+        }
+        writer.write_text(&self.value)?;
+        if !defaults[5] {
+            writer.write_bitshort(self.generation);
+        }
+        if !defaults[6] {
+            writer.write_bitshort(self.horizontal_alignment);
+        }
+        if !defaults[7] {
+            writer.write_bitshort(self.vertical_alignment);
+        }
+        writer.write_handle(self.style);
+        Ok(())
+    }
+}
+
+/// A multiline text entity
+///
+/// DXF splits long text into chunks of 250 characters, but DWG stores the whole of `value` as
+/// one string. The column data of AC1032 (R2018) is not decoded
+#[derive(Clone, Debug, PartialEq)]
+pub struct MText {
+    pub header: CommonEntityData,
+    pub insertion: Point3,
+    pub extrusion: Point3,
+    /// Direction of the x axis of the text
+    pub x_axis_direction: Point3,
+    /// Width the text wraps at, or 0 if it does not wrap
+    pub rect_width: f64,
+    /// Height of the text box, stored from AC1021 (R2007)
+    pub rect_height: f64,
+    pub text_height: f64,
+    /// Where the text is attached to the insertion point, from 1 for top left to 9 for
+    /// bottom right
+    pub attachment: i16,
+    /// 1 for left to right, 3 for top to bottom or 5 for the direction of the text style
+    pub drawing_direction: i16,
+    /// Height of the text as drawn
+    pub extents_height: f64,
+    /// Width of the text as drawn
+    pub extents_width: f64,
+    /// The text, with its formatting codes
+    pub value: String,
+    /// 1 for at least `linespacing_factor`, 2 for exactly. Stored from AC1015 (R2000)
+    pub linespacing_style: i16,
+    pub linespacing_factor: f64,
+    /// An undocumented bit stored from AC1015 (R2000)
+    pub unknown: bool,
+    /// 1 to fill the background with `background_color`, 2 to fill it with the color of
+    /// the drawing window, and 0x10 from AC1032 (R2018) to draw a frame. Stored from AC1018
+    /// (R2004)
+    pub background_flags: u32,
+    /// Size of the background relative to the text height, stored if `background_flags`
+    /// has 1 or 0x10 set
+    pub background_scale: f64,
+    pub background_color: CmColor,
+    pub background_transparency: u32,
+    pub style: HandleRef,
+}
+
+impl MText {
+    /// Returns true if the background fields are stored
+    fn has_background(&self) -> bool {
+        self.background_flags & 0x11 != 0
+    }
+}
+
+impl DwgDecode for MText {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let header = CommonEntityData::decode(reader)?;
+        let data = reader.data();
+        let version = data.get_version();
+        let insertion = data.read_3bd()?;
+        let extrusion = data.read_3bd()?;
+        let x_axis_direction = data.read_3bd()?;
+        let rect_width = data.read_bitdouble()?;
+        let rect_height = match version >= DWGVersion::AC1021 {
+            true => data.read_bitdouble()?,
+            false => 0.0,
+        };
+        let text_height = data.read_bitdouble()?;
+        let attachment = data.read_bitshort()?;
+        let drawing_direction = data.read_bitshort()?;
+        let extents_height = data.read_bitdouble()?;
+        let extents_width = data.read_bitdouble()?;
+        let value = reader.read_text()?;
+        let mut mtext = Self {
+            header,
+            insertion,
+            extrusion,
+            x_axis_direction,
+            rect_width,
+            rect_height,
+            text_height,
+            attachment,
+            drawing_direction,
+            extents_height,
+            extents_width,
+            value,
+            linespacing_style: 1,
+            linespacing_factor: 1.0,
+            unknown: false,
+            background_flags: 0,
+            background_scale: 1.5,
+            background_color: CmColor::default(),
+            background_transparency: 0,
+            style: HandleRef::new(RefType::HardPointer, 0),
+        };
+        let data = reader.data();
+        if version >= DWGVersion::AC1015 {
+            mtext.linespacing_style = data.read_bitshort()?;
+            mtext.linespacing_factor = data.read_bitdouble()?;
+            mtext.unknown = data.read_bit()? == 1;
+        }
+        if version >= DWGVersion::AC1018 {
+            mtext.background_flags = data.read_bitlong()? as u32;
+            if mtext.has_background() {
+                mtext.background_scale = data.read_bitdouble()?;
+                mtext.background_color = data.read_cm_color()?;
+                mtext.background_transparency = data.read_bitlong()? as u32;
+            }
+        }
+        mtext.style = reader.read_handle()?;
+        Ok(mtext)
+    }
+}
+
+impl DwgEncode for MText {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        let version = writer.get_version();
+        writer.write_3bd(self.insertion);
+        writer.write_3bd(self.extrusion);
+        writer.write_3bd(self.x_axis_direction);
+        writer.write_bitdouble(self.rect_width);
+        if version >= DWGVersion::AC1021 {
+            writer.write_bitdouble(self.rect_height);
+        }
+        writer.write_bitdouble(self.text_height);
+        writer.write_bitshort(self.attachment);
+        writer.write_bitshort(self.drawing_direction);
+        writer.write_bitdouble(self.extents_height);
+        writer.write_bitdouble(self.extents_width);
+        writer.write_text(&self.value)?;
+        if version >= DWGVersion::AC1015 {
+            writer.write_bitshort(self.linespacing_style);
+            writer.write_bitdouble(self.linespacing_factor);
+            writer.write_bit(self.unknown as u8);
+        }
+        if version >= DWGVersion::AC1018 {
+            writer.write_bitlong(self.background_flags as i32);
+            if self.has_background() {
+                let codepage = writer.codepage();
+                writer.write_bitdouble(self.background_scale);
+                writer
+                    .data()
+                    .write_cm_color(&self.background_color, codepage)?;
+                writer.write_bitlong(self.background_transparency as i32);
+            }
+        }
+        writer.write_handle(self.style);
+        Ok(())
+    }
+}
+
+/// An attribute entity, holding a value for an attribute definition of the block an INSERT
+/// inserts. The attributes of an insert follow it, ended by a SEQEND
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attrib {
+    /// The geometry, style and value of the attribute, stored as by a TEXT
+    pub text: Text,
+    /// Stored from AC1024 (R2010)
+    pub class_version: u8,
+    /// The MTEXT holding the value of a multiline attribute, which AC1032 (R2018) adds
+    pub multiline: Option<MultilineAttribute>,
+    /// The name the value is for, matching the tag of the attribute definition
+    pub tag: String,
+    pub field_length: i16,
+    /// 1 if the attribute is invisible, 2 if it is constant, 4 if it is verified on input and
+    /// 8 if it is preset
+    pub flags: u8,
+    /// Whether the attribute keeps its position relative to the block when the block is
+    /// edited. Stored from AC1021 (R2007)
+    pub lock_position: bool,
+}
+
+/// The MTEXT of a multiline attribute or attribute definition
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultilineAttribute {
+    /// 2 for an attribute or 4 for an attribute definition
+    pub attribute_type: u8,
+    pub mtext: HandleRef,
+    pub annotative_data_size: i16,
+    /// Undocumented data stored if `annotative_data_size` is more than 1: a byte, the handle
+    /// of an application and a short
+    pub annotative_data: Option<(u8, HandleRef, i16)>,
+}
+
+impl DwgDecode for Attrib {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let text = Text::decode(reader)?;
+        let data = reader.data();
+        let version = data.get_version();
+        let class_version = match version >= DWGVersion::AC1024 {
+            true => data.read_raw_char()? as u8,
+            false => 0,
+        };
+        // 1 for a single line attribute
+        let attribute_type = match version >= DWGVersion::AC1032 {
+            true => data.read_raw_char()? as u8,
+            false => 1,
+        };
+        let multiline = match attribute_type {
+            1 => None,
+            attribute_type => {
+                let mtext = reader.read_handle()?;
+                let annotative_data_size = reader.data().read_bitshort()?;
+                let annotative_data = match annotative_data_size > 1 {
+                    true => Some((
+                        reader.data().read_raw_char()? as u8,
+                        reader.read_handle()?,
+                        reader.data().read_bitshort()?,
+                    )),
+                    false => None,
+                };
+                Some(MultilineAttribute {
+                    attribute_type,
+                    mtext,
+                    annotative_data_size,
+                    annotative_data,
+                })
+            }
+        };
+        let tag = reader.read_text()?;
+        let data = reader.data();
+        let field_length = data.read_bitshort()?;
+        let flags = data.read_raw_char()? as u8;
+        let lock_position = version >= DWGVersion::AC1021 && data.read_bit()? == 1;
+        Ok(Self {
+            text,
+            class_version,
+            multiline,
+            tag,
+            field_length,
+            flags,
+            lock_position,
+        })
+    }
+}
+
+impl DwgEncode for Attrib {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.text.encode(writer)?;
+        let version = writer.get_version();
+        if version >= DWGVersion::AC1024 {
+            writer.write_raw_char(self.class_version as i8);
+        }
+        if version >= DWGVersion::AC1032 {
+            match &self.multiline {
+                None => writer.write_raw_char(1),
+                Some(multiline) => {
+                    writer.write_raw_char(multiline.attribute_type as i8);
+                    writer.write_handle(multiline.mtext);
+                    writer.write_bitshort(multiline.annotative_data_size);
+                    if let Some((byte, app, short)) = multiline.annotative_data {
+                        writer.write_raw_char(byte as i8);
+                        writer.write_handle(app);
+                        writer.write_bitshort(short);
+                    }
+                }
+            }
+        }
+        writer.write_text(&self.tag)?;
+        writer.write_bitshort(self.field_length);
+        writer.write_raw_char(self.flags as i8);
+        if version >= DWGVersion::AC1021 {
+            writer.write_bit(self.lock_position as u8);
+        }
+        Ok(())
+    }
+}
+
+/// An attribute definition entity, in the block definition whose inserts get an ATTRIB for it
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttDef {
+    /// The tag, the default value and how attributes for the definition are drawn
+    pub attrib: Attrib,
+    /// Stored from AC1024 (R2010)
+    pub attdef_class_version: u8,
+    /// The text asking for the value when the block is inserted
+    pub prompt: String,
+}
+
+impl DwgDecode for AttDef {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let attrib = Attrib::decode(reader)?;
+        let data = reader.data();
+        let attdef_class_version = match data.get_version() >= DWGVersion::AC1024 {
+            true => data.read_raw_char()? as u8,
+            false => 0,
+        };
+        Ok(Self {
+            attrib,
+            attdef_class_version,
+            prompt: reader.read_text()?,
+        })
+    }
+}
+
+impl DwgEncode for AttDef {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.attrib.encode(writer)?;
+        if writer.get_version() >= DWGVersion::AC1024 {
+            writer.write_raw_char(self.attdef_class_version as i8);
+        }
+        writer.write_text(&self.prompt)?;
+        Ok(())
+    }
+}
+
+dwg_object! {
+    /// The BLOCK entity that starts a block, owned by its block header rather than being one
+    /// of its entities
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Block {
+        pub header: CommonEntityData,
+        pub name: TV,
+    }
+}
+
+impl DwgEncode for Block {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        writer.write_text(&self.name)?;
+        Ok(())
+    }
+}
+
+dwg_object! {
+    /// The ENDBLK entity that ends a block, owned by its block header
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct EndBlk {
+        pub header: CommonEntityData,
+    }
+}
+
+impl DwgEncode for EndBlk {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        Ok(())
+    }
+}
+
+/// An insert of a block, along with the ATTRIBs holding its attribute values
+#[derive(Clone, Debug, PartialEq)]
+pub struct Insert {
+    pub header: CommonEntityData,
+    pub insertion: Point3,
+    /// Scale along each axis of the block
+    pub scale: Point3,
+    pub rotation: f64,
+    pub extrusion: Point3,
+    /// The block header of the block inserted
+    pub block_header: HandleRef,
+    /// The first and last ATTRIBs before AC1018 (R2004), which are linked through
+    /// [`CommonEntityData::links`]. See [`Dwg::insert_attribs`]
+    pub first_last_attribs: Option<(HandleRef, HandleRef)>,
+    /// Every ATTRIB from AC1018 (R2004)
+    pub attribs: Vec<HandleRef>,
+    /// The SEQEND ending the ATTRIBs, stored if there are any
+    pub seqend: Option<HandleRef>,
+}
+
+impl DwgDecode for Insert {
+    /// AC1015 (R2000) and later store the scale after a 2 bit flag: 3 if it is 1.0 on every
+    /// axis, 2 if it is the same on every axis, so only x is stored as an RD, 1 if x is 1.0 and
+    /// y and z are DDs defaulting to it, or 0 if x is an RD and y and z are DDs defaulting to x
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let header = CommonEntityData::decode(reader)?;
+        let data = reader.data();
+        let version = data.get_version();
+        let insertion = data.read_3bd()?;
+        let scale = match version >= DWGVersion::AC1015 {
+            true => match data.read_bits_dyn(2)? {
+                3 => Point3::from((1.0, 1.0, 1.0)),
+                2 => {
+                    let x = data.read_raw_double()?;
+                    Point3::from((x, x, x))
+                }
+                flags => {
+                    let x = match flags {
+                        1 => 1.0,
+                        _ => data.read_raw_double()?,
+                    };
+                    Point3::from((x, data.read_dd(x)?, data.read_dd(x)?))
+                }
+            },
+            false => data.read_3bd()?,
+        };
+        let rotation = data.read_bitdouble()?;
+        let extrusion = data.read_3bd()?;
+        let has_attribs = data.read_bit()? == 1;
+        let n_attribs = match has_attribs && version >= DWGVersion::AC1018 {
+            true => data.read_bitlong()?.max(0) as usize,
+            false => 0,
+        };
+
+        let block_header = reader.read_handle()?;
+        let first_last_attribs = match has_attribs && version < DWGVersion::AC1018 {
+            true => Some((reader.read_handle()?, reader.read_handle()?)),
+            false => None,
+        };
+        let attribs = (0..n_attribs)
+            .map(|_| reader.read_handle())
+            .collect::<Result<_>>()?;
+        let seqend = match has_attribs {
+            true => Some(reader.read_handle()?),
+            false => None,
+        };
+        Ok(Self {
+            header,
+            insertion,
+            scale,
+            rotation,
+            extrusion,
+            block_header,
+            first_last_attribs,
+            attribs,
+            seqend,
+        })
+    }
+}
+
+impl DwgEncode for Insert {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        let version = writer.get_version();
+        writer.write_3bd(self.insertion);
+        let Point3 { x, y, z } = self.scale;
+        if version < DWGVersion::AC1015 {
+            writer.write_3bd(self.scale);
+        } else if (x, y, z) == (1.0, 1.0, 1.0) {
+            writer.write_bits_dyn(3, 2)?;
+        } else if y == x && z == x {
+            writer.write_bits_dyn(2, 2)?;
+            writer.write_raw_double(x);
+        } else {
+            writer.write_bits_dyn((x == 1.0) as u64, 2)?;
+            if x != 1.0 {
+                writer.write_raw_double(x);
+            }
+            writer.write_dd(y, x);
+            writer.write_dd(z, x);
+        }
+        writer.write_bitdouble(self.rotation);
+        writer.write_3bd(self.extrusion);
+        writer.write_bit(self.seqend.is_some() as u8);
+        let r2004 = version >= DWGVersion::AC1018;
+        if let (true, Some(_)) = (r2004, self.seqend) {
+            writer.write_bitlong(self.attribs.len() as i32);
+        }
+
+        writer.write_handle(self.block_header);
+        let Some(seqend) = self.seqend else {
+            return Ok(());
+        };
+        match self.first_last_attribs {
+            Some((first, last)) if !r2004 => {
+                writer.write_handle(first);
+                writer.write_handle(last);
+            }
+            _ if !r2004 => {
+                let null = HandleRef::new(RefType::SoftPointer, 0);
+                writer.write_handle(null);
+                writer.write_handle(null);
+            }
+            _ => self
+                .attribs
+                .iter()
+                .for_each(|&attrib| writer.write_handle(attrib)),
+        }
+        writer.write_handle(seqend);
+        Ok(())
+    }
+}
+
+/// An insert of a block repeated in rows and columns
+#[derive(Clone, Debug, PartialEq)]
+pub struct MInsert {
+    /// The first insert of the array, at the first row and column
+    pub insert: Insert,
+    pub columns: i16,
+    pub rows: i16,
+    pub column_spacing: f64,
+    pub row_spacing: f64,
+}
+
+impl MInsert {
+    /// Returns the insertion point of each insert of the array, row by row
+    ///
+    /// Columns are spaced along the x axis of the insert and rows along its y axis, both turned
+    /// by its rotation, so the points are in the same coordinates as [`Insert::insertion`]
+    pub fn insertion_points(&self) -> Vec<Point3> {
+        let Insert {
+            insertion,
+            rotation,
+            ..
+        } = self.insert;
+        let (sin, cos) = rotation.sin_cos();
+        let rows = 0..self.rows.max(0);
+        rows.flat_map(|row| {
+            (0..self.columns.max(0)).map(move |column| {
+                let x = column as f64 * self.column_spacing;
+                let y = row as f64 * self.row_spacing;
+                Point3 {
+                    x: insertion.x + x * cos - y * sin,
+                    y: insertion.y + x * sin + y * cos,
+                    z: insertion.z,
+                }
+            })
+        })
+        .collect()
+    }
+}
+
+impl DwgDecode for MInsert {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let insert = Insert::decode(reader)?;
+        let data = reader.data();
+        Ok(Self {
+            insert,
+            columns: data.read_bitshort()?,
+            rows: data.read_bitshort()?,
+            column_spacing: data.read_bitdouble()?,
+            row_spacing: data.read_bitdouble()?,
+        })
+    }
+}
+
+impl DwgEncode for MInsert {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.insert.encode(writer)?;
+        writer.write_bitshort(self.columns);
+        writer.write_bitshort(self.rows);
+        writer.write_bitdouble(self.column_spacing);
+        writer.write_bitdouble(self.row_spacing);
+        Ok(())
+    }
+}
+
+/// The vertices a polyline owns, which are entities of their own ended by a SEQEND
+///
+/// Stored at the end of each polyline, with their number at the end of its data from AC1018
+/// (R2004) and their handles at the end of its handles
+#[derive(Clone, Debug, PartialEq)]
+pub struct OwnedVertices {
+    /// The first and last vertices before AC1018 (R2004), which are linked through
+    /// [`CommonEntityData::links`]
+    pub first_last: Option<(HandleRef, HandleRef)>,
+    /// Every vertex from AC1018 (R2004)
+    pub vertices: Vec<HandleRef>,
+    pub seqend: HandleRef,
+}
+
+impl DwgDecode for OwnedVertices {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let r2004 = reader.data().get_version() >= DWGVersion::AC1018;
+        let count = match r2004 {
+            true => reader.data().read_bitlong()?.max(0) as usize,
+            false => 0,
+        };
+        let first_last = match r2004 {
+            true => None,
+            false => Some((reader.read_handle()?, reader.read_handle()?)),
+        };
+        let vertices = (0..count)
+            .map(|_| reader.read_handle())
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            first_last,
+            vertices,
+            seqend: reader.read_handle()?,
+        })
+    }
+}
+
+impl DwgEncode for OwnedVertices {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        if writer.get_version() >= DWGVersion::AC1018 {
+            writer.write_bitlong(self.vertices.len() as i32);
+            self.vertices
+                .iter()
+                .for_each(|&vertex| writer.write_handle(vertex));
+        } else {
+            let null = HandleRef::new(RefType::SoftPointer, 0);
+            let (first, last) = self.first_last.unwrap_or((null, null));
+            writer.write_handle(first);
+            writer.write_handle(last);
+        }
+        writer.write_handle(self.seqend);
+        Ok(())
+    }
+}
+
+/// A polyline decoded along with its vertices, see [`Dwg::polyline_2d`],
+/// [`Dwg::polyline_3d`] and [`Dwg::polygon_mesh`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polyline<P, V> {
+    pub polyline: P,
+    pub vertices: Vec<V>,
+}
+
+dwg_object! {
+    /// A 2D polyline, in the plane normal to its extrusion at its elevation. Its vertices are
+    /// VERTEX_2D entities it owns
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Polyline2d {
+        pub header: CommonEntityData,
+        /// 1 if the polyline is closed, 2 if it is curve fit, 4 if it is spline fit and 0x80 if
+        /// its linetype is continued through the vertices
+        pub flags: BS,
+        /// 0 for none, 5 for a quadratic B-spline, 6 for a cubic B-spline or 8 for a Bezier
+        /// surface
+        pub curve_type: BS,
+        /// Default width at the start of each segment
+        pub start_width: BD,
+        /// Default width at the end of each segment
+        pub end_width: BD,
+        pub thickness: BT,
+        pub elevation: BD,
+        pub extrusion: BE,
+        pub owned: OwnedVertices,
+    }
+}
+
+impl DwgEncode for Polyline2d {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        writer.write_bitshort(self.flags);
+        writer.write_bitshort(self.curve_type);
+        writer.write_bitdouble(self.start_width);
+        writer.write_bitdouble(self.end_width);
+        writer.write_bit_thickness(self.thickness);
+        writer.write_bitdouble(self.elevation);
+        writer.write_bit_extrusion(self.extrusion);
+        self.owned.encode(writer)?;
+        Ok(())
+    }
+}
+
+/// A vertex of a 2D polyline
+#[derive(Clone, Debug, PartialEq)]
+pub struct Vertex2d {
+    pub header: CommonEntityData,
+    /// 1 if the vertex was added by curve fitting, 2 if `tangent_direction` is set, 8 if it
+    /// was added by spline fitting and 0x10 if it is a control point of the spline
+    pub flags: u8,
+    /// The position, where z is ignored in favour of the elevation of the polyline
+    pub point: Point3,
+    pub start_width: f64,
+    pub end_width: f64,
+    /// Tangent of the arc the segment from the vertex is, 0 for a straight segment and 1 for a
+    /// half circle counterclockwise
+    pub bulge: f64,
+    /// Stored from AC1024 (R2010)
+    pub vertex_id: i32,
+    /// Curve fit tangent direction, in radians
+    pub tangent_direction: f64,
+}
+
+impl DwgDecode for Vertex2d {
+    /// A negative start width is both the start and end width, with no end width stored
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let header = CommonEntityData::decode(reader)?;
+        let data = reader.data();
+        let flags = data.read_raw_char()? as u8;
+        let point = data.read_3bd()?;
+        let start_width = data.read_bitdouble()?;
+        let (start_width, end_width) = match start_width < 0.0 {
+            true => (-start_width, -start_width),
+            false => (start_width, data.read_bitdouble()?),
+        };
+        let bulge = data.read_bitdouble()?;
+        let vertex_id = match data.get_version() >= DWGVersion::AC1024 {
+            true => data.read_bitlong()?,
+            false => 0,
+        };
+        Ok(Self {
+            header,
+            flags,
+            point,
+            start_width,
+            end_width,
+            bulge,
+            vertex_id,
+            tangent_direction: data.read_bitdouble()?,
+        })
+    }
+}
+
+impl DwgEncode for Vertex2d {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        writer.write_raw_char(self.flags as i8);
+        writer.write_3bd(self.point);
+        if self.start_width == self.end_width && self.start_width > 0.0 {
+            writer.write_bitdouble(-self.start_width);
+        } else {
+            writer.write_bitdouble(self.start_width);
+            writer.write_bitdouble(self.end_width);
+        }
+        writer.write_bitdouble(self.bulge);
+        if writer.get_version() >= DWGVersion::AC1024 {
+            writer.write_bitlong(self.vertex_id);
+        }
+        writer.write_bitdouble(self.tangent_direction);
+        Ok(())
+    }
+}
+
+dwg_object! {
+    /// A 3D polyline, whose vertices are VERTEX_3D entities it owns
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Polyline3d {
+        pub header: CommonEntityData,
+        /// 1 for a quadratic and 2 for a cubic B-spline fit, 0 if not spline fit
+        pub spline_flags: RC,
+        /// 1 if the polyline is closed
+        pub closed_flags: RC,
+        pub owned: OwnedVertices,
+    }
+}
+
+impl Polyline3d {
+    pub fn is_closed(&self) -> bool {
+        self.closed_flags & 1 != 0
+    }
+
+    pub fn is_spline_fit(&self) -> bool {
+        self.spline_flags & 3 != 0
+    }
+}
+
+impl DwgEncode for Polyline3d {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        writer.write_raw_char(self.spline_flags);
+        writer.write_raw_char(self.closed_flags);
+        self.owned.encode(writer)?;
+        Ok(())
+    }
+}
+
+/// A vertex of a 3D polyline, or of a polyface mesh
+#[derive(Clone, Debug, PartialEq)]
+pub struct Vertex3d {
+    pub header: CommonEntityData,
+    /// 8 if the vertex was added by spline fitting and 0x10 if it is a control point of the
+    /// spline. 0xC0 for the vertices of a polyface mesh
+    pub flags: u8,
+    pub point: Point3,
+}
+
+impl DwgDecode for Vertex3d {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let header = CommonEntityData::decode(reader)?;
+        let data = reader.data();
+        Ok(Self {
+            header,
+            flags: data.read_raw_char()? as u8,
+            point: data.read_3bd()?,
+        })
+    }
+}
+
+impl DwgEncode for Vertex3d {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        writer.write_raw_char(self.flags as i8);
+        writer.write_3bd(self.point);
+        Ok(())
+    }
+}
+
+dwg_object! {
+    /// A polyface mesh, whose vertices are VERTEX_PFACE entities it owns followed by
+    /// VERTEX_PFACE_FACE entities listing the vertices of each face
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct PolylinePface {
+        pub header: CommonEntityData,
+        pub num_vertices: BS,
+        pub num_faces: BS,
+        pub owned: OwnedVertices,
+    }
+}
+
+impl DwgEncode for PolylinePface {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        writer.write_bitshort(self.num_vertices);
+        writer.write_bitshort(self.num_faces);
+        self.owned.encode(writer)?;
+        Ok(())
+    }
+}
+
+/// A face of a polyface mesh, stored as a VERTEX_PFACE_FACE
+#[derive(Clone, Debug, PartialEq)]
+pub struct FaceRecord {
+    pub header: CommonEntityData,
+    /// 1-based indices of the vertices of the face, negative if the edge from the vertex is
+    /// invisible and 0 for no vertex, as the fourth of a triangle
+    pub indices: [i16; 4],
+}
+
+impl DwgDecode for FaceRecord {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let header = CommonEntityData::decode(reader)?;
+        let mut indices = [0; 4];
+        for index in &mut indices {
+            *index = reader.data().read_bitshort()?;
+        }
+        Ok(Self { header, indices })
+    }
+}
+
+impl FaceRecord {
+    /// Returns the 0-based index of each vertex of the face, along with whether the edge from
+    /// it is visible
+    pub fn vertices(&self) -> impl Iterator<Item = (usize, bool)> + '_ {
+        self.indices
+            .iter()
+            .filter(|&&index| index != 0)
+            .map(|&index| (index.unsigned_abs() as usize - 1, index > 0))
+    }
+}
+
+impl DwgEncode for FaceRecord {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        self.indices
+            .iter()
+            .for_each(|&index| writer.write_bitshort(index));
+        Ok(())
+    }
+}
+
+/// A polyface mesh decoded along with its vertices and faces, see [`Dwg::polyface_mesh`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PolyfaceMesh {
+    pub polyline: PolylinePface,
+    pub vertices: Vec<Vertex3d>,
+    /// The faces, whose indices are into `vertices`
+    pub faces: Vec<FaceRecord>,
+}
+
+dwg_object! {
+    /// A polygon mesh, a grid of M by N VERTEX_MESH entities it owns, stored row by row
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct PolylineMesh {
+        pub header: CommonEntityData,
+        /// 1 if the mesh is closed in M, 4 if it is a fit surface, 0x10 always and 0x20 if it
+        /// is closed in N
+        pub flags: BS,
+        /// 0 for none, 5 for a quadratic B-spline, 6 for a cubic B-spline or 8 for a Bezier
+        /// surface
+        pub curve_type: BS,
+        pub m_vertex_count: BS,
+        pub n_vertex_count: BS,
+        /// Density of the fit surface in M
+        pub m_density: BS,
+        /// Density of the fit surface in N
+        pub n_density: BS,
+        pub owned: OwnedVertices,
+    }
+}
+
+impl DwgEncode for PolylineMesh {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        writer.write_bitshort(self.flags);
+        writer.write_bitshort(self.curve_type);
+        writer.write_bitshort(self.m_vertex_count);
+        writer.write_bitshort(self.n_vertex_count);
+        writer.write_bitshort(self.m_density);
+        writer.write_bitshort(self.n_density);
+        self.owned.encode(writer)?;
+        Ok(())
+    }
+}
+
+impl Polyline<PolylineMesh, Vertex3d> {
+    /// Returns each row of the grid, its N vertices at one M
+    pub fn rows(&self) -> std::slice::Chunks<'_, Vertex3d> {
+        let n = self.polyline.n_vertex_count.max(1) as usize;
+        self.vertices.chunks(n)
+    }
+
+    /// Returns the vertex at row `m` and column `n` of the grid
+    pub fn vertex(&self, m: usize, n: usize) -> Option<&Vertex3d> {
+        let columns = self.polyline.n_vertex_count.max(0) as usize;
+        match n < columns {
+            true => self.vertices.get(m * columns + n),
+            false => None,
+        }
+    }
+}
+
+/// A 3DFACE, a triangle or quadrilateral in space. A triangle repeats its third corner
+#[derive(Clone, Debug, PartialEq)]
+pub struct Face3d {
+    pub header: CommonEntityData,
+    pub corners: [Point3; 4],
+    /// 1, 2, 4 and 8 if the first, second, third and fourth edge are invisible
+    pub invisible_edges: i16,
+}
+
+impl Face3d {
+    /// Returns true if the edge from corner `edge` to the next is visible
+    pub fn is_edge_visible(&self, edge: usize) -> bool {
+        self.invisible_edges & (1 << edge) == 0
+    }
+}
+
+impl DwgDecode for Face3d {
+    /// From AC1015 (R2000) each corner after the first is stored as DDs defaulting to the one
+    /// before it. The z of the first corner is left out if it is 0 and the invisible edges if
+    /// there are none
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let header = CommonEntityData::decode(reader)?;
+        let data = reader.data();
+        let mut corners = [Point3::default(); 4];
+        if data.get_version() < DWGVersion::AC1015 {
+            for corner in &mut corners {
+                *corner = data.read_3bd()?;
+            }
+            let invisible_edges = data.read_bitshort()?;
+            return Ok(Self {
+                header,
+                corners,
+                invisible_edges,
+            });
+        }
+        let has_no_flags = data.read_bit()? == 1;
+        let z_is_zero = data.read_bit()? == 1;
+        corners[0].x = data.read_raw_double()?;
+        corners[0].y = data.read_raw_double()?;
+        if !z_is_zero {
+            corners[0].z = data.read_raw_double()?;
+        }
+        for i in 1..4 {
+            let last = corners[i - 1];
+            corners[i] = Point3 {
+                x: data.read_dd(last.x)?,
+                y: data.read_dd(last.y)?,
+                z: data.read_dd(last.z)?,
+            };
+        }
+        let invisible_edges = match has_no_flags {
+            true => 0,
+            false => data.read_bitshort()?,
+        };
+        Ok(Self {
+            header,
+            corners,
+            invisible_edges,
+        })
+    }
+}
+
+impl DwgEncode for Face3d {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        if writer.get_version() < DWGVersion::AC1015 {
+            self.corners
+                .iter()
+                .for_each(|&corner| writer.write_3bd(corner));
+            writer.write_bitshort(self.invisible_edges);
+            return Ok(());
+        }
+        let [first, ..] = self.corners;
+        let has_no_flags = self.invisible_edges == 0;
+        writer.write_bit(has_no_flags as u8);
+        writer.write_bit((first.z == 0.0) as u8);
+        writer.write_raw_double(first.x);
+        writer.write_raw_double(first.y);
+        if first.z != 0.0 {
+            writer.write_raw_double(first.z);
+        }
+        for pair in self.corners.windows(2) {
+            writer.write_dd(pair[1].x, pair[0].x);
+            writer.write_dd(pair[1].y, pair[0].y);
+            writer.write_dd(pair[1].z, pair[0].z);
+        }
+        if !has_no_flags {
+            writer.write_bitshort(self.invisible_edges);
+        }
+        Ok(())
+    }
+}
+
+/// A filled quadrilateral, in the plane normal to its extrusion at its elevation. Its corners
+/// are in the order they are connected in as a Z, so the third is opposite the second
+#[derive(Clone, Debug, PartialEq)]
+pub struct Solid {
+    pub header: CommonEntityData,
+    pub thickness: f64,
+    pub elevation: f64,
+    pub corners: [Point2; 4],
+    pub extrusion: Point3,
+}
+
+impl DwgDecode for Solid {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let header = CommonEntityData::decode(reader)?;
+        let data = reader.data();
+        let thickness = data.read_bit_thickness()?;
+        let elevation = data.read_bitdouble()?;
+        let mut corners = [Point2::default(); 4];
+        for corner in &mut corners {
+            *corner = data.read_2rd()?;
+        }
+        Ok(Self {
+            header,
+            thickness,
+            elevation,
+            corners,
+            extrusion: data.read_bit_extrusion()?,
+        })
+    }
+}
+
+impl DwgEncode for Solid {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        writer.write_bit_thickness(self.thickness);
+        writer.write_bitdouble(self.elevation);
+        self.corners
+            .iter()
+            .for_each(|&corner| writer.write_2rd(corner));
+        writer.write_bit_extrusion(self.extrusion);
+        Ok(())
+    }
+}
+
+dwg_object! {
+    /// A shape from a compiled SHX shape file, drawn like a single character of text
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Shape {
+        pub header: CommonEntityData,
+        pub insertion: BD3,
+        /// Height of the shape
+        pub scale: BD,
+        pub rotation: BD,
+        pub width_factor: BD,
+        /// Oblique angle, in radians
+        pub oblique: BD,
+        pub thickness: BD,
+        /// Number of the shape within the shape file
+        pub shape_number: BS,
+        pub extrusion: BD3,
+        /// The STYLE entry naming the shape file
+        pub style: H,
+    }
+}
+
+impl DwgEncode for Shape {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        writer.write_3bd(self.insertion);
+        writer.write_bitdouble(self.scale);
+        writer.write_bitdouble(self.rotation);
+        writer.write_bitdouble(self.width_factor);
+        writer.write_bitdouble(self.oblique);
+        writer.write_bitdouble(self.thickness);
+        writer.write_bitshort(self.shape_number);
+        writer.write_3bd(self.extrusion);
+        writer.write_handle(self.style);
+        Ok(())
+    }
+}
+
+dwg_object! {
+    /// A construction line through `point` along `direction`, a unit vector. A RAY runs on
+    /// from the point in one direction and an XLINE in both
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Ray {
+        pub header: CommonEntityData,
+        pub point: BD3,
+        pub direction: BD3,
+    }
+}
+
+impl DwgEncode for Ray {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        writer.write_3bd(self.point);
+        writer.write_3bd(self.direction);
+        Ok(())
+    }
+}
+
+/// A leader, a line or spline along its points pointing from an annotation to what it
+/// describes
+///
+/// The arrowhead and its size are stored before AC1015 (R2000), and taken from the dimension
+/// style in later versions
+#[derive(Clone, Debug, PartialEq)]
+pub struct Leader {
+    pub header: CommonEntityData,
+    pub unknown_bit: bool,
+    /// 0 for an MTEXT, 1 for a tolerance, 2 for an INSERT or 3 for no annotation
+    pub annotation_type: i16,
+    /// 0 for straight line segments or 1 for a spline
+    pub path_type: i16,
+    pub points: Vec<Point3>,
+    pub origin: Point3,
+    pub extrusion: Point3,
+    pub x_direction: Point3,
+    /// Offset of the last point from the insertion of the annotation block
+    pub block_offset: Point3,
+    /// Stored from AC1014 (R14)
+    pub end_projection: Point3,
+    /// Stored before AC1015 (R2000)
+    pub dimgap: f64,
+    pub box_height: f64,
+    pub box_width: f64,
+    /// True if the hookline is in the direction of the x axis rather than against it
+    pub hookline_on_x_dir: bool,
+    pub arrowhead_on: bool,
+    /// Stored before AC1015 (R2000)
+    pub arrowhead_type: i16,
+    /// Stored before AC1015 (R2000)
+    pub arrowhead_size: f64,
+    /// Stored before AC1015 (R2000)
+    pub byblock_color: i16,
+    pub hookline: bool,
+    /// The annotation the leader is attached to
+    pub annotation: HandleRef,
+    pub dimstyle: HandleRef,
+}
+
+impl DwgDecode for Leader {
+    /// The unknown values that follow the arrowhead flag are skipped, apart from the hookline
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let header = CommonEntityData::decode(reader)?;
+        let data = reader.data();
+        let version = data.get_version();
+        let r2000 = version >= DWGVersion::AC1015;
+        let unknown_bit = data.read_bit()? == 1;
+        let annotation_type = data.read_bitshort()?;
+        let path_type = data.read_bitshort()?;
+        let num_points = data.read_bitlong()?.max(0) as usize;
+        let points = (0..num_points)
+            .map(|_| data.read_3bd())
+            .collect::<Result<_>>()?;
+        let origin = data.read_3bd()?;
+        let extrusion = data.read_3bd()?;
+        let x_direction = data.read_3bd()?;
+        let block_offset = data.read_3bd()?;
+        let end_projection = match version >= DWGVersion::AC1014 {
+            true => data.read_3bd()?,
+            false => Point3::default(),
+        };
+        let dimgap = match r2000 {
+            true => 0.0,
+            false => data.read_bitdouble()?,
+        };
+        let box_height = data.read_bitdouble()?;
+        let box_width = data.read_bitdouble()?;
+        let hookline_on_x_dir = data.read_bit()? == 1;
+        let arrowhead_on = data.read_bit()? == 1;
+        let (mut arrowhead_type, mut arrowhead_size, mut byblock_color) = (0, 0.0, 0);
+        if !r2000 {
+            arrowhead_type = data.read_bitshort()?;
+            arrowhead_size = data.read_bitdouble()?;
+            data.read_bit()?;
+            data.read_bit()?;
+            data.read_bitshort()?;
+            byblock_color = data.read_bitshort()?;
+        } else {
+            data.read_bitshort()?;
+        }
+        let hookline = data.read_bit()? == 1;
+        data.read_bit()?;
+        Ok(Self {
+            header,
+            unknown_bit,
+            annotation_type,
+            path_type,
+            points,
+            origin,
+            extrusion,
+            x_direction,
+            block_offset,
+            end_projection,
+            dimgap,
+            box_height,
+            box_width,
+            hookline_on_x_dir,
+            arrowhead_on,
+            arrowhead_type,
+            arrowhead_size,
+            byblock_color,
+            hookline,
+            annotation: reader.read_handle()?,
+            dimstyle: reader.read_handle()?,
+        })
+    }
+}
+
+impl DwgEncode for Leader {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        let version = writer.get_version();
+        let r2000 = version >= DWGVersion::AC1015;
+        writer.write_bit(self.unknown_bit as u8);
+        writer.write_bitshort(self.annotation_type);
+        writer.write_bitshort(self.path_type);
+        writer.write_bitlong(self.points.len() as i32);
+        self.points
+            .iter()
+            .for_each(|&point| writer.write_3bd(point));
+        writer.write_3bd(self.origin);
+        writer.write_3bd(self.extrusion);
+        writer.write_3bd(self.x_direction);
+        writer.write_3bd(self.block_offset);
+        if version >= DWGVersion::AC1014 {
+            writer.write_3bd(self.end_projection);
+        }
+        if !r2000 {
+            writer.write_bitdouble(self.dimgap);
+        }
+        writer.write_bitdouble(self.box_height);
+        writer.write_bitdouble(self.box_width);
+        writer.write_bit(self.hookline_on_x_dir as u8);
+        writer.write_bit(self.arrowhead_on as u8);
+        if !r2000 {
+            writer.write_bitshort(self.arrowhead_type);
+            writer.write_bitdouble(self.arrowhead_size);
+            writer.write_bit(0);
+            writer.write_bit(0);
+            writer.write_bitshort(0);
+            writer.write_bitshort(self.byblock_color);
+        } else {
+            writer.write_bitshort(0);
+        }
+        writer.write_bit(self.hookline as u8);
+        writer.write_bit(0);
+        writer.write_handle(self.annotation);
+        writer.write_handle(self.dimstyle);
+        Ok(())
+    }
+}
+
+/// A multileader, any number of leaders pointing from one MTEXT or block
+///
+/// Its type is defined by the class named `MULTILEADER`, so it is read through
+/// [`Dwg::multileader`] rather than [`Entity::decode`]. Most of its values override those of
+/// its MLEADERSTYLE, see [`crate::objects::MLeaderStyle`], where set in `override_flags`
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiLeader {
+    pub header: CommonEntityData,
+    /// Stored from AC1024 (R2010), 2
+    pub class_version: i16,
+    pub context: MLeaderContext,
+    pub style: HandleRef,
+    pub override_flags: i32,
+    /// 0 for invisible leaders, 1 for straight line segments or 2 for splines
+    pub leader_type: i16,
+    pub line_color: CmColor,
+    pub line_type: HandleRef,
+    pub line_weight: i32,
+    pub landing_enabled: bool,
+    pub dogleg_enabled: bool,
+    pub landing_distance: f64,
+    pub arrowhead: HandleRef,
+    pub arrowhead_size: f64,
+    /// 0 for none, 1 for a block, 2 for an MTEXT or 3 for a tolerance
+    pub content_type: i16,
+    pub text_style: HandleRef,
+    pub text_left_attachment: i16,
+    pub text_right_attachment: i16,
+    pub text_angle_type: i16,
+    pub text_alignment: i16,
+    pub text_color: CmColor,
+    pub text_frame: bool,
+    pub block: HandleRef,
+    pub block_color: CmColor,
+    pub block_scale: Point3,
+    pub block_rotation: f64,
+    /// 0 to connect to the extents of the block or 1 to its insertion point
+    pub block_connection: i16,
+    pub annotative: bool,
+    /// The arrowheads of the leaders, and whether each is the default
+    pub arrowheads: Vec<(bool, HandleRef)>,
+    /// The values of the ATTDEFs of the block content
+    pub block_labels: Vec<BlockLabel>,
+    pub text_direction_negative: bool,
+    pub ipe_alignment: i16,
+    pub justification: i16,
+    pub scale_factor: f64,
+    /// Stored from AC1024 (R2010), 0 for horizontal or 1 for vertical attachment
+    pub attachment_direction: i16,
+    /// Stored from AC1024 (R2010)
+    pub text_top_attachment: i16,
+    /// Stored from AC1024 (R2010)
+    pub text_bottom_attachment: i16,
+    /// Stored from AC1027 (R2013)
+    pub extended_to_text: bool,
+}
+
+/// The value of an ATTDEF of the block of a [`MultiLeader`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockLabel {
+    pub attdef: HandleRef,
+    pub text: String,
+    pub ui_index: i16,
+    pub width: f64,
+}
+
+/// The geometry of a [`MultiLeader`] at its annotation scale: its leaders and content
+#[derive(Clone, Debug, PartialEq)]
+pub struct MLeaderContext {
+    pub roots: Vec<LeaderRoot>,
+    pub scale: f64,
+    pub content_base: Point3,
+    pub text_height: f64,
+    pub arrowhead_size: f64,
+    pub landing_gap: f64,
+    pub text_left_attachment: i16,
+    pub text_right_attachment: i16,
+    pub text_alignment: i16,
+    pub attachment_type: i16,
+    pub content: MLeaderContent,
+    pub base_point: Point3,
+    pub base_direction: Point3,
+    pub base_vertical: Point3,
+    pub normal_reversed: bool,
+    /// Stored from AC1024 (R2010)
+    pub text_top_attachment: i16,
+    /// Stored from AC1024 (R2010)
+    pub text_bottom_attachment: i16,
+}
+
+/// The leader lines of a [`MultiLeader`] that leave the content at one point
+#[derive(Clone, Debug, PartialEq)]
+pub struct LeaderRoot {
+    pub content_valid: bool,
+    pub unknown: bool,
+    /// Where the lines connect to the landing
+    pub connection: Point3,
+    /// Direction of the landing from the connection
+    pub direction: Point3,
+    /// Start and end of each break in the landing
+    pub breaks: Vec<(Point3, Point3)>,
+    pub index: i32,
+    pub landing_distance: f64,
+    pub lines: Vec<LeaderLine>,
+    /// Stored from AC1024 (R2010)
+    pub attachment_direction: i16,
+}
+
+/// A leader line of a [`LeaderRoot`], running from its first point to the connection
+#[derive(Clone, Debug, PartialEq)]
+pub struct LeaderLine {
+    pub points: Vec<Point3>,
+    /// The index of each segment with breaks, along with the start and end of each break
+    pub breaks: Vec<(i32, Vec<(Point3, Point3)>)>,
+    pub index: i32,
+    /// How the line is drawn, stored from AC1024 (R2010)
+    pub style: Option<LeaderLineStyle>,
+}
+
+/// The properties of a [`LeaderLine`] that override those of its [`MultiLeader`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct LeaderLineStyle {
+    pub leader_type: i16,
+    pub color: CmColor,
+    pub line_type: HandleRef,
+    pub line_weight: i32,
+    pub arrowhead_size: f64,
+    pub arrowhead: HandleRef,
+    pub override_flags: i32,
+}
+
+/// The content a [`MultiLeader`] points from
+#[derive(Clone, Debug, PartialEq)]
+pub enum MLeaderContent {
+    None,
+    MText(MLeaderText),
+    Block(MLeaderBlock),
+}
+
+/// The MTEXT content of a [`MultiLeader`], with the values of an MTEXT entity
+#[derive(Clone, Debug, PartialEq)]
+pub struct MLeaderText {
+    pub text: String,
+    pub normal: Point3,
+    pub style: HandleRef,
+    pub location: Point3,
+    pub direction: Point3,
+    pub rotation: f64,
+    pub width: f64,
+    pub height: f64,
+    pub line_spacing_factor: f64,
+    pub line_spacing_style: i16,
+    pub color: CmColor,
+    pub alignment: i16,
+    pub flow_direction: i16,
+    pub background_color: CmColor,
+    pub background_scale: f64,
+    pub background_transparency: i32,
+    pub background_enabled: bool,
+    pub background_mask_fill: bool,
+    pub column_type: i16,
+    pub auto_height: bool,
+    pub column_width: f64,
+    pub column_gutter: f64,
+    pub columns_reversed: bool,
+    pub column_sizes: Vec<f64>,
+    pub word_break: bool,
+    pub unknown: bool,
+}
+
+/// The block content of a [`MultiLeader`], inserted like an INSERT
+#[derive(Clone, Debug, PartialEq)]
+pub struct MLeaderBlock {
+    pub block_header: HandleRef,
+    pub normal: Point3,
+    pub location: Point3,
+    pub scale: Point3,
+    pub rotation: f64,
+    pub color: CmColor,
+    /// The transformation matrix of the block, by rows
+    pub transform: [f64; 16],
+}
+
+fn read_count<R: ObjectRead>(reader: &mut R) -> Result<usize> {
+    reader
+        .data()
+        .read_bitlong()
+        .map(|count| count.max(0) as usize)
+}
+
+fn read_point_pairs<R: ObjectRead>(reader: &mut R) -> Result<Vec<(Point3, Point3)>> {
+    let count = read_count(reader)?;
+    (0..count)
+        .map(|_| Ok((reader.data().read_3bd()?, reader.data().read_3bd()?)))
+        .collect()
+}
+
+fn write_point_pairs(writer: &mut DualStreamWriter, pairs: &[(Point3, Point3)]) {
+    writer.write_bitlong(pairs.len() as i32);
+    for &(start, end) in pairs {
+        writer.write_3bd(start);
+        writer.write_3bd(end);
+    }
+}
+
+impl DwgDecode for LeaderLine {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let num_points = read_count(reader)?;
+        let points = (0..num_points)
+            .map(|_| reader.data().read_3bd())
+            .collect::<Result<_>>()?;
+        let num_breaks = read_count(reader)?;
+        let breaks = (0..num_breaks)
+            .map(|_| Ok((reader.data().read_bitlong()?, read_point_pairs(reader)?)))
+            .collect::<Result<_>>()?;
+        let index = reader.data().read_bitlong()?;
+        let style = match reader.data().get_version() >= DWGVersion::AC1024 {
+            true => Some(LeaderLineStyle {
+                leader_type: reader.data().read_bitshort()?,
+                color: reader.data().read_cm_color()?,
+                line_type: reader.read_handle()?,
+                line_weight: reader.data().read_bitlong()?,
+                arrowhead_size: reader.data().read_bitdouble()?,
+                arrowhead: reader.read_handle()?,
+                override_flags: reader.data().read_bitlong()?,
+            }),
+            false => None,
+        };
+        Ok(Self {
+            points,
+            breaks,
+            index,
+            style,
+        })
+    }
+}
+
+impl DwgEncode for LeaderLine {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        writer.write_bitlong(self.points.len() as i32);
+        self.points
+            .iter()
+            .for_each(|&point| writer.write_3bd(point));
+        writer.write_bitlong(self.breaks.len() as i32);
+        for (segment, pairs) in &self.breaks {
+            writer.write_bitlong(*segment);
+            write_point_pairs(writer, pairs);
+        }
+        writer.write_bitlong(self.index);
+        if writer.get_version() < DWGVersion::AC1024 {
+            return Ok(());
+        }
+        let null = HandleRef::new(RefType::HardPointer, 0);
+        let by_layer = CmColor::from_color(Color::ByLayer);
+        let style = self.style.as_ref();
+        let codepage = writer.codepage();
+        writer.write_bitshort(style.map_or(1, |style| style.leader_type));
+        let color = style.map_or(&by_layer, |style| &style.color);
+        writer.data().write_cm_color(color, codepage)?;
+        writer.write_handle(style.map_or(null, |style| style.line_type));
+        writer.write_bitlong(style.map_or(-1, |style| style.line_weight));
+        writer.write_bitdouble(style.map_or(0.0, |style| style.arrowhead_size));
+        writer.write_handle(style.map_or(null, |style| style.arrowhead));
+        writer.write_bitlong(style.map_or(0, |style| style.override_flags));
+        Ok(())
+    }
+}
+
+impl DwgDecode for LeaderRoot {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let data = reader.data();
+        let content_valid = data.read_bit()? == 1;
+        let unknown = data.read_bit()? == 1;
+        let connection = data.read_3bd()?;
+        let direction = data.read_3bd()?;
+        let breaks = read_point_pairs(reader)?;
+        let index = reader.data().read_bitlong()?;
+        let landing_distance = reader.data().read_bitdouble()?;
+        let num_lines = read_count(reader)?;
+        let lines = (0..num_lines)
+            .map(|_| LeaderLine::decode(reader))
+            .collect::<Result<_>>()?;
+        let attachment_direction = match reader.data().get_version() >= DWGVersion::AC1024 {
+            true => reader.data().read_bitshort()?,
+            false => 0,
+        };
+        Ok(Self {
+            content_valid,
+            unknown,
+            connection,
+            direction,
+            breaks,
+            index,
+            landing_distance,
+            lines,
+            attachment_direction,
+        })
+    }
+}
+
+impl DwgEncode for LeaderRoot {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        writer.write_bit(self.content_valid as u8);
+        writer.write_bit(self.unknown as u8);
+        writer.write_3bd(self.connection);
+        writer.write_3bd(self.direction);
+        write_point_pairs(writer, &self.breaks);
+        writer.write_bitlong(self.index);
+        writer.write_bitdouble(self.landing_distance);
+        writer.write_bitlong(self.lines.len() as i32);
+        self.lines.iter().try_for_each(|line| line.encode(writer))?;
+        if writer.get_version() >= DWGVersion::AC1024 {
+            writer.write_bitshort(self.attachment_direction);
+        }
+        Ok(())
+    }
+}
+
+impl DwgDecode for MLeaderText {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let text = reader.read_text()?;
+        let normal = reader.data().read_3bd()?;
+        let style = reader.read_handle()?;
+        let data = reader.data();
+        let location = data.read_3bd()?;
+        let direction = data.read_3bd()?;
+        let rotation = data.read_bitdouble()?;
+        let width = data.read_bitdouble()?;
+        let height = data.read_bitdouble()?;
+        let line_spacing_factor = data.read_bitdouble()?;
+        let line_spacing_style = data.read_bitshort()?;
+        let color = data.read_cm_color()?;
+        let alignment = data.read_bitshort()?;
+        let flow_direction = data.read_bitshort()?;
+        let background_color = data.read_cm_color()?;
+        let background_scale = data.read_bitdouble()?;
+        let background_transparency = data.read_bitlong()?;
+        let background_enabled = data.read_bit()? == 1;
+        let background_mask_fill = data.read_bit()? == 1;
+        let column_type = data.read_bitshort()?;
+        let auto_height = data.read_bit()? == 1;
+        let column_width = data.read_bitdouble()?;
+        let column_gutter = data.read_bitdouble()?;
+        let columns_reversed = data.read_bit()? == 1;
+        let num_column_sizes = data.read_bitlong()?.max(0) as usize;
+        let column_sizes = (0..num_column_sizes)
+            .map(|_| data.read_bitdouble())
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            text,
+            normal,
+            style,
+            location,
+            direction,
+            rotation,
+            width,
+            height,
+            line_spacing_factor,
+            line_spacing_style,
+            color,
+            alignment,
+            flow_direction,
+            background_color,
+            background_scale,
+            background_transparency,
+            background_enabled,
+            background_mask_fill,
+            column_type,
+            auto_height,
+            column_width,
+            column_gutter,
+            columns_reversed,
+            column_sizes,
+            word_break: data.read_bit()? == 1,
+            unknown: data.read_bit()? == 1,
+        })
+    }
+}
+
+impl DwgEncode for MLeaderText {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        let codepage = writer.codepage();
+        writer.write_text(&self.text)?;
+        writer.write_3bd(self.normal);
+        writer.write_handle(self.style);
+        writer.write_3bd(self.location);
+        writer.write_3bd(self.direction);
+        writer.write_bitdouble(self.rotation);
+        writer.write_bitdouble(self.width);
+        writer.write_bitdouble(self.height);
+        writer.write_bitdouble(self.line_spacing_factor);
+        writer.write_bitshort(self.line_spacing_style);
+        writer.data().write_cm_color(&self.color, codepage)?;
+        writer.write_bitshort(self.alignment);
+        writer.write_bitshort(self.flow_direction);
+        writer
+            .data()
+            .write_cm_color(&self.background_color, codepage)?;
+        writer.write_bitdouble(self.background_scale);
+        writer.write_bitlong(self.background_transparency);
+        writer.write_bit(self.background_enabled as u8);
+        writer.write_bit(self.background_mask_fill as u8);
+        writer.write_bitshort(self.column_type);
+        writer.write_bit(self.auto_height as u8);
+        writer.write_bitdouble(self.column_width);
+        writer.write_bitdouble(self.column_gutter);
+        writer.write_bit(self.columns_reversed as u8);
+        writer.write_bitlong(self.column_sizes.len() as i32);
+        self.column_sizes
+            .iter()
+            .for_each(|&size| writer.write_bitdouble(size));
+        writer.write_bit(self.word_break as u8);
+        writer.write_bit(self.unknown as u8);
+        Ok(())
+    }
+}
+
+impl DwgDecode for MLeaderBlock {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let block_header = reader.read_handle()?;
+        let data = reader.data();
+        let normal = data.read_3bd()?;
+        let location = data.read_3bd()?;
+        let scale = data.read_3bd()?;
+        let rotation = data.read_bitdouble()?;
+        let color = data.read_cm_color()?;
+        let mut transform = [0.0; 16];
+        for value in &mut transform {
+            *value = data.read_bitdouble()?;
+        }
+        Ok(Self {
+            block_header,
+            normal,
+            location,
+            scale,
+            rotation,
+            color,
+            transform,
+        })
+    }
+}
+
+impl DwgEncode for MLeaderBlock {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        let codepage = writer.codepage();
+        writer.write_handle(self.block_header);
+        writer.write_3bd(self.normal);
+        writer.write_3bd(self.location);
+        writer.write_3bd(self.scale);
+        writer.write_bitdouble(self.rotation);
+        writer.data().write_cm_color(&self.color, codepage)?;
+        self.transform
+            .iter()
+            .for_each(|&value| writer.write_bitdouble(value));
+        Ok(())
+    }
+}
+
+impl DwgDecode for MLeaderContext {
+    /// The content is flagged as text, then as a block if it is not text
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let num_roots = read_count(reader)?;
+        let roots = (0..num_roots)
+            .map(|_| LeaderRoot::decode(reader))
+            .collect::<Result<_>>()?;
+        let data = reader.data();
+        let scale = data.read_bitdouble()?;
+        let content_base = data.read_3bd()?;
+        let text_height = data.read_bitdouble()?;
+        let arrowhead_size = data.read_bitdouble()?;
+        let landing_gap = data.read_bitdouble()?;
+        let text_left_attachment = data.read_bitshort()?;
+        let text_right_attachment = data.read_bitshort()?;
+        let text_alignment = data.read_bitshort()?;
+        let attachment_type = data.read_bitshort()?;
+        let content = if data.read_bit()? == 1 {
+            MLeaderContent::MText(MLeaderText::decode(reader)?)
+        } else if reader.data().read_bit()? == 1 {
+            MLeaderContent::Block(MLeaderBlock::decode(reader)?)
+        } else {
+            MLeaderContent::None
+        };
+        let data = reader.data();
+        let base_point = data.read_3bd()?;
+        let base_direction = data.read_3bd()?;
+        let base_vertical = data.read_3bd()?;
+        let normal_reversed = data.read_bit()? == 1;
+        let (text_top_attachment, text_bottom_attachment) =
+            match data.get_version() >= DWGVersion::AC1024 {
+                true => (data.read_bitshort()?, data.read_bitshort()?),
+                false => (0, 0),
+            };
+        Ok(Self {
+            roots,
+            scale,
+            content_base,
+            text_height,
+            arrowhead_size,
+            landing_gap,
+            text_left_attachment,
+            text_right_attachment,
+            text_alignment,
+            attachment_type,
+            content,
+            base_point,
+            base_direction,
+            base_vertical,
+            normal_reversed,
+            text_top_attachment,
+            text_bottom_attachment,
+        })
+    }
+}
+
+impl DwgEncode for MLeaderContext {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        writer.write_bitlong(self.roots.len() as i32);
+        self.roots.iter().try_for_each(|root| root.encode(writer))?;
+        writer.write_bitdouble(self.scale);
+        writer.write_3bd(self.content_base);
+        writer.write_bitdouble(self.text_height);
+        writer.write_bitdouble(self.arrowhead_size);
+        writer.write_bitdouble(self.landing_gap);
+        writer.write_bitshort(self.text_left_attachment);
+        writer.write_bitshort(self.text_right_attachment);
+        writer.write_bitshort(self.text_alignment);
+        writer.write_bitshort(self.attachment_type);
+        match &self.content {
+            MLeaderContent::MText(text) => {
+                writer.write_bit(1);
+                text.encode(writer)?;
+            }
+            MLeaderContent::Block(block) => {
+                writer.write_bit(0);
+                writer.write_bit(1);
+                block.encode(writer)?;
+            }
+            MLeaderContent::None => {
+                writer.write_bit(0);
+                writer.write_bit(0);
+            }
+        }
+        writer.write_3bd(self.base_point);
+        writer.write_3bd(self.base_direction);
+        writer.write_3bd(self.base_vertical);
+        writer.write_bit(self.normal_reversed as u8);
+        if writer.get_version() >= DWGVersion::AC1024 {
+            writer.write_bitshort(self.text_top_attachment);
+            writer.write_bitshort(self.text_bottom_attachment);
+        }
+        Ok(())
+    }
+}
+
+impl DwgDecode for MultiLeader {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let header = CommonEntityData::decode(reader)?;
+        let version = reader.data().get_version();
+        let class_version = match version >= DWGVersion::AC1024 {
+            true => reader.data().read_bitshort()?,
+            false => 0,
+        };
+        let context = MLeaderContext::decode(reader)?;
+        let style = reader.read_handle()?;
+        let data = reader.data();
+        let override_flags = data.read_bitlong()?;
+        let leader_type = data.read_bitshort()?;
+        let line_color = data.read_cm_color()?;
+        let line_type = reader.read_handle()?;
+        let data = reader.data();
+        let line_weight = data.read_bitlong()?;
+        let landing_enabled = data.read_bit()? == 1;
+        let dogleg_enabled = data.read_bit()? == 1;
+        let landing_distance = data.read_bitdouble()?;
+        let arrowhead = reader.read_handle()?;
+        let data = reader.data();
+        let arrowhead_size = data.read_bitdouble()?;
+        let content_type = data.read_bitshort()?;
+        let text_style = reader.read_handle()?;
+        let data = reader.data();
+        let text_left_attachment = data.read_bitshort()?;
+        let text_right_attachment = data.read_bitshort()?;
+        let text_angle_type = data.read_bitshort()?;
+        let text_alignment = data.read_bitshort()?;
+        let text_color = data.read_cm_color()?;
+        let text_frame = data.read_bit()? == 1;
+        let block = reader.read_handle()?;
+        let data = reader.data();
+        let block_color = data.read_cm_color()?;
+        let block_scale = data.read_3bd()?;
+        let block_rotation = data.read_bitdouble()?;
+        let block_connection = data.read_bitshort()?;
+        let annotative = data.read_bit()? == 1;
+        let num_arrowheads = read_count(reader)?;
+        let arrowheads = (0..num_arrowheads)
+            .map(|_| Ok((reader.data().read_bit()? == 1, reader.read_handle()?)))
+            .collect::<Result<_>>()?;
+        let num_block_labels = read_count(reader)?;
+        let block_labels = (0..num_block_labels)
+            .map(|_| {
+                Ok(BlockLabel {
+                    attdef: reader.read_handle()?,
+                    text: reader.read_text()?,
+                    ui_index: reader.data().read_bitshort()?,
+                    width: reader.data().read_bitdouble()?,
+                })
+            })
+            .collect::<Result<_>>()?;
+        let data = reader.data();
+        let text_direction_negative = data.read_bit()? == 1;
+        let ipe_alignment = data.read_bitshort()?;
+        let justification = data.read_bitshort()?;
+        let scale_factor = data.read_bitdouble()?;
+        let (attachment_direction, text_top_attachment, text_bottom_attachment) =
+            match version >= DWGVersion::AC1024 {
+                true => (
+                    data.read_bitshort()?,
+                    data.read_bitshort()?,
+                    data.read_bitshort()?,
+                ),
+                false => (0, 0, 0),
+            };
+        let extended_to_text = match version >= DWGVersion::AC1027 {
+            true => data.read_bit()? == 1,
+            false => false,
+        };
+        Ok(Self {
+            header,
+            class_version,
+            context,
+            style,
+            override_flags,
+            leader_type,
+            line_color,
+            line_type,
+            line_weight,
+            landing_enabled,
+            dogleg_enabled,
+            landing_distance,
+            arrowhead,
+            arrowhead_size,
+            content_type,
+            text_style,
+            text_left_attachment,
+            text_right_attachment,
+            text_angle_type,
+            text_alignment,
+            text_color,
+            text_frame,
+            block,
+            block_color,
+            block_scale,
+            block_rotation,
+            block_connection,
+            annotative,
+            arrowheads,
+            block_labels,
+            text_direction_negative,
+            ipe_alignment,
+            justification,
+            scale_factor,
+            attachment_direction,
+            text_top_attachment,
+            text_bottom_attachment,
+            extended_to_text,
+        })
+    }
+}
+
+impl DwgEncode for MultiLeader {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        let version = writer.get_version();
+        let codepage = writer.codepage();
+        if version >= DWGVersion::AC1024 {
+            writer.write_bitshort(self.class_version);
+        }
+        self.context.encode(writer)?;
+        writer.write_handle(self.style);
+        writer.write_bitlong(self.override_flags);
+        writer.write_bitshort(self.leader_type);
+        writer.data().write_cm_color(&self.line_color, codepage)?;
+        writer.write_handle(self.line_type);
+        writer.write_bitlong(self.line_weight);
+        writer.write_bit(self.landing_enabled as u8);
+        writer.write_bit(self.dogleg_enabled as u8);
+        writer.write_bitdouble(self.landing_distance);
+        writer.write_handle(self.arrowhead);
+        writer.write_bitdouble(self.arrowhead_size);
+        writer.write_bitshort(self.content_type);
+        writer.write_handle(self.text_style);
+        writer.write_bitshort(self.text_left_attachment);
+        writer.write_bitshort(self.text_right_attachment);
+        writer.write_bitshort(self.text_angle_type);
+        writer.write_bitshort(self.text_alignment);
+        writer.data().write_cm_color(&self.text_color, codepage)?;
+        writer.write_bit(self.text_frame as u8);
+        writer.write_handle(self.block);
+        writer.data().write_cm_color(&self.block_color, codepage)?;
+        writer.write_3bd(self.block_scale);
+        writer.write_bitdouble(self.block_rotation);
+        writer.write_bitshort(self.block_connection);
+        writer.write_bit(self.annotative as u8);
+        writer.write_bitlong(self.arrowheads.len() as i32);
+        for &(is_default, arrowhead) in &self.arrowheads {
+            writer.write_bit(is_default as u8);
+            writer.write_handle(arrowhead);
+        }
+        writer.write_bitlong(self.block_labels.len() as i32);
+        for label in &self.block_labels {
+            writer.write_handle(label.attdef);
+            writer.write_text(&label.text)?;
+            writer.write_bitshort(label.ui_index);
+            writer.write_bitdouble(label.width);
+        }
+        writer.write_bit(self.text_direction_negative as u8);
+        writer.write_bitshort(self.ipe_alignment);
+        writer.write_bitshort(self.justification);
+        writer.write_bitdouble(self.scale_factor);
+        if version >= DWGVersion::AC1024 {
+            writer.write_bitshort(self.attachment_direction);
+            writer.write_bitshort(self.text_top_attachment);
+            writer.write_bitshort(self.text_bottom_attachment);
+        }
+        if version >= DWGVersion::AC1027 {
+            writer.write_bit(self.extended_to_text as u8);
+        }
+        Ok(())
+    }
+}
+
+/// A 2D polyline storing its vertices itself, in the plane normal to its extrusion at its
+/// elevation
+///
+/// Each optional value is only stored if its bit of `flags` is set, and left at its default
+/// otherwise
+#[derive(Clone, Debug, PartialEq)]
+pub struct LwPolyline {
+    pub header: CommonEntityData,
+    /// 1 if the extrusion is stored, 2 the thickness, 4 the constant width, 8 the elevation,
+    /// 0x10 the bulges, 0x20 the widths and 0x400 the vertex ids. 0x100 if the linetype is
+    /// continued through the vertices and 0x200 if the polyline is closed
+    pub flags: i16,
+    pub const_width: f64,
+    pub elevation: f64,
+    pub thickness: f64,
+    pub extrusion: Point3,
+    pub vertices: Vec<Point2>,
+    /// The bulge of the segment from each vertex, see [`Vertex2d::bulge`]
+    pub bulges: Vec<f64>,
+    /// Stored from AC1024 (R2010)
+    pub vertex_ids: Vec<i32>,
+    /// The start and end width of the segment from each vertex
+    pub widths: Vec<(f64, f64)>,
+}
+
+impl LwPolyline {
+    pub fn is_closed(&self) -> bool {
+        self.flags & 0x200 != 0
+    }
+}
+
+impl DwgDecode for LwPolyline {
+    /// From AC1015 (R2000) each vertex after the first is stored as a DD defaulting to the one
+    /// before it
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let header = CommonEntityData::decode(reader)?;
+        let data = reader.data();
+        let version = data.get_version();
+        let flags = data.read_bitshort()?;
+        let mut optional = |bit: i16| match flags & bit != 0 {
+            true => data.read_bitdouble().map(Some),
+            false => Ok(None),
+        };
+        let const_width = optional(0x4)?.unwrap_or(0.0);
+        let elevation = optional(0x8)?.unwrap_or(0.0);
+        let thickness = optional(0x2)?.unwrap_or(0.0);
+        let extrusion = match flags & 0x1 != 0 {
+            true => data.read_3bd()?,
+            false => Point3::from((0.0, 0.0, 1.0)),
+        };
+        let num_vertices = data.read_bitlong()?.max(0) as usize;
+        let mut count = |bit: i16| match flags & bit != 0 {
+            true => data.read_bitlong().map(|count| count.max(0) as usize),
+            false => Ok(0),
+        };
+        let num_bulges = count(0x10)?;
+        let num_ids = match version >= DWGVersion::AC1024 {
+            true => count(0x400)?,
+            false => 0,
+        };
+        let num_widths = count(0x20)?;
+
+        let mut vertices: Vec<Point2> = Vec::new();
+        for _ in 0..num_vertices {
+            let vertex = match vertices.last() {
+                Some(last) if version >= DWGVersion::AC1015 => Point2 {
+                    x: data.read_dd(last.x)?,
+                    y: data.read_dd(last.y)?,
+                },
+                _ => data.read_2rd()?,
+            };
+            vertices.push(vertex);
+        }
+        let bulges = (0..num_bulges)
+            .map(|_| data.read_bitdouble())
+            .collect::<Result<_>>()?;
+        let vertex_ids = (0..num_ids)
+            .map(|_| data.read_bitlong())
+            .collect::<Result<_>>()?;
+        let widths = (0..num_widths)
+            .map(|_| Ok((data.read_bitdouble()?, data.read_bitdouble()?)))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            header,
+            flags,
+            const_width,
+            elevation,
+            thickness,
+            extrusion,
+            vertices,
+            bulges,
+            vertex_ids,
+            widths,
+        })
+    }
+}
+
+impl DwgEncode for LwPolyline {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        let version = writer.get_version();
+        let flags = self.flags;
+        writer.write_bitshort(flags);
+        if flags & 0x4 != 0 {
+            writer.write_bitdouble(self.const_width);
+        }
+        if flags & 0x8 != 0 {
+            writer.write_bitdouble(self.elevation);
+        }
+        if flags & 0x2 != 0 {
+            writer.write_bitdouble(self.thickness);
+        }
+        if flags & 0x1 != 0 {
+            writer.write_3bd(self.extrusion);
+        }
+        writer.write_bitlong(self.vertices.len() as i32);
+        let r2010 = version >= DWGVersion::AC1024;
+        let bulges = flags & 0x10 != 0;
+        let ids = flags & 0x400 != 0 && r2010;
+        let widths = flags & 0x20 != 0;
+        if bulges {
+            writer.write_bitlong(self.bulges.len() as i32);
+        }
+        if ids {
+            writer.write_bitlong(self.vertex_ids.len() as i32);
+        }
+        if widths {
+            writer.write_bitlong(self.widths.len() as i32);
+        }
+
+        let mut last: Option<Point2> = None;
+        for &vertex in &self.vertices {
+            match last {
+                Some(last) if version >= DWGVersion::AC1015 => {
+                    writer.write_dd(vertex.x, last.x);
+                    writer.write_dd(vertex.y, last.y);
+                }
+                _ => writer.write_2rd(vertex),
+            }
+            last = Some(vertex);
+        }
+        if bulges {
+            self.bulges
+                .iter()
+                .for_each(|&bulge| writer.write_bitdouble(bulge));
+        }
+        if ids {
+            self.vertex_ids
+                .iter()
+                .for_each(|&id| writer.write_bitlong(id));
+        }
+        if widths {
+            for &(start, end) in &self.widths {
+                writer.write_bitdouble(start);
+                writer.write_bitdouble(end);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A spline, defined either by its control points or by points it is fit through
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spline {
+    pub header: CommonEntityData,
+    /// Stored from AC1027 (R2013), 1 if the spline was created from fit points, 2 if its
+    /// control frame is shown and 4 if it is closed
+    pub spline_flags: i32,
+    /// Stored from AC1027 (R2013), how the knots were parameterized: 0 for chord length, 1
+    /// for square root of chord length, 2 for uniform and 15 for custom
+    pub knot_parameter: i32,
+    pub degree: i32,
+    pub data: SplineData,
+}
+
+/// The points defining a [`Spline`], stored after a scenario of 1 for control points or 2
+/// for fit points
+#[derive(Clone, Debug, PartialEq)]
+pub enum SplineData {
+    Control {
+        rational: bool,
+        closed: bool,
+        periodic: bool,
+        knot_tolerance: f64,
+        control_tolerance: f64,
+        knots: Vec<f64>,
+        control_points: Vec<Point3>,
+        /// The weight of each control point, empty if the spline is not weighted
+        weights: Vec<f64>,
+    },
+    Fit {
+        fit_tolerance: f64,
+        start_tangent: Point3,
+        end_tangent: Point3,
+        fit_points: Vec<Point3>,
+    },
+}
+
+impl DwgDecode for Spline {
+    /// Fails with `InvalidFlag` if the scenario is neither 1 nor 2
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let header = CommonEntityData::decode(reader)?;
+        let data = reader.data();
+        let at_bit = data.bit_position();
+        let scenario = data.read_bitlong()?;
+        let (spline_flags, knot_parameter) = match data.get_version() >= DWGVersion::AC1027 {
+            true => (data.read_bitlong()?, data.read_bitlong()?),
+            false => (0, 0),
+        };
+        let degree = data.read_bitlong()?;
+        let spline_data = match scenario {
+            1 => {
+                let rational = data.read_bit()? == 1;
+                let closed = data.read_bit()? == 1;
+                let periodic = data.read_bit()? == 1;
+                let knot_tolerance = data.read_bitdouble()?;
+                let control_tolerance = data.read_bitdouble()?;
+                let num_knots = data.read_bitlong()?.max(0) as usize;
+                let num_control_points = data.read_bitlong()?.max(0) as usize;
+                let weighted = data.read_bit()? == 1;
+                let knots = (0..num_knots)
+                    .map(|_| data.read_bitdouble())
+                    .collect::<Result<_>>()?;
+                let mut control_points = Vec::new();
+                let mut weights = Vec::new();
+                for _ in 0..num_control_points {
+                    control_points.push(data.read_3bd()?);
+                    if weighted {
+                        weights.push(data.read_bitdouble()?);
+                    }
+                }
+                SplineData::Control {
+                    rational,
+                    closed,
+                    periodic,
+                    knot_tolerance,
+                    control_tolerance,
+                    knots,
+                    control_points,
+                    weights,
+                }
+            }
+            2 => {
+                let fit_tolerance = data.read_bitdouble()?;
+                let start_tangent = data.read_3bd()?;
+                let end_tangent = data.read_3bd()?;
+                let num_fit_points = data.read_bitlong()?.max(0) as usize;
+                SplineData::Fit {
+                    fit_tolerance,
+                    start_tangent,
+                    end_tangent,
+                    fit_points: (0..num_fit_points)
+                        .map(|_| data.read_3bd())
+                        .collect::<Result<_>>()?,
+                }
+            }
+            _ => {
+                return Err(DwgError::InvalidFlag {
+                    at_bit,
+                    value: scenario as u64,
+                })
+            }
+        };
+        Ok(Self {
+            header,
+            spline_flags,
+            knot_parameter,
+            degree,
+            data: spline_data,
+        })
+    }
+}
+
+impl DwgEncode for Spline {
+    /// Control points are weighted if any weight is given, with a weight for each
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        let scenario = match self.data {
+            SplineData::Control { .. } => 1,
+            SplineData::Fit { .. } => 2,
+        };
+        writer.write_bitlong(scenario);
+        if writer.get_version() >= DWGVersion::AC1027 {
+            writer.write_bitlong(self.spline_flags);
+            writer.write_bitlong(self.knot_parameter);
+        }
+        writer.write_bitlong(self.degree);
+        match &self.data {
+            SplineData::Control {
+                rational,
+                closed,
+                periodic,
+                knot_tolerance,
+                control_tolerance,
+                knots,
+                control_points,
+                weights,
+            } => {
+                writer.write_bit(*rational as u8);
+                writer.write_bit(*closed as u8);
+                writer.write_bit(*periodic as u8);
+                writer.write_bitdouble(*knot_tolerance);
+                writer.write_bitdouble(*control_tolerance);
+                writer.write_bitlong(knots.len() as i32);
+                writer.write_bitlong(control_points.len() as i32);
+                writer.write_bit(!weights.is_empty() as u8);
+                knots.iter().for_each(|&knot| writer.write_bitdouble(knot));
+                for (i, &point) in control_points.iter().enumerate() {
+                    writer.write_3bd(point);
+                    if !weights.is_empty() {
+                        writer.write_bitdouble(weights.get(i).copied().unwrap_or(1.0));
+                    }
+                }
+            }
+            SplineData::Fit {
+                fit_tolerance,
+                start_tangent,
+                end_tangent,
+                fit_points,
+            } => {
+                writer.write_bitdouble(*fit_tolerance);
+                writer.write_3bd(*start_tangent);
+                writer.write_3bd(*end_tangent);
+                writer.write_bitlong(fit_points.len() as i32);
+                fit_points.iter().for_each(|&point| writer.write_3bd(point));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Dwg {
+    /// Returns the handles of the ATTRIBs of an INSERT of the drawing, in order
+    ///
+    /// Before AC1018 (R2004) they are a linked list from the first to the last ATTRIB the
+    /// insert stores, ending early at its SEQEND. Later versions list every ATTRIB. Fails with
+    /// `MissingObject` if an ATTRIB in the list cannot be found
+    pub fn insert_attribs(&self, insert: &Insert) -> Result<Vec<u64>> {
+        let Some(seqend) = insert.seqend else {
+            return Ok(Vec::new());
+        };
+        let owned = (insert.first_last_attribs, &insert.attribs[..], seqend);
+        self.owned_entities(insert.header.handle, owned)
+    }
+
+    /// Returns the handles of the vertices of a polyline of the drawing with the handle
+    /// `polyline`, in order, the same way as [`Dwg::insert_attribs`]
+    pub fn polyline_vertices(&self, polyline: u64, owned: &OwnedVertices) -> Result<Vec<u64>> {
+        let owned = (owned.first_last, &owned.vertices[..], owned.seqend);
+        self.owned_entities(polyline, owned)
+    }
+
+    /// Reads the 2D polyline with the handle `polyline` along with its vertices
+    ///
+    /// Fails with `MissingObject` if the polyline or a vertex cannot be found, and with
+    /// `InvalidFlag` if one is not of the type expected
+    pub fn polyline_2d(&self, polyline: u64) -> Result<Polyline<Polyline2d, Vertex2d>> {
+        let polyline: Polyline2d = self.typed_entity(polyline, object_types::POLYLINE_2D)?;
+        let vertices =
+            self.read_vertices(&polyline.header, &polyline.owned, object_types::VERTEX_2D)?;
+        Ok(Polyline { polyline, vertices })
+    }
+
+    /// Reads the 3D polyline with the handle `polyline` along with its vertices, failing the
+    /// same way as [`Dwg::polyline_2d`]
+    pub fn polyline_3d(&self, polyline: u64) -> Result<Polyline<Polyline3d, Vertex3d>> {
+        let polyline: Polyline3d = self.typed_entity(polyline, object_types::POLYLINE_3D)?;
+        let vertices =
+            self.read_vertices(&polyline.header, &polyline.owned, object_types::VERTEX_3D)?;
+        Ok(Polyline { polyline, vertices })
+    }
+
+    /// Reads the polygon mesh with the handle `polyline` along with its grid of vertices,
+    /// failing the same way as [`Dwg::polyline_2d`]
+    pub fn polygon_mesh(&self, polyline: u64) -> Result<Polyline<PolylineMesh, Vertex3d>> {
+        let polyline: PolylineMesh = self.typed_entity(polyline, object_types::POLYLINE_MESH)?;
+        let vertices =
+            self.read_vertices(&polyline.header, &polyline.owned, object_types::VERTEX_MESH)?;
+        Ok(Polyline { polyline, vertices })
+    }
+
+    /// Reads the MULTILEADER with the handle `handle`
+    ///
+    /// Fails with `MissingObject` if there is no such object, and with `InvalidFlag` if it is
+    /// not of the type the drawing defines MULTILEADER as
+    pub fn multileader(&self, handle: u64) -> Result<MultiLeader> {
+        self.decode_class_object(handle, "MULTILEADER")
+    }
+
+    /// Reads the polyface mesh with the handle `polyline` along with its vertices and faces,
+    /// failing the same way as [`Dwg::polyline_2d`]
+    pub fn polyface_mesh(&self, polyline: u64) -> Result<PolyfaceMesh> {
+        let polyline: PolylinePface = self.typed_entity(polyline, object_types::POLYLINE_PFACE)?;
+        let mut vertices = Vec::new();
+        let mut faces = Vec::new();
+        for handle in self.polyline_vertices(polyline.header.handle, &polyline.owned)? {
+            let data = self
+                .get_object(handle)
+                .ok_or(DwgError::MissingObject { handle })?;
+            match Entity::decode(data, self.version(), self.codepage)? {
+                Entity::VertexPface(vertex) => vertices.push(vertex),
+                Entity::FaceRecord(face) => faces.push(face),
+                entity => {
+                    return Err(DwgError::InvalidFlag {
+                        at_bit: 0,
+                        value: entity.object_type() as u16 as u64,
+                    })
+                }
+            }
+        }
+        Ok(PolyfaceMesh {
+            polyline,
+            vertices,
+            faces,
+        })
+    }
+
+    fn read_vertices<V: DwgDecode>(
+        &self,
+        polyline: &CommonEntityData,
+        owned: &OwnedVertices,
+        vertex_type: i16,
+    ) -> Result<Vec<V>> {
+        let vertices = self.polyline_vertices(polyline.handle, owned)?;
+        vertices
+            .into_iter()
+            .map(|vertex| self.typed_entity(vertex, vertex_type))
+            .collect()
+    }
+
+    /// Returns the entities an entity owns, given as the first and last before AC1018 (R2004),
+    /// every entity from AC1018 and the SEQEND ending them
+    fn owned_entities(&self, owner: u64, owned: OwnedHandles) -> Result<Vec<u64>> {
+        let (first_last, entities, seqend) = owned;
+        if self.version() >= DWGVersion::AC1018 {
+            let entities = entities.iter();
+            return Ok(entities.map(|entity| entity.absolute(owner)).collect());
+        }
+        let Some((first, last)) = first_last else {
+            return Ok(Vec::new());
+        };
+        self.linked_entities(
+            first.absolute(owner),
+            last.absolute(owner),
+            seqend.absolute(owner),
+        )
+    }
+
+    fn typed_entity<T: DwgDecode>(&self, handle: u64, object_type: i16) -> Result<T> {
+        let data = self
+            .get_object(handle)
+            .ok_or(DwgError::MissingObject { handle })?;
+        decode_object(data, object_type, self.version(), self.codepage)
+    }
+}
+
+/// The first and last owned entities, every owned entity and the SEQEND ending them
+type OwnedHandles<'a> = (Option<(HandleRef, HandleRef)>, &'a [HandleRef], HandleRef);
+
+/// Returns the common data of a model space entity on layer 0x10 in tests, with null links
+/// to the previous and next entities before AC1018 (R2004)
+#[cfg(test)]
+fn entity_header(handle: u64, version: DWGVersion) -> CommonEntityData {
+    let mut header = CommonEntityData::new(handle, 2, None, 0x10);
+    if version < DWGVersion::AC1018 {
+        let null = HandleRef::new(RefType::SoftPointer, 0);
+        header.links = Some((null, null));
+    }
+    header
+}
+
+/// Encodes an entity of `object_type` in tests
+#[cfg(test)]
+fn encode_entity(object_type: i16, entity: &impl DwgEncode, version: DWGVersion) -> Vec<u8> {
+    crate::objects::encode_object(object_type, entity, version, CodePage::ANSI1252).unwrap()
+}
+
+/// Decodes an entity in tests, see [`Entity::decode`]
+#[cfg(test)]
+fn decode_entity(data: &[u8], version: DWGVersion) -> Result<Entity> {
+    Entity::decode(data, version, CodePage::ANSI1252)
+}
+
+/// Encodes an entity of `object_type` and decodes it again in tests
+#[cfg(test)]
+fn round_trip(object_type: i16, entity: &impl DwgEncode, version: DWGVersion) -> Result<Entity> {
+    decode_entity(&encode_entity(object_type, entity, version), version)
+}
+
+/// Packs an AC1015 (R2000) or AC1018 (R2004) entity of `object_type`, with the common data of
+/// [`entity_header`] with handle 0x30 followed by `data`, and `handles` after those of the
+/// common data
+#[cfg(test)]
+fn pack_entity(version: DWGVersion, object_type: i16, data: Bits, handles: Bits) -> Vec<u8> {
+    let r2004 = version >= DWGVersion::AC1018;
+    let common = Bits::default()
+        .h(0, 0x30)
+        // No EED or proxy graphics, in model space and with no reactors
+        .bs(0)
+        .b(false)
+        .bb(2)
+        .bl(0)
+        // From R2004 the extension dictionary is missing, before then the links are stored
+        .b(r2004)
+        // ByLayer color, linetype scale 1, ByLayer linetype and plot style
+        .bs(256)
+        .bd(1.0)
+        .bb(0)
+        .bb(0)
+        // Visible, with ByLayer lineweight
+        .bs(0)
+        .rc(29);
+    let common_handles = match r2004 {
+        true => Bits::default(),
+        // No extension dictionary, then the null previous and next entities
+        false => Bits::default().h(3, 0).h(4, 0).h(4, 0),
+    };
+    let start = Bits::default().bs(object_type);
+    let bitsize = start.len() + 32 + common.len() + data.len();
+    start
+        .rl(bitsize as i32)
+        .then(common)
+        .then(data)
+        .then(common_handles.h(5, 0x10))
+        .then(handles)
+        .pack()
+}
+
+#[test]
+fn test_line() {
+    for version in [
+        DWGVersion::AC1014,
+        DWGVersion::AC1015,
+        DWGVersion::AC1018,
+        DWGVersion::AC1024,
+    ] {
+        let flat = Line {
+            header: entity_header(0x30, version),
+            start: Point3::from((1.0, 2.0, 0.0)),
+            end: Point3::from((1.0, 5.0, 0.0)),
+            thickness: 0.0,
+            extrusion: Point3::from((0.0, 0.0, 1.0)),
+        };
+        let raised = Line {
+            start: Point3::from((1.0, 2.0, 3.0)),
+            end: Point3::from((-4.5, 2.0, 3.0)),
+            thickness: 2.5,
+            extrusion: Point3::from((0.0, 1.0, 0.0)),
+            ..flat.clone()
+        };
+        for line in [&flat, &raised] {
+            let decoded = round_trip(object_types::LINE, line, version);
+            assert_eq!(decoded, Ok(Entity::Line(line.clone())), "{version:?}");
+        }
+        if version >= DWGVersion::AC1015 {
+            // Leaving out the z coordinates saves an RD and a DD
+            let mut lifted = flat.clone();
+            lifted.start.z = 3.0;
+            lifted.end.z = 3.0;
+            assert_eq!(
+                encode_entity(object_types::LINE, &flat, version).len() + 8,
+                encode_entity(object_types::LINE, &lifted, version).len()
+            );
+        }
+
+        // Entities of other types only have their common data decoded
+        let entity = round_trip(object_types::ELLIPSE, &flat, version).unwrap();
+        assert!(matches!(entity, Entity::Other(_)));
+        assert_eq!(entity.object_type(), object_types::ELLIPSE);
+        assert_eq!(entity.header(), &flat.header);
+    }
+
+    // The end x is the same as the start x, so is stored as a DD with no bytes
+    let version = DWGVersion::AC1015;
+    let data = Bits::default()
+        .b(true)
+        .rd(1.0)
+        .dd(1.0, 1.0)
+        .rd(2.0)
+        .dd(5.0, 2.0)
+        .bt(0.0)
+        .be((0.0, 0.0, 1.0));
+    let expected = pack_entity(version, object_types::LINE, data, Bits::default());
+    let line = Line {
+        header: entity_header(0x30, version),
+        start: Point3::from((1.0, 2.0, 0.0)),
+        end: Point3::from((1.0, 5.0, 0.0)),
+        thickness: 0.0,
+        extrusion: Point3::from((0.0, 0.0, 1.0)),
+    };
+    assert_eq!(encode_entity(object_types::LINE, &line, version), expected);
+    assert_eq!(decode_entity(&expected, version), Ok(Entity::Line(line)));
+}
+
+#[test]
+#[ignore = "needs test_data/Line.dwg"]
+fn test_line_dwg() {
+    let mut d = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    d.push("test_data/Line.dwg");
+
+    let dwg = Dwg::from_bytes(&std::fs::read(d).unwrap()).unwrap();
+    let lines: Vec<_> = dwg
+        .objects
+        .values()
+        .filter(|data| {
+            let object_type = open_object(data, dwg.version(), dwg.codepage).map(|(t, _)| t);
+            object_type == Ok(object_types::LINE)
+        })
+        .map(|data| Entity::decode(data, dwg.version(), dwg.codepage).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 1);
+    assert!(matches!(lines[0], Entity::Line(_)));
+}
+
+#[test]
+fn test_point() {
+    for version in [DWGVersion::AC1014, DWGVersion::AC1018, DWGVersion::AC1027] {
+        let point = Point {
+            header: entity_header(0x30, version),
+            position: Point3::from((1.5, -2.0, 0.0)),
+            thickness: 0.0,
+            extrusion: Point3::from((0.0, 0.0, 1.0)),
+            x_axis_angle: 0.0,
+        };
+        let tilted = Point {
+            position: Point3::from((1.5, -2.0, 7.25)),
+            thickness: 3.0,
+            extrusion: Point3::from((1.0, 0.0, 0.0)),
+            x_axis_angle: std::f64::consts::FRAC_PI_2,
+            ..point.clone()
+        };
+        for point in [point, tilted] {
+            let entity = round_trip(object_types::POINT, &point, version);
+            assert_eq!(entity, Ok(Entity::Point(point)), "{version:?}");
+        }
+    }
+
+    let version = DWGVersion::AC1015;
+    let data = Bits::default()
+        .p3((1.5, -2.0, 0.0))
+        .bt(3.0)
+        .be((1.0, 0.0, 0.0))
+        .bd(0.0);
+    let expected = pack_entity(version, object_types::POINT, data, Bits::default());
+    let point = Point {
+        header: entity_header(0x30, version),
+        position: Point3::from((1.5, -2.0, 0.0)),
+        thickness: 3.0,
+        extrusion: Point3::from((1.0, 0.0, 0.0)),
+        x_axis_angle: 0.0,
+    };
+    assert_eq!(
+        encode_entity(object_types::POINT, &point, version),
+        expected
+    );
+    assert_eq!(decode_entity(&expected, version), Ok(Entity::Point(point)));
+}
+
+#[test]
+fn test_circle() {
+    for version in [
+        DWGVersion::AC1014,
+        DWGVersion::AC1015,
+        DWGVersion::AC1018,
+        DWGVersion::AC1024,
+    ] {
+        let circle = Circle {
+            header: entity_header(0x30, version),
+            center: Point3::from((10.0, 20.0, 0.0)),
+            radius: 2.5,
+            thickness: 0.0,
+            extrusion: Point3::from((0.0, 0.0, 1.0)),
+        };
+        let data = encode_entity(object_types::CIRCLE, &circle, version);
+        if version == DWGVersion::AC1018 {
+            let bits = Bits::default()
+                .p3((10.0, 20.0, 0.0))
+                .bd(2.5)
+                .bt(0.0)
+                .be((0.0, 0.0, 1.0));
+            let expected = pack_entity(version, object_types::CIRCLE, bits, Bits::default());
+            assert_eq!(data, expected);
+        }
+        let entity = decode_entity(&data, version).unwrap();
+        assert_eq!(entity.object_type(), object_types::CIRCLE);
+        assert_eq!(entity, Entity::Circle(circle), "{version:?}");
+    }
+}
+
+#[test]
+fn test_arc() {
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    for version in [
+        DWGVersion::AC1014,
+        DWGVersion::AC1015,
+        DWGVersion::AC1018,
+        DWGVersion::AC1024,
+    ] {
+        let arc = Arc {
+            header: entity_header(0x30, version),
+            center: Point3::from((1.0, 1.0, 0.0)),
+            radius: 4.0,
+            thickness: 0.0,
+            extrusion: Point3::from((0.0, 0.0, 1.0)),
+            start_angle: FRAC_PI_2,
+            end_angle: PI,
+        };
+        let data = encode_entity(object_types::ARC, &arc, version);
+        if version == DWGVersion::AC1015 {
+            let bits = Bits::default()
+                .p3((1.0, 1.0, 0.0))
+                .bd(4.0)
+                .bt(0.0)
+                .be((0.0, 0.0, 1.0))
+                .bd(FRAC_PI_2)
+                .bd(PI);
+            let expected = pack_entity(version, object_types::ARC, bits, Bits::default());
+            assert_eq!(data, expected);
+        }
+        assert_eq!(decode_entity(&data, version), Ok(Entity::Arc(arc.clone())));
+
+        // Angles out of range are brought into it
+        let unnormalized = Arc {
+            start_angle: -FRAC_PI_2,
+            end_angle: 5.0 * PI,
+            ..arc.clone()
+        };
+        let Ok(Entity::Arc(read)) = round_trip(object_types::ARC, &unnormalized, version) else {
+            panic!("{version:?}");
+        };
+        assert!((read.start_angle - 3.0 * FRAC_PI_2).abs() < 1e-12);
+        assert!((read.end_angle - PI).abs() < 1e-12);
+    }
+    assert_eq!(normalize_angle(-1e-20), 0.0);
+    assert_eq!(normalize_angle(2.0 * std::f64::consts::TAU), 0.0);
+}
+
+#[test]
+fn test_text() {
+    for version in [
+        DWGVersion::AC1014,
+        DWGVersion::AC1015,
+        DWGVersion::AC1018,
+        DWGVersion::AC1024,
+    ] {
+        let plain = Text {
+            header: entity_header(0x30, version),
+            insertion: Point3::from((3.0, 4.0, 0.0)),
+            alignment: Point2::default(),
+            extrusion: Point3::from((0.0, 0.0, 1.0)),
+            thickness: 0.0,
+            oblique_angle: 0.0,
+            rotation: 0.0,
+            height: 2.5,
+            width_factor: 1.0,
+            value: String::from("Room 101"),
+            generation: 0,
+            horizontal_alignment: 0,
+            vertical_alignment: 0,
+            style: HandleRef::new(RefType::HardPointer, 0x11),
+        };
+        // Every value the data flags can leave out is stored
+        let styled = Text {
+            insertion: Point3::from((3.0, 4.0, 1.5)),
+            alignment: Point2 { x: 3.0, y: 9.0 },
+            oblique_angle: 0.25,
+            rotation: std::f64::consts::FRAC_PI_4,
+            width_factor: 0.8,
+            generation: 2,
+            horizontal_alignment: 1,
+            vertical_alignment: 3,
+            ..plain.clone()
+        };
+        for text in [&plain, &styled] {
+            let decoded = round_trip(object_types::TEXT, text, version);
+            assert_eq!(decoded, Ok(Entity::Text(text.clone())), "{version:?}");
+        }
+        if version >= DWGVersion::AC1015 {
+            assert!(
+                encode_entity(object_types::TEXT, &plain, version).len()
+                    < encode_entity(object_types::TEXT, &styled, version).len()
+            );
+        }
+        if version == DWGVersion::AC1015 {
+            // Every bit of the data flags is set, leaving only the height and value
+            let data = Bits::default()
+                .rc(0xFF)
+                .p2((3.0, 4.0))
+                .be((0.0, 0.0, 1.0))
+                .bt(0.0)
+                .rd(2.5)
+                .tv("Room 101");
+            let style = Bits::default().h(5, 0x11);
+            let expected = pack_entity(version, object_types::TEXT, data, style);
+            assert_eq!(encode_entity(object_types::TEXT, &plain, version), expected);
+            assert_eq!(decode_entity(&expected, version), Ok(Entity::Text(plain)));
+        }
+    }
+}
+
+#[test]
+fn test_mtext() {
+    for version in [
+        DWGVersion::AC1014,
+        DWGVersion::AC1015,
+        DWGVersion::AC1018,
+        DWGVersion::AC1021,
+        DWGVersion::AC1024,
+    ] {
+        let mut mtext = MText {
+            header: entity_header(0x30, version),
+            insertion: Point3::from((1.0, 2.0, 0.0)),
+            extrusion: Point3::from((0.0, 0.0, 1.0)),
+            x_axis_direction: Point3::from((1.0, 0.0, 0.0)),
+            rect_width: 40.0,
+            rect_height: 0.0,
+            text_height: 2.5,
+            attachment: 1,
+            drawing_direction: 1,
+            extents_height: 5.0,
+            extents_width: 38.5,
+            value: String::from("{\\fArial|b1;Notes}\\PAll dimensions in mm"),
+            linespacing_style: 1,
+            linespacing_factor: 1.0,
+            unknown: false,
+            background_flags: 0,
+            background_scale: 1.5,
+            background_color: CmColor::default(),
+            background_transparency: 0,
+            style: HandleRef::new(RefType::HardPointer, 0x11),
+        };
+        if version >= DWGVersion::AC1015 {
+            mtext.linespacing_style = 2;
+            mtext.linespacing_factor = 1.25;
+        }
+        if version >= DWGVersion::AC1018 {
+            mtext.background_flags = 1;
+            mtext.background_scale = 1.25;
+            mtext.background_color.rgb = 0xC2FF8000;
+            mtext.background_transparency = 0x2000000;
+        }
+        if version >= DWGVersion::AC1021 {
+            mtext.rect_height = 12.0;
+        }
+        let data = encode_entity(object_types::MTEXT, &mtext, version);
+        if version == DWGVersion::AC1015 {
+            let bits = Bits::default()
+                .p3((1.0, 2.0, 0.0))
+                .p3((0.0, 0.0, 1.0))
+                .p3((1.0, 0.0, 0.0))
+                .bd(40.0)
+                .bd(2.5)
+                .bs(1)
+                .bs(1)
+                .bd(5.0)
+                .bd(38.5)
+                .tv(&mtext.value)
+                .bs(2)
+                .bd(1.25)
+                .b(false);
+            let style = Bits::default().h(5, 0x11);
+            assert_eq!(data, pack_entity(version, object_types::MTEXT, bits, style));
+        }
+        let decoded = decode_entity(&data, version);
+        assert_eq!(decoded, Ok(Entity::MText(mtext)), "{version:?}");
+    }
+}
+
+#[test]
+fn test_attrib() {
+    for version in [
+        DWGVersion::AC1014,
+        DWGVersion::AC1018,
+        DWGVersion::AC1021,
+        DWGVersion::AC1024,
+        DWGVersion::AC1032,
+    ] {
+        let mut header = entity_header(0x31, version);
+        header.entmode = 0;
+        header.owner = Some(HandleRef::new(RefType::SoftPointer, 0x30));
+        let mut attrib = Attrib {
+            text: Text {
+                header,
+                insertion: Point3::from((3.0, 4.0, 0.0)),
+                alignment: Point2::default(),
+                extrusion: Point3::from((0.0, 0.0, 1.0)),
+                thickness: 0.0,
+                oblique_angle: 0.0,
+                rotation: 0.0,
+                height: 2.5,
+                width_factor: 1.0,
+                value: String::from("D-101"),
+                generation: 0,
+                horizontal_alignment: 0,
+                vertical_alignment: 0,
+                style: HandleRef::new(RefType::HardPointer, 0x11),
+            },
+            class_version: 0,
+            multiline: None,
+            tag: String::from("DOOR_NUMBER"),
+            field_length: 0,
+            flags: 8,
+            lock_position: false,
+        };
+        if version >= DWGVersion::AC1021 {
+            attrib.lock_position = true;
+        }
+        let decoded = round_trip(object_types::ATTRIB, &attrib, version);
+        assert_eq!(decoded, Ok(Entity::Attrib(attrib.clone())), "{version:?}");
+
+        let mut attdef = AttDef {
+            attrib,
+            attdef_class_version: 0,
+            prompt: String::from("Door number"),
+        };
+        attdef.attrib.flags = 4;
+        if version >= DWGVersion::AC1032 {
+            attdef.attrib.multiline = Some(MultilineAttribute {
+                attribute_type: 4,
+                mtext: HandleRef::new(RefType::HardOwned, 0x32),
+                annotative_data_size: 2,
+                annotative_data: Some((1, HandleRef::new(RefType::HardPointer, 0x12), 0)),
+            });
+        }
+        let decoded = round_trip(object_types::ATTDEF, &attdef, version);
+        assert_eq!(decoded, Ok(Entity::AttDef(attdef)), "{version:?}");
+    }
+
+    // The data of a TEXT is followed by the tag, field length and flags, then the prompt of a
+    // definition
+    let version = DWGVersion::AC1015;
+    let text = Bits::default()
+        .rc(0xFF)
+        .p2((3.0, 4.0))
+        .be((0.0, 0.0, 1.0))
+        .bt(0.0)
+        .rd(2.5)
+        .tv("D-101");
+    let attrib = text.tv("DOOR_NUMBER").bs(0).rc(8);
+    let style = Bits::default().h(5, 0x11);
+    let expected = pack_entity(version, object_types::ATTRIB, attrib.clone(), style.clone());
+    let Ok(Entity::Attrib(decoded)) = decode_entity(&expected, version) else {
+        panic!("not an ATTRIB");
+    };
+    assert_eq!(decoded.text.header, entity_header(0x30, version));
+    assert_eq!(decoded.text.value, "D-101");
+    assert_eq!((decoded.tag.as_str(), decoded.flags), ("DOOR_NUMBER", 8));
+    assert_eq!(
+        encode_entity(object_types::ATTRIB, &decoded, version),
+        expected
+    );
+
+    let attdef = attrib.tv("Door number");
+    let expected = pack_entity(version, object_types::ATTDEF, attdef, style);
+    let attdef = AttDef {
+        attrib: decoded,
+        attdef_class_version: 0,
+        prompt: String::from("Door number"),
+    };
+    assert_eq!(
+        encode_entity(object_types::ATTDEF, &attdef, version),
+        expected
+    );
+    assert_eq!(
+        decode_entity(&expected, version),
+        Ok(Entity::AttDef(attdef))
+    );
+}
+
+#[test]
+fn test_block() {
+    for version in [
+        DWGVersion::AC1014,
+        DWGVersion::AC1015,
+        DWGVersion::AC1018,
+        DWGVersion::AC1024,
+    ] {
+        let mut header = entity_header(0x20, version);
+        header.entmode = 0;
+        header.owner = Some(HandleRef::new(RefType::SoftPointer, 0x1F));
+        let block = Block {
+            header: header.clone(),
+            name: String::from("*Model_Space"),
+        };
+        let endblk = EndBlk { header };
+        let decoded = round_trip(object_types::BLOCK, &block, version);
+        assert_eq!(decoded, Ok(Entity::Block(block)), "{version:?}");
+        let decoded = round_trip(object_types::ENDBLK, &endblk, version);
+        assert_eq!(decoded, Ok(Entity::EndBlk(endblk)), "{version:?}");
+    }
+
+    // A BLOCK only adds its name to the common data, and an ENDBLK adds nothing
+    let version = DWGVersion::AC1015;
+    let name = Bits::default().tv("*Model_Space");
+    let expected = pack_entity(version, object_types::BLOCK, name, Bits::default());
+    let block = Block {
+        header: entity_header(0x30, version),
+        name: String::from("*Model_Space"),
+    };
+    assert_eq!(
+        encode_entity(object_types::BLOCK, &block, version),
+        expected
+    );
+    assert_eq!(decode_entity(&expected, version), Ok(Entity::Block(block)));
+    let expected = pack_entity(
+        version,
+        object_types::ENDBLK,
+        Bits::default(),
+        Bits::default(),
+    );
+    let endblk = EndBlk {
+        header: entity_header(0x30, version),
+    };
+    assert_eq!(
+        encode_entity(object_types::ENDBLK, &endblk, version),
+        expected
+    );
+    assert_eq!(
+        decode_entity(&expected, version),
+        Ok(Entity::EndBlk(endblk))
+    );
+}
+
+#[test]
+fn test_insert() {
+    let soft = |handle| HandleRef::new(RefType::SoftPointer, handle);
+    let owned = |handle| HandleRef::new(RefType::HardOwned, handle);
+    for version in [DWGVersion::AC1014, DWGVersion::AC1015, DWGVersion::AC1018] {
+        let plain = Insert {
+            header: entity_header(0x30, version),
+            insertion: Point3::from((5.0, 5.0, 0.0)),
+            scale: Point3::from((1.0, 1.0, 1.0)),
+            rotation: 0.0,
+            extrusion: Point3::from((0.0, 0.0, 1.0)),
+            block_header: HandleRef::new(RefType::HardPointer, 0x20),
+            first_last_attribs: None,
+            attribs: Vec::new(),
+            seqend: None,
+        };
+        let mut with_attribs = Insert {
+            seqend: Some(owned(0x43)),
+            ..plain.clone()
+        };
+        if version < DWGVersion::AC1018 {
+            with_attribs.first_last_attribs = Some((soft(0x41), soft(0x42)));
+        } else {
+            with_attribs.attribs = vec![owned(0x41), owned(0x42)];
+        }
+        // Every way of storing the scale
+        let scales = [
+            (2.0, 2.0, 2.0),
+            (1.0, 2.0, 1.0),
+            (0.5, -0.5, 3.0),
+            (1.0, 1.0, 1.0),
+        ];
+        for scale in scales {
+            for insert in [&plain, &with_attribs] {
+                let insert = Insert {
+                    scale: Point3::from(scale),
+                    ..insert.clone()
+                };
+                let decoded = round_trip(object_types::INSERT, &insert, version);
+                assert_eq!(decoded, Ok(Entity::Insert(insert)), "{version:?} {scale:?}");
+            }
+        }
+    }
+
+    // A uniform scale is stored once, and the first and last ATTRIBs come before the SEQEND
+    let version = DWGVersion::AC1015;
+    let data = Bits::default()
+        .p3((5.0, 5.0, 0.0))
+        .bb(2)
+        .rd(2.0)
+        .bd(0.0)
+        .p3((0.0, 0.0, 1.0))
+        .b(true);
+    let handles = Bits::default().h(5, 0x20).h(4, 0x41).h(4, 0x42).h(3, 0x43);
+    let expected = pack_entity(version, object_types::INSERT, data, handles);
+    let insert = Insert {
+        header: entity_header(0x30, version),
+        insertion: Point3::from((5.0, 5.0, 0.0)),
+        scale: Point3::from((2.0, 2.0, 2.0)),
+        rotation: 0.0,
+        extrusion: Point3::from((0.0, 0.0, 1.0)),
+        block_header: HandleRef::new(RefType::HardPointer, 0x20),
+        first_last_attribs: Some((soft(0x41), soft(0x42))),
+        attribs: Vec::new(),
+        seqend: Some(owned(0x43)),
+    };
+    assert_eq!(
+        encode_entity(object_types::INSERT, &insert, version),
+        expected
+    );
+    assert_eq!(
+        decode_entity(&expected, version),
+        Ok(Entity::Insert(insert))
+    );
+}
+
+#[test]
+fn test_insert_attribs() {
+    for version in [DWGVersion::AC1015, DWGVersion::AC1018] {
+        let mut dwg = Dwg::new(version).unwrap();
+        let [insert, first, second, seqend] = std::array::from_fn(|i| 0x100 + i as u64);
+        let soft = |handle| HandleRef::new(RefType::SoftPointer, handle);
+        let owner = Some(soft(insert));
+        // The ATTRIBs are linked to each other and on to the SEQEND
+        let links = [(first, (0, second)), (second, (first, seqend))];
+        for (handle, (prev, next)) in links {
+            let mut header = CommonEntityData::new(handle, 0, owner, 0x10);
+            if version < DWGVersion::AC1018 {
+                header.links = Some((soft(prev), soft(next)));
+            }
+            let line = Line {
+                header,
+                start: Point3::default(),
+                end: Point3::default(),
+                thickness: 0.0,
+                extrusion: Point3::from((0.0, 0.0, 1.0)),
+            };
+            dwg.insert_object(handle, object_types::ATTRIB, &line)
+                .unwrap();
+        }
+        let insert = Insert {
+            header: CommonEntityData::new(insert, 2, None, 0x10),
+            insertion: Point3::default(),
+            scale: Point3::from((1.0, 1.0, 1.0)),
+            rotation: 0.0,
+            extrusion: Point3::from((0.0, 0.0, 1.0)),
+            block_header: soft(0x30),
+            // A last ATTRIB that is never reached stops at the SEQEND
+            first_last_attribs: Some((soft(first), soft(0x999))),
+            attribs: vec![soft(first), soft(second)],
+            seqend: Some(soft(seqend)),
+        };
+        assert_eq!(dwg.insert_attribs(&insert), Ok(vec![first, second]));
+        let no_attribs = Insert {
+            seqend: None,
+            ..insert
+        };
+        assert_eq!(dwg.insert_attribs(&no_attribs), Ok(vec![]));
+    }
+}
+
+#[test]
+fn test_minsert() {
+    for version in [DWGVersion::AC1014, DWGVersion::AC1015, DWGVersion::AC1018] {
+        let minsert = MInsert {
+            insert: Insert {
+                header: entity_header(0x30, version),
+                insertion: Point3::from((10.0, 0.0, 1.0)),
+                scale: Point3::from((2.0, 2.0, 1.0)),
+                rotation: std::f64::consts::FRAC_PI_2,
+                extrusion: Point3::from((0.0, 0.0, 1.0)),
+                block_header: HandleRef::new(RefType::HardPointer, 0x20),
+                first_last_attribs: None,
+                attribs: Vec::new(),
+                seqend: None,
+            },
+            columns: 3,
+            rows: 2,
+            column_spacing: 5.0,
+            row_spacing: 4.0,
+        };
+        let data = encode_entity(object_types::MINSERT, &minsert, version);
+        if version == DWGVersion::AC1015 {
+            // The y scale is the same as the x scale, but the z scale shares none of its bytes
+            let bits = Bits::default()
+                .p3((10.0, 0.0, 1.0))
+                .bb(0)
+                .rd(2.0)
+                .dd(2.0, 2.0)
+                .dd(1.0, 2.0)
+                .bd(std::f64::consts::FRAC_PI_2)
+                .p3((0.0, 0.0, 1.0))
+                .b(false)
+                .bs(3)
+                .bs(2)
+                .bd(5.0)
+                .bd(4.0);
+            let block_header = Bits::default().h(5, 0x20);
+            let expected = pack_entity(version, object_types::MINSERT, bits, block_header);
+            assert_eq!(data, expected);
+        }
+        let decoded = decode_entity(&data, version);
+        assert_eq!(decoded, Ok(Entity::MInsert(minsert.clone())), "{version:?}");
+
+        // Turned a quarter, columns go up the y axis and rows go back along the x axis
+        let points: Vec<_> = minsert
+            .insertion_points()
+            .iter()
+            .map(|point| (point.x.round(), point.y.round(), point.z))
+            .collect();
+        assert_eq!(
+            points,
+            [
+                (10.0, 0.0, 1.0),
+                (10.0, 5.0, 1.0),
+                (10.0, 10.0, 1.0),
+                (6.0, 0.0, 1.0),
+                (6.0, 5.0, 1.0),
+                (6.0, 10.0, 1.0),
+            ]
+        );
+    }
+}
+
+#[test]
+fn test_polyline_2d() {
+    for version in [DWGVersion::AC1015, DWGVersion::AC1018] {
+        let mut dwg = Dwg::new(version).unwrap();
+        let [polyline, first, second, seqend] = std::array::from_fn(|i| 0x100 + i as u64);
+        let soft = |handle| HandleRef::new(RefType::SoftPointer, handle);
+        let header = |handle, owner: Option<u64>, links: (u64, u64)| {
+            let entmode = if owner.is_none() { 2 } else { 0 };
+            let mut header = CommonEntityData::new(handle, entmode, owner.map(soft), 0x10);
+            if version < DWGVersion::AC1018 {
+                header.links = Some((soft(links.0), soft(links.1)));
+            }
+            header
+        };
+        let vertices = [
+            Vertex2d {
+                header: header(first, Some(polyline), (0, second)),
+                flags: 0,
+                point: Point3::from((0.0, 0.0, 0.0)),
+                start_width: 0.5,
+                end_width: 0.5,
+                bulge: 1.0,
+                vertex_id: 0,
+                tangent_direction: 0.0,
+            },
+            Vertex2d {
+                header: header(second, Some(polyline), (first, seqend)),
+                flags: 2,
+                point: Point3::from((10.0, 0.0, 0.0)),
+                start_width: 0.0,
+                end_width: 1.5,
+                bulge: 0.0,
+                vertex_id: 0,
+                tangent_direction: std::f64::consts::FRAC_PI_2,
+            },
+        ];
+        let owned = |handle| HandleRef::new(RefType::HardOwned, handle);
+        let polyline_entity = Polyline2d {
+            header: header(polyline, None, (0, 0)),
+            flags: 1,
+            curve_type: 0,
+            start_width: 0.0,
+            end_width: 0.0,
+            thickness: 0.0,
+            elevation: 2.0,
+            extrusion: Point3::from((0.0, 0.0, 1.0)),
+            owned: OwnedVertices {
+                first_last: match version < DWGVersion::AC1018 {
+                    true => Some((soft(first), soft(second))),
+                    false => None,
+                },
+                vertices: match version < DWGVersion::AC1018 {
+                    true => vec![],
+                    false => vec![owned(first), owned(second)],
+                },
+                seqend: owned(seqend),
+            },
+        };
+        dwg.insert_object(polyline, object_types::POLYLINE_2D, &polyline_entity)
+            .unwrap();
+        for vertex in &vertices {
+            dwg.insert_object(vertex.header.handle, object_types::VERTEX_2D, vertex)
+                .unwrap();
+        }
+
+        let data = dwg.get_object(polyline).unwrap();
+        let decoded = decode_entity(data, version);
+        assert_eq!(decoded, Ok(Entity::Polyline2d(polyline_entity.clone())));
+        // Equal widths are stored once
+        let encode = |vertex| encode_entity(object_types::VERTEX_2D, vertex, version);
+        let mut uneven = vertices[0].clone();
+        uneven.end_width = 0.25;
+        assert!(encode(&vertices[0]).len() < encode(&uneven).len());
+
+        let read = dwg.polyline_2d(polyline).unwrap();
+        assert_eq!(read.polyline, polyline_entity, "{version:?}");
+        assert_eq!(read.vertices, vertices, "{version:?}");
+        assert!(dwg.polyline_2d(first).is_err());
+    }
+
+    let mut header = entity_header(0x101, DWGVersion::AC1024);
+    header.entmode = 0;
+    header.owner = Some(HandleRef::new(RefType::SoftPointer, 0x100));
+    let mut vertex = Vertex2d {
+        header,
+        flags: 0,
+        point: Point3::from((1.0, 2.0, 0.0)),
+        start_width: 0.0,
+        end_width: 0.0,
+        bulge: -0.5,
+        vertex_id: 7,
+        tangent_direction: 0.0,
+    };
+    let decoded = round_trip(object_types::VERTEX_2D, &vertex, DWGVersion::AC1024);
+    assert_eq!(decoded, Ok(Entity::Vertex2d(vertex.clone())));
+    // The vertex id is only stored from AC1024
+    let data = encode_entity(object_types::VERTEX_2D, &vertex, DWGVersion::AC1018);
+    vertex.vertex_id = 0;
+    let decoded = decode_entity(&data, DWGVersion::AC1018);
+    assert_eq!(decoded, Ok(Entity::Vertex2d(vertex)));
+
+    // The vertices are stored as the first and last of them and the SEQEND before AC1018
+    let version = DWGVersion::AC1015;
+    let data = Bits::default()
+        .bs(1)
+        .bs(0)
+        .bd(0.0)
+        .bd(0.0)
+        .bt(0.0)
+        .bd(2.0)
+        .be((0.0, 0.0, 1.0));
+    let owned = Bits::default().h(4, 0x101).h(4, 0x102).h(3, 0x103);
+    let expected = pack_entity(version, object_types::POLYLINE_2D, data, owned);
+    let polyline = Polyline2d {
+        header: entity_header(0x30, version),
+        flags: 1,
+        curve_type: 0,
+        start_width: 0.0,
+        end_width: 0.0,
+        thickness: 0.0,
+        elevation: 2.0,
+        extrusion: Point3::from((0.0, 0.0, 1.0)),
+        owned: OwnedVertices {
+            first_last: Some((
+                HandleRef::new(RefType::SoftPointer, 0x101),
+                HandleRef::new(RefType::SoftPointer, 0x102),
+            )),
+            vertices: vec![],
+            seqend: HandleRef::new(RefType::HardOwned, 0x103),
+        },
+    };
+    let encoded = encode_entity(object_types::POLYLINE_2D, &polyline, version);
+    assert_eq!(encoded, expected);
+    assert_eq!(
+        decode_entity(&expected, version),
+        Ok(Entity::Polyline2d(polyline))
+    );
+
+    // Equal widths are stored once, negated
+    let data = Bits::default()
+        .rc(0)
+        .p3((10.0, 0.0, 0.0))
+        .bd(-0.5)
+        .bd(1.0)
+        .bd(std::f64::consts::FRAC_PI_2);
+    let expected = pack_entity(version, object_types::VERTEX_2D, data, Bits::default());
+    let vertex = Vertex2d {
+        header: entity_header(0x30, version),
+        flags: 0,
+        point: Point3::from((10.0, 0.0, 0.0)),
+        start_width: 0.5,
+        end_width: 0.5,
+        bulge: 1.0,
+        vertex_id: 0,
+        tangent_direction: std::f64::consts::FRAC_PI_2,
+    };
+    assert_eq!(
+        encode_entity(object_types::VERTEX_2D, &vertex, version),
+        expected
+    );
+    assert_eq!(
+        decode_entity(&expected, version),
+        Ok(Entity::Vertex2d(vertex))
+    );
+}
+
+#[test]
+fn test_polyline_3d() {
+    for version in [DWGVersion::AC1015, DWGVersion::AC1018] {
+        let mut dwg = Dwg::new(version).unwrap();
+        let [polyline, first, second, seqend] = std::array::from_fn(|i| 0x100 + i as u64);
+        let soft = |handle| HandleRef::new(RefType::SoftPointer, handle);
+        let owned = |handle| HandleRef::new(RefType::HardOwned, handle);
+        let links = [(first, (0, second)), (second, (first, seqend))];
+        let vertices: Vec<_> = links
+            .into_iter()
+            .zip([(0.0, 0.0, 0.0), (1.0, 2.0, 3.0)])
+            .map(|((handle, (prev, next)), point)| {
+                let mut header = CommonEntityData::new(handle, 0, Some(soft(polyline)), 0x10);
+                if version < DWGVersion::AC1018 {
+                    header.links = Some((soft(prev), soft(next)));
+                }
+                Vertex3d {
+                    header,
+                    flags: 0x10,
+                    point: point.into(),
+                }
+            })
+            .collect();
+        let polyline_entity = Polyline3d {
+            header: CommonEntityData::new(polyline, 2, None, 0x10),
+            spline_flags: 2,
+            closed_flags: 1,
+            owned: OwnedVertices {
+                first_last: (version < DWGVersion::AC1018).then_some((soft(first), soft(second))),
+                vertices: match version < DWGVersion::AC1018 {
+                    true => vec![],
+                    false => vec![owned(first), owned(second)],
+                },
+                seqend: owned(seqend),
+            },
+        };
+        assert!(polyline_entity.is_closed() && polyline_entity.is_spline_fit());
+        dwg.insert_object(polyline, object_types::POLYLINE_3D, &polyline_entity)
+            .unwrap();
+        for vertex in &vertices {
+            dwg.insert_object(vertex.header.handle, object_types::VERTEX_3D, vertex)
+                .unwrap();
+        }
+
+        let data = dwg.get_object(first).unwrap();
+        let decoded = decode_entity(data, version);
+        assert_eq!(decoded, Ok(Entity::Vertex3d(vertices[0].clone())));
+        let read = dwg.polyline_3d(polyline).unwrap();
+        assert_eq!(read.polyline, polyline_entity, "{version:?}");
+        assert_eq!(read.vertices, vertices, "{version:?}");
+        // A 3D polyline does not own 2D vertices
+        assert!(dwg.polyline_2d(polyline).is_err());
+    }
+
+    // From AC1018 the number of vertices ends the data and each vertex is stored
+    let version = DWGVersion::AC1018;
+    let data = Bits::default().rc(2).rc(1).bl(2);
+    let owned = Bits::default().h(3, 0x101).h(3, 0x102).h(3, 0x103);
+    let expected = pack_entity(version, object_types::POLYLINE_3D, data, owned);
+    let owned = |handle| HandleRef::new(RefType::HardOwned, handle);
+    let polyline = Polyline3d {
+        header: entity_header(0x30, version),
+        spline_flags: 2,
+        closed_flags: 1,
+        owned: OwnedVertices {
+            first_last: None,
+            vertices: vec![owned(0x101), owned(0x102)],
+            seqend: owned(0x103),
+        },
+    };
+    let encoded = encode_entity(object_types::POLYLINE_3D, &polyline, version);
+    assert_eq!(encoded, expected);
+    assert_eq!(
+        decode_entity(&expected, version),
+        Ok(Entity::Polyline3d(polyline))
+    );
+
+    let data = Bits::default().rc(0x10).p3((1.0, 2.0, 3.0));
+    let expected = pack_entity(version, object_types::VERTEX_3D, data, Bits::default());
+    let vertex = Vertex3d {
+        header: entity_header(0x30, version),
+        flags: 0x10,
+        point: Point3::from((1.0, 2.0, 3.0)),
+    };
+    assert_eq!(
+        encode_entity(object_types::VERTEX_3D, &vertex, version),
+        expected
+    );
+    assert_eq!(
+        decode_entity(&expected, version),
+        Ok(Entity::Vertex3d(vertex))
+    );
+}
+
+#[test]
+fn test_lwpolyline() {
+    let owner = Some(HandleRef::new(RefType::SoftPointer, 0x1F));
+    let mut polyline = LwPolyline {
+        header: CommonEntityData::new(0x100, 0, owner, 0x10),
+        flags: 0x200 | 0x20 | 0x10 | 0x8 | 0x4,
+        const_width: 0.5,
+        elevation: 3.0,
+        thickness: 0.0,
+        extrusion: Point3::from((0.0, 0.0, 1.0)),
+        vertices: vec![
+            Point2::from((0.0, 0.0)),
+            Point2::from((10.0, 0.0)),
+            Point2::from((10.0, 5.25)),
+            Point2::from((0.0, 5.25)),
+        ],
+        bulges: vec![0.0, 1.0, 0.0, -0.5],
+        vertex_ids: vec![],
+        widths: vec![(0.0, 0.0), (0.5, 1.0), (0.0, 0.0), (1.0, 0.5)],
+    };
+    assert!(polyline.is_closed());
+    for version in [DWGVersion::AC1014, DWGVersion::AC1015, DWGVersion::AC1024] {
+        let decoded = round_trip(object_types::LWPOLYLINE, &polyline, version);
+        assert_eq!(
+            decoded,
+            Ok(Entity::LwPolyline(polyline.clone())),
+            "{version:?}"
+        );
+    }
+
+    // Vertex ids are only stored from AC1024, and the extrusion and thickness when flagged
+    polyline.flags = 0x400 | 0x2 | 0x1;
+    polyline.const_width = 0.0;
+    polyline.elevation = 0.0;
+    polyline.thickness = 2.0;
+    polyline.extrusion = Point3::from((0.0, 1.0, 0.0));
+    polyline.bulges.clear();
+    polyline.widths.clear();
+    polyline.vertex_ids = vec![1, 2, 3, 4];
+    let encode = |polyline, version| encode_entity(object_types::LWPOLYLINE, polyline, version);
+    let decoded = round_trip(object_types::LWPOLYLINE, &polyline, DWGVersion::AC1024);
+    assert_eq!(decoded, Ok(Entity::LwPolyline(polyline.clone())));
+    let data = encode(&polyline, DWGVersion::AC1018);
+    let Ok(Entity::LwPolyline(decoded)) = decode_entity(&data, DWGVersion::AC1018) else {
+        panic!("not an LWPOLYLINE");
+    };
+    assert!(decoded.vertex_ids.is_empty());
+    assert_eq!(decoded.vertices, polyline.vertices);
+    assert_eq!(decoded.extrusion, polyline.extrusion);
+
+    // Repeated coordinates are compressed from AC1015
+    let r14 = encode(&polyline, DWGVersion::AC1014);
+    let r2000 = encode(&polyline, DWGVersion::AC1015);
+    assert!(r2000.len() < r14.len());
+
+    // The second vertex shares no bytes of its x with the first, and all of its y
+    let version = DWGVersion::AC1015;
+    let data = Bits::default()
+        .bs(0x10)
+        .bl(2)
+        .bl(2)
+        .p2((0.0, 0.0))
+        .dd(10.0, 0.0)
+        .dd(0.0, 0.0)
+        .bd(0.0)
+        .bd(1.0);
+    let expected = pack_entity(version, object_types::LWPOLYLINE, data, Bits::default());
+    let polyline = LwPolyline {
+        header: entity_header(0x30, version),
+        flags: 0x10,
+        const_width: 0.0,
+        elevation: 0.0,
+        thickness: 0.0,
+        extrusion: Point3::from((0.0, 0.0, 1.0)),
+        vertices: vec![Point2::from((0.0, 0.0)), Point2::from((10.0, 0.0))],
+        bulges: vec![0.0, 1.0],
+        vertex_ids: vec![],
+        widths: vec![],
+    };
+    assert_eq!(encode(&polyline, version), expected);
+    assert_eq!(
+        decode_entity(&expected, version),
+        Ok(Entity::LwPolyline(polyline))
+    );
+}
+
+#[test]
+fn test_spline() {
+    let owner = Some(HandleRef::new(RefType::SoftPointer, 0x1F));
+    let control = Spline {
+        header: CommonEntityData::new(0x100, 0, owner, 0x10),
+        spline_flags: 0,
+        knot_parameter: 0,
+        degree: 3,
+        data: SplineData::Control {
+            rational: true,
+            closed: false,
+            periodic: false,
+            knot_tolerance: 1e-7,
+            control_tolerance: 1e-7,
+            knots: vec![0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0],
+            control_points: vec![
+                Point3::from((0.0, 0.0, 0.0)),
+                Point3::from((1.0, 2.0, 0.0)),
+                Point3::from((3.0, 2.0, 0.0)),
+                Point3::from((4.0, 0.0, 1.0)),
+            ],
+            weights: vec![1.0, 0.5, 0.5, 1.0],
+        },
+    };
+    let mut fit = Spline {
+        header: CommonEntityData::new(0x101, 0, owner, 0x10),
+        spline_flags: 0,
+        knot_parameter: 0,
+        degree: 3,
+        data: SplineData::Fit {
+            fit_tolerance: 0.0,
+            start_tangent: Point3::from((1.0, 0.0, 0.0)),
+            end_tangent: Point3::default(),
+            fit_points: vec![Point3::from((0.0, 0.0, 0.0)), Point3::from((5.0, 5.0, 0.0))],
+        },
+    };
+    for version in [DWGVersion::AC1015, DWGVersion::AC1024, DWGVersion::AC1027] {
+        if version >= DWGVersion::AC1027 {
+            fit.spline_flags = 1;
+            fit.knot_parameter = 15;
+        }
+        for spline in [&control, &fit] {
+            let decoded = round_trip(object_types::SPLINE, spline, version);
+            assert_eq!(decoded, Ok(Entity::Spline(spline.clone())), "{version:?}");
+        }
+    }
+
+    // Unweighted control points store no weights
+    let mut unweighted = control.clone();
+    if let SplineData::Control { weights, .. } = &mut unweighted.data {
+        weights.clear();
+    }
+    let version = DWGVersion::AC1018;
+    let decoded = round_trip(object_types::SPLINE, &unweighted, version);
+    assert_eq!(decoded, Ok(Entity::Spline(unweighted)));
+
+    struct BadScenario(CommonEntityData);
+    impl DwgEncode for BadScenario {
+        fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+            self.0.encode(writer)?;
+            writer.write_bitlong(3);
+            writer.write_bitlong(3);
+            Ok(())
+        }
+    }
+    let bad = BadScenario(CommonEntityData::new(0x102, 0, owner, 0x10));
+    let decoded = round_trip(object_types::SPLINE, &bad, version);
+    assert!(matches!(
+        decoded,
+        Err(DwgError::InvalidFlag { value: 3, .. })
+    ));
+
+    // Scenario 2, fit through points, before the spline flags and knot parameter are stored
+    let version = DWGVersion::AC1015;
+    let data = Bits::default()
+        .bl(2)
+        .bl(3)
+        .bd(0.0)
+        .p3((1.0, 0.0, 0.0))
+        .p3((0.0, 0.0, 0.0))
+        .bl(2)
+        .p3((0.0, 0.0, 0.0))
+        .p3((5.0, 5.0, 0.0));
+    let expected = pack_entity(version, object_types::SPLINE, data, Bits::default());
+    let fit = Spline {
+        header: entity_header(0x30, version),
+        spline_flags: 0,
+        knot_parameter: 0,
+        ..fit
+    };
+    assert_eq!(encode_entity(object_types::SPLINE, &fit, version), expected);
+    assert_eq!(decode_entity(&expected, version), Ok(Entity::Spline(fit)));
+}
+
+#[test]
+fn test_polyface_mesh() {
+    for version in [DWGVersion::AC1015, DWGVersion::AC1018] {
+        let mut dwg = Dwg::new(version).unwrap();
+        let polyline = 0x100;
+        let owned_handles: Vec<u64> = (0x101..0x107).collect();
+        let seqend = 0x107;
+        let soft = |handle| HandleRef::new(RefType::SoftPointer, handle);
+        let owned = |handle| HandleRef::new(RefType::HardOwned, handle);
+        let header = |i: usize| {
+            let handle = owned_handles[i];
+            let mut header = CommonEntityData::new(handle, 0, Some(soft(polyline)), 0x10);
+            if version < DWGVersion::AC1018 {
+                let prev = i.checked_sub(1).map_or(0, |prev| owned_handles[prev]);
+                let next = owned_handles.get(i + 1).copied().unwrap_or(seqend);
+                header.links = Some((soft(prev), soft(next)));
+            }
+            header
+        };
+        // A square split into two triangles along an invisible diagonal
+        let points = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        let vertices: Vec<_> = points
+            .into_iter()
+            .enumerate()
+            .map(|(i, (x, y))| Vertex3d {
+                header: header(i),
+                flags: 0x40 | 0x80,
+                point: Point3::from((x, y, 0.0)),
+            })
+            .collect();
+        let faces = vec![
+            FaceRecord {
+                header: header(4),
+                indices: [1, 2, -3, 0],
+            },
+            FaceRecord {
+                header: header(5),
+                indices: [3, 4, -1, 0],
+            },
+        ];
+        let polyline_entity = PolylinePface {
+            header: CommonEntityData::new(polyline, 2, None, 0x10),
+            num_vertices: 4,
+            num_faces: 2,
+            owned: OwnedVertices {
+                first_last: (version < DWGVersion::AC1018)
+                    .then_some((soft(owned_handles[0]), soft(owned_handles[5]))),
+                vertices: match version < DWGVersion::AC1018 {
+                    true => vec![],
+                    false => owned_handles.iter().map(|&handle| owned(handle)).collect(),
+                },
+                seqend: owned(seqend),
+            },
+        };
+        dwg.insert_object(polyline, object_types::POLYLINE_PFACE, &polyline_entity)
+            .unwrap();
+        for vertex in &vertices {
+            dwg.insert_object(vertex.header.handle, object_types::VERTEX_PFACE, vertex)
+                .unwrap();
+        }
+        for face in &faces {
+            dwg.insert_object(face.header.handle, object_types::VERTEX_PFACE_FACE, face)
+                .unwrap();
+        }
+
+        let mesh = dwg.polyface_mesh(polyline).unwrap();
+        assert_eq!(mesh.polyline, polyline_entity, "{version:?}");
+        assert_eq!(mesh.vertices, vertices, "{version:?}");
+        assert_eq!(mesh.faces, faces, "{version:?}");
+        let indices: Vec<_> = mesh.faces[1].vertices().collect();
+        assert_eq!(indices, [(2, true), (3, true), (0, false)]);
+
+        // Any other entity in the chain is rejected
+        let point = Point {
+            header: header(5),
+            position: Point3::default(),
+            thickness: 0.0,
+            extrusion: Point3::from((0.0, 0.0, 1.0)),
+            x_axis_angle: 0.0,
+        };
+        dwg.insert_object(owned_handles[5], object_types::POINT, &point)
+            .unwrap();
+        assert!(matches!(
+            dwg.polyface_mesh(polyline),
+            Err(DwgError::InvalidFlag { .. })
+        ));
+    }
+
+    let version = DWGVersion::AC1015;
+    let soft = |handle| HandleRef::new(RefType::SoftPointer, handle);
+    let data = Bits::default().bs(4).bs(2);
+    let owned = Bits::default().h(4, 0x101).h(4, 0x106).h(3, 0x107);
+    let expected = pack_entity(version, object_types::POLYLINE_PFACE, data, owned);
+    let polyline = PolylinePface {
+        header: entity_header(0x30, version),
+        num_vertices: 4,
+        num_faces: 2,
+        owned: OwnedVertices {
+            first_last: Some((soft(0x101), soft(0x106))),
+            vertices: vec![],
+            seqend: HandleRef::new(RefType::HardOwned, 0x107),
+        },
+    };
+    let encoded = encode_entity(object_types::POLYLINE_PFACE, &polyline, version);
+    assert_eq!(encoded, expected);
+    let decoded = decode_entity(&expected, version);
+    assert_eq!(decoded, Ok(Entity::PolylinePface(polyline)));
+
+    // The vertices are stored as a VERTEX_3D is, and the faces as 4 BS indices
+    let data = Bits::default().rc(0xC0).p3((1.0, 1.0, 0.0));
+    let expected = pack_entity(version, object_types::VERTEX_PFACE, data, Bits::default());
+    let vertex = Vertex3d {
+        header: entity_header(0x30, version),
+        flags: 0xC0,
+        point: Point3::from((1.0, 1.0, 0.0)),
+    };
+    assert_eq!(
+        encode_entity(object_types::VERTEX_PFACE, &vertex, version),
+        expected
+    );
+    assert_eq!(
+        decode_entity(&expected, version),
+        Ok(Entity::VertexPface(vertex))
+    );
+    let data = Bits::default().bs(1).bs(2).bs(-3).bs(0);
+    let expected = pack_entity(
+        version,
+        object_types::VERTEX_PFACE_FACE,
+        data,
+        Bits::default(),
+    );
+    let face = FaceRecord {
+        header: entity_header(0x30, version),
+        indices: [1, 2, -3, 0],
+    };
+    let encoded = encode_entity(object_types::VERTEX_PFACE_FACE, &face, version);
+    assert_eq!(encoded, expected);
+    assert_eq!(
+        decode_entity(&expected, version),
+        Ok(Entity::FaceRecord(face))
+    );
+}
+
+#[test]
+fn test_polygon_mesh() {
+    for version in [DWGVersion::AC1015, DWGVersion::AC1018] {
+        let mut dwg = Dwg::new(version).unwrap();
+        let polyline = 0x100;
+        let handles: Vec<u64> = (0x101..0x107).collect();
+        let seqend = 0x107;
+        let soft = |handle| HandleRef::new(RefType::SoftPointer, handle);
+        let owned = |handle| HandleRef::new(RefType::HardOwned, handle);
+        // A grid of 2 by 3 vertices, with the height of each the sum of its row and column
+        let vertices: Vec<_> = (0..handles.len())
+            .map(|i| {
+                let mut header = CommonEntityData::new(handles[i], 0, Some(soft(polyline)), 0x10);
+                if version < DWGVersion::AC1018 {
+                    let prev = i.checked_sub(1).map_or(0, |prev| handles[prev]);
+                    let next = handles.get(i + 1).copied().unwrap_or(seqend);
+                    header.links = Some((soft(prev), soft(next)));
+                }
+                let (m, n) = ((i / 3) as f64, (i % 3) as f64);
+                Vertex3d {
+                    header,
+                    flags: 0x40,
+                    point: Point3::from((m, n, m + n)),
+                }
+            })
+            .collect();
+        let polyline_entity = PolylineMesh {
+            header: CommonEntityData::new(polyline, 2, None, 0x10),
+            flags: 0x10 | 0x1,
+            curve_type: 0,
+            m_vertex_count: 2,
+            n_vertex_count: 3,
+            m_density: 0,
+            n_density: 0,
+            owned: OwnedVertices {
+                first_last: (version < DWGVersion::AC1018)
+                    .then_some((soft(handles[0]), soft(handles[5]))),
+                vertices: match version < DWGVersion::AC1018 {
+                    true => vec![],
+                    false => handles.iter().map(|&handle| owned(handle)).collect(),
+                },
+                seqend: owned(seqend),
+            },
+        };
+        dwg.insert_object(polyline, object_types::POLYLINE_MESH, &polyline_entity)
+            .unwrap();
+        for vertex in &vertices {
+            dwg.insert_object(vertex.header.handle, object_types::VERTEX_MESH, vertex)
+                .unwrap();
+        }
+
+        let data = dwg.get_object(polyline).unwrap();
+        let decoded = decode_entity(data, version);
+        assert_eq!(decoded, Ok(Entity::PolylineMesh(polyline_entity.clone())));
+        let mesh = dwg.polygon_mesh(polyline).unwrap();
+        assert_eq!(mesh.polyline, polyline_entity, "{version:?}");
+        assert_eq!(mesh.vertices, vertices, "{version:?}");
+        assert_eq!(mesh.rows().count(), 2);
+        assert!(mesh.rows().all(|row| row.len() == 3));
+        assert_eq!(
+            mesh.vertex(1, 2).unwrap().point,
+            Point3::from((1.0, 2.0, 3.0))
+        );
+        assert_eq!(mesh.vertex(0, 3), None);
+        assert_eq!(mesh.vertex(2, 0), None);
+    }
+
+    let version = DWGVersion::AC1018;
+    let data = Bits::default().bs(0x11).bs(0).bs(2).bs(3).bs(0).bs(0).bl(1);
+    let owned = Bits::default().h(3, 0x101).h(3, 0x102);
+    let expected = pack_entity(version, object_types::POLYLINE_MESH, data, owned);
+    let polyline = PolylineMesh {
+        header: entity_header(0x30, version),
+        flags: 0x11,
+        curve_type: 0,
+        m_vertex_count: 2,
+        n_vertex_count: 3,
+        m_density: 0,
+        n_density: 0,
+        owned: OwnedVertices {
+            first_last: None,
+            vertices: vec![HandleRef::new(RefType::HardOwned, 0x101)],
+            seqend: HandleRef::new(RefType::HardOwned, 0x102),
+        },
+    };
+    let encoded = encode_entity(object_types::POLYLINE_MESH, &polyline, version);
+    assert_eq!(encoded, expected);
+    let decoded = decode_entity(&expected, version);
+    assert_eq!(decoded, Ok(Entity::PolylineMesh(polyline)));
+
+    let data = Bits::default().rc(0x40).p3((1.0, 2.0, 3.0));
+    let expected = pack_entity(version, object_types::VERTEX_MESH, data, Bits::default());
+    let vertex = Vertex3d {
+        header: entity_header(0x30, version),
+        flags: 0x40,
+        point: Point3::from((1.0, 2.0, 3.0)),
+    };
+    assert_eq!(
+        encode_entity(object_types::VERTEX_MESH, &vertex, version),
+        expected
+    );
+    assert_eq!(
+        decode_entity(&expected, version),
+        Ok(Entity::VertexMesh(vertex))
+    );
+}
+
+#[test]
+fn test_face_3d() {
+    let owner = Some(HandleRef::new(RefType::SoftPointer, 0x1F));
+    let mut face = Face3d {
+        header: CommonEntityData::new(0x100, 0, owner, 0x10),
+        corners: [
+            Point3::from((0.0, 0.0, 0.0)),
+            Point3::from((4.0, 0.0, 0.0)),
+            Point3::from((4.0, 3.0, 0.0)),
+            Point3::from((4.0, 3.0, 0.0)),
+        ],
+        invisible_edges: 0,
+    };
+    let encode = |face: &Face3d, version| encode_entity(object_types::FACE_3D, face, version);
+    let flat_r2000 = encode(&face, DWGVersion::AC1015);
+    for version in [DWGVersion::AC1014, DWGVersion::AC1015, DWGVersion::AC1018] {
+        for invisible_edges in [0, 0x4 | 0x8] {
+            face.invisible_edges = invisible_edges;
+            face.corners[0].z = if invisible_edges == 0 { 0.0 } else { 1.5 };
+            let decoded = round_trip(object_types::FACE_3D, &face, version);
+            assert_eq!(decoded, Ok(Entity::Face3d(face.clone())), "{version:?}");
+        }
+    }
+    assert!(face.is_edge_visible(0) && face.is_edge_visible(1));
+    assert!(!face.is_edge_visible(2) && !face.is_edge_visible(3));
+    // A flat face with no invisible edges leaves out the z of the first corner and the flags
+    assert!(flat_r2000.len() < encode(&face, DWGVersion::AC1015).len());
+    assert!(flat_r2000.len() < encode(&face, DWGVersion::AC1014).len());
+
+    // Each corner after the first is stored as DDs of the corner before it
+    let version = DWGVersion::AC1015;
+    let data = Bits::default()
+        .b(true)
+        .b(true)
+        .p2((0.0, 0.0))
+        .dd(4.0, 0.0)
+        .dd(0.0, 0.0)
+        .dd(0.0, 0.0)
+        .dd(4.0, 4.0)
+        .dd(3.0, 0.0)
+        .dd(0.0, 0.0)
+        .dd(4.0, 4.0)
+        .dd(3.0, 3.0)
+        .dd(0.0, 0.0);
+    let expected = pack_entity(version, object_types::FACE_3D, data, Bits::default());
+    let face = Face3d {
+        header: entity_header(0x30, version),
+        corners: [
+            Point3::from((0.0, 0.0, 0.0)),
+            Point3::from((4.0, 0.0, 0.0)),
+            Point3::from((4.0, 3.0, 0.0)),
+            Point3::from((4.0, 3.0, 0.0)),
+        ],
+        invisible_edges: 0,
+    };
+    assert_eq!(encode(&face, version), expected);
+    assert_eq!(decode_entity(&expected, version), Ok(Entity::Face3d(face)));
+}
+
+#[test]
+fn test_solid() {
+    let owner = Some(HandleRef::new(RefType::SoftPointer, 0x1F));
+    let solid = Solid {
+        header: CommonEntityData::new(0x100, 0, owner, 0x10),
+        thickness: 0.0,
+        elevation: 1.0,
+        corners: [
+            Point2::from((0.0, 0.0)),
+            Point2::from((2.0, 0.0)),
+            Point2::from((0.0, 1.0)),
+            Point2::from((2.0, 1.0)),
+        ],
+        extrusion: Point3::from((0.0, 0.0, 1.0)),
+    };
+    let mut trace = solid.clone();
+    trace.thickness = 0.25;
+    trace.extrusion = Point3::from((0.0, 0.0, -1.0));
+    for version in [DWGVersion::AC1014, DWGVersion::AC1015, DWGVersion::AC1018] {
+        let decoded = round_trip(object_types::SOLID, &solid, version);
+        assert_eq!(decoded, Ok(Entity::Solid(solid.clone())), "{version:?}");
+        let decoded = round_trip(object_types::TRACE, &trace, version).unwrap();
+        assert_eq!(decoded.object_type(), object_types::TRACE);
+        assert_eq!(decoded, Entity::Trace(trace.clone()), "{version:?}");
+    }
+
+    // A TRACE is stored as a SOLID is
+    let version = DWGVersion::AC1015;
+    let data = Bits::default()
+        .bt(0.0)
+        .bd(1.0)
+        .p2((0.0, 0.0))
+        .p2((2.0, 0.0))
+        .p2((0.0, 1.0))
+        .p2((2.0, 1.0))
+        .be((0.0, 0.0, 1.0));
+    let solid = Solid {
+        header: entity_header(0x30, version),
+        ..solid
+    };
+    for object_type in [object_types::SOLID, object_types::TRACE] {
+        let expected = pack_entity(version, object_type, data.clone(), Bits::default());
+        assert_eq!(encode_entity(object_type, &solid, version), expected);
+        let decoded = decode_entity(&expected, version).unwrap();
+        assert_eq!(decoded.object_type(), object_type);
+        assert!(matches!(decoded, Entity::Solid(read) | Entity::Trace(read) if read == solid));
+    }
+}
+
+#[test]
+fn test_shape() {
+    let owner = Some(HandleRef::new(RefType::SoftPointer, 0x1F));
+    let shape = Shape {
+        header: CommonEntityData::new(0x100, 0, owner, 0x10),
+        insertion: Point3::from((5.0, 6.0, 0.0)),
+        scale: 2.5,
+        rotation: std::f64::consts::FRAC_PI_4,
+        width_factor: 1.0,
+        oblique: 0.0,
+        thickness: 0.0,
+        shape_number: 135,
+        extrusion: Point3::from((0.0, 0.0, 1.0)),
+        style: HandleRef::new(RefType::HardPointer, 0x40),
+    };
+    for version in [DWGVersion::AC1014, DWGVersion::AC1015, DWGVersion::AC1024] {
+        let decoded = round_trip(object_types::SHAPE, &shape, version);
+        assert_eq!(decoded, Ok(Entity::Shape(shape.clone())), "{version:?}");
+    }
+
+    let version = DWGVersion::AC1015;
+    let data = Bits::default()
+        .p3((5.0, 6.0, 0.0))
+        .bd(2.5)
+        .bd(std::f64::consts::FRAC_PI_4)
+        .bd(1.0)
+        .bd(0.0)
+        .bd(0.0)
+        .bs(135)
+        .p3((0.0, 0.0, 1.0));
+    let style = Bits::default().h(5, 0x40);
+    let expected = pack_entity(version, object_types::SHAPE, data, style);
+    let shape = Shape {
+        header: entity_header(0x30, version),
+        ..shape
+    };
+    assert_eq!(
+        encode_entity(object_types::SHAPE, &shape, version),
+        expected
+    );
+    assert_eq!(decode_entity(&expected, version), Ok(Entity::Shape(shape)));
+}
+
+#[test]
+fn test_ray() {
+    let owner = Some(HandleRef::new(RefType::SoftPointer, 0x1F));
+    let ray = Ray {
+        header: CommonEntityData::new(0x100, 0, owner, 0x10),
+        point: Point3::from((1.0, 1.0, 0.0)),
+        direction: Point3::from((0.6, 0.8, 0.0)),
+    };
+    for version in [DWGVersion::AC1014, DWGVersion::AC1018] {
+        let decoded = round_trip(object_types::RAY, &ray, version);
+        assert_eq!(decoded, Ok(Entity::Ray(ray.clone())), "{version:?}");
+        let decoded = round_trip(object_types::XLINE, &ray, version).unwrap();
+        assert_eq!(decoded.object_type(), object_types::XLINE);
+        assert_eq!(decoded, Entity::XLine(ray.clone()), "{version:?}");
+    }
+
+    // An XLINE is stored as a RAY is
+    let version = DWGVersion::AC1015;
+    let data = Bits::default().p3((1.0, 1.0, 0.0)).p3((0.6, 0.8, 0.0));
+    let ray = Ray {
+        header: entity_header(0x30, version),
+        ..ray
+    };
+    for object_type in [object_types::RAY, object_types::XLINE] {
+        let expected = pack_entity(version, object_type, data.clone(), Bits::default());
+        assert_eq!(encode_entity(object_type, &ray, version), expected);
+        let decoded = decode_entity(&expected, version).unwrap();
+        assert_eq!(decoded.object_type(), object_type);
+        assert!(matches!(decoded, Entity::Ray(read) | Entity::XLine(read) if read == ray));
+    }
+}
+
+#[test]
+fn test_leader() {
+    let owner = Some(HandleRef::new(RefType::SoftPointer, 0x1F));
+    let mut leader = Leader {
+        header: CommonEntityData::new(0x100, 0, owner, 0x10),
+        unknown_bit: false,
+        annotation_type: 0,
+        path_type: 1,
+        points: vec![
+            Point3::from((0.0, 0.0, 0.0)),
+            Point3::from((2.0, 2.0, 0.0)),
+            Point3::from((4.0, 2.0, 0.0)),
+        ],
+        origin: Point3::from((0.0, 0.0, 0.0)),
+        extrusion: Point3::from((0.0, 0.0, 1.0)),
+        x_direction: Point3::from((1.0, 0.0, 0.0)),
+        block_offset: Point3::default(),
+        end_projection: Point3::default(),
+        dimgap: 0.0,
+        box_height: 0.18,
+        box_width: 1.5,
+        hookline_on_x_dir: true,
+        arrowhead_on: true,
+        arrowhead_type: 0,
+        arrowhead_size: 0.0,
+        byblock_color: 0,
+        hookline: true,
+        annotation: HandleRef::new(RefType::SoftPointer, 0x101),
+        dimstyle: HandleRef::new(RefType::HardPointer, 0x27),
+    };
+    let decode = |leader: &Leader, version| round_trip(object_types::LEADER, leader, version);
+    for version in [DWGVersion::AC1015, DWGVersion::AC1018] {
+        assert_eq!(decode(&leader, version), Ok(Entity::Leader(leader.clone())));
+    }
+
+    // From R2000 the dimension gap and arrowhead are left to the dimension style
+    let version = DWGVersion::AC1015;
+    let data = Bits::default()
+        .b(false)
+        .bs(0)
+        .bs(1)
+        .bl(3)
+        .p3((0.0, 0.0, 0.0))
+        .p3((2.0, 2.0, 0.0))
+        .p3((4.0, 2.0, 0.0))
+        .p3((0.0, 0.0, 0.0))
+        .p3((0.0, 0.0, 1.0))
+        .p3((1.0, 0.0, 0.0))
+        .p3((0.0, 0.0, 0.0))
+        .p3((0.0, 0.0, 0.0))
+        .bd(0.18)
+        .bd(1.5)
+        .b(true)
+        .b(true)
+        .bs(0)
+        .b(true)
+        .b(false);
+    let handles = Bits::default().h(4, 0x101).h(5, 0x27);
+    let expected = pack_entity(version, object_types::LEADER, data, handles);
+    let r2000 = Leader {
+        header: entity_header(0x30, version),
+        ..leader.clone()
+    };
+    assert_eq!(
+        encode_entity(object_types::LEADER, &r2000, version),
+        expected
+    );
+    assert_eq!(decode_entity(&expected, version), Ok(Entity::Leader(r2000)));
+    // Before R2000 the arrowhead is stored on the leader
+    leader.end_projection = Point3::from((4.0, 2.0, 0.0));
+    leader.dimgap = 0.09;
+    leader.arrowhead_type = 1;
+    leader.arrowhead_size = 0.18;
+    leader.byblock_color = 7;
+    assert_eq!(
+        decode(&leader, DWGVersion::AC1014),
+        Ok(Entity::Leader(leader.clone()))
+    );
+}
+
+#[test]
+fn test_multileader() {
+    use crate::classes::{write_classes, DwgClass, ENTITY_CLASS_ID};
+
+    let hard = |handle| HandleRef::new(RefType::HardPointer, handle);
+    let by_block = CmColor::from_color(Color::ByBlock);
+    let owner = Some(HandleRef::new(RefType::SoftPointer, 0x1F));
+    let line = |points: &[(f64, f64)], index| LeaderLine {
+        points: points
+            .iter()
+            .map(|&(x, y)| Point3::from((x, y, 0.0)))
+            .collect(),
+        breaks: vec![],
+        index,
+        style: None,
+    };
+    let text = MLeaderText {
+        text: "Fillet R2".into(),
+        normal: Point3::from((0.0, 0.0, 1.0)),
+        style: hard(0x11),
+        location: Point3::from((4.09, 2.09, 0.0)),
+        direction: Point3::from((1.0, 0.0, 0.0)),
+        rotation: 0.0,
+        width: 0.0,
+        height: 0.0,
+        line_spacing_factor: 1.0,
+        line_spacing_style: 1,
+        color: by_block.clone(),
+        alignment: 1,
+        flow_direction: 1,
+        background_color: CmColor::from_color(Color::Index(5)),
+        background_scale: 1.5,
+        background_transparency: 0,
+        background_enabled: false,
+        background_mask_fill: false,
+        column_type: 0,
+        auto_height: false,
+        column_width: 0.0,
+        column_gutter: 0.0,
+        columns_reversed: false,
+        column_sizes: vec![],
+        word_break: true,
+        unknown: false,
+    };
+    let mut multileader = MultiLeader {
+        header: CommonEntityData::new(0x100, 0, owner, 0x10),
+        class_version: 0,
+        context: MLeaderContext {
+            roots: vec![LeaderRoot {
+                content_valid: true,
+                unknown: true,
+                connection: Point3::from((4.0, 2.0, 0.0)),
+                direction: Point3::from((1.0, 0.0, 0.0)),
+                breaks: vec![],
+                index: 0,
+                landing_distance: 0.36,
+                lines: vec![
+                    line(&[(0.0, 0.0), (3.64, 2.0)], 0),
+                    line(&[(0.0, 4.0), (3.64, 2.0)], 1),
+                ],
+                attachment_direction: 0,
+            }],
+            scale: 1.0,
+            content_base: Point3::from((4.0, 2.0, 0.0)),
+            text_height: 0.18,
+            arrowhead_size: 0.18,
+            landing_gap: 0.09,
+            text_left_attachment: 1,
+            text_right_attachment: 1,
+            text_alignment: 0,
+            attachment_type: 0,
+            content: MLeaderContent::MText(text),
+            base_point: Point3::default(),
+            base_direction: Point3::from((1.0, 0.0, 0.0)),
+            base_vertical: Point3::from((0.0, 1.0, 0.0)),
+            normal_reversed: false,
+            text_top_attachment: 0,
+            text_bottom_attachment: 0,
+        },
+        style: hard(0x40),
+        override_flags: 0,
+        leader_type: 1,
+        line_color: by_block.clone(),
+        line_type: hard(0x14),
+        line_weight: -2,
+        landing_enabled: true,
+        dogleg_enabled: true,
+        landing_distance: 0.36,
+        arrowhead: hard(0),
+        arrowhead_size: 0.18,
+        content_type: 2,
+        text_style: hard(0x11),
+        text_left_attachment: 1,
+        text_right_attachment: 1,
+        text_angle_type: 1,
+        text_alignment: 0,
+        text_color: by_block.clone(),
+        text_frame: false,
+        block: hard(0),
+        block_color: by_block.clone(),
+        block_scale: Point3::from((1.0, 1.0, 1.0)),
+        block_rotation: 0.0,
+        block_connection: 0,
+        annotative: false,
+        arrowheads: vec![],
+        block_labels: vec![],
+        text_direction_negative: false,
+        ipe_alignment: 0,
+        justification: 0,
+        scale_factor: 1.0,
+        attachment_direction: 0,
+        text_top_attachment: 0,
+        text_bottom_attachment: 0,
+        extended_to_text: false,
+    };
+    let round_trip = |multileader: &MultiLeader, version| {
+        let data = encode_entity(500, multileader, version);
+        let decoded = decode_object(&data, 500, version, CodePage::ANSI1252);
+        assert_eq!(decoded, Ok(multileader.clone()), "{version:?}");
+    };
+    round_trip(&multileader, DWGVersion::AC1021);
+
+    // A single straight leader line with no content, before AC1024 adds the line styles. The
+    // class number of 500 is stored as a BS of 00 and an RS
+    let version = DWGVersion::AC1018;
+    let then_by_block = |bits: Bits| bits.cmc(version, 0, 0xC1000000);
+    let context = Bits::default()
+        .bl(1)
+        .b(true)
+        .b(true)
+        .p3((4.0, 2.0, 0.0))
+        .p3((1.0, 0.0, 0.0))
+        .bl(0)
+        .bl(0)
+        .bd(0.36)
+        .bl(1)
+        .bl(2)
+        .p3((0.0, 0.0, 0.0))
+        .p3((3.64, 2.0, 0.0))
+        .bl(0)
+        .bl(0)
+        .bd(1.0)
+        .p3((4.0, 2.0, 0.0))
+        .bd(0.18)
+        .bd(0.18)
+        .bd(0.09)
+        .bs(1)
+        .bs(1)
+        .bs(0)
+        .bs(0)
+        .b(false)
+        .b(false)
+        .p3((0.0, 0.0, 0.0))
+        .p3((1.0, 0.0, 0.0))
+        .p3((0.0, 1.0, 0.0))
+        .b(false);
+    let leader = then_by_block(context.bl(0).bs(1))
+        .bl(-2)
+        .b(true)
+        .b(true)
+        .bd(0.36);
+    let text = then_by_block(leader.bd(0.18).bs(0).bs(1).bs(1).bs(1).bs(0));
+    let block = then_by_block(text.b(false))
+        .p3((1.0, 1.0, 1.0))
+        .bd(0.0)
+        .bs(0);
+    let data = block.b(false).bl(0).bl(0).b(false).bs(0).bs(0).bd(1.0);
+    let handles = Bits::default()
+        .h(5, 0x40)
+        .h(5, 0x14)
+        .h(5, 0)
+        .h(5, 0x11)
+        .h(5, 0);
+    let expected = pack_entity(version, 500, data, handles);
+    let mut single = MultiLeader {
+        header: entity_header(0x30, version),
+        content_type: 0,
+        ..multileader.clone()
+    };
+    single.context.roots[0].lines.truncate(1);
+    single.context.content = MLeaderContent::None;
+    assert_eq!(encode_entity(500, &single, version), expected);
+    let decoded = decode_object(&expected, 500, version, CodePage::ANSI1252);
+    assert_eq!(decoded, Ok(single));
+
+    // Leader lines carry their own style from AC1024, and block content its ATTDEF values
+    multileader.class_version = 2;
+    multileader.context.roots[0].attachment_direction = 1;
+    multileader.context.roots[0].lines[1].breaks = vec![(
+        0,
+        vec![(Point3::from((1.0, 3.5, 0.0)), Point3::from((1.5, 3.2, 0.0)))],
+    )];
+    multileader.context.roots[0].lines[0].style = Some(LeaderLineStyle {
+        leader_type: 2,
+        color: CmColor::from_color(Color::Index(1)),
+        line_type: hard(0x15),
+        line_weight: 25,
+        arrowhead_size: 0.25,
+        arrowhead: hard(0x50),
+        override_flags: 0x7,
+    });
+    multileader.context.roots[0].lines[1].style = Some(LeaderLineStyle {
+        leader_type: 1,
+        color: by_block,
+        line_type: hard(0),
+        line_weight: -1,
+        arrowhead_size: 0.0,
+        arrowhead: hard(0),
+        override_flags: 0,
+    });
+    multileader.context.text_top_attachment = 9;
+    multileader.context.text_bottom_attachment = 9;
+    multileader.text_top_attachment = 9;
+    multileader.text_bottom_attachment = 9;
+    multileader.attachment_direction = 1;
+    round_trip(&multileader, DWGVersion::AC1024);
+
+    multileader.content_type = 1;
+    multileader.block = hard(0x60);
+    multileader.context.content = MLeaderContent::Block(MLeaderBlock {
+        block_header: hard(0x60),
+        normal: Point3::from((0.0, 0.0, 1.0)),
+        location: Point3::from((4.5, 2.0, 0.0)),
+        scale: Point3::from((1.0, 1.0, 1.0)),
+        rotation: 0.0,
+        color: CmColor::from_color(Color::ByBlock),
+        transform: std::array::from_fn(|i| if i % 5 == 0 { 1.0 } else { 0.0 }),
+    });
+    multileader.arrowheads = vec![(true, hard(0)), (false, hard(0x50))];
+    multileader.block_labels = vec![BlockLabel {
+        attdef: hard(0x61),
+        text: "A1".into(),
+        ui_index: 0,
+        width: 0.0,
+    }];
+    multileader.extended_to_text = true;
+    round_trip(&multileader, DWGVersion::AC1027);
+
+    // The type is looked up through the classes of the drawing
+    let version = DWGVersion::AC1018;
+    let mut dwg = Dwg::new(version).unwrap();
+    let class = DwgClass {
+        number: 500,
+        proxy_flags: 0xC00,
+        app_name: "ObjectDBX Classes".into(),
+        cpp_class_name: "AcDbMLeader".into(),
+        dxf_name: "MULTILEADER".into(),
+        was_zombie: false,
+        item_class_id: ENTITY_CLASS_ID,
+        instance_count: 1,
+        dwg_version: 0,
+        maintenance_version: 0,
+    };
+    dwg.classes = write_classes(&[class], version, dwg.codepage).unwrap();
+    let data = encode_entity(500, &multileader, version);
+    let Ok(expected) = decode_object::<MultiLeader>(&data, 500, version, dwg.codepage) else {
+        panic!("not a MULTILEADER");
+    };
+    dwg.insert_object(0x100, 500, &multileader).unwrap();
+    assert_eq!(dwg.multileader(0x100), Ok(expected));
+    assert!(dwg.multileader(0x100).unwrap().context.roots[0].lines[0]
+        .style
+        .is_none());
+    // LAYER 0
+    assert!(matches!(
+        dwg.multileader(0xF),
+        Err(DwgError::InvalidFlag { .. })
+    ));
+}
@@ -0,0 +1,182 @@
+//! Errors that can occur while reading a DWG
+use std::{fmt, io};
+
+use crate::dwg::SecondHeaderWarning;
+
+/// An error encountered while reading a DWG
+///
+/// Positions are in bits relative to the start of the stream being read, see
+/// [`crate::bitcodes::BitReader::bit_position`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DwgError {
+    /// The stream ended before a value could be fully read
+    UnexpectedEof { at_bit: u64 },
+    /// A flag or code held a value that is not valid for the type being read
+    InvalidFlag { at_bit: u64, value: u64 },
+    /// A variable length value does not fit in the type being read
+    Overflow { at_bit: u64 },
+    /// A sentinel did not match its expected value
+    BadSentinel { at_bit: u64 },
+    /// The magic number is not a DWG version that can be read
+    UnsupportedVersion { found: String },
+    /// The underlying source does not support seeking
+    SeekUnsupported,
+    /// Attempted to seek past the end of the stream
+    SeekOutOfRange { offset: u64 },
+    /// A read went past the end of a reader bounded by
+    /// [`crate::bitcodes::BitReader::take_bits`]
+    BoundExceeded { at_bit: u64, end_bit: u64 },
+    /// The size of a string or handle stream does not fit within its object
+    InvalidStreamSize { at_bit: u64 },
+    /// The CRC of a section page did not match the CRC stored in the file
+    SectionCrcMismatch {
+        page: u32,
+        expected: u32,
+        found: u32,
+    },
+    /// The CRC of an R13-R2000 file header did not match the CRC stored after its locator
+    /// records
+    FileHeaderCrcMismatch { expected: u16, found: u16 },
+    /// The CRC following the data of an object did not match the CRC of the data
+    ObjectCrcMismatch {
+        handle: u64,
+        expected: u16,
+        found: u16,
+    },
+    /// A checksum of a data page of an AC1018 (R2004) or later file did not match the
+    /// checksum stored in the page header
+    PageChecksumMismatch {
+        section: String,
+        page: i32,
+        expected: u32,
+        found: u32,
+    },
+    /// A Reed-Solomon coded block of an AC1021 (R2007) or later file has too many errors to
+    /// correct
+    UncorrectableBlock { block: u32 },
+    /// The drawing holds data encoded for its version that cannot be converted to another
+    ConversionUnsupported { from: String, to: String },
+    /// An object of the drawing is of a type that is not typed, so it cannot be converted to
+    /// another version
+    ObjectConversionUnsupported { handle: u64, object_type: i16 },
+    /// An object the drawing needs, such as a table or a table entry, is missing or is not
+    /// of the expected type
+    MissingObject { handle: u64 },
+    /// A page of an AC1018 (R2004) or later file is not listed in the page map
+    MissingSectionPage { number: i32 },
+    /// A value does not fit in the datatype it is being written as, such as text too long
+    /// for its BS length
+    ValueOutOfRange { at_bit: u64 },
+    /// A CRC was written without first marking the start of the data it covers
+    CrcNotStarted,
+    /// The drawing is protected by a password, with its data or properties encrypted as the
+    /// security `flags` of the file header say, see [`crate::dwg::ENCRYPT_DATA`]
+    Encrypted { flags: u32 },
+    /// The second header of an R13-R2000 file does not match its data or the rest of the
+    /// file
+    SecondHeaderMismatch(SecondHeaderWarning),
+    /// An IO error from the underlying source
+    Io(io::ErrorKind),
+}
+
+pub type Result<T> = std::result::Result<T, DwgError>;
+
+impl fmt::Display for DwgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DwgError::UnexpectedEof { at_bit } => {
+                write!(f, "unexpected end of stream at bit {at_bit}")
+            }
+            DwgError::InvalidFlag { at_bit, value } => {
+                write!(f, "invalid flag value {value:#x} at bit {at_bit}")
+            }
+            DwgError::Overflow { at_bit } => write!(f, "value overflow at bit {at_bit}"),
+            DwgError::BadSentinel { at_bit } => write!(f, "bad sentinel at bit {at_bit}"),
+            DwgError::UnsupportedVersion { found } => {
+                write!(f, "unsupported DWG version {found:?}")
+            }
+            DwgError::SeekUnsupported => write!(f, "source does not support seeking"),
+            DwgError::SeekOutOfRange { offset } => {
+                write!(f, "seek to bit {offset} is past the end of the stream")
+            }
+            DwgError::BoundExceeded { at_bit, end_bit } => {
+                write!(f, "read at bit {at_bit} past the bound at bit {end_bit}")
+            }
+            DwgError::InvalidStreamSize { at_bit } => {
+                write!(f, "invalid stream size for stream ending at bit {at_bit}")
+            }
+            DwgError::SectionCrcMismatch {
+                page,
+                expected,
+                found,
+            } => write!(
+                f,
+                "CRC mismatch in section page {page}, expected {expected:#x} but found {found:#x}"
+            ),
+            DwgError::FileHeaderCrcMismatch { expected, found } => write!(
+                f,
+                "file header CRC mismatch, expected {expected:#x} but found {found:#x}"
+            ),
+            DwgError::ObjectCrcMismatch {
+                handle,
+                expected,
+                found,
+            } => write!(
+                f,
+                "CRC mismatch in object {handle:#x}, expected {expected:#x} but found {found:#x}"
+            ),
+            DwgError::PageChecksumMismatch {
+                section,
+                page,
+                expected,
+                found,
+            } => write!(
+                f,
+                "checksum mismatch in page {page} of section {section:?}, expected {expected:#x} \
+                 but found {found:#x}"
+            ),
+            DwgError::UncorrectableBlock { block } => {
+                write!(
+                    f,
+                    "too many errors to correct in Reed-Solomon block {block}"
+                )
+            }
+            DwgError::ConversionUnsupported { from, to } => {
+                write!(f, "cannot convert drawing from {from} to {to}")
+            }
+            DwgError::ObjectConversionUnsupported {
+                handle,
+                object_type,
+            } => write!(
+                f,
+                "cannot convert object {handle:#x} of untyped type {object_type:#x}"
+            ),
+            DwgError::MissingObject { handle } => {
+                write!(f, "missing object with handle {handle:#x}")
+            }
+            DwgError::MissingSectionPage { number } => {
+                write!(f, "section page {number} is not in the page map")
+            }
+            DwgError::ValueOutOfRange { at_bit } => {
+                write!(
+                    f,
+                    "value written at bit {at_bit} is out of range for its type"
+                )
+            }
+            DwgError::CrcNotStarted => write!(f, "CRC written before its start was set"),
+            DwgError::Encrypted { flags } => {
+                write!(f, "drawing is encrypted with security flags {flags:#x}")
+            }
+            DwgError::SecondHeaderMismatch(warning) => write!(f, "{warning}"),
+            DwgError::Io(kind) => write!(f, "io error: {kind}"),
+        }
+    }
+}
+
+impl std::error::Error for DwgError {}
+
+impl From<io::Error> for DwgError {
+    fn from(err: io::Error) -> Self {
+        DwgError::Io(err.kind())
+    }
+}
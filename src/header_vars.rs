@@ -0,0 +1,1555 @@
+//! The header variables section, holding the settings of a drawing and the handles of its
+//! tables and dictionaries
+//!
+//! Variables are stored one after another with no names or types, in the order of chapter 9
+//! of the ODS, which differs between versions. From AC1021 (R2007) the section is split into
+//! streams as objects are: it starts with the RL size of the data in bits, text is stored in
+//! a string stream at the end of the data and handles other than HANDSEED in a handle stream
+//! after it
+#[cfg(test)]
+use crate::bitcodes::Bits;
+use crate::{
+    bitcodes::BitSliceReader,
+    bitcodes_write::BitWriter,
+    dwg::Dwg,
+    error::Result,
+    streams::{join_section_streams, DualStreamReader},
+    types::{CmColor, CodePage, HandleRef, JulianDate, Point2, Point3, RawBits},
+    version::DWGVersion,
+};
+
+/// The value of a header variable, by the type it is stored as
+#[derive(Clone, Debug, PartialEq)]
+pub enum HeaderValue {
+    /// B
+    Bit(bool),
+    /// RC
+    Char(u8),
+    /// BS
+    Short(i16),
+    /// BL
+    Long(i32),
+    /// BLL
+    LongLong(i64),
+    /// BD
+    Double(f64),
+    /// TV
+    Text(String),
+    /// H
+    Handle(HandleRef),
+    /// 2RD
+    Point2(Point2),
+    /// 3BD
+    Point3(Point3),
+    /// CMC
+    Color(CmColor),
+    /// Two BLs, the day and milliseconds into the day
+    Date(JulianDate),
+}
+
+impl HeaderValue {
+    /// Returns the type the value is stored as
+    fn kind(&self) -> Kind {
+        match self {
+            Self::Bit(_) => Kind::Bit,
+            Self::Char(_) => Kind::Char,
+            Self::Short(_) => Kind::Short,
+            Self::Long(_) => Kind::Long,
+            Self::LongLong(_) => Kind::LongLong,
+            Self::Double(_) => Kind::Double,
+            Self::Text(_) => Kind::Text,
+            Self::Handle(_) => Kind::Handle,
+            Self::Point2(_) => Kind::Point2,
+            Self::Point3(_) => Kind::Point3,
+            Self::Color(_) => Kind::Color,
+            Self::Date(_) => Kind::Date,
+        }
+    }
+}
+
+/// The type a header variable is stored as, see [`HeaderValue`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Bit,
+    Char,
+    Short,
+    Long,
+    LongLong,
+    Double,
+    Text,
+    Handle,
+    Point2,
+    Point3,
+    Color,
+    Date,
+}
+
+/// The header variables of a drawing, see [`Dwg::header_variables`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HeaderVariables {
+    /// Each variable in the order it is stored, by its DXF name without the `$`
+    ///
+    /// Variables with no DXF name are named after what they hold, such as `LAYER_CONTROL`,
+    /// and those whose meaning is unknown are all named `UNKNOWN`
+    pub values: Vec<(&'static str, HeaderValue)>,
+    /// Whatever follows the last variable known for the version, written back after it
+    ///
+    /// Later releases may store variables this does not know of there, which are kept so
+    /// they are not lost when the drawing is saved again
+    pub trailing: TrailingBits,
+}
+
+/// The bits following the last known header variable in each stream, see
+/// [`HeaderVariables::trailing`]
+///
+/// Only the data stream is kept before AC1021 (R2007), as there are no others. It includes
+/// the padding to the end of the last byte, as does the handle stream from AC1021
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TrailingBits {
+    pub data: RawBits,
+    pub strings: RawBits,
+    pub handles: RawBits,
+}
+
+impl HeaderVariables {
+    /// Reads the header variables section data of a drawing of `version`
+    pub fn read(data: &[u8], version: DWGVersion, codepage: CodePage) -> Result<Self> {
+        let mut data = BitSliceReader::from_slice(data);
+        data.set_version(version);
+        data.set_codepage(codepage);
+        let mut reader = DualStreamReader::section(data)?;
+        let mut values = Vec::new();
+        let mut has_cpsnid = false;
+        for (name, kind) in layout(version) {
+            // The plot style is only stored if CEPSNTYPE says it is set by handle
+            if name == "CPSNID" && !has_cpsnid {
+                continue;
+            }
+            let value = match kind {
+                Kind::Bit => HeaderValue::Bit(reader.read_bit()? == 1),
+                Kind::Char => HeaderValue::Char(reader.read_raw_char()? as u8),
+                Kind::Short => HeaderValue::Short(reader.read_bitshort()?),
+                Kind::Long => HeaderValue::Long(reader.read_bitlong()?),
+                Kind::LongLong => HeaderValue::LongLong(reader.read_bitlonglong()?),
+                Kind::Double => HeaderValue::Double(reader.read_bitdouble()?),
+                Kind::Text => HeaderValue::Text(reader.read_text()?),
+                // HANDSEED stays in the data stream
+                Kind::Handle if name == "HANDSEED" => {
+                    HeaderValue::Handle(BitSliceReader::read_handle(&mut reader)?)
+                }
+                Kind::Handle => HeaderValue::Handle(reader.read_handle()?),
+                Kind::Point2 => HeaderValue::Point2(reader.read_2rd()?),
+                Kind::Point3 => HeaderValue::Point3(reader.read_3bd()?),
+                Kind::Color => HeaderValue::Color(read_color(&mut reader)?),
+                Kind::Date => HeaderValue::Date(reader.read_bl_date()?),
+            };
+            if name == "CEPSNTYPE" {
+                has_cpsnid = value == HeaderValue::Short(3);
+            }
+            values.push((name, value));
+        }
+
+        let data_end = reader.data_end();
+        let mut trailing = TrailingBits {
+            data: read_rest(&mut reader, data_end)?,
+            ..Default::default()
+        };
+        if version >= DWGVersion::AC1021 {
+            let strings_end = reader.strings_end();
+            trailing.strings = read_rest(reader.strings(), strings_end)?;
+            let end = reader.data().len() as u64 * 8;
+            trailing.handles = read_rest(reader.handles(), end)?;
+        }
+        Ok(Self { values, trailing })
+    }
+
+    /// Writes the header variables section data for a drawing of `version`, the inverse of
+    /// [`HeaderVariables::read`]
+    ///
+    /// Each value is written as its type is stored, so the values must be in the order and
+    /// of the types they are read as for `version`
+    pub fn write(&self, version: DWGVersion, codepage: CodePage) -> Result<Vec<u8>> {
+        let new_writer = || {
+            let mut writer = BitWriter::new();
+            writer.set_version(version);
+            writer
+        };
+        let (mut data, mut strings, mut handles) = (new_writer(), new_writer(), new_writer());
+        let split = version >= DWGVersion::AC1021;
+        for (name, value) in &self.values {
+            match value {
+                HeaderValue::Bit(value) => data.write_bit(*value as u8),
+                HeaderValue::Char(value) => data.write_raw_char(*value as i8),
+                HeaderValue::Short(value) => data.write_bitshort(*value),
+                HeaderValue::Long(value) => data.write_bitlong(*value),
+                HeaderValue::LongLong(value) => data.write_bitlonglong(*value)?,
+                HeaderValue::Double(value) => data.write_bitdouble(*value),
+                HeaderValue::Text(value) if split => strings.write_text(value, codepage)?,
+                HeaderValue::Text(value) => data.write_text(value, codepage)?,
+                HeaderValue::Handle(value) if split && *name != "HANDSEED" => {
+                    handles.write_handle(*value)
+                }
+                HeaderValue::Handle(value) => data.write_handle(*value),
+                HeaderValue::Point2(value) => data.write_2rd(*value),
+                HeaderValue::Point3(value) => data.write_3bd(*value),
+                HeaderValue::Color(value) if split => {
+                    write_color(&mut data, &mut strings, value, codepage)?
+                }
+                HeaderValue::Color(value) => data.write_cm_color(value, codepage)?,
+                HeaderValue::Date(value) => data.write_bl_date(*value),
+            }
+        }
+        data.write_raw_bits(&self.trailing.data);
+        if !split {
+            return Ok(data.into_bytes());
+        }
+        strings.write_raw_bits(&self.trailing.strings);
+        handles.write_raw_bits(&self.trailing.handles);
+        join_section_streams(data, strings, handles)
+    }
+
+    /// Returns the variables as a drawing of `version` stores them, see [`Dwg::save_as`]
+    ///
+    /// Each variable is taken from these variables if they have one of the same name and
+    /// type, otherwise from `defaults`, which must be variables of `version`. Those named
+    /// `UNKNOWN` are matched by their position among the others of the same type. The
+    /// trailing bits are left out, as they are only known to follow the variables of the
+    /// version they were read as
+    pub(crate) fn convert(&self, version: DWGVersion, defaults: &Self) -> Self {
+        let nth = |values: &[(&'static str, HeaderValue)], name: &str, kind, n| {
+            let mut matching = values
+                .iter()
+                .filter(|(found, value)| *found == name && value.kind() == kind);
+            matching.nth(n).map(|(_, value)| value.clone())
+        };
+        let mut values: Vec<(&'static str, HeaderValue)> = Vec::new();
+        for (name, kind) in layout(version) {
+            let cepsntype = nth(&values, "CEPSNTYPE", Kind::Short, 0);
+            if name == "CPSNID" && cepsntype != Some(HeaderValue::Short(3)) {
+                continue;
+            }
+            let n = values
+                .iter()
+                .filter(|(found, value)| *found == name && value.kind() == kind)
+                .count();
+            let value =
+                nth(&self.values, name, kind, n).or_else(|| nth(&defaults.values, name, kind, n));
+            if let Some(value) = value {
+                values.push((name, value));
+            }
+        }
+        Self {
+            values,
+            trailing: TrailingBits::default(),
+        }
+    }
+
+    /// Returns the value of the variable `name`, if the drawing stores it
+    pub fn get(&self, name: &str) -> Option<&HeaderValue> {
+        self.values
+            .iter()
+            .find(|(found, _)| *found == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the value of the variable `name` to be changed, see [`HeaderVariables::get`]
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut HeaderValue> {
+        self.values
+            .iter_mut()
+            .find(|(found, _)| *found == name)
+            .map(|(_, value)| value)
+    }
+}
+
+/// Drawing units, the value of INSUNITS
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Units {
+    Unitless,
+    Inches,
+    Feet,
+    Miles,
+    Millimeters,
+    Centimeters,
+    Meters,
+    Kilometers,
+    Microinches,
+    Mils,
+    Yards,
+    Angstroms,
+    Nanometers,
+    Microns,
+    Decimeters,
+    Decameters,
+    Hectometers,
+    Gigameters,
+    AstronomicalUnits,
+    LightYears,
+    Parsecs,
+    UsSurveyFeet,
+    UsSurveyInches,
+    UsSurveyYards,
+    UsSurveyMiles,
+}
+
+impl Units {
+    /// Every unit, in the order of their codes
+    const ALL: [Units; 25] = [
+        Self::Unitless,
+        Self::Inches,
+        Self::Feet,
+        Self::Miles,
+        Self::Millimeters,
+        Self::Centimeters,
+        Self::Meters,
+        Self::Kilometers,
+        Self::Microinches,
+        Self::Mils,
+        Self::Yards,
+        Self::Angstroms,
+        Self::Nanometers,
+        Self::Microns,
+        Self::Decimeters,
+        Self::Decameters,
+        Self::Hectometers,
+        Self::Gigameters,
+        Self::AstronomicalUnits,
+        Self::LightYears,
+        Self::Parsecs,
+        Self::UsSurveyFeet,
+        Self::UsSurveyInches,
+        Self::UsSurveyYards,
+        Self::UsSurveyMiles,
+    ];
+
+    /// Returns the unit stored as `code`, or None if it is not a known unit
+    pub fn from_code(code: i16) -> Option<Units> {
+        usize::try_from(code)
+            .ok()
+            .and_then(|code| Self::ALL.get(code).copied())
+    }
+
+    /// Returns the code the unit is stored as, the inverse of [`Units::from_code`]
+    pub fn code(self) -> i16 {
+        self as i16
+    }
+}
+
+/// The dimension variables new dimensions default to, see
+/// [`HeaderVariables::dimension_defaults`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DimensionDefaults {
+    /// DIMSCALE, the factor applied to every size below
+    pub scale: f64,
+    /// DIMASZ
+    pub arrow_size: f64,
+    /// DIMTXT
+    pub text_height: f64,
+    /// DIMGAP, between the text and the dimension line
+    pub text_gap: f64,
+    /// DIMEXO, between the extension lines and the points they start from
+    pub extension_offset: f64,
+    /// DIMEXE, past the dimension line
+    pub extension_extension: f64,
+    /// DIMLFAC, applied to measured lengths
+    pub linear_factor: f64,
+    /// DIMDEC, decimal places of measurements
+    pub decimal_places: i16,
+    /// DIMTXSTY, the handle of the text style
+    pub text_style: u64,
+}
+
+impl HeaderVariables {
+    /// Minimum corner of the extents of model space, EXTMIN
+    pub fn extmin(&self) -> Option<Point3> {
+        self.point3("EXTMIN")
+    }
+
+    /// Maximum corner of the extents of model space, EXTMAX
+    pub fn extmax(&self) -> Option<Point3> {
+        self.point3("EXTMAX")
+    }
+
+    /// Minimum corner of the limits of model space, LIMMIN
+    pub fn limmin(&self) -> Option<Point2> {
+        match self.get("LIMMIN")? {
+            HeaderValue::Point2(point) => Some(*point),
+            _ => None,
+        }
+    }
+
+    /// Maximum corner of the limits of model space, LIMMAX
+    pub fn limmax(&self) -> Option<Point2> {
+        match self.get("LIMMAX")? {
+            HeaderValue::Point2(point) => Some(*point),
+            _ => None,
+        }
+    }
+
+    /// Insertion base point of model space, INSBASE
+    pub fn insbase(&self) -> Option<Point3> {
+        self.point3("INSBASE")
+    }
+
+    /// Units of the drawing, INSUNITS, which AC1015 (R2000) and later store
+    pub fn insunits(&self) -> Option<Units> {
+        Units::from_code(self.short("INSUNITS")?)
+    }
+
+    /// Handle of the current layer, CLAYER
+    pub fn clayer(&self) -> Option<u64> {
+        self.handle("CLAYER")
+    }
+
+    /// Handle of the current linetype, CELTYPE
+    pub fn celtype(&self) -> Option<u64> {
+        self.handle("CELTYPE")
+    }
+
+    /// Handle of the current text style, TEXTSTYLE
+    pub fn textstyle(&self) -> Option<u64> {
+        self.handle("TEXTSTYLE")
+    }
+
+    /// Handle of the current dimension style, DIMSTYLE
+    pub fn dimstyle(&self) -> Option<u64> {
+        self.handle("DIMSTYLE")
+    }
+
+    /// The next handle to be given to a new object, HANDSEED
+    pub fn handseed(&self) -> Option<u64> {
+        self.handle("HANDSEED")
+    }
+
+    /// Global linetype scale, LTSCALE
+    pub fn ltscale(&self) -> Option<f64> {
+        self.double("LTSCALE")
+    }
+
+    /// Default height of new text, TEXTSIZE
+    pub fn textsize(&self) -> Option<f64> {
+        self.double("TEXTSIZE")
+    }
+
+    /// When the drawing was created, TDCREATE
+    pub fn tdcreate(&self) -> Option<JulianDate> {
+        match self.get("TDCREATE")? {
+            HeaderValue::Date(date) => Some(*date),
+            _ => None,
+        }
+    }
+
+    /// When the drawing was last saved, TDUPDATE
+    pub fn tdupdate(&self) -> Option<JulianDate> {
+        match self.get("TDUPDATE")? {
+            HeaderValue::Date(date) => Some(*date),
+            _ => None,
+        }
+    }
+
+    /// Returns the dimension variables new dimensions default to, or None if any is missing
+    pub fn dimension_defaults(&self) -> Option<DimensionDefaults> {
+        Some(DimensionDefaults {
+            scale: self.double("DIMSCALE")?,
+            arrow_size: self.double("DIMASZ")?,
+            text_height: self.double("DIMTXT")?,
+            text_gap: self.double("DIMGAP")?,
+            extension_offset: self.double("DIMEXO")?,
+            extension_extension: self.double("DIMEXE")?,
+            linear_factor: self.double("DIMLFAC")?,
+            decimal_places: self.short("DIMDEC")?,
+            text_style: self.handle("DIMTXSTY")?,
+        })
+    }
+
+    fn double(&self, name: &str) -> Option<f64> {
+        match self.get(name)? {
+            HeaderValue::Double(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns a BS variable, or an RC variable R13 and R14 store some as
+    fn short(&self, name: &str) -> Option<i16> {
+        match self.get(name)? {
+            HeaderValue::Short(value) => Some(*value),
+            HeaderValue::Char(value) => Some(*value as i16),
+            _ => None,
+        }
+    }
+
+    fn point3(&self, name: &str) -> Option<Point3> {
+        match self.get(name)? {
+            HeaderValue::Point3(point) => Some(*point),
+            _ => None,
+        }
+    }
+
+    fn handle(&self, name: &str) -> Option<u64> {
+        match self.get(name)? {
+            HeaderValue::Handle(handle) => Some(handle.handle),
+            _ => None,
+        }
+    }
+}
+
+impl Dwg {
+    /// Reads the header variables of the drawing, see [`HeaderVariables::read`]
+    pub fn header_variables(&self) -> Result<HeaderVariables> {
+        HeaderVariables::read(&self.header_vars, self.version(), self.codepage)
+    }
+}
+
+/// Reads the rest of a stream of the header variables, up to `end`
+fn read_rest(reader: &mut BitSliceReader, end: u64) -> Result<RawBits> {
+    reader.read_raw_bits(end.saturating_sub(reader.bit_position()))
+}
+
+/// Reads a CMC, whose names are in the string stream from AC1021 (R2007), see
+/// [`crate::bitcodes::BitReader::read_cm_color`]
+fn read_color(reader: &mut DualStreamReader) -> Result<CmColor> {
+    if reader.get_version() < DWGVersion::AC1021 {
+        return reader.read_cm_color();
+    }
+    let index = reader.read_bitshort()?;
+    let rgb = reader.read_bitlong()? as u32;
+    let flags = reader.read_raw_char()? as u8;
+    let mut read_name = |flag: u8| match flags & flag {
+        0 => Ok(None),
+        _ => reader.read_text().map(Some),
+    };
+    Ok(CmColor {
+        index,
+        rgb,
+        flags,
+        name: read_name(0x1)?,
+        book_name: read_name(0x2)?,
+    })
+}
+
+/// Writes a CMC to `data` with its names in `strings`, the inverse of `read_color` for
+/// AC1021 (R2007) and later
+fn write_color(
+    data: &mut BitWriter,
+    strings: &mut BitWriter,
+    value: &CmColor,
+    codepage: CodePage,
+) -> Result<()> {
+    let inline = CmColor {
+        name: None,
+        book_name: None,
+        ..value.clone()
+    };
+    data.write_cm_color(&inline, codepage)?;
+    if value.method() == 0 {
+        return Ok(());
+    }
+    for (flag, name) in [(0x1, &value.name), (0x2, &value.book_name)] {
+        if let (true, Some(name)) = (value.flags & flag != 0, name) {
+            strings.write_text(name, codepage)?;
+        }
+    }
+    Ok(())
+}
+
+/// The names and types of the header variables of `version`, in the order they are stored
+fn layout(version: DWGVersion) -> Vec<(&'static str, Kind)> {
+    use Kind::*;
+
+    let r13_r14 = version < DWGVersion::AC1015;
+    let r2000 = !r13_r14;
+    let r2004 = version >= DWGVersion::AC1018;
+    let r2007 = version >= DWGVersion::AC1021;
+    let r2010 = version >= DWGVersion::AC1024;
+    let r2013 = version >= DWGVersion::AC1027;
+    let mut layout = Vec::new();
+    let mut add = |stored: bool, kind: Kind, names: &[&'static str]| {
+        if stored {
+            layout.extend(names.iter().map(|&name| (name, kind)));
+        }
+    };
+
+    add(r2013, LongLong, &["REQUIREDVERSIONS"]);
+    add(true, Double, &["UNKNOWN"; 4]);
+    add(true, Text, &["UNKNOWN"; 4]);
+    add(true, Long, &["UNKNOWN"; 2]);
+    add(r13_r14, Short, &["UNKNOWN"]);
+    add(!r2004, Handle, &["VIEWPORT_ENTITY_HEADER"]);
+    add(true, Bit, &["DIMASO", "DIMSHO"]);
+    add(r13_r14, Bit, &["DIMSAV"]);
+    add(
+        true,
+        Bit,
+        &[
+            "PLINEGEN",
+            "ORTHOMODE",
+            "REGENMODE",
+            "FILLMODE",
+            "QTEXTMODE",
+            "PSLTSCALE",
+            "LIMCHECK",
+        ],
+    );
+    add(r13_r14, Bit, &["BLIPMODE"]);
+    add(r2004, Bit, &["UNKNOWN"]);
+    add(true, Bit, &["USRTIMER", "SKPOLY", "ANGDIR", "SPLFRAME"]);
+    add(r13_r14, Bit, &["ATTREQ", "ATTDIA"]);
+    add(true, Bit, &["MIRRTEXT", "WORLDVIEW"]);
+    add(r13_r14, Bit, &["WIREFRAME"]);
+    add(true, Bit, &["TILEMODE", "PLIMCHECK", "VISRETAIN"]);
+    add(r13_r14, Bit, &["DELOBJ"]);
+    add(true, Bit, &["DISPSILH", "PELLIPSE"]);
+    add(true, Short, &["PROXYGRAPHICS"]);
+    add(r13_r14, Short, &["DRAGMODE"]);
+    add(
+        true,
+        Short,
+        &["TREEDEPTH", "LUNITS", "LUPREC", "AUNITS", "AUPREC"],
+    );
+    add(r13_r14, Short, &["OSMODE"]);
+    add(true, Short, &["ATTMODE"]);
+    add(r13_r14, Short, &["COORDS"]);
+    add(true, Short, &["PDMODE"]);
+    add(r13_r14, Short, &["PICKSTYLE"]);
+    add(r2004, Long, &["UNKNOWN"; 3]);
+    add(
+        true,
+        Short,
+        &[
+            "USERI1",
+            "USERI2",
+            "USERI3",
+            "USERI4",
+            "USERI5",
+            "SPLINESEGS",
+            "SURFU",
+            "SURFV",
+            "SURFTYPE",
+            "SURFTAB1",
+            "SURFTAB2",
+            "SPLINETYPE",
+            "SHADEDGE",
+            "SHADEDIF",
+            "UNITMODE",
+            "MAXACTVP",
+            "ISOLINES",
+            "CMLJUST",
+            "TEXTQLTY",
+        ],
+    );
+    add(
+        true,
+        Double,
+        &[
+            "LTSCALE",
+            "TEXTSIZE",
+            "TRACEWID",
+            "SKETCHINC",
+            "FILLETRAD",
+            "THICKNESS",
+            "ANGBASE",
+            "PDSIZE",
+            "PLINEWID",
+            "USERR1",
+            "USERR2",
+            "USERR3",
+            "USERR4",
+            "USERR5",
+            "CHAMFERA",
+            "CHAMFERB",
+            "CHAMFERC",
+            "CHAMFERD",
+            "FACETRES",
+            "CMLSCALE",
+            "CELTSCALE",
+        ],
+    );
+    add(true, Text, &["MENUNAME"]);
+    add(true, Date, &["TDCREATE", "TDUPDATE"]);
+    add(r2004, Long, &["UNKNOWN"; 3]);
+    add(true, Date, &["TDINDWG", "TDUSRTIMER"]);
+    add(true, Color, &["CECOLOR"]);
+    add(
+        true,
+        Handle,
+        &[
+            "HANDSEED",
+            "CLAYER",
+            "TEXTSTYLE",
+            "CELTYPE",
+            "DIMSTYLE",
+            "CMLSTYLE",
+        ],
+    );
+    add(r2000, Double, &["PSVPSCALE"]);
+
+    // Paper space then model space
+    for [insbase, extmin, extmax, limmin, limmax, elevation, ucs @ ..] in [
+        [
+            "PINSBASE",
+            "PEXTMIN",
+            "PEXTMAX",
+            "PLIMMIN",
+            "PLIMMAX",
+            "PELEVATION",
+            "PUCSORG",
+            "PUCSXDIR",
+            "PUCSYDIR",
+            "PUCSNAME",
+            "PUCSORTHOREF",
+            "PUCSORTHOVIEW",
+            "PUCSBASE",
+            "PUCSORGTOP",
+            "PUCSORGBOTTOM",
+            "PUCSORGLEFT",
+            "PUCSORGRIGHT",
+            "PUCSORGFRONT",
+            "PUCSORGBACK",
+        ],
+        [
+            "INSBASE",
+            "EXTMIN",
+            "EXTMAX",
+            "LIMMIN",
+            "LIMMAX",
+            "ELEVATION",
+            "UCSORG",
+            "UCSXDIR",
+            "UCSYDIR",
+            "UCSNAME",
+            "UCSORTHOREF",
+            "UCSORTHOVIEW",
+            "UCSBASE",
+            "UCSORGTOP",
+            "UCSORGBOTTOM",
+            "UCSORGLEFT",
+            "UCSORGRIGHT",
+            "UCSORGFRONT",
+            "UCSORGBACK",
+        ],
+    ] {
+        let [org, xdir, ydir, name, orthoref, orthoview, base, origins @ ..] = ucs;
+        add(true, Point3, &[insbase, extmin, extmax]);
+        add(true, Point2, &[limmin, limmax]);
+        add(true, Double, &[elevation]);
+        add(true, Point3, &[org, xdir, ydir]);
+        add(true, Handle, &[name]);
+        add(r2000, Handle, &[orthoref]);
+        add(r2000, Short, &[orthoview]);
+        add(r2000, Handle, &[base]);
+        add(r2000, Point3, &origins);
+    }
+
+    add(r2000, Text, &["DIMPOST", "DIMAPOST"]);
+    add(
+        r13_r14,
+        Bit,
+        &[
+            "DIMTOL", "DIMLIM", "DIMTIH", "DIMTOH", "DIMSE1", "DIMSE2", "DIMALT", "DIMTOFL",
+            "DIMSAH", "DIMTIX", "DIMSOXD",
+        ],
+    );
+    add(r13_r14, Char, &["DIMALTD", "DIMZIN"]);
+    add(r13_r14, Bit, &["DIMSD1", "DIMSD2"]);
+    add(r13_r14, Char, &["DIMTOLJ", "DIMJUST", "DIMFIT"]);
+    add(r13_r14, Bit, &["DIMUPT"]);
+    add(r13_r14, Char, &["DIMTZIN", "DIMALTZ", "DIMALTTZ", "DIMTAD"]);
+    add(
+        r13_r14,
+        Short,
+        &[
+            "DIMUNIT", "DIMAUNIT", "DIMDEC", "DIMTDEC", "DIMALTU", "DIMALTTD",
+        ],
+    );
+    add(r13_r14, Handle, &["DIMTXSTY"]);
+    add(
+        true,
+        Double,
+        &[
+            "DIMSCALE", "DIMASZ", "DIMEXO", "DIMDLI", "DIMEXE", "DIMRND", "DIMDLE", "DIMTP",
+            "DIMTM",
+        ],
+    );
+    add(r2007, Double, &["DIMFXL", "DIMJOGANG"]);
+    add(r2007, Short, &["DIMTFILL"]);
+    add(r2007, Color, &["DIMTFILLCLR"]);
+    add(
+        r2000,
+        Bit,
+        &["DIMTOL", "DIMLIM", "DIMTIH", "DIMTOH", "DIMSE1", "DIMSE2"],
+    );
+    add(r2000, Short, &["DIMTAD", "DIMZIN", "DIMAZIN"]);
+    add(r2007, Short, &["DIMARCSYM"]);
+    add(
+        true,
+        Double,
+        &[
+            "DIMTXT", "DIMCEN", "DIMTSZ", "DIMALTF", "DIMLFAC", "DIMTVP", "DIMTFAC", "DIMGAP",
+        ],
+    );
+    add(
+        r13_r14,
+        Text,
+        &["DIMPOST", "DIMAPOST", "DIMBLK", "DIMBLK1", "DIMBLK2"],
+    );
+    add(r2000, Double, &["DIMALTRND"]);
+    add(r2000, Bit, &["DIMALT"]);
+    add(r2000, Short, &["DIMALTD"]);
+    add(r2000, Bit, &["DIMTOFL", "DIMSAH", "DIMTIX", "DIMSOXD"]);
+    add(true, Color, &["DIMCLRD", "DIMCLRE", "DIMCLRT"]);
+    add(
+        r2000,
+        Short,
+        &[
+            "DIMADEC", "DIMDEC", "DIMTDEC", "DIMALTU", "DIMALTTD", "DIMAUNIT", "DIMFRAC",
+            "DIMLUNIT", "DIMDSEP", "DIMTMOVE", "DIMJUST",
+        ],
+    );
+    add(r2000, Bit, &["DIMSD1", "DIMSD2"]);
+    add(r2000, Short, &["DIMTOLJ", "DIMTZIN", "DIMALTZ", "DIMALTTZ"]);
+    add(r2000, Bit, &["DIMUPT"]);
+    add(r2000, Short, &["DIMATFIT"]);
+    add(r2007, Bit, &["DIMFXLON"]);
+    add(r2010, Bit, &["DIMTXTDIRECTION"]);
+    add(r2010, Double, &["DIMALTMZF"]);
+    add(r2010, Text, &["DIMALTMZS"]);
+    add(r2010, Double, &["DIMMZF"]);
+    add(r2010, Text, &["DIMMZS"]);
+    add(
+        r2000,
+        Handle,
+        &["DIMTXSTY", "DIMLDRBLK", "DIMBLK", "DIMBLK1", "DIMBLK2"],
+    );
+    add(r2007, Handle, &["DIMLTYPE", "DIMLTEX1", "DIMLTEX2"]);
+    add(r2000, Short, &["DIMLWD", "DIMLWE"]);
+
+    add(
+        true,
+        Handle,
+        &[
+            "BLOCK_CONTROL",
+            "LAYER_CONTROL",
+            "STYLE_CONTROL",
+            "LTYPE_CONTROL",
+            "VIEW_CONTROL",
+            "UCS_CONTROL",
+            "VPORT_CONTROL",
+            "APPID_CONTROL",
+            "DIMSTYLE_CONTROL",
+        ],
+    );
+    add(!r2004, Handle, &["VP_ENT_HDR_CONTROL"]);
+    add(
+        true,
+        Handle,
+        &[
+            "DICTIONARY_ACAD_GROUP",
+            "DICTIONARY_ACAD_MLINESTYLE",
+            "DICTIONARY_NAMED_OBJECTS",
+        ],
+    );
+    add(r2000, Short, &["TSTACKALIGN", "TSTACKSIZE"]);
+    add(r2000, Text, &["HYPERLINKBASE", "STYLESHEET"]);
+    add(
+        r2000,
+        Handle,
+        &[
+            "DICTIONARY_LAYOUTS",
+            "DICTIONARY_PLOTSETTINGS",
+            "DICTIONARY_PLOTSTYLES",
+        ],
+    );
+    add(
+        r2004,
+        Handle,
+        &["DICTIONARY_MATERIALS", "DICTIONARY_COLORS"],
+    );
+    add(r2007, Handle, &["DICTIONARY_VISUALSTYLE"]);
+    add(r2013, Handle, &["UNKNOWN"]);
+    // CELWEIGHT, ENDCAPS, JOINSTYLE, LWDISPLAY, XEDIT, EXTNAMES, PSTYLEMODE and OLESTARTUP
+    add(r2000, Long, &["FLAGS"]);
+    add(r2000, Short, &["INSUNITS", "CEPSNTYPE"]);
+    add(r2000, Handle, &["CPSNID"]);
+    add(r2000, Text, &["FINGERPRINTGUID", "VERSIONGUID"]);
+    add(
+        r2004,
+        Char,
+        &[
+            "SORTENTS",
+            "INDEXCTL",
+            "HIDETEXT",
+            "XCLIPFRAME",
+            "DIMASSOC",
+            "HALOGAP",
+        ],
+    );
+    add(r2004, Short, &["OBSCUREDCOLOR", "INTERSECTIONCOLOR"]);
+    add(r2004, Char, &["OBSCUREDLTYPE", "INTERSECTIONDISPLAY"]);
+    add(r2004, Text, &["PROJECTNAME"]);
+    add(
+        true,
+        Handle,
+        &[
+            "BLOCK_RECORD_PAPER_SPACE",
+            "BLOCK_RECORD_MODEL_SPACE",
+            "LTYPE_BYLAYER",
+            "LTYPE_BYBLOCK",
+            "LTYPE_CONTINUOUS",
+        ],
+    );
+    add(r2007, Bit, &["CAMERADISPLAY"]);
+    add(r2007, Long, &["UNKNOWN"; 2]);
+    add(
+        r2007,
+        Double,
+        &[
+            "UNKNOWN",
+            "STEPSPERSEC",
+            "STEPSIZE",
+            "3DDWFPREC",
+            "LENSLENGTH",
+            "CAMERAHEIGHT",
+        ],
+    );
+    add(r2007, Char, &["SOLIDHIST", "SHOWHIST"]);
+    add(
+        r2007,
+        Double,
+        &[
+            "PSOLWIDTH",
+            "PSOLHEIGHT",
+            "LOFTANG1",
+            "LOFTANG2",
+            "LOFTMAG1",
+            "LOFTMAG2",
+        ],
+    );
+    add(r2007, Short, &["LOFTPARAM"]);
+    add(r2007, Char, &["LOFTNORMALS"]);
+    add(r2007, Double, &["LATITUDE", "LONGITUDE", "NORTHDIRECTION"]);
+    add(r2007, Long, &["TIMEZONE"]);
+    add(
+        r2007,
+        Char,
+        &[
+            "LIGHTGLYPHDISPLAY",
+            "TILEMODELIGHTSYNCH",
+            "DWFFRAME",
+            "DGNFRAME",
+        ],
+    );
+    add(r2007, Bit, &["UNKNOWN"]);
+    add(r2007, Color, &["INTERFERECOLOR"]);
+    add(
+        r2007,
+        Handle,
+        &["INTERFEREOBJVS", "INTERFEREVPVS", "DRAGVS"],
+    );
+    add(r2007, Char, &["CSHADOW"]);
+    add(r2007, Double, &["UNKNOWN"]);
+    // R13 files do not seem to store these
+    add(version >= DWGVersion::AC1014, Short, &["UNKNOWN"; 4]);
+    layout
+}
+
+/// Returns a value for every variable of `version`, with a plot style handle and named colors
+/// where the version stores them
+#[cfg(test)]
+fn sample_values(version: DWGVersion) -> Vec<(&'static str, HeaderValue)> {
+    use crate::types::RefType;
+
+    let named_color = CmColor {
+        index: 0,
+        rgb: 0xC2FF0000,
+        flags: 0x3,
+        name: Some("red".into()),
+        book_name: Some("book".into()),
+    };
+    layout(version)
+        .into_iter()
+        .map(|(name, kind)| {
+            let value = match kind {
+                Kind::Bit => HeaderValue::Bit(true),
+                Kind::Char => HeaderValue::Char(2),
+                Kind::Short if name == "CEPSNTYPE" => HeaderValue::Short(3),
+                Kind::Short => HeaderValue::Short(-4),
+                Kind::Long => HeaderValue::Long(5),
+                Kind::LongLong => HeaderValue::LongLong(1 << 40),
+                Kind::Double => HeaderValue::Double(0.5),
+                Kind::Text => HeaderValue::Text(name.into()),
+                Kind::Handle => HeaderValue::Handle(HandleRef::new(RefType::HardPointer, 6)),
+                Kind::Point2 => HeaderValue::Point2(Point2 { x: 1.0, y: 2.0 }),
+                Kind::Point3 => HeaderValue::Point3(Point3 {
+                    x: 1.0,
+                    y: 2.0,
+                    z: 3.0,
+                }),
+                Kind::Color if version >= DWGVersion::AC1018 => {
+                    HeaderValue::Color(named_color.clone())
+                }
+                Kind::Color => HeaderValue::Color(CmColor {
+                    index: 1,
+                    ..Default::default()
+                }),
+                Kind::Date => HeaderValue::Date(JulianDate { day: 7, ms: 8 }),
+            };
+            (name, value)
+        })
+        .collect()
+}
+
+/// A header variables section built by hand for tests, with the text and handles other than
+/// HANDSEED in their own streams from AC1021 (R2007)
+#[cfg(test)]
+struct Streams {
+    version: DWGVersion,
+    data: Bits,
+    strings: Bits,
+    handles: Bits,
+}
+
+#[cfg(test)]
+impl Streams {
+    fn new(version: DWGVersion) -> Self {
+        Self {
+            version,
+            data: Bits::default(),
+            strings: Bits::default(),
+            handles: Bits::default(),
+        }
+    }
+
+    fn split(&self) -> bool {
+        self.version >= DWGVersion::AC1021
+    }
+
+    fn data(&mut self, f: impl FnOnce(Bits) -> Bits) -> &mut Self {
+        self.data = f(std::mem::take(&mut self.data));
+        self
+    }
+
+    /// A TV in the data stream, or a TU in the string stream from AC1021 (R2007)
+    fn text(&mut self, text: &str) -> &mut Self {
+        match self.split() {
+            true => self.strings = std::mem::take(&mut self.strings).tu(text),
+            false => self.data = std::mem::take(&mut self.data).tv(text),
+        }
+        self
+    }
+
+    fn handle(&mut self, code: u8, handle: u64) -> &mut Self {
+        match self.split() {
+            true => self.handles = std::mem::take(&mut self.handles).h(code, handle),
+            false => self.data = std::mem::take(&mut self.data).h(code, handle),
+        }
+        self
+    }
+
+    /// Packs the streams, from AC1021 (R2007) as the RL size in bits of the data, the data and
+    /// string streams, the RS size of the string stream and a set bit, then the handles
+    fn pack(&self) -> Vec<u8> {
+        if !self.split() {
+            return self.data.pack();
+        }
+        let bitsize = 32 + self.data.len() + self.strings.len() + 16 + 1;
+        Bits::default()
+            .rl(bitsize as i32)
+            .then(self.data.clone())
+            .then(self.strings.clone())
+            .rs(self.strings.len() as i16)
+            .b(true)
+            .then(self.handles.clone())
+            .pack()
+    }
+}
+
+/// Builds the header variables of an AC1015 (R2000) or AC1021 (R2007) drawing by hand in the
+/// order of chapter 9 of the ODS, with a value of its own for most variables
+#[cfg(test)]
+fn header_vector(version: DWGVersion) -> Vec<u8> {
+    let r2000 = version == DWGVersion::AC1015;
+    let r2007 = version == DWGVersion::AC1021;
+    assert!(r2000 || r2007);
+    let mut s = Streams::new(version);
+
+    s.data(|b| b.bd(412148.0).bd(1.0).bd(0.0).bd(2.5))
+        .text("u1")
+        .text("")
+        .text("u3")
+        .text("u4")
+        .data(|b| b.bl(24).bl(0));
+    if r2000 {
+        // VIEWPORT_ENTITY_HEADER
+        s.handle(5, 0);
+    }
+    // DIMASO, DIMSHO, then PLINEGEN to LIMCHECK
+    s.data(|b| b.bits(0b10, 2).bits(0b1011001, 7));
+    if r2007 {
+        s.data(|b| b.b(true));
+    }
+    // USRTIMER to SPLFRAME, MIRRTEXT and WORLDVIEW, TILEMODE to VISRETAIN, DISPSILH and
+    // PELLIPSE
+    s.data(|b| {
+        b.bits(0b0110, 4)
+            .bits(0b01, 2)
+            .bits(0b101, 3)
+            .bits(0b11, 2)
+            // PROXYGRAPHICS to AUPREC, ATTMODE and PDMODE
+            .bs(1)
+            .bs(3020)
+            .bs(2)
+            .bs(4)
+            .bs(0)
+            .bs(256)
+            .bs(5)
+            .bs(34)
+    });
+    if r2007 {
+        s.data(|b| b.bl(0).bl(1).bl(300));
+    }
+    // USERI1 to TEXTQLTY, then LTSCALE to CELTSCALE
+    s.data(|b| {
+        let b = (101..=119).fold(b, |b, n| b.bs(n));
+        (1..=21).fold(b, |b, n| b.bd(n as f64 * 0.5))
+    })
+    .text("acad")
+    // TDCREATE and TDUPDATE
+    .data(|b| b.bl(2460000).bl(1000).bl(2460001).bl(2000));
+    if r2007 {
+        s.data(|b| b.bl(7).bl(8).bl(9));
+    }
+    // TDINDWG and TDUSRTIMER
+    s.data(|b| b.bl(0).bl(3000).bl(1).bl(4000));
+    // CECOLOR, by name from AC1021 (R2007)
+    match r2007 {
+        true => s
+            .data(|b| b.bs(0).bl(0xC2FF0000u32 as i32).rc(1))
+            .text("red"),
+        false => s.data(|b| b.bs(256)),
+    };
+    // HANDSEED stays in the data stream
+    s.data(|b| b.h(0, 0x2A1))
+        .handle(5, 0x10)
+        .handle(5, 0x11)
+        .handle(5, 0x14)
+        .handle(5, 0x1D)
+        .handle(5, 0x18)
+        // PSVPSCALE
+        .data(|b| b.bd(2.0));
+
+    // Paper space then model space
+    for (k, name) in [(0.0, 0x20), (100.0, 0x21)] {
+        s.data(|b| {
+            // INSBASE, EXTMIN, EXTMAX, LIMMIN, LIMMAX and ELEVATION
+            b.p3((k, 0.0, 0.0))
+                .p3((k - 1.0, -2.0, 0.0))
+                .p3((k + 10.0, 20.0, 0.0))
+                .p2((k, 0.0))
+                .p2((k + 12.0, 9.0))
+                .bd(k + 0.25)
+                // UCSORG, UCSXDIR and UCSYDIR
+                .p3((k, 1.0, 0.0))
+                .p3((1.0, 0.0, 0.0))
+                .p3((0.0, 1.0, 0.0))
+        })
+        .handle(5, name)
+        .handle(5, name + 2)
+        // UCSORTHOVIEW
+        .data(|b| b.bs(name as i16 - 0x1F))
+        .handle(5, name + 4)
+        .data(|b| (1..=6).fold(b, |b, n| b.p3((k + n as f64, 0.0, 0.0))));
+    }
+
+    s.text("")
+        .text("mm")
+        // DIMSCALE to DIMTM
+        .data(|b| (1..=9).fold(b, |b, n| b.bd(n as f64 * 0.25)));
+    if r2007 {
+        // DIMFXL, DIMJOGANG, DIMTFILL and DIMTFILLCLR
+        s.data(|b| b.bd(1.5).bd(0.75).bs(2).cmc(version, 0, 0xC3000002));
+    }
+    // DIMTOL to DIMSE2, DIMTAD, DIMZIN and DIMAZIN
+    s.data(|b| b.bits(0b100101, 6).bs(1).bs(8).bs(3));
+    if r2007 {
+        // DIMARCSYM
+        s.data(|b| b.bs(2));
+    }
+    s.data(|b| {
+        // DIMTXT to DIMGAP, DIMALTRND, DIMALT, DIMALTD and DIMTOFL to DIMSOXD
+        let b = (1..=8).fold(b, |b, n| b.bd(n as f64 * 0.125));
+        let b = b.bd(0.0).b(true).bs(2).bits(0b0101, 4);
+        // DIMCLRD, DIMCLRE and DIMCLRT
+        let b = b
+            .cmc(version, 0, 0xC1000000)
+            .cmc(version, 256, 0xC0000000)
+            .cmc(version, 7, 0xC3000007);
+        // DIMADEC to DIMJUST, DIMSD1 and DIMSD2, DIMTOLJ to DIMALTTZ, DIMUPT and DIMATFIT
+        let b = (21..=31).fold(b, |b, n| b.bs(n)).bits(0b01, 2);
+        (1..=4).fold(b, |b, n| b.bs(n)).b(true).bs(3)
+    });
+    if r2007 {
+        // DIMFXLON
+        s.data(|b| b.b(true));
+    }
+    // DIMTXSTY, DIMLDRBLK, DIMBLK, DIMBLK1 and DIMBLK2
+    s.handle(5, 0x11)
+        .handle(5, 0)
+        .handle(5, 0)
+        .handle(5, 0x40)
+        .handle(5, 0x41);
+    if r2007 {
+        // DIMLTYPE, DIMLTEX1 and DIMLTEX2
+        s.handle(5, 0x14).handle(5, 0x15).handle(5, 0x16);
+    }
+    // DIMLWD and DIMLWE
+    s.data(|b| b.bs(-2).bs(-3));
+
+    // The table controls
+    for handle in 1..=9 {
+        s.handle(3, handle);
+    }
+    if r2000 {
+        // VP_ENT_HDR_CONTROL
+        s.handle(3, 0xB);
+    }
+    // DICTIONARY_ACAD_GROUP, DICTIONARY_ACAD_MLINESTYLE and DICTIONARY_NAMED_OBJECTS
+    s.handle(5, 0xD)
+        .handle(5, 0xE)
+        .handle(5, 0xC)
+        // TSTACKALIGN and TSTACKSIZE
+        .data(|b| b.bs(1).bs(70))
+        .text("")
+        .text("style")
+        // DICTIONARY_LAYOUTS, DICTIONARY_PLOTSETTINGS and DICTIONARY_PLOTSTYLES
+        .handle(5, 0x1A)
+        .handle(5, 0x19)
+        .handle(5, 0xF);
+    if r2007 {
+        // DICTIONARY_MATERIALS, DICTIONARY_COLORS and DICTIONARY_VISUALSTYLE
+        s.handle(5, 0x42).handle(5, 0x43).handle(5, 0x44);
+    }
+    // FLAGS, INSUNITS and CEPSNTYPE, with CPSNID only stored if it is 3
+    match r2007 {
+        true => s.data(|b| b.bl(0xA1F).bs(4).bs(1)),
+        false => s.data(|b| b.bl(0xA1F).bs(4).bs(3)).handle(5, 0x45),
+    };
+    s.text("{FINGERPRINT}").text("{VERSION}");
+    if r2007 {
+        // SORTENTS to HALOGAP, OBSCUREDCOLOR to INTERSECTIONDISPLAY and PROJECTNAME
+        s.data(|b| {
+            let b = [127, 0, 1, 2, 3, 4].into_iter().fold(b, |b, n| b.rc(n));
+            b.bs(257).bs(7).rc(0).rc(1)
+        })
+        .text("project");
+    }
+    // BLOCK_RECORD_PAPER_SPACE to LTYPE_CONTINUOUS
+    s.handle(5, 0x1E)
+        .handle(5, 0x1F)
+        .handle(5, 0x14)
+        .handle(5, 0x15)
+        .handle(5, 0x16);
+    if r2007 {
+        s.data(|b| {
+            // CAMERADISPLAY, two unknown BLs, then BDs up to CAMERAHEIGHT
+            let b = b.b(false).bl(1).bl(2);
+            let b = [0.5, 2.0, 6.0, 3.0, 50.0, 1.0]
+                .into_iter()
+                .fold(b, |b, n| b.bd(n));
+            // SOLIDHIST and SHOWHIST, then PSOLWIDTH to LOFTMAG2
+            let b = [0.25, 4.0, 1.25, 1.75, 0.0, 1.0]
+                .into_iter()
+                .fold(b.rc(1).rc(2), |b, n| b.bd(n));
+            // LOFTPARAM and LOFTNORMALS, LATITUDE to NORTHDIRECTION and TIMEZONE
+            let b = b.bs(7).rc(1).bd(37.795).bd(-122.394).bd(0.0).bl(-8000);
+            // LIGHTGLYPHDISPLAY to DGNFRAME, an unknown bit and INTERFERECOLOR
+            let b = (1..=4).fold(b, |b, n| b.rc(n)).b(true);
+            b.cmc(version, 0, 0xC3000001)
+        })
+        // INTERFEREOBJVS, INTERFEREVPVS and DRAGVS
+        .handle(5, 0x50)
+        .handle(5, 0x51)
+        .handle(5, 0x52)
+        // CSHADOW and an unknown BD
+        .data(|b| b.rc(5).bd(0.0));
+    }
+    s.data(|b| (0..=3).fold(b, |b, n| b.bs(n)));
+    s.pack()
+}
+
+#[test]
+fn test_header_variables() {
+    use crate::{objects::object_types, types::RefType};
+
+    for version in [DWGVersion::AC1015, DWGVersion::AC1018] {
+        let dwg = Dwg::new(version).unwrap();
+        let mut vars = dwg.header_variables().unwrap();
+        assert_eq!(vars.write(version, dwg.codepage).unwrap(), dwg.header_vars);
+        let layer_0 = dwg.find_table_entry(object_types::LAYER_CONTROL, "0");
+        let clayer = HandleRef::new(RefType::HardPointer, layer_0.unwrap().unwrap());
+        assert_eq!(vars.get("CLAYER"), Some(&HeaderValue::Handle(clayer)));
+        assert_eq!(vars.get("LTSCALE"), Some(&HeaderValue::Double(1.0)));
+        assert_eq!(
+            vars.get("MENUNAME"),
+            Some(&HeaderValue::Text("acad".into()))
+        );
+        assert_eq!(vars.get("BLIPMODE"), None);
+        assert_eq!(
+            vars.get("DICTIONARY_COLORS").is_some(),
+            version == DWGVersion::AC1018
+        );
+
+        *vars.get_mut("TEXTSIZE").unwrap() = HeaderValue::Double(2.5);
+        let data = vars.write(version, dwg.codepage).unwrap();
+        let read = HeaderVariables::read(&data, version, dwg.codepage).unwrap();
+        assert_eq!(read.get("TEXTSIZE"), Some(&HeaderValue::Double(2.5)));
+        assert_eq!(read, vars);
+    }
+
+    // R13 and R14 store their own variables, plot styles are by handle from R2000, and R2007
+    // and later split text and handles into their own streams
+    for version in [
+        DWGVersion::AC1012,
+        DWGVersion::AC1014,
+        DWGVersion::AC1015,
+        DWGVersion::AC1018,
+        DWGVersion::AC1021,
+        DWGVersion::AC1024,
+        DWGVersion::AC1027,
+        DWGVersion::AC1032,
+    ] {
+        let values = sample_values(version);
+        let vars = HeaderVariables {
+            values,
+            ..Default::default()
+        };
+        let data = vars.write(version, CodePage::ANSI1252).unwrap();
+        let read = HeaderVariables::read(&data, version, CodePage::ANSI1252).unwrap();
+        assert_eq!(read.values, vars.values);
+        assert_eq!(read.write(version, CodePage::ANSI1252).unwrap(), data);
+        assert_eq!(read.get("CPSNID").is_some(), version >= DWGVersion::AC1015);
+        assert_eq!(read.get("DIMSAV").is_some(), version < DWGVersion::AC1015);
+        let dimblk1 = match version {
+            DWGVersion::AC1012 | DWGVersion::AC1014 => HeaderValue::Text("DIMBLK1".into()),
+            _ => HeaderValue::Handle(HandleRef::new(RefType::HardPointer, 6)),
+        };
+        assert_eq!(read.get("DIMBLK1"), Some(&dimblk1));
+        assert_eq!(read.get("DIMFXL").is_some(), version >= DWGVersion::AC1021);
+        assert_eq!(
+            read.get("DIMTXTDIRECTION").is_some(),
+            version >= DWGVersion::AC1024
+        );
+        assert_eq!(
+            read.get("REQUIREDVERSIONS").is_some(),
+            version >= DWGVersion::AC1027
+        );
+        if version >= DWGVersion::AC1021 {
+            // The data and string streams end where the size at the start says
+            let bitsize = u32::from_le_bytes(data[..4].try_into().unwrap()) as u64;
+            let mut reader = BitSliceReader::from_slice(&data);
+            reader.seek_to_bit(bitsize - 1).unwrap();
+            assert_eq!(reader.read_bit(), Ok(1));
+            assert_eq!(
+                reader.read_handle(),
+                Ok(HandleRef::new(RefType::HardPointer, 6))
+            );
+        }
+    }
+}
+
+#[test]
+fn test_typed_header_variables() {
+    use crate::objects::object_types;
+
+    let dwg = Dwg::new(DWGVersion::AC1015).unwrap();
+    let vars = dwg.header_variables().unwrap();
+    let layer_0 = dwg.find_table_entry(object_types::LAYER_CONTROL, "0");
+    assert_eq!(vars.clayer(), layer_0.unwrap());
+    assert_eq!(vars.handseed(), Some(dwg.next_handle()));
+    assert_eq!(vars.extmin().map(|point| point.x), Some(1e20));
+    assert_eq!(vars.limmax(), Some(Point2 { x: 12.0, y: 9.0 }));
+    assert_eq!(vars.insunits(), Some(Units::Unitless));
+    assert_eq!(vars.ltscale(), Some(1.0));
+    assert_eq!(vars.tdcreate(), vars.tdupdate());
+    let dimensions = vars.dimension_defaults().unwrap();
+    assert_eq!(dimensions.text_height, 0.18);
+    assert_eq!(dimensions.decimal_places, 4);
+    assert_eq!(vars.textstyle(), Some(dimensions.text_style));
+
+    assert_eq!(Units::from_code(6), Some(Units::Meters));
+    assert_eq!(Units::from_code(25), None);
+    assert_eq!(Units::UsSurveyMiles.code(), 24);
+    assert_eq!(HeaderVariables::default().ltscale(), None);
+}
+
+#[test]
+fn test_trailing_header_variables() {
+    use crate::types::RefType;
+
+    // Variables of a later release in each stream are kept and written back
+    for version in [DWGVersion::AC1015, DWGVersion::AC1018, DWGVersion::AC1024] {
+        let vars = HeaderVariables {
+            values: sample_values(version),
+            ..Default::default()
+        };
+        let mut later = vars.clone();
+        later.values.extend([
+            ("LATER", HeaderValue::Long(1234)),
+            ("LATER", HeaderValue::Text("later".into())),
+            (
+                "LATER",
+                HeaderValue::Handle(HandleRef::new(RefType::SoftPointer, 5)),
+            ),
+        ]);
+        let data = later.write(version, CodePage::ANSI1252).unwrap();
+        let read = HeaderVariables::read(&data, version, CodePage::ANSI1252).unwrap();
+        assert_eq!(read.values, vars.values);
+        assert!(read.trailing.data.bit_len() >= 8);
+        assert_eq!(
+            read.trailing.strings.is_empty(),
+            version < DWGVersion::AC1021
+        );
+        assert_eq!(read.write(version, CodePage::ANSI1252).unwrap(), data);
+    }
+}
+
+#[test]
+fn test_header_variable_vectors() {
+    use crate::types::RefType;
+
+    let handle = |code, handle| Some(HeaderValue::Handle(HandleRef::new(code, handle)));
+    let text = |text: &str| Some(HeaderValue::Text(text.into()));
+    for version in [DWGVersion::AC1015, DWGVersion::AC1021] {
+        let r2007 = version == DWGVersion::AC1021;
+        let data = header_vector(version);
+        let vars = HeaderVariables::read(&data, version, CodePage::ANSI1252).unwrap();
+        assert_eq!(vars.write(version, CodePage::ANSI1252).unwrap(), data);
+        let get = |name| vars.get(name).cloned();
+
+        // Every stream is read up to the padding at the end
+        assert!(vars.trailing.strings.is_empty());
+        let padding = match r2007 {
+            true => (&vars.trailing.handles, &vars.trailing.data),
+            false => (&vars.trailing.data, &vars.trailing.handles),
+        };
+        assert!(padding.0.bit_len() < 8 && padding.0.as_bytes().iter().all(|&byte| byte == 0));
+        assert!(padding.1.is_empty());
+
+        let unknown: Vec<_> = vars.values[..10].iter().map(|(_, value)| value).collect();
+        assert_eq!(unknown[0], &HeaderValue::Double(412148.0));
+        assert_eq!(unknown[3], &HeaderValue::Double(2.5));
+        assert_eq!(unknown[5], &HeaderValue::Text(String::new()));
+        assert_eq!(unknown[9], &HeaderValue::Long(0));
+        assert_eq!(
+            get("VIEWPORT_ENTITY_HEADER"),
+            handle(RefType::HardPointer, 0).filter(|_| !r2007)
+        );
+        assert_eq!(get("DIMSHO"), Some(HeaderValue::Bit(false)));
+        assert_eq!(get("LIMCHECK"), Some(HeaderValue::Bit(true)));
+        assert_eq!(get("USRTIMER"), Some(HeaderValue::Bit(false)));
+        assert_eq!(get("PELLIPSE"), Some(HeaderValue::Bit(true)));
+        assert_eq!(get("TREEDEPTH"), Some(HeaderValue::Short(3020)));
+        assert_eq!(get("PDMODE"), Some(HeaderValue::Short(34)));
+        assert_eq!(get("USERI1"), Some(HeaderValue::Short(101)));
+        assert_eq!(get("TEXTQLTY"), Some(HeaderValue::Short(119)));
+        assert_eq!(get("LTSCALE"), Some(HeaderValue::Double(0.5)));
+        assert_eq!(get("CELTSCALE"), Some(HeaderValue::Double(10.5)));
+        assert_eq!(get("MENUNAME"), text("acad"));
+        let date = |day, ms| Some(HeaderValue::Date(JulianDate { day, ms }));
+        assert_eq!(get("TDUPDATE"), date(2460001, 2000));
+        assert_eq!(get("TDUSRTIMER"), date(1, 4000));
+        let cecolor = match r2007 {
+            true => CmColor {
+                rgb: 0xC2FF0000,
+                flags: 0x1,
+                name: Some("red".into()),
+                ..Default::default()
+            },
+            false => CmColor {
+                index: 256,
+                ..Default::default()
+            },
+        };
+        assert_eq!(get("CECOLOR"), Some(HeaderValue::Color(cecolor)));
+        assert_eq!(get("HANDSEED"), handle(RefType::Unspecified, 0x2A1));
+        assert_eq!(get("CMLSTYLE"), handle(RefType::HardPointer, 0x18));
+        assert_eq!(get("PSVPSCALE"), Some(HeaderValue::Double(2.0)));
+        let point3 = |x, y, z| Some(HeaderValue::Point3(Point3 { x, y, z }));
+        assert_eq!(get("PEXTMAX"), point3(10.0, 20.0, 0.0));
+        assert_eq!(get("EXTMAX"), point3(110.0, 20.0, 0.0));
+        assert_eq!(
+            get("LIMMAX"),
+            Some(HeaderValue::Point2(Point2 { x: 112.0, y: 9.0 }))
+        );
+        assert_eq!(get("PUCSORGBACK"), point3(6.0, 0.0, 0.0));
+        assert_eq!(get("UCSBASE"), handle(RefType::HardPointer, 0x25));
+        assert_eq!(get("UCSORTHOVIEW"), Some(HeaderValue::Short(2)));
+        assert_eq!(get("DIMAPOST"), text("mm"));
+        assert_eq!(get("DIMTM"), Some(HeaderValue::Double(2.25)));
+        assert_eq!(
+            get("DIMJOGANG"),
+            Some(HeaderValue::Double(0.75)).filter(|_| r2007)
+        );
+        assert_eq!(get("DIMAZIN"), Some(HeaderValue::Short(3)));
+        assert_eq!(
+            get("DIMARCSYM"),
+            Some(HeaderValue::Short(2)).filter(|_| r2007)
+        );
+        assert_eq!(get("DIMGAP"), Some(HeaderValue::Double(1.0)));
+        assert_eq!(get("DIMSOXD"), Some(HeaderValue::Bit(true)));
+        let dimclrt = match r2007 {
+            true => CmColor {
+                rgb: 0xC3000007,
+                ..Default::default()
+            },
+            false => CmColor {
+                index: 7,
+                ..Default::default()
+            },
+        };
+        assert_eq!(get("DIMCLRT"), Some(HeaderValue::Color(dimclrt)));
+        assert_eq!(get("DIMJUST"), Some(HeaderValue::Short(31)));
+        assert_eq!(get("DIMALTTZ"), Some(HeaderValue::Short(4)));
+        assert_eq!(get("DIMATFIT"), Some(HeaderValue::Short(3)));
+        assert_eq!(
+            get("DIMFXLON"),
+            Some(HeaderValue::Bit(true)).filter(|_| r2007)
+        );
+        assert_eq!(get("DIMBLK2"), handle(RefType::HardPointer, 0x41));
+        assert_eq!(
+            get("DIMLTEX2"),
+            handle(RefType::HardPointer, 0x16).filter(|_| r2007)
+        );
+        assert_eq!(get("DIMLWE"), Some(HeaderValue::Short(-3)));
+        assert_eq!(get("DIMSTYLE_CONTROL"), handle(RefType::HardOwned, 9));
+        assert_eq!(
+            get("VP_ENT_HDR_CONTROL"),
+            handle(RefType::HardOwned, 0xB).filter(|_| !r2007)
+        );
+        assert_eq!(get("TSTACKSIZE"), Some(HeaderValue::Short(70)));
+        assert_eq!(get("STYLESHEET"), text("style"));
+        assert_eq!(
+            get("DICTIONARY_VISUALSTYLE"),
+            handle(RefType::HardPointer, 0x44).filter(|_| r2007)
+        );
+        assert_eq!(
+            get("CPSNID"),
+            handle(RefType::HardPointer, 0x45).filter(|_| !r2007)
+        );
+        assert_eq!(get("VERSIONGUID"), text("{VERSION}"));
+        assert_eq!(get("PROJECTNAME"), text("project").filter(|_| r2007));
+        assert_eq!(get("LTYPE_CONTINUOUS"), handle(RefType::HardPointer, 0x16));
+        assert_eq!(
+            get("LATITUDE"),
+            Some(HeaderValue::Double(37.795)).filter(|_| r2007)
+        );
+        assert_eq!(
+            get("TIMEZONE"),
+            Some(HeaderValue::Long(-8000)).filter(|_| r2007)
+        );
+        assert_eq!(
+            get("DRAGVS"),
+            handle(RefType::HardPointer, 0x52).filter(|_| r2007)
+        );
+        let last = vars.values.last().map(|(_, value)| value);
+        assert_eq!(last, Some(&HeaderValue::Short(3)));
+    }
+}
@@ -1,7 +1,26 @@
 pub mod bitcodes;
+pub mod bitcodes_write;
+pub mod builder;
+pub mod classes;
+pub mod compression;
+pub mod convert;
 pub mod crc;
+pub mod decode;
 pub mod dwg;
+pub mod dwg_file;
+pub mod dwg_write;
+pub mod entities;
+pub mod error;
+pub mod header_vars;
+pub mod merge;
+pub mod objects;
+pub mod preview;
+pub mod reed_solomon;
+pub mod sentinels;
+pub mod streams;
+pub mod summary_info;
 pub mod types;
+pub mod verify;
 pub mod version;
 
 pub fn add(left: usize, right: usize) -> usize {
@@ -0,0 +1,625 @@
+//! Copying objects from one drawing to another
+//!
+//! Objects are copied with new handles, and every reference between copied objects is updated
+//! to the new handles. Entries of the symbol tables, such as layers, are matched by name with
+//! the entries of the drawing they are copied into and are only copied if there is no match,
+//! as when AutoCAD inserts one drawing into another. References to the control objects of the
+//! tables and to the dictionaries listed in the second header are replaced with those of the
+//! drawing copied into
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::{
+    decode::DwgDecode,
+    dwg::Dwg,
+    error::{DwgError, Result},
+    objects::{
+        is_entity_type, object_types, open_object, table_control_type, BlockHeader,
+        CommonEntityData, ObjectHeader, PartialObject, TableEntry,
+    },
+    types::{HandleRef, RefType},
+    version::DWGVersion,
+};
+
+/// Records of [`Dwg::handle_records`] listing the named object, MLINESTYLE and GROUP
+/// dictionaries
+const DICTIONARY_RECORDS: [usize; 3] = [11, 12, 13];
+
+/// An object being copied, with its common data and handles decoded
+enum Copied {
+    Object(PartialObject<ObjectHeader>, Vec<HandleRef>),
+    Entity(PartialObject<CommonEntityData>, Vec<HandleRef>),
+    Block(BlockHeader),
+}
+
+impl Copied {
+    fn encode(&self, target: &mut Dwg, handle: u64) -> Result<()> {
+        match self {
+            Copied::Object(object, _) => target.insert_object(handle, object.object_type, object),
+            Copied::Entity(entity, _) => target.insert_object(handle, entity.object_type, entity),
+            Copied::Block(block) => target.insert_object(handle, object_types::BLOCK_HEADER, block),
+        }
+    }
+
+    /// Calls `f` with every handle reference of the object other than its own handle, along
+    /// with whether the reference is weak
+    ///
+    /// Objects referenced through strong references are copied with the object, while weak
+    /// references, such as reactors and the links between the entities of a block, are only
+    /// kept if the object they reference is copied anyway
+    fn visit_handles(&mut self, f: &mut impl FnMut(&mut HandleRef, bool)) {
+        match self {
+            Copied::Object(object, refs) => {
+                visit_object_header(&mut object.header, f);
+                refs.iter_mut().for_each(|handle| f(handle, false));
+            }
+            Copied::Entity(entity, refs) => {
+                let header = &mut entity.header;
+                header.eed.iter_mut().for_each(|(app, _)| f(app, false));
+                if let Some(owner) = &mut header.owner {
+                    f(owner, false);
+                }
+                header
+                    .reactors
+                    .iter_mut()
+                    .for_each(|handle| f(handle, true));
+                if let Some(xdic) = &mut header.xdic {
+                    f(xdic, false);
+                }
+                if let Some((prev, next)) = &mut header.links {
+                    f(prev, true);
+                    f(next, true);
+                }
+                let [full, face, edge] = &mut header.visual_styles;
+                let optional = [
+                    &mut header.color_handle,
+                    &mut header.linetype,
+                    &mut header.material,
+                    &mut header.plotstyle,
+                    full,
+                    face,
+                    edge,
+                ];
+                optional
+                    .into_iter()
+                    .flatten()
+                    .for_each(|handle| f(handle, false));
+                f(&mut header.layer, false);
+                refs.iter_mut().for_each(|handle| f(handle, false));
+            }
+            Copied::Block(block) => {
+                visit_object_header(&mut block.entry.header, f);
+                f(&mut block.entry.xref_block, false);
+                f(&mut block.block_entity, false);
+                f(&mut block.endblk_entity, false);
+                if let Some((first, last)) = &mut block.first_last {
+                    f(first, true);
+                    f(last, true);
+                }
+                block.entities.iter_mut().for_each(|handle| f(handle, true));
+                block.inserts.iter_mut().for_each(|handle| f(handle, true));
+                f(&mut block.layout, true);
+            }
+        }
+    }
+
+    /// Sets the handle of the object and drops references that were cleared from its reactors
+    fn set_handle(&mut self, handle: u64) {
+        let (own, reactors) = match self {
+            Copied::Object(object, _) => (&mut object.header.handle, &mut object.header.reactors),
+            Copied::Entity(entity, _) => (&mut entity.header.handle, &mut entity.header.reactors),
+            Copied::Block(block) => (
+                &mut block.entry.header.handle,
+                &mut block.entry.header.reactors,
+            ),
+        };
+        *own = handle;
+        reactors.retain(|reactor| reactor.absolute(handle) != 0);
+        match self {
+            Copied::Object(object, refs) => object.set_handle_refs(refs),
+            Copied::Entity(entity, refs) => entity.set_handle_refs(refs),
+            Copied::Block(_) => {}
+        }
+    }
+}
+
+fn visit_object_header(header: &mut ObjectHeader, f: &mut impl FnMut(&mut HandleRef, bool)) {
+    header.eed.iter_mut().for_each(|(app, _)| f(app, false));
+    f(&mut header.owner, false);
+    header
+        .reactors
+        .iter_mut()
+        .for_each(|handle| f(handle, true));
+    if let Some(xdic) = &mut header.xdic {
+        f(xdic, false);
+    }
+}
+
+/// Returns a reference to `target` from the object `reference` with the same code as
+/// `handle`
+///
+/// Offset codes are recalculated from the new handles, and references to nothing become
+/// null soft pointers
+fn rebase(handle: HandleRef, target: u64, reference: u64) -> HandleRef {
+    match handle.code {
+        RefType::PlusOne | RefType::MinusOne | RefType::PlusOffset | RefType::MinusOffset => {
+            match (target.checked_sub(reference), reference.checked_sub(target)) {
+                _ if target == 0 || target == reference => {
+                    HandleRef::new(RefType::SoftPointer, target)
+                }
+                (Some(1), _) => HandleRef::new(RefType::PlusOne, 0),
+                (Some(offset), _) => HandleRef::new(RefType::PlusOffset, offset),
+                (_, Some(1)) => HandleRef::new(RefType::MinusOne, 0),
+                (_, offset) => HandleRef::new(RefType::MinusOffset, offset.unwrap_or(0)),
+            }
+        }
+        code => HandleRef::new(code, target),
+    }
+}
+
+/// Copies objects from `source` into another drawing
+///
+/// Objects are first resolved to their handle in the other drawing with
+/// [`Importer::resolve`], which allocates a handle for objects that need to be copied. These
+/// are then decoded, resolving what they reference in turn. [`Importer::finish`] encodes the
+/// copies once every handle is known
+struct Importer<'a> {
+    source: &'a Dwg,
+    /// Handle in the drawing copied into of every source object resolved so far, 0 for objects
+    /// that are not copied
+    handles: BTreeMap<u64, u64>,
+    /// Source handles of objects to copy that have not been decoded yet
+    queue: VecDeque<u64>,
+    /// Decoded objects by source handle
+    copied: Vec<(u64, Copied)>,
+    /// Source handles of the entities of every copied block, by source handle of the block
+    blocks: Vec<(u64, Vec<u64>)>,
+    /// Copied table entries and the type of their control object
+    entries: Vec<(u64, i16)>,
+}
+
+impl<'a> Importer<'a> {
+    fn new(source: &'a Dwg) -> Self {
+        Self {
+            source,
+            handles: BTreeMap::new(),
+            queue: VecDeque::new(),
+            copied: Vec::new(),
+            blocks: Vec::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Returns the handle in `target` of the source object `handle`, copying it and everything
+    /// it references if it has no equivalent in `target`
+    ///
+    /// Objects defined by classes are not copied and resolve to 0, as their type numbers
+    /// depend on the classes of the drawing
+    fn resolve(&mut self, target: &mut Dwg, handle: u64) -> Result<u64> {
+        if handle == 0 {
+            return Ok(0);
+        }
+        if let Some(&resolved) = self.handles.get(&handle) {
+            return Ok(resolved);
+        }
+        let source = self.source;
+        let Some(data) = source.get_object(handle) else {
+            return Ok(0);
+        };
+        let (object_type, mut reader) = open_object(data, source.version(), source.codepage)?;
+        let dictionary = DICTIONARY_RECORDS
+            .into_iter()
+            .find(|&record| source.handle_records[record] == handle);
+        let resolved = if let Some(record) = dictionary {
+            target.handle_records[record]
+        } else if is_control_type(object_type) {
+            target.control_object(object_type)?.0
+        } else if let Some(control_type) = table_control_type(object_type) {
+            let name = TableEntry::decode(&mut reader)?.name;
+            // Anonymous blocks, such as those of dimensions, only share their name prefix
+            let anonymous =
+                object_type == object_types::BLOCK_HEADER && source.block_header(handle)?.anonymous;
+            match target.find_table_entry(control_type, &name)? {
+                Some(existing) if !anonymous => existing,
+                _ => {
+                    let copy = self.allocate(target, handle);
+                    self.entries.push((copy, control_type));
+                    copy
+                }
+            }
+        } else if object_type >= 500 {
+            0
+        } else {
+            self.allocate(target, handle)
+        };
+        self.handles.insert(handle, resolved);
+        Ok(resolved)
+    }
+
+    /// Allocates a handle in `target` for a copy of the source object `handle`
+    fn allocate(&mut self, target: &mut Dwg, handle: u64) -> u64 {
+        let copy = target.next_handle();
+        target.handle_records[0] = copy + 1;
+        self.handles.insert(handle, copy);
+        self.queue.push_back(handle);
+        copy
+    }
+
+    /// Decodes the objects waiting to be copied, resolving everything they reference
+    fn copy_queued(&mut self, target: &mut Dwg) -> Result<()> {
+        let source = self.source;
+        let (version, codepage) = (source.version(), source.codepage);
+        while let Some(handle) = self.queue.pop_front() {
+            let data = source.object(handle)?;
+            let (object_type, _) = open_object(data, version, codepage)?;
+            let mut copied = if object_type == object_types::BLOCK_HEADER {
+                Copied::Block(source.block_header(handle)?)
+            } else if is_entity_type(object_type) {
+                let entity = PartialObject::<CommonEntityData>::decode(data, version, codepage)?;
+                let refs = entity.handle_refs()?;
+                Copied::Entity(entity, refs)
+            } else {
+                let object = PartialObject::<ObjectHeader>::decode(data, version, codepage)?;
+                let refs = object.handle_refs()?;
+                Copied::Object(object, refs)
+            };
+
+            let mut strong = Vec::new();
+            copied.visit_handles(&mut |reference, weak| {
+                if !weak {
+                    strong.push(reference.absolute(handle));
+                }
+            });
+            for reference in strong {
+                self.resolve(target, reference)?;
+            }
+            if let Copied::Block(_) = copied {
+                let entities = source.block_entities(handle)?;
+                for &entity in &entities {
+                    self.resolve(target, entity)?;
+                }
+                self.blocks.push((handle, entities));
+            }
+            self.copied.push((handle, copied));
+        }
+        Ok(())
+    }
+
+    /// Copies every object resolved so far into `target`
+    fn finish(mut self, target: &mut Dwg) -> Result<()> {
+        self.copy_queued(target)?;
+        let empty_list = target.version() < DWGVersion::AC1018;
+        for (handle, mut copied) in self.copied {
+            let copy = self.handles[&handle];
+            copied.visit_handles(&mut |reference, _| {
+                let resolved = self.handles.get(&reference.absolute(handle));
+                *reference = rebase(*reference, resolved.copied().unwrap_or(0), copy);
+            });
+            copied.set_handle(copy);
+            // The entities of blocks are added once they are all copied
+            if let Copied::Block(block) = &mut copied {
+                let null = HandleRef::new(RefType::SoftPointer, 0);
+                block.first_last = empty_list.then_some((null, null));
+                block.entities.clear();
+            }
+            copied.encode(target, copy)?;
+        }
+        for (block, entities) in self.blocks {
+            let entities: Vec<_> = entities
+                .iter()
+                .filter_map(|entity| self.handles.get(entity).copied())
+                .filter(|&entity| entity != 0)
+                .collect();
+            target.append_to_block(self.handles[&block], &entities)?;
+        }
+        for (entry, control_type) in self.entries {
+            target.add_table_entry(control_type, entry)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns true if objects of type `object_type` are the control objects of tables
+fn is_control_type(object_type: i16) -> bool {
+    object_type.checked_add(1).and_then(table_control_type) == Some(object_type)
+}
+
+impl Dwg {
+    /// Copies a block from another drawing, returning the handle of the block in this drawing
+    ///
+    /// The entities of the block are copied along with the objects they reference, such as
+    /// layers, linetypes, text styles and nested blocks, which are matched by name with those
+    /// of this drawing. If this drawing already has a block named `block_name`, ignoring
+    /// case, it is kept and returned rather than redefined.
+    ///
+    /// Objects defined by classes, such as LWPOLYLINE, are not copied, as classes are not
+    /// merged. Entities of these types are left out of the block and other references to
+    /// them are cleared. Fails with `ConversionUnsupported` if the drawings differ in version
+    /// or codepage and `MissingObject` if `other` has no such block or a table it needs is
+    /// missing from either drawing
+    pub fn import_block(&mut self, other: &Dwg, block_name: &str) -> Result<u64> {
+        self.check_mergeable(other)?;
+        let block = other
+            .find_table_entry(object_types::BLOCK_CONTROL, block_name)?
+            .ok_or(DwgError::MissingObject {
+                handle: other.handle_records[1],
+            })?;
+        let mut importer = Importer::new(other);
+        let copy = importer.resolve(self, block)?;
+        importer.finish(self)?;
+        Ok(copy)
+    }
+
+    /// Copies the model space entities of another drawing to the end of model space, along
+    /// with its blocks, layers, linetypes and text styles
+    ///
+    /// Blocks and table entries are matched by name with those of this drawing, which are
+    /// kept when both drawings have them, and everything is copied as by
+    /// [`Dwg::import_block`]. Fails as it does
+    pub fn merge(&mut self, other: &Dwg) -> Result<()> {
+        self.check_mergeable(other)?;
+        let mut importer = Importer::new(other);
+        for control_type in [
+            object_types::BLOCK_CONTROL,
+            object_types::LAYER_CONTROL,
+            object_types::LTYPE_CONTROL,
+            object_types::STYLE_CONTROL,
+        ] {
+            let (control_handle, control) = other.control_object(control_type)?;
+            for entry in &control.entries {
+                importer.resolve(self, entry.absolute(control_handle))?;
+            }
+        }
+        let mut entities = Vec::new();
+        for entity in other.block_entities(other.model_space()?)? {
+            match importer.resolve(self, entity)? {
+                0 => {}
+                copy => entities.push(copy),
+            }
+        }
+        importer.finish(self)?;
+        let space = self.model_space()?;
+        self.append_to_block(space, &entities)
+    }
+
+    /// Fails with `ConversionUnsupported` unless objects of `other` can be copied unchanged
+    fn check_mergeable(&self, other: &Dwg) -> Result<()> {
+        if self.version() != other.version() || self.codepage != other.codepage {
+            return Err(DwgError::ConversionUnsupported {
+                from: format!("{:?} ({:?})", other.version(), other.codepage),
+                to: format!("{:?} ({:?})", self.version(), self.codepage),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Adds a layer using the Continuous linetype, for tests
+#[cfg(test)]
+fn add_test_layer(dwg: &mut Dwg, name: &str) -> u64 {
+    use crate::{
+        objects::Layer,
+        types::{CmColor, Color},
+    };
+
+    let handle = dwg.next_handle();
+    dwg.handle_records[0] = handle + 1;
+    let linetype = dwg
+        .find_table_entry(object_types::LTYPE_CONTROL, "Continuous")
+        .unwrap()
+        .unwrap();
+    let layer = Layer {
+        entry: TableEntry::new(handle, dwg.handle_records[2], name),
+        flags: 0,
+        color: CmColor::from_color(Color::Index(1)),
+        plotstyle: HandleRef::new(RefType::HardPointer, 0),
+        linetype: HandleRef::new(RefType::HardPointer, linetype),
+    };
+    dwg.insert_object(handle, object_types::LAYER, &layer)
+        .unwrap();
+    dwg.add_table_entry(object_types::LAYER_CONTROL, handle)
+        .unwrap();
+    handle
+}
+
+/// Adds a block holding a line on `layer` then text on layer "0", for tests
+#[cfg(test)]
+fn add_test_block(dwg: &mut Dwg, name: &str, layer: u64) -> u64 {
+    use crate::{
+        entities::{Block, EndBlk, Line, Text},
+        types::{Point2, Point3},
+    };
+
+    let [block, block_entity, endblk, line, text] = std::array::from_fn(|_| {
+        let handle = dwg.next_handle();
+        dwg.handle_records[0] = handle + 1;
+        handle
+    });
+    let find = |control_type, name| dwg.find_table_entry(control_type, name).unwrap().unwrap();
+    let layer_0 = find(object_types::LAYER_CONTROL, "0");
+    let style = find(object_types::STYLE_CONTROL, "Standard");
+    let null = HandleRef::new(RefType::SoftPointer, 0);
+    let owner = Some(HandleRef::new(RefType::SoftPointer, block));
+    let entity = |handle, layer| CommonEntityData::new(handle, 0, owner, layer);
+    let z_axis = Point3 {
+        x: 0.0,
+        y: 0.0,
+        z: 1.0,
+    };
+
+    let entry = TableEntry::new(block, dwg.handle_records[1], name);
+    let mut header = BlockHeader::new(entry, block_entity, endblk);
+    if dwg.version() < DWGVersion::AC1018 {
+        header.first_last = Some((null, null));
+    }
+    dwg.insert_object(block, object_types::BLOCK_HEADER, &header)
+        .unwrap();
+    let mut block_header = entity(block_entity, layer_0);
+    block_header.links = Some((null, null));
+    let block_entity_object = Block {
+        header: block_header.clone(),
+        name: String::from(name),
+    };
+    dwg.insert_object(block_entity, object_types::BLOCK, &block_entity_object)
+        .unwrap();
+    block_header.handle = endblk;
+    let endblk_object = EndBlk {
+        header: block_header,
+    };
+    dwg.insert_object(endblk, object_types::ENDBLK, &endblk_object)
+        .unwrap();
+
+    let line_object = Line {
+        header: entity(line, layer),
+        start: Point3::default(),
+        end: z_axis,
+        thickness: 0.0,
+        extrusion: z_axis,
+    };
+    dwg.insert_object(line, object_types::LINE, &line_object)
+        .unwrap();
+    let text_object = Text {
+        header: entity(text, layer_0),
+        insertion: Point3::default(),
+        alignment: Point2::default(),
+        extrusion: z_axis,
+        thickness: 0.0,
+        oblique_angle: 0.0,
+        rotation: 0.0,
+        height: 1.0,
+        width_factor: 1.0,
+        value: String::from(name),
+        generation: 0,
+        horizontal_alignment: 0,
+        vertical_alignment: 0,
+        style: HandleRef::new(RefType::HardPointer, style),
+    };
+    dwg.insert_object(text, object_types::TEXT, &text_object)
+        .unwrap();
+    dwg.append_to_block(block, &[line, text]).unwrap();
+    dwg.add_table_entry(object_types::BLOCK_CONTROL, block)
+        .unwrap();
+    block
+}
+
+#[test]
+fn test_rebase() {
+    let soft = HandleRef::new(RefType::SoftPointer, 0x10);
+    assert_eq!(
+        rebase(soft, 0x20, 0x5),
+        HandleRef::new(RefType::SoftPointer, 0x20)
+    );
+    let offset = HandleRef::new(RefType::PlusOne, 0);
+    assert_eq!(rebase(offset, 0x21, 0x20), offset);
+    assert_eq!(
+        rebase(offset, 0x1F, 0x20),
+        HandleRef::new(RefType::MinusOne, 0)
+    );
+    assert_eq!(
+        rebase(offset, 0x30, 0x20),
+        HandleRef::new(RefType::PlusOffset, 0x10)
+    );
+    assert_eq!(
+        rebase(offset, 0x10, 0x20),
+        HandleRef::new(RefType::MinusOffset, 0x10)
+    );
+    assert_eq!(
+        rebase(offset, 0, 0x20),
+        HandleRef::new(RefType::SoftPointer, 0)
+    );
+}
+
+#[test]
+fn test_import_block() {
+    use crate::types::Point3;
+
+    for version in [DWGVersion::AC1015, DWGVersion::AC1018] {
+        let mut source = Dwg::new(version).unwrap();
+        let walls = add_test_layer(&mut source, "Walls");
+        add_test_block(&mut source, "Door", walls);
+
+        let mut dwg = Dwg::new(version).unwrap();
+        add_test_layer(&mut dwg, "Other");
+        dwg.add_line(Point3::default(), Point3::default()).unwrap();
+        let block = dwg.import_block(&source, "DOOR").unwrap();
+        assert_eq!(
+            dwg.find_table_entry(object_types::BLOCK_CONTROL, "Door"),
+            Ok(Some(block))
+        );
+        let find = |control_type, name| dwg.find_table_entry(control_type, name).unwrap();
+        let walls_copy = find(object_types::LAYER_CONTROL, "Walls").unwrap();
+        assert_ne!(walls_copy, walls);
+
+        let entities = dwg.block_entities(block).unwrap();
+        assert_eq!(entities.len(), 2);
+        let entity = |handle| {
+            PartialObject::<CommonEntityData>::decode(
+                dwg.get_object(handle).unwrap(),
+                version,
+                dwg.codepage,
+            )
+            .unwrap()
+        };
+        let line = entity(entities[0]);
+        assert_eq!(line.header.layer.absolute(entities[0]), walls_copy);
+        assert_eq!(line.header.owner.unwrap().absolute(entities[0]), block);
+        let text = entity(entities[1]);
+        let layer_0 = find(object_types::LAYER_CONTROL, "0");
+        assert_eq!(Some(text.header.layer.absolute(entities[1])), layer_0);
+        let style = text.handle_refs().unwrap()[0].absolute(entities[1]);
+        assert_eq!(Some(style), find(object_types::STYLE_CONTROL, "Standard"));
+
+        // The block is not redefined by importing it again
+        let n_objects = dwg.objects.len();
+        assert_eq!(dwg.import_block(&source, "Door"), Ok(block));
+        assert_eq!(dwg.objects.len(), n_objects);
+        assert_eq!(
+            dwg.import_block(&source, "Window"),
+            Err(DwgError::MissingObject {
+                handle: source.handle_records[1]
+            })
+        );
+
+        if version == DWGVersion::AC1015 {
+            let read = Dwg::from_bytes(&dwg.to_bytes().unwrap()).unwrap();
+            assert_eq!(read.objects, dwg.objects);
+        }
+    }
+}
+
+#[test]
+fn test_merge() {
+    use crate::types::Point3;
+
+    for version in [DWGVersion::AC1015, DWGVersion::AC1018] {
+        let mut source = Dwg::new(version).unwrap();
+        let walls = add_test_layer(&mut source, "Walls");
+        add_test_block(&mut source, "Door", walls);
+        source
+            .add_line(Point3::default(), Point3::default())
+            .unwrap();
+        source.add_text(Point3::default(), 1.0, "Merged").unwrap();
+
+        let mut dwg = Dwg::new(version).unwrap();
+        let line = dwg.add_line(Point3::default(), Point3::default()).unwrap();
+        dwg.merge(&source).unwrap();
+
+        let entities = dwg.block_entities(dwg.model_space().unwrap()).unwrap();
+        assert_eq!(entities.len(), 3);
+        assert_eq!(entities[0], line);
+        let door = dwg
+            .find_table_entry(object_types::BLOCK_CONTROL, "Door")
+            .unwrap()
+            .unwrap();
+        assert_eq!(dwg.block_entities(door).unwrap().len(), 2);
+        // Layer 0 is shared rather than copied
+        let (_, layers) = dwg.control_object(object_types::LAYER_CONTROL).unwrap();
+        assert_eq!(layers.entries.len(), 2);
+    }
+
+    let mut dwg = Dwg::new(DWGVersion::AC1018).unwrap();
+    let source = Dwg::new(DWGVersion::AC1015).unwrap();
+    assert!(matches!(
+        dwg.merge(&source),
+        Err(DwgError::ConversionUnsupported { .. })
+    ));
+}
@@ -0,0 +1,1995 @@
+//! Typed objects and the common data at the start of every object
+//!
+//! In AC1015 (R2000) and AC1018 (R2004) objects start with their type and an RL holding the
+//! size of their data stream in bits, where their handle stream starts. This is followed by
+//! the common data of the object, decoded as an [`ObjectHeader`] or, for entities, an
+//! [`CommonEntityData`], then the data of the particular type. Both have parts in each stream.
+//! R13 and R14 objects store the RL within their common data instead, after the EED and any
+//! proxy graphics.
+//!
+//! Only the objects needed to build drawings are typed here, and entities in
+//! [`crate::entities`]. The common data of any other object can be edited through
+//! [`PartialObject`], which keeps the rest of the object raw
+use crate::{
+    bitcodes::{BitReader, BitSliceReader, ByteSource},
+    bitcodes_write::BitWriter,
+    decode::{DwgDecode, ObjectRead},
+    dwg::Dwg,
+    error::{DwgError, Result},
+    streams::{DualStreamReader, DualStreamWriter},
+    types::{CmColor, CodePage, Color, EntityColor, HandleRef, Point3, RawBits, RefType},
+    version::DWGVersion,
+};
+
+/// Codes of the object types with fixed type numbers
+pub mod object_types {
+    pub const TEXT: i16 = 0x01;
+    pub const ATTRIB: i16 = 0x02;
+    pub const ATTDEF: i16 = 0x03;
+    pub const BLOCK: i16 = 0x04;
+    pub const ENDBLK: i16 = 0x05;
+    pub const SEQEND: i16 = 0x06;
+    pub const INSERT: i16 = 0x07;
+    pub const MINSERT: i16 = 0x08;
+    pub const VERTEX_2D: i16 = 0x0A;
+    pub const VERTEX_3D: i16 = 0x0B;
+    pub const VERTEX_MESH: i16 = 0x0C;
+    pub const VERTEX_PFACE: i16 = 0x0D;
+    pub const VERTEX_PFACE_FACE: i16 = 0x0E;
+    pub const POLYLINE_2D: i16 = 0x0F;
+    pub const POLYLINE_3D: i16 = 0x10;
+    pub const ARC: i16 = 0x11;
+    pub const CIRCLE: i16 = 0x12;
+    pub const LINE: i16 = 0x13;
+    pub const POINT: i16 = 0x1B;
+    pub const FACE_3D: i16 = 0x1C;
+    pub const POLYLINE_PFACE: i16 = 0x1D;
+    pub const POLYLINE_MESH: i16 = 0x1E;
+    pub const SOLID: i16 = 0x1F;
+    pub const TRACE: i16 = 0x20;
+    pub const SHAPE: i16 = 0x21;
+    pub const ELLIPSE: i16 = 0x23;
+    pub const SPLINE: i16 = 0x24;
+    pub const RAY: i16 = 0x28;
+    pub const XLINE: i16 = 0x29;
+    pub const DICTIONARY: i16 = 0x2A;
+    pub const MTEXT: i16 = 0x2C;
+    pub const LEADER: i16 = 0x2D;
+    pub const BLOCK_CONTROL: i16 = 0x30;
+    pub const BLOCK_HEADER: i16 = 0x31;
+    pub const LAYER_CONTROL: i16 = 0x32;
+    pub const LAYER: i16 = 0x33;
+    pub const STYLE_CONTROL: i16 = 0x34;
+    pub const STYLE: i16 = 0x35;
+    pub const LTYPE_CONTROL: i16 = 0x38;
+    pub const LTYPE: i16 = 0x39;
+    pub const VIEW_CONTROL: i16 = 0x3C;
+    pub const VIEW: i16 = 0x3D;
+    pub const UCS_CONTROL: i16 = 0x3E;
+    pub const UCS: i16 = 0x3F;
+    pub const VPORT_CONTROL: i16 = 0x40;
+    pub const VPORT: i16 = 0x41;
+    pub const APPID_CONTROL: i16 = 0x42;
+    pub const APPID: i16 = 0x43;
+    pub const DIMSTYLE_CONTROL: i16 = 0x44;
+    pub const DIMSTYLE: i16 = 0x45;
+    pub const VP_ENT_HDR_CONTROL: i16 = 0x46;
+    pub const VP_ENT_HDR: i16 = 0x47;
+    pub const MLINESTYLE: i16 = 0x49;
+    pub const LWPOLYLINE: i16 = 0x4D;
+}
+
+/// Returns true if objects of type `object_type` are entities
+///
+/// Types from 500 are defined by the classes of the drawing, which must be looked up to tell
+/// whether they are entities. This returns false for them
+pub fn is_entity_type(object_type: i16) -> bool {
+    matches!(object_type, 0x01..=0x29 | 0x2B..=0x2F | 0x4A | 0x4D | 0x4E)
+}
+
+/// Returns the type of the control object of the table entries of type `object_type`, or
+/// None if they are not table entries
+pub fn table_control_type(object_type: i16) -> Option<i16> {
+    match object_type {
+        0x31 | 0x33 | 0x35 | 0x39 | 0x3D | 0x3F | 0x41 | 0x43 | 0x45 | 0x47 => {
+            Some(object_type - 1)
+        }
+        _ => None,
+    }
+}
+
+/// A type that can be encoded to the streams of an object, the counterpart of [`DwgDecode`]
+pub trait DwgEncode {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()>;
+}
+
+/// Encodes an object as its type and the size of its data stream followed by `object`
+///
+/// Text is kept in the data stream, so AC1021 (R2007) and later objects are written with no
+/// string stream. Fails with `ValueOutOfRange` if a value of the object does not fit in the
+/// datatype it is written as
+pub fn encode_object(
+    object_type: i16,
+    object: &impl DwgEncode,
+    version: DWGVersion,
+    codepage: CodePage,
+) -> Result<Vec<u8>> {
+    let mut writer = DualStreamWriter::new(version, codepage);
+    writer.write_object_type(object_type);
+    if version >= DWGVersion::AC1015 {
+        writer.write_bitsize();
+    }
+    object.encode(&mut writer)?;
+    if version >= DWGVersion::AC1021 {
+        writer.write_bit(0);
+    }
+    writer.into_bytes()
+}
+
+/// Returns the type of an object and a reader over its streams, positioned after the type and
+/// the size of the data stream
+pub fn open_object(
+    data: &[u8],
+    version: DWGVersion,
+    codepage: CodePage,
+) -> Result<(i16, DualStreamReader<'_>)> {
+    let (object_type, bitsize, reader) = read_object_start(data, version, codepage)?;
+    Ok((object_type, DualStreamReader::new(reader, bitsize)?))
+}
+
+/// Decodes an object, failing with `InvalidFlag` if it is not of type `object_type`
+pub fn decode_object<T: DwgDecode>(
+    data: &[u8],
+    object_type: i16,
+    version: DWGVersion,
+    codepage: CodePage,
+) -> Result<T> {
+    let (found, mut reader) = open_object(data, version, codepage)?;
+    if found != object_type {
+        return Err(DwgError::InvalidFlag {
+            at_bit: 0,
+            value: found as u16 as u64,
+        });
+    }
+    T::decode(&mut reader)
+}
+
+/// Reads the type and data stream size of an object, returning a reader positioned after them
+///
+/// AC1024 (R2010) and later objects instead start with the MC size of the handle stream,
+/// which ends the object. R13 and R14 objects store the size after their handle, EED and,
+/// for entities, proxy graphics, which are skipped to find it and read again by the common
+/// data. Types from 500 are taken not to be entities, see [`is_entity_type`]
+fn read_object_start(
+    data: &[u8],
+    version: DWGVersion,
+    codepage: CodePage,
+) -> Result<(i16, u64, BitSliceReader<'_>)> {
+    let mut reader = BitSliceReader::from_slice(data);
+    reader.set_version(version);
+    reader.set_codepage(codepage);
+    if version >= DWGVersion::AC1024 {
+        let handle_bits = reader.read_modular_char_u64()?;
+        let size = data.len() as u64 * 8;
+        let bitsize = size
+            .checked_sub(handle_bits)
+            .ok_or(DwgError::InvalidStreamSize { at_bit: size })?;
+        return Ok((reader.read_object_type()?, bitsize, reader));
+    }
+    let object_type = reader.read_object_type()?;
+    if version < DWGVersion::AC1015 {
+        let mut ahead = reader.sub_reader(0, data.len())?;
+        ahead.seek_to_bit(reader.bit_position())?;
+        ahead.read_handle()?;
+        read_eed(&mut ahead)?;
+        if is_entity_type(object_type) {
+            read_graphics(&mut ahead)?;
+        }
+        let bitsize = ahead.read_raw_long()? as u32 as u64;
+        return Ok((object_type, bitsize, reader));
+    }
+    let bitsize = reader.read_raw_long()? as u32 as u64;
+    Ok((object_type, bitsize, reader))
+}
+
+/// Reads the flag saying whether an entity has proxy graphics, followed by their size and
+/// data if it does. The size is an RL, or a BLL from AC1024 (R2010)
+fn read_graphics<S: ByteSource>(reader: &mut BitReader<S>) -> Result<Option<Vec<u8>>> {
+    if reader.read_bit()? == 0 {
+        return Ok(None);
+    }
+    let at_bit = reader.bit_position();
+    let size = match reader.get_version() >= DWGVersion::AC1024 {
+        true => usize::try_from(reader.read_bitlonglong()?),
+        false => Ok(reader.read_raw_long()? as u32 as usize),
+    };
+    let size = size.map_err(|_| DwgError::Overflow { at_bit })?;
+    Ok(Some(reader.read_vec(size)?))
+}
+
+/// Reads extended entity data (EED), blocks of a BS size, the handle of the application the
+/// block belongs to and the data, ending with a size of 0
+fn read_eed<S: ByteSource>(reader: &mut BitReader<S>) -> Result<Vec<(HandleRef, Vec<u8>)>> {
+    let mut eed = Vec::new();
+    loop {
+        let size = reader.read_bitshort()? as u16 as usize;
+        if size == 0 {
+            return Ok(eed);
+        }
+        let app = reader.read_handle()?;
+        eed.push((app, reader.read_vec(size)?));
+    }
+}
+
+fn write_eed(writer: &mut BitWriter, eed: &[(HandleRef, Vec<u8>)]) {
+    for (app, data) in eed {
+        writer.write_bitshort(data.len() as i16);
+        writer.write_handle(*app);
+        writer.write_bytes(data);
+    }
+    writer.write_bitshort(0);
+}
+
+/// Reads the flag AC1018 (R2004) and later store when an object has no extension dictionary
+fn read_xdic_missing<S: ByteSource>(reader: &mut BitReader<S>) -> Result<bool> {
+    if reader.get_version() < DWGVersion::AC1018 {
+        return Ok(false);
+    }
+    Ok(reader.read_bit()? == 1)
+}
+
+fn write_xdic_missing(writer: &mut BitWriter, xdic: Option<HandleRef>) {
+    if writer.get_version() >= DWGVersion::AC1018 {
+        writer.write_bit(xdic.is_none() as u8);
+    }
+}
+
+/// Reads the extension dictionary handle, which is null rather than missing before AC1018
+/// (R2004)
+fn read_xdic<R: ObjectRead>(reader: &mut R, missing: bool) -> Result<Option<HandleRef>> {
+    if missing {
+        return Ok(None);
+    }
+    let xdic = reader.read_handle()?;
+    Ok((xdic.handle != 0).then_some(xdic))
+}
+
+fn write_xdic(writer: &mut DualStreamWriter, xdic: Option<HandleRef>) {
+    match xdic {
+        Some(xdic) => writer.write_handle(xdic),
+        None if writer.get_version() < DWGVersion::AC1018 => {
+            writer.write_handle(HandleRef::new(RefType::HardOwned, 0))
+        }
+        None => {}
+    }
+}
+
+fn read_handles<R: ObjectRead>(reader: &mut R, n: usize) -> Result<Vec<HandleRef>> {
+    (0..n).map(|_| reader.read_handle()).collect()
+}
+
+fn write_handles(writer: &mut DualStreamWriter, handles: &[HandleRef]) {
+    for handle in handles {
+        writer.write_handle(*handle);
+    }
+}
+
+/// The common data of objects other than entities
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectHeader {
+    pub handle: u64,
+    /// Extended entity data, the handle of the application each block belongs to and its data
+    pub eed: Vec<(HandleRef, Vec<u8>)>,
+    pub owner: HandleRef,
+    pub reactors: Vec<HandleRef>,
+    /// The extension dictionary, if the object has one
+    pub xdic: Option<HandleRef>,
+}
+
+impl ObjectHeader {
+    /// Creates the header of an object with no EED, reactors or extension dictionary
+    pub fn new(handle: u64, owner: HandleRef) -> Self {
+        Self {
+            handle,
+            eed: Vec::new(),
+            owner,
+            reactors: Vec::new(),
+            xdic: None,
+        }
+    }
+}
+
+impl DwgDecode for ObjectHeader {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let handle = reader.data().read_handle()?.handle;
+        let eed = read_eed(reader.data())?;
+        if reader.data().get_version() < DWGVersion::AC1015 {
+            // The size of the data stream, see read_object_start
+            reader.data().read_raw_long()?;
+        }
+        let n_reactors = reader.data().read_bitlong()?;
+        let xdic_missing = read_xdic_missing(reader.data())?;
+
+        let owner = reader.read_handle()?;
+        let reactors = read_handles(reader, n_reactors.max(0) as usize)?;
+        let xdic = read_xdic(reader, xdic_missing)?;
+        Ok(Self {
+            handle,
+            eed,
+            owner,
+            reactors,
+            xdic,
+        })
+    }
+}
+
+impl DwgEncode for ObjectHeader {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        let data = writer.data();
+        data.write_handle(HandleRef::new(RefType::Unspecified, self.handle));
+        write_eed(data, &self.eed);
+        if data.get_version() < DWGVersion::AC1015 {
+            writer.write_bitsize();
+        }
+        let data = writer.data();
+        data.write_bitlong(self.reactors.len() as i32);
+        write_xdic_missing(data, self.xdic);
+
+        writer.write_handle(self.owner);
+        write_handles(writer, &self.reactors);
+        write_xdic(writer, self.xdic);
+        Ok(())
+    }
+}
+
+/// The common data of entities, which every entity starts with before its own data
+///
+/// AC1021 (R2007) adds the material and shadow flags, AC1024 (R2010) the visual styles and
+/// AC1027 (R2013) the flag for DS binary data, see chapter 19.4.1 of the ODS
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommonEntityData {
+    pub handle: u64,
+    /// Extended entity data, the handle of the application each block belongs to and its data
+    pub eed: Vec<(HandleRef, Vec<u8>)>,
+    /// Proxy graphics, if present
+    pub graphics: Option<Vec<u8>>,
+    /// 0 if the owner is stored in `owner`, 1 if the entity is in paper space and 2 if it is
+    /// in model space
+    pub entmode: u8,
+    pub owner: Option<HandleRef>,
+    pub reactors: Vec<HandleRef>,
+    /// The extension dictionary, if the entity has one
+    pub xdic: Option<HandleRef>,
+    /// The previous and next entities of the block, stored before AC1018 (R2004) unless they
+    /// are the adjacent handles
+    pub links: Option<(HandleRef, HandleRef)>,
+    pub color: EntityColor,
+    /// The AcDbColor object, stored if `color.has_color_handle` is set
+    pub color_handle: Option<HandleRef>,
+    pub linetype_scale: f64,
+    /// 0 for ByLayer, 1 for ByBlock, 2 for Continuous or 3 if `linetype` is stored
+    ///
+    /// R13 and R14 only tell ByLayer, 0, from a stored linetype, 3
+    pub linetype_flags: u8,
+    pub linetype: Option<HandleRef>,
+    /// 0 for ByLayer, 1 for ByBlock, 2 for the dictionary default or 3 if `plotstyle` is
+    /// stored. Always 0 before AC1015 (R2000)
+    pub plotstyle_flags: u8,
+    pub plotstyle: Option<HandleRef>,
+    /// 0 for ByLayer, 1 for ByBlock, 2 for the global material or 3 if `material` is stored.
+    /// Always 0 before AC1021 (R2007)
+    pub material_flags: u8,
+    pub material: Option<HandleRef>,
+    /// Whether the entity casts and receives shadows, stored from AC1021 (R2007)
+    pub shadow_flags: u8,
+    /// The full, face and edge visual styles of the entity, stored from AC1024 (R2010) if it
+    /// has them
+    pub visual_styles: [Option<HandleRef>; 3],
+    /// Whether the entity has DS binary data, stored from AC1027 (R2013)
+    pub has_ds_data: bool,
+    pub invisibility: i16,
+    /// Index of the lineweight, 29 for ByLayer. Always 29 before AC1015 (R2000)
+    pub lineweight: u8,
+    pub layer: HandleRef,
+}
+
+impl CommonEntityData {
+    /// Creates the header of a visible entity on `layer` with ByLayer properties
+    ///
+    /// `owner` must be given if `entmode` is 0
+    pub fn new(handle: u64, entmode: u8, owner: Option<HandleRef>, layer: u64) -> Self {
+        Self {
+            handle,
+            eed: Vec::new(),
+            graphics: None,
+            entmode,
+            owner,
+            reactors: Vec::new(),
+            xdic: None,
+            links: None,
+            color: EntityColor {
+                color: Color::ByLayer,
+                transparency: None,
+                has_color_handle: false,
+            },
+            color_handle: None,
+            linetype_scale: 1.0,
+            linetype_flags: 0,
+            linetype: None,
+            plotstyle_flags: 0,
+            plotstyle: None,
+            material_flags: 0,
+            material: None,
+            shadow_flags: 0,
+            visual_styles: [None; 3],
+            has_ds_data: false,
+            invisibility: 0,
+            lineweight: 29,
+            layer: HandleRef::new(RefType::HardPointer, layer),
+        }
+    }
+
+    /// Returns the previous and next entities of the block, resolving the adjacent handles
+    /// assumed when no links are stored
+    pub fn resolved_links(&self) -> (u64, u64) {
+        match self.links {
+            Some((prev, next)) => (prev.absolute(self.handle), next.absolute(self.handle)),
+            None => (self.handle.wrapping_sub(1), self.handle.wrapping_add(1)),
+        }
+    }
+}
+
+impl DwgDecode for CommonEntityData {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let data = reader.data();
+        let version = data.get_version();
+        let handle = data.read_handle()?.handle;
+        let eed = read_eed(data)?;
+        let graphics = read_graphics(data)?;
+        let r2000 = version >= DWGVersion::AC1015;
+        if !r2000 {
+            // The size of the data stream, see read_object_start
+            data.read_raw_long()?;
+        }
+        let entmode = data.read_bits_dyn(2)? as u8;
+        let n_reactors = data.read_bitlong()?;
+        let xdic_missing = read_xdic_missing(data)?;
+        let has_ds_data = version >= DWGVersion::AC1027 && data.read_bit()? == 1;
+        // R13 and R14 only flag whether the linetype is ByLayer
+        let by_layer_linetype = !r2000 && data.read_bit()? == 1;
+        let has_links = version < DWGVersion::AC1018 && data.read_bit()? == 0;
+        let color = data.read_entity_color()?;
+        let linetype_scale = data.read_bitdouble()?;
+        let (linetype_flags, plotstyle_flags) = match r2000 {
+            true => (data.read_bits_dyn(2)? as u8, data.read_bits_dyn(2)? as u8),
+            false if by_layer_linetype => (0, 0),
+            false => (3, 0),
+        };
+        let (material_flags, shadow_flags) = match version >= DWGVersion::AC1021 {
+            true => (data.read_bits_dyn(2)? as u8, data.read_raw_char()? as u8),
+            false => (0, 0),
+        };
+        let mut has_visual_styles = [false; 3];
+        if version >= DWGVersion::AC1024 {
+            for has_visual_style in &mut has_visual_styles {
+                *has_visual_style = data.read_bit()? == 1;
+            }
+        }
+        let invisibility = data.read_bitshort()?;
+        let lineweight = match r2000 {
+            true => data.read_raw_char()? as u8,
+            false => 29,
+        };
+
+        let owner = match entmode {
+            0 => Some(reader.read_handle()?),
+            _ => None,
+        };
+        let reactors = read_handles(reader, n_reactors.max(0) as usize)?;
+        let xdic = read_xdic(reader, xdic_missing)?;
+        // R13 and R14 store the layer and linetype before the links
+        let layer_linetype = |reader: &mut R| -> Result<_> {
+            let layer = reader.read_handle()?;
+            let linetype = match linetype_flags {
+                3 => Some(reader.read_handle()?),
+                _ => None,
+            };
+            Ok((layer, linetype))
+        };
+        let early = match r2000 {
+            true => None,
+            false => Some(layer_linetype(reader)?),
+        };
+        let links = match has_links {
+            true => Some((reader.read_handle()?, reader.read_handle()?)),
+            false => None,
+        };
+        let color_handle = match version >= DWGVersion::AC1018 && color.has_color_handle {
+            true => Some(reader.read_handle()?),
+            false => None,
+        };
+        let (layer, linetype) = match early {
+            Some(early) => early,
+            None => layer_linetype(reader)?,
+        };
+        let material = match material_flags {
+            3 => Some(reader.read_handle()?),
+            _ => None,
+        };
+        let plotstyle = match plotstyle_flags {
+            3 => Some(reader.read_handle()?),
+            _ => None,
+        };
+        let mut visual_styles = [None; 3];
+        for (visual_style, has) in visual_styles.iter_mut().zip(has_visual_styles) {
+            if has {
+                *visual_style = Some(reader.read_handle()?);
+            }
+        }
+        Ok(Self {
+            handle,
+            eed,
+            graphics,
+            entmode,
+            owner,
+            reactors,
+            xdic,
+            links,
+            color,
+            color_handle,
+            linetype_scale,
+            linetype_flags,
+            linetype,
+            plotstyle_flags,
+            plotstyle,
+            material_flags,
+            material,
+            shadow_flags,
+            visual_styles,
+            has_ds_data,
+            invisibility,
+            lineweight,
+            layer,
+        })
+    }
+}
+
+impl DwgEncode for CommonEntityData {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        let data = writer.data();
+        let version = data.get_version();
+        data.write_handle(HandleRef::new(RefType::Unspecified, self.handle));
+        write_eed(data, &self.eed);
+        data.write_bit(self.graphics.is_some() as u8);
+        if let Some(graphics) = &self.graphics {
+            match version >= DWGVersion::AC1024 {
+                true => data.write_bitlonglong(graphics.len() as i64)?,
+                false => data.write_raw_long(graphics.len() as i32),
+            }
+            data.write_bytes(graphics);
+        }
+        let r2000 = version >= DWGVersion::AC1015;
+        let r2007 = version >= DWGVersion::AC1021;
+        if !r2000 {
+            writer.write_bitsize();
+        }
+        let linetype = self.linetype.filter(|_| self.linetype_flags == 3);
+        let data = writer.data();
+        data.write_bits_dyn(self.entmode as u64, 2)?;
+        data.write_bitlong(self.reactors.len() as i32);
+        write_xdic_missing(data, self.xdic);
+        if version >= DWGVersion::AC1027 {
+            data.write_bit(self.has_ds_data as u8);
+        }
+        if !r2000 {
+            data.write_bit(linetype.is_none() as u8);
+        }
+        if version < DWGVersion::AC1018 {
+            data.write_bit(self.links.is_none() as u8);
+        }
+        data.write_entity_color(&self.color);
+        data.write_bitdouble(self.linetype_scale);
+        if r2000 {
+            data.write_bits_dyn(self.linetype_flags as u64, 2)?;
+            data.write_bits_dyn(self.plotstyle_flags as u64, 2)?;
+        }
+        if r2007 {
+            data.write_bits_dyn(self.material_flags as u64, 2)?;
+            data.write_raw_char(self.shadow_flags as i8);
+        }
+        if version >= DWGVersion::AC1024 {
+            for visual_style in self.visual_styles {
+                data.write_bit(visual_style.is_some() as u8);
+            }
+        }
+        data.write_bitshort(self.invisibility);
+        if r2000 {
+            data.write_raw_char(self.lineweight as i8);
+        }
+
+        if self.entmode == 0 {
+            let owner = self
+                .owner
+                .unwrap_or(HandleRef::new(RefType::SoftPointer, 0));
+            writer.write_handle(owner);
+        }
+        write_handles(writer, &self.reactors);
+        write_xdic(writer, self.xdic);
+        let write_layer_linetype = |writer: &mut DualStreamWriter| {
+            writer.write_handle(self.layer);
+            if let Some(linetype) = linetype {
+                writer.write_handle(linetype);
+            }
+        };
+        if !r2000 {
+            write_layer_linetype(writer);
+        }
+        if let (true, Some((prev, next))) = (version < DWGVersion::AC1018, self.links) {
+            writer.write_handle(prev);
+            writer.write_handle(next);
+        }
+        if let (true, Some(color_handle)) = (version >= DWGVersion::AC1018, self.color_handle) {
+            writer.write_handle(color_handle);
+        }
+        if r2000 {
+            write_layer_linetype(writer);
+        }
+        if let (true, 3, Some(material)) = (r2007, self.material_flags, self.material) {
+            writer.write_handle(material);
+        }
+        if let (true, 3, Some(plotstyle)) = (r2000, self.plotstyle_flags, self.plotstyle) {
+            writer.write_handle(plotstyle);
+        }
+        if version >= DWGVersion::AC1024 {
+            self.visual_styles
+                .into_iter()
+                .flatten()
+                .for_each(|visual_style| writer.write_handle(visual_style));
+        }
+        Ok(())
+    }
+}
+
+/// An object with only its common data decoded
+///
+/// The rest of the data and handle streams are kept raw, so the common data of an object of
+/// any type can be edited and the object encoded again with [`encode_object`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartialObject<H> {
+    pub object_type: i16,
+    pub header: H,
+    pub data: RawBits,
+    pub handles: RawBits,
+}
+
+impl<H: DwgDecode> PartialObject<H> {
+    pub fn decode(data: &[u8], version: DWGVersion, codepage: CodePage) -> Result<Self> {
+        let (object_type, bitsize, reader) = read_object_start(data, version, codepage)?;
+        let mut reader = DualStreamReader::new(reader, bitsize)?;
+        let header = H::decode(&mut reader)?;
+        let data_end = reader.data_end();
+        let data_bits = data_end
+            .checked_sub(reader.bit_position())
+            .ok_or(DwgError::InvalidStreamSize { at_bit: data_end })?;
+        let rest = reader.read_raw_bits(data_bits)?;
+        let handles = reader.handles();
+        let handle_bits = (data.len() as u64 * 8).saturating_sub(handles.bit_position());
+        let handles = handles.read_raw_bits(handle_bits)?;
+        Ok(Self {
+            object_type,
+            header,
+            data: rest,
+            handles,
+        })
+    }
+}
+
+impl<H> PartialObject<H> {
+    /// Decodes the handle stream following the common data
+    ///
+    /// Every handle reference an object stores after its common data is in this part of the
+    /// stream, so it is a list of handles. Fewer than 8 bits left at the end are padding
+    pub fn handle_refs(&self) -> Result<Vec<HandleRef>> {
+        let mut reader = BitSliceReader::from_slice(self.handles.as_bytes());
+        let mut refs = Vec::new();
+        while self.handles.bit_len() - reader.bit_position() >= 8 {
+            refs.push(reader.read_handle()?);
+        }
+        Ok(refs)
+    }
+
+    /// Replaces the handle stream following the common data with `refs`
+    pub fn set_handle_refs(&mut self, refs: &[HandleRef]) {
+        let mut writer = BitWriter::new();
+        for handle in refs {
+            writer.write_handle(*handle);
+        }
+        let bit_len = writer.bit_position();
+        self.handles = RawBits::new(writer.into_bytes(), bit_len);
+    }
+}
+
+impl<H: DwgEncode> DwgEncode for PartialObject<H> {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        writer.write_raw_bits(&self.data);
+        writer.handles().write_raw_bits(&self.handles);
+        Ok(())
+    }
+}
+
+/// The data common to entries of the symbol tables, such as layers and blocks
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableEntry {
+    pub header: ObjectHeader,
+    pub name: String,
+    pub flag_64: bool,
+    /// One more than the index of the xref the entry comes from, or 0
+    pub xref_index: i16,
+    /// True if the entry depends on an xref
+    pub xdep: bool,
+    pub xref_block: HandleRef,
+}
+
+impl TableEntry {
+    /// Creates an entry of the table controlled by `control`
+    pub fn new(handle: u64, control: u64, name: &str) -> Self {
+        Self {
+            header: ObjectHeader::new(handle, HandleRef::new(RefType::SoftPointer, control)),
+            name: String::from(name),
+            flag_64: false,
+            xref_index: 0,
+            xdep: false,
+            xref_block: HandleRef::new(RefType::HardPointer, 0),
+        }
+    }
+}
+
+impl DwgDecode for TableEntry {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let header = ObjectHeader::decode(reader)?;
+        let name = reader.read_text()?;
+        let data = reader.data();
+        let flag_64 = data.read_bit()? == 1;
+        let xref_index = data.read_bitshort()?;
+        let xdep = data.read_bit()? == 1;
+        let xref_block = reader.read_handle()?;
+        Ok(Self {
+            header,
+            name,
+            flag_64,
+            xref_index,
+            xdep,
+            xref_block,
+        })
+    }
+}
+
+impl DwgEncode for TableEntry {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        writer.write_text(&self.name)?;
+        writer.write_bit(self.flag_64 as u8);
+        writer.write_bitshort(self.xref_index);
+        writer.write_bit(self.xdep as u8);
+        writer.write_handle(self.xref_block);
+        Ok(())
+    }
+}
+
+/// The control object of a symbol table, which owns its entries
+#[derive(Clone, Debug, PartialEq)]
+pub struct ControlObject {
+    pub header: ObjectHeader,
+    pub entries: Vec<HandleRef>,
+    /// Entries that are not counted, *Model_Space and *Paper_Space for the block table and
+    /// ByBlock and ByLayer for the linetype table
+    pub extra: Vec<HandleRef>,
+}
+
+impl ControlObject {
+    /// Number of uncounted entries tables of type `object_type` have
+    fn n_extra(object_type: i16) -> usize {
+        match object_type {
+            object_types::BLOCK_CONTROL | object_types::LTYPE_CONTROL => 2,
+            _ => 0,
+        }
+    }
+
+    /// Decodes a control object, which must be of type `object_type`
+    pub fn read<R: ObjectRead>(reader: &mut R, object_type: i16) -> Result<Self> {
+        let header = ObjectHeader::decode(reader)?;
+        let n_entries = reader.data().read_bitlong()?;
+        let entries = read_handles(reader, n_entries.max(0) as usize)?;
+        let extra = read_handles(reader, Self::n_extra(object_type))?;
+        Ok(Self {
+            header,
+            entries,
+            extra,
+        })
+    }
+}
+
+impl DwgEncode for ControlObject {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        writer.write_bitlong(self.entries.len() as i32);
+        write_handles(writer, &self.entries);
+        write_handles(writer, &self.extra);
+        Ok(())
+    }
+}
+
+/// A block table entry
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockHeader {
+    pub entry: TableEntry,
+    pub anonymous: bool,
+    pub has_attributes: bool,
+    pub is_xref: bool,
+    pub is_overlaid: bool,
+    /// Only stored from AC1015 (R2000)
+    pub loaded: bool,
+    pub base_point: Point3,
+    pub xref_path: String,
+    /// Only stored from AC1015 (R2000)
+    pub description: String,
+    /// Only stored from AC1015 (R2000)
+    pub preview: Vec<u8>,
+    /// Only stored from AC1021 (R2007)
+    pub insert_units: i16,
+    /// Only stored from AC1021 (R2007)
+    pub explodable: bool,
+    /// Only stored from AC1021 (R2007)
+    pub block_scaling: u8,
+    /// The BLOCK entity starting the block
+    pub block_entity: HandleRef,
+    /// The first and last entities of the block before AC1018 (R2004), which are linked
+    /// through [`CommonEntityData::links`]. Not stored for xrefs
+    pub first_last: Option<(HandleRef, HandleRef)>,
+    /// Every entity of the block from AC1018 (R2004). Not stored for xrefs
+    pub entities: Vec<HandleRef>,
+    /// The ENDBLK entity ending the block
+    pub endblk_entity: HandleRef,
+    /// INSERT entities referencing the block. Only stored from AC1015 (R2000)
+    pub inserts: Vec<HandleRef>,
+    /// Only stored from AC1015 (R2000)
+    pub layout: HandleRef,
+}
+
+impl BlockHeader {
+    /// Creates an empty block
+    pub fn new(entry: TableEntry, block_entity: u64, endblk_entity: u64) -> Self {
+        Self {
+            entry,
+            anonymous: false,
+            has_attributes: false,
+            is_xref: false,
+            is_overlaid: false,
+            loaded: false,
+            base_point: Point3::default(),
+            xref_path: String::new(),
+            description: String::new(),
+            preview: Vec::new(),
+            insert_units: 0,
+            explodable: true,
+            block_scaling: 0,
+            block_entity: HandleRef::new(RefType::HardOwned, block_entity),
+            first_last: None,
+            entities: Vec::new(),
+            endblk_entity: HandleRef::new(RefType::HardOwned, endblk_entity),
+            inserts: Vec::new(),
+            layout: HandleRef::new(RefType::HardPointer, 0),
+        }
+    }
+
+    /// Returns true if the entities of the block are stored
+    pub(crate) fn has_entities(&self) -> bool {
+        !self.is_xref && !self.is_overlaid
+    }
+}
+
+impl DwgDecode for BlockHeader {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let entry = TableEntry::decode(reader)?;
+        let data = reader.data();
+        let version = data.get_version();
+        let anonymous = data.read_bit()? == 1;
+        let has_attributes = data.read_bit()? == 1;
+        let is_xref = data.read_bit()? == 1;
+        let is_overlaid = data.read_bit()? == 1;
+        let r2000 = version >= DWGVersion::AC1015;
+        let loaded = r2000 && data.read_bit()? == 1;
+        let has_entities = !is_xref && !is_overlaid;
+        let n_entities = match version >= DWGVersion::AC1018 && has_entities {
+            true => data.read_bitlong()?.max(0) as usize,
+            false => 0,
+        };
+        let base_point = data.read_3bd()?;
+        let xref_path = reader.read_text()?;
+        let (n_inserts, description, preview) = match r2000 {
+            true => {
+                let data = reader.data();
+                // One non-zero byte per insert, ending with a zero byte
+                let mut n_inserts = 0;
+                while data.read_raw_char()? != 0 {
+                    n_inserts += 1;
+                }
+                let description = reader.read_text()?;
+                let data = reader.data();
+                let preview_size = data.read_bitlong()?.max(0) as usize;
+                (n_inserts, description, data.read_vec(preview_size)?)
+            }
+            false => (0, String::new(), Vec::new()),
+        };
+        let data = reader.data();
+        let (insert_units, explodable, block_scaling) = match version >= DWGVersion::AC1021 {
+            true => (
+                data.read_bitshort()?,
+                data.read_bit()? == 1,
+                data.read_raw_char()? as u8,
+            ),
+            false => (0, true, 0),
+        };
+
+        let block_entity = reader.read_handle()?;
+        let first_last = match version < DWGVersion::AC1018 && has_entities {
+            true => Some((reader.read_handle()?, reader.read_handle()?)),
+            false => None,
+        };
+        let entities = read_handles(reader, n_entities)?;
+        let endblk_entity = reader.read_handle()?;
+        let inserts = read_handles(reader, n_inserts)?;
+        let layout = match r2000 {
+            true => reader.read_handle()?,
+            false => HandleRef::new(RefType::HardPointer, 0),
+        };
+        Ok(Self {
+            entry,
+            anonymous,
+            has_attributes,
+            is_xref,
+            is_overlaid,
+            loaded,
+            base_point,
+            xref_path,
+            description,
+            preview,
+            insert_units,
+            explodable,
+            block_scaling,
+            block_entity,
+            first_last,
+            entities,
+            endblk_entity,
+            inserts,
+            layout,
+        })
+    }
+}
+
+impl DwgEncode for BlockHeader {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.entry.encode(writer)?;
+        let version = writer.get_version();
+        writer.write_bit(self.anonymous as u8);
+        writer.write_bit(self.has_attributes as u8);
+        writer.write_bit(self.is_xref as u8);
+        writer.write_bit(self.is_overlaid as u8);
+        let r2000 = version >= DWGVersion::AC1015;
+        if r2000 {
+            writer.write_bit(self.loaded as u8);
+        }
+        if version >= DWGVersion::AC1018 && self.has_entities() {
+            writer.write_bitlong(self.entities.len() as i32);
+        }
+        writer.write_3bd(self.base_point);
+        writer.write_text(&self.xref_path)?;
+        if r2000 {
+            for _ in &self.inserts {
+                writer.write_raw_char(1);
+            }
+            writer.write_raw_char(0);
+            writer.write_text(&self.description)?;
+            writer.write_bitlong(self.preview.len() as i32);
+            writer.write_bytes(&self.preview);
+        }
+        if version >= DWGVersion::AC1021 {
+            writer.write_bitshort(self.insert_units);
+            writer.write_bit(self.explodable as u8);
+            writer.write_raw_char(self.block_scaling as i8);
+        }
+
+        writer.write_handle(self.block_entity);
+        if version < DWGVersion::AC1018 && self.has_entities() {
+            let (first, last) = self.first_last.unwrap_or((
+                HandleRef::new(RefType::SoftPointer, 0),
+                HandleRef::new(RefType::SoftPointer, 0),
+            ));
+            writer.write_handle(first);
+            writer.write_handle(last);
+        }
+        if version >= DWGVersion::AC1018 && self.has_entities() {
+            write_handles(writer, &self.entities);
+        }
+        writer.write_handle(self.endblk_entity);
+        if r2000 {
+            write_handles(writer, &self.inserts);
+            writer.write_handle(self.layout);
+        }
+        Ok(())
+    }
+}
+
+/// A layer table entry
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layer {
+    pub entry: TableEntry,
+    /// 0x1 if frozen, 0x2 if off, 0x4 if frozen in new viewports, 0x8 if locked and 0x10 if
+    /// plotted. Bits 5-9 hold the index of the lineweight
+    ///
+    /// Before AC1015 (R2000) only the first four flags are stored
+    pub flags: i16,
+    pub color: CmColor,
+    /// Only stored from AC1015 (R2000)
+    pub plotstyle: HandleRef,
+    pub linetype: HandleRef,
+}
+
+impl DwgDecode for Layer {
+    /// The material AC1021 (R2007) and later layers store after the plot style is not kept,
+    /// and is written as none
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let entry = TableEntry::decode(reader)?;
+        let data = reader.data();
+        let version = data.get_version();
+        let r2000 = version >= DWGVersion::AC1015;
+        let flags = match r2000 {
+            true => data.read_bitshort()?,
+            // Frozen, on, frozen in new viewports and locked bits
+            false => {
+                let mut flags = 0;
+                for (flag, set) in [(0x1, 1), (0x2, 0), (0x4, 1), (0x8, 1)] {
+                    if data.read_bit()? == set {
+                        flags |= flag;
+                    }
+                }
+                flags
+            }
+        };
+        let color = data.read_cm_color()?;
+        let plotstyle = match r2000 {
+            true => reader.read_handle()?,
+            false => HandleRef::new(RefType::HardPointer, 0),
+        };
+        if version >= DWGVersion::AC1021 {
+            reader.read_handle()?;
+        }
+        Ok(Self {
+            entry,
+            flags,
+            color,
+            plotstyle,
+            linetype: reader.read_handle()?,
+        })
+    }
+}
+
+impl DwgEncode for Layer {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.entry.encode(writer)?;
+        let r2000 = writer.get_version() >= DWGVersion::AC1015;
+        match r2000 {
+            true => writer.write_bitshort(self.flags),
+            // Frozen, on, frozen in new viewports and locked bits
+            false => {
+                writer.write_bit((self.flags & 0x1 != 0) as u8);
+                writer.write_bit((self.flags & 0x2 == 0) as u8);
+                writer.write_bit((self.flags & 0x4 != 0) as u8);
+                writer.write_bit((self.flags & 0x8 != 0) as u8);
+            }
+        }
+        let codepage = writer.codepage();
+        writer.write_cm_color(&self.color, codepage)?;
+        if r2000 {
+            writer.write_handle(self.plotstyle);
+        }
+        if writer.get_version() >= DWGVersion::AC1021 {
+            // No material
+            writer.write_handle(HandleRef::new(RefType::HardPointer, 0));
+        }
+        writer.write_handle(self.linetype);
+        Ok(())
+    }
+}
+
+/// A text style table entry
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextStyle {
+    pub entry: TableEntry,
+    pub is_shape: bool,
+    pub is_vertical: bool,
+    /// Height of text using the style, or 0 if it is not fixed
+    pub fixed_height: f64,
+    pub width_factor: f64,
+    pub oblique_angle: f64,
+    /// 0x2 if text is mirrored in X and 0x4 if it is mirrored in Y
+    pub generation: u8,
+    pub last_height: f64,
+    pub font_name: String,
+    pub bigfont_name: String,
+}
+
+impl DwgDecode for TextStyle {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let entry = TableEntry::decode(reader)?;
+        let data = reader.data();
+        let is_shape = data.read_bit()? == 1;
+        let is_vertical = data.read_bit()? == 1;
+        let fixed_height = data.read_bitdouble()?;
+        let width_factor = data.read_bitdouble()?;
+        let oblique_angle = data.read_bitdouble()?;
+        let generation = data.read_raw_char()? as u8;
+        let last_height = data.read_bitdouble()?;
+        Ok(Self {
+            entry,
+            is_shape,
+            is_vertical,
+            fixed_height,
+            width_factor,
+            oblique_angle,
+            generation,
+            last_height,
+            font_name: reader.read_text()?,
+            bigfont_name: reader.read_text()?,
+        })
+    }
+}
+
+impl DwgEncode for TextStyle {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.entry.encode(writer)?;
+        writer.write_bit(self.is_shape as u8);
+        writer.write_bit(self.is_vertical as u8);
+        writer.write_bitdouble(self.fixed_height);
+        writer.write_bitdouble(self.width_factor);
+        writer.write_bitdouble(self.oblique_angle);
+        writer.write_raw_char(self.generation as i8);
+        writer.write_bitdouble(self.last_height);
+        writer.write_text(&self.font_name)?;
+        writer.write_text(&self.bigfont_name)?;
+        Ok(())
+    }
+}
+
+/// A linetype table entry without dashes, such as Continuous
+#[derive(Clone, Debug, PartialEq)]
+pub struct Linetype {
+    pub entry: TableEntry,
+    pub description: String,
+}
+
+impl DwgDecode for Linetype {
+    /// Fails with `InvalidFlag` if the linetype has dashes or a pattern length
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let entry = TableEntry::decode(reader)?;
+        let description = reader.read_text()?;
+        let data = reader.data();
+        let at_bit = data.bit_position();
+        let pattern_length = data.read_bitdouble()?;
+        data.read_raw_char()?;
+        let n_dashes = data.read_raw_char()? as u8;
+        if pattern_length != 0.0 || n_dashes != 0 {
+            return Err(DwgError::InvalidFlag {
+                at_bit,
+                value: n_dashes as u64,
+            });
+        }
+        if data.get_version() <= DWGVersion::AC1018 {
+            data.read_vec(256)?;
+        }
+        Ok(Self { entry, description })
+    }
+}
+
+impl DwgEncode for Linetype {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.entry.encode(writer)?;
+        writer.write_text(&self.description)?;
+        // Pattern length
+        writer.write_bitdouble(0.0);
+        writer.write_raw_char(b'A' as i8);
+        // Number of dashes
+        writer.write_raw_char(0);
+        if writer.get_version() <= DWGVersion::AC1018 {
+            // Area holding the text of dashes
+            writer.write_bytes(&[0; 256]);
+        }
+        Ok(())
+    }
+}
+
+/// A registered application table entry
+#[derive(Clone, Debug, PartialEq)]
+pub struct AppId {
+    pub entry: TableEntry,
+}
+
+impl DwgDecode for AppId {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let entry = TableEntry::decode(reader)?;
+        reader.data().read_raw_char()?;
+        Ok(Self { entry })
+    }
+}
+
+impl DwgEncode for AppId {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.entry.encode(writer)?;
+        writer.write_raw_char(0);
+        Ok(())
+    }
+}
+
+/// A dictionary, mapping names to objects
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dictionary {
+    pub header: ObjectHeader,
+    /// How entries are merged when cloned, 1 to keep existing entries. Only stored from
+    /// AC1015 (R2000)
+    pub cloning: i16,
+    /// True if the dictionary is the hard owner of its entries. Only stored from AC1015
+    /// (R2000)
+    pub hard_owner: bool,
+    pub entries: Vec<(String, HandleRef)>,
+}
+
+impl Dictionary {
+    /// Creates a dictionary owned by `owner`, with entries soft owned by the dictionary
+    pub fn new(handle: u64, owner: u64, entries: &[(&str, u64)]) -> Self {
+        Self {
+            header: ObjectHeader::new(handle, HandleRef::new(RefType::SoftPointer, owner)),
+            cloning: 1,
+            hard_owner: false,
+            entries: entries
+                .iter()
+                .map(|&(name, handle)| {
+                    (
+                        String::from(name),
+                        HandleRef::new(RefType::SoftOwned, handle),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl DwgDecode for Dictionary {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let header = ObjectHeader::decode(reader)?;
+        let data = reader.data();
+        let version = data.get_version();
+        let n_entries = data.read_bitlong()?.max(0) as usize;
+        if version == DWGVersion::AC1014 {
+            // Unknown
+            data.read_raw_char()?;
+        }
+        let (cloning, hard_owner) = match version >= DWGVersion::AC1015 {
+            true => (data.read_bitshort()?, data.read_raw_char()? != 0),
+            false => (1, false),
+        };
+        let names = (0..n_entries)
+            .map(|_| reader.read_text())
+            .collect::<Result<Vec<_>>>()?;
+        let handles = read_handles(reader, n_entries)?;
+        Ok(Self {
+            header,
+            cloning,
+            hard_owner,
+            entries: names.into_iter().zip(handles).collect(),
+        })
+    }
+}
+
+impl DwgEncode for Dictionary {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        writer.write_bitlong(self.entries.len() as i32);
+        let version = writer.get_version();
+        if version == DWGVersion::AC1014 {
+            writer.write_raw_char(0);
+        }
+        if version >= DWGVersion::AC1015 {
+            writer.write_bitshort(self.cloning);
+            writer.write_raw_char(self.hard_owner as i8);
+        }
+        for (name, _) in &self.entries {
+            writer.write_text(name)?;
+        }
+        for (_, handle) in &self.entries {
+            writer.write_handle(*handle);
+        }
+        Ok(())
+    }
+}
+
+/// A multiline style with two lines, such as Standard
+#[derive(Clone, Debug, PartialEq)]
+pub struct MlineStyle {
+    pub header: ObjectHeader,
+    pub name: String,
+    pub description: String,
+    /// Offset of each line from the center
+    pub offsets: [f64; 2],
+}
+
+impl DwgDecode for MlineStyle {
+    /// Fails with `InvalidFlag` unless the style has two ByLayer lines and no flags, fill or
+    /// end angles of its own, the only style it encodes
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let header = ObjectHeader::decode(reader)?;
+        let name = reader.read_text()?;
+        let description = reader.read_text()?;
+        let data = reader.data();
+        let at_bit = data.bit_position();
+        let invalid = |value| DwgError::InvalidFlag { at_bit, value };
+        let flags = data.read_bitshort()?;
+        let fill = data.read_cm_color()?.color();
+        let angles = (data.read_bitdouble()?, data.read_bitdouble()?);
+        let n_lines = data.read_raw_char()? as u8;
+        let right_angle = std::f64::consts::FRAC_PI_2;
+        if flags != 0 || fill != Color::ByLayer || angles != (right_angle, right_angle) {
+            return Err(invalid(flags as u16 as u64));
+        }
+        if n_lines != 2 {
+            return Err(invalid(n_lines as u64));
+        }
+        let mut offsets = [0.0; 2];
+        for offset in &mut offsets {
+            *offset = data.read_bitdouble()?;
+            let color = data.read_cm_color()?.color();
+            let linetype = data.read_bitshort()?;
+            if color != Color::ByLayer || linetype != 0x7FFF {
+                return Err(invalid(linetype as u16 as u64));
+            }
+        }
+        Ok(Self {
+            header,
+            name,
+            description,
+            offsets,
+        })
+    }
+}
+
+impl DwgEncode for MlineStyle {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        writer.write_text(&self.name)?;
+        writer.write_text(&self.description)?;
+        // Flags
+        writer.write_bitshort(0);
+        let codepage = writer.codepage();
+        let by_layer = CmColor::from_color(Color::ByLayer);
+        writer.write_cm_color(&by_layer, codepage)?;
+        // Start and end angles
+        writer.write_bitdouble(std::f64::consts::FRAC_PI_2);
+        writer.write_bitdouble(std::f64::consts::FRAC_PI_2);
+        writer.write_raw_char(self.offsets.len() as i8);
+        for offset in self.offsets {
+            writer.write_bitdouble(offset);
+            writer.write_cm_color(&by_layer, codepage)?;
+            // Index of the linetype, ByLayer
+            writer.write_bitshort(0x7FFF);
+        }
+        Ok(())
+    }
+}
+
+/// The style of multileaders, see [`crate::entities::MultiLeader`]
+///
+/// Its type is defined by the class named `MLEADERSTYLE`, so it is read through
+/// [`Dwg::mleader_style`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MLeaderStyle {
+    pub header: ObjectHeader,
+    /// Stored from AC1024 (R2010), 2
+    pub class_version: i16,
+    /// 0 for none, 1 for a block, 2 for an MTEXT or 3 for a tolerance
+    pub content_type: i16,
+    /// 0 to draw content first or 1 to draw leaders first
+    pub multileader_order: i16,
+    /// 0 to draw leaders head first or 1 tail first
+    pub leader_order: i16,
+    pub max_points: i32,
+    pub first_segment_angle: f64,
+    pub second_segment_angle: f64,
+    /// 0 for invisible leaders, 1 for straight line segments or 2 for splines
+    pub leader_type: i16,
+    pub line_color: CmColor,
+    pub line_type: HandleRef,
+    pub line_weight: i32,
+    pub landing_enabled: bool,
+    pub dogleg_enabled: bool,
+    pub landing_gap: f64,
+    pub landing_distance: f64,
+    pub description: String,
+    pub arrowhead: HandleRef,
+    pub arrowhead_size: f64,
+    pub default_text: String,
+    pub text_style: HandleRef,
+    pub text_left_attachment: i16,
+    pub text_right_attachment: i16,
+    pub text_angle_type: i16,
+    pub text_alignment: i16,
+    pub text_color: CmColor,
+    pub text_height: f64,
+    pub text_frame: bool,
+    pub text_always_left: bool,
+    pub align_space: f64,
+    pub block: HandleRef,
+    pub block_color: CmColor,
+    pub block_scale: Point3,
+    pub block_scale_enabled: bool,
+    pub block_rotation: f64,
+    pub block_rotation_enabled: bool,
+    /// 0 to connect to the extents of the block or 1 to its insertion point
+    pub block_connection: i16,
+    pub scale_factor: f64,
+    /// True if a property was changed since the style was last applied
+    pub changed: bool,
+    pub annotative: bool,
+    pub break_size: f64,
+    /// Stored from AC1024 (R2010), 0 for horizontal or 1 for vertical attachment
+    pub attachment_direction: i16,
+    /// Stored from AC1024 (R2010)
+    pub text_top_attachment: i16,
+    /// Stored from AC1024 (R2010)
+    pub text_bottom_attachment: i16,
+    /// Stored from AC1027 (R2013)
+    pub extended_to_text: bool,
+}
+
+impl DwgDecode for MLeaderStyle {
+    fn decode<R: ObjectRead>(reader: &mut R) -> Result<Self> {
+        let header = ObjectHeader::decode(reader)?;
+        let data = reader.data();
+        let version = data.get_version();
+        let class_version = match version >= DWGVersion::AC1024 {
+            true => data.read_bitshort()?,
+            false => 0,
+        };
+        let content_type = data.read_bitshort()?;
+        let multileader_order = data.read_bitshort()?;
+        let leader_order = data.read_bitshort()?;
+        let max_points = data.read_bitlong()?;
+        let first_segment_angle = data.read_bitdouble()?;
+        let second_segment_angle = data.read_bitdouble()?;
+        let leader_type = data.read_bitshort()?;
+        let line_color = data.read_cm_color()?;
+        let line_type = reader.read_handle()?;
+        let data = reader.data();
+        let line_weight = data.read_bitlong()?;
+        let landing_enabled = data.read_bit()? == 1;
+        let dogleg_enabled = data.read_bit()? == 1;
+        let landing_gap = data.read_bitdouble()?;
+        let landing_distance = data.read_bitdouble()?;
+        let description = reader.read_text()?;
+        let arrowhead = reader.read_handle()?;
+        let arrowhead_size = reader.data().read_bitdouble()?;
+        let default_text = reader.read_text()?;
+        let text_style = reader.read_handle()?;
+        let data = reader.data();
+        let text_left_attachment = data.read_bitshort()?;
+        let text_right_attachment = data.read_bitshort()?;
+        let text_angle_type = data.read_bitshort()?;
+        let text_alignment = data.read_bitshort()?;
+        let text_color = data.read_cm_color()?;
+        let text_height = data.read_bitdouble()?;
+        let text_frame = data.read_bit()? == 1;
+        let text_always_left = data.read_bit()? == 1;
+        let align_space = data.read_bitdouble()?;
+        let block = reader.read_handle()?;
+        let data = reader.data();
+        let block_color = data.read_cm_color()?;
+        let block_scale = data.read_3bd()?;
+        let block_scale_enabled = data.read_bit()? == 1;
+        let block_rotation = data.read_bitdouble()?;
+        let block_rotation_enabled = data.read_bit()? == 1;
+        let block_connection = data.read_bitshort()?;
+        let scale_factor = data.read_bitdouble()?;
+        let changed = data.read_bit()? == 1;
+        let annotative = data.read_bit()? == 1;
+        let break_size = data.read_bitdouble()?;
+        let (attachment_direction, text_top_attachment, text_bottom_attachment) =
+            match version >= DWGVersion::AC1024 {
+                true => (
+                    data.read_bitshort()?,
+                    data.read_bitshort()?,
+                    data.read_bitshort()?,
+                ),
+                false => (0, 0, 0),
+            };
+        let extended_to_text = match version >= DWGVersion::AC1027 {
+            true => data.read_bit()? == 1,
+            false => false,
+        };
+        Ok(Self {
+            header,
+            class_version,
+            content_type,
+            multileader_order,
+            leader_order,
+            max_points,
+            first_segment_angle,
+            second_segment_angle,
+            leader_type,
+            line_color,
+            line_type,
+            line_weight,
+            landing_enabled,
+            dogleg_enabled,
+            landing_gap,
+            landing_distance,
+            description,
+            arrowhead,
+            arrowhead_size,
+            default_text,
+            text_style,
+            text_left_attachment,
+            text_right_attachment,
+            text_angle_type,
+            text_alignment,
+            text_color,
+            text_height,
+            text_frame,
+            text_always_left,
+            align_space,
+            block,
+            block_color,
+            block_scale,
+            block_scale_enabled,
+            block_rotation,
+            block_rotation_enabled,
+            block_connection,
+            scale_factor,
+            changed,
+            annotative,
+            break_size,
+            attachment_direction,
+            text_top_attachment,
+            text_bottom_attachment,
+            extended_to_text,
+        })
+    }
+}
+
+impl DwgEncode for MLeaderStyle {
+    fn encode(&self, writer: &mut DualStreamWriter) -> Result<()> {
+        self.header.encode(writer)?;
+        let version = writer.get_version();
+        let codepage = writer.codepage();
+        if version >= DWGVersion::AC1024 {
+            writer.write_bitshort(self.class_version);
+        }
+        writer.write_bitshort(self.content_type);
+        writer.write_bitshort(self.multileader_order);
+        writer.write_bitshort(self.leader_order);
+        writer.write_bitlong(self.max_points);
+        writer.write_bitdouble(self.first_segment_angle);
+        writer.write_bitdouble(self.second_segment_angle);
+        writer.write_bitshort(self.leader_type);
+        writer.data().write_cm_color(&self.line_color, codepage)?;
+        writer.write_handle(self.line_type);
+        writer.write_bitlong(self.line_weight);
+        writer.write_bit(self.landing_enabled as u8);
+        writer.write_bit(self.dogleg_enabled as u8);
+        writer.write_bitdouble(self.landing_gap);
+        writer.write_bitdouble(self.landing_distance);
+        writer.write_text(&self.description)?;
+        writer.write_handle(self.arrowhead);
+        writer.write_bitdouble(self.arrowhead_size);
+        writer.write_text(&self.default_text)?;
+        writer.write_handle(self.text_style);
+        writer.write_bitshort(self.text_left_attachment);
+        writer.write_bitshort(self.text_right_attachment);
+        writer.write_bitshort(self.text_angle_type);
+        writer.write_bitshort(self.text_alignment);
+        writer.data().write_cm_color(&self.text_color, codepage)?;
+        writer.write_bitdouble(self.text_height);
+        writer.write_bit(self.text_frame as u8);
+        writer.write_bit(self.text_always_left as u8);
+        writer.write_bitdouble(self.align_space);
+        writer.write_handle(self.block);
+        writer.data().write_cm_color(&self.block_color, codepage)?;
+        writer.write_3bd(self.block_scale);
+        writer.write_bit(self.block_scale_enabled as u8);
+        writer.write_bitdouble(self.block_rotation);
+        writer.write_bit(self.block_rotation_enabled as u8);
+        writer.write_bitshort(self.block_connection);
+        writer.write_bitdouble(self.scale_factor);
+        writer.write_bit(self.changed as u8);
+        writer.write_bit(self.annotative as u8);
+        writer.write_bitdouble(self.break_size);
+        if version >= DWGVersion::AC1024 {
+            writer.write_bitshort(self.attachment_direction);
+            writer.write_bitshort(self.text_top_attachment);
+            writer.write_bitshort(self.text_bottom_attachment);
+        }
+        if version >= DWGVersion::AC1027 {
+            writer.write_bit(self.extended_to_text as u8);
+        }
+        Ok(())
+    }
+}
+
+impl Dwg {
+    /// Reads the MLEADERSTYLE with the handle `handle`, failing the same way as
+    /// [`Dwg::multileader`]
+    pub fn mleader_style(&self, handle: u64) -> Result<MLeaderStyle> {
+        self.decode_class_object(handle, "MLEADERSTYLE")
+    }
+}
+
+#[test]
+fn test_object_headers() {
+    for version in [DWGVersion::AC1014, DWGVersion::AC1015, DWGVersion::AC1018] {
+        let mut entry = TableEntry::new(0x1F, 0x1, "*Model_Space");
+        entry
+            .header
+            .eed
+            .push((HandleRef::new(RefType::HardPointer, 0x12), vec![1, 2, 3]));
+        entry
+            .header
+            .reactors
+            .push(HandleRef::new(RefType::SoftPointer, 0x40));
+        entry.header.xdic = Some(HandleRef::new(RefType::HardOwned, 0x41));
+        let mut block = BlockHeader::new(entry, 0x20, 0x21);
+        if version >= DWGVersion::AC1015 {
+            block
+                .inserts
+                .push(HandleRef::new(RefType::SoftPointer, 0x50));
+            block.preview = vec![9; 3];
+        }
+        let entity = HandleRef::new(RefType::SoftPointer, 0x30);
+        if version < DWGVersion::AC1018 {
+            block.first_last = Some((entity, entity));
+        } else {
+            block.entities.push(entity);
+        }
+        let data = encode_object(
+            object_types::BLOCK_HEADER,
+            &block,
+            version,
+            CodePage::ANSI1252,
+        )
+        .unwrap();
+        let decoded = decode_object::<BlockHeader>(
+            &data,
+            object_types::BLOCK_HEADER,
+            version,
+            CodePage::ANSI1252,
+        );
+        assert_eq!(decoded, Ok(block));
+        assert!(decode_object::<BlockHeader>(
+            &data,
+            object_types::LAYER,
+            version,
+            CodePage::ANSI1252
+        )
+        .is_err());
+
+        let dictionary = Dictionary::new(0xC, 0, &[("ACAD_GROUP", 0xD)]);
+        let data = encode_object(
+            object_types::DICTIONARY,
+            &dictionary,
+            version,
+            CodePage::ANSI1252,
+        )
+        .unwrap();
+        let decoded = decode_object(&data, object_types::DICTIONARY, version, CodePage::ANSI1252);
+        assert_eq!(decoded, Ok(dictionary));
+    }
+}
+
+#[test]
+fn test_partial_object() {
+    for version in [
+        DWGVersion::AC1014,
+        DWGVersion::AC1015,
+        DWGVersion::AC1018,
+        DWGVersion::AC1021,
+        DWGVersion::AC1024,
+        DWGVersion::AC1027,
+    ] {
+        let mut header = CommonEntityData::new(0x30, 2, None, 0x10);
+        header.graphics = Some(vec![1, 2]);
+        header.linetype_flags = 3;
+        header.linetype = Some(HandleRef::new(RefType::HardPointer, 0x14));
+        if version >= DWGVersion::AC1021 {
+            header.material_flags = 3;
+            header.material = Some(HandleRef::new(RefType::HardPointer, 0x15));
+            header.shadow_flags = 2;
+        }
+        if version >= DWGVersion::AC1024 {
+            header.visual_styles[1] = Some(HandleRef::new(RefType::HardPointer, 0x16));
+        }
+        header.has_ds_data = version >= DWGVersion::AC1027;
+        if version < DWGVersion::AC1018 {
+            header.links = Some((
+                HandleRef::new(RefType::SoftPointer, 0),
+                HandleRef::new(RefType::SoftPointer, 0),
+            ));
+        }
+        let line = crate::entities::Line {
+            header,
+            start: Point3 {
+                x: 1.0,
+                y: 2.0,
+                z: 0.0,
+            },
+            end: Point3 {
+                x: 1.0,
+                y: 5.0,
+                z: 3.0,
+            },
+            thickness: 0.0,
+            extrusion: Point3 {
+                x: 0.0,
+                y: 0.0,
+                z: 1.0,
+            },
+        };
+        let data = encode_object(object_types::LINE, &line, version, CodePage::ANSI1252).unwrap();
+        let mut partial =
+            PartialObject::<CommonEntityData>::decode(&data, version, CodePage::ANSI1252).unwrap();
+        assert_eq!(partial.object_type, object_types::LINE);
+        assert_eq!(partial.handle_refs(), Ok(vec![]));
+        assert_eq!(partial.header, line.header);
+        assert_eq!(
+            encode_object(object_types::LINE, &partial, version, CodePage::ANSI1252).unwrap(),
+            data
+        );
+
+        // Editing the common data keeps the rest of the line
+        partial.header.layer = HandleRef::new(RefType::HardPointer, 0x1234);
+        let mut expected = line.clone();
+        expected.header.layer = partial.header.layer;
+        assert_eq!(
+            encode_object(object_types::LINE, &partial, version, CodePage::ANSI1252).unwrap(),
+            encode_object(object_types::LINE, &expected, version, CodePage::ANSI1252).unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_common_entity_data_r2007() {
+    let hard = |handle| Some(HandleRef::new(RefType::HardPointer, handle));
+    let mut header = CommonEntityData::new(0x30, 2, None, 0x10);
+    header.material_flags = 3;
+    header.material = hard(0x15);
+    header.shadow_flags = 2;
+    header.plotstyle_flags = 3;
+    header.plotstyle = hard(0x17);
+    header.visual_styles = [hard(0x16), None, hard(0x18)];
+
+    // A LINE with no data of its own, the visual styles stored after the plot style
+    let expected = [
+        0x56, 0x04, 0xC0, 0x4C, 0x25, 0x74, 0xF0, 0x2B, 0x0E, 0x94, 0x44, 0x14, 0x45, 0x54, 0x45,
+        0xD4, 0x45, 0x94, 0x46, 0x00,
+    ];
+    let partial = PartialObject {
+        object_type: object_types::LINE,
+        header: header.clone(),
+        data: RawBits::default(),
+        handles: RawBits::default(),
+    };
+    let version = DWGVersion::AC1024;
+    let data = encode_object(object_types::LINE, &partial, version, CodePage::ANSI1252).unwrap();
+    assert_eq!(data, expected);
+    let decoded = PartialObject::<CommonEntityData>::decode(&expected, version, CodePage::ANSI1252);
+    assert_eq!(decoded.unwrap().header, header);
+
+    for version in [DWGVersion::AC1021, DWGVersion::AC1024, DWGVersion::AC1027] {
+        let mut variants = vec![header.clone()];
+        for flags in 0..3 {
+            variants.push(CommonEntityData {
+                material_flags: flags,
+                material: None,
+                ..header.clone()
+            });
+        }
+        for shadow_flags in [0, 1, 3] {
+            variants.push(CommonEntityData {
+                shadow_flags,
+                ..header.clone()
+            });
+        }
+        for flags in 0..3 {
+            variants.push(CommonEntityData {
+                plotstyle_flags: flags,
+                plotstyle: None,
+                ..header.clone()
+            });
+        }
+        for i in 0..8 {
+            let mut visual_styles = [None; 3];
+            for (bit, visual_style) in visual_styles.iter_mut().enumerate() {
+                if i >> bit & 1 == 1 {
+                    *visual_style = hard(0x20 + bit as u64);
+                }
+            }
+            variants.push(CommonEntityData {
+                visual_styles,
+                ..header.clone()
+            });
+        }
+        variants.push(CommonEntityData {
+            has_ds_data: true,
+            ..header.clone()
+        });
+        for variant in variants {
+            let partial = PartialObject {
+                header: variant.clone(),
+                ..partial.clone()
+            };
+            let data =
+                encode_object(object_types::LINE, &partial, version, CodePage::ANSI1252).unwrap();
+            let decoded =
+                PartialObject::<CommonEntityData>::decode(&data, version, CodePage::ANSI1252)
+                    .unwrap();
+            // Visual styles are only stored from AC1024 and DS data from AC1027
+            let mut expected = variant;
+            if version < DWGVersion::AC1024 {
+                expected.visual_styles = [None; 3];
+            }
+            expected.has_ds_data &= version >= DWGVersion::AC1027;
+            assert_eq!(decoded.header, expected, "{version:?}");
+        }
+    }
+}
+
+#[test]
+fn test_handle_refs() {
+    let style = |handle| HandleRef::new(RefType::HardPointer, handle);
+    let text = |style| crate::entities::Text {
+        header: CommonEntityData::new(0x30, 2, None, 0x10),
+        insertion: Point3::default(),
+        alignment: crate::types::Point2::default(),
+        extrusion: Point3 {
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        },
+        thickness: 0.0,
+        oblique_angle: 0.0,
+        rotation: 0.0,
+        height: 1.0,
+        width_factor: 1.0,
+        value: String::from("a"),
+        generation: 0,
+        horizontal_alignment: 0,
+        vertical_alignment: 0,
+        style,
+    };
+    let version = DWGVersion::AC1018;
+    let data = encode_object(
+        object_types::TEXT,
+        &text(style(0x11)),
+        version,
+        CodePage::ANSI1252,
+    )
+    .unwrap();
+    let mut partial =
+        PartialObject::<CommonEntityData>::decode(&data, version, CodePage::ANSI1252).unwrap();
+    assert_eq!(partial.handle_refs(), Ok(vec![style(0x11)]));
+
+    partial.set_handle_refs(&[style(0x1234)]);
+    assert_eq!(
+        encode_object(object_types::TEXT, &partial, version, CodePage::ANSI1252).unwrap(),
+        encode_object(
+            object_types::TEXT,
+            &text(style(0x1234)),
+            version,
+            CodePage::ANSI1252
+        )
+        .unwrap()
+    );
+}
+
+#[test]
+fn test_mleader_style() {
+    use crate::classes::{write_classes, DwgClass, OBJECT_CLASS_ID};
+
+    let hard = |handle| HandleRef::new(RefType::HardPointer, handle);
+    let by_block = CmColor::from_color(Color::ByBlock);
+    let mut style = MLeaderStyle {
+        header: ObjectHeader::new(0x100, HandleRef::new(RefType::SoftPointer, 0x0C)),
+        class_version: 0,
+        content_type: 2,
+        multileader_order: 1,
+        leader_order: 0,
+        max_points: 2,
+        first_segment_angle: 0.0,
+        second_segment_angle: 0.0,
+        leader_type: 1,
+        line_color: by_block.clone(),
+        line_type: hard(0x14),
+        line_weight: -2,
+        landing_enabled: true,
+        dogleg_enabled: true,
+        landing_gap: 0.09,
+        landing_distance: 0.36,
+        description: String::new(),
+        arrowhead: hard(0),
+        arrowhead_size: 0.18,
+        default_text: "Note".into(),
+        text_style: hard(0x11),
+        text_left_attachment: 1,
+        text_right_attachment: 1,
+        text_angle_type: 1,
+        text_alignment: 0,
+        text_color: by_block.clone(),
+        text_height: 0.18,
+        text_frame: false,
+        text_always_left: false,
+        align_space: 4.0,
+        block: hard(0),
+        block_color: by_block,
+        block_scale: Point3::from((1.0, 1.0, 1.0)),
+        block_scale_enabled: false,
+        block_rotation: 0.0,
+        block_rotation_enabled: false,
+        block_connection: 0,
+        scale_factor: 1.0,
+        changed: false,
+        annotative: false,
+        break_size: 0.125,
+        attachment_direction: 0,
+        text_top_attachment: 0,
+        text_bottom_attachment: 0,
+        extended_to_text: false,
+    };
+    for version in [DWGVersion::AC1018, DWGVersion::AC1024, DWGVersion::AC1027] {
+        if version >= DWGVersion::AC1024 {
+            style.class_version = 2;
+            style.text_top_attachment = 9;
+            style.text_bottom_attachment = 9;
+        }
+        style.extended_to_text = version >= DWGVersion::AC1027;
+        let data = encode_object(500, &style, version, CodePage::ANSI1252).unwrap();
+        let decoded = decode_object(&data, 500, version, CodePage::ANSI1252);
+        assert_eq!(decoded, Ok(style.clone()), "{version:?}");
+    }
+
+    // The type is looked up through the classes of the drawing
+    let mut dwg = Dwg::new(DWGVersion::AC1018).unwrap();
+    let class = DwgClass {
+        number: 500,
+        proxy_flags: 0xFFF,
+        app_name: "ObjectDBX Classes".into(),
+        cpp_class_name: "AcDbMLeaderStyle".into(),
+        dxf_name: "MLEADERSTYLE".into(),
+        was_zombie: false,
+        item_class_id: OBJECT_CLASS_ID,
+        instance_count: 1,
+        dwg_version: 0,
+        maintenance_version: 0,
+    };
+    dwg.classes = write_classes(&[class], dwg.version(), dwg.codepage).unwrap();
+    style.class_version = 0;
+    style.text_top_attachment = 0;
+    style.text_bottom_attachment = 0;
+    style.extended_to_text = false;
+    dwg.insert_object(0x100, 500, &style).unwrap();
+    dwg.insert_object(0x101, 501, &style).unwrap();
+    assert_eq!(dwg.mleader_style(0x100), Ok(style));
+    assert!(matches!(
+        dwg.mleader_style(0x101),
+        Err(DwgError::InvalidFlag { value: 501, .. })
+    ));
+    assert!(matches!(
+        dwg.mleader_style(0x102),
+        Err(DwgError::MissingObject { handle: 0x102 })
+    ));
+}
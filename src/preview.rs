@@ -0,0 +1,235 @@
+//! The preview image section, holding the thumbnail file browsers show for a drawing
+//!
+//! The section is wrapped in sentinels and starts with a directory of its entries, each a
+//! code and the address and size of its data. The entries follow the directory in order
+use crate::{
+    bitcodes::BitSliceReader,
+    bitcodes_write::BitWriter,
+    error::{DwgError, Result},
+    sentinels,
+};
+
+/// Code of the entry holding the preview header data
+pub const HEADER: u8 = 1;
+
+/// Code of the entry holding a BMP image, without its file header
+pub const BMP: u8 = 2;
+
+/// Code of the entry holding a WMF image
+pub const WMF: u8 = 3;
+
+/// Code of the entry holding a PNG image, used from AC1027 (R2013)
+pub const PNG: u8 = 6;
+
+/// Size of the BMP file header that is left out of BMP entries
+const BMP_FILE_HEADER_SIZE: usize = 14;
+
+/// An entry of the preview image section
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreviewEntry {
+    /// What the entry holds, such as [`BMP`]
+    pub code: u8,
+    pub data: Vec<u8>,
+}
+
+/// The preview image section of a drawing
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Preview {
+    pub entries: Vec<PreviewEntry>,
+}
+
+/// The image of a preview, see [`Preview::thumbnail`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Thumbnail<'a> {
+    /// A device independent bitmap, a BMP file without its file header
+    Bmp(&'a [u8]),
+    Wmf(&'a [u8]),
+    Png(&'a [u8]),
+}
+
+impl Thumbnail<'_> {
+    /// Returns the image as the contents of an image file, adding the file header BMP images
+    /// are stored without
+    pub fn to_file(&self) -> Vec<u8> {
+        match *self {
+            Thumbnail::Bmp(dib) => {
+                // Pixels follow the info header and the color table of 4 byte entries
+                let info_size = dib.get(..4).map_or(0, |size| {
+                    u32::from_le_bytes(size.try_into().unwrap()) as usize
+                });
+                let bit_count = dib.get(14..16).map_or(0, |count| {
+                    u16::from_le_bytes(count.try_into().unwrap()) as u32
+                });
+                let colors = match dib.get(32..36) {
+                    Some(used) if used != [0; 4] => {
+                        u32::from_le_bytes(used.try_into().unwrap()) as usize
+                    }
+                    _ if bit_count <= 8 => 1 << bit_count,
+                    _ => 0,
+                };
+                let pixels = BMP_FILE_HEADER_SIZE + info_size + 4 * colors;
+
+                let mut writer = BitWriter::new();
+                writer.write_bytes(b"BM");
+                writer.write_raw_long((BMP_FILE_HEADER_SIZE + dib.len()) as i32);
+                writer.write_raw_long(0);
+                writer.write_raw_long(pixels as i32);
+                writer.write_bytes(dib);
+                writer.into_bytes()
+            }
+            Thumbnail::Wmf(data) | Thumbnail::Png(data) => data.to_vec(),
+        }
+    }
+}
+
+impl Preview {
+    /// Reads the preview image section from the start of `data`
+    ///
+    /// The addresses of the entries are from the start of the file in R13-R2000 files but
+    /// are not always so in later files, so the entries are located relative to the first,
+    /// which immediately follows the directory. Fails with `BadSentinel` if either sentinel
+    /// is missing and `SeekOutOfRange` if an entry is past the end of the section
+    pub fn read(data: &[u8]) -> Result<Preview> {
+        let mut reader = BitSliceReader::from_slice(data);
+        reader.read_sentinel(&sentinels::PREVIEW_START)?;
+        let size = reader.read_raw_long()? as u32 as u64;
+        let n_entries = reader.read_raw_char()? as u8;
+        let mut directory = Vec::with_capacity(n_entries as usize);
+        for _ in 0..n_entries {
+            let code = reader.read_raw_char()? as u8;
+            let address = reader.read_raw_long()? as u32 as u64;
+            let size = reader.read_raw_long()? as u32 as u64;
+            directory.push((code, address, size));
+        }
+
+        let first = reader.byte_position();
+        let base = directory.iter().map(|&(_, address, _)| address).min();
+        let mut entries = Vec::with_capacity(directory.len());
+        for (code, address, size) in directory {
+            let start = first + address - base.unwrap_or_default();
+            let data = data.get(start as usize..(start + size) as usize).ok_or(
+                DwgError::SeekOutOfRange {
+                    offset: (start + size) * 8,
+                },
+            )?;
+            entries.push(PreviewEntry {
+                code,
+                data: data.to_vec(),
+            });
+        }
+        reader.seek_to_bit((16 + 4 + size) * 8)?;
+        reader.read_sentinel(&sentinels::PREVIEW_END)?;
+        Ok(Preview { entries })
+    }
+
+    /// Writes the preview image section at `address` in the file, the inverse of
+    /// [`Preview::read`]
+    pub fn write(&self, address: u64) -> Vec<u8> {
+        let directory_size = 1 + 9 * self.entries.len();
+        let data_size: usize = self.entries.iter().map(|entry| entry.data.len()).sum();
+        let mut writer = BitWriter::new();
+        writer.write_sentinel(&sentinels::PREVIEW_START);
+        writer.write_raw_long((directory_size + data_size) as i32);
+        writer.write_raw_char(self.entries.len() as i8);
+        let mut entry_address = address + 16 + 4 + directory_size as u64;
+        for entry in &self.entries {
+            writer.write_raw_char(entry.code as i8);
+            writer.write_raw_long(entry_address as i32);
+            writer.write_raw_long(entry.data.len() as i32);
+            entry_address += entry.data.len() as u64;
+        }
+        for entry in &self.entries {
+            writer.write_bytes(&entry.data);
+        }
+        writer.write_sentinel(&sentinels::PREVIEW_END);
+        writer.into_bytes()
+    }
+
+    /// Returns the image of the preview, preferring a PNG to a BMP to a WMF if it has several
+    pub fn thumbnail(&self) -> Option<Thumbnail<'_>> {
+        let find = |code| {
+            self.entries
+                .iter()
+                .find(|entry| entry.code == code)
+                .map(|entry| entry.data.as_slice())
+        };
+        find(PNG)
+            .map(Thumbnail::Png)
+            .or_else(|| find(BMP).map(Thumbnail::Bmp))
+            .or_else(|| find(WMF).map(Thumbnail::Wmf))
+    }
+}
+
+#[test]
+fn test_preview() {
+    let preview = Preview {
+        entries: vec![
+            PreviewEntry {
+                code: HEADER,
+                data: vec![1; 80],
+            },
+            PreviewEntry {
+                code: BMP,
+                data: vec![2; 10],
+            },
+        ],
+    };
+    let data = preview.write(0x1000);
+    assert_eq!(data.len(), 16 + 4 + 1 + 18 + 90 + 16);
+    // The header follows the directory
+    assert_eq!(data[22..26], (0x1000u32 + 39).to_le_bytes());
+    assert_eq!(Preview::read(&data), Ok(preview.clone()));
+    assert_eq!(preview.thumbnail(), Some(Thumbnail::Bmp(&[2; 10])));
+
+    // Addresses relative to the section are read the same
+    assert_eq!(Preview::read(&preview.write(0)), Ok(preview));
+
+    let mut corrupt = data.clone();
+    corrupt[data.len() - 1] ^= 1;
+    assert!(matches!(
+        Preview::read(&corrupt),
+        Err(DwgError::BadSentinel { .. })
+    ));
+    assert!(matches!(
+        Preview::read(&data[..100]),
+        Err(DwgError::SeekOutOfRange { .. })
+    ));
+
+    let png = Preview {
+        entries: vec![
+            PreviewEntry {
+                code: BMP,
+                data: vec![2; 10],
+            },
+            PreviewEntry {
+                code: PNG,
+                data: b"\x89PNG".to_vec(),
+            },
+        ],
+    };
+    assert_eq!(png.thumbnail(), Some(Thumbnail::Png(b"\x89PNG")));
+    assert_eq!(Preview::default().thumbnail(), None);
+}
+
+#[test]
+fn test_bmp_file() {
+    // A 1x1 24 bit bitmap, with no color table
+    let mut dib = vec![0; 40];
+    dib[..4].copy_from_slice(&40u32.to_le_bytes());
+    dib[14..16].copy_from_slice(&24u16.to_le_bytes());
+    dib.extend([0xFF, 0, 0, 0]);
+    let file = Thumbnail::Bmp(&dib).to_file();
+    assert_eq!(file[..2], *b"BM");
+    assert_eq!(file[2..6], 58u32.to_le_bytes());
+    assert_eq!(file[10..14], 54u32.to_le_bytes());
+    assert_eq!(file[14..], dib);
+
+    // 8 bit bitmaps have a color table of 256 entries unless it says otherwise
+    dib[14] = 8;
+    assert_eq!(
+        Thumbnail::Bmp(&dib).to_file()[10..14],
+        1078u32.to_le_bytes()
+    );
+    dib[32] = 2;
+    assert_eq!(Thumbnail::Bmp(&dib).to_file()[10..14], 62u32.to_le_bytes());
+}
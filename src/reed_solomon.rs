@@ -0,0 +1,327 @@
+//! The Reed-Solomon (255,239) code protecting the file header and system pages of AC1021
+//! (R2007) files
+//!
+//! Each block is 239 data bytes followed by 16 parity bytes, so up to 8 corrupt bytes can be
+//! corrected. Bytes are elements of GF(256) with the primitive polynomial
+//! x^8 + x^4 + x^3 + x^2 + 1 and the generator polynomial has the roots α^1 to α^16. Data of
+//! several blocks is interleaved, byte `j` of block `i` of `n` being stored at `i + j * n`
+use crate::error::{DwgError, Result};
+
+/// Size of an encoded block
+pub const BLOCK_SIZE: usize = 255;
+
+/// Number of data bytes in each block
+pub const DATA_SIZE: usize = 239;
+
+/// Number of parity bytes in each block
+const PARITY_SIZE: usize = BLOCK_SIZE - DATA_SIZE;
+
+/// Powers of α, repeated so that sums of two logarithms can be looked up directly
+const EXP: [u8; 512] = {
+    let mut table = [0u8; 512];
+    let mut value = 1u16;
+    let mut i = 0;
+    while i < 255 {
+        table[i] = value as u8;
+        table[i + 255] = value as u8;
+        value <<= 1;
+        if value & 0x100 != 0 {
+            value ^= 0x11D;
+        }
+        i += 1;
+    }
+    table
+};
+
+/// Logarithms to base α, with the logarithm of 0 left as 0
+const LOG: [u8; 256] = {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < 255 {
+        table[EXP[i] as usize] = i as u8;
+        i += 1;
+    }
+    table
+};
+
+/// Coefficients of the generator polynomial, highest power first, without the leading 1
+const GENERATOR: [u8; PARITY_SIZE] = {
+    let mut poly = [0u8; PARITY_SIZE + 1];
+    poly[0] = 1;
+    let mut root = 1;
+    while root <= PARITY_SIZE {
+        // Multiply by (x + α^root), from the lowest power so each term is only used once
+        let mut j = root;
+        while j > 0 {
+            poly[j] ^= mul(poly[j - 1], EXP[root]);
+            j -= 1;
+        }
+        root += 1;
+    }
+    let mut generator = [0u8; PARITY_SIZE];
+    let mut i = 0;
+    while i < PARITY_SIZE {
+        generator[i] = poly[i + 1];
+        i += 1;
+    }
+    generator
+};
+
+const fn mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        0
+    } else {
+        EXP[LOG[a as usize] as usize + LOG[b as usize] as usize]
+    }
+}
+
+fn div(a: u8, b: u8) -> u8 {
+    debug_assert_ne!(b, 0);
+    if a == 0 {
+        0
+    } else {
+        EXP[LOG[a as usize] as usize + 255 - LOG[b as usize] as usize]
+    }
+}
+
+/// Evaluates a polynomial with coefficients from the lowest power at `x`
+fn eval_low_first(poly: &[u8], x: u8) -> u8 {
+    poly.iter().rev().fold(0, |acc, &coef| mul(acc, x) ^ coef)
+}
+
+/// Encodes the data of a block, appending its parity bytes
+pub fn encode_block(data: &[u8; DATA_SIZE]) -> [u8; BLOCK_SIZE] {
+    let mut parity = [0u8; PARITY_SIZE];
+    for &byte in data {
+        let feedback = byte ^ parity[0];
+        parity.copy_within(1.., 0);
+        parity[PARITY_SIZE - 1] = 0;
+        for (parity, &coef) in parity.iter_mut().zip(&GENERATOR) {
+            *parity ^= mul(coef, feedback);
+        }
+    }
+    let mut block = [0u8; BLOCK_SIZE];
+    block[..DATA_SIZE].copy_from_slice(data);
+    block[DATA_SIZE..].copy_from_slice(&parity);
+    block
+}
+
+/// Corrects the errors in a block in place, returning how many bytes were corrected, or
+/// `None` if there are more errors than can be corrected
+///
+/// Uses the Berlekamp-Massey algorithm to find the error locations and Forney's algorithm
+/// to find their values
+pub fn decode_block(block: &mut [u8; BLOCK_SIZE]) -> Option<usize> {
+    // Byte i of the block is the coefficient of x^(254 - i)
+    let mut syndromes = [0u8; PARITY_SIZE];
+    for (i, syndrome) in syndromes.iter_mut().enumerate() {
+        let x = EXP[i + 1];
+        *syndrome = block.iter().fold(0, |acc, &byte| mul(acc, x) ^ byte);
+    }
+    if syndromes.iter().all(|&s| s == 0) {
+        return Some(0);
+    }
+
+    // Error locator polynomial, lowest power first
+    let mut locator = vec![1u8];
+    let mut prev = vec![1u8];
+    let mut prev_discrepancy = 1u8;
+    let mut shift = 1;
+    for n in 0..PARITY_SIZE {
+        let discrepancy = (0..locator.len())
+            .filter(|&i| i <= n)
+            .fold(0, |acc, i| acc ^ mul(locator[i], syndromes[n - i]));
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+        let scale = div(discrepancy, prev_discrepancy);
+        let mut next = locator.clone();
+        next.resize(next.len().max(prev.len() + shift), 0);
+        for (i, &coef) in prev.iter().enumerate() {
+            next[i + shift] ^= mul(scale, coef);
+        }
+        if 2 * (locator.len() - 1) <= n {
+            prev = std::mem::replace(&mut locator, next);
+            prev_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            locator = next;
+            shift += 1;
+        }
+    }
+    while locator.last() == Some(&0) {
+        locator.pop();
+    }
+    let n_errors = locator.len() - 1;
+    if n_errors > PARITY_SIZE / 2 {
+        return None;
+    }
+
+    // Error evaluator polynomial, the product of the syndromes and locator mod x^16
+    let mut evaluator = [0u8; PARITY_SIZE];
+    for (i, &coef) in locator.iter().enumerate() {
+        for (j, &syndrome) in syndromes.iter().enumerate().take(PARITY_SIZE - i) {
+            evaluator[i + j] ^= mul(coef, syndrome);
+        }
+    }
+    // The formal derivative keeps only the odd powers
+    let derivative: Vec<u8> = locator
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, &coef)| if i % 2 == 1 { coef } else { 0 })
+        .collect();
+
+    let mut corrected = 0;
+    for power in 0..BLOCK_SIZE {
+        // The locator has a root at the inverse of α^power for an error at x^power
+        let inverse = EXP[(255 - power) % 255];
+        if eval_low_first(&locator, inverse) != 0 {
+            continue;
+        }
+        let denominator = eval_low_first(&derivative, inverse);
+        if denominator == 0 {
+            return None;
+        }
+        block[BLOCK_SIZE - 1 - power] ^= div(eval_low_first(&evaluator, inverse), denominator);
+        corrected += 1;
+    }
+    (corrected == n_errors).then_some(corrected)
+}
+
+/// Corrects and returns the data of `n_blocks` interleaved blocks at the start of `encoded`
+///
+/// Fails with `UnexpectedEof` if `encoded` is too short and `UncorrectableBlock` if a block
+/// has too many errors
+pub fn decode(encoded: &[u8], n_blocks: usize) -> Result<Vec<u8>> {
+    if encoded.len() < n_blocks * BLOCK_SIZE {
+        return Err(DwgError::UnexpectedEof {
+            at_bit: encoded.len() as u64 * 8,
+        });
+    }
+    let mut data = Vec::with_capacity(n_blocks * DATA_SIZE);
+    for i in 0..n_blocks {
+        let mut block = [0u8; BLOCK_SIZE];
+        for (j, byte) in block.iter_mut().enumerate() {
+            *byte = encoded[i + j * n_blocks];
+        }
+        decode_block(&mut block).ok_or(DwgError::UncorrectableBlock { block: i as u32 })?;
+        data.extend(&block[..DATA_SIZE]);
+    }
+    Ok(data)
+}
+
+/// Returns the first `data_size` bytes of each of `n_blocks` interleaved blocks at the start
+/// of `encoded`, without checking or correcting them
+///
+/// This is used for blocks with fewer parity bytes than the code here has, such as the
+/// (255,251) blocks of AC1021 (R2007) data pages
+pub fn deinterleave(encoded: &[u8], n_blocks: usize, data_size: usize) -> Result<Vec<u8>> {
+    if encoded.len() < n_blocks * BLOCK_SIZE {
+        return Err(DwgError::UnexpectedEof {
+            at_bit: encoded.len() as u64 * 8,
+        });
+    }
+    let mut data = Vec::with_capacity(n_blocks * data_size);
+    for i in 0..n_blocks {
+        data.extend((0..data_size).map(|j| encoded[i + j * n_blocks]));
+    }
+    Ok(data)
+}
+
+/// The inverse of [`deinterleave`], leaving the parity bytes 0
+#[cfg(test)]
+pub(crate) fn interleave(data: &[u8], n_blocks: usize, data_size: usize) -> Vec<u8> {
+    let mut encoded = vec![0u8; n_blocks * BLOCK_SIZE];
+    for (k, &byte) in data.iter().enumerate() {
+        encoded[k / data_size + (k % data_size) * n_blocks] = byte;
+    }
+    encoded
+}
+
+/// Encodes `data`, padded with zeros, as `n_blocks` interleaved blocks
+///
+/// Panics if `data` does not fit in `n_blocks` blocks
+pub fn encode(data: &[u8], n_blocks: usize) -> Vec<u8> {
+    assert!(
+        data.len() <= n_blocks * DATA_SIZE,
+        "too much data to encode"
+    );
+    let mut encoded = vec![0u8; n_blocks * BLOCK_SIZE];
+    for i in 0..n_blocks {
+        let mut block_data = [0u8; DATA_SIZE];
+        let chunk = data.get(i * DATA_SIZE..).unwrap_or_default();
+        let len = chunk.len().min(DATA_SIZE);
+        block_data[..len].copy_from_slice(&chunk[..len]);
+        for (j, &byte) in encode_block(&block_data).iter().enumerate() {
+            encoded[i + j * n_blocks] = byte;
+        }
+    }
+    encoded
+}
+
+#[test]
+fn test_field() {
+    assert_eq!(EXP[8], 0x1D);
+    for a in 1..=255u8 {
+        assert_eq!(mul(a, div(1, a)), 1);
+    }
+    // The generator is zero at each of its roots
+    for &root in &EXP[1..=PARITY_SIZE] {
+        let value = GENERATOR.iter().fold(1, |acc, &coef| mul(acc, root) ^ coef);
+        assert_eq!(value, 0);
+    }
+}
+
+#[test]
+fn test_decode_block() {
+    let data: [u8; DATA_SIZE] = std::array::from_fn(|i| (i * 7 + 3) as u8);
+    let block = encode_block(&data);
+    assert_eq!(block[..DATA_SIZE], data);
+    assert_eq!(decode_block(&mut block.clone()), Some(0));
+
+    // Up to 8 errors, anywhere in the block, are corrected
+    let mut corrupt = block;
+    for (n, at) in [0, 17, 100, 238, 239, 240, 250, 254]
+        .into_iter()
+        .enumerate()
+    {
+        corrupt[at] ^= (n as u8 + 1) * 0x11;
+        let mut decoded = corrupt;
+        assert_eq!(decode_block(&mut decoded), Some(n + 1));
+        assert_eq!(decoded, block);
+    }
+    corrupt[50] ^= 0xFF;
+    assert_ne!(decode_block(&mut corrupt), Some(9));
+}
+
+#[test]
+fn test_decode() {
+    let data: Vec<u8> = (0..600).map(|i| (i % 251) as u8).collect();
+    let mut encoded = encode(&data, 3);
+    assert_eq!(encoded.len(), 3 * BLOCK_SIZE);
+    // Interleaving spreads a run of corrupt bytes across the blocks
+    for byte in &mut encoded[30..54] {
+        *byte = !*byte;
+    }
+    let decoded = decode(&encoded, 3).unwrap();
+    assert_eq!(decoded[..600], data);
+    assert!(decoded[600..].iter().all(|&byte| byte == 0));
+
+    for byte in &mut encoded[0..27] {
+        *byte = !*byte;
+    }
+    assert_eq!(
+        decode(&encoded, 3),
+        Err(DwgError::UncorrectableBlock { block: 0 })
+    );
+    assert_eq!(
+        decode(&encoded[1..], 3),
+        Err(DwgError::UnexpectedEof { at_bit: 764 * 8 })
+    );
+
+    let encoded = interleave(&data, 3, 251);
+    assert_eq!(deinterleave(&encoded, 3, 251).unwrap()[..600], data);
+    assert_eq!(encoded[..4], [data[0], data[251], data[502], data[1]]);
+}
@@ -0,0 +1,440 @@
+//! Readers and writers for objects split across multiple bit streams
+//!
+//! Handle references are stored in a handle stream following the object data. From AC1021
+//! (R2007) objects also store their strings in a separate string stream at the end of the
+//! object data rather than inline with the rest of the data
+use std::ops::{Deref, DerefMut};
+
+use crate::{
+    bitcodes::BitSliceReader,
+    bitcodes_write::BitWriter,
+    error::{DwgError, Result},
+    types::{CodePage, HandleRef, RawBits},
+    version::DWGVersion,
+};
+
+/// Reads an object split into data, string and handle streams
+///
+/// Handle reads go to the handle stream. For AC1021 (R2007) and later, text reads go to the
+/// string stream, otherwise they are read inline from the data stream. All other reads go to
+/// the data stream, which this derefs to
+pub struct DualStreamReader<'a> {
+    data: BitSliceReader<'a>,
+    strings: Option<BitSliceReader<'a>>,
+    handles: Option<BitSliceReader<'a>>,
+    /// Where the data stream and, if there is one, the string stream end
+    ends: (u64, u64),
+}
+
+impl<'a> DualStreamReader<'a> {
+    /// Creates a reader for an object
+    ///
+    /// `bitsize` is the size of the object data in bits, the position relative to the start of
+    /// `data` just after the string stream where the handle stream starts. For AC1021 (R2007)
+    /// and later the string stream is located by reading backwards from it: the bit before
+    /// `bitsize` flags whether there is a string stream, preceded by the RS size of the stream
+    /// in bits, preceded by another RS holding the high bits of the size if the high bit of the
+    /// first is set
+    pub fn new(data: BitSliceReader<'a>, bitsize: u64) -> Result<Self> {
+        let strings = if data.get_version() >= DWGVersion::AC1021 {
+            locate_string_stream(&data, bitsize)?
+        } else {
+            None
+        };
+        let mut handles = data.sub_reader(0, data.data().len())?;
+        handles
+            .seek_to_bit(bitsize)
+            .map_err(|_| DwgError::InvalidStreamSize { at_bit: bitsize })?;
+        let ends = match &strings {
+            Some((strings, end)) => (strings.bit_position(), *end),
+            // The flag saying there is no string stream is not part of the data
+            None if data.get_version() >= DWGVersion::AC1021 && bitsize > 0 => {
+                (bitsize - 1, bitsize - 1)
+            }
+            None => (bitsize, bitsize),
+        };
+        Ok(Self {
+            data,
+            strings: strings.map(|(strings, _)| strings),
+            handles: Some(handles),
+            ends,
+        })
+    }
+
+    /// Creates a reader for data with no separate streams, which reads everything inline
+    /// from the data stream
+    pub fn inline(data: BitSliceReader<'a>) -> Self {
+        let end = data.data().len() as u64 * 8;
+        Self {
+            data,
+            strings: None,
+            handles: None,
+            ends: (end, end),
+        }
+    }
+
+    /// Creates a reader for the header variables or classes section data
+    ///
+    /// AC1021 (R2007) and later sections start with the RL size of their data in bits, before
+    /// that the whole section is read inline
+    pub fn section(mut data: BitSliceReader<'a>) -> Result<Self> {
+        if data.get_version() < DWGVersion::AC1021 {
+            return Ok(Self::inline(data));
+        }
+        let bitsize = data.read_raw_long()? as u32 as u64;
+        Self::new(data, bitsize)
+    }
+
+    /// Creates a reader for an AC1024 (R2010) or later object from the size of its handle
+    /// stream in bits
+    ///
+    /// These objects store the MC size of the handle stream after the MS size of the object
+    /// rather than the size of the object data. `data` must span exactly the object, excluding
+    /// the size fields, so that the handle stream ends at the end of `data`
+    pub fn from_handle_stream_size(data: BitSliceReader<'a>, handle_bits: u64) -> Result<Self> {
+        let size = data.data().len() as u64 * 8;
+        let bitsize = size
+            .checked_sub(handle_bits)
+            .ok_or(DwgError::InvalidStreamSize { at_bit: size })?;
+        Self::new(data, bitsize)
+    }
+
+    /// Returns the position the data stream ends at, the start of the string stream if there
+    /// is one, otherwise of the handle stream, or the end of the data if there are no separate
+    /// streams. From AC1021 (R2007) the flag saying there is no string stream is left out
+    pub fn data_end(&self) -> u64 {
+        self.ends.0
+    }
+
+    /// Returns the position the string stream ends at, the start of its size, or
+    /// [`DualStreamReader::data_end`] if there is no string stream
+    pub fn strings_end(&self) -> u64 {
+        self.ends.1
+    }
+
+    /// Returns true if the object has a separate string stream
+    pub fn has_string_stream(&self) -> bool {
+        self.strings.is_some()
+    }
+
+    /// Returns the string stream, or the data stream if strings are stored inline
+    pub fn strings(&mut self) -> &mut BitSliceReader<'a> {
+        self.strings.as_mut().unwrap_or(&mut self.data)
+    }
+
+    /// Reads text from the string stream, see [`crate::bitcodes::BitReader::read_text`]
+    pub fn read_text(&mut self) -> Result<String> {
+        self.strings().read_text()
+    }
+
+    /// Reads unicode text from the string stream, see
+    /// [`crate::bitcodes::BitReader::read_text_unicode`]
+    pub fn read_text_unicode(&mut self) -> Result<String> {
+        self.strings().read_text_unicode()
+    }
+
+    /// Returns the handle stream, or the data stream if handles are stored inline
+    pub fn handles(&mut self) -> &mut BitSliceReader<'a> {
+        self.handles.as_mut().unwrap_or(&mut self.data)
+    }
+
+    /// Reads a handle reference from the handle stream, see
+    /// [`crate::bitcodes::BitReader::read_handle`]
+    pub fn read_handle(&mut self) -> Result<HandleRef> {
+        self.handles().read_handle()
+    }
+
+    /// Returns the data stream
+    pub fn into_inner(self) -> BitSliceReader<'a> {
+        self.data
+    }
+}
+
+impl<'a> Deref for DualStreamReader<'a> {
+    type Target = BitSliceReader<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl DerefMut for DualStreamReader<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
+
+/// Writes an object split into data and handle streams, the counterpart of
+/// [`DualStreamReader`]
+///
+/// Handle writes go to the handle stream, which is appended to the data stream by
+/// [`DualStreamWriter::into_bytes`]. All other writes go to the data stream, which this
+/// derefs to. Text is always written inline, string streams are not supported
+pub struct DualStreamWriter {
+    data: BitWriter,
+    handles: BitWriter,
+    codepage: CodePage,
+    /// Position of the RL written by `write_bitsize`, if it was called
+    bitsize_at: Option<u64>,
+}
+
+impl DualStreamWriter {
+    /// Creates a writer for an object, with text encoded in `codepage`
+    pub fn new(version: DWGVersion, codepage: CodePage) -> Self {
+        let mut data = BitWriter::new();
+        data.set_version(version);
+        let mut handles = BitWriter::new();
+        handles.set_version(version);
+        Self {
+            data,
+            handles,
+            codepage,
+            bitsize_at: None,
+        }
+    }
+
+    /// Writes a placeholder RL that [`DualStreamWriter::into_bytes`] sets to the size of the
+    /// data stream in bits, the position of the handle stream
+    ///
+    /// AC1024 (R2010) and later objects have no such RL, instead `into_bytes` starts the
+    /// object with the MC size of the handle stream in bits
+    pub fn write_bitsize(&mut self) {
+        self.bitsize_at = Some(self.data.bit_position());
+        if self.data.get_version() < DWGVersion::AC1024 {
+            self.data.write_raw_long(0);
+        }
+    }
+
+    /// Returns the codepage text is encoded in
+    pub fn codepage(&self) -> CodePage {
+        self.codepage
+    }
+
+    /// Writes text to the data stream in the codepage of the writer, see
+    /// [`BitWriter::write_text`]
+    pub fn write_text(&mut self, text: &str) -> Result<()> {
+        self.data.write_text(text, self.codepage)
+    }
+
+    /// Returns the data stream
+    pub fn data(&mut self) -> &mut BitWriter {
+        &mut self.data
+    }
+
+    /// Returns the handle stream
+    pub fn handles(&mut self) -> &mut BitWriter {
+        &mut self.handles
+    }
+
+    /// Writes a handle reference to the handle stream
+    pub fn write_handle(&mut self, handle: HandleRef) {
+        self.handles.write_handle(handle);
+    }
+
+    /// Returns the data stream followed by the handle stream
+    pub fn into_bytes(mut self) -> Result<Vec<u8>> {
+        let version = self.data.get_version();
+        let bitsize = self.data.bit_position();
+        let bit_len = self.handles.bit_position();
+        let handles = RawBits::new(self.handles.into_bytes(), bit_len);
+        match self.bitsize_at {
+            Some(_) if version >= DWGVersion::AC1024 => {
+                // The handle stream size includes the padding to the end of the last byte
+                let handle_bits = (bitsize + bit_len).next_multiple_of(8) - bitsize;
+                let mut out = BitWriter::new();
+                out.set_version(version);
+                out.write_modular_char_u64(handle_bits);
+                out.write_raw_bits(&RawBits::new(self.data.into_bytes(), bitsize));
+                out.write_raw_bits(&handles);
+                return Ok(out.into_bytes());
+            }
+            Some(at_bit) => self.data.set_raw_long_at(at_bit, bitsize as i32)?,
+            None => {}
+        }
+        self.data.write_raw_bits(&handles);
+        Ok(self.data.into_bytes())
+    }
+}
+
+impl Deref for DualStreamWriter {
+    type Target = BitWriter;
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl DerefMut for DualStreamWriter {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
+
+/// Joins the streams of the header variables or classes section data of an AC1021 (R2007) or
+/// later drawing, as read by [`DualStreamReader::section`]
+///
+/// The data stream is preceded by its RL size in bits, which counts the string stream that
+/// follows it along with the size of the string stream and the bit flagging it is there, and
+/// is followed by the handle stream
+pub(crate) fn join_section_streams(
+    data: BitWriter,
+    strings: BitWriter,
+    handles: BitWriter,
+) -> Result<Vec<u8>> {
+    let mut out = BitWriter::new();
+    out.set_version(data.get_version());
+    out.write_raw_long(0);
+    let bits = data.bit_position();
+    out.write_raw_bits(&RawBits::new(data.into_bytes(), bits));
+    let string_bits = strings.bit_position();
+    out.write_raw_bits(&RawBits::new(strings.into_bytes(), string_bits));
+    // The high bits of the size go in an RS before it if it does not fit in 15
+    if string_bits >= 0x8000 {
+        out.write_raw_short((string_bits >> 15) as i16);
+        out.write_raw_short((string_bits & 0x7FFF | 0x8000) as u16 as i16);
+    } else {
+        out.write_raw_short(string_bits as i16);
+    }
+    out.write_bit(1);
+    let bitsize = out.bit_position();
+    out.set_raw_long_at(0, bitsize as i32)?;
+    let bits = handles.bit_position();
+    out.write_raw_bits(&RawBits::new(handles.into_bytes(), bits));
+    Ok(out.into_bytes())
+}
+
+/// Returns a reader positioned at the start of the string stream along with where the stream
+/// ends, or None if the object has no strings
+fn locate_string_stream<'a>(
+    data: &BitSliceReader<'a>,
+    bitsize: u64,
+) -> Result<Option<(BitSliceReader<'a>, u64)>> {
+    if bitsize == 0 {
+        return Ok(None);
+    }
+    let invalid = DwgError::InvalidStreamSize { at_bit: bitsize };
+    let mut strings = data.sub_reader(0, data.data().len())?;
+    strings.seek_to_bit(bitsize - 1)?;
+    if strings.read_bit()? == 0 {
+        return Ok(None);
+    }
+    let mut pos = (bitsize - 1).checked_sub(16).ok_or(invalid.clone())?;
+    strings.seek_to_bit(pos)?;
+    let mut size = strings.read_raw_short()? as u16 as u64;
+    if size & 0x8000 != 0 {
+        pos = pos.checked_sub(16).ok_or(invalid.clone())?;
+        strings.seek_to_bit(pos)?;
+        let hi = strings.read_raw_short()? as u16 as u64;
+        size = (size & 0x7FFF) | hi << 15;
+    }
+    strings.seek_to_bit(pos.checked_sub(size).ok_or(invalid)?)?;
+    Ok(Some((strings, pos)))
+}
+
+#[test]
+fn test_dual_stream_reader() {
+    // Data stream holding a BS of 5, then a string stream with the TU "ab"
+    let mut bits: Vec<(u64, u32)> = vec![(0b01, 2), (5, 8)];
+    let strings = [
+        (0b01, 2),
+        (2, 8),
+        (b'a' as u64, 8),
+        (0, 8),
+        (b'b' as u64, 8),
+        (0, 8),
+    ];
+    let string_bits: u32 = strings.iter().map(|(_, n)| n).sum();
+    bits.extend(strings);
+    bits.extend([
+        (string_bits as u64 & 0xFF, 8),
+        (string_bits as u64 >> 8, 8),
+        (1, 1),
+    ]);
+    let bitsize = bits.iter().map(|(_, n)| *n as u64).sum();
+    let buf = crate::bitcodes::pack_bits(&bits);
+
+    let mut reader = BitSliceReader::from_slice(&buf);
+    reader.set_version(DWGVersion::AC1021);
+    let mut reader = DualStreamReader::new(reader, bitsize).unwrap();
+    assert!(reader.has_string_stream());
+    assert_eq!(reader.read_text(), Ok(String::from("ab")));
+    assert_eq!(reader.read_bitshort(), Ok(5));
+
+    // Before R2007 strings are inline
+    let buf = crate::bitcodes::pack_bits(&[(0b01, 2), (1, 8), (b'x' as u64, 8)]);
+    let mut reader = DualStreamReader::new(BitSliceReader::from_slice(&buf), 18).unwrap();
+    assert!(!reader.has_string_stream());
+    assert_eq!(reader.read_text(), Ok(String::from("x")));
+}
+
+#[test]
+fn test_handle_stream() {
+    use crate::types::RefType;
+
+    // Data stream holding a BS of 5, a string stream with the TU "a", then a handle stream
+    // holding a soft pointer to 0x1F
+    let mut bits: Vec<(u64, u32)> = vec![(0b01, 2), (5, 8)];
+    bits.extend([(0b01, 2), (1, 8), (b'a' as u64, 8), (0, 8)]);
+    bits.extend([(26, 8), (0, 8), (1, 1)]);
+    bits.extend([(0x41, 8), (0x1F, 8)]);
+    let buf = crate::bitcodes::pack_bits(&bits);
+
+    // The handle stream is padded to the end of the last byte
+    let handle_bits = buf.len() as u64 * 8 - 53;
+    let mut reader = BitSliceReader::from_slice(&buf);
+    reader.set_version(DWGVersion::AC1024);
+    let mut reader = DualStreamReader::from_handle_stream_size(reader, handle_bits).unwrap();
+    assert_eq!(
+        reader.read_handle(),
+        Ok(HandleRef {
+            code: RefType::SoftPointer,
+            handle: 0x1F
+        })
+    );
+    assert_eq!(reader.read_bitshort(), Ok(5));
+    assert_eq!(reader.read_text(), Ok(String::from("a")));
+
+    let reader = BitSliceReader::from_slice(&buf);
+    assert!(DualStreamReader::from_handle_stream_size(reader, 128).is_err());
+}
+
+#[test]
+fn test_dual_stream_writer() {
+    use crate::types::RefType;
+
+    let handle = HandleRef {
+        code: RefType::SoftPointer,
+        handle: 0x1F,
+    };
+    let mut writer = DualStreamWriter::new(DWGVersion::AC1015, CodePage::ANSI1252);
+    writer.write_bitsize();
+    writer.write_handle(handle);
+    writer.write_bitshort(5);
+    writer.write_text("a").unwrap();
+    let buf = writer.into_bytes().unwrap();
+
+    let mut reader = BitSliceReader::from_slice(&buf);
+    let bitsize = reader.read_raw_long().unwrap() as u64;
+    assert_eq!(bitsize, 32 + 10 + 26);
+    let mut reader = DualStreamReader::new(reader, bitsize).unwrap();
+    assert_eq!(reader.read_handle(), Ok(handle));
+    assert_eq!(reader.read_bitshort(), Ok(5));
+    assert_eq!(reader.read_text(), Ok(String::from("a")));
+
+    // From R2010 the object starts with the size of the handle stream, including the padding
+    let mut writer = DualStreamWriter::new(DWGVersion::AC1024, CodePage::ANSI1252);
+    writer.write_bitsize();
+    writer.write_handle(handle);
+    writer.write_bitshort(5);
+    // No string stream
+    writer.write_bit(0);
+    let buf = writer.into_bytes().unwrap();
+    assert_eq!(buf.len(), 5);
+    assert_eq!(buf[0], 32 - 11);
+    let mut reader = BitSliceReader::from_slice(&buf[1..]);
+    reader.set_version(DWGVersion::AC1024);
+    let mut reader = DualStreamReader::from_handle_stream_size(reader, 21).unwrap();
+    assert!(!reader.has_string_stream());
+    assert_eq!(reader.read_handle(), Ok(handle));
+    assert_eq!(reader.read_bitshort(), Ok(5));
+}
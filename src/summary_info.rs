@@ -0,0 +1,188 @@
+//! The SummaryInfo section of AC1018 (R2004) and later files, holding the document properties
+//! shown in the drawing properties dialog
+//!
+//! Strings are an RS length, including a null terminator, followed by that many bytes in the
+//! codepage of the drawing, or UTF-16LE code units from AC1021 (R2007). Times are two RLs
+use std::time::Duration;
+
+use crate::{
+    bitcodes::BitSliceReader,
+    bitcodes_write::BitWriter,
+    error::{DwgError, Result},
+    types::{CodePage, JulianDate},
+    version::DWGVersion,
+};
+
+/// The document properties of a drawing
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SummaryInfo {
+    pub title: String,
+    pub subject: String,
+    pub author: String,
+    pub keywords: String,
+    pub comments: String,
+    pub last_saved_by: String,
+    pub revision: String,
+    pub hyperlink_base: String,
+    /// Total time the drawing has been open for editing
+    pub total_editing_time: Duration,
+    pub created: JulianDate,
+    pub modified: JulianDate,
+    /// Custom properties as key and value pairs
+    pub custom: Vec<(String, String)>,
+}
+
+impl SummaryInfo {
+    /// Reads the SummaryInfo section of a drawing of `version`, with strings before AC1021
+    /// (R2007) decoded using `codepage`
+    ///
+    /// Fails with `Overflow` if the editing time is negative
+    pub fn read(data: &[u8], version: DWGVersion, codepage: CodePage) -> Result<SummaryInfo> {
+        let mut reader = BitSliceReader::from_slice(data);
+        let mut string = || read_string(&mut reader, version, codepage);
+        let (title, subject, author) = (string()?, string()?, string()?);
+        let (keywords, comments, last_saved_by) = (string()?, string()?, string()?);
+        let (revision, hyperlink_base) = (string()?, string()?);
+
+        let at_bit = reader.bit_position();
+        let days = u64::try_from(reader.read_raw_long()?);
+        let ms = u64::try_from(reader.read_raw_long()?);
+        let total_editing_time = match (days, ms) {
+            (Ok(days), Ok(ms)) => Duration::from_secs(days * 86_400) + Duration::from_millis(ms),
+            _ => return Err(DwgError::Overflow { at_bit }),
+        };
+        let mut date = || -> Result<JulianDate> {
+            Ok(JulianDate {
+                day: reader.read_raw_long()?,
+                ms: reader.read_raw_long()?,
+            })
+        };
+        let (created, modified) = (date()?, date()?);
+
+        let n_custom = reader.read_raw_short()? as u16;
+        let mut custom = Vec::with_capacity(n_custom as usize);
+        for _ in 0..n_custom {
+            let key = read_string(&mut reader, version, codepage)?;
+            custom.push((key, read_string(&mut reader, version, codepage)?));
+        }
+        // Followed by two unknown RLs
+        Ok(SummaryInfo {
+            title,
+            subject,
+            author,
+            keywords,
+            comments,
+            last_saved_by,
+            revision,
+            hyperlink_base,
+            total_editing_time,
+            created,
+            modified,
+            custom,
+        })
+    }
+
+    /// Writes the SummaryInfo section for a drawing of `version`, the inverse of
+    /// [`SummaryInfo::read`]
+    pub fn write(&self, version: DWGVersion, codepage: CodePage) -> Vec<u8> {
+        let mut writer = BitWriter::new();
+        for string in [
+            &self.title,
+            &self.subject,
+            &self.author,
+            &self.keywords,
+            &self.comments,
+            &self.last_saved_by,
+            &self.revision,
+            &self.hyperlink_base,
+        ] {
+            write_string(&mut writer, string, version, codepage);
+        }
+        let days = i32::try_from(self.total_editing_time.as_secs() / 86_400).unwrap_or(i32::MAX);
+        writer.write_raw_long(days);
+        writer.write_raw_long((self.total_editing_time.as_millis() % 86_400_000) as i32);
+        for date in [self.created, self.modified] {
+            writer.write_raw_long(date.day);
+            writer.write_raw_long(date.ms);
+        }
+        writer.write_raw_short(self.custom.len() as i16);
+        for (key, value) in &self.custom {
+            write_string(&mut writer, key, version, codepage);
+            write_string(&mut writer, value, version, codepage);
+        }
+        writer.write_raw_long(0);
+        writer.write_raw_long(0);
+        writer.into_bytes()
+    }
+}
+
+fn read_string(
+    reader: &mut BitSliceReader,
+    version: DWGVersion,
+    codepage: CodePage,
+) -> Result<String> {
+    let len = reader.read_raw_short()? as u16 as usize;
+    if version < DWGVersion::AC1021 {
+        return reader.read_fixed_string(len, codepage);
+    }
+    let mut units = Vec::with_capacity(len);
+    for _ in 0..len {
+        units.push(reader.read_raw_short()? as u16);
+    }
+    while units.last() == Some(&0) {
+        units.pop();
+    }
+    Ok(String::from_utf16_lossy(&units))
+}
+
+fn write_string(writer: &mut BitWriter, text: &str, version: DWGVersion, codepage: CodePage) {
+    if version < DWGVersion::AC1021 {
+        let mut bytes = codepage.encode(text);
+        bytes.push(0);
+        writer.write_raw_short(bytes.len() as i16);
+        writer.write_bytes(&bytes);
+    } else {
+        let units: Vec<u16> = text.encode_utf16().chain([0]).collect();
+        writer.write_raw_short(units.len() as i16);
+        for unit in units {
+            writer.write_raw_short(unit as i16);
+        }
+    }
+}
+
+#[test]
+fn test_summary_info() {
+    let info = SummaryInfo {
+        title: String::from("Plan"),
+        author: String::from("Zoë"),
+        revision: String::from("3"),
+        total_editing_time: Duration::from_millis(2 * 86_400_000 + 1500),
+        created: JulianDate {
+            day: 2_460_000,
+            ms: 1000,
+        },
+        modified: JulianDate {
+            day: 2_460_001,
+            ms: 0,
+        },
+        custom: vec![(String::from("Client"), String::from("ACME"))],
+        ..Default::default()
+    };
+    for version in [DWGVersion::AC1018, DWGVersion::AC1021] {
+        let data = info.write(version, CodePage::ANSI1252);
+        assert_eq!(
+            SummaryInfo::read(&data, version, CodePage::ANSI1252),
+            Ok(info.clone())
+        );
+    }
+
+    // The title is 5 bytes, including its null, or 5 UTF-16 code units
+    let data = info.write(DWGVersion::AC1018, CodePage::ANSI1252);
+    assert_eq!(data[..7], [5, 0, b'P', b'l', b'a', b'n', 0]);
+    let data = info.write(DWGVersion::AC1021, CodePage::ANSI1252);
+    assert_eq!(data[..6], [5, 0, b'P', 0, b'l', 0]);
+    assert!(matches!(
+        SummaryInfo::read(&data[..20], DWGVersion::AC1021, CodePage::ANSI1252),
+        Err(DwgError::UnexpectedEof { .. })
+    ));
+}
@@ -1,13 +1,293 @@
+use encoding_rs::Encoding;
 use strum::FromRepr;
 
+/// A 2D point or vector
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Point2 {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl From<(f64, f64)> for Point2 {
+    fn from((x, y): (f64, f64)) -> Self {
+        Self { x, y }
+    }
+}
+
+impl From<Point2> for (f64, f64) {
+    fn from(p: Point2) -> Self {
+        (p.x, p.y)
+    }
+}
+
+/// A 3D point or vector
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Point3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl From<(f64, f64, f64)> for Point3 {
+    fn from((x, y, z): (f64, f64, f64)) -> Self {
+        Self { x, y, z }
+    }
+}
+
+impl From<Point3> for (f64, f64, f64) {
+    fn from(p: Point3) -> Self {
+        (p.x, p.y, p.z)
+    }
+}
+
+/// A date stored as a Julian day number and milliseconds since midnight
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct JulianDate {
+    pub day: i32,
+    pub ms: i32,
+}
+
+impl JulianDate {
+    /// Returns the date as a fractional Julian day, as it is stored in a DXF
+    pub fn as_f64(&self) -> f64 {
+        self.day as f64 + self.ms as f64 / 86_400_000.0
+    }
+
+    /// Converts the date to a `chrono::NaiveDateTime`, returns None if it is out of range
+    #[cfg(feature = "chrono")]
+    pub fn to_datetime(&self) -> Option<chrono::NaiveDateTime> {
+        // Julian day 1721426 is 0001-01-01 in the proleptic Gregorian calendar
+        let date = chrono::NaiveDate::from_num_days_from_ce_opt(self.day.checked_sub(1721425)?)?;
+        let ms = u32::try_from(self.ms).ok()?;
+        let time = chrono::NaiveTime::from_num_seconds_from_midnight_opt(
+            ms / 1000,
+            ms % 1000 * 1_000_000,
+        )?;
+        Some(date.and_time(time))
+    }
+}
+
+/// An exact run of bits copied from a DWG, which need not start or end on a byte boundary
+///
+/// Used to keep data that is not understood, such as unknown objects and proxy data, so it
+/// can be written back unchanged. Bits are packed in stream order, most significant bit
+/// first, with any unused bits of the last byte zero
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RawBits {
+    bytes: Vec<u8>,
+    bit_len: u64,
+}
+
+impl RawBits {
+    /// Creates a run of the first `bit_len` bits of `bytes`
+    ///
+    /// Panics if `bytes` is not exactly the number of bytes needed to hold `bit_len` bits
+    pub fn new(mut bytes: Vec<u8>, bit_len: u64) -> Self {
+        assert_eq!(bytes.len() as u64, bit_len.div_ceil(8));
+        let rem = bit_len % 8;
+        if let (Some(last), true) = (bytes.last_mut(), rem != 0) {
+            *last &= !(0xFF >> rem);
+        }
+        Self { bytes, bit_len }
+    }
+
+    /// Returns the number of bits in the run
+    pub fn bit_len(&self) -> u64 {
+        self.bit_len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bit_len == 0
+    }
+
+    /// Returns the packed bits
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// The code nibble of a handle reference
+///
+/// See chapter 2.13 of the ODS. Codes 2-5 describe ownership of the referenced object while
+/// codes 6-C are offsets relative to the handle of the object containing the reference
+#[derive(FromRepr, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
 pub enum RefType {
-    SoftOwned,
-    HardOwned,
-    SoftPointer,
-    HardPointer,
+    /// Code 0, used for an object's own handle and header variables
+    Unspecified = 0x0,
+    SoftOwned = 0x2,
+    HardOwned = 0x3,
+    SoftPointer = 0x4,
+    HardPointer = 0x5,
+    /// Reference handle + 1, no handle bytes follow
+    PlusOne = 0x6,
+    /// Reference handle - 1, no handle bytes follow
+    MinusOne = 0x8,
+    /// Reference handle + offset
+    PlusOffset = 0xA,
+    /// Reference handle - offset
+    MinusOffset = 0xC,
+}
+
+/// A handle reference (H) as stored in the DWG
+///
+/// For the offset codes `handle` holds the offset rather than an absolute handle, use
+/// [`HandleRef::absolute`] to resolve it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HandleRef {
+    pub code: RefType,
+    pub handle: u64,
+}
+
+impl HandleRef {
+    pub fn new(code: RefType, handle: u64) -> Self {
+        Self { code, handle }
+    }
+
+    /// Resolves the absolute handle referenced, `reference` is the handle of the object
+    /// that contains this reference
+    pub fn absolute(&self, reference: u64) -> u64 {
+        match self.code {
+            RefType::PlusOne => reference.wrapping_add(1),
+            RefType::MinusOne => reference.wrapping_sub(1),
+            RefType::PlusOffset => reference.wrapping_add(self.handle),
+            RefType::MinusOffset => reference.wrapping_sub(self.handle),
+            _ => self.handle,
+        }
+    }
+}
+
+/// A color value (CMC) as stored in header variables and table entries
+///
+/// Before AC1018 (R2004) only `index` is stored. For AC1018 and later the color is stored as
+/// an RGB value where the high byte is the color method, optionally followed by a name and
+/// the name of the color book the color belongs to
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CmColor {
+    /// The ACI color index, always 0 for AC1018 and later
+    pub index: i16,
+    /// Color method in the high byte followed by red, green, blue
+    pub rgb: u32,
+    /// Bit 1 set if `name` is present, bit 2 set if `book_name` is present
+    pub flags: u8,
+    pub name: Option<String>,
+    pub book_name: Option<String>,
+}
+
+impl CmColor {
+    /// The color method stored in the high byte of `rgb`, e.g. 0xC2 for true color and
+    /// 0xC3 for an ACI index
+    pub fn method(&self) -> u8 {
+        (self.rgb >> 24) as u8
+    }
+
+    /// Stores `color` as AC1018 (R2004) and later do, with the method in the high byte of
+    /// `rgb`
+    pub fn from_color(color: Color) -> Self {
+        let rgb = match color {
+            Color::ByLayer => 0xC0000000,
+            Color::ByBlock => 0xC1000000,
+            Color::Index(index) => 0xC3000000 | index as u32,
+            Color::TrueColor { rgb, .. } => 0xC2000000 | rgb & 0xFFFFFF,
+        };
+        CmColor {
+            rgb,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the color, from `rgb` if a color method is set, otherwise from `index`
+    ///
+    /// True colors have no ACI index to fall back on, so use 7 (white)
+    pub fn color(&self) -> Color {
+        match self.method() {
+            0xC0 => Color::ByLayer,
+            0xC1 => Color::ByBlock,
+            0xC2 => Color::TrueColor {
+                rgb: self.rgb & 0xFFFFFF,
+                aci: 7,
+            },
+            0xC3 => Color::Index(self.rgb as u8),
+            _ => Color::from_index(self.index),
+        }
+    }
+}
+
+/// The color of an entity
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    ByLayer,
+    ByBlock,
+    /// An ACI color index in 1-255
+    Index(u8),
+    /// A 24 bit RGB value with the closest ACI index as a fallback for older readers
+    TrueColor {
+        rgb: u32,
+        aci: u8,
+    },
+}
+
+impl Color {
+    /// Maps an ACI color index, where 0 is ByBlock and 256 is ByLayer
+    pub fn from_index(index: i16) -> Self {
+        match index {
+            0 => Color::ByBlock,
+            256 => Color::ByLayer,
+            i => Color::Index(i as u8),
+        }
+    }
+
+    /// Returns the ACI color index, the closest index for true colors
+    pub fn index(&self) -> i16 {
+        match *self {
+            Color::ByBlock => 0,
+            Color::ByLayer => 256,
+            Color::Index(index) | Color::TrueColor { aci: index, .. } => index as i16,
+        }
+    }
+}
+
+/// The transparency of an entity
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transparency {
+    ByLayer,
+    ByBlock,
+    /// Alpha value where 0 is fully transparent and 255 is opaque
+    Alpha(u8),
+}
+
+impl Transparency {
+    /// Decodes a transparency BL, the high byte is the type and the low byte the alpha value
+    pub fn from_raw(raw: u32) -> Self {
+        match raw >> 24 {
+            1 => Transparency::ByBlock,
+            3 => Transparency::Alpha(raw as u8),
+            _ => Transparency::ByLayer,
+        }
+    }
+
+    /// Encodes the transparency as a BL, see [`Transparency::from_raw`]
+    pub fn to_raw(&self) -> u32 {
+        match *self {
+            Transparency::ByLayer => 0,
+            Transparency::ByBlock => 0x01000000,
+            Transparency::Alpha(alpha) => 0x03000000 | alpha as u32,
+        }
+    }
 }
 
-#[derive(FromRepr, Debug, PartialEq)]
+/// An entity color (ENC) as stored in the common entity data
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntityColor {
+    pub color: Color,
+    /// Only present for AC1018 (R2004) and later when set
+    pub transparency: Option<Transparency>,
+    /// True if the color references an AcDbColor object, the handle for which is stored in
+    /// the handle stream of the entity
+    pub has_color_handle: bool,
+}
+
+#[derive(FromRepr, Clone, Copy, Debug, PartialEq)]
 #[repr(u16)]
 pub enum CodePage {
     UTF8,
@@ -56,3 +336,134 @@ pub enum CodePage {
     UTF16,    // Default Since R2007
     ANSI1258, // Windows Vietnamese
 }
+
+impl CodePage {
+    /// Returns the matching `encoding_rs` encoding, or None if the codepage is not supported
+    ///
+    /// The DOS codepages (other than CP866) and Johab have no equivalent
+    pub fn encoding(&self) -> Option<&'static Encoding> {
+        use encoding_rs::*;
+        match self {
+            CodePage::UTF8 => Some(UTF_8),
+            CodePage::USAscii | CodePage::ISO8859_1 | CodePage::ANSI1252 => Some(WINDOWS_1252),
+            CodePage::ISO8859_2 => Some(ISO_8859_2),
+            CodePage::ISO8859_3 => Some(ISO_8859_3),
+            CodePage::ISO8859_4 => Some(ISO_8859_4),
+            CodePage::ISO8859_5 => Some(ISO_8859_5),
+            CodePage::ISO8859_6 => Some(ISO_8859_6),
+            CodePage::ISO8859_7 => Some(ISO_8859_7),
+            CodePage::ISO8859_8 => Some(ISO_8859_8),
+            CodePage::ISO8859_9 | CodePage::ANSI1254 => Some(WINDOWS_1254),
+            CodePage::CP932 | CodePage::ANSI932 => Some(SHIFT_JIS),
+            CodePage::Macintosh => Some(MACINTOSH),
+            CodePage::BIG5 | CodePage::ANSI950 => Some(BIG5),
+            CodePage::CP949 | CodePage::ANSI949 => Some(EUC_KR),
+            CodePage::CP866 => Some(IBM866),
+            CodePage::ANSI1250 => Some(WINDOWS_1250),
+            CodePage::ANSI1251 => Some(WINDOWS_1251),
+            CodePage::GB2312 | CodePage::ANSI936 => Some(GBK),
+            CodePage::ANSI1253 => Some(WINDOWS_1253),
+            CodePage::ANSI1255 => Some(WINDOWS_1255),
+            CodePage::ANSI1256 => Some(WINDOWS_1256),
+            CodePage::ANSI1257 => Some(WINDOWS_1257),
+            CodePage::ANSI874 => Some(WINDOWS_874),
+            CodePage::UTF16 => Some(UTF_16LE),
+            CodePage::ANSI1258 => Some(WINDOWS_1258),
+            _ => None,
+        }
+    }
+
+    /// Encodes a string in this codepage
+    ///
+    /// Unsupported codepages fall back to Windows-1252. Characters the codepage cannot
+    /// represent are written as DWG unicode escapes, such as `\U+20AC`, with characters
+    /// outside the basic multilingual plane escaped as their two UTF-16 surrogates
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        use encoding_rs::EncoderResult;
+
+        if *self == CodePage::UTF16 {
+            return text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        }
+        let encoding = self.encoding().unwrap_or(encoding_rs::WINDOWS_1252);
+        let mut encoder = encoding.new_encoder();
+        let mut bytes = Vec::with_capacity(text.len());
+        let mut text = text;
+        loop {
+            let needed = encoder.max_buffer_length_from_utf8_without_replacement(text.len());
+            bytes.reserve(needed.unwrap_or(text.len()));
+            let (result, read) =
+                encoder.encode_from_utf8_to_vec_without_replacement(text, &mut bytes, true);
+            text = &text[read..];
+            match result {
+                EncoderResult::InputEmpty => return bytes,
+                EncoderResult::OutputFull => {}
+                EncoderResult::Unmappable(c) => {
+                    for unit in c.encode_utf16(&mut [0; 2]) {
+                        bytes.extend(format!("\\U+{unit:04X}").bytes());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Decodes bytes in this codepage to a `String`
+    ///
+    /// Unsupported codepages fall back to Windows-1252 and malformed sequences are replaced,
+    /// so this never fails
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        let encoding = self.encoding().unwrap_or(encoding_rs::WINDOWS_1252);
+        let (text, _, _) = encoding.decode(bytes);
+        text.into_owned()
+    }
+}
+
+#[test]
+fn test_cm_color_conversion() {
+    for color in [
+        Color::ByLayer,
+        Color::ByBlock,
+        Color::Index(5),
+        Color::TrueColor {
+            rgb: 0x123456,
+            aci: 7,
+        },
+    ] {
+        assert_eq!(CmColor::from_color(color).color(), color);
+    }
+    let old = CmColor {
+        index: 256,
+        ..Default::default()
+    };
+    assert_eq!(old.color(), Color::ByLayer);
+    assert_eq!(old.color().index(), 256);
+}
+
+#[test]
+fn test_raw_bits_new() {
+    let bits = RawBits::new(vec![0xFF, 0xFF], 12);
+    assert_eq!(bits.as_bytes(), [0xFF, 0xF0]);
+    assert_eq!(bits.bit_len(), 12);
+    assert!(RawBits::new(Vec::new(), 0).is_empty());
+}
+
+#[test]
+fn test_codepage_encode() {
+    assert_eq!(CodePage::ANSI1252.encode("Layer \u{B0}"), b"Layer \xB0");
+    assert_eq!(CodePage::ANSI1251.encode("\u{410}\u{411}"), [0xC0, 0xC1]);
+    // Characters the codepage lacks are escaped as DWG text does
+    assert_eq!(CodePage::ANSI1252.encode("\u{3A9} 5"), b"\\U+03A9 5");
+    assert_eq!(
+        CodePage::ANSI1251.encode("\u{410}\u{1F600}"),
+        b"\xC0\\U+D83D\\U+DE00"
+    );
+    assert_eq!(CodePage::UTF16.encode("A\u{3A9}"), [0x41, 0, 0xA9, 0x03]);
+    let text = "\u{410}\u{1F600}";
+    assert_eq!(CodePage::UTF16.decode(&CodePage::UTF16.encode(text)), text);
+}
+
+#[test]
+fn test_codepage_decode() {
+    assert_eq!(CodePage::ANSI1252.decode(b"Layer \xB0"), "Layer \u{B0}");
+    assert_eq!(CodePage::ANSI1251.decode(&[0xC0, 0xC1]), "\u{410}\u{411}");
+    assert_eq!(CodePage::from_repr(30), Some(CodePage::ANSI1252));
+}
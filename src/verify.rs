@@ -0,0 +1,431 @@
+//! Checking the integrity of a DWG file without decoding its contents
+//!
+//! [`Dwg::verify`] runs every check the file format allows for, the magic number, file
+//! header, sentinels, CRCs and checksums of the sections and objects, the object map and the
+//! second header, and reports each rather than stopping at the first failure as reading does
+use std::collections::BTreeMap;
+
+use crate::{
+    bitcodes::BitSliceReader,
+    crc::CRC8_SEED,
+    dwg::{
+        has_size_high, locator_data, read_object_map_into, read_r2000_header, read_second_header,
+        second_header_address, Dwg, ParseOptions, ParseWarning, R2000FileHeader, SectionDirectory,
+        SectionKind,
+    },
+    dwg_file::DwgFile,
+    error::{DwgError, Result},
+    version::DWGVersion,
+};
+
+/// A part of a DWG file checked by [`Dwg::verify`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IntegrityCheck {
+    /// The magic number naming the version
+    Magic,
+    /// The file header of R13-R2000 files, with its CRC and sentinel, or the file header and
+    /// page and section maps of later files
+    FileHeader,
+    /// A section by its AC1018 (R2004) name, or `record N` for R13-R2000 sections of no
+    /// known kind. The sentinels and CRC of the header variables, classes and preview image
+    /// are checked as well as the page checksums of later files
+    Section(String),
+    /// The object map, with the CRC of each of its parts
+    ObjectMap,
+    /// The object with the handle, with its CRC
+    Object(u64),
+    /// The second header of R13-R2000 files, with its sentinels and CRC, compared with the
+    /// file header and the object handles, see [`crate::dwg::SecondHeader::cross_check`]
+    SecondHeader,
+}
+
+/// The result of every check made by [`Dwg::verify`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntegrityReport {
+    /// Version of the file, if its magic number could be read
+    pub version: Option<DWGVersion>,
+    /// Each check in the order it was made, along with the error it failed with
+    pub checks: Vec<(IntegrityCheck, Result<()>)>,
+}
+
+impl IntegrityReport {
+    /// Returns true if every check passed
+    pub fn is_ok(&self) -> bool {
+        self.checks.iter().all(|(_, result)| result.is_ok())
+    }
+
+    /// Returns the checks that failed, along with their errors
+    pub fn failures(&self) -> impl Iterator<Item = (&IntegrityCheck, &DwgError)> {
+        self.checks
+            .iter()
+            .filter_map(|(check, result)| result.as_ref().err().map(|err| (check, err)))
+    }
+}
+
+impl Dwg {
+    /// Checks the integrity of the DWG file `bytes`, reporting every check made
+    ///
+    /// Checks that depend on an earlier one, such as those of the sections on the file
+    /// header, are left out when it fails. The file is read leniently, with each
+    /// [`ParseWarning`] reported as a failure of the file header or the section it was found
+    /// in
+    pub fn verify(bytes: &[u8]) -> IntegrityReport {
+        let mut checks = Vec::new();
+        let version = BitSliceReader::from_slice(bytes).read_version();
+        checks.push((IntegrityCheck::Magic, version.clone().map(drop)));
+        let Ok(version) = version else {
+            return IntegrityReport {
+                version: None,
+                checks,
+            };
+        };
+
+        let mut bit_reader = BitSliceReader::from_slice(bytes);
+        if version <= DWGVersion::AC1015 {
+            let options = ParseOptions { strict: false };
+            let header = match read_r2000_header(&mut bit_reader, options) {
+                Ok(header) => header,
+                Err(err) => {
+                    checks.push((IntegrityCheck::FileHeader, Err(err)));
+                    return IntegrityReport {
+                        version: Some(version),
+                        checks,
+                    };
+                }
+            };
+            push_check(
+                &mut checks,
+                IntegrityCheck::FileHeader,
+                Ok(()),
+                &header.warnings,
+            );
+            let directory = SectionDirectory::Locators {
+                bytes,
+                records: &header.records,
+                preview_address: header.preview_address,
+            };
+            let size_high = has_size_high(version, header.maintenance_version);
+            for record in &header.records {
+                let kind = SectionKind::ALL
+                    .into_iter()
+                    .find(|kind| kind.record_number() == Some(record.number));
+                let (name, result) = match kind {
+                    Some(kind) => (
+                        kind.name().into(),
+                        check_section(&directory, kind, size_high),
+                    ),
+                    None => (
+                        format!("record {}", record.number),
+                        locator_data(bytes, record).map(drop),
+                    ),
+                };
+                checks.push((IntegrityCheck::Section(name), result));
+            }
+            if header.preview_address != 0 {
+                let result = check_section(&directory, SectionKind::Preview, size_high);
+                checks.push((
+                    IntegrityCheck::Section(SectionKind::Preview.name().into()),
+                    result,
+                ));
+            }
+            let last_handle = check_objects(&directory, version, &mut checks);
+            check_second_header(bytes, &header, last_handle, &mut checks);
+        } else {
+            let file = match DwgFile::open_with(bytes, ParseOptions { strict: false }) {
+                Ok(file) => file,
+                Err(err) => {
+                    checks.push((IntegrityCheck::FileHeader, Err(err)));
+                    return IntegrityReport {
+                        version: Some(version),
+                        checks,
+                    };
+                }
+            };
+            push_check(
+                &mut checks,
+                IntegrityCheck::FileHeader,
+                Ok(()),
+                &file.warnings(),
+            );
+            let directory = file.directory();
+            let size_high = has_size_high(version, file.maintenance_version());
+            for name in file.section_names() {
+                let kind = SectionKind::ALL
+                    .into_iter()
+                    .find(|kind| kind.name() == name);
+                let before = file.warnings().len();
+                let result = file.named_section(name).and_then(|_| match kind {
+                    Some(kind) => check_section(&directory, kind, size_high),
+                    None => Ok(()),
+                });
+                let warnings = &file.warnings()[before..];
+                push_check(
+                    &mut checks,
+                    IntegrityCheck::Section(name.into()),
+                    result,
+                    warnings,
+                );
+            }
+            check_objects(&directory, version, &mut checks);
+        }
+        IntegrityReport {
+            version: Some(version),
+            checks,
+        }
+    }
+}
+
+/// Adds `check` with each of `warnings` as a failure, followed by `result` unless it passed
+/// with warnings
+fn push_check(
+    checks: &mut Vec<(IntegrityCheck, Result<()>)>,
+    check: IntegrityCheck,
+    result: Result<()>,
+    warnings: &[ParseWarning],
+) {
+    checks.extend(
+        warnings
+            .iter()
+            .map(|warning| (check.clone(), Err(warning.clone().into()))),
+    );
+    if result.is_err() || warnings.is_empty() {
+        checks.push((check, result));
+    }
+}
+
+/// Reads a section, checking the sentinels and CRC of the sections that have them
+fn check_section(directory: &SectionDirectory, kind: SectionKind, size_high: bool) -> Result<()> {
+    match kind {
+        SectionKind::Header => directory.header_vars(size_high).map(drop),
+        SectionKind::Classes => directory.classes(size_high).map(drop),
+        SectionKind::Preview => directory.preview().map(drop),
+        _ => directory.get(kind).map(drop),
+    }
+}
+
+/// Checks the object map and then each object it locates, if the file has both sections
+///
+/// Returns the largest handle the object map locates
+fn check_objects(
+    directory: &SectionDirectory,
+    version: DWGVersion,
+    checks: &mut Vec<(IntegrityCheck, Result<()>)>,
+) -> Option<u64> {
+    let sections = (
+        directory.get(SectionKind::ObjectMap),
+        directory.get(SectionKind::Objects),
+    );
+    let (Ok(Some(map)), Ok(Some(objects))) = sections else {
+        return None;
+    };
+    let mut locations = BTreeMap::new();
+    let result = read_object_map_into(map.data(), &mut locations);
+    checks.push((IntegrityCheck::ObjectMap, result));
+    let last_handle = locations.keys().next_back().copied();
+    for (handle, location) in locations {
+        let result = check_object(objects.data(), handle, location, version);
+        checks.push((IntegrityCheck::Object(handle), result));
+    }
+    last_handle
+}
+
+/// Checks the sentinels and CRC of the second header of an R13-R2000 file and compares it
+/// with the file `header` and the largest object handle, if the file has a second header
+fn check_second_header(
+    bytes: &[u8],
+    header: &R2000FileHeader,
+    last_handle: Option<u64>,
+    checks: &mut Vec<(IntegrityCheck, Result<()>)>,
+) {
+    let address = second_header_address(header);
+    if address >= bytes.len() as u64 {
+        return;
+    }
+    let mut reader = BitSliceReader::from_slice(bytes);
+    let second_header = reader
+        .seek_to_bit(address * 8)
+        .and_then(|_| read_second_header(&mut reader));
+    let (second_header, crc_warning) = match second_header {
+        Ok(read) => read,
+        Err(err) => {
+            checks.push((IntegrityCheck::SecondHeader, Err(err)));
+            return;
+        }
+    };
+    let cross_check = second_header.cross_check_handles(address, &header.records, last_handle);
+    let warnings: Vec<_> = crc_warning.into_iter().chain(cross_check).collect();
+    checks.extend(
+        warnings
+            .iter()
+            .map(|warning| (IntegrityCheck::SecondHeader, Err(warning.clone().into()))),
+    );
+    if warnings.is_empty() {
+        checks.push((IntegrityCheck::SecondHeader, Ok(())));
+    }
+}
+
+/// Checks the CRC of the object at `location` in `data`, which follows its MS size, the MC
+/// size of its handle stream from AC1024 (R2010) and its data, and covers all three
+fn check_object(data: &[u8], handle: u64, location: u64, version: DWGVersion) -> Result<()> {
+    let mut reader = BitSliceReader::from_slice(data);
+    reader.seek_to_bit(location.saturating_mul(8))?;
+    reader.start_crc(CRC8_SEED);
+    let size = reader.read_modular_short_u64()? as usize;
+    if version >= DWGVersion::AC1024 {
+        reader.read_modular_char_u64()?;
+    }
+    reader.read_vec(size)?;
+    let found = reader.finish_crc().unwrap_or_default();
+    let expected = reader.read_raw_short()? as u16;
+    if found != expected {
+        return Err(DwgError::ObjectCrcMismatch {
+            handle,
+            expected,
+            found,
+        });
+    }
+    Ok(())
+}
+
+#[test]
+fn test_verify() {
+    let mut dwg = Dwg::empty(DWGVersion::AC1015);
+    dwg.header_vars = vec![1, 2, 3];
+    dwg.objects.insert(1, vec![4; 5]);
+    dwg.objects.insert(2, vec![6; 7]);
+    let buf = dwg.to_bytes().unwrap();
+    let report = Dwg::verify(&buf);
+    assert!(
+        report.is_ok(),
+        "{:?}",
+        report.failures().collect::<Vec<_>>()
+    );
+    assert_eq!(report.version, Some(DWGVersion::AC1015));
+    let checks: Vec<_> = report
+        .checks
+        .iter()
+        .map(|(check, _)| check.clone())
+        .collect();
+    assert_eq!(
+        checks[..2],
+        [IntegrityCheck::Magic, IntegrityCheck::FileHeader]
+    );
+    assert!(checks.contains(&IntegrityCheck::Section("AcDb:Header".into())));
+    assert_eq!(
+        checks[checks.len() - 4..],
+        [
+            IntegrityCheck::ObjectMap,
+            IntegrityCheck::Object(1),
+            IntegrityCheck::Object(2),
+            IntegrityCheck::SecondHeader
+        ]
+    );
+
+    // Every failure is reported, not only the first
+    let mut corrupt = buf.clone();
+    // The maintenance version, covered by the file header CRC
+    corrupt[0x0B] ^= 1;
+    let header_start = u32::from_le_bytes(corrupt[0x1A..0x1E].try_into().unwrap()) as usize;
+    corrupt[header_start] ^= 1;
+    let read = Dwg::from_bytes(&buf).unwrap();
+    let objects_start = read.obj_free_space.unwrap().objects_offset as usize;
+    corrupt[objects_start + 2] ^= 1;
+    let failures: Vec<_> = Dwg::verify(&corrupt)
+        .failures()
+        .map(|(check, err)| (check.clone(), err.clone()))
+        .collect();
+    assert_eq!(failures.len(), 3, "{failures:?}");
+    assert!(matches!(
+        failures[0],
+        (
+            IntegrityCheck::FileHeader,
+            DwgError::FileHeaderCrcMismatch { .. }
+        )
+    ));
+    assert!(matches!(
+        &failures[1],
+        (IntegrityCheck::Section(name), DwgError::BadSentinel { .. }) if name == "AcDb:Header"
+    ));
+    assert!(matches!(
+        failures[2],
+        (
+            IntegrityCheck::Object(1),
+            DwgError::ObjectCrcMismatch { handle: 1, .. }
+        )
+    ));
+
+    // The second header is checked against its CRC and the file header
+    let mut corrupt = buf.clone();
+    let crc_at = corrupt.len() - 16 - 8 - 2;
+    corrupt[crc_at] ^= 1;
+    let report = Dwg::verify(&corrupt);
+    let failures: Vec<_> = report.failures().collect();
+    assert!(
+        matches!(
+            failures[..],
+            [(
+                IntegrityCheck::SecondHeader,
+                DwgError::SecondHeaderMismatch(crate::dwg::SecondHeaderWarning::CrcMismatch { .. })
+            )]
+        ),
+        "{failures:?}"
+    );
+    let mut corrupt = buf.clone();
+    let header = read_r2000_header(
+        &mut BitSliceReader::from_slice(&buf),
+        ParseOptions::default(),
+    )
+    .unwrap();
+    corrupt[second_header_address(&header) as usize] ^= 1;
+    let report = Dwg::verify(&corrupt);
+    let failures: Vec<_> = report.failures().collect();
+    assert!(
+        matches!(
+            failures[..],
+            [(IntegrityCheck::SecondHeader, DwgError::BadSentinel { .. })]
+        ),
+        "{failures:?}"
+    );
+
+    let report = Dwg::verify(b"AC1009");
+    assert_eq!(report.version, None);
+    assert_eq!(report.checks.len(), 1);
+
+    let mut dwg = Dwg::empty(DWGVersion::AC1018);
+    dwg.objects.insert(1, vec![4; 5]);
+    let report = Dwg::verify(&dwg.to_bytes().unwrap());
+    assert!(
+        report.is_ok(),
+        "{:?}",
+        report.failures().collect::<Vec<_>>()
+    );
+    assert!(report
+        .checks
+        .contains(&(IntegrityCheck::Section("AcDb:AcDbObjects".into()), Ok(()))));
+    assert!(report.checks.contains(&(IntegrityCheck::Object(1), Ok(()))));
+
+    // A bad page checksum is reported for its section, and the other checks still run
+    let mut buf = dwg.to_bytes().unwrap();
+    let header = crate::dwg::read_r2004_file_header(&mut BitSliceReader::from_slice(&buf))
+        .unwrap()
+        .header;
+    let (lenient, mut warnings) = (ParseOptions { strict: false }, Vec::new());
+    let page_map = crate::dwg::read_r2004_page_map(&buf, &header, lenient, &mut warnings).unwrap();
+    let section_map =
+        crate::dwg::read_r2004_section_map(&buf, &header, &page_map, lenient, &mut warnings)
+            .unwrap();
+    let objects = section_map.section("AcDb:AcDbObjects").unwrap();
+    let page = page_map.page(objects.pages[0].number).unwrap().address as usize;
+    buf[page + 0x0C] ^= 1;
+    let report = Dwg::verify(&buf);
+    let failures: Vec<_> = report.failures().collect();
+    assert!(
+        matches!(
+            &failures[..],
+            [(IntegrityCheck::Section(name), DwgError::PageChecksumMismatch { .. })]
+                if name == "AcDb:AcDbObjects"
+        ),
+        "{failures:?}"
+    );
+    assert!(report.checks.contains(&(IntegrityCheck::Object(1), Ok(()))));
+}
@@ -5,6 +5,7 @@ pub enum DWGVersion {
     AC1015, // R2000
     AC1018, // R2004
     AC1021, // R2007
+    AC1024, // R2010
     AC1027, // R2013
     AC1032, // R2018
 }
@@ -17,14 +18,78 @@ impl DWGVersion {
             b"AC1015" => Some(Self::AC1015),
             b"AC1018" => Some(Self::AC1018),
             b"AC1021" => Some(Self::AC1021),
+            b"AC1024" => Some(Self::AC1024),
             b"AC1027" => Some(Self::AC1027),
             b"AC1032" => Some(Self::AC1032),
             _ => None,
         }
     }
+
+    /// Returns the magic number starting files of this version, the inverse of
+    /// [`DWGVersion::from_magic`]
+    pub fn magic(self) -> &'static [u8; 6] {
+        match self {
+            Self::AC1012 => b"AC1012",
+            Self::AC1014 => b"AC1014",
+            Self::AC1015 => b"AC1015",
+            Self::AC1018 => b"AC1018",
+            Self::AC1021 => b"AC1021",
+            Self::AC1024 => b"AC1024",
+            Self::AC1027 => b"AC1027",
+            Self::AC1032 => b"AC1032",
+        }
+    }
+}
+
+/// A version from before R13, which used a different file format that cannot be read
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LegacyVersion {
+    AC1002, // R2.5
+    AC1003, // R2.6
+    AC1004, // R9
+    AC1006, // R10
+    AC1009, // R11 and R12
+}
+
+impl LegacyVersion {
+    pub fn from_magic(magic: &[u8; 6]) -> Option<Self> {
+        match magic {
+            b"AC1002" => Some(Self::AC1002),
+            b"AC1003" => Some(Self::AC1003),
+            b"AC1004" => Some(Self::AC1004),
+            b"AC1006" => Some(Self::AC1006),
+            b"AC1009" => Some(Self::AC1009),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the AutoCAD release that wrote files of this version
+    pub fn release(self) -> &'static str {
+        match self {
+            Self::AC1002 => "R2.5",
+            Self::AC1003 => "R2.6",
+            Self::AC1004 => "R9",
+            Self::AC1006 => "R10",
+            Self::AC1009 => "R11/R12",
+        }
+    }
 }
 
 #[test]
 fn test_from_magic() {
     assert_eq!(DWGVersion::from_magic(b"AC1012"), Some(DWGVersion::AC1012));
+    assert_eq!(DWGVersion::from_magic(b"AC1020"), None);
+    for version in [DWGVersion::AC1015, DWGVersion::AC1032] {
+        assert_eq!(DWGVersion::from_magic(version.magic()), Some(version));
+    }
+}
+
+#[test]
+fn test_legacy_from_magic() {
+    assert_eq!(
+        LegacyVersion::from_magic(b"AC1009"),
+        Some(LegacyVersion::AC1009)
+    );
+    assert_eq!(LegacyVersion::from_magic(b"AC1015"), None);
+    assert_eq!(LegacyVersion::AC1006.release(), "R10");
 }